@@ -0,0 +1,121 @@
+//! Compares the owned syntax tree with the arena syntax tree on a large page
+//!
+//! Run with `cargo bench --bench arena`. Each parser builds the tree of the same
+//! generated page several times; the report gives the mean time and the number of
+//! heap allocations of one parse, counted by a wrapper around the system allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use asp_classic_parser::parser::{self, arena::Bump};
+
+/// System allocator counting the allocations
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+const ITERATIONS: u32 = 10;
+
+/// Section of a generated report page, repeated to build the benchmarked page
+const SECTION: &str = r#"<h2>Orders of <%= customer.Name %></h2>
+<table class="orders">
+<% Dim total, i
+total = 0
+For i = 0 To UBound(orders)
+    Set order = orders(i)
+    If order.Status = "shipped" And Not order.Cancelled Then
+        total = total + order.Amount * (1 + taxRate / 100)
+%>
+    <tr><td><%= Server.HTMLEncode(order.Reference) %></td><td><%= FormatNumber(order.Amount, 2) %></td></tr>
+<%  ElseIf order.Status = "pending" Then
+        Response.Write "<tr class=""pending""><td colspan=""2"">" & order.Reference & "</td></tr>"
+    End If
+Next %>
+</table>
+<% Select Case total
+    Case 0
+        Response.Write "No shipped order"
+    Case Else
+        Response.Write "Total: " & FormatCurrency(total)
+End Select
+
+Function Discount(amount, rate)
+    If rate > 0 Then Discount = amount * rate / 100 Else Discount = 0
+End Function
+%>
+"#;
+
+/// Parses the page `ITERATIONS` times, returning the mean time and number of
+/// allocations of one parse
+fn measure(page: &str, parse: impl Fn(&str) -> usize) -> (Duration, usize) {
+    // Warm up the lazily built operator table and the allocator
+    black_box(parse(page));
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(parse(black_box(page)));
+    }
+    let elapsed = start.elapsed() / ITERATIONS;
+    let count = (ALLOCATIONS.load(Ordering::Relaxed) - allocations) / ITERATIONS as usize;
+    (elapsed, count)
+}
+
+fn main() {
+    let page = SECTION.repeat(2000);
+    println!(
+        "page: {:.1} MB, {} lines",
+        page.len() as f64 / 1_000_000.0,
+        page.lines().count()
+    );
+
+    let results = [
+        (
+            "parse_to_ast",
+            measure(&page, |page| {
+                parser::parse_to_ast(page)
+                    .expect("the page is valid")
+                    .body
+                    .len()
+            }),
+        ),
+        (
+            "parse_in",
+            measure(&page, |page| {
+                let arena = Bump::new();
+                parser::parse_in(page, &arena)
+                    .expect("the page is valid")
+                    .body
+                    .len()
+            }),
+        ),
+    ];
+    for (name, (elapsed, allocations)) in results {
+        println!(
+            "{:<14} {:>8.1} ms {:>10} allocations",
+            name,
+            elapsed.as_secs_f64() * 1000.0,
+            allocations
+        );
+    }
+}
@@ -0,0 +1,38 @@
+/*
+ * C API of the ASP Classic Parser
+ *
+ * Link with libasp_classic_parser (built by `cargo build --release --lib`).
+ * Results are JSON strings owned by the caller, to be released with
+ * asp_free_string:
+ *
+ *   {"file": "page.asp", "success": false, "diagnostics": [
+ *     {"line": 3, "column": 1, "code": "ASP002", "message": "...", "severity": "error"}
+ *   ]}
+ *
+ * When a file cannot be read, or the parser fails unexpectedly, "success" is
+ * false and an "error" field describes the problem.
+ */
+#ifndef ASP_CLASSIC_PARSER_H
+#define ASP_CLASSIC_PARSER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Parses a file; returns NULL if path is NULL or not valid UTF-8 */
+char *asp_parse_file(const char *path);
+
+/* Parses ASP source code (the result has no "file" field); returns NULL if source is NULL */
+char *asp_parse_source(const char *source);
+
+/* Releases a string returned by asp_parse_file or asp_parse_source; NULL is ignored */
+void asp_free_string(char *string);
+
+/* Returns the version of the library; the string must not be released */
+const char *asp_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ASP_CLASSIC_PARSER_H */
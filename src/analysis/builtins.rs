@@ -0,0 +1,264 @@
+//! Names predefined by VBScript and ASP
+//!
+//! These names can be used without being declared, even with `Option Explicit`.
+
+/// ASP intrinsic objects, and the VBScript `Err` object
+pub const OBJECTS: &[&str] = &[
+    "Application",
+    "Err",
+    "ObjectContext",
+    "Request",
+    "Response",
+    "Server",
+    "Session",
+];
+
+/// VBScript built-in functions
+pub const FUNCTIONS: &[&str] = &[
+    "Abs",
+    "Array",
+    "Asc",
+    "AscB",
+    "AscW",
+    "Atn",
+    "CBool",
+    "CByte",
+    "CCur",
+    "CDate",
+    "CDbl",
+    "Chr",
+    "ChrB",
+    "ChrW",
+    "CInt",
+    "CLng",
+    "Cos",
+    "CreateObject",
+    "CSng",
+    "CStr",
+    "Date",
+    "DateAdd",
+    "DateDiff",
+    "DatePart",
+    "DateSerial",
+    "DateValue",
+    "Day",
+    "Escape",
+    "Eval",
+    "Execute",
+    "ExecuteGlobal",
+    "Exp",
+    "Filter",
+    "Fix",
+    "FormatCurrency",
+    "FormatDateTime",
+    "FormatNumber",
+    "FormatPercent",
+    "GetLocale",
+    "GetObject",
+    "GetRef",
+    "Hex",
+    "Hour",
+    "InputBox",
+    "InStr",
+    "InStrB",
+    "InStrRev",
+    "Int",
+    "IsArray",
+    "IsDate",
+    "IsEmpty",
+    "IsNull",
+    "IsNumeric",
+    "IsObject",
+    "Join",
+    "LBound",
+    "LCase",
+    "Left",
+    "LeftB",
+    "Len",
+    "LenB",
+    "LoadPicture",
+    "Log",
+    "LTrim",
+    "Mid",
+    "MidB",
+    "Minute",
+    "Month",
+    "MonthName",
+    "MsgBox",
+    "Now",
+    "Oct",
+    "Replace",
+    "RGB",
+    "Right",
+    "RightB",
+    "Rnd",
+    "Round",
+    "RTrim",
+    "ScriptEngine",
+    "ScriptEngineBuildVersion",
+    "ScriptEngineMajorVersion",
+    "ScriptEngineMinorVersion",
+    "Second",
+    "SetLocale",
+    "Sgn",
+    "Sin",
+    "Space",
+    "Split",
+    "Sqr",
+    "StrComp",
+    "String",
+    "StrReverse",
+    "Tan",
+    "Time",
+    "Timer",
+    "TimeSerial",
+    "TimeValue",
+    "Trim",
+    "TypeName",
+    "UBound",
+    "UCase",
+    "Unescape",
+    "VarType",
+    "Weekday",
+    "WeekdayName",
+    "Year",
+];
+
+/// VBScript built-in constants
+pub const CONSTANTS: &[&str] = &[
+    "vbAbort",
+    "vbAbortRetryIgnore",
+    "vbApplicationModal",
+    "vbArray",
+    "vbBinaryCompare",
+    "vbBlack",
+    "vbBlue",
+    "vbBoolean",
+    "vbByte",
+    "vbCancel",
+    "vbCr",
+    "vbCritical",
+    "vbCrLf",
+    "vbCurrency",
+    "vbCyan",
+    "vbDataObject",
+    "vbDate",
+    "vbDecimal",
+    "vbDefaultButton1",
+    "vbDefaultButton2",
+    "vbDefaultButton3",
+    "vbDefaultButton4",
+    "vbDouble",
+    "vbEmpty",
+    "vbError",
+    "vbExclamation",
+    "vbFalse",
+    "vbFirstFourDays",
+    "vbFirstFullWeek",
+    "vbFirstJan1",
+    "vbFormFeed",
+    "vbFriday",
+    "vbGeneralDate",
+    "vbGreen",
+    "vbIgnore",
+    "vbInformation",
+    "vbInteger",
+    "vbLf",
+    "vbLong",
+    "vbLongDate",
+    "vbLongTime",
+    "vbMagenta",
+    "vbMonday",
+    "vbNewLine",
+    "vbNo",
+    "vbNull",
+    "vbNullChar",
+    "vbNullString",
+    "vbObject",
+    "vbObjectError",
+    "vbOK",
+    "vbOKCancel",
+    "vbOKOnly",
+    "vbQuestion",
+    "vbRed",
+    "vbRetry",
+    "vbRetryCancel",
+    "vbSaturday",
+    "vbShortDate",
+    "vbShortTime",
+    "vbSingle",
+    "vbString",
+    "vbSunday",
+    "vbSystemModal",
+    "vbTab",
+    "vbTextCompare",
+    "vbThursday",
+    "vbTrue",
+    "vbTuesday",
+    "vbUseDefault",
+    "vbUseSystem",
+    "vbUseSystemDayOfWeek",
+    "vbVariant",
+    "vbVerticalTab",
+    "vbWednesday",
+    "vbWhite",
+    "vbYellow",
+    "vbYes",
+    "vbYesNo",
+    "vbYesNoCancel",
+];
+
+/// Returns true if `name` is predefined by VBScript or ASP, ignoring case
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::builtins;
+///
+/// assert!(builtins::is_builtin("response"));
+/// assert!(builtins::is_builtin("vbCrLf"));
+/// assert!(builtins::is_builtin("UCase"));
+/// assert!(!builtins::is_builtin("customer"));
+/// ```
+pub fn is_builtin(name: &str) -> bool {
+    builtin_name(name).is_some()
+}
+
+/// Returns the usual spelling of a name predefined by VBScript or ASP
+/// (`Response`, `UCase`, `vbCrLf`), or `None` if `name` is not one
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::builtins;
+///
+/// assert_eq!(builtins::builtin_name("ucase"), Some("UCase"));
+/// assert_eq!(builtins::builtin_name("VBCRLF"), Some("vbCrLf"));
+/// assert_eq!(builtins::builtin_name("customer"), None);
+/// ```
+pub fn builtin_name(name: &str) -> Option<&'static str> {
+    [OBJECTS, FUNCTIONS, CONSTANTS]
+        .iter()
+        .flat_map(|names| names.iter())
+        .find(|builtin| builtin.eq_ignore_ascii_case(name))
+        .copied()
+}
+
+/// Returns the usual spelling of a member of a predefined object, or `None` if
+/// the object has no such member (see [`objects`](super::objects) for the members
+/// of each object)
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::builtins;
+///
+/// assert_eq!(builtins::member_name("response", "write"), Some("Write"));
+/// assert_eq!(builtins::member_name("Server", "htmlencode"), Some("HTMLEncode"));
+/// assert_eq!(builtins::member_name("Response", "Form"), None);
+/// ```
+pub fn member_name(object: &str, member: &str) -> Option<&'static str> {
+    super::objects::object_type(object)?
+        .member(member)
+        .map(|member| member.name)
+}
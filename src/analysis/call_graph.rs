@@ -0,0 +1,193 @@
+//! Call graph of a page
+//!
+//! [`call_graph`] lists the subs, functions and properties of a page and the calls
+//! between them, whether they use `Call Foo(1)`, `Foo 1`, `x = Foo(1)` or take a
+//! reference with `GetRef("Foo")`. Calls are resolved with the scopes of
+//! [`bindings`](super::bindings), so a local variable named like a procedure does
+//! not count as a call. Methods called through an object (`cart.Total()`) cannot be
+//! resolved without types and are not part of the graph.
+//!
+//! ```
+//! use asp_classic_parser::analysis;
+//! use asp_classic_parser::parser;
+//!
+//! let program = parser::parse_to_ast(
+//!     "<%\nSub Main()\n  Call Greet(\"world\")\n  Log \"done\"\nEnd Sub\nSub Greet(name)\n  Log name\nEnd Sub\nSub Log(message)\nEnd Sub\nSub Unused()\nEnd Sub\nMain\n%>",
+//! )
+//! .unwrap();
+//! let graph = analysis::call_graph(&program);
+//!
+//! let main = graph.procedure("main").unwrap();
+//! let callees: Vec<&str> = graph
+//!     .callees(main)
+//!     .map(|i| graph.procedures[i].name.as_str())
+//!     .collect();
+//! assert_eq!(callees, ["Greet", "Log"]);
+//!
+//! let log = graph.procedure("Log").unwrap();
+//! assert_eq!(graph.callers(log).count(), 2);
+//! assert!(!graph.is_called(graph.procedure("Unused").unwrap()));
+//! ```
+
+use serde::Serialize;
+
+use super::scopes::{
+    Bindings, CallKind, DeclarationId, DeclarationKind, Resolution, ScopeId, ScopeKind, bindings,
+};
+use crate::parser::ast::{ProcedureKind, Program, Span};
+
+/// A sub, function or property of the page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProcedureNode {
+    /// Name, as written in the declaration
+    pub name: String,
+    /// Sub, function or property
+    pub kind: ProcedureKind,
+    /// Name of the class, for methods and properties
+    pub class: Option<String>,
+    /// Location of the name in the declaration
+    pub span: Span,
+    /// Declaration of the procedure in [`Bindings::declarations`]
+    pub declaration: DeclarationId,
+}
+
+/// A call from a procedure, or from page-level code, to a procedure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CallEdge {
+    /// Index of the calling procedure in [`CallGraph::procedures`]; `None` for calls
+    /// made by page-level code
+    pub caller: Option<usize>,
+    /// Index of the called procedure in [`CallGraph::procedures`]
+    pub callee: usize,
+    /// How the procedure is called
+    pub kind: CallKind,
+    /// Location of the name of the called procedure
+    pub span: Span,
+}
+
+/// Procedures of a page and the calls between them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CallGraph {
+    /// Procedures, in source order
+    pub procedures: Vec<ProcedureNode>,
+    /// Calls, in source order
+    pub calls: Vec<CallEdge>,
+}
+
+impl CallGraph {
+    /// Builds the call graph from the resolved names of a page
+    ///
+    /// Use this instead of [`call_graph`] when the bindings are already computed.
+    pub fn from_bindings(bindings: &Bindings) -> Self {
+        let mut graph = CallGraph::default();
+        // Class members are declared after page-level procedures; sort them by position
+        let mut declarations: Vec<_> = bindings.declarations.iter().enumerate().collect();
+        declarations.sort_by_key(|(_, declaration)| declaration.span.start);
+        // Index in `procedures` of each declaration
+        let mut nodes = vec![None; bindings.declarations.len()];
+        for (id, declaration) in declarations {
+            if let DeclarationKind::Procedure(kind) = declaration.kind {
+                let scope = &bindings.scopes[declaration.scope];
+                nodes[id] = Some(graph.procedures.len());
+                graph.procedures.push(ProcedureNode {
+                    name: declaration.name.clone(),
+                    kind,
+                    class: match scope.kind {
+                        ScopeKind::Class => scope.name.clone(),
+                        _ => None,
+                    },
+                    span: declaration.span,
+                    declaration: id,
+                });
+            }
+        }
+
+        for reference in &bindings.references {
+            if reference.is_write {
+                continue;
+            }
+            let Resolution::Declaration(id) = reference.resolution else {
+                continue;
+            };
+            let Some(callee) = nodes[id] else {
+                continue;
+            };
+            let caller = enclosing_procedure(bindings, reference.scope)
+                .and_then(|declaration| nodes[declaration]);
+            graph.calls.push(CallEdge {
+                caller,
+                callee,
+                kind: reference.call.unwrap_or(CallKind::Expression),
+                span: reference.span,
+            });
+        }
+        graph
+    }
+
+    /// Returns the index of the procedure with the given name (ignoring case)
+    ///
+    /// When methods of several classes share the name, the first one declared is
+    /// returned.
+    pub fn procedure(&self, name: &str) -> Option<usize> {
+        self.procedures
+            .iter()
+            .position(|procedure| procedure.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the procedures called by a procedure, once each, in order of first call
+    pub fn callees(&self, procedure: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut seen = vec![false; self.procedures.len()];
+        self.calls
+            .iter()
+            .filter(move |call| call.caller == Some(procedure))
+            .filter_map(move |call| {
+                (!std::mem::replace(&mut seen[call.callee], true)).then_some(call.callee)
+            })
+    }
+
+    /// Returns the procedures calling a procedure, once each, in order of first call
+    ///
+    /// Calls from page-level code are not included; see [`CallGraph::is_called`].
+    pub fn callers(&self, procedure: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut seen = vec![false; self.procedures.len()];
+        self.calls
+            .iter()
+            .filter(move |call| call.callee == procedure)
+            .filter_map(|call| call.caller)
+            .filter(move |&caller| !std::mem::replace(&mut seen[caller], true))
+    }
+
+    /// Returns true if a procedure is called from anywhere, other than from itself
+    pub fn is_called(&self, procedure: usize) -> bool {
+        self.calls
+            .iter()
+            .any(|call| call.callee == procedure && call.caller != Some(procedure))
+    }
+}
+
+/// Returns the declaration of the procedure a scope belongs to, if any
+fn enclosing_procedure(bindings: &Bindings, scope: ScopeId) -> Option<DeclarationId> {
+    let mut current = Some(scope);
+    while let Some(id) = current {
+        let scope = &bindings.scopes[id];
+        if scope.kind == ScopeKind::Procedure {
+            return scope.declaration;
+        }
+        current = scope.parent;
+    }
+    None
+}
+
+/// Builds the call graph of a page
+///
+/// # Arguments
+///
+/// * `program` - The syntax tree of the page
+///
+/// # Returns
+///
+/// * `CallGraph` - The subs, functions and properties of the page, and the calls
+///   between them
+pub fn call_graph(program: &Program) -> CallGraph {
+    CallGraph::from_bindings(&bindings(program))
+}
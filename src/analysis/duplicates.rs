@@ -0,0 +1,297 @@
+//! Duplicated code across pages
+//!
+//! [`DuplicateFinder`] compares the token streams of several pages and reports
+//! the sequences of statements that appear more than once, within a page or
+//! across pages, as [`CloneGroup`]s. Such code is a candidate for a procedure in
+//! an include file.
+//!
+//! Tokens are compared ignoring case, whitespace, comments and line
+//! continuations, so reformatted copies are still found. Clones start and end on
+//! statement boundaries.
+//!
+//! ```
+//! use asp_classic_parser::analysis::duplicates::DuplicateFinder;
+//! use std::path::Path;
+//!
+//! let code = "<%\nSet rs = conn.Execute(\"SELECT * FROM items\")\nDo While Not rs.EOF\n  Response.Write rs(\"name\")\n  rs.MoveNext\nLoop\n%>";
+//! let mut finder = DuplicateFinder::new(20);
+//! finder.add_file(Path::new("list.asp"), code);
+//! finder.add_file(Path::new("print.asp"), &code.replace("  ", "\t"));
+//!
+//! let groups = finder.clone_groups();
+//! assert_eq!(groups.len(), 1);
+//! assert_eq!(groups[0].occurrences[1].path, Path::new("print.asp"));
+//! assert_eq!((groups[0].occurrences[1].span.line, groups[0].occurrences[1].end_line), (2, 6));
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::parser::ast::Span;
+use crate::parser::lexer::TokenKind;
+use crate::parser::tokenize;
+
+/// Identifier of the statement separators: line breaks, `:` and ASP tags
+const SEPARATOR: u32 = 0;
+
+/// A sequence of statements found at several places
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CloneGroup {
+    /// Number of tokens of the sequence, statement separators excluded
+    pub tokens: usize,
+    /// Places where the sequence appears, by file and position
+    pub occurrences: Vec<Occurrence>,
+}
+
+/// A place where a duplicated sequence appears
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Occurrence {
+    /// File containing the sequence
+    pub path: PathBuf,
+    /// Location of the sequence
+    pub span: Span,
+    /// Line of the end of the sequence
+    pub end_line: usize,
+}
+
+/// A significant token of a page
+#[derive(Debug, Clone)]
+struct CodeToken {
+    /// File of the token
+    file: usize,
+    /// Location of the token
+    span: Span,
+    /// Line of the end of the token
+    end_line: usize,
+}
+
+/// Finds duplicated statement sequences in a set of pages
+#[derive(Debug, Clone)]
+pub struct DuplicateFinder {
+    /// Minimum number of tokens of a reported sequence
+    min_tokens: usize,
+    /// Paths of the added files
+    files: Vec<PathBuf>,
+    /// Normalized tokens of every file, each file being followed by a token that
+    /// matches nothing
+    ids: Vec<u32>,
+    /// Positions of the tokens of `ids`
+    tokens: Vec<CodeToken>,
+    /// Identifier of each normalized token text
+    interned: HashMap<String, u32>,
+}
+
+impl DuplicateFinder {
+    /// Creates a finder reporting sequences of at least `min_tokens` tokens
+    pub fn new(min_tokens: usize) -> Self {
+        DuplicateFinder {
+            min_tokens: min_tokens.max(1),
+            files: Vec::new(),
+            ids: Vec::new(),
+            tokens: Vec::new(),
+            interned: HashMap::new(),
+        }
+    }
+
+    /// Adds a page to compare
+    pub fn add_file(&mut self, path: &Path, source: &str) {
+        let file = self.files.len();
+        self.files.push(path.to_path_buf());
+        for token in tokenize(source) {
+            let key = match token.kind {
+                kind if kind.is_trivia() && kind != TokenKind::Newline => continue,
+                TokenKind::Html | TokenKind::HtmlComment if token.text.trim().is_empty() => {
+                    continue;
+                }
+                TokenKind::Newline
+                | TokenKind::OpenTag
+                | TokenKind::CloseTag
+                | TokenKind::ScriptOpenTag
+                | TokenKind::ScriptCloseTag => None,
+                TokenKind::Punctuation if token.text == ":" => None,
+                TokenKind::Keyword | TokenKind::Identifier | TokenKind::Operator => {
+                    Some(token.text.to_ascii_lowercase())
+                }
+                TokenKind::Html | TokenKind::HtmlComment => Some(token.text.trim().to_string()),
+                _ => Some(token.text.to_string()),
+            };
+            let id = match key {
+                None if self.ids.last() == Some(&SEPARATOR) => continue,
+                None => SEPARATOR,
+                Some(key) => {
+                    // Identifiers past the separator, with one left for file ends
+                    let next = self.interned.len() as u32 + 1;
+                    *self.interned.entry(key).or_insert(next)
+                }
+            };
+            self.ids.push(id);
+            self.tokens.push(CodeToken {
+                file,
+                span: token.span,
+                end_line: token.span.line + token.text.matches('\n').count(),
+            });
+        }
+        // End of file, different from every other token
+        self.ids.push(u32::MAX - file as u32);
+        self.tokens.push(CodeToken {
+            file,
+            span: Span::default(),
+            end_line: 0,
+        });
+    }
+
+    /// Returns the groups of duplicated sequences, longest first
+    pub fn clone_groups(&self) -> Vec<CloneGroup> {
+        let window = self.min_tokens;
+        // Positions of the sequences of `window` tokens starting with a statement,
+        // by hash of their tokens
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for start in 0..self.ids.len().saturating_sub(window) {
+            if !self.is_statement_start(start) {
+                continue;
+            }
+            let slice = &self.ids[start..start + window];
+            if slice
+                .iter()
+                .any(|&id| id > u32::MAX - self.files.len() as u32)
+            {
+                continue;
+            }
+            buckets.entry(hash(slice)).or_default().push(start);
+        }
+
+        // Starts of each duplicated sequence, by hash and length
+        let mut groups: HashMap<(u64, usize), BTreeSet<usize>> = HashMap::new();
+        for starts in buckets.values().filter(|starts| starts.len() > 1) {
+            for (i, &first) in starts.iter().enumerate() {
+                for &second in &starts[i + 1..] {
+                    if let Some(length) = self.clone_length(first, second) {
+                        let key = (hash(&self.ids[first..first + length]), length);
+                        groups.entry(key).or_default().extend([first, second]);
+                    }
+                }
+            }
+        }
+
+        let mut clone_groups: Vec<CloneGroup> = groups
+            .into_iter()
+            .map(|((_, length), starts)| CloneGroup {
+                tokens: self.ids[*starts.first().unwrap_or(&0)..][..length]
+                    .iter()
+                    .filter(|&&id| id != SEPARATOR)
+                    .count(),
+                occurrences: starts
+                    .into_iter()
+                    .map(|start| self.occurrence(start, length))
+                    .collect(),
+            })
+            .collect();
+        clone_groups.sort_by(|a, b| {
+            b.tokens
+                .cmp(&a.tokens)
+                .then_with(|| a.occurrences[0].path.cmp(&b.occurrences[0].path))
+                .then_with(|| {
+                    a.occurrences[0]
+                        .span
+                        .start
+                        .cmp(&b.occurrences[0].span.start)
+                })
+        });
+        clone_groups
+    }
+
+    /// Whether a token starts a statement
+    fn is_statement_start(&self, position: usize) -> bool {
+        self.ids[position] != SEPARATOR
+            && (position == 0
+                || self.ids[position - 1] == SEPARATOR
+                || self.is_file_end(position - 1))
+    }
+
+    /// Whether a position holds the token following the last token of a file
+    fn is_file_end(&self, position: usize) -> bool {
+        self.ids[position] > u32::MAX - self.files.len() as u32
+    }
+
+    /// Returns the length of the longest sequence of whole statements starting at
+    /// two positions, if it is long enough and does not start within a longer
+    /// duplicated sequence
+    fn clone_length(&self, first: usize, second: usize) -> Option<usize> {
+        // Sequences preceded by the same statement are part of a longer one
+        if let (Some(a), Some(b)) = (
+            self.previous_statement(first),
+            self.previous_statement(second),
+        ) && self.ids[a.clone()] == self.ids[b]
+            && a.end < second
+        {
+            return None;
+        }
+        let mut length = 0;
+        while second + length < self.ids.len()
+            && self.ids[first + length] == self.ids[second + length]
+            && !self.is_file_end(first + length)
+            && first + length < second
+        {
+            length += 1;
+        }
+        // End on the last complete statement
+        while length > 0 {
+            let next_is_end = |start: usize| {
+                let next = start + length;
+                self.ids[next] == SEPARATOR || self.is_file_end(next)
+            };
+            if self.ids[first + length - 1] != SEPARATOR
+                && next_is_end(first)
+                && next_is_end(second)
+            {
+                break;
+            }
+            length -= 1;
+        }
+        let tokens = self.ids[first..first + length]
+            .iter()
+            .filter(|&&id| id != SEPARATOR)
+            .count();
+        (tokens >= self.min_tokens).then_some(length)
+    }
+
+    /// Returns the positions of the tokens of the statement before the one
+    /// starting at a position, if it is in the same file
+    fn previous_statement(&self, start: usize) -> Option<Range<usize>> {
+        if start < 2 || self.ids[start - 1] != SEPARATOR {
+            return None;
+        }
+        let end = start - 1;
+        let mut begin = end;
+        while begin > 0 && self.ids[begin - 1] != SEPARATOR && !self.is_file_end(begin - 1) {
+            begin -= 1;
+        }
+        (begin < end).then_some(begin..end)
+    }
+
+    /// Returns the location of a sequence of tokens
+    fn occurrence(&self, start: usize, length: usize) -> Occurrence {
+        let first = &self.tokens[start];
+        let last = &self.tokens[start + length - 1];
+        Occurrence {
+            path: self.files[first.file].clone(),
+            span: Span::new(
+                first.span.start,
+                last.span.end,
+                first.span.line,
+                first.span.column,
+            ),
+            end_line: last.end_line,
+        }
+    }
+}
+
+/// Hashes a sequence of token identifiers
+fn hash(ids: &[u32]) -> u64 {
+    ids.iter().fold(0xcbf2_9ce4_8422_2325, |hash: u64, &id| {
+        (hash ^ u64::from(id)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
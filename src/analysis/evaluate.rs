@@ -0,0 +1,448 @@
+//! Evaluation of constant expressions
+//!
+//! [`evaluate`] computes the value of an expression made of literals, constants and
+//! calls to built-in functions such as `Chr`, `Len` or `Replace`, following the
+//! conversion rules of VBScript. It lets lints see through string concatenation, for
+//! example to check a SQL query or a redirect URL assembled from several fragments.
+//!
+//! Expressions depending on variables, objects or user-defined functions have no
+//! constant value, and neither have those raising a runtime error (division by
+//! zero, type mismatch).
+//!
+//! ```
+//! use asp_classic_parser::analysis::{Evaluator, Value};
+//! use asp_classic_parser::parser::{self, ast::Statement};
+//!
+//! let program = parser::parse_to_ast(
+//!     "<%\nConst TABLE = \"users\"\nsql = \"SELECT * FROM \" & UCase(TABLE) & \" WHERE id = \" & (40 + 2)\n%>",
+//! )
+//! .unwrap();
+//! let evaluator = Evaluator::from_program(&program);
+//!
+//! let Statement::Assignment(assignment) = &program.body[1] else { unreachable!() };
+//! assert_eq!(
+//!     evaluator.evaluate(&assignment.value),
+//!     Some(Value::String("SELECT * FROM USERS WHERE id = 42".to_string()))
+//! );
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use super::scopes::blocks;
+use crate::parser::ast::{
+    BinaryOperator, Expression, LiteralValue, Program, Statement, UnaryOperator,
+};
+
+/// Value of a constant expression
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum Value {
+    /// `Empty`, the value of uninitialized variables
+    Empty,
+    /// `Null`
+    Null,
+    /// `True` or `False`
+    Boolean(bool),
+    /// Integer
+    Integer(i64),
+    /// Floating-point number
+    Float(f64),
+    /// String
+    String(String),
+}
+
+impl Value {
+    /// Returns the value converted to a number, as arithmetic operators do
+    ///
+    /// `Empty` is 0, `True` is -1 and strings must contain a number; `Null` and
+    /// other strings have no numeric value.
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Empty => Some(0.0),
+            Value::Null => None,
+            Value::Boolean(value) => Some(if *value { -1.0 } else { 0.0 }),
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            Value::String(value) => value.trim().parse().ok(),
+        }
+    }
+
+    /// Returns the value converted to a string, as `&` does; `Null` has no string value
+    pub fn to_text(&self) -> Option<String> {
+        match self {
+            Value::Null => None,
+            value => Some(value.to_string()),
+        }
+    }
+
+    /// Returns the value converted to an integer, rounding halves to even
+    fn to_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(value) => Some(*value),
+            value => {
+                let number = value.to_number()?.round_ties_even();
+                (number.abs() < i64::MAX as f64).then_some(number as i64)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Empty => Ok(()),
+            Value::Null => write!(f, "Null"),
+            Value::Boolean(true) => write!(f, "True"),
+            Value::Boolean(false) => write!(f, "False"),
+            Value::Integer(value) => write!(f, "{}", value),
+            Value::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                write!(f, "{}", *value as i64)
+            }
+            Value::Float(value) => write!(f, "{}", value),
+            Value::String(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Evaluates constant expressions, knowing the constants of a page
+///
+/// A new evaluator knows the VBScript built-in constants (`vbCrLf`, `vbTab`, ...);
+/// [`Evaluator::from_program`] adds the constants declared by a page.
+#[derive(Debug, Clone)]
+pub struct Evaluator {
+    /// Values of the known constants, by lowercase name
+    constants: HashMap<String, Value>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evaluator {
+    /// Creates an evaluator knowing only the VBScript built-in constants
+    pub fn new() -> Self {
+        let string = |value: &str| Value::String(value.to_string());
+        let constants = [
+            ("vbcr", string("\r")),
+            ("vblf", string("\n")),
+            ("vbcrlf", string("\r\n")),
+            ("vbnewline", string("\r\n")),
+            ("vbtab", string("\t")),
+            ("vbformfeed", string("\x0c")),
+            ("vbverticaltab", string("\x0b")),
+            ("vbnullchar", string("\0")),
+            ("vbnullstring", string("")),
+            ("vbtrue", Value::Integer(-1)),
+            ("vbfalse", Value::Integer(0)),
+            ("vbusedefault", Value::Integer(-2)),
+            ("vbbinarycompare", Value::Integer(0)),
+            ("vbtextcompare", Value::Integer(1)),
+        ];
+        Evaluator {
+            constants: constants
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value))
+                .collect(),
+        }
+    }
+
+    /// Creates an evaluator knowing the page-level constants declared by a page
+    ///
+    /// Constants are evaluated in order, so that a constant may use the ones declared
+    /// before it. Constants declared inside procedures and classes are local and are
+    /// not included.
+    pub fn from_program(program: &Program) -> Self {
+        let mut evaluator = Self::new();
+        evaluator.declare_constants(&program.body);
+        evaluator
+    }
+
+    fn declare_constants(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            if let Statement::Const(statement) = statement {
+                for constant in &statement.constants {
+                    if let Some(value) = self.evaluate(&constant.value) {
+                        self.define(&constant.name.name, value);
+                    }
+                }
+            }
+            for block in blocks(statement) {
+                self.declare_constants(block);
+            }
+        }
+    }
+
+    /// Defines (or redefines) a constant
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.constants.insert(name.to_lowercase(), value);
+    }
+
+    /// Returns the value of a known constant (ignoring case)
+    pub fn constant(&self, name: &str) -> Option<&Value> {
+        self.constants.get(&name.to_lowercase())
+    }
+
+    /// Returns the value of an expression, or `None` if it is not constant
+    pub fn evaluate(&self, expression: &Expression) -> Option<Value> {
+        match expression {
+            Expression::Literal(literal) => match &literal.value {
+                LiteralValue::String(value) => Some(Value::String(value.clone())),
+                LiteralValue::Integer(value) => Some(Value::Integer(*value)),
+                LiteralValue::Float(value) => Some(Value::Float(*value)),
+                LiteralValue::Boolean(value) => Some(Value::Boolean(*value)),
+                LiteralValue::Null => Some(Value::Null),
+                LiteralValue::Empty => Some(Value::Empty),
+                LiteralValue::Date(_) | LiteralValue::Nothing => None,
+            },
+            Expression::Identifier(identifier) => self.constant(&identifier.name).cloned(),
+            Expression::Parenthesized(expression) => self.evaluate(&expression.expression),
+            Expression::Unary(expression) => {
+                unary(expression.operator, self.evaluate(&expression.operand)?)
+            }
+            Expression::Binary(expression) => binary(
+                expression.operator,
+                self.evaluate(&expression.left)?,
+                self.evaluate(&expression.right)?,
+            ),
+            Expression::Call(call) => {
+                let Expression::Identifier(callee) = call.callee.as_ref() else {
+                    return None;
+                };
+                let arguments = call
+                    .arguments
+                    .iter()
+                    .map(|argument| self.evaluate(argument.as_ref()?))
+                    .collect::<Option<Vec<_>>>()?;
+                function(&callee.name, &arguments)
+            }
+            Expression::Member(_) | Expression::New(_) | Expression::Unparsed(_) => None,
+        }
+    }
+}
+
+/// Returns the value of an expression that only uses literals, built-in constants
+/// and built-in functions
+///
+/// # Arguments
+///
+/// * `expression` - The expression to evaluate
+///
+/// # Returns
+///
+/// * `Option<Value>` - The value of the expression, or `None` if it is not constant
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::{self, Value};
+/// use asp_classic_parser::parser::{self, ast::Statement};
+///
+/// let program = parser::parse_to_ast("<% x = \"Line\" & Chr(32) & (1 + 1) & vbCrLf %>").unwrap();
+/// let Statement::Assignment(assignment) = &program.body[0] else { unreachable!() };
+/// assert_eq!(
+///     analysis::evaluate(&assignment.value),
+///     Some(Value::String("Line 2\r\n".to_string()))
+/// );
+/// ```
+pub fn evaluate(expression: &Expression) -> Option<Value> {
+    Evaluator::new().evaluate(expression)
+}
+
+fn unary(operator: UnaryOperator, operand: Value) -> Option<Value> {
+    if operand == Value::Null {
+        return Some(Value::Null);
+    }
+    match operator {
+        UnaryOperator::Plus => Some(operand),
+        UnaryOperator::Negate => match operand {
+            Value::Integer(value) => Some(integer_or_float(value.checked_neg(), -(value as f64))),
+            Value::Empty => Some(Value::Integer(0)),
+            operand => Some(Value::Float(-operand.to_number()?)),
+        },
+        UnaryOperator::Not => match operand {
+            Value::Boolean(value) => Some(Value::Boolean(!value)),
+            operand => Some(Value::Integer(!operand.to_integer()?)),
+        },
+    }
+}
+
+fn binary(operator: BinaryOperator, left: Value, right: Value) -> Option<Value> {
+    use BinaryOperator::*;
+
+    match operator {
+        Concat => match (left.to_text(), right.to_text()) {
+            (None, None) => Some(Value::Null),
+            (left, right) => Some(Value::String(
+                left.unwrap_or_default() + &right.unwrap_or_default(),
+            )),
+        },
+        Is => None,
+        _ if left == Value::Null || right == Value::Null => Some(Value::Null),
+        Add => match (&left, &right) {
+            (Value::String(left), Value::String(right)) => {
+                Some(Value::String(format!("{}{}", left, right)))
+            }
+            _ => arithmetic(left, right, i64::checked_add, |a, b| a + b),
+        },
+        Subtract => arithmetic(left, right, i64::checked_sub, |a, b| a - b),
+        Multiply => arithmetic(left, right, i64::checked_mul, |a, b| a * b),
+        Divide => {
+            let (left, right) = (left.to_number()?, right.to_number()?);
+            (right != 0.0).then_some(Value::Float(left / right))
+        }
+        IntegerDivide => left
+            .to_integer()?
+            .checked_div(right.to_integer()?)
+            .map(Value::Integer),
+        Modulo => left
+            .to_integer()?
+            .checked_rem(right.to_integer()?)
+            .map(Value::Integer),
+        Power => Some(Value::Float(left.to_number()?.powf(right.to_number()?))),
+        Equal => compare(&left, &right).map(|o| Value::Boolean(o == Ordering::Equal)),
+        NotEqual => compare(&left, &right).map(|o| Value::Boolean(o != Ordering::Equal)),
+        Less => compare(&left, &right).map(|o| Value::Boolean(o == Ordering::Less)),
+        Greater => compare(&left, &right).map(|o| Value::Boolean(o == Ordering::Greater)),
+        LessOrEqual => compare(&left, &right).map(|o| Value::Boolean(o != Ordering::Greater)),
+        GreaterOrEqual => compare(&left, &right).map(|o| Value::Boolean(o != Ordering::Less)),
+        And => logical(left, right, |a, b| a & b),
+        Or => logical(left, right, |a, b| a | b),
+        Xor => logical(left, right, |a, b| a ^ b),
+        Eqv => logical(left, right, |a, b| !(a ^ b)),
+        Imp => logical(left, right, |a, b| !a | b),
+    }
+}
+
+/// Applies an arithmetic operator, on integers while the result fits
+fn arithmetic(
+    left: Value,
+    right: Value,
+    integer: fn(i64, i64) -> Option<i64>,
+    float: fn(f64, f64) -> f64,
+) -> Option<Value> {
+    let result = float(left.to_number()?, right.to_number()?);
+    match (integer_operand(&left), integer_operand(&right)) {
+        (Some(left), Some(right)) => Some(integer_or_float(integer(left, right), result)),
+        _ => Some(Value::Float(result)),
+    }
+}
+
+/// Returns the value of operands that keep arithmetic on integers
+fn integer_operand(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(value) => Some(*value),
+        Value::Empty => Some(0),
+        Value::Boolean(value) => Some(if *value { -1 } else { 0 }),
+        _ => None,
+    }
+}
+
+fn integer_or_float(integer: Option<i64>, float: f64) -> Value {
+    integer.map_or(Value::Float(float), Value::Integer)
+}
+
+/// Applies a logical operator: on booleans if both operands are, bitwise otherwise
+fn logical(left: Value, right: Value, operator: fn(i64, i64) -> i64) -> Option<Value> {
+    match (&left, &right) {
+        (Value::Boolean(left), Value::Boolean(right)) => Some(Value::Boolean(
+            operator(-(*left as i64), -(*right as i64)) != 0,
+        )),
+        _ => Some(Value::Integer(operator(
+            left.to_integer()?,
+            right.to_integer()?,
+        ))),
+    }
+}
+
+/// Compares two values: strings by their characters, anything else as numbers
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (Value::String(_), _) | (_, Value::String(_)) => None,
+        _ => left.to_number()?.partial_cmp(&right.to_number()?),
+    }
+}
+
+/// Calls a VBScript built-in function on constant arguments
+fn function(name: &str, arguments: &[Value]) -> Option<Value> {
+    let text = |i: usize| arguments.get(i).and_then(Value::to_text);
+    let integer = |i: usize| arguments.get(i).and_then(Value::to_integer);
+    let count = |i: usize| integer(i).and_then(|n| usize::try_from(n).ok());
+    let string = |value: String| Some(Value::String(value));
+
+    if arguments.first() == Some(&Value::Null) && !name.eq_ignore_ascii_case("IsNull") {
+        return Some(Value::Null);
+    }
+    match name.to_lowercase().as_str() {
+        "chr" | "chrw" if arguments.len() == 1 => {
+            let code = u32::try_from(integer(0)? & 0xFFFF).ok()?;
+            string(char::from_u32(code)?.to_string())
+        }
+        "asc" | "ascw" if arguments.len() == 1 => {
+            Some(Value::Integer(text(0)?.chars().next()? as i64))
+        }
+        "len" if arguments.len() == 1 => Some(Value::Integer(text(0)?.chars().count() as i64)),
+        "lcase" if arguments.len() == 1 => string(text(0)?.to_lowercase()),
+        "ucase" if arguments.len() == 1 => string(text(0)?.to_uppercase()),
+        "trim" if arguments.len() == 1 => string(text(0)?.trim_matches(' ').to_string()),
+        "ltrim" if arguments.len() == 1 => string(text(0)?.trim_start_matches(' ').to_string()),
+        "rtrim" if arguments.len() == 1 => string(text(0)?.trim_end_matches(' ').to_string()),
+        "left" if arguments.len() == 2 => string(text(0)?.chars().take(count(1)?).collect()),
+        "right" if arguments.len() == 2 => {
+            let value = text(0)?;
+            let skip = value.chars().count().saturating_sub(count(1)?);
+            string(value.chars().skip(skip).collect())
+        }
+        "mid" if matches!(arguments.len(), 2 | 3) => {
+            let start = count(1)?.checked_sub(1)?;
+            let length = match arguments.len() {
+                3 => count(2)?,
+                _ => usize::MAX,
+            };
+            string(text(0)?.chars().skip(start).take(length).collect())
+        }
+        "replace" if arguments.len() == 3 => {
+            let find = text(1)?;
+            match find.is_empty() {
+                true => string(text(0)?),
+                false => string(text(0)?.replace(&find, &text(2)?)),
+            }
+        }
+        "space" if arguments.len() == 1 => string(" ".repeat(count(0)?)),
+        "string" if arguments.len() == 2 => {
+            let character = match &arguments[1] {
+                Value::String(value) => value.chars().next()?,
+                value => char::from_u32(u32::try_from(value.to_integer()? & 0xFF).ok()?)?,
+            };
+            string(character.to_string().repeat(count(0)?))
+        }
+        "strreverse" if arguments.len() == 1 => string(text(0)?.chars().rev().collect()),
+        "instr" if arguments.len() == 2 => {
+            let (haystack, needle) = (text(0)?, text(1)?);
+            let position = haystack
+                .find(&needle)
+                .map_or(0, |i| haystack[..i].chars().count() + 1);
+            Some(Value::Integer(position as i64))
+        }
+        "cstr" if arguments.len() == 1 => string(text(0)?),
+        "cint" | "clng" if arguments.len() == 1 => Some(Value::Integer(integer(0)?)),
+        "cdbl" | "csng" if arguments.len() == 1 => Some(Value::Float(arguments[0].to_number()?)),
+        "cbool" if arguments.len() == 1 => Some(Value::Boolean(arguments[0].to_number()? != 0.0)),
+        "hex" if arguments.len() == 1 => string(format!("{:X}", count(0)?)),
+        "oct" if arguments.len() == 1 => string(format!("{:o}", count(0)?)),
+        "abs" if arguments.len() == 1 => match &arguments[0] {
+            Value::Integer(value) => {
+                Some(integer_or_float(value.checked_abs(), (*value as f64).abs()))
+            }
+            value => Some(Value::Float(value.to_number()?.abs())),
+        },
+        "isnull" if arguments.len() == 1 => Some(Value::Boolean(arguments[0] == Value::Null)),
+        _ => None,
+    }
+}
@@ -0,0 +1,328 @@
+//! Declarations of `global.asa`
+//!
+//! The `global.asa` file at the root of an ASP application holds no page: its
+//! `<script runat="server">` blocks define the handlers of the application and
+//! session events (`Application_OnStart`, `Session_OnEnd`, ...), and its
+//! `<object runat="server">` tags declare objects created once for the whole
+//! application or once per session, which every page of the application uses
+//! without declaring them.
+//!
+//! [`global_asa`] lists these declarations. [`symbols`](super::symbols) and
+//! [`bindings`](super::bindings) also declare the objects of `<object>` tags, and
+//! the [`WorkspaceIndex`](super::WorkspaceIndex) makes the declarations of
+//! `global.asa` visible from the pages of its application.
+//!
+//! ```
+//! use asp_classic_parser::analysis::global_asa::{self, ApplicationEvent, ObjectScope};
+//! use asp_classic_parser::parser;
+//!
+//! let source = "<object runat=\"server\" scope=\"Session\" id=\"Cart\" progid=\"Shop.Cart\"></object>\n<script language=\"VBScript\" runat=\"server\">\nSub Session_OnStart\n  Cart.Clear\nEnd Sub\n</script>";
+//! let global = global_asa::global_asa(&parser::parse_recovering(source).program);
+//!
+//! let cart = &global.objects[0];
+//! assert_eq!((cart.id.as_str(), cart.scope), ("Cart", ObjectScope::Session));
+//! assert_eq!(cart.prog_id.as_deref(), Some("Shop.Cart"));
+//! assert_eq!(global.events[0].event, ApplicationEvent::SessionOnStart);
+//! assert_eq!(global.scripts.len(), 1);
+//! ```
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::scopes::blocks;
+use super::symbols::{self, Symbol, SymbolKind};
+use crate::parser::ast::{Html, Program, Span, Statement};
+
+/// Name of the file of the application declarations
+pub const FILE_NAME: &str = "global.asa";
+
+/// Lifetime of an object declared with an `<object runat="server">` tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ObjectScope {
+    /// Created for the page declaring it, without `scope` or with `scope="Page"`
+    Page,
+    /// Created once per session, in `global.asa`
+    Session,
+    /// Created once for the application, in `global.asa`
+    Application,
+}
+
+/// An object declared with an `<object runat="server">` tag
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ObjectDeclaration {
+    /// Name of the object, from the `id` attribute
+    pub id: String,
+    /// Lifetime of the object
+    pub scope: ObjectScope,
+    /// Programmatic identifier of the component (`progid`), such as
+    /// `MSWC.BrowserType`
+    pub prog_id: Option<String>,
+    /// Class identifier of the component (`classid`), such as `clsid:...`
+    pub class_id: Option<String>,
+    /// Location of the value of the `id` attribute
+    pub id_span: Span,
+    /// Location of the opening tag
+    pub span: Span,
+}
+
+/// Events of an application whose handlers `global.asa` defines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ApplicationEvent {
+    /// First request to the application
+    ApplicationOnStart,
+    /// Shutdown of the application
+    ApplicationOnEnd,
+    /// First request of a new session
+    SessionOnStart,
+    /// End or timeout of a session
+    SessionOnEnd,
+}
+
+impl ApplicationEvent {
+    /// Every event, in the order of the application lifetime
+    pub const ALL: [ApplicationEvent; 4] = [
+        ApplicationEvent::ApplicationOnStart,
+        ApplicationEvent::SessionOnStart,
+        ApplicationEvent::SessionOnEnd,
+        ApplicationEvent::ApplicationOnEnd,
+    ];
+
+    /// Returns the name of the sub handling the event, such as
+    /// `Application_OnStart`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApplicationEvent::ApplicationOnStart => "Application_OnStart",
+            ApplicationEvent::ApplicationOnEnd => "Application_OnEnd",
+            ApplicationEvent::SessionOnStart => "Session_OnStart",
+            ApplicationEvent::SessionOnEnd => "Session_OnEnd",
+        }
+    }
+
+    /// Returns the event handled by a sub of the given name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|event| event.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// A sub of `global.asa` handling an application or session event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EventHandler {
+    /// The handled event
+    pub event: ApplicationEvent,
+    /// The sub
+    pub symbol: Symbol,
+}
+
+/// Declarations of a `global.asa` file
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct GlobalAsa {
+    /// Objects of the `<object runat="server">` tags, in source order
+    pub objects: Vec<ObjectDeclaration>,
+    /// Subs handling application and session events, in source order
+    pub events: Vec<EventHandler>,
+    /// Locations of the `<script runat="server">` blocks
+    pub scripts: Vec<Span>,
+}
+
+/// Returns whether a file is the `global.asa` of an application, ignoring case
+pub fn is_global_asa(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.eq_ignore_ascii_case(FILE_NAME))
+}
+
+/// Returns the `global.asa` of the application of a file: the closest one in the
+/// directory of the file or its parents, ignoring the case of its name
+pub fn find_global_asa(path: &Path) -> Option<PathBuf> {
+    path.parent()?.ancestors().find_map(|directory| {
+        fs::read_dir(directory)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|candidate| is_global_asa(candidate) && candidate.is_file())
+    })
+}
+
+/// Lists the declarations of a `global.asa` file
+///
+/// # Arguments
+///
+/// * `program` - The syntax tree of the file
+///
+/// # Returns
+///
+/// * `GlobalAsa` - The objects, event handlers and script blocks of the file
+pub fn global_asa(program: &Program) -> GlobalAsa {
+    let events = symbols::symbols(program)
+        .symbols
+        .into_iter()
+        .filter(|symbol| symbol.kind == SymbolKind::Sub)
+        .filter_map(|symbol| {
+            ApplicationEvent::from_name(&symbol.name).map(|event| EventHandler { event, symbol })
+        })
+        .collect();
+    let scripts = program
+        .body
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::ServerScript(script) => Some(script.span),
+            _ => None,
+        })
+        .collect();
+    GlobalAsa {
+        objects: objects(program),
+        events,
+        scripts,
+    }
+}
+
+/// Lists the objects declared with `<object runat="server">` tags in a page or in
+/// `global.asa`
+///
+/// Tags without `runat="server"` are client-side objects, and tags without `id`
+/// declare no name; neither is listed.
+pub fn objects(program: &Program) -> Vec<ObjectDeclaration> {
+    let mut objects = Vec::new();
+    collect(&program.body, &mut objects);
+    objects
+}
+
+fn collect(statements: &[Statement], objects: &mut Vec<ObjectDeclaration>) {
+    for statement in statements {
+        match statement {
+            Statement::Html(html) => objects.extend(html_objects(html)),
+            statement => {
+                for body in blocks(statement) {
+                    collect(body, objects);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the objects declared by the `<object runat="server">` tags of markup
+pub(crate) fn html_objects(html: &Html) -> Vec<ObjectDeclaration> {
+    if html.text.starts_with("<!--") {
+        return Vec::new();
+    }
+    let lowercase = html.text.to_ascii_lowercase();
+    lowercase
+        .match_indices("<object")
+        .filter(|(index, tag)| {
+            lowercase[index + tag.len()..]
+                .chars()
+                .next()
+                .is_some_and(char::is_whitespace)
+        })
+        .filter_map(|(start, _)| {
+            let end = tag_end(&html.text, start)?;
+            object(html, start, end)
+        })
+        .collect()
+}
+
+/// Returns the object declared by the tag between two offsets of markup
+fn object(html: &Html, start: usize, end: usize) -> Option<ObjectDeclaration> {
+    let attributes = attributes(&html.text[start..end]);
+    let value = |name: &str| {
+        attributes
+            .iter()
+            .find(|(attribute, _, _)| attribute.eq_ignore_ascii_case(name))
+            .map(|(_, value, offset)| (*value, start + offset))
+    };
+    if !value("runat").is_some_and(|(runat, _)| runat.eq_ignore_ascii_case("server")) {
+        return None;
+    }
+    let (id, id_offset) = value("id").filter(|(id, _)| !id.is_empty())?;
+    let scope = match value("scope").map(|(scope, _)| scope.to_ascii_lowercase()) {
+        Some(scope) if scope == "application" => ObjectScope::Application,
+        Some(scope) if scope == "session" => ObjectScope::Session,
+        _ => ObjectScope::Page,
+    };
+    Some(ObjectDeclaration {
+        id: id.to_string(),
+        scope,
+        prog_id: value("progid").map(|(prog_id, _)| prog_id.to_string()),
+        class_id: value("classid").map(|(class_id, _)| class_id.to_string()),
+        id_span: span_in(html, id_offset, id_offset + id.len()),
+        span: span_in(html, start, end),
+    })
+}
+
+/// Returns the offset just past the `>` closing the tag starting at an offset,
+/// skipping quoted attribute values
+fn tag_end(text: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in text[start..].char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(start + index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Returns the `name="value"` attributes of a tag, with the offset of each value
+/// in the tag; values are unquoted, and attributes without value are skipped
+fn attributes(tag: &str) -> Vec<(&str, &str, usize)> {
+    let mut attributes = Vec::new();
+    // Skip the tag name
+    let mut rest = tag.trim_start_matches('<');
+    rest = rest.trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+    loop {
+        rest = rest.trim_start();
+        let name_length = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len());
+        if name_length == 0 {
+            // `/` of a self-closing tag, or the end of the tag
+            match rest.chars().next() {
+                Some('/') => {
+                    rest = &rest[1..];
+                    continue;
+                }
+                _ => break,
+            }
+        }
+        let name = &rest[..name_length];
+        rest = rest[name_length..].trim_start();
+        let Some(after_equals) = rest.strip_prefix('=') else {
+            continue;
+        };
+        rest = after_equals.trim_start();
+        let (value, value_start, consumed) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let length = rest[1..].find(quote).unwrap_or(rest.len() - 1);
+                (&rest[1..1 + length], 1, (length + 2).min(rest.len()))
+            }
+            _ => {
+                let length = rest
+                    .find(|c: char| c.is_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                (&rest[..length], 0, length)
+            }
+        };
+        let offset = tag.len() - rest.len() + value_start;
+        attributes.push((name, value, offset));
+        rest = &rest[consumed..];
+    }
+    attributes
+}
+
+/// Returns the location of a part of markup, from offsets in its text
+fn span_in(html: &Html, start: usize, end: usize) -> Span {
+    let before = &html.text[..start];
+    let (line, column) = match before.rfind('\n') {
+        Some(newline) => (
+            html.span.line + before.matches('\n').count(),
+            before[newline + 1..].chars().count() + 1,
+        ),
+        None => (html.span.line, html.span.column + before.chars().count()),
+    };
+    Span::new(html.span.start + start, html.span.start + end, line, column)
+}
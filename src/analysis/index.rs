@@ -0,0 +1,519 @@
+//! Symbol index of a workspace
+//!
+//! Code of an ASP site is spread over pages and the files they include. A
+//! [`WorkspaceIndex`] keeps the syntax tree and the symbol table of every file it
+//! is given, and the files each one includes, so that a function defined in
+//! `lib/db.asp` is found when it is called from `orders.asp`.
+//!
+//! A file sees the declarations of the files it includes, directly or not. Since an
+//! included file is inlined into the pages including it, it also sees the
+//! declarations of these pages and of everything they include. Every file also
+//! sees the `global.asa` of its application, the closest one in its directory or
+//! their parents, which declares the application and session objects.
+//!
+//! ```no_run
+//! use asp_classic_parser::analysis::WorkspaceIndex;
+//! use asp_classic_parser::includes::IncludeResolver;
+//! use std::path::Path;
+//!
+//! let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root("/var/www/site"));
+//! let page = Path::new("/var/www/site/orders.asp");
+//! index.update(page, "<!--#include virtual=\"/lib/db.asp\" -->\n<% Connect %>");
+//! index.load_includes(page);
+//!
+//! let connect = index.definition(page, "Connect").unwrap();
+//! println!("Connect is defined in {}", connect.path.display());
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use super::global_asa::{find_global_asa, is_global_asa};
+use super::scopes::{
+    Bindings, DeclarationId, DeclarationKind, Reference, Resolution, ScopeKind, bindings,
+};
+use super::symbols::{Symbol, SymbolTable, symbols};
+use crate::file_utils::read_file_with_encoding;
+use crate::includes::{self, IncludeDirective, IncludeGraph, IncludeResolver};
+use crate::parser::{
+    self,
+    ast::{Program, Span},
+};
+
+/// A file of the index
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedFile {
+    /// Path of the file, as returned by [`includes::canonical_path`]
+    pub path: PathBuf,
+    /// Content of the file
+    pub source: String,
+    /// Syntax tree of the file; partial if the file has syntax errors
+    pub program: Program,
+    /// Symbols declared by the file
+    pub symbols: SymbolTable,
+    /// Include directives of the file
+    pub directives: Vec<IncludeDirective>,
+    /// Files included by the file, in directive order
+    pub includes: Vec<PathBuf>,
+}
+
+/// A symbol found in the index
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SymbolLocation<'a> {
+    /// File declaring the symbol
+    pub path: &'a Path,
+    /// The symbol
+    pub symbol: &'a Symbol,
+    /// Class declaring the symbol, for class members
+    pub container: Option<&'a Symbol>,
+}
+
+/// A name in a file of the index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NameLocation<'a> {
+    /// File containing the name
+    pub path: &'a Path,
+    /// Location of the name
+    pub span: Span,
+}
+
+/// Symbols of the files of a workspace
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceIndex {
+    /// Resolves the include directives of the files
+    resolver: IncludeResolver,
+    /// Indexed files, by path
+    files: BTreeMap<PathBuf, IndexedFile>,
+    /// Path of the indexed `global.asa` of each application, by the directory
+    /// of the application
+    applications: BTreeMap<PathBuf, PathBuf>,
+}
+
+impl WorkspaceIndex {
+    /// Creates an empty index resolving includes with the given resolver
+    pub fn new(resolver: IncludeResolver) -> Self {
+        WorkspaceIndex {
+            resolver,
+            files: BTreeMap::new(),
+            applications: BTreeMap::new(),
+        }
+    }
+
+    /// Creates an index of the files of an include graph
+    pub fn from_graph(graph: &IncludeGraph, resolver: IncludeResolver) -> Self {
+        let mut index = Self::new(resolver);
+        for file in &graph.files {
+            index.update(&file.path, &file.source);
+        }
+        index
+    }
+
+    /// Returns the resolver of the include directives of the files
+    pub fn resolver(&self) -> &IncludeResolver {
+        &self.resolver
+    }
+
+    /// Replaces the resolver of the include directives, and resolves again the
+    /// includes of the indexed files
+    ///
+    /// The files they now include are not loaded; see
+    /// [`WorkspaceIndex::load_includes`].
+    pub fn set_resolver(&mut self, resolver: IncludeResolver) {
+        self.resolver = resolver;
+        let resolver = &self.resolver;
+        for file in self.files.values_mut() {
+            file.includes = file
+                .directives
+                .iter()
+                .filter_map(|directive| resolver.resolve(&file.path, directive))
+                .map(|included| includes::canonical_path(&included))
+                .collect();
+        }
+    }
+
+    /// Adds a file to the index, or replaces its content
+    ///
+    /// The files it includes are not loaded; see [`WorkspaceIndex::load_includes`].
+    pub fn update(&mut self, path: &Path, source: &str) {
+        self.update_parsed(path, source, parser::parse_recovering(source).program);
+    }
+
+    /// Adds a file to the index from its syntax tree, or replaces it
+    ///
+    /// The syntax tree must be the one of `source`, as returned by
+    /// [`parser::parse_recovering`].
+    pub fn update_parsed(&mut self, path: &Path, source: &str, program: Program) {
+        let path = includes::canonical_path(path);
+        let directives = includes::directives(source);
+        let includes = directives
+            .iter()
+            .filter_map(|directive| self.resolver.resolve(&path, directive))
+            .map(|included| includes::canonical_path(&included))
+            .collect();
+        let file = IndexedFile {
+            symbols: symbols(&program),
+            path: path.clone(),
+            source: source.to_string(),
+            program,
+            directives,
+            includes,
+        };
+        if is_global_asa(&path)
+            && let Some(directory) = path.parent()
+        {
+            self.applications
+                .insert(directory.to_path_buf(), path.clone());
+        }
+        self.files.insert(path, file);
+    }
+
+    /// Loads from disk the files included by a file, directly or not, and the
+    /// `global.asa` of its application, that are not indexed yet
+    ///
+    /// Files that cannot be read are skipped.
+    pub fn load_includes(&mut self, path: &Path) {
+        let mut pending = vec![includes::canonical_path(path)];
+        if let Some(global_asa) = find_global_asa(&pending[0]) {
+            pending.push(includes::canonical_path(&global_asa));
+        }
+        let mut seen = HashSet::new();
+        while let Some(path) = pending.pop() {
+            if !seen.insert(path.clone()) {
+                continue;
+            }
+            if !self.files.contains_key(&path) {
+                match read_file_with_encoding(&path) {
+                    Ok(source) => self.update(&path, &source),
+                    Err(_) => continue,
+                }
+            }
+            pending.extend(self.files[&path].includes.iter().cloned());
+        }
+    }
+
+    /// Removes a file from the index
+    pub fn remove(&mut self, path: &Path) {
+        let path = includes::canonical_path(path);
+        if let Some(directory) = path.parent()
+            && self.applications.get(directory) == Some(&path)
+        {
+            self.applications.remove(directory);
+        }
+        self.files.remove(&path);
+    }
+
+    /// Returns an indexed file
+    pub fn file(&self, path: &Path) -> Option<&IndexedFile> {
+        self.files.get(&includes::canonical_path(path))
+    }
+
+    /// Returns the indexed files, sorted by path
+    pub fn files(&self) -> impl Iterator<Item = &IndexedFile> {
+        self.files.values()
+    }
+
+    /// Returns the indexed files whose declarations a file sees
+    ///
+    /// The file itself comes first, then the files it includes in the order IIS
+    /// inlines them, then the pages including it (directly or not) with their own
+    /// includes, and last the `global.asa` of its application.
+    pub fn visible_files(&self, path: &Path) -> Vec<&IndexedFile> {
+        let path = includes::canonical_path(path);
+        let mut seen = HashSet::new();
+        let mut visible = Vec::new();
+        self.collect_includes(&path, &mut seen, &mut visible);
+        for includer in self.includers(&path) {
+            self.collect_includes(includer, &mut seen, &mut visible);
+        }
+        if let Some(global_asa) = self.application_file(&path) {
+            self.collect_includes(&global_asa.path, &mut seen, &mut visible);
+        }
+        visible
+    }
+
+    /// Returns the indexed `global.asa` of the application of a file: the closest
+    /// one in the directory of the file or its parents
+    pub fn application_file(&self, path: &Path) -> Option<&IndexedFile> {
+        let path = includes::canonical_path(path);
+        path.ancestors()
+            .find_map(|directory| self.applications.get(directory))
+            .and_then(|global_asa| self.files.get(global_asa))
+    }
+
+    /// Adds a file and the files it includes, depth first
+    fn collect_includes<'a>(
+        &'a self,
+        path: &Path,
+        seen: &mut HashSet<&'a Path>,
+        visible: &mut Vec<&'a IndexedFile>,
+    ) {
+        let Some(file) = self.files.get(path) else {
+            return;
+        };
+        if !seen.insert(&file.path) {
+            return;
+        }
+        visible.push(file);
+        for included in &file.includes {
+            self.collect_includes(included, seen, visible);
+        }
+    }
+
+    /// Returns the files including a file, directly or not
+    fn includers(&self, path: &Path) -> Vec<&Path> {
+        let mut includers: Vec<&Path> = Vec::new();
+        let mut pending = vec![path];
+        while let Some(included) = pending.pop() {
+            for file in self.files.values() {
+                if file.includes.iter().any(|path| path == included)
+                    && file.path != path
+                    && !includers.contains(&file.path.as_path())
+                {
+                    includers.push(&file.path);
+                    pending.push(&file.path);
+                }
+            }
+        }
+        includers
+    }
+
+    /// Returns the include directives of a file that do not resolve to an indexed
+    /// file
+    ///
+    /// Names used by a file with unresolved includes may be declared in the missing
+    /// files.
+    pub fn unresolved_includes(&self, path: &Path) -> Vec<&IncludeDirective> {
+        let Some(file) = self.file(path) else {
+            return Vec::new();
+        };
+        file.directives
+            .iter()
+            .filter(|directive| {
+                self.resolver
+                    .resolve(&file.path, directive)
+                    .is_none_or(|included| self.file(&included).is_none())
+            })
+            .collect()
+    }
+
+    /// Returns the declaration of a page-level symbol visible from a file
+    ///
+    /// Classes, procedures, constants and variables declared at the page level of
+    /// the file and of the files it sees are searched, ignoring case; class members
+    /// are not.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file using the name
+    /// * `name` - The name of the symbol
+    ///
+    /// # Returns
+    ///
+    /// * `Option<SymbolLocation>` - The first declaration found, in the order of
+    ///   [`WorkspaceIndex::visible_files`]
+    pub fn definition(&self, path: &Path, name: &str) -> Option<SymbolLocation<'_>> {
+        self.visible_files(path).into_iter().find_map(|file| {
+            file.symbols.get(name).map(|symbol| SymbolLocation {
+                path: &file.path,
+                symbol,
+                container: None,
+            })
+        })
+    }
+
+    /// Returns the declaration of the name at a byte offset of a file, whether the
+    /// offset is on a use of the name or on its declaration
+    ///
+    /// Names declared in the file resolve to their declaration, including local
+    /// variables and parameters; other names are searched in the files the file
+    /// sees, as with [`WorkspaceIndex::definition`]. On an include directive, the
+    /// start of the included file is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The indexed file
+    /// * `offset` - The byte offset of the name in the file
+    ///
+    /// # Returns
+    ///
+    /// * `Option<NameLocation>` - The name in its declaration, or `None` for
+    ///   predefined and undeclared names
+    pub fn definition_at(&self, path: &Path, offset: usize) -> Option<NameLocation<'_>> {
+        let file = self.file(path)?;
+        let contains = |span: &Span| span.start <= offset && offset <= span.end;
+        if let Some(directive) = file
+            .directives
+            .iter()
+            .find(|directive| contains(&directive.span))
+        {
+            let included = self.file(&self.resolver.resolve(&file.path, directive)?)?;
+            return Some(NameLocation {
+                path: &included.path,
+                span: Span::new(0, 0, 1, 1),
+            });
+        }
+
+        let bindings = bindings(&file.program);
+        match bindings
+            .references
+            .iter()
+            .find(|reference| contains(&reference.span))
+        {
+            Some(reference) => self.resolve(file, &bindings, reference),
+            None => bindings.declaration_at(offset).map(|id| NameLocation {
+                path: &file.path,
+                span: bindings.declarations[id].span,
+            }),
+        }
+    }
+
+    /// Returns the uses of the name at a byte offset of a file, in every indexed
+    /// file where the name refers to the same declaration
+    ///
+    /// Names declared in a procedure or a class are only searched in the file
+    /// declaring them. Names used in strings (`GetRef("Name")`) are included.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The indexed file
+    /// * `offset` - The byte offset of the name in the file, on a use of the name
+    ///   or on its declaration
+    /// * `include_declaration` - Whether to return the name in its declaration
+    ///   first
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<NameLocation>` - The uses of the name, by file and in source order;
+    ///   empty for predefined and undeclared names
+    pub fn references(
+        &self,
+        path: &Path,
+        offset: usize,
+        include_declaration: bool,
+    ) -> Vec<NameLocation<'_>> {
+        let Some(target) = self.definition_at(path, offset) else {
+            return Vec::new();
+        };
+        let Some(declaring) = self.file(target.path) else {
+            return Vec::new();
+        };
+        let declaring_bindings = bindings(&declaring.program);
+        // Include directives lead to a file, not to a declaration
+        let Some(declaration) = declaring_bindings
+            .declarations
+            .iter()
+            .find(|declaration| declaration.span == target.span)
+        else {
+            return Vec::new();
+        };
+        let files: Vec<&IndexedFile> =
+            if declaring_bindings.scopes[declaration.scope].kind == ScopeKind::Global {
+                self.files.values().collect()
+            } else {
+                vec![declaring]
+            };
+
+        let mut found = Vec::new();
+        if include_declaration {
+            found.push(target);
+        }
+        for file in files {
+            let file_bindings = bindings(&file.program);
+            for reference in &file_bindings.references {
+                let location = NameLocation {
+                    path: &file.path,
+                    span: reference.span,
+                };
+                if reference.name.eq_ignore_ascii_case(&declaration.name)
+                    && location != target
+                    && self.resolve(file, &file_bindings, reference) == Some(target)
+                {
+                    found.push(location);
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns the declaration a use of a name refers to
+    fn resolve<'a>(
+        &'a self,
+        file: &'a IndexedFile,
+        bindings: &Bindings,
+        reference: &Reference,
+    ) -> Option<NameLocation<'a>> {
+        let local = |id: DeclarationId| NameLocation {
+            path: &file.path,
+            span: bindings.declarations[id].span,
+        };
+        match reference.resolution {
+            Resolution::Declaration(id)
+                if bindings.declarations[id].kind != DeclarationKind::Implicit =>
+            {
+                Some(local(id))
+            }
+            Resolution::Builtin => None,
+            // Variables created by their first use may be declared by an include
+            resolution => self
+                .definition(&file.path, &reference.name)
+                .map(|location| NameLocation {
+                    path: location.path,
+                    span: location.symbol.name_span,
+                })
+                .or(match resolution {
+                    Resolution::Declaration(id) => Some(local(id)),
+                    _ => None,
+                }),
+        }
+    }
+
+    /// Returns the symbols of all the files whose name contains a query, ignoring
+    /// case
+    ///
+    /// Class members are included. An empty query returns every symbol.
+    pub fn search(&self, query: &str) -> Vec<SymbolLocation<'_>> {
+        let query = query.to_lowercase();
+        let matches = |symbol: &Symbol| symbol.name.to_lowercase().contains(&query);
+        let mut found = Vec::new();
+        for file in self.files.values() {
+            for symbol in &file.symbols.symbols {
+                if matches(symbol) {
+                    found.push(SymbolLocation {
+                        path: &file.path,
+                        symbol,
+                        container: None,
+                    });
+                }
+                for member in symbol.members.iter().filter(|member| matches(member)) {
+                    found.push(SymbolLocation {
+                        path: &file.path,
+                        symbol: member,
+                        container: Some(symbol),
+                    });
+                }
+            }
+        }
+        found
+    }
+
+    /// Returns the uses of names declared nowhere, neither in a file nor in the
+    /// files it sees
+    ///
+    /// As with [`Bindings::undeclared`](super::Bindings::undeclared), only files
+    /// with `Option Explicit` can have undeclared names.
+    pub fn undeclared(&self, path: &Path) -> Vec<Reference> {
+        let Some(file) = self.file(path) else {
+            return Vec::new();
+        };
+        let visible = self.visible_files(path);
+        bindings(&file.program)
+            .undeclared()
+            .filter(|reference| {
+                visible[1..]
+                    .iter()
+                    .all(|other| other.symbols.get(&reference.name).is_none())
+            })
+            .cloned()
+            .collect()
+    }
+}
@@ -0,0 +1,37 @@
+//! Semantic analysis of ASP Classic pages
+//!
+//! The functions of this module work on the syntax tree returned by
+//! [`parse_to_ast`](crate::parser::parse_to_ast) (or the partial tree of
+//! [`parse_recovering`](crate::parser::parse_recovering)), and never need the source
+//! text again, except [`doc_comment`] which reads the comments the tree does not
+//! keep.
+//!
+//! [`symbols`] lists the declarations of a page: procedures, classes and their
+//! members, constants and module-level variables. [`bindings`] resolves every use
+//! of a name to its declaration, and [`call_graph`] links procedures to the
+//! procedures they call. [`evaluate`] computes the value of constant expressions.
+//! [`objects`] describes the members of the ASP intrinsic objects.
+//!
+//! [`WorkspaceIndex`] gathers the symbols of several files, following their
+//! `#include` directives, and the declarations of the `global.asa` of their
+//! application, which [`global_asa`] lists.
+pub mod builtins;
+pub mod call_graph;
+pub mod duplicates;
+pub mod evaluate;
+pub mod global_asa;
+pub mod index;
+pub mod objects;
+pub mod scopes;
+pub mod symbols;
+
+pub use call_graph::{CallEdge, CallGraph, ProcedureNode, call_graph};
+pub use duplicates::{CloneGroup, DuplicateFinder, Occurrence};
+pub use evaluate::{Evaluator, Value, evaluate};
+pub use global_asa::{ApplicationEvent, GlobalAsa, ObjectDeclaration, ObjectScope};
+pub use index::{IndexedFile, NameLocation, SymbolLocation, WorkspaceIndex};
+pub use scopes::{
+    Bindings, CallKind, Declaration, DeclarationId, DeclarationKind, Reference, Resolution, Scope,
+    ScopeId, ScopeKind, bindings,
+};
+pub use symbols::{Symbol, SymbolKind, SymbolTable, doc_comment, symbols};
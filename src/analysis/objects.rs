@@ -0,0 +1,1010 @@
+//! Object model of the objects ASP pages use
+//!
+//! [`INTRINSIC_OBJECTS`] describes the objects ASP gives every page (`Response`,
+//! `Request`, `Server`, ...), the VBScript `Err` object, and the `ASPError` object
+//! returned by `Server.GetLastError`, with their properties, methods and
+//! collections. [`COM_OBJECTS`] describes the COM objects pages commonly create
+//! with `Server.CreateObject` (ADO, the scripting runtime, MSXML and CDO), found
+//! from their ProgID by [`prog_id_type`].
+//!
+//! [`ObjectTypes`] finds the object an expression of a page refers to, so that its
+//! members can be checked and completed.
+//!
+//! # Examples
+//!
+//! ```
+//! use asp_classic_parser::analysis::objects::{self, MemberKind};
+//!
+//! let response = objects::object_type("response").unwrap();
+//! assert_eq!(response.member("write").unwrap().kind, MemberKind::Method);
+//! assert!(response.member("Wrtie").is_none());
+//! assert_eq!(response.closest_member("Wrtie").unwrap().name, "Write");
+//!
+//! let connection = objects::prog_id_type("ADODB.Connection").unwrap();
+//! let recordset = connection.member("Execute").unwrap().returns;
+//! assert_eq!(recordset, Some("ADODB.Recordset"));
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use super::scopes::blocks;
+use super::{Bindings, Resolution, builtins};
+use crate::parser::ast::{
+    AssignmentKind, Expression, Identifier, LiteralValue, Program, Statement,
+};
+
+/// Kinds of members of an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    /// Value read or assigned (`Response.Buffer`)
+    Property,
+    /// Procedure called (`Response.Write`)
+    Method,
+    /// Collection indexed by name or position (`Request.Form("name")`)
+    Collection,
+}
+
+/// A property, method or collection of an object
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Member {
+    /// Name of the member, in its usual spelling
+    pub name: &'static str,
+    /// Kind of member
+    pub kind: MemberKind,
+    /// Name of the object type of the value of the member, when it is an object
+    /// described by the model: the result of a method, or the items of a
+    /// collection
+    pub returns: Option<&'static str>,
+}
+
+impl Member {
+    /// Sets the object type of the value of the member
+    const fn returning(self, returns: &'static str) -> Member {
+        Member {
+            returns: Some(returns),
+            ..self
+        }
+    }
+}
+
+/// An object type and its members
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjectType {
+    /// Name of the type, in its usual spelling; the intrinsic objects have the name
+    /// of their type, and the COM objects the ProgID creating them
+    pub name: &'static str,
+    /// Members of the type, sorted by name
+    pub members: &'static [Member],
+}
+
+impl ObjectType {
+    /// Returns the member of a name, ignoring case
+    pub fn member(&self, name: &str) -> Option<&'static Member> {
+        self.members
+            .iter()
+            .find(|member| member.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the type of the value of a member read without arguments, such as
+    /// `Server.GetLastError`, when it is an object described by the model
+    pub fn member_type(&self, name: &str) -> Option<&'static ObjectType> {
+        let member = self.member(name)?;
+        match member.kind {
+            // Collections hold the items of their type
+            MemberKind::Collection => None,
+            _ => object_type(member.returns?),
+        }
+    }
+
+    /// Returns the member whose name is the closest to a misspelled name, if one is
+    /// close enough to be a likely correction
+    pub fn closest_member(&self, name: &str) -> Option<&'static Member> {
+        let name = name.to_ascii_lowercase();
+        let limit = (name.len() / 3).clamp(1, 2);
+        self.members
+            .iter()
+            .map(|member| {
+                (
+                    edit_distance(&member.name.to_ascii_lowercase(), &name),
+                    member,
+                )
+            })
+            .filter(|(distance, _)| *distance <= limit)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, member)| member)
+    }
+}
+
+const fn property(name: &'static str) -> Member {
+    Member {
+        name,
+        kind: MemberKind::Property,
+        returns: None,
+    }
+}
+
+const fn method(name: &'static str) -> Member {
+    Member {
+        name,
+        kind: MemberKind::Method,
+        returns: None,
+    }
+}
+
+const fn collection(name: &'static str) -> Member {
+    Member {
+        name,
+        kind: MemberKind::Collection,
+        returns: None,
+    }
+}
+
+/// ASP intrinsic objects, the VBScript `Err` object, and the `ASPError` object
+pub const INTRINSIC_OBJECTS: &[ObjectType] = &[
+    ObjectType {
+        name: "Application",
+        members: &[
+            collection("Contents"),
+            method("Lock"),
+            collection("StaticObjects"),
+            method("Unlock"),
+            property("Value"),
+        ],
+    },
+    ObjectType {
+        name: "ASPError",
+        members: &[
+            property("ASPCode"),
+            property("ASPDescription"),
+            property("Category"),
+            property("Column"),
+            property("Description"),
+            property("File"),
+            property("Line"),
+            property("Number"),
+            property("Source"),
+        ],
+    },
+    ObjectType {
+        name: "Err",
+        members: &[
+            method("Clear"),
+            property("Description"),
+            property("HelpContext"),
+            property("HelpFile"),
+            property("Number"),
+            method("Raise"),
+            property("Source"),
+        ],
+    },
+    ObjectType {
+        name: "ObjectContext",
+        members: &[
+            property("ContextInfo"),
+            property("Count"),
+            method("CreateInstance"),
+            method("DisableCommit"),
+            method("EnableCommit"),
+            method("IsCallerInRole"),
+            method("IsInTransaction"),
+            method("IsSecurityEnabled"),
+            property("Item"),
+            property("Security"),
+            method("SetAbort"),
+            method("SetComplete"),
+        ],
+    },
+    ObjectType {
+        name: "Request",
+        members: &[
+            method("BinaryRead"),
+            collection("ClientCertificate"),
+            collection("Cookies"),
+            collection("Form"),
+            property("Item"),
+            collection("QueryString"),
+            collection("ServerVariables"),
+            property("TotalBytes"),
+        ],
+    },
+    ObjectType {
+        name: "Response",
+        members: &[
+            method("AddHeader"),
+            method("AppendToLog"),
+            method("BinaryWrite"),
+            property("Buffer"),
+            property("CacheControl"),
+            property("Charset"),
+            method("Clear"),
+            property("CodePage"),
+            property("ContentType"),
+            collection("Cookies"),
+            method("End"),
+            property("Expires"),
+            property("ExpiresAbsolute"),
+            method("Flush"),
+            property("IsClientConnected"),
+            property("LCID"),
+            property("PICS"),
+            method("Redirect"),
+            property("Status"),
+            method("Write"),
+        ],
+    },
+    ObjectType {
+        name: "Server",
+        members: &[
+            method("CreateObject"),
+            method("Execute"),
+            method("GetLastError").returning("ASPError"),
+            method("HTMLEncode"),
+            method("MapPath"),
+            property("ScriptTimeout"),
+            method("Transfer"),
+            method("URLEncode"),
+            method("URLPathEncode"),
+        ],
+    },
+    ObjectType {
+        name: "Session",
+        members: &[
+            method("Abandon"),
+            property("CodePage"),
+            collection("Contents"),
+            property("LCID"),
+            property("SessionID"),
+            collection("StaticObjects"),
+            property("Timeout"),
+            property("Value"),
+        ],
+    },
+];
+
+/// COM objects commonly created by pages, and the objects their members return
+pub const COM_OBJECTS: &[ObjectType] = &[
+    ObjectType {
+        name: "ADODB.Command",
+        members: &[
+            property("ActiveConnection"),
+            method("Cancel"),
+            property("CommandStream"),
+            property("CommandText"),
+            property("CommandTimeout"),
+            property("CommandType"),
+            method("CreateParameter").returning("ADODB.Parameter"),
+            property("Dialect"),
+            method("Execute").returning("ADODB.Recordset"),
+            property("Name"),
+            property("NamedParameters"),
+            collection("Parameters").returning("ADODB.Parameter"),
+            property("Prepared"),
+            collection("Properties"),
+            property("State"),
+        ],
+    },
+    ObjectType {
+        name: "ADODB.Connection",
+        members: &[
+            property("Attributes"),
+            method("BeginTrans"),
+            method("Cancel"),
+            method("Close"),
+            property("CommandTimeout"),
+            method("CommitTrans"),
+            property("ConnectionString"),
+            property("ConnectionTimeout"),
+            property("CursorLocation"),
+            property("DefaultDatabase"),
+            collection("Errors"),
+            method("Execute").returning("ADODB.Recordset"),
+            property("IsolationLevel"),
+            property("Mode"),
+            method("Open"),
+            method("OpenSchema").returning("ADODB.Recordset"),
+            collection("Properties"),
+            property("Provider"),
+            method("RollbackTrans"),
+            property("State"),
+            property("Version"),
+        ],
+    },
+    ObjectType {
+        name: "ADODB.Field",
+        members: &[
+            property("ActualSize"),
+            method("AppendChunk"),
+            property("Attributes"),
+            property("DataFormat"),
+            property("DefinedSize"),
+            method("GetChunk"),
+            property("Name"),
+            property("NumericScale"),
+            property("OriginalValue"),
+            property("Precision"),
+            collection("Properties"),
+            property("Status"),
+            property("Type"),
+            property("UnderlyingValue"),
+            property("Value"),
+        ],
+    },
+    ObjectType {
+        name: "ADODB.Parameter",
+        members: &[
+            method("AppendChunk"),
+            property("Attributes"),
+            property("Direction"),
+            property("Name"),
+            property("NumericScale"),
+            property("Precision"),
+            collection("Properties"),
+            property("Size"),
+            property("Type"),
+            property("Value"),
+        ],
+    },
+    ObjectType {
+        name: "ADODB.Recordset",
+        members: &[
+            property("AbsolutePage"),
+            property("AbsolutePosition"),
+            property("ActiveCommand"),
+            property("ActiveConnection"),
+            method("AddNew"),
+            property("BOF"),
+            property("Bookmark"),
+            property("CacheSize"),
+            method("Cancel"),
+            method("CancelBatch"),
+            method("CancelUpdate"),
+            method("Clone").returning("ADODB.Recordset"),
+            method("Close"),
+            method("CompareBookmarks"),
+            property("CursorLocation"),
+            property("CursorType"),
+            property("DataMember"),
+            property("DataSource"),
+            method("Delete"),
+            property("EditMode"),
+            property("EOF"),
+            collection("Fields").returning("ADODB.Field"),
+            property("Filter"),
+            method("Find"),
+            method("GetRows"),
+            method("GetString"),
+            property("Index"),
+            property("LockType"),
+            property("MarshalOptions"),
+            property("MaxRecords"),
+            method("Move"),
+            method("MoveFirst"),
+            method("MoveLast"),
+            method("MoveNext"),
+            method("MovePrevious"),
+            method("NextRecordset").returning("ADODB.Recordset"),
+            method("Open"),
+            property("PageCount"),
+            property("PageSize"),
+            collection("Properties"),
+            property("RecordCount"),
+            method("Requery"),
+            method("Resync"),
+            method("Save"),
+            method("Seek"),
+            property("Sort"),
+            property("Source"),
+            property("State"),
+            property("Status"),
+            property("StayInSync"),
+            method("Supports"),
+            method("Update"),
+            method("UpdateBatch"),
+        ],
+    },
+    ObjectType {
+        name: "ADODB.Stream",
+        members: &[
+            method("Cancel"),
+            property("Charset"),
+            method("Close"),
+            method("CopyTo"),
+            property("EOS"),
+            method("Flush"),
+            property("LineSeparator"),
+            method("LoadFromFile"),
+            property("Mode"),
+            method("Open"),
+            property("Position"),
+            method("Read"),
+            method("ReadText"),
+            method("SaveToFile"),
+            method("SetEOS"),
+            property("Size"),
+            method("SkipLine"),
+            property("State"),
+            property("Type"),
+            method("Write"),
+            method("WriteText"),
+        ],
+    },
+    ObjectType {
+        name: "CDO.Configuration",
+        members: &[collection("Fields"), method("GetInterface"), method("Load")],
+    },
+    ObjectType {
+        name: "CDO.Message",
+        members: &[
+            method("AddAttachment"),
+            method("AddRelatedBodyPart"),
+            collection("Attachments"),
+            property("AutoGenerateTextBody"),
+            property("BCC"),
+            property("BodyPart"),
+            property("CC"),
+            property("Configuration").returning("CDO.Configuration"),
+            method("CreateMHTMLBody"),
+            property("DataSource"),
+            property("DSNOptions"),
+            property("EnvelopeFields"),
+            collection("Fields"),
+            property("FollowUpTo"),
+            method("Forward"),
+            property("From"),
+            method("GetInterface"),
+            method("GetStream"),
+            property("HTMLBody"),
+            property("HTMLBodyPart"),
+            property("Keywords"),
+            property("MDNRequested"),
+            property("MimeFormatted"),
+            property("Newsgroups"),
+            property("Organization"),
+            method("Post"),
+            method("PostReply"),
+            property("ReceivedTime"),
+            method("Reply"),
+            method("ReplyAll"),
+            property("ReplyTo"),
+            method("Send"),
+            property("Sender"),
+            property("SentOn"),
+            property("Subject"),
+            property("TextBody"),
+            property("TextBodyPart"),
+            property("To"),
+        ],
+    },
+    ObjectType {
+        name: "MSXML2.DOMDocument",
+        members: &[
+            method("abort"),
+            method("appendChild"),
+            property("async"),
+            property("attributes"),
+            property("baseName"),
+            property("childNodes"),
+            method("cloneNode"),
+            method("createAttribute"),
+            method("createCDATASection"),
+            method("createComment"),
+            method("createDocumentFragment"),
+            method("createElement"),
+            method("createEntityReference"),
+            method("createNode"),
+            method("createProcessingInstruction"),
+            method("createTextNode"),
+            property("dataType"),
+            property("definition"),
+            property("doctype"),
+            property("documentElement"),
+            property("firstChild"),
+            method("getElementsByTagName"),
+            method("getProperty"),
+            method("hasChildNodes"),
+            property("implementation"),
+            method("importNode"),
+            method("insertBefore"),
+            property("lastChild"),
+            method("load"),
+            method("loadXML"),
+            property("namespaces"),
+            property("namespaceURI"),
+            property("nextSibling"),
+            method("nodeFromID"),
+            property("nodeName"),
+            property("nodeType"),
+            property("nodeTypedValue"),
+            property("nodeTypeString"),
+            property("nodeValue"),
+            property("ondataavailable"),
+            property("onreadystatechange"),
+            property("ontransformnode"),
+            property("ownerDocument"),
+            property("parentNode"),
+            property("parsed"),
+            property("parseError"),
+            property("prefix"),
+            property("preserveWhiteSpace"),
+            property("previousSibling"),
+            property("readyState"),
+            method("removeChild"),
+            method("replaceChild"),
+            property("resolveExternals"),
+            method("save"),
+            property("schemas"),
+            method("selectNodes"),
+            method("selectSingleNode"),
+            method("setProperty"),
+            property("specified"),
+            property("text"),
+            method("transformNode"),
+            method("transformNodeToObject"),
+            property("url"),
+            method("validate"),
+            property("validateOnParse"),
+            property("xml"),
+        ],
+    },
+    ObjectType {
+        name: "MSXML2.ServerXMLHTTP",
+        members: &[
+            method("abort"),
+            method("getAllResponseHeaders"),
+            method("getOption"),
+            method("getResponseHeader"),
+            property("onreadystatechange"),
+            method("open"),
+            property("readyState"),
+            property("responseBody"),
+            property("responseStream"),
+            property("responseText"),
+            property("responseXML").returning("MSXML2.DOMDocument"),
+            method("send"),
+            method("setOption"),
+            method("setProxy"),
+            method("setProxyCredentials"),
+            method("setRequestHeader"),
+            method("setTimeouts"),
+            property("status"),
+            property("statusText"),
+            method("waitForResponse"),
+        ],
+    },
+    ObjectType {
+        name: "MSXML2.XMLHTTP",
+        members: &[
+            method("abort"),
+            method("getAllResponseHeaders"),
+            method("getResponseHeader"),
+            property("onreadystatechange"),
+            method("open"),
+            property("readyState"),
+            property("responseBody"),
+            property("responseStream"),
+            property("responseText"),
+            property("responseXML").returning("MSXML2.DOMDocument"),
+            method("send"),
+            method("setRequestHeader"),
+            property("status"),
+            property("statusText"),
+        ],
+    },
+    ObjectType {
+        name: "Scripting.Dictionary",
+        members: &[
+            method("Add"),
+            property("CompareMode"),
+            property("Count"),
+            method("Exists"),
+            property("Item"),
+            method("Items"),
+            property("Key"),
+            method("Keys"),
+            method("Remove"),
+            method("RemoveAll"),
+        ],
+    },
+    ObjectType {
+        name: "Scripting.File",
+        members: &[
+            property("Attributes"),
+            method("Copy"),
+            property("DateCreated"),
+            property("DateLastAccessed"),
+            property("DateLastModified"),
+            method("Delete"),
+            property("Drive"),
+            method("Move"),
+            property("Name"),
+            method("OpenAsTextStream").returning("Scripting.TextStream"),
+            property("ParentFolder").returning("Scripting.Folder"),
+            property("Path"),
+            property("ShortName"),
+            property("ShortPath"),
+            property("Size"),
+            property("Type"),
+        ],
+    },
+    ObjectType {
+        name: "Scripting.FileSystemObject",
+        members: &[
+            method("BuildPath"),
+            method("CopyFile"),
+            method("CopyFolder"),
+            method("CreateFolder").returning("Scripting.Folder"),
+            method("CreateTextFile").returning("Scripting.TextStream"),
+            method("DeleteFile"),
+            method("DeleteFolder"),
+            method("DriveExists"),
+            collection("Drives"),
+            method("FileExists"),
+            method("FolderExists"),
+            method("GetAbsolutePathName"),
+            method("GetBaseName"),
+            method("GetDrive"),
+            method("GetDriveName"),
+            method("GetExtensionName"),
+            method("GetFile").returning("Scripting.File"),
+            method("GetFileName"),
+            method("GetFileVersion"),
+            method("GetFolder").returning("Scripting.Folder"),
+            method("GetParentFolderName"),
+            method("GetSpecialFolder").returning("Scripting.Folder"),
+            method("GetStandardStream").returning("Scripting.TextStream"),
+            method("GetTempName"),
+            method("MoveFile"),
+            method("MoveFolder"),
+            method("OpenTextFile").returning("Scripting.TextStream"),
+        ],
+    },
+    ObjectType {
+        name: "Scripting.Folder",
+        members: &[
+            property("Attributes"),
+            method("Copy"),
+            method("CreateTextFile").returning("Scripting.TextStream"),
+            property("DateCreated"),
+            property("DateLastAccessed"),
+            property("DateLastModified"),
+            method("Delete"),
+            property("Drive"),
+            collection("Files").returning("Scripting.File"),
+            property("IsRootFolder"),
+            method("Move"),
+            property("Name"),
+            property("ParentFolder").returning("Scripting.Folder"),
+            property("Path"),
+            property("ShortName"),
+            property("ShortPath"),
+            property("Size"),
+            collection("SubFolders").returning("Scripting.Folder"),
+            property("Type"),
+        ],
+    },
+    ObjectType {
+        name: "Scripting.TextStream",
+        members: &[
+            property("AtEndOfLine"),
+            property("AtEndOfStream"),
+            method("Close"),
+            property("Column"),
+            property("Line"),
+            method("Read"),
+            method("ReadAll"),
+            method("ReadLine"),
+            method("Skip"),
+            method("SkipLine"),
+            method("Write"),
+            method("WriteBlankLines"),
+            method("WriteLine"),
+        ],
+    },
+];
+
+/// ProgIDs of the objects of [`COM_OBJECTS`] pages create, with the name of their
+/// type
+///
+/// Version-dependent ProgIDs, such as `MSXML2.DOMDocument.6.0`, are found from
+/// their version-independent ProgID.
+pub const PROG_IDS: &[(&str, &str)] = &[
+    ("ADODB.Command", "ADODB.Command"),
+    ("ADODB.Connection", "ADODB.Connection"),
+    ("ADODB.Recordset", "ADODB.Recordset"),
+    ("ADODB.Stream", "ADODB.Stream"),
+    ("CDO.Configuration", "CDO.Configuration"),
+    ("CDO.Message", "CDO.Message"),
+    ("Microsoft.XMLDOM", "MSXML2.DOMDocument"),
+    ("Microsoft.XMLHTTP", "MSXML2.XMLHTTP"),
+    ("MSXML2.DOMDocument", "MSXML2.DOMDocument"),
+    ("MSXML2.FreeThreadedDOMDocument", "MSXML2.DOMDocument"),
+    ("MSXML2.ServerXMLHTTP", "MSXML2.ServerXMLHTTP"),
+    ("MSXML2.XMLHTTP", "MSXML2.XMLHTTP"),
+    ("Scripting.Dictionary", "Scripting.Dictionary"),
+    ("Scripting.FileSystemObject", "Scripting.FileSystemObject"),
+];
+
+/// Returns the object type of a name, ignoring case
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::objects;
+///
+/// assert_eq!(objects::object_type("aspERROR").unwrap().name, "ASPError");
+/// assert_eq!(objects::object_type("adodb.field").unwrap().name, "ADODB.Field");
+/// assert!(objects::object_type("Customer").is_none());
+/// ```
+pub fn object_type(name: &str) -> Option<&'static ObjectType> {
+    INTRINSIC_OBJECTS
+        .iter()
+        .chain(COM_OBJECTS)
+        .find(|object| object.name.eq_ignore_ascii_case(name))
+}
+
+/// Returns the type of the objects created from a ProgID, ignoring case and
+/// version numbers, if the model describes them
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::objects;
+///
+/// let document = objects::prog_id_type("msxml2.domdocument.6.0").unwrap();
+/// assert_eq!(document.name, "MSXML2.DOMDocument");
+/// assert_eq!(objects::prog_id_type("Microsoft.XMLDOM"), Some(document));
+/// // Objects that are only returned by other objects are not created
+/// assert!(objects::prog_id_type("Scripting.TextStream").is_none());
+/// ```
+pub fn prog_id_type(prog_id: &str) -> Option<&'static ObjectType> {
+    let mut prog_id = prog_id.trim();
+    // Version numbers, such as `.6.0` or `.3`
+    while let Some((rest, version)) = prog_id.rsplit_once('.')
+        && !version.is_empty()
+        && version.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        prog_id = rest;
+    }
+    let (_, name) = PROG_IDS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(prog_id))?;
+    object_type(name)
+}
+
+/// Types of the objects the names of a page refer to
+///
+/// Intrinsic objects are known by their name, unless a declaration of the page
+/// hides them. A variable has the type of the objects assigned to it with `Set`
+/// (`Set conn = Server.CreateObject("ADODB.Connection")`, `Set rs =
+/// conn.Execute(sql)`), `Nothing` apart, and of the items of the collections it
+/// iterates in `For Each` loops (`For Each field In rs.Fields`), if they are all of
+/// the same type known by the model. Assignments are followed in source order.
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::analysis::{self, objects::ObjectTypes};
+/// use asp_classic_parser::parser::{self, ast::{Expression, Statement}};
+///
+/// let source = "<%\nSet conn = CreateObject(\"ADODB.Connection\")\nSet rs = conn.Execute(\"SELECT 1\")\nrs.MoveNext\n%>";
+/// let program = parser::parse_to_ast(source).unwrap();
+/// let types = ObjectTypes::new(&program, &analysis::bindings(&program));
+/// let Statement::Call(call) = &program.body[2] else { unreachable!() };
+/// let Expression::Member(access) = &call.target else { unreachable!() };
+/// let object = types.expression_type(access.object.as_deref().unwrap()).unwrap();
+/// assert_eq!(object.name, "ADODB.Recordset");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ObjectTypes {
+    /// Positions of the names referring to a name predefined by VBScript or ASP
+    builtins: HashSet<usize>,
+    /// Declarations the names refer to, by position of the name, as the positions
+    /// of the declarations
+    references: HashMap<usize, usize>,
+    /// Types of the variables, by position of their declaration
+    variables: HashMap<usize, &'static ObjectType>,
+}
+
+impl ObjectTypes {
+    /// Finds the types of the variables of a page
+    ///
+    /// # Arguments
+    ///
+    /// * `program` - The syntax tree of the page
+    /// * `bindings` - The resolved names of the page
+    pub fn new(program: &Program, bindings: &Bindings) -> Self {
+        let mut types = ObjectTypes::default();
+        for reference in &bindings.references {
+            match reference.resolution {
+                Resolution::Builtin => {
+                    types.builtins.insert(reference.span.start);
+                }
+                Resolution::Declaration(id) => {
+                    let declaration = bindings.declarations[id].span.start;
+                    types.references.insert(reference.span.start, declaration);
+                }
+                Resolution::Unresolved => {}
+            }
+        }
+
+        let mut assignments = Vec::new();
+        object_assignments(&program.body, &mut assignments);
+        // Variables assigned objects of different or unknown types
+        let mut untyped = HashSet::new();
+        for (target, value) in assignments {
+            let Some(&declaration) = types.references.get(&target.span.start) else {
+                continue;
+            };
+            if untyped.contains(&declaration) {
+                continue;
+            }
+            let object = match value {
+                Assigned::Object(value) => types.expression_type(value),
+                Assigned::Item(collection) => types.item_type(collection),
+            };
+            match object {
+                Some(object)
+                    if types
+                        .variables
+                        .get(&declaration)
+                        .is_none_or(|known| known.name == object.name) =>
+                {
+                    types.variables.insert(declaration, object);
+                }
+                _ => {
+                    types.variables.remove(&declaration);
+                    untyped.insert(declaration);
+                }
+            }
+        }
+        types
+    }
+
+    /// Returns the type of the object a name refers to
+    pub fn name_type(&self, name: &Identifier) -> Option<&'static ObjectType> {
+        if self.builtins.contains(&name.span.start) {
+            return intrinsic_type(&name.name);
+        }
+        let declaration = self.references.get(&name.span.start)?;
+        self.variables.get(declaration).copied()
+    }
+
+    /// Returns the type of the items of a collection, such as `rs.Fields`
+    fn item_type(&self, collection: &Expression) -> Option<&'static ObjectType> {
+        let Expression::Member(access) = collection else {
+            return None;
+        };
+        let member = self
+            .expression_type(access.object.as_deref()?)?
+            .member(&access.member.name)?;
+        match member.kind {
+            MemberKind::Collection => object_type(member.returns?),
+            _ => None,
+        }
+    }
+
+    /// Returns the type of the objects assigned to a variable, from the position of
+    /// its declaration
+    pub fn variable_type(&self, declaration: usize) -> Option<&'static ObjectType> {
+        self.variables.get(&declaration).copied()
+    }
+
+    /// Returns the type of the object an expression refers to, when the model
+    /// describes it
+    ///
+    /// The expression is a name, a member whose value is an object
+    /// (`Server.GetLastError`), a call of a method returning an object
+    /// (`conn.Execute(sql)`), an item of a collection (`rs.Fields("id")`), an
+    /// object created from a known ProgID (`Server.CreateObject("CDO.Message")`),
+    /// or one of them in parentheses.
+    pub fn expression_type(&self, expression: &Expression) -> Option<&'static ObjectType> {
+        match expression {
+            Expression::Identifier(name) => self.name_type(name),
+            Expression::Member(access) => self
+                .expression_type(access.object.as_deref()?)?
+                .member_type(&access.member.name),
+            Expression::Call(call) => {
+                let prog_id = match call.arguments.first() {
+                    Some(Some(Expression::Literal(literal))) => match &literal.value {
+                        LiteralValue::String(prog_id) => Some(prog_id.as_str()),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match call.callee.as_ref() {
+                    Expression::Identifier(name)
+                        if name.name.eq_ignore_ascii_case("CreateObject")
+                            && self.builtins.contains(&name.span.start) =>
+                    {
+                        prog_id_type(prog_id?)
+                    }
+                    Expression::Member(access) => {
+                        let object = self.expression_type(access.object.as_deref()?)?;
+                        if object.name == "Server"
+                            && access.member.name.eq_ignore_ascii_case("CreateObject")
+                        {
+                            return prog_id_type(prog_id?);
+                        }
+                        object_type(object.member(&access.member.name)?.returns?)
+                    }
+                    _ => None,
+                }
+            }
+            Expression::Parenthesized(inner) => self.expression_type(&inner.expression),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the type of an intrinsic object, or of `Err`, from its name, ignoring
+/// case
+pub fn intrinsic_type(name: &str) -> Option<&'static ObjectType> {
+    builtins::OBJECTS
+        .iter()
+        .find(|object| object.eq_ignore_ascii_case(name))
+        .and_then(|name| object_type(name))
+}
+
+/// Object assigned to a variable
+enum Assigned<'a> {
+    /// Value of a `Set` assignment
+    Object(&'a Expression),
+    /// Item of the collection iterated by a `For Each` loop
+    Item(&'a Expression),
+}
+
+/// Lists the assignments of objects to variables, `Set x = Nothing` apart, in
+/// source order
+fn object_assignments<'a>(
+    statements: &'a [Statement],
+    assignments: &mut Vec<(&'a Identifier, Assigned<'a>)>,
+) {
+    for statement in statements {
+        match statement {
+            Statement::Assignment(assignment) => {
+                if assignment.kind == AssignmentKind::Set
+                    && let Expression::Identifier(target) = &assignment.target
+                    && !matches!(
+                        &assignment.value,
+                        Expression::Literal(literal) if literal.value == LiteralValue::Nothing
+                    )
+                {
+                    assignments.push((target, Assigned::Object(&assignment.value)));
+                }
+            }
+            Statement::ForEach(statement) => {
+                assignments.push((&statement.variable, Assigned::Item(&statement.collection)));
+                object_assignments(&statement.body, assignments);
+            }
+            Statement::Procedure(procedure) => object_assignments(&procedure.body, assignments),
+            Statement::Class(class) => object_assignments(&class.body, assignments),
+            statement => {
+                for body in blocks(statement) {
+                    object_assignments(body, assignments);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the number of insertions, deletions, substitutions and swaps of
+/// adjacent characters turning a name into another
+fn edit_distance(from: &str, to: &str) -> usize {
+    let (from, to): (Vec<char>, Vec<char>) = (from.chars().collect(), to.chars().collect());
+    // Distances from the prefixes of `from` to the prefixes of `to`, for the last
+    // two rows
+    let mut before: Vec<usize> = Vec::new();
+    let mut previous: Vec<usize> = (0..=to.len()).collect();
+    for i in 1..=from.len() {
+        let mut current = vec![i; to.len() + 1];
+        for j in 1..=to.len() {
+            let substitution = previous[j - 1] + usize::from(from[i - 1] != to[j - 1]);
+            current[j] = substitution.min(previous[j] + 1).min(current[j - 1] + 1);
+            if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut previous, current);
+    }
+    previous[to.len()]
+}
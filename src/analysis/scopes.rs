@@ -0,0 +1,678 @@
+//! Scopes and name resolution
+//!
+//! [`bindings`] links every use of a name in a page to its declaration: a `Dim`,
+//! `ReDim` or `Const` declaration, a procedure parameter, a procedure, a class or
+//! an `<object runat="server">` tag.
+//! Names are resolved as VBScript does:
+//!
+//! - names are looked up in the current procedure, then in its class (for methods),
+//!   then at the page level; case is ignored;
+//! - declarations apply to their whole scope, even before the declaring statement;
+//! - without `Option Explicit`, using an undeclared name implicitly declares a
+//!   variable in the current scope. With `Option Explicit`, such uses are left
+//!   unresolved.
+//!
+//! ```
+//! use asp_classic_parser::analysis::{self, DeclarationKind, Resolution};
+//! use asp_classic_parser::parser;
+//!
+//! let program = parser::parse_to_ast(
+//!     "<%\nOption Explicit\nDim total\nSub Add(value)\n  total = total + value\n  count = 1\nEnd Sub\n%>",
+//! )
+//! .unwrap();
+//! let bindings = analysis::bindings(&program);
+//!
+//! let total = &bindings.references[0];
+//! assert_eq!(total.name, "total");
+//! let declaration = bindings.declaration_of(total).unwrap();
+//! assert_eq!(declaration.kind, DeclarationKind::Variable);
+//! assert_eq!(declaration.span.line, 3);
+//!
+//! let undeclared: Vec<&str> = bindings.undeclared().map(|r| r.name.as_str()).collect();
+//! assert_eq!(undeclared, ["count"]);
+//! ```
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use super::builtins;
+use super::global_asa::html_objects;
+use crate::parser::ast::{
+    Expression, Identifier, Literal, LiteralValue, ProcedureKind, Program, Span, Statement,
+};
+
+/// Index of a scope in [`Bindings::scopes`]
+pub type ScopeId = usize;
+
+/// Index of a declaration in [`Bindings::declarations`]
+pub type DeclarationId = usize;
+
+/// Kinds of scopes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ScopeKind {
+    /// The page itself, including its `<script runat="server">` blocks
+    Global,
+    /// The members of a class
+    Class,
+    /// The body of a `Sub`, `Function` or `Property`
+    Procedure,
+}
+
+/// A scope in which names are declared
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Scope {
+    /// Kind of scope
+    pub kind: ScopeKind,
+    /// Name of the class or procedure; `None` for the global scope
+    pub name: Option<String>,
+    /// Enclosing scope; `None` for the global scope
+    pub parent: Option<ScopeId>,
+    /// Declaration of the class or procedure; `None` for the global scope
+    pub declaration: Option<DeclarationId>,
+    /// Location of the class or procedure, or of the whole page
+    pub span: Span,
+}
+
+/// Kinds of declarations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum DeclarationKind {
+    /// Variable declared with `Dim`, `Public`, `Private` or `ReDim`
+    Variable,
+    /// Constant declared with `Const`
+    Constant,
+    /// Procedure parameter
+    Parameter,
+    /// `Sub`, `Function` or `Property` definition
+    Procedure(ProcedureKind),
+    /// `Class` definition
+    Class,
+    /// Object declared with an `<object runat="server">` tag
+    Object,
+    /// Variable created by its first use, without `Option Explicit`
+    Implicit,
+}
+
+/// A declared name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Declaration {
+    /// The name, as written in the declaration
+    pub name: String,
+    /// Kind of declaration
+    pub kind: DeclarationKind,
+    /// Scope the name is declared in
+    pub scope: ScopeId,
+    /// Location of the name in the declaration (of the first use for implicit
+    /// declarations)
+    pub span: Span,
+}
+
+/// How a procedure is called
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CallKind {
+    /// `Call Foo(1)`
+    Call,
+    /// Call statement without `Call`: `Foo 1` or `Foo(1)`
+    Statement,
+    /// Call in an expression: `x = Foo(1)`, or `x = Foo` without parentheses
+    Expression,
+    /// Reference taken with `GetRef("Foo")`
+    GetRef,
+}
+
+/// What a name refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Resolution {
+    /// A declaration of the page
+    Declaration(DeclarationId),
+    /// A name predefined by VBScript or ASP (`Response`, `Len`, `vbCrLf`, ...)
+    Builtin,
+    /// A name declared nowhere, with `Option Explicit`
+    Unresolved,
+}
+
+/// A use of a name
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reference {
+    /// The name, as written
+    pub name: String,
+    /// Location of the name
+    pub span: Span,
+    /// Scope of the code using the name
+    pub scope: ScopeId,
+    /// What the name refers to
+    pub resolution: Resolution,
+    /// Whether the use assigns the name (`x = 1`, `For x = ...`, `ReDim x(1)`,
+    /// `Erase x`, or an element assignment such as `x(0) = 1`)
+    pub is_write: bool,
+    /// How the name is called, when it is used as a call (`Foo 1`, `Foo(1)`,
+    /// `GetRef("Foo")`); whether it is a procedure or an array depends on its
+    /// declaration
+    pub call: Option<CallKind>,
+}
+
+/// Scopes, declarations and name uses of a page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Bindings {
+    /// Whether the page has an `Option Explicit` statement
+    pub option_explicit: bool,
+    /// Scopes, the global scope first
+    pub scopes: Vec<Scope>,
+    /// Declarations, in the order they were found
+    pub declarations: Vec<Declaration>,
+    /// Uses of names, in source order within each scope
+    pub references: Vec<Reference>,
+}
+
+impl Bindings {
+    /// Returns the declaration a reference resolves to, if any
+    pub fn declaration_of(&self, reference: &Reference) -> Option<&Declaration> {
+        match reference.resolution {
+            Resolution::Declaration(id) => self.declarations.get(id),
+            _ => None,
+        }
+    }
+
+    /// Returns the uses of a declaration
+    pub fn references_to(&self, declaration: DeclarationId) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(move |reference| reference.resolution == Resolution::Declaration(declaration))
+    }
+
+    /// Returns the uses of names declared nowhere (only with `Option Explicit`)
+    pub fn undeclared(&self) -> impl Iterator<Item = &Reference> {
+        self.references
+            .iter()
+            .filter(|reference| reference.resolution == Resolution::Unresolved)
+    }
+
+    /// Returns the declarations visible at a byte offset: those of the innermost
+    /// procedure or class containing it, then those of the enclosing scopes
+    ///
+    /// Names declared in several of these scopes are returned once, from the
+    /// innermost one. This is what editors need to complete names.
+    pub fn visible_declarations(&self, offset: usize) -> Vec<&Declaration> {
+        let innermost = self
+            .scopes
+            .iter()
+            .enumerate()
+            .filter(|(_, scope)| scope.span.start <= offset && offset <= scope.span.end)
+            .min_by_key(|(_, scope)| scope.span.end - scope.span.start)
+            .map_or(0, |(id, _)| id);
+        let mut visible: Vec<&Declaration> = Vec::new();
+        let mut current = Some(innermost);
+        while let Some(scope) = current {
+            for declaration in self
+                .declarations
+                .iter()
+                .filter(|declaration| declaration.scope == scope)
+            {
+                if !visible
+                    .iter()
+                    .any(|seen| seen.name.eq_ignore_ascii_case(&declaration.name))
+                {
+                    visible.push(declaration);
+                }
+            }
+            current = self.scopes.get(scope).and_then(|scope| scope.parent);
+        }
+        visible
+    }
+
+    /// Returns the declaration of the name at a byte offset, whether the offset is
+    /// on a use of the name or on its declaration
+    ///
+    /// This is what editors need to go to the definition of a name.
+    pub fn declaration_at(&self, offset: usize) -> Option<DeclarationId> {
+        let contains = |span: &Span| span.start <= offset && offset <= span.end;
+        self.references
+            .iter()
+            .find(|reference| contains(&reference.span))
+            .and_then(|reference| match reference.resolution {
+                Resolution::Declaration(id) => Some(id),
+                _ => None,
+            })
+            .or_else(|| {
+                self.declarations
+                    .iter()
+                    .position(|declaration| contains(&declaration.span))
+            })
+    }
+}
+
+/// Resolves the names used in a page
+///
+/// # Arguments
+///
+/// * `program` - The syntax tree of the page
+///
+/// # Returns
+///
+/// * `Bindings` - The scopes and declarations of the page, and the declaration each
+///   use of a name refers to
+pub fn bindings(program: &Program) -> Bindings {
+    let mut resolver = Resolver {
+        bindings: Bindings {
+            option_explicit: has_option_explicit(&program.body),
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                name: None,
+                parent: None,
+                declaration: None,
+                span: program.span,
+            }],
+            declarations: Vec::new(),
+            references: Vec::new(),
+        },
+        names: HashMap::new(),
+    };
+    resolver.hoist(0, &program.body);
+    resolver.statements(0, &program.body);
+    resolver.bindings
+}
+
+fn has_option_explicit(statements: &[Statement]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Statement::OptionExplicit { .. } => true,
+        Statement::ServerScript(script) => has_option_explicit(&script.body),
+        _ => false,
+    })
+}
+
+struct Resolver {
+    bindings: Bindings,
+    /// Declarations by scope and lowercase name
+    names: HashMap<(ScopeId, String), DeclarationId>,
+}
+
+impl Resolver {
+    fn declare(
+        &mut self,
+        scope: ScopeId,
+        name: &Identifier,
+        kind: DeclarationKind,
+    ) -> DeclarationId {
+        let id = self.bindings.declarations.len();
+        self.bindings.declarations.push(Declaration {
+            name: name.name.clone(),
+            kind,
+            scope,
+            span: name.span,
+        });
+        // The first declaration of a name wins, as for `Property Get` and `Let`
+        self.names
+            .entry((scope, name.name.to_ascii_lowercase()))
+            .or_insert(id);
+        id
+    }
+
+    fn new_scope(
+        &mut self,
+        kind: ScopeKind,
+        name: &Identifier,
+        parent: ScopeId,
+        span: Span,
+    ) -> ScopeId {
+        // The class or procedure was declared in the enclosing scope by `hoist`
+        let declaration =
+            self.bindings.declarations.iter().position(|declaration| {
+                declaration.scope == parent && declaration.span == name.span
+            });
+        self.bindings.scopes.push(Scope {
+            kind,
+            name: Some(name.name.clone()),
+            parent: Some(parent),
+            declaration,
+            span,
+        });
+        self.bindings.scopes.len() - 1
+    }
+
+    /// Declares the names declared by statements of a scope, before resolving any
+    /// use, since declarations apply to their whole scope
+    fn hoist(&mut self, scope: ScopeId, statements: &[Statement]) {
+        for statement in statements {
+            match statement {
+                Statement::Dim(statement) => {
+                    for variable in &statement.variables {
+                        self.declare(scope, &variable.name, DeclarationKind::Variable);
+                    }
+                }
+                Statement::Const(statement) => {
+                    for constant in &statement.constants {
+                        self.declare(scope, &constant.name, DeclarationKind::Constant);
+                    }
+                }
+                Statement::Procedure(procedure) => {
+                    self.declare(
+                        scope,
+                        &procedure.name,
+                        DeclarationKind::Procedure(procedure.kind),
+                    );
+                }
+                Statement::Class(class) => {
+                    self.declare(scope, &class.name, DeclarationKind::Class);
+                }
+                Statement::Html(html) => {
+                    for object in html_objects(html) {
+                        let name = Identifier {
+                            name: object.id,
+                            span: object.id_span,
+                        };
+                        self.declare(scope, &name, DeclarationKind::Object);
+                    }
+                }
+                statement => {
+                    for body in blocks(statement) {
+                        self.hoist(scope, body);
+                    }
+                }
+            }
+        }
+    }
+
+    fn statements(&mut self, scope: ScopeId, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(scope, statement);
+        }
+    }
+
+    fn statement(&mut self, scope: ScopeId, statement: &Statement) {
+        match statement {
+            Statement::Output(output) => self.expression(scope, &output.value),
+            Statement::Dim(statement) => {
+                for variable in &statement.variables {
+                    self.bounds(scope, &variable.bounds);
+                }
+            }
+            Statement::ReDim(statement) => {
+                for variable in &statement.variables {
+                    self.bounds(scope, &variable.bounds);
+                    // `ReDim` declares the array when it is not declared yet
+                    if self.lookup(scope, &variable.name.name).is_none() {
+                        self.declare(scope, &variable.name, DeclarationKind::Variable);
+                    }
+                    self.reference(scope, &variable.name, true);
+                }
+            }
+            Statement::Const(statement) => {
+                for constant in &statement.constants {
+                    self.expression(scope, &constant.value);
+                }
+            }
+            Statement::Erase(statement) => {
+                for array in &statement.arrays {
+                    self.reference(scope, array, true);
+                }
+            }
+            Statement::Assignment(assignment) => {
+                self.target(scope, &assignment.target);
+                self.expression(scope, &assignment.value);
+            }
+            Statement::Call(call) => {
+                let kind = match call.explicit {
+                    true => CallKind::Call,
+                    false => CallKind::Statement,
+                };
+                match &call.target {
+                    Expression::Identifier(name) => self.call(scope, name, kind),
+                    Expression::Call(inner) => match inner.callee.as_ref() {
+                        Expression::Identifier(name) => {
+                            self.call(scope, name, kind);
+                            self.arguments(scope, &inner.arguments);
+                        }
+                        _ => self.expression(scope, &call.target),
+                    },
+                    target => self.expression(scope, target),
+                }
+                self.arguments(scope, &call.arguments);
+            }
+            Statement::If(statement) => {
+                self.expression(scope, &statement.condition);
+                self.statements(scope, &statement.then_branch);
+                for branch in &statement.else_if_branches {
+                    self.expression(scope, &branch.condition);
+                    self.statements(scope, &branch.body);
+                }
+                if let Some(body) = &statement.else_branch {
+                    self.statements(scope, body);
+                }
+            }
+            Statement::Select(statement) => {
+                self.expression(scope, &statement.subject);
+                for case in &statement.cases {
+                    for value in &case.values {
+                        self.expression(scope, value);
+                    }
+                    self.statements(scope, &case.body);
+                }
+                if let Some(body) = &statement.else_branch {
+                    self.statements(scope, body);
+                }
+            }
+            Statement::For(statement) => {
+                self.reference(scope, &statement.variable, true);
+                self.expression(scope, &statement.start);
+                self.expression(scope, &statement.end);
+                if let Some(step) = &statement.step {
+                    self.expression(scope, step);
+                }
+                self.statements(scope, &statement.body);
+            }
+            Statement::ForEach(statement) => {
+                self.reference(scope, &statement.variable, true);
+                self.expression(scope, &statement.collection);
+                self.statements(scope, &statement.body);
+            }
+            Statement::DoLoop(statement) => {
+                if let Some(condition) = &statement.pre_condition {
+                    self.expression(scope, &condition.condition);
+                }
+                self.statements(scope, &statement.body);
+                if let Some(condition) = &statement.post_condition {
+                    self.expression(scope, &condition.condition);
+                }
+            }
+            Statement::While(statement) => {
+                self.expression(scope, &statement.condition);
+                self.statements(scope, &statement.body);
+            }
+            Statement::With(statement) => {
+                self.expression(scope, &statement.object);
+                self.statements(scope, &statement.body);
+            }
+            Statement::ServerScript(script) => self.statements(scope, &script.body),
+            Statement::Procedure(procedure) => {
+                let inner =
+                    self.new_scope(ScopeKind::Procedure, &procedure.name, scope, procedure.span);
+                for parameter in &procedure.parameters {
+                    self.declare(inner, &parameter.name, DeclarationKind::Parameter);
+                }
+                self.hoist(inner, &procedure.body);
+                self.statements(inner, &procedure.body);
+            }
+            Statement::Class(class) => {
+                let inner = self.new_scope(ScopeKind::Class, &class.name, scope, class.span);
+                self.hoist(inner, &class.body);
+                self.statements(inner, &class.body);
+            }
+            Statement::Randomize(statement) => {
+                if let Some(seed) = &statement.seed {
+                    self.expression(scope, seed);
+                }
+            }
+            Statement::Html(_)
+            | Statement::Directive(_)
+            | Statement::OptionExplicit { .. }
+            | Statement::Exit(_)
+            | Statement::OnError(_)
+            | Statement::Stop { .. }
+            | Statement::Unparsed(_) => {}
+        }
+    }
+
+    /// Resolves the names of an assigned expression
+    fn target(&mut self, scope: ScopeId, target: &Expression) {
+        match target {
+            Expression::Identifier(name) => self.reference(scope, name, true),
+            // Assigning an array element: `items(0) = 1`
+            Expression::Call(call) => {
+                match call.callee.as_ref() {
+                    Expression::Identifier(name) => self.reference(scope, name, true),
+                    callee => self.expression(scope, callee),
+                }
+                self.arguments(scope, &call.arguments);
+            }
+            target => self.expression(scope, target),
+        }
+    }
+
+    fn expression(&mut self, scope: ScopeId, expression: &Expression) {
+        match expression {
+            Expression::Identifier(name) => self.reference(scope, name, false),
+            Expression::Member(member) => {
+                // Member names are resolved at run time, on the object
+                if let Some(object) = &member.object {
+                    self.expression(scope, object);
+                }
+            }
+            Expression::Call(call) => {
+                match call.callee.as_ref() {
+                    Expression::Identifier(name) => {
+                        self.call(scope, name, CallKind::Expression);
+                        self.get_ref(scope, name, &call.arguments);
+                    }
+                    callee => self.expression(scope, callee),
+                }
+                self.arguments(scope, &call.arguments);
+            }
+            Expression::Unary(unary) => self.expression(scope, &unary.operand),
+            Expression::Binary(binary) => {
+                self.expression(scope, &binary.left);
+                self.expression(scope, &binary.right);
+            }
+            Expression::Parenthesized(inner) => self.expression(scope, &inner.expression),
+            Expression::New(new) => self.reference(scope, &new.class_name, false),
+            Expression::Literal(_) | Expression::Unparsed(_) => {}
+        }
+    }
+
+    fn arguments(&mut self, scope: ScopeId, arguments: &[Option<Expression>]) {
+        for argument in arguments.iter().flatten() {
+            self.expression(scope, argument);
+        }
+    }
+
+    fn bounds(&mut self, scope: ScopeId, bounds: &Option<Vec<Expression>>) {
+        for bound in bounds.iter().flatten() {
+            self.expression(scope, bound);
+        }
+    }
+
+    /// Looks up a name in a scope and its enclosing scopes
+    fn lookup(&self, scope: ScopeId, name: &str) -> Option<DeclarationId> {
+        let name = name.to_ascii_lowercase();
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            if let Some(&declaration) = self.names.get(&(id, name.clone())) {
+                return Some(declaration);
+            }
+            current = self.bindings.scopes[id].parent;
+        }
+        None
+    }
+
+    fn reference(&mut self, scope: ScopeId, name: &Identifier, is_write: bool) {
+        self.add_reference(scope, name, is_write, None);
+    }
+
+    fn call(&mut self, scope: ScopeId, name: &Identifier, kind: CallKind) {
+        self.add_reference(scope, name, false, Some(kind));
+    }
+
+    /// Resolves the procedure named by `GetRef("Name")`
+    fn get_ref(&mut self, scope: ScopeId, callee: &Identifier, arguments: &[Option<Expression>]) {
+        if !callee.name.eq_ignore_ascii_case("GetRef") {
+            return;
+        }
+        if let Some(Some(Expression::Literal(Literal {
+            value: LiteralValue::String(name),
+            span,
+        }))) = arguments.first()
+        {
+            // The name is inside the quotes of the literal
+            let name = Identifier {
+                name: name.clone(),
+                span: Span::new(span.start + 1, span.end - 1, span.line, span.column + 1),
+            };
+            self.call(scope, &name, CallKind::GetRef);
+        }
+    }
+
+    fn add_reference(
+        &mut self,
+        scope: ScopeId,
+        name: &Identifier,
+        is_write: bool,
+        call: Option<CallKind>,
+    ) {
+        let resolution = match self.lookup(scope, &name.name) {
+            Some(declaration) => Resolution::Declaration(declaration),
+            None if builtins::is_builtin(&name.name) || self.is_me(scope, &name.name) => {
+                Resolution::Builtin
+            }
+            None if self.bindings.option_explicit => Resolution::Unresolved,
+            None => Resolution::Declaration(self.declare(scope, name, DeclarationKind::Implicit)),
+        };
+        self.bindings.references.push(Reference {
+            name: name.name.clone(),
+            span: name.span,
+            scope,
+            resolution,
+            is_write,
+            call,
+        });
+    }
+
+    /// Whether a name is `Me`, used inside a class
+    fn is_me(&self, scope: ScopeId, name: &str) -> bool {
+        let mut current = Some(scope);
+        while let Some(id) = current {
+            if self.bindings.scopes[id].kind == ScopeKind::Class {
+                return name.eq_ignore_ascii_case("me");
+            }
+            current = self.bindings.scopes[id].parent;
+        }
+        false
+    }
+}
+
+/// Returns the statement lists nested in a block statement, whose declarations
+/// belong to the enclosing scope
+pub(crate) fn blocks(statement: &Statement) -> Vec<&[Statement]> {
+    match statement {
+        Statement::ServerScript(script) => vec![&script.body],
+        Statement::If(statement) => {
+            let mut blocks: Vec<&[Statement]> = vec![&statement.then_branch];
+            blocks.extend(statement.else_if_branches.iter().map(|b| b.body.as_slice()));
+            blocks.extend(statement.else_branch.as_deref());
+            blocks
+        }
+        Statement::Select(statement) => {
+            let mut blocks: Vec<&[Statement]> = statement
+                .cases
+                .iter()
+                .map(|case| case.body.as_slice())
+                .collect();
+            blocks.extend(statement.else_branch.as_deref());
+            blocks
+        }
+        Statement::For(statement) => vec![&statement.body],
+        Statement::ForEach(statement) => vec![&statement.body],
+        Statement::DoLoop(statement) => vec![&statement.body],
+        Statement::While(statement) => vec![&statement.body],
+        Statement::With(statement) => vec![&statement.body],
+        _ => Vec::new(),
+    }
+}
@@ -0,0 +1,314 @@
+//! Symbol table of a page
+//!
+//! ```
+//! use asp_classic_parser::analysis::{self, SymbolKind};
+//! use asp_classic_parser::parser;
+//!
+//! let program = parser::parse_to_ast(
+//!     "<%\nConst MAX = 10\nDim total\nClass Cart\n  Public Items\n  Function Count()\n  End Function\nEnd Class\n%>",
+//! )
+//! .unwrap();
+//! let table = analysis::symbols(&program);
+//!
+//! let names: Vec<&str> = table.symbols.iter().map(|s| s.name.as_str()).collect();
+//! assert_eq!(names, ["MAX", "total", "Cart"]);
+//! let cart = table.get("cart").unwrap();
+//! assert_eq!(cart.kind, SymbolKind::Class);
+//! assert_eq!(cart.members[1].kind, SymbolKind::Function);
+//! assert_eq!(cart.members[1].signature(), "Function Count()");
+//! ```
+
+use serde::Serialize;
+
+use super::global_asa::html_objects;
+use super::scopes::blocks;
+use crate::parser::ast::{ClassDeclaration, ProcedureKind, Program, Span, Statement, Visibility};
+
+/// Kinds of declared symbols
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum SymbolKind {
+    /// `Class` definition
+    Class,
+    /// `Sub` definition
+    Sub,
+    /// `Function` definition
+    Function,
+    /// `Property Get` definition
+    PropertyGet,
+    /// `Property Let` definition
+    PropertyLet,
+    /// `Property Set` definition
+    PropertySet,
+    /// Constant declared with `Const`
+    Constant,
+    /// Variable declared with `Dim`, `Public` or `Private`
+    Variable,
+    /// Object declared with an `<object runat="server">` tag
+    Object,
+}
+
+impl SymbolKind {
+    /// Returns true for subs, functions and properties
+    pub fn is_procedure(&self) -> bool {
+        matches!(
+            self,
+            SymbolKind::Sub
+                | SymbolKind::Function
+                | SymbolKind::PropertyGet
+                | SymbolKind::PropertyLet
+                | SymbolKind::PropertySet
+        )
+    }
+}
+
+impl From<ProcedureKind> for SymbolKind {
+    fn from(kind: ProcedureKind) -> Self {
+        match kind {
+            ProcedureKind::Sub => SymbolKind::Sub,
+            ProcedureKind::Function => SymbolKind::Function,
+            ProcedureKind::PropertyGet => SymbolKind::PropertyGet,
+            ProcedureKind::PropertyLet => SymbolKind::PropertyLet,
+            ProcedureKind::PropertySet => SymbolKind::PropertySet,
+        }
+    }
+}
+
+/// A declared symbol
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Symbol {
+    /// Name of the symbol, as written in its declaration
+    pub name: String,
+    /// Kind of symbol
+    pub kind: SymbolKind,
+    /// Visibility of the symbol; declarations without `Public` or `Private` are
+    /// public, as in VBScript
+    pub visibility: Visibility,
+    /// Names of the parameters of a procedure (empty for other symbols)
+    pub parameters: Vec<String>,
+    /// Location of the name in the declaration
+    pub name_span: Span,
+    /// Location of the whole declaration (the whole block for procedures and
+    /// classes, the declarator for variables and constants)
+    pub span: Span,
+    /// Members of a class, in source order (empty for other symbols)
+    pub members: Vec<Symbol>,
+}
+
+impl Symbol {
+    /// Returns the declaration of the symbol without its body, such as
+    /// `Function Add(a, b)` or `Const MAX`
+    pub fn signature(&self) -> String {
+        let keyword = match self.kind {
+            SymbolKind::Class => "Class",
+            SymbolKind::Sub => "Sub",
+            SymbolKind::Function => "Function",
+            SymbolKind::PropertyGet => "Property Get",
+            SymbolKind::PropertyLet => "Property Let",
+            SymbolKind::PropertySet => "Property Set",
+            SymbolKind::Constant => "Const",
+            SymbolKind::Variable => "Dim",
+            SymbolKind::Object => "Object",
+        };
+        if self.kind.is_procedure() {
+            format!("{} {}({})", keyword, self.name, self.parameters.join(", "))
+        } else {
+            format!("{} {}", keyword, self.name)
+        }
+    }
+
+    /// Returns the member of a class with the given name, ignoring case
+    ///
+    /// Properties may have several members with the same name (`Property Get` and
+    /// `Property Let`); the first one is returned.
+    pub fn member(&self, name: &str) -> Option<&Symbol> {
+        self.members
+            .iter()
+            .find(|member| member.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Symbols declared at the top level of a page
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct SymbolTable {
+    /// Top-level symbols, in source order; class members are nested in their class
+    pub symbols: Vec<Symbol>,
+}
+
+impl SymbolTable {
+    /// Returns the top-level symbol with the given name, ignoring case as VBScript
+    /// does
+    pub fn get(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Returns every symbol, class members included, in source order
+    pub fn iter(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols
+            .iter()
+            .flat_map(|symbol| std::iter::once(symbol).chain(symbol.members.iter()))
+    }
+}
+
+/// Lists the symbols declared in a page
+///
+/// Symbols are the procedures, classes, constants and variables declared at the
+/// top level of the page, including in `<script runat="server">` blocks and in
+/// blocks such as `If`, and the objects of `<object runat="server">` tags.
+/// Variables and constants local to a procedure are not listed.
+///
+/// # Arguments
+///
+/// * `program` - The syntax tree of the page
+///
+/// # Returns
+///
+/// * `SymbolTable` - The declared symbols
+pub fn symbols(program: &Program) -> SymbolTable {
+    let mut symbols = Vec::new();
+    collect(&program.body, &mut symbols);
+    SymbolTable { symbols }
+}
+
+/// Returns the comment block written just above a declaration
+///
+/// The block is made of the `'` or `Rem` comment lines directly preceding the
+/// line of the declaration, without blank lines in between. Comment markers
+/// and the space following them are removed.
+///
+/// ```
+/// use asp_classic_parser::{analysis, parser};
+///
+/// let source = "<%\n' Adds two numbers\n' and returns the sum\nFunction Add(a, b)\nEnd Function\n%>";
+/// let table = analysis::symbols(&parser::parse_to_ast(source).unwrap());
+/// let add = table.get("Add").unwrap();
+/// assert_eq!(
+///     analysis::doc_comment(source, add.span).as_deref(),
+///     Some("Adds two numbers\nand returns the sum")
+/// );
+/// ```
+///
+/// # Arguments
+///
+/// * `source` - The source text of the page
+/// * `span` - The location of the declaration
+///
+/// # Returns
+///
+/// * `Option<String>` - The text of the comments, or `None` if the declaration
+///   has no comment above it or only empty ones
+pub fn doc_comment(source: &str, span: Span) -> Option<String> {
+    let line_start = source[..span.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    if !source[line_start..span.start].trim().is_empty() {
+        return None;
+    }
+    let mut comments = Vec::new();
+    for line in source[..line_start].lines().rev() {
+        let line = line.trim();
+        let text = match line.strip_prefix('\'') {
+            Some(text) => text.trim_start_matches('\''),
+            None if line
+                .get(..3)
+                .is_some_and(|rem| rem.eq_ignore_ascii_case("rem"))
+                && line[3..].chars().next().is_none_or(char::is_whitespace) =>
+            {
+                &line[3..]
+            }
+            None => break,
+        };
+        comments.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+    }
+    comments.reverse();
+    let comment = comments.join("\n");
+    let comment = comment.trim_matches('\n');
+    (!comment.is_empty()).then(|| comment.to_string())
+}
+
+/// Collects the symbols declared by module-level statements
+fn collect(statements: &[Statement], symbols: &mut Vec<Symbol>) {
+    for statement in statements {
+        match statement {
+            Statement::Class(class) => symbols.push(class_symbol(class)),
+            Statement::Procedure(_) | Statement::Dim(_) | Statement::Const(_) => {
+                declarations(statement, symbols)
+            }
+            Statement::Html(html) => {
+                symbols.extend(html_objects(html).into_iter().map(|object| Symbol {
+                    name: object.id,
+                    kind: SymbolKind::Object,
+                    visibility: Visibility::Public,
+                    parameters: Vec::new(),
+                    name_span: object.id_span,
+                    span: object.span,
+                    members: Vec::new(),
+                }))
+            }
+            statement => {
+                for body in blocks(statement) {
+                    collect(body, symbols);
+                }
+            }
+        }
+    }
+}
+
+fn class_symbol(class: &ClassDeclaration) -> Symbol {
+    let mut members = Vec::new();
+    for statement in &class.body {
+        declarations(statement, &mut members);
+    }
+    Symbol {
+        name: class.name.name.clone(),
+        kind: SymbolKind::Class,
+        visibility: Visibility::Public,
+        parameters: Vec::new(),
+        name_span: class.name.span,
+        span: class.span,
+        members,
+    }
+}
+
+/// Collects the symbols declared by a procedure, variable or constant declaration
+fn declarations(statement: &Statement, symbols: &mut Vec<Symbol>) {
+    match statement {
+        Statement::Procedure(procedure) => symbols.push(Symbol {
+            name: procedure.name.name.clone(),
+            kind: procedure.kind.into(),
+            visibility: procedure.visibility.unwrap_or(Visibility::Public),
+            parameters: procedure
+                .parameters
+                .iter()
+                .map(|parameter| parameter.name.name.clone())
+                .collect(),
+            name_span: procedure.name.span,
+            span: procedure.span,
+            members: Vec::new(),
+        }),
+        Statement::Dim(statement) => {
+            symbols.extend(statement.variables.iter().map(|variable| Symbol {
+                name: variable.name.name.clone(),
+                kind: SymbolKind::Variable,
+                visibility: statement.visibility.unwrap_or(Visibility::Public),
+                parameters: Vec::new(),
+                name_span: variable.name.span,
+                span: variable.span,
+                members: Vec::new(),
+            }))
+        }
+        Statement::Const(statement) => {
+            symbols.extend(statement.constants.iter().map(|constant| Symbol {
+                name: constant.name.name.clone(),
+                kind: SymbolKind::Constant,
+                visibility: statement.visibility.unwrap_or(Visibility::Public),
+                parameters: Vec::new(),
+                name_span: constant.name.span,
+                span: constant.span,
+                members: Vec::new(),
+            }))
+        }
+        _ => {}
+    }
+}
@@ -4,15 +4,64 @@
 //! This module implements a cache for parsed files based on their content hash and parsing options.
 //! It helps accelerate repeated runs by avoiding re-parsing files that haven't changed.
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::LazyLock;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 
+#[cfg(not(target_arch = "wasm32"))]
+use dashmap::DashMap;
+
+use crate::parser::Diagnostic;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::remote_cache::RemoteCache;
+
+/// Name of the cache file in its directory
+pub const CACHE_FILE_NAME: &str = "parse_cache.bin";
+
+/// Name of the JSON cache file of the previous versions, migrated to the binary
+/// format when found instead of the cache file
+const LEGACY_CACHE_FILE_NAME: &str = "parse_cache.json";
+
+/// Bytes starting a cache file, followed by its format as a little-endian `u32`
+/// and by the cache encoded with bincode
+const CACHE_MAGIC: &[u8; 8] = b"ASPCACHE";
+
+/// Directory of the cache of a project, in its root directory
+pub const PROJECT_CACHE_DIRECTORY: &str = ".asp-parser-cache";
+
+/// Format of the cache file, increased when its entries change: caches of other
+/// formats are ignored
+const CACHE_FORMAT: u32 = 1;
+
+/// Grammars of the parser, whose changes invalidate the cached results even
+/// without a new version
+const GRAMMARS: [&str; 3] = [
+    include_str!("parser/grammar.pest"),
+    include_str!("parser/jscript.pest"),
+    include_str!("parser/markup.pest"),
+];
+
+/// Hash of the crate version and of the grammars of the parser
+static PARSER_FINGERPRINT: LazyLock<String> = LazyLock::new(|| {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    for grammar in GRAMMARS {
+        hasher.update([0]);
+        hasher.update(grammar.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+});
+
 /// Cache-related errors
 #[derive(Error, Debug)]
 pub enum CacheError {
@@ -22,6 +71,12 @@ pub enum CacheError {
     #[error("Serialization/deserialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("Binary encoding error: {0}")]
+    EncodingError(#[from] bincode::Error),
+
+    #[error("Remote cache error: {0}")]
+    RemoteError(String),
+
     #[error("Invalid cache entry")]
     #[allow(dead_code)]
     InvalidEntry,
@@ -30,6 +85,58 @@ pub enum CacheError {
 /// Result type for cache operations
 pub type CacheResult<T> = Result<T, CacheError>;
 
+/// Kind of store of the remote cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RemoteBackend {
+    /// An HTTP server storing the bodies of `PUT` requests and returning them to
+    /// `GET` requests, such as a WebDAV share or a build cache server
+    #[default]
+    Http,
+    /// An S3-compatible blob store, whose requests are signed with the AWS
+    /// credentials of the environment
+    S3,
+}
+
+impl FromStr for RemoteBackend {
+    type Err = String;
+
+    /// Parses `http` or `s3`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_lowercase().as_str() {
+            "http" => Ok(RemoteBackend::Http),
+            "s3" => Ok(RemoteBackend::S3),
+            _ => Err(format!(
+                "unknown remote backend '{}', expected http or s3",
+                value
+            )),
+        }
+    }
+}
+
+/// Encode a value in the binary format of the cache files: the magic bytes, the
+/// format of the cache and the value encoded with bincode
+pub(crate) fn encode_versioned<T: Serialize>(value: &T) -> CacheResult<Vec<u8>> {
+    let mut bytes = CACHE_MAGIC.to_vec();
+    bytes.extend_from_slice(&CACHE_FORMAT.to_le_bytes());
+    bincode::serialize_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+/// Decode a value encoded by [`encode_versioned`], `None` if it is not in the
+/// current format
+pub(crate) fn decode_versioned<T: DeserializeOwned>(bytes: &[u8]) -> CacheResult<Option<T>> {
+    let Some((format, encoded)) = bytes
+        .strip_prefix(CACHE_MAGIC)
+        .and_then(|bytes| bytes.split_first_chunk::<4>())
+    else {
+        return Ok(None);
+    };
+    if u32::from_le_bytes(*format) != CACHE_FORMAT {
+        return Ok(None);
+    }
+    Ok(Some(bincode::deserialize(encoded)?))
+}
+
 /// Represents a hashed file entry in the cache
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CacheEntry {
@@ -48,8 +155,40 @@ pub struct CacheEntry {
     /// Options hash used for parsing (to invalidate when options change)
     pub options_hash: String,
 
-    /// Error message if parsing failed
-    pub error_message: Option<String>,
+    /// Problems reported when the file was parsed: its syntax errors, or the
+    /// warning of a file skipped for having no ASP code or being empty
+    #[serde(default)]
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CacheEntry {
+    /// Create the entry of a file, hashing its current content
+    pub(crate) fn new(
+        path: &Path,
+        success: bool,
+        options_hash: &str,
+        diagnostics: Vec<Diagnostic>,
+    ) -> CacheResult<Self> {
+        Ok(Self {
+            file_path: path.to_path_buf(),
+            content_hash: Cache::hash_file(path)?,
+            timestamp: SystemTime::now(),
+            success,
+            options_hash: options_hash.to_string(),
+            diagnostics,
+        })
+    }
+
+    /// Check if the entry is not older than a maximum age and was made with the
+    /// same options, whatever the current content of its file
+    fn is_current(&self, max_age: Duration, options_hash: &str) -> bool {
+        if let Ok(age) = self.timestamp.elapsed()
+            && age > max_age
+        {
+            return false;
+        }
+        self.options_hash == options_hash
+    }
 }
 
 /// Cache for parsed files
@@ -61,11 +200,58 @@ pub struct Cache {
     /// When the cache was last modified
     last_modified: SystemTime,
 
-    /// Version of the cache format
+    /// Version of the parser that wrote the cache
     version: String,
 
+    /// Format of the cache file, `0` for the caches written before it was recorded
+    #[serde(default)]
+    format: u32,
+
     /// Maximum age of cache entries before automatic invalidation (in seconds)
     max_age_secs: u64,
+
+    /// Number of lookups that found a valid entry since the cache was created
+    #[serde(default)]
+    hits: u64,
+
+    /// Number of lookups that found no valid entry since the cache was created
+    #[serde(default)]
+    misses: u64,
+
+    /// File the cache is saved to, the default cache path when not set
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    /// Remote cache sharing the results with other machines
+    #[cfg(not(target_arch = "wasm32"))]
+    #[serde(skip)]
+    remote: Option<RemoteCache>,
+}
+
+/// Statistics of a cache
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CacheStats {
+    /// Number of entries
+    pub entries: usize,
+    /// Number of entries of files that were successfully parsed
+    pub successful: usize,
+    /// Number of entries of files that failed to parse
+    pub failed: usize,
+    /// Number of entries older than the maximum age, ignored until pruned
+    pub expired: usize,
+    /// Number of lookups that found a valid entry
+    pub hits: u64,
+    /// Number of lookups that found no valid entry
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Returns the share of lookups that found a valid entry, from 0 to 1, or
+    /// `None` without lookups
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
 }
 
 impl Default for Cache {
@@ -81,31 +267,59 @@ impl Cache {
             entries: HashMap::new(),
             last_modified: SystemTime::now(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            format: CACHE_FORMAT,
             max_age_secs: 86400, // 24 hours default
+            hits: 0,
+            misses: 0,
+            path: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            remote: None,
         }
     }
 
+    /// Get the fingerprint of the parser, a hash of the crate version and of its
+    /// grammars
+    pub fn parser_fingerprint() -> &'static str {
+        &PARSER_FINGERPRINT
+    }
+
     /// Compute a hash for CLI options to detect when parsing options change
+    ///
+    /// The fingerprint of the parser is part of the hash, so that the entries
+    /// made by another version of the parser or with other grammars are invalid.
     pub fn hash_options(options: &[String]) -> String {
         let options_str = options.join(",");
         let mut hasher = Sha256::new();
+        hasher.update(Self::parser_fingerprint().as_bytes());
         hasher.update(options_str.as_bytes());
         format!("{:x}", hasher.finalize())
     }
 
     /// Compute a hash of a file's contents
+    ///
+    /// The file is read in chunks, so hashing a large file does not load it
+    /// in memory.
     pub fn hash_file(path: &Path) -> CacheResult<String> {
-        let content = fs::read(path)?;
+        let mut reader = io::BufReader::new(fs::File::open(path)?);
         let mut hasher = Sha256::new();
-        hasher.update(&content);
+        io::copy(&mut reader, &mut hasher)?;
         Ok(format!("{:x}", hasher.finalize()))
     }
 
     /// Get the path to the cache file
     pub fn get_cache_path() -> PathBuf {
+        Self::get_cache_path_in(None)
+    }
+
+    /// Get the path to the cache file, in a directory unless the
+    /// `ASP_PARSER_CACHE_DIR` environment variable sets another one
+    pub fn get_cache_path_in(directory: Option<&Path>) -> PathBuf {
         // Check for environment variable override first
         if let Ok(cache_dir_override) = std::env::var("ASP_PARSER_CACHE_DIR") {
-            return PathBuf::from(cache_dir_override).join("parse_cache.json");
+            return PathBuf::from(cache_dir_override).join(CACHE_FILE_NAME);
+        }
+        if let Some(directory) = directory {
+            return directory.join(CACHE_FILE_NAME);
         }
 
         let cache_dir = dirs::cache_dir().unwrap_or_else(|| PathBuf::from("./.cache"));
@@ -118,107 +332,198 @@ impl Cache {
             let _ = fs::create_dir_all(&app_cache_dir);
         }
 
-        app_cache_dir.join("parse_cache.json")
+        app_cache_dir.join(CACHE_FILE_NAME)
     }
 
     /// Load the cache from disk
     pub fn load() -> Self {
-        let cache_path = Self::get_cache_path();
+        Self::load_from(&Self::get_cache_path())
+    }
 
-        if !cache_path.exists() {
-            return Self::new();
-        }
+    /// Load the cache from a file, which it is then saved to
+    pub fn load_from(cache_path: &Path) -> Self {
+        let mut cache = Self::read(cache_path);
+        cache.path = Some(cache_path.to_path_buf());
+        cache
+    }
 
-        match fs::read_to_string(&cache_path) {
-            Ok(content) => match serde_json::from_str(&content) {
-                Ok(cache) => cache,
-                Err(e) => {
-                    eprintln!("Warning: Failed to parse cache file: {}", e);
-                    Self::new()
-                }
-            },
+    /// Read the cache of a file, empty if the file does not exist, is invalid or
+    /// of another format
+    ///
+    /// Without the file, the JSON cache of the previous versions in the same
+    /// directory is read, to be saved in the binary format.
+    fn read(cache_path: &Path) -> Self {
+        let bytes = match fs::read(cache_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Self::read_legacy(&cache_path.with_file_name(LEGACY_CACHE_FILE_NAME));
+            }
             Err(e) => {
                 eprintln!("Warning: Failed to read cache file: {}", e);
+                return Self::new();
+            }
+        };
+
+        match Self::decode(&bytes) {
+            Ok(Some(cache)) => cache,
+            Ok(None) => Self::new(),
+            Err(e) => {
+                eprintln!("Warning: Failed to parse cache file: {}", e);
                 Self::new()
             }
         }
     }
 
-    /// Save the cache to disk
+    /// Read a JSON cache file of the previous versions, empty if it does not
+    /// exist, is invalid or of another format
+    fn read_legacy(json_path: &Path) -> Self {
+        let Ok(content) = fs::read_to_string(json_path) else {
+            return Self::new();
+        };
+        match serde_json::from_str::<Self>(&content) {
+            Ok(cache) if cache.format == CACHE_FORMAT => cache,
+            _ => Self::new(),
+        }
+    }
+
+    /// Encode the cache in the binary format of the cache files
+    fn encode(&self) -> CacheResult<Vec<u8>> {
+        encode_versioned(self)
+    }
+
+    /// Decode a cache file, `None` if it is not in the current format
+    fn decode(bytes: &[u8]) -> CacheResult<Option<Self>> {
+        decode_versioned(bytes)
+    }
+
+    /// Share the results of the cache with a remote cache: the files missing from
+    /// the cache are looked up in it, and the results of the files parsed are
+    /// stored in it
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set_remote(&mut self, remote: RemoteCache) {
+        self.remote = Some(remote);
+    }
+
+    /// Get the error that disabled the remote cache during the run, if any
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn remote_error(&self) -> Option<String> {
+        self.remote.as_ref().and_then(RemoteCache::error)
+    }
+
+    /// Save the cache to disk, removing the JSON cache it may have been
+    /// migrated from
     pub fn save(&self) -> CacheResult<()> {
-        let cache_path = Self::get_cache_path();
+        let cache_path = self.path.clone().unwrap_or_else(Self::get_cache_path);
         let cache_dir = cache_path.parent().unwrap();
 
         if !cache_dir.exists() {
             fs::create_dir_all(cache_dir)?;
         }
 
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(cache_path, json)?;
+        fs::write(&cache_path, self.encode()?)?;
+        Self::clear(&cache_path.with_file_name(LEGACY_CACHE_FILE_NAME))?;
         Ok(())
     }
 
-    /// Check if a file is in the cache and hasn't changed
-    pub fn is_valid(&self, path: &Path, options_hash: &str) -> CacheResult<bool> {
-        let path_str = path.to_string_lossy().to_string();
-
-        if let Some(entry) = self.entries.get(&path_str) {
-            // Check if the entry is too old
-            if let Ok(age) = entry.timestamp.elapsed() {
-                if age > Duration::from_secs(self.max_age_secs) {
-                    return Ok(false);
-                }
+    /// Delete a cache file from disk, and the JSON cache of the previous versions
+    /// next to it
+    ///
+    /// # Returns
+    ///
+    /// * `CacheResult<bool>` - Whether there was a cache file to delete
+    pub fn clear(cache_path: &Path) -> CacheResult<bool> {
+        let mut deleted = false;
+        for path in [
+            cache_path.to_path_buf(),
+            cache_path.with_file_name(LEGACY_CACHE_FILE_NAME),
+        ] {
+            match fs::remove_file(path) {
+                Ok(()) => deleted = true,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
             }
+        }
+        Ok(deleted)
+    }
 
-            // Check if options have changed
-            if entry.options_hash != options_hash {
-                return Ok(false);
-            }
+    /// Check if a file is in the cache and hasn't changed, counting the lookup
+    /// as a hit or a miss
+    pub fn is_valid(&mut self, path: &Path, options_hash: &str) -> CacheResult<bool> {
+        let valid = match self.is_entry_valid(path, options_hash) {
+            #[cfg(not(target_arch = "wasm32"))]
+            Ok(false) => self.fetch_remote(path, options_hash),
+            valid => valid,
+        };
+        if matches!(valid, Ok(true)) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        valid
+    }
+
+    /// Look up a file missing from the cache in the remote cache, adding the
+    /// entry found to the cache
+    #[cfg(not(target_arch = "wasm32"))]
+    fn fetch_remote(&mut self, path: &Path, options_hash: &str) -> CacheResult<bool> {
+        let Some(remote) = &self.remote else {
+            return Ok(false);
+        };
+        let Some(entry) = remote.fetch(path, options_hash)? else {
+            return Ok(false);
+        };
+        self.entries
+            .insert(path.to_string_lossy().to_string(), entry);
+        self.last_modified = SystemTime::now();
+        Ok(true)
+    }
+
+    /// Check if a file is in the cache and hasn't changed
+    fn is_entry_valid(&self, path: &Path, options_hash: &str) -> CacheResult<bool> {
+        let path_str = path.to_string_lossy().to_string();
+        let max_age = Duration::from_secs(self.max_age_secs);
 
+        match self.entries.get(&path_str) {
             // Check if file content has changed
-            let current_hash = Self::hash_file(path)?;
-            Ok(current_hash == entry.content_hash)
-        } else {
-            Ok(false)
+            Some(entry) if entry.is_current(max_age, options_hash) => {
+                Ok(Self::hash_file(path)? == entry.content_hash)
+            }
+            _ => Ok(false),
         }
     }
 
     /// Add or update a file in the cache
     pub fn update(&mut self, path: &Path, success: bool, options_hash: &str) -> CacheResult<()> {
-        self.update_with_error(path, success, options_hash, None)
+        self.update_with_diagnostics(path, success, options_hash, Vec::new())
     }
 
-    /// Add or update a file in the cache with error information
-    pub fn update_with_error(
+    /// Add or update a file in the cache with the problems reported when it was
+    /// parsed
+    pub fn update_with_diagnostics(
         &mut self,
         path: &Path,
         success: bool,
         options_hash: &str,
-        error_message: Option<String>,
+        diagnostics: Vec<Diagnostic>,
     ) -> CacheResult<()> {
         let path_str = path.to_string_lossy().to_string();
-        let content_hash = Self::hash_file(path)?;
-
-        let entry = CacheEntry {
-            file_path: path.to_path_buf(),
-            content_hash,
-            timestamp: SystemTime::now(),
-            success,
-            options_hash: options_hash.to_string(),
-            error_message,
-        };
+        let entry = CacheEntry::new(path, success, options_hash, diagnostics)?;
 
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(remote) = &self.remote {
+            remote.store(&entry);
+        }
         self.entries.insert(path_str, entry);
         self.last_modified = SystemTime::now();
         Ok(())
     }
 
-    /// Get the error message for a file if it exists
-    pub fn get_error_message(&self, path: &Path) -> Option<String> {
+    /// Get the problems reported when a file was parsed, if it is in the cache
+    pub fn get_diagnostics(&self, path: &Path) -> Option<&[Diagnostic]> {
         let path_str = path.to_string_lossy().to_string();
         self.entries
             .get(&path_str)
-            .and_then(|entry| entry.error_message.clone())
+            .map(|entry| entry.diagnostics.as_slice())
     }
 
     /// Check if a file was successfully parsed according to the cache
@@ -242,8 +547,16 @@ impl Cache {
 
     /// Clean old entries from the cache
     pub fn clean_old_entries(&mut self) -> usize {
+        self.clean_entries_older_than(Duration::from_secs(self.max_age_secs))
+    }
+
+    /// Clean the entries older than a given age from the cache
+    ///
+    /// # Returns
+    ///
+    /// * `usize` - The number of entries removed
+    pub fn clean_entries_older_than(&mut self, max_age: Duration) -> usize {
         let now = SystemTime::now();
-        let max_age = Duration::from_secs(self.max_age_secs);
 
         let old_entries: Vec<String> = self
             .entries
@@ -267,6 +580,28 @@ impl Cache {
         count
     }
 
+    /// Get the statistics of the cache
+    pub fn stats(&self) -> CacheStats {
+        let max_age = Duration::from_secs(self.max_age_secs);
+        let mut stats = CacheStats {
+            entries: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+            ..CacheStats::default()
+        };
+        for entry in self.entries.values() {
+            if entry.success {
+                stats.successful += 1;
+            } else {
+                stats.failed += 1;
+            }
+            if matches!(entry.timestamp.elapsed(), Ok(age) if age > max_age) {
+                stats.expired += 1;
+            }
+        }
+        stats
+    }
+
     /// Get the number of entries in the cache
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -279,15 +614,162 @@ impl Cache {
     }
 
     /// Set the maximum age for cache entries
-    #[allow(dead_code)]
     pub fn set_max_age(&mut self, seconds: u64) {
         self.max_age_secs = seconds;
     }
 }
 
+/// Cache shared by the threads of a parallel run
+///
+/// Its entries are kept in a sharded map, so that looking up or updating the
+/// entry of a file only locks the shard of that entry, and the files are hashed
+/// without holding any lock.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct SharedCache {
+    /// Map of file paths to cache entries
+    entries: DashMap<String, CacheEntry>,
+
+    /// Number of lookups that found a valid entry
+    hits: AtomicU64,
+
+    /// Number of lookups that found no valid entry
+    misses: AtomicU64,
+
+    /// Whether an entry was added or updated
+    modified: AtomicBool,
+
+    /// The cache the entries were taken from, given them back by `into_inner`
+    cache: Cache,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl From<Cache> for SharedCache {
+    fn from(mut cache: Cache) -> Self {
+        Self {
+            entries: std::mem::take(&mut cache.entries).into_iter().collect(),
+            hits: AtomicU64::new(cache.hits),
+            misses: AtomicU64::new(cache.misses),
+            modified: AtomicBool::new(false),
+            cache,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SharedCache {
+    /// Check if a file is in the cache and hasn't changed, counting the lookup
+    /// as a hit or a miss
+    pub fn is_valid(&self, path: &Path, options_hash: &str) -> CacheResult<bool> {
+        let path_str = path.to_string_lossy().to_string();
+        let max_age = Duration::from_secs(self.cache.max_age_secs);
+
+        // The hash is copied for the shard to be unlocked while the file is hashed
+        let content_hash = self
+            .entries
+            .get(&path_str)
+            .filter(|entry| entry.is_current(max_age, options_hash))
+            .map(|entry| entry.content_hash.clone());
+        let valid = match content_hash {
+            Some(content_hash) => Cache::hash_file(path).map(|hash| hash == content_hash),
+            None => Ok(false),
+        };
+        let valid = match (valid, &self.cache.remote) {
+            // Files missing from the cache are looked up in the remote cache
+            (Ok(false), Some(remote)) => remote.fetch(path, options_hash).map(|entry| {
+                entry
+                    .map(|entry| {
+                        self.entries.insert(path_str, entry);
+                        self.modified.store(true, Ordering::Relaxed);
+                    })
+                    .is_some()
+            }),
+            (valid, _) => valid,
+        };
+
+        if matches!(valid, Ok(true)) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        valid
+    }
+
+    /// Add or update a file in the cache with the problems reported when it was
+    /// parsed
+    pub fn update_with_diagnostics(
+        &self,
+        path: &Path,
+        success: bool,
+        options_hash: &str,
+        diagnostics: Vec<Diagnostic>,
+    ) -> CacheResult<()> {
+        let path_str = path.to_string_lossy().to_string();
+        let entry = CacheEntry::new(path, success, options_hash, diagnostics)?;
+
+        if let Some(remote) = &self.cache.remote {
+            remote.store(&entry);
+        }
+        self.entries.insert(path_str, entry);
+        self.modified.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Get the problems reported when a file was parsed, if it is in the cache
+    pub fn get_diagnostics(&self, path: &Path) -> Option<Vec<Diagnostic>> {
+        let path_str = path.to_string_lossy().to_string();
+        self.entries
+            .get(&path_str)
+            .map(|entry| entry.diagnostics.clone())
+    }
+
+    /// Check if a file was successfully parsed according to the cache
+    pub fn was_successful(&self, path: &Path) -> Option<bool> {
+        let path_str = path.to_string_lossy().to_string();
+        self.entries.get(&path_str).map(|entry| entry.success)
+    }
+
+    /// Get the cache back with the entries and lookups of the run, once the
+    /// threads are done
+    pub fn into_inner(self) -> Cache {
+        let mut cache = self.cache;
+        cache.entries = self.entries.into_iter().collect();
+        cache.hits = self.hits.into_inner();
+        cache.misses = self.misses.into_inner();
+        if self.modified.into_inner() {
+            cache.last_modified = SystemTime::now();
+        }
+        cache
+    }
+}
+
+/// Parses an age or duration: a number of days (`30d`), hours (`12h`), minutes
+/// (`45m`) or seconds (`90s` or `90`)
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit_secs) = match value.char_indices().last() {
+        Some((index, 'd')) => (&value[..index], 86400),
+        Some((index, 'h')) => (&value[..index], 3600),
+        Some((index, 'm')) => (&value[..index], 60),
+        Some((index, 's')) => (&value[..index], 1),
+        _ => (value, 1),
+    };
+    number
+        .parse::<u64>()
+        .map(|number| Duration::from_secs(number.saturating_mul(unit_secs)))
+        .map_err(|_| {
+            format!(
+                "invalid duration '{}', expected e.g. 30d, 12h, 45m or 90s",
+                value
+            )
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::DiagnosticCode;
+    use crate::parser::ast::Span;
     use std::io::Write;
     use std::thread::sleep;
     use tempfile::NamedTempFile;
@@ -313,6 +795,13 @@ mod tests {
 
         let hash3 = Cache::hash_file(file3.path()).unwrap();
         assert_ne!(hash, hash3);
+
+        // A file read in several chunks hashes as its whole content
+        let content = "<% Response.Write \"x\" %>\n".repeat(10_000);
+        let mut file4 = NamedTempFile::new().unwrap();
+        file4.write_all(content.as_bytes()).unwrap();
+        let hash4 = Cache::hash_file(file4.path()).unwrap();
+        assert_eq!(hash4, format!("{:x}", Sha256::digest(content.as_bytes())));
     }
 
     #[test]
@@ -365,28 +854,148 @@ mod tests {
     }
 
     #[test]
-    fn test_error_message() {
-        let mut cache = Cache::new();
+    fn test_diagnostics() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(CACHE_FILE_NAME);
+        let mut cache = Cache::load_from(&cache_path);
         let options_hash = "test_hash";
 
         let mut file = NamedTempFile::new().unwrap();
-        writeln!(file, "Test file with error").unwrap();
-
-        let error_msg = "Parse error at line 5".to_string();
+        writeln!(file, "Test file with errors").unwrap();
+
+        let diagnostics = vec![
+            Diagnostic::new(
+                DiagnosticCode::SyntaxError,
+                Span::new(10, 14, 2, 5),
+                "Expected expression",
+            ),
+            Diagnostic::new(
+                DiagnosticCode::UnclosedBlock,
+                Span::new(20, 22, 3, 1),
+                "If is not closed",
+            )
+            .with_related(
+                Some(PathBuf::from("lib.asp")),
+                Span::new(0, 2, 1, 1),
+                "opened here",
+            ),
+        ];
         cache
-            .update_with_error(file.path(), false, options_hash, Some(error_msg.clone()))
+            .update_with_diagnostics(file.path(), false, options_hash, diagnostics.clone())
             .unwrap();
+        assert_eq!(
+            cache.get_diagnostics(file.path()),
+            Some(diagnostics.as_slice())
+        );
 
-        assert_eq!(cache.get_error_message(file.path()), Some(error_msg));
-
-        // Successful file should have no error message
+        // Successful files have no diagnostics
         let mut success_file = NamedTempFile::new().unwrap();
         writeln!(success_file, "Success file").unwrap();
         cache
             .update(success_file.path(), true, options_hash)
             .unwrap();
+        assert_eq!(cache.get_diagnostics(success_file.path()), Some(&[][..]));
+
+        // Diagnostics are saved as they are
+        cache.save().unwrap();
+        let loaded = Cache::load_from(&cache_path);
+        assert_eq!(
+            loaded.get_diagnostics(file.path()),
+            Some(diagnostics.as_slice())
+        );
+
+        // Caches of older formats, without diagnostics, are ignored
+        let json = serde_json::to_string_pretty(&cache).unwrap();
+        assert!(json.contains("\"code\": \"ASP002\""), "{}", json);
+        fs::write(
+            dir.path().join(LEGACY_CACHE_FILE_NAME),
+            json.replace("\"format\": 1,", ""),
+        )
+        .unwrap();
+        fs::remove_file(&cache_path).unwrap();
+        assert!(Cache::load_from(&cache_path).is_empty());
+    }
+
+    #[test]
+    fn test_binary_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join(CACHE_FILE_NAME);
+        let legacy_path = dir.path().join(LEGACY_CACHE_FILE_NAME);
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "Test content").unwrap();
 
-        assert_eq!(cache.get_error_message(success_file.path()), None);
+        // The JSON cache of the previous versions is migrated when saving
+        let mut cache = Cache::new();
+        cache.update(file.path(), true, "test_hash").unwrap();
+        fs::write(&legacy_path, serde_json::to_string_pretty(&cache).unwrap()).unwrap();
+        let mut loaded = Cache::load_from(&cache_path);
+        assert_eq!(loaded.len(), 1);
+        assert!(loaded.is_valid(file.path(), "test_hash").unwrap());
+        loaded.save().unwrap();
+        assert!(!legacy_path.exists());
+        let bytes = fs::read(&cache_path).unwrap();
+        assert!(bytes.starts_with(CACHE_MAGIC));
+        assert_eq!(Cache::load_from(&cache_path).stats().hits, 1);
+
+        // Files of other formats are discarded
+        let mut other_format = bytes.clone();
+        other_format[CACHE_MAGIC.len()] = CACHE_FORMAT as u8 + 1;
+        fs::write(&cache_path, other_format).unwrap();
+        assert!(Cache::load_from(&cache_path).is_empty());
+
+        // Clearing deletes both files
+        fs::write(&legacy_path, "{}").unwrap();
+        assert!(Cache::clear(&cache_path).unwrap());
+        assert!(!cache_path.exists() && !legacy_path.exists());
+        assert!(!Cache::clear(&cache_path).unwrap());
+    }
+
+    #[test]
+    fn test_shared_cache() {
+        let options_hash = "test_hash";
+        let files: Vec<NamedTempFile> = (0..8)
+            .map(|index| {
+                let mut file = NamedTempFile::new().unwrap();
+                writeln!(file, "File {}", index).unwrap();
+                file
+            })
+            .collect();
+
+        let mut cache = Cache::new();
+        cache.update(files[0].path(), true, options_hash).unwrap();
+        let shared = SharedCache::from(cache);
+
+        // Every thread looks up and updates the entry of its file
+        std::thread::scope(|scope| {
+            for (index, file) in files.iter().enumerate() {
+                let shared = &shared;
+                scope.spawn(move || {
+                    let valid = shared.is_valid(file.path(), options_hash).unwrap();
+                    assert_eq!(valid, index == 0);
+                    if !valid {
+                        let diagnostics = vec![Diagnostic::new(
+                            DiagnosticCode::SyntaxError,
+                            Span::new(0, 1, 1, 1),
+                            "Expected expression",
+                        )];
+                        shared
+                            .update_with_diagnostics(file.path(), false, options_hash, diagnostics)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        assert!(shared.is_valid(files[1].path(), options_hash).unwrap());
+        assert_eq!(shared.was_successful(files[1].path()), Some(false));
+        assert_eq!(shared.get_diagnostics(files[1].path()).unwrap().len(), 1);
+        assert_eq!(shared.get_diagnostics(files[0].path()), Some(Vec::new()));
+
+        let cache = shared.into_inner();
+        assert_eq!(cache.len(), 8);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (2, 7));
+        assert_eq!(stats.failed, 7);
     }
 
     #[test]
@@ -430,6 +1039,57 @@ mod tests {
         assert_eq!(cache.len(), 0);
     }
 
+    #[test]
+    fn test_stats() {
+        let mut cache = Cache::new();
+        let options_hash = "test_hash";
+        assert_eq!(cache.stats().hit_rate(), None);
+
+        let mut success_file = NamedTempFile::new().unwrap();
+        writeln!(success_file, "Success file").unwrap();
+        let mut fail_file = NamedTempFile::new().unwrap();
+        writeln!(fail_file, "Failure file").unwrap();
+
+        assert!(!cache.is_valid(success_file.path(), options_hash).unwrap());
+        cache
+            .update(success_file.path(), true, options_hash)
+            .unwrap();
+        cache.update(fail_file.path(), false, options_hash).unwrap();
+        assert!(cache.is_valid(success_file.path(), options_hash).unwrap());
+        assert!(cache.is_valid(fail_file.path(), options_hash).unwrap());
+        assert!(!cache.is_valid(fail_file.path(), "other_hash").unwrap());
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.successful, stats.failed), (2, 1, 1));
+        assert_eq!((stats.hits, stats.misses, stats.expired), (2, 2, 0));
+        assert_eq!(stats.hit_rate(), Some(0.5));
+
+        // The counters are kept on disk, and files of older versions have none
+        let json = serde_json::to_string(&cache).unwrap();
+        let loaded: Cache = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.stats(), stats);
+        let json = json.replace(",\"hits\":2,\"misses\":2", "");
+        let loaded: Cache = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.stats().hit_rate(), None);
+
+        assert_eq!(cache.clean_entries_older_than(Duration::from_secs(3600)), 0);
+        sleep(Duration::from_millis(10));
+        assert_eq!(cache.clean_entries_older_than(Duration::ZERO), 2);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86400)));
+        assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * 3600)));
+        assert_eq!(parse_duration("45m"), Ok(Duration::from_secs(45 * 60)));
+        assert_eq!(parse_duration(" 90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert!(parse_duration("soon").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("-1h").is_err());
+    }
+
     #[test]
     fn test_hash_options() {
         let options1 = vec!["--format=ascii".to_string(), "--verbose".to_string()];
@@ -444,5 +1104,10 @@ mod tests {
         assert_ne!(hash1, hash2);
         assert_ne!(hash1, hash3);
         assert_ne!(hash2, hash3);
+
+        // The hash depends on the parser, not only on the options
+        let options_only = format!("{:x}", Sha256::digest("--format=ascii,--verbose"));
+        assert_ne!(hash1, options_only);
+        assert_eq!(Cache::parser_fingerprint().len(), 64);
     }
 }
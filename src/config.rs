@@ -1,9 +1,23 @@
+use globset::{GlobBuilder, GlobMatcher};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::cache::RemoteBackend;
+use crate::file_utils::{
+    DEFAULT_MAX_FILE_SIZE, DefaultEncoding, ExcludePatterns, ScanOptions, parse_size,
+};
+use crate::formatter::{FormatOptions, IndentStyle, KeywordCase};
+use crate::includes::IncludeResolver;
+use crate::lint::Registry;
+use crate::lint::plugin::CommandPlugin;
+use crate::output_format::OutputFormat;
+use crate::parser::{DiagnosticCode, Severity};
+
 /// Errors that can occur when working with configuration files
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -14,15 +28,120 @@ pub enum ConfigError {
     ParseError(#[from] toml::de::Error),
 
     #[error("Invalid configuration value: {0}")]
-    #[allow(dead_code)]
     InvalidValue(String),
 }
 
+/// Keys of the top level of a configuration file
+const KEYS: [&str; 22] = [
+    "extends",
+    "format",
+    "color",
+    "verbose",
+    "quiet_success",
+    "strict",
+    "ignore_warnings",
+    "exclude",
+    "replace_exclude",
+    "cache",
+    "threads",
+    "include_root",
+    "virtual_paths",
+    "extensions",
+    "follow_symlinks",
+    "max_depth",
+    "max_file_size",
+    "default_encoding",
+    "mmap",
+    "rules",
+    "plugins",
+    "override",
+];
+
+/// Output formats of the `format` option
+const OUTPUT_FORMATS: [&str; 7] = [
+    "ascii",
+    "ci",
+    "json",
+    "jsonl",
+    "ast-json",
+    "codeclimate",
+    "auto",
+];
+
+/// Named configurations that `extends` can give instead of a file, with the
+/// TOML of their settings
+const PRESETS: [(&str, &str); 3] = [
+    (
+        "recommended",
+        r#"
+[rules]
+long-procedure = "warning"
+deep-nesting = "warning"
+"#,
+    ),
+    (
+        "strict",
+        r#"
+strict = true
+
+[rules]
+unused-variable = "error"
+unused-parameter = "warning"
+unreachable-code = "error"
+duplicate-declaration = "error"
+shadowed-variable = "error"
+unchecked-error = "error"
+long-procedure = "warning"
+deep-nesting = "warning"
+naming-convention = "notice"
+"#,
+    ),
+    (
+        "security",
+        r#"
+[rules]
+cross-site-scripting = "error"
+hardcoded-credentials = "error"
+dangerous-function = "error"
+unchecked-error = "error"
+"#,
+    ),
+];
+
+/// Prefix of the environment variables setting options
+const ENV_PREFIX: &str = "ASP_PARSER_";
+
+/// Value of the `extends` option
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Extends {
+    /// A single preset or file: `extends = "recommended"`
+    One(String),
+    /// Presets or files, the later ones taking precedence:
+    /// `extends = ["recommended", "../shared/asp-parser-base.toml"]`
+    Many(Vec<String>),
+}
+
+impl Extends {
+    /// Returns the presets and files, the later ones taking precedence
+    pub fn names(&self) -> Vec<&str> {
+        match self {
+            Extends::One(name) => vec![name.as_str()],
+            Extends::Many(names) => names.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
 /// Configuration options that can be set in a TOML configuration file
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct Config {
-    /// Format for output (ascii, ci, json)
-    pub format: Option<String>,
+    /// Presets or configuration files whose settings this configuration extends,
+    /// files being relative to the configuration file
+    pub extends: Option<Extends>,
+
+    /// Format for output (ascii, ci, json), or the `[format]` table of the code
+    /// formatter settings
+    pub format: Option<FormatConfig>,
 
     /// Show colored output in terminal
     pub color: Option<bool>,
@@ -45,21 +164,510 @@ pub struct Config {
     /// Replace default exclusions instead of extending them
     pub replace_exclude: Option<bool>,
 
-    /// Enable parsing cache
-    pub cache: Option<bool>,
+    /// Enable parsing cache, or the `[cache]` table of the cache settings
+    pub cache: Option<CacheConfig>,
 
     /// Number of threads for parallel processing
     pub threads: Option<usize>,
+
+    /// Web root of the site, which `virtual` includes are relative to, relative to
+    /// the configuration file
+    pub include_root: Option<String>,
+
+    /// Directories of the virtual directories of the site, relative to the
+    /// configuration file, by virtual path (`[virtual_paths]` table)
+    pub virtual_paths: Option<BTreeMap<String, String>>,
+
+    /// Extensions of the files looked for in directories
+    pub extensions: Option<Vec<String>>,
+
+    /// Follow symbolic links when looking for files in directories
+    pub follow_symlinks: Option<bool>,
+
+    /// Number of directory levels searched, 1 for the files of the given
+    /// directories only
+    pub max_depth: Option<usize>,
+
+    /// Size above which the files found in directories are skipped, such as
+    /// `512K` or `10M`, or `0` for no limit
+    pub max_file_size: Option<String>,
+
+    /// Encoding of the files that are not valid UTF-8, without byte order mark
+    /// nor `CodePage` directive: an encoding label, a Windows code page, or
+    /// `auto` to guess it from their content
+    pub default_encoding: Option<String>,
+
+    /// Map the files to parse in memory instead of reading them
+    pub mmap: Option<bool>,
+
+    /// Settings of the lint rules, by rule code or name (`[rules]` table)
+    pub rules: Option<HashMap<String, RuleConfig>>,
+
+    /// Programs providing additional lint rules, relative to the configuration file
+    pub plugins: Option<Vec<String>>,
+
+    /// Settings of the files matching glob patterns, relative to the configuration
+    /// file (`[override."admin/**"]` tables)
+    #[serde(rename = "override")]
+    pub overrides: Option<BTreeMap<String, Override>>,
+}
+
+/// Settings of the files matching a pattern, in an `[override."pattern"]` table,
+/// taking precedence over those of the configuration
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Override {
+    /// Treat warnings as errors
+    pub strict: Option<bool>,
+
+    /// Warnings to ignore, in addition to those of the configuration
+    pub ignore_warnings: Option<Vec<String>>,
+
+    /// Output format or code formatter settings
+    pub format: Option<FormatConfig>,
+
+    /// Settings of the lint rules, merged option by option with the `[rules]` table
+    pub rules: Option<HashMap<String, RuleConfig>>,
+}
+
+/// Value of the `format` option
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum FormatConfig {
+    /// Only the output format: `format = "json"`
+    Output(String),
+    /// The `[format]` table, with the code formatter settings
+    Settings(FormatSettings),
+}
+
+/// Settings of the code formatter in the `[format]` table
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FormatSettings {
+    /// Format for output (ascii, ci, json), which cannot be given as
+    /// `format = "..."` when the table is used
+    pub output: Option<String>,
+    /// Indentation with spaces or tabs
+    pub indent_style: Option<IndentStyle>,
+    /// Number of spaces of an indentation level
+    pub indent_width: Option<usize>,
+    /// Casing of keywords: "pascal" (`End If`) or "lower" (`end if`)
+    pub keyword_case: Option<KeywordCase>,
+    /// Length beyond which statements are wrapped with line continuations
+    pub max_line_length: Option<usize>,
+    /// Align the continuation lines of declarations with the first name
+    pub align_dims: Option<bool>,
+}
+
+impl FormatConfig {
+    /// Returns the settings, with only the output format for `format = "..."`
+    pub fn settings(&self) -> FormatSettings {
+        match self {
+            FormatConfig::Output(output) => FormatSettings {
+                output: Some(output.clone()),
+                ..FormatSettings::default()
+            },
+            FormatConfig::Settings(settings) => settings.clone(),
+        }
+    }
+
+    /// Merges the value with the one of another config, where this value takes
+    /// precedence setting by setting
+    pub fn merge(&self, other: &FormatConfig) -> FormatConfig {
+        if let (FormatConfig::Output(_), FormatConfig::Output(_)) = (self, other) {
+            return self.clone();
+        }
+        let (ours, theirs) = (self.settings(), other.settings());
+        FormatConfig::Settings(FormatSettings {
+            output: ours.output.or(theirs.output),
+            indent_style: ours.indent_style.or(theirs.indent_style),
+            indent_width: ours.indent_width.or(theirs.indent_width),
+            keyword_case: ours.keyword_case.or(theirs.keyword_case),
+            max_line_length: ours.max_line_length.or(theirs.max_line_length),
+            align_dims: ours.align_dims.or(theirs.align_dims),
+        })
+    }
+}
+
+/// Value of the `cache` option
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum CacheConfig {
+    /// Only whether the cache is enabled: `cache = false`
+    Enabled(bool),
+    /// The `[cache]` table, with the cache settings
+    Settings(CacheSettings),
+}
+
+/// Settings of the parsing cache in the `[cache]` table
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CacheSettings {
+    /// Whether the cache is enabled
+    pub enabled: Option<bool>,
+    /// Age after which entries are parsed again, such as `"7d"` or `"12h"`
+    pub ttl: Option<String>,
+    /// Directory of the cache file, relative to the configuration file
+    pub directory: Option<String>,
+    /// URL of the remote cache shared with other machines
+    pub remote: Option<String>,
+    /// Kind of store of the remote cache, `"http"` (the default) or `"s3"`
+    pub remote_backend: Option<String>,
+}
+
+impl CacheConfig {
+    /// Returns the settings, with only `enabled` for `cache = true` or `false`
+    pub fn settings(&self) -> CacheSettings {
+        match self {
+            CacheConfig::Enabled(enabled) => CacheSettings {
+                enabled: Some(*enabled),
+                ..CacheSettings::default()
+            },
+            CacheConfig::Settings(settings) => settings.clone(),
+        }
+    }
+
+    /// Merges the value with the one of another config, where this value takes
+    /// precedence setting by setting
+    pub fn merge(&self, other: &CacheConfig) -> CacheConfig {
+        if let (CacheConfig::Enabled(_), CacheConfig::Enabled(_)) = (self, other) {
+            return self.clone();
+        }
+        let (ours, theirs) = (self.settings(), other.settings());
+        CacheConfig::Settings(CacheSettings {
+            enabled: ours.enabled.or(theirs.enabled),
+            ttl: ours.ttl.or(theirs.ttl),
+            directory: ours.directory.or(theirs.directory),
+            remote: ours.remote.or(theirs.remote),
+            remote_backend: ours.remote_backend.or(theirs.remote_backend),
+        })
+    }
+}
+
+/// Level of a lint rule in the `[rules]` table
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleLevel {
+    /// The rule does not run
+    Off,
+    /// The rule reports notices
+    Notice,
+    /// The rule reports warnings
+    Warning,
+    /// The rule reports errors
+    Error,
+}
+
+impl RuleLevel {
+    /// Returns the severity of the diagnostics, or `None` if the rule is off
+    pub fn severity(&self) -> Option<Severity> {
+        match self {
+            RuleLevel::Off => None,
+            RuleLevel::Notice => Some(Severity::Notice),
+            RuleLevel::Warning => Some(Severity::Warning),
+            RuleLevel::Error => Some(Severity::Error),
+        }
+    }
+}
+
+/// Settings of a lint rule in the `[rules]` table
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum RuleConfig {
+    /// Only the level: `unused-variable = "off"`
+    Level(RuleLevel),
+    /// The level and options of the rule:
+    /// `complex-procedure = { level = "error", max_complexity = 15 }`
+    Options {
+        /// Level of the rule; its default severity when not set
+        level: Option<RuleLevel>,
+        /// Options of the rule, such as thresholds or allowed names
+        #[serde(flatten)]
+        options: toml::Table,
+    },
+}
+
+impl RuleConfig {
+    /// Returns the level of the rule, if set
+    pub fn level(&self) -> Option<RuleLevel> {
+        match self {
+            RuleConfig::Level(level) => Some(*level),
+            RuleConfig::Options { level, .. } => *level,
+        }
+    }
+
+    /// Returns the options of the rule
+    pub fn options(&self) -> toml::Table {
+        match self {
+            RuleConfig::Level(_) => toml::Table::new(),
+            RuleConfig::Options { options, .. } => options.clone(),
+        }
+    }
+
+    /// Merges the settings with those of another config, where these settings
+    /// take precedence option by option
+    pub fn merge(&self, other: &RuleConfig) -> RuleConfig {
+        let mut options = other.options();
+        options.extend(self.options());
+        RuleConfig::Options {
+            level: self.level().or(other.level()),
+            options,
+        }
+    }
+}
+
+impl Override {
+    /// Returns a configuration with the settings of the override
+    pub fn to_config(&self) -> Config {
+        Config {
+            strict: self.strict,
+            ignore_warnings: self.ignore_warnings.clone(),
+            format: self.format.clone(),
+            rules: self.rules.clone(),
+            ..Config::default()
+        }
+    }
+
+    /// Merges the settings with those of the same pattern in another config,
+    /// where these settings take precedence
+    pub fn merge(&self, other: &Override) -> Override {
+        let merged = self.to_config().merge(&other.to_config());
+        Override {
+            strict: merged.strict,
+            ignore_warnings: merged.ignore_warnings,
+            format: merged.format,
+            rules: merged.rules,
+        }
+    }
 }
 
 impl Config {
     /// Load configuration from a TOML file at the specified path
     pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
-        let content = fs::read_to_string(path)?;
-        let config: Config = toml::from_str(&content)?;
+        Ok(Config::from_file_layers(path)?
+            .into_iter()
+            .fold(Config::default(), |merged, (_, config)| {
+                config.merge(&merged)
+            }))
+    }
+
+    /// Load a configuration file as layers: the presets and files it extends,
+    /// then the file itself with its own settings only
+    ///
+    /// The layers are ordered from the most general to the most specific, as
+    /// [`Config::find_configs`] returns them, so that merging them in this order
+    /// gives the settings of [`Config::from_file`]. Presets are named
+    /// `preset '<name>'`.
+    pub fn from_file_layers(path: &Path) -> Result<Vec<(PathBuf, Config)>, ConfigError> {
+        Config::from_file_extending(path, &mut Vec::new())
+    }
+
+    /// Returns the settings of a preset given to `extends`, such as `recommended`
+    pub fn preset(name: &str) -> Option<Config> {
+        PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .and_then(|(_, settings)| toml::from_str(settings).ok())
+    }
+
+    /// Load the settings of the `ASP_PARSER_*` environment variables, which take
+    /// precedence over the configuration files
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Config::from_env_vars(std::env::vars())
+    }
+
+    /// Load the settings of environment variables
+    ///
+    /// Variables are named after the options, in upper case with the
+    /// `ASP_PARSER_` prefix (`ASP_PARSER_QUIET_SUCCESS`), and the keys of the
+    /// `[cache]` table are prefixed with `CACHE_` (`ASP_PARSER_CACHE_TTL`);
+    /// `ASP_PARSER_CACHE_DIR` is read
+    /// by [`Cache::get_cache_path_in`](crate::cache::Cache::get_cache_path_in). Lists are
+    /// comma-separated and booleans are `true`, `false`, `1` or `0`. Empty and
+    /// unknown variables are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `vars` - The names and values of the environment variables
+    pub fn from_env_vars(
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut config = Config::default();
+        let mut cache = CacheSettings::default();
+        for (name, value) in vars {
+            let Some(option) = name.strip_prefix(ENV_PREFIX) else {
+                continue;
+            };
+            let value = value.trim();
+            if value.is_empty() {
+                continue;
+            }
+            let invalid = |expected: &str| {
+                ConfigError::InvalidValue(format!(
+                    "{}: expected {}, got '{}'",
+                    name, expected, value
+                ))
+            };
+            let boolean = || match value.to_lowercase().as_str() {
+                "true" | "1" => Ok(true),
+                "false" | "0" => Ok(false),
+                _ => Err(invalid("true or false")),
+            };
+            let list = || {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            };
+            match option {
+                "FORMAT" => config.format = Some(FormatConfig::Output(value.to_string())),
+                "COLOR" => config.color = Some(boolean()?),
+                "VERBOSE" => config.verbose = Some(boolean()?),
+                "QUIET_SUCCESS" => config.quiet_success = Some(boolean()?),
+                "STRICT" => config.strict = Some(boolean()?),
+                "IGNORE_WARNINGS" => config.ignore_warnings = Some(list()),
+                "EXCLUDE" => config.exclude = Some(value.to_string()),
+                "REPLACE_EXCLUDE" => config.replace_exclude = Some(boolean()?),
+                "CACHE" => cache.enabled = Some(boolean()?),
+                "CACHE_TTL" => cache.ttl = Some(value.to_string()),
+                "CACHE_REMOTE" => cache.remote = Some(value.to_string()),
+                "CACHE_REMOTE_BACKEND" => cache.remote_backend = Some(value.to_string()),
+                "THREADS" => config.threads = Some(value.parse().map_err(|_| invalid("a number"))?),
+                "INCLUDE_ROOT" => config.include_root = Some(value.to_string()),
+                "EXTENSIONS" => config.extensions = Some(list()),
+                "FOLLOW_SYMLINKS" => config.follow_symlinks = Some(boolean()?),
+                "MAX_DEPTH" => {
+                    config.max_depth = Some(value.parse().map_err(|_| invalid("a number"))?)
+                }
+                "MAX_FILE_SIZE" => config.max_file_size = Some(value.to_string()),
+                "DEFAULT_ENCODING" => config.default_encoding = Some(value.to_string()),
+                "MMAP" => config.mmap = Some(boolean()?),
+                "PLUGINS" => config.plugins = Some(list()),
+                _ => {}
+            }
+        }
+        if cache != CacheSettings::default() {
+            config.cache = Some(CacheConfig::Settings(cache));
+        }
         Ok(config)
     }
 
+    /// Load a configuration file as layers, after the presets and files it extends
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the configuration file
+    /// * `extending` - The files extended by the file being loaded, to detect cycles
+    fn from_file_extending(
+        path: &Path,
+        extending: &mut Vec<PathBuf>,
+    ) -> Result<Vec<(PathBuf, Config)>, ConfigError> {
+        let content = fs::read_to_string(path)?;
+        let mut config: Config = toml::from_str(&content)?;
+        if let (Some(plugins), Some(directory)) = (&mut config.plugins, path.parent()) {
+            for plugin in plugins.iter_mut() {
+                if Path::new(plugin).is_relative() {
+                    *plugin = directory.join(&plugin).to_string_lossy().into_owned();
+                }
+            }
+        }
+        if let Some(directory) = path.parent() {
+            let relative_to_file = |value: &mut String| {
+                if Path::new(value.as_str()).is_relative() {
+                    *value = directory.join(&value).to_string_lossy().into_owned();
+                }
+            };
+            if let Some(CacheConfig::Settings(CacheSettings {
+                directory: Some(cache_directory),
+                ..
+            })) = &mut config.cache
+            {
+                relative_to_file(cache_directory);
+            }
+            config.include_root.iter_mut().for_each(relative_to_file);
+            config
+                .virtual_paths
+                .iter_mut()
+                .flat_map(|paths| paths.values_mut())
+                .for_each(relative_to_file);
+        }
+        if let Some(overrides) = config.overrides.take() {
+            // The patterns are made absolute, to match the files wherever the
+            // configuration applies from
+            let directory = absolute(path.parent().unwrap_or(Path::new("")));
+            let directory = globset::escape(&directory.to_string_lossy());
+            let mut absolute_overrides = BTreeMap::new();
+            for (pattern, settings) in overrides {
+                if let Err(e) = override_matcher(&pattern) {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "override '{}': {}",
+                        pattern,
+                        e.kind()
+                    )));
+                }
+                let pattern = match pattern.strip_prefix("./") {
+                    Some(pattern) => pattern,
+                    None => &pattern,
+                };
+                absolute_overrides.insert(format!("{}/{}", directory, pattern), settings);
+            }
+            config.overrides = Some(absolute_overrides);
+        }
+        let mut layers = config.extended_layers(path, extending)?;
+        layers.push((path.to_path_buf(), config));
+        Ok(layers)
+    }
+
+    /// Loads the presets and files given to `extends` by a configuration loaded
+    /// from a file, as layers from the most general to the most specific
+    fn extended_layers(
+        &self,
+        path: &Path,
+        extending: &mut Vec<PathBuf>,
+    ) -> Result<Vec<(PathBuf, Config)>, ConfigError> {
+        let Some(extends) = &self.extends else {
+            return Ok(Vec::new());
+        };
+        let directory = path.parent().unwrap_or(Path::new(""));
+        extending.push(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        let mut layers = Vec::new();
+        for name in extends.names() {
+            // Presets are names, files are paths to a TOML file
+            if !name.contains(['/', '\\']) && !name.ends_with(".toml") {
+                let preset = Config::preset(name).ok_or_else(|| {
+                    let presets: Vec<&str> = PRESETS.iter().map(|(preset, _)| *preset).collect();
+                    ConfigError::InvalidValue(format!(
+                        "extends: unknown preset '{}', expected one of {} or a path to a .toml file",
+                        name,
+                        presets.join(", ")
+                    ))
+                })?;
+                layers.push((PathBuf::from(format!("preset '{}'", name)), preset));
+            } else {
+                let extended_path = directory.join(name);
+                let canonical = fs::canonicalize(&extended_path).unwrap_or(extended_path.clone());
+                if extending.contains(&canonical) {
+                    return Err(ConfigError::InvalidValue(format!(
+                        "extends: circular extension of '{}'",
+                        name
+                    )));
+                }
+                layers.extend(
+                    Config::from_file_extending(&extended_path, extending).map_err(|e| {
+                        ConfigError::InvalidValue(match e {
+                            ConfigError::InvalidValue(message) => message,
+                            e => format!("extends '{}': {}", name, e),
+                        })
+                    })?,
+                );
+            }
+        }
+        extending.pop();
+        Ok(layers)
+    }
+
     /// Create a default configuration with recommended settings and comments
     pub fn default_with_comments() -> String {
         r#"# ASP Classic Parser Configuration
@@ -69,6 +677,10 @@ impl Config {
 #   - .asp-parser.toml (as a hidden file)
 # Or in any parent directory, with closer files taking precedence
 
+# Presets ("recommended", "strict", "security") or files, relative to this file,
+# whose settings this file extends; the later ones and this file take precedence
+# extends = ["recommended", "../shared/asp-parser-base.toml"]
+
 # Output format: "ascii" (human-readable), "ci" (GitHub Actions), "json" (machine-readable)
 # format = "ascii"
 
@@ -96,16 +708,105 @@ impl Config {
 # Enable parsing cache
 # cache = false
 
+# Or the cache settings: whether it is enabled, the age after which files are
+# parsed again, the directory of the cache file, relative to this file, and the
+# URL of a remote cache shared by CI machines, an HTTP server or an S3-compatible
+# store ("s3" backend, with the AWS_* credentials of the environment)
+# [cache]
+# enabled = true
+# ttl = "7d"
+# directory = ".cache"
+# remote = "https://cache.example.com/asp-parser"
+# remote_backend = "http"
+
 # Number of threads for parallel processing
 # threads = 4
+
+# Web root of the site, which `virtual` includes are relative to, relative to
+# this file (default: the current directory, or --web-root)
+# include_root = "wwwroot"
+
+# Directories of the virtual directories of the site, relative to this file
+# [virtual_paths]
+# "/shared" = "../shared-includes"
+
+# Extensions of the files looked for in directories
+# extensions = ["asp", "vbs", "inc"]
+
+# Follow symbolic links when looking for files in directories
+# follow_symlinks = false
+
+# Number of directory levels searched (1: the files of the given directories only)
+# max_depth = 3
+
+# Size above which the files found in directories are skipped with a notice,
+# or 0 for no limit (default: "10M")
+# max_file_size = "2M"
+
+# Encoding of the files that are not valid UTF-8, without byte order mark nor
+# CodePage directive: a label, a Windows code page, or "auto" to guess it
+# (default: "iso-8859-1")
+# default_encoding = "windows-1252"
+
+# Map the files to parse in memory instead of reading them, for a lower memory
+# use when many large files are parsed at once
+# mmap = false
+
+# Lint rules, by code or name: "off", "notice", "warning" or "error",
+# or a table with the level and the options of the rule
+# [rules]
+# unused-variable = "off"
+# undeclared-name = "warning"
+# complex-procedure = { level = "error", max_complexity = 15 }
+# cross-site-scripting = { sanitizers = ["Server.HTMLEncode", "HtmlSafe"] }
+
+# Programs providing additional lint rules, relative to this file
+# plugins = ["./rules/company_rules"]
+
+# Code formatter settings (`fmt` subcommand); with this table, the output
+# format is set by its `output` key instead of `format = "..."`
+# [format]
+# output = "ascii"
+# indent_style = "spaces"     # or "tabs"
+# indent_width = 4
+# keyword_case = "pascal"     # `End If`, or "lower" for `end if`
+# max_line_length = 120       # wrap longer statements with line continuations
+# align_dims = false          # align continued Dim lists with the first name
+
+# Settings of the files matching a glob pattern, relative to this file: `strict`,
+# `ignore_warnings`, `format` and `rules` take precedence for these files
+# [override."admin/legacy/**"]
+# strict = false
+# rules = { undeclared-name = "off" }
 "#
         .to_string()
     }
 
     /// Look for configuration files in parent directories, starting from the given path
-    /// Returns a list of configs from most specific (closest to path) to most general
+    /// Returns a list of configs from most general to most specific (closest to path),
+    /// each file preceded by the presets and files it extends
     pub fn find_configs(start_path: &Path) -> Vec<(PathBuf, Config)> {
-        let mut configs = Vec::new();
+        Config::find_config_files(start_path)
+            .into_iter()
+            .flat_map(|config_path| match Config::from_file_layers(&config_path) {
+                Ok(layers) => layers,
+                Err(e) => {
+                    eprintln!(
+                        "Warning: Failed to load config from {}: {}",
+                        config_path.display(),
+                        e
+                    );
+                    Vec::new()
+                }
+            })
+            .collect()
+    }
+
+    /// Look for configuration files in parent directories, starting from the given path,
+    /// without loading them
+    /// Returns their paths from most general to most specific (closest to path)
+    pub fn find_config_files(start_path: &Path) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
         let mut current_dir = if start_path.is_file() {
             start_path.parent().map(Path::to_path_buf)
         } else {
@@ -120,18 +821,7 @@ impl Config {
             for filename in &config_filenames {
                 let config_path = dir.join(filename);
                 if config_path.exists() {
-                    match Config::from_file(&config_path) {
-                        Ok(config) => {
-                            configs.push((config_path, config));
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "Warning: Failed to load config from {}: {}",
-                                config_path.display(),
-                                e
-                            );
-                        }
-                    }
+                    paths.push(config_path);
                 }
             }
 
@@ -139,16 +829,506 @@ impl Config {
             current_dir = dir.parent().map(Path::to_path_buf);
         }
 
-        // Reverse so most specific (closest to path) is first
-        configs.reverse();
-        configs
+        // Reverse so most general (farthest from path) is first
+        paths.reverse();
+        paths
+    }
+
+    /// Returns the root directory of the project of a directory: the directory of
+    /// the outermost configuration file found from it, if any
+    pub fn project_root(directory: &Path) -> Option<PathBuf> {
+        Self::find_config_files(directory)
+            .first()
+            .and_then(|path| path.parent())
+            .map(Path::to_path_buf)
+    }
+
+    /// Checks a configuration file for what loading it would ignore or reject
+    ///
+    /// Loading a configuration ignores unknown keys, and invalid values only fail
+    /// the commands using them; checking reports both.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the configuration file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<String>, ConfigError>` - The problems found, each naming its
+    ///   key or line, or an error if the file cannot be read
+    pub fn check_file(path: &Path) -> Result<Vec<String>, ConfigError> {
+        let content = fs::read_to_string(path)?;
+        // Line of a position of the file, for the errors of the TOML parser
+        let line = |error: &toml::de::Error| {
+            error
+                .span()
+                .map(|span| content[..span.start].matches('\n').count() + 1)
+        };
+        let describe = |error: toml::de::Error| match line(&error) {
+            Some(line) => format!("line {}: {}", line, error.message().trim_end()),
+            None => error.message().trim_end().to_string(),
+        };
+
+        let table: toml::Table = match toml::from_str(&content) {
+            Ok(table) => table,
+            Err(e) => return Ok(vec![describe(e)]),
+        };
+        let mut problems: Vec<String> = table
+            .keys()
+            .filter(|key| !KEYS.contains(&key.as_str()))
+            .map(|key| format!("unknown key '{}'", key))
+            .collect();
+        let config = match Config::from_file(path) {
+            Ok(config) => config,
+            Err(ConfigError::ParseError(e)) => {
+                // Errors of the untagged `format` and rule values do not tell
+                // which of their values is invalid
+                let nested = nested_problems(&table);
+                if nested.is_empty() {
+                    problems.push(describe(e));
+                } else {
+                    problems.extend(nested);
+                }
+                return Ok(problems);
+            }
+            Err(ConfigError::InvalidValue(message)) => {
+                problems.push(message);
+                return Ok(problems);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let Some(output) = config.output_format()
+            && OutputFormat::from_str(&output).is_err()
+        {
+            problems.push(format!(
+                "format: unknown output format '{}', expected one of {}",
+                output,
+                OUTPUT_FORMATS.join(", ")
+            ));
+        }
+        for warning in config.ignore_warnings.iter().flatten() {
+            if DiagnosticCode::from_name(warning).is_none() {
+                problems.push(format!("ignore_warnings: unknown warning '{}'", warning));
+            }
+        }
+        if config.threads == Some(0) {
+            problems.push("threads: must be at least 1".to_string());
+        }
+        if let Err(ConfigError::InvalidValue(message)) = config.cache_ttl() {
+            problems.push(message);
+        }
+        if let Err(ConfigError::InvalidValue(message)) = config.remote_cache() {
+            problems.push(message);
+        }
+        if config.extensions.as_ref().is_some_and(Vec::is_empty) {
+            problems.push("extensions: must not be empty".to_string());
+        }
+        if config.max_depth == Some(0) {
+            problems.push("max_depth: must be at least 1".to_string());
+        }
+        if let Err(ConfigError::InvalidValue(message)) = config.scan_options() {
+            problems.push(message);
+        }
+        if let Err(ConfigError::InvalidValue(message)) = config.default_encoding() {
+            problems.push(message);
+        }
+        if let Some(exclude) = &config.exclude {
+            let patterns: Vec<String> = exclude.split(',').map(String::from).collect();
+            if let Err(e) = ExcludePatterns::new(&patterns) {
+                problems.push(format!("exclude: {}", e));
+            }
+        }
+        let directories = config
+            .include_root
+            .iter()
+            .chain(config.virtual_paths.iter().flat_map(|paths| paths.values()));
+        for directory in directories {
+            if !Path::new(directory).is_dir() {
+                problems.push(format!("'{}' is not a directory", directory));
+            }
+        }
+        for plugin in config.plugins.iter().flatten() {
+            if !Path::new(plugin).exists() {
+                problems.push(format!("plugins: '{}' does not exist", plugin));
+            }
+        }
+        if let Err(e) = config.lint_registry() {
+            problems.push(match e {
+                ConfigError::InvalidValue(message) => format!("rules: {}", message),
+                e => e.to_string(),
+            });
+        }
+        let overrides = table.get("override").and_then(toml::Value::as_table);
+        for (pattern, settings) in overrides.into_iter().flatten() {
+            if let Ok(settings) = settings.clone().try_into::<Override>()
+                && let Err(ConfigError::InvalidValue(message)) =
+                    settings.to_config().lint_registry()
+            {
+                problems.push(format!("override '{}': rules: {}", pattern, message));
+            }
+        }
+        Ok(problems)
+    }
+
+    /// Writes the configuration merged from files as TOML, each setting followed by
+    /// the files setting it
+    ///
+    /// # Arguments
+    ///
+    /// * `configs` - The configuration files, from the most general to the most
+    ///   specific, as returned by [`Config::find_configs`]
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The merged settings, each with a comment listing the files
+    ///   setting it, closest first
+    pub fn describe_merged(configs: &[(PathBuf, Config)]) -> String {
+        let merged = configs
+            .iter()
+            .fold(Config::default(), |merged, (_, config)| {
+                config.merge(&merged)
+            });
+        // Tables of the files, closest first, where `format = "..."` is the
+        // `output` key of the `[format]` table
+        let tables: Vec<(&PathBuf, toml::Table)> = configs
+            .iter()
+            .rev()
+            .map(|(path, config)| {
+                let mut table = config.to_table();
+                if let Some(toml::Value::String(output)) = table.get("format") {
+                    let mut format = toml::Table::new();
+                    format.insert("output".to_string(), toml::Value::String(output.clone()));
+                    table.insert("format".to_string(), toml::Value::Table(format));
+                }
+                (path, table)
+            })
+            .collect();
+        let sources = |keys: &[&str]| {
+            let files: Vec<String> = tables
+                .iter()
+                .filter(|(_, table)| {
+                    let mut value = table.get(keys[0]);
+                    for key in &keys[1..] {
+                        value = value.and_then(|value| value.get(key));
+                    }
+                    value.is_some()
+                })
+                .map(|(path, _)| path.display().to_string())
+                .collect();
+            files.join(", ")
+        };
+
+        let mut out = String::new();
+        if configs.is_empty() {
+            out.push_str("# No configuration file found\n");
+            return out;
+        }
+        out.push_str("# Configuration files, closest first:\n");
+        for (path, _) in &tables {
+            out.push_str(&format!("#   {}\n", path.display()));
+        }
+        let table = merged.to_table();
+        for (key, value) in table.iter().filter(|(_, value)| !value.is_table()) {
+            out.push_str(&format!(
+                "{} = {} # from {}\n",
+                key,
+                value,
+                sources(&[key, "output"][..if key == "format" { 2 } else { 1 }])
+            ));
+        }
+        for (key, value) in table.iter() {
+            let Some(settings) = value.as_table() else {
+                continue;
+            };
+            out.push_str(&format!("\n[{}]\n", key));
+            for (name, value) in settings {
+                out.push_str(&format!(
+                    "{} = {} # from {}\n",
+                    toml_key(name),
+                    value,
+                    sources(&[key, name])
+                ));
+            }
+        }
+        out
+    }
+
+    /// Returns whether no option is set
+    pub fn is_empty(&self) -> bool {
+        self.to_table().is_empty()
+    }
+
+    /// Returns the settings as a TOML table, without the unset ones
+    fn to_table(&self) -> toml::Table {
+        match toml::Value::try_from(self) {
+            Ok(toml::Value::Table(table)) => table,
+            _ => toml::Table::new(),
+        }
+    }
+
+    /// Returns the JSON Schema of the configuration files, for editors to
+    /// validate and complete them
+    ///
+    /// The rules and warnings are those of this version, and unknown keys are
+    /// rejected, as `config check` reports them.
+    pub fn json_schema() -> serde_json::Value {
+        use serde_json::{Map, Value, json};
+
+        let codes: Vec<&str> = DiagnosticCode::ALL
+            .iter()
+            .flat_map(|code| [code.as_str(), code.name()])
+            .collect();
+        let mut rules = Map::new();
+        for rule in Registry::default().rules() {
+            let code = rule.code();
+            let description = format!("{} ({})", rule.description(), code.as_str());
+            for key in [code.name(), code.as_str()] {
+                rules.insert(
+                    key.to_string(),
+                    json!({ "description": description, "$ref": "#/definitions/rule" }),
+                );
+            }
+        }
+        let presets: Vec<Value> = PRESETS.iter().map(|(name, _)| json!(name)).collect();
+
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "asp-parser.toml",
+            "description": "Configuration of asp-classic-parser",
+            "type": "object",
+            "additionalProperties": false,
+            "properties": {
+                "extends": {
+                    "description": "Presets or configuration files, relative to this file, whose settings this file extends; the later ones take precedence",
+                    "anyOf": [
+                        { "$ref": "#/definitions/extends" },
+                        { "type": "array", "items": { "$ref": "#/definitions/extends" } }
+                    ]
+                },
+                "format": { "$ref": "#/definitions/format" },
+                "color": { "description": "Show colored output", "type": "boolean" },
+                "verbose": { "description": "Verbose output", "type": "boolean" },
+                "quiet_success": { "description": "Hide successful parse messages", "type": "boolean" },
+                "strict": { "$ref": "#/definitions/strict" },
+                "ignore_warnings": { "$ref": "#/definitions/ignore_warnings" },
+                "exclude": {
+                    "description": "Comma-separated glob patterns of the files to exclude",
+                    "type": "string"
+                },
+                "replace_exclude": {
+                    "description": "Replace the default exclusions instead of extending them",
+                    "type": "boolean"
+                },
+                "cache": {
+                    "description": "Enable the parsing cache, or the settings of the cache",
+                    "anyOf": [
+                        { "type": "boolean" },
+                        {
+                            "type": "object",
+                            "additionalProperties": false,
+                            "properties": {
+                                "enabled": { "description": "Enable the parsing cache", "type": "boolean" },
+                                "ttl": {
+                                    "description": "Age after which files are parsed again: days (30d), hours (12h), minutes (45m) or seconds (90s)",
+                                    "type": "string",
+                                    "pattern": "^\\s*[0-9]+[dhms]?\\s*$"
+                                },
+                                "directory": {
+                                    "description": "Directory of the cache file, relative to this file",
+                                    "type": "string"
+                                },
+                                "remote": {
+                                    "description": "HTTP or HTTPS URL of a remote cache shared with other machines",
+                                    "type": "string",
+                                    "pattern": "^https?://"
+                                },
+                                "remote_backend": {
+                                    "description": "Kind of store of the remote cache: an HTTP server, or an S3-compatible store signed with the AWS credentials of the environment",
+                                    "enum": ["http", "s3"]
+                                }
+                            }
+                        }
+                    ]
+                },
+                "threads": {
+                    "description": "Number of threads for parallel processing",
+                    "type": "integer",
+                    "minimum": 1
+                },
+                "include_root": {
+                    "description": "Web root that virtual includes are relative to, relative to this file",
+                    "type": "string"
+                },
+                "virtual_paths": {
+                    "description": "Directories of the virtual directories of the site, relative to this file, by virtual path",
+                    "type": "object",
+                    "additionalProperties": { "type": "string" }
+                },
+                "extensions": {
+                    "description": "Extensions of the files looked for in directories",
+                    "type": "array",
+                    "items": { "type": "string" },
+                    "minItems": 1
+                },
+                "follow_symlinks": {
+                    "description": "Follow symbolic links when looking for files in directories",
+                    "type": "boolean"
+                },
+                "max_depth": {
+                    "description": "Number of directory levels searched, 1 for the files of the given directories only",
+                    "type": "integer",
+                    "minimum": 1
+                },
+                "max_file_size": {
+                    "description": "Size above which the files found in directories are skipped, such as 512K or 10M, or 0 for no limit",
+                    "type": "string",
+                    "pattern": "^\\s*[0-9]+\\s*([kKmMgG]?[bB]?)\\s*$"
+                },
+                "default_encoding": {
+                    "description": "Encoding of the files that are not valid UTF-8, without byte order mark nor CodePage directive: a label such as windows-1252, a Windows code page such as 932, or auto to guess it",
+                    "type": "string"
+                },
+                "mmap": {
+                    "description": "Map the files to parse in memory instead of reading them",
+                    "type": "boolean"
+                },
+                "rules": { "$ref": "#/definitions/rules" },
+                "plugins": {
+                    "description": "Programs providing additional lint rules, relative to this file",
+                    "type": "array",
+                    "items": { "type": "string" }
+                },
+                "override": {
+                    "description": "Settings of the files matching glob patterns, relative to this file",
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "additionalProperties": false,
+                        "properties": {
+                            "strict": { "$ref": "#/definitions/strict" },
+                            "ignore_warnings": { "$ref": "#/definitions/ignore_warnings" },
+                            "format": { "$ref": "#/definitions/format" },
+                            "rules": { "$ref": "#/definitions/rules" }
+                        }
+                    }
+                }
+            },
+            "definitions": {
+                "extends": {
+                    "description": "A preset or a configuration file",
+                    "type": "string",
+                    "anyOf": [
+                        { "enum": presets },
+                        { "pattern": "[/\\\\]|\\.toml$" }
+                    ]
+                },
+                "output_format": {
+                    "description": "Format of the results",
+                    "enum": OUTPUT_FORMATS
+                },
+                "format": {
+                    "description": "Output format, or the settings of the code formatter",
+                    "anyOf": [
+                        { "$ref": "#/definitions/output_format" },
+                        {
+                            "type": "object",
+                            "additionalProperties": false,
+                            "properties": {
+                                "output": { "$ref": "#/definitions/output_format" },
+                                "indent_style": {
+                                    "description": "Indentation with spaces or tabs",
+                                    "enum": ["spaces", "tabs"]
+                                },
+                                "indent_width": {
+                                    "description": "Number of spaces of an indentation level",
+                                    "type": "integer",
+                                    "minimum": 0
+                                },
+                                "keyword_case": {
+                                    "description": "Casing of keywords: pascal (End If) or lower (end if)",
+                                    "enum": ["pascal", "lower"]
+                                },
+                                "max_line_length": {
+                                    "description": "Length beyond which statements are wrapped with line continuations",
+                                    "type": "integer",
+                                    "minimum": 0
+                                },
+                                "align_dims": {
+                                    "description": "Align the continuation lines of declarations with the first name",
+                                    "type": "boolean"
+                                }
+                            }
+                        }
+                    ]
+                },
+                "strict": { "description": "Treat warnings as errors", "type": "boolean" },
+                "ignore_warnings": {
+                    "description": "Warnings to ignore, by code or name",
+                    "type": "array",
+                    "items": { "enum": codes }
+                },
+                "level": {
+                    "description": "Level of the diagnostics of a rule",
+                    "enum": ["off", "notice", "warning", "error"]
+                },
+                "rule": {
+                    "anyOf": [
+                        { "$ref": "#/definitions/level" },
+                        {
+                            "type": "object",
+                            "description": "Level and options of the rule",
+                            "properties": { "level": { "$ref": "#/definitions/level" } }
+                        }
+                    ]
+                },
+                "rules": {
+                    "description": "Settings of the lint rules, by code or name",
+                    "type": "object",
+                    "additionalProperties": false,
+                    "properties": rules
+                }
+            }
+        })
+    }
+
+    /// Returns the patterns of the overrides matching a file, in the order they
+    /// apply
+    pub fn matching_overrides(&self, path: &Path) -> Vec<&str> {
+        let path = absolute(path);
+        self.overrides
+            .iter()
+            .flatten()
+            .map(|(pattern, _)| pattern.as_str())
+            .filter(|pattern| override_matcher(pattern).is_ok_and(|glob| glob.is_match(&path)))
+            .collect()
+    }
+
+    /// Returns the configuration of a file, with the settings of the overrides
+    /// matching it
+    ///
+    /// Overrides apply in the order of their patterns, so that a pattern
+    /// extending another one, such as `admin/legacy/**` after `admin/**`, takes
+    /// precedence.
+    pub fn for_path(&self, path: &Path) -> Config {
+        let mut config = self.clone();
+        for pattern in self.matching_overrides(path) {
+            if let Some(settings) = self.overrides.as_ref().and_then(|o| o.get(pattern)) {
+                config = settings.to_config().merge(&config);
+            }
+        }
+        config
     }
 
     /// Merge configuration options with another config,
     /// where this config's values take precedence over the other
     pub fn merge(&self, other: &Config) -> Config {
         Config {
-            format: self.format.clone().or_else(|| other.format.clone()),
+            extends: self.extends.clone().or_else(|| other.extends.clone()),
+            format: match (&self.format, &other.format) {
+                (Some(ours), Some(theirs)) => Some(ours.merge(theirs)),
+                (ours, theirs) => ours.clone().or_else(|| theirs.clone()),
+            },
             color: self.color.or(other.color),
             verbose: self.verbose.or(other.verbose),
             quiet_success: self.quiet_success.or(other.quiet_success),
@@ -165,8 +1345,268 @@ impl Config {
             },
             exclude: self.exclude.clone().or_else(|| other.exclude.clone()),
             replace_exclude: self.replace_exclude.or(other.replace_exclude),
-            cache: self.cache.or(other.cache),
+            cache: match (&self.cache, &other.cache) {
+                (Some(ours), Some(theirs)) => Some(ours.merge(theirs)),
+                (ours, theirs) => ours.clone().or_else(|| theirs.clone()),
+            },
             threads: self.threads.or(other.threads),
+            include_root: self
+                .include_root
+                .clone()
+                .or_else(|| other.include_root.clone()),
+            virtual_paths: match (&self.virtual_paths, &other.virtual_paths) {
+                (Some(ours), Some(theirs)) => {
+                    let mut merged = theirs.clone();
+                    merged.extend(ours.iter().map(|(k, v)| (k.clone(), v.clone())));
+                    Some(merged)
+                }
+                (ours, theirs) => ours.clone().or_else(|| theirs.clone()),
+            },
+            extensions: self.extensions.clone().or_else(|| other.extensions.clone()),
+            follow_symlinks: self.follow_symlinks.or(other.follow_symlinks),
+            max_depth: self.max_depth.or(other.max_depth),
+            max_file_size: self
+                .max_file_size
+                .clone()
+                .or_else(|| other.max_file_size.clone()),
+            default_encoding: self
+                .default_encoding
+                .clone()
+                .or_else(|| other.default_encoding.clone()),
+            mmap: self.mmap.or(other.mmap),
+            rules: match (&self.rules, &other.rules) {
+                (Some(ours), Some(theirs)) => {
+                    let mut merged = theirs.clone();
+                    for (name, rule) in ours {
+                        let rule = match theirs.get(name) {
+                            Some(their_rule) => rule.merge(their_rule),
+                            None => rule.clone(),
+                        };
+                        merged.insert(name.clone(), rule);
+                    }
+                    Some(merged)
+                }
+                (Some(ours), None) => Some(ours.clone()),
+                (None, Some(theirs)) => Some(theirs.clone()),
+                (None, None) => None,
+            },
+            plugins: match (&self.plugins, &other.plugins) {
+                (Some(ours), Some(theirs)) => {
+                    let mut merged = ours.clone();
+                    merged.extend(
+                        theirs
+                            .iter()
+                            .filter(|plugin| !ours.contains(plugin))
+                            .cloned(),
+                    );
+                    Some(merged)
+                }
+                (Some(ours), None) => Some(ours.clone()),
+                (None, Some(theirs)) => Some(theirs.clone()),
+                (None, None) => None,
+            },
+            overrides: match (&self.overrides, &other.overrides) {
+                (Some(ours), Some(theirs)) => {
+                    let mut merged = theirs.clone();
+                    for (pattern, settings) in ours {
+                        let settings = match theirs.get(pattern) {
+                            Some(their_settings) => settings.merge(their_settings),
+                            None => settings.clone(),
+                        };
+                        merged.insert(pattern.clone(), settings);
+                    }
+                    Some(merged)
+                }
+                (ours, theirs) => ours.clone().or_else(|| theirs.clone()),
+            },
+        }
+    }
+
+    /// Creates the lint rules with the levels and options of the `[rules]` table
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Registry, ConfigError>` - The default rules with their configured
+    ///   severities and options, or an error naming an unknown rule or invalid
+    ///   option
+    pub fn lint_registry(&self) -> Result<Registry, ConfigError> {
+        let mut registry = Registry::default();
+        for plugin in self.plugins.iter().flatten() {
+            if Path::new(plugin)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("wasm"))
+            {
+                return Err(ConfigError::InvalidValue(format!(
+                    "plugin '{}': WebAssembly plugins are not supported, use an executable",
+                    plugin
+                )));
+            }
+            registry.register(Box::new(CommandPlugin::new(plugin)));
+        }
+        let Some(rules) = &self.rules else {
+            return Ok(registry);
+        };
+        // Sorted, so that errors do not depend on the map order
+        let mut names: Vec<&String> = rules.keys().collect();
+        names.sort();
+        for name in names {
+            let rule = &rules[name];
+            let code = DiagnosticCode::from_name(name).ok_or_else(|| {
+                ConfigError::InvalidValue(format!("unknown rule '{}' in [rules]", name))
+            })?;
+            registry
+                .configure(code, &rule.options())
+                .map_err(|e| ConfigError::InvalidValue(format!("rule '{}': {}", name, e)))?;
+            match rule.level().map(|level| level.severity()) {
+                Some(Some(severity)) => registry.set_severity(code, severity),
+                Some(None) => registry.disable(code),
+                // Options given for an optional rule enable it
+                None if registry.severity(code).is_none() && !rule.options().is_empty() => {
+                    registry.set_severity(code, code.default_severity())
+                }
+                None => {}
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Returns the output format, given by `format = "..."` or the `output` key
+    /// of the `[format]` table
+    pub fn output_format(&self) -> Option<String> {
+        self.format
+            .as_ref()
+            .and_then(|format| format.settings().output)
+    }
+
+    /// Returns whether the cache is enabled, given by `cache = ...` or the `enabled`
+    /// key of the `[cache]` table
+    pub fn cache_enabled(&self) -> Option<bool> {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.settings().enabled)
+    }
+
+    /// Returns the age after which cache entries are parsed again, or an error if
+    /// the `ttl` key of the `[cache]` table is not a duration
+    pub fn cache_ttl(&self) -> Result<Option<Duration>, ConfigError> {
+        let Some(ttl) = self.cache.as_ref().and_then(|cache| cache.settings().ttl) else {
+            return Ok(None);
+        };
+        crate::cache::parse_duration(&ttl)
+            .map(Some)
+            .map_err(|e| ConfigError::InvalidValue(format!("cache: ttl: {}", e)))
+    }
+
+    /// Returns the URL and the backend of the remote cache, from the `[cache]`
+    /// table, or an error if the URL is not an HTTP one or the backend is unknown
+    pub fn remote_cache(&self) -> Result<Option<(String, RemoteBackend)>, ConfigError> {
+        let settings = self
+            .cache
+            .as_ref()
+            .map(CacheConfig::settings)
+            .unwrap_or_default();
+        let backend = match &settings.remote_backend {
+            Some(backend) => RemoteBackend::from_str(backend)
+                .map_err(|e| ConfigError::InvalidValue(format!("cache: remote_backend: {}", e)))?,
+            None => RemoteBackend::default(),
+        };
+        let Some(remote) = settings.remote else {
+            return Ok(None);
+        };
+        if !["http://", "https://"]
+            .iter()
+            .any(|scheme| remote.to_lowercase().starts_with(scheme))
+        {
+            return Err(ConfigError::InvalidValue(format!(
+                "cache: remote: expected an http or https URL, got '{}'",
+                remote
+            )));
+        }
+        Ok(Some((remote, backend)))
+    }
+
+    /// Returns how the files that are not valid UTF-8, without byte order mark nor
+    /// `CodePage` directive, are decoded, or an error if `default_encoding` is not
+    /// a known encoding
+    pub fn default_encoding(&self) -> Result<DefaultEncoding, ConfigError> {
+        match &self.default_encoding {
+            Some(encoding) => DefaultEncoding::from_str(encoding)
+                .map_err(|e| ConfigError::InvalidValue(format!("default_encoding: {}", e))),
+            None => Ok(DefaultEncoding::default()),
+        }
+    }
+
+    /// Returns the directory of the cache file, from the `[cache]` table
+    pub fn cache_directory(&self) -> Option<PathBuf> {
+        self.cache
+            .as_ref()
+            .and_then(|cache| cache.settings().directory)
+            .map(PathBuf::from)
+    }
+
+    /// Returns the resolver of the include directives, with the virtual paths of
+    /// the configuration
+    ///
+    /// # Arguments
+    ///
+    /// * `web_root` - The web root given on the command line, taking precedence
+    ///   over `include_root`; the current directory is the web root when neither
+    ///   is given
+    pub fn include_resolver(&self, web_root: Option<&String>) -> IncludeResolver {
+        let web_root = match (web_root, &self.include_root) {
+            (Some(web_root), _) | (None, Some(web_root)) => PathBuf::from(web_root),
+            (None, None) => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+        self.virtual_paths.iter().flatten().fold(
+            IncludeResolver::new().with_web_root(web_root),
+            |resolver, (virtual_path, directory)| {
+                resolver.with_virtual_path(virtual_path, directory)
+            },
+        )
+    }
+
+    /// Returns the extensions of the files looked for in directories, `asp`,
+    /// `vbs` and `asa` by default
+    pub fn scan_extensions(&self) -> Vec<String> {
+        match &self.extensions {
+            Some(extensions) => extensions
+                .iter()
+                .map(|extension| extension.trim_start_matches('.').to_lowercase())
+                .collect(),
+            None => vec!["asp".to_string(), "vbs".to_string(), "asa".to_string()],
+        }
+    }
+
+    /// Returns the options of the search of files in directories, with a maximum
+    /// file size of [`DEFAULT_MAX_FILE_SIZE`] unless configured otherwise, or an
+    /// error if `max_file_size` is not a size
+    pub fn scan_options(&self) -> Result<ScanOptions, ConfigError> {
+        let max_file_size = match &self.max_file_size {
+            Some(size) => parse_size(size)
+                .map_err(|e| ConfigError::InvalidValue(format!("max_file_size: {}", e)))?,
+            None => DEFAULT_MAX_FILE_SIZE,
+        };
+        Ok(ScanOptions {
+            follow_symlinks: self.follow_symlinks.unwrap_or(false),
+            max_depth: self.max_depth,
+            max_file_size: (max_file_size > 0).then_some(max_file_size),
+        })
+    }
+
+    /// Returns the options of the code formatter, from the `[format]` table
+    pub fn format_options(&self) -> FormatOptions {
+        let settings = self
+            .format
+            .as_ref()
+            .map(FormatConfig::settings)
+            .unwrap_or_default();
+        let defaults = FormatOptions::default();
+        FormatOptions {
+            indent_style: settings.indent_style.unwrap_or(defaults.indent_style),
+            indent_width: settings.indent_width.unwrap_or(defaults.indent_width),
+            keyword_case: settings.keyword_case.unwrap_or(defaults.keyword_case),
+            max_line_length: settings.max_line_length.or(defaults.max_line_length),
+            align_dims: settings.align_dims.unwrap_or(defaults.align_dims),
         }
     }
 
@@ -174,8 +1614,8 @@ impl Config {
     /// Only sets values that aren't already set in the arguments
     pub fn apply_to_args(&self, args: &mut HashMap<String, String>) {
         // Only set values if they're not already defined in args
-        if let Some(format) = &self.format {
-            args.entry("format".to_string()).or_insert(format.clone());
+        if let Some(format) = self.output_format() {
+            args.entry("format".to_string()).or_insert(format);
         }
 
         if let Some(color) = self.color {
@@ -216,7 +1656,7 @@ impl Config {
                 .or_insert(value.to_string());
         }
 
-        if let Some(cache) = self.cache {
+        if let Some(cache) = self.cache_enabled() {
             let value = if cache { "true" } else { "false" };
             args.entry("cache".to_string()).or_insert(value.to_string());
         }
@@ -228,6 +1668,83 @@ impl Config {
     }
 }
 
+/// Returns a path from the current directory if it is relative, without `.`
+/// components
+fn absolute(path: &Path) -> PathBuf {
+    let path = match std::env::current_dir() {
+        Ok(current_dir) if path.is_relative() => current_dir.join(path),
+        _ => path.to_path_buf(),
+    };
+    path.components()
+        .filter(|component| *component != std::path::Component::CurDir)
+        .collect()
+}
+
+/// Compiles the glob pattern of an override, matching files as the exclude
+/// patterns do: `*` does not match `/`, while `**` matches any directories
+fn override_matcher(pattern: &str) -> Result<GlobMatcher, globset::Error> {
+    GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+}
+
+/// Returns the problems of the `[format]` table and of the rules of a configuration
+/// file, naming their keys
+fn nested_problems(table: &toml::Table) -> Vec<String> {
+    let mut problems = Vec::new();
+    let overrides = table.get("override").and_then(toml::Value::as_table);
+    for (pattern, settings) in overrides.into_iter().flatten() {
+        if let Some(settings) = settings.as_table() {
+            problems.extend(
+                nested_problems(settings)
+                    .into_iter()
+                    .map(|problem| format!("override '{}': {}", pattern, problem)),
+            );
+        }
+    }
+    if let Some(format @ toml::Value::Table(_)) = table.get("format")
+        && let Err(e) = format.clone().try_into::<FormatSettings>()
+    {
+        problems.push(format!("format: {}", e.message().trim_end()));
+    }
+    if let Some(cache @ toml::Value::Table(_)) = table.get("cache")
+        && let Err(e) = cache.clone().try_into::<CacheSettings>()
+    {
+        problems.push(format!("cache: {}", e.message().trim_end()));
+    }
+    let rules = table.get("rules").and_then(toml::Value::as_table);
+    for (name, rule) in rules.into_iter().flatten() {
+        let level = match rule {
+            toml::Value::Table(options) => options.get("level"),
+            rule => Some(rule),
+        };
+        if let Some(level) = level
+            && let Err(e) = level.clone().try_into::<RuleLevel>()
+        {
+            problems.push(format!(
+                "rules: rule '{}': {}",
+                name,
+                e.message().trim_end()
+            ));
+        }
+    }
+    problems
+}
+
+/// Writes a key of a TOML table, quoted if it is not a bare key
+fn toml_key(key: &str) -> String {
+    if !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        key.to_string()
+    } else {
+        toml::Value::String(key.to_string()).to_string()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -253,7 +1770,7 @@ threads = 4
 
         let config = Config::from_file(file.path()).unwrap();
 
-        assert_eq!(config.format, Some("json".to_string()));
+        assert_eq!(config.output_format(), Some("json".to_string()));
         assert_eq!(config.color, Some(false));
         assert_eq!(config.verbose, Some(true));
         assert_eq!(config.strict, Some(true));
@@ -271,7 +1788,8 @@ threads = 4
     #[test]
     fn test_config_merge() {
         let config1 = Config {
-            format: Some("json".to_string()),
+            extends: None,
+            format: Some(FormatConfig::Output("json".to_string())),
             color: Some(false),
             verbose: None,
             quiet_success: None,
@@ -281,10 +1799,22 @@ threads = 4
             replace_exclude: None,
             cache: None,
             threads: Some(4),
+            include_root: None,
+            virtual_paths: None,
+            extensions: None,
+            follow_symlinks: None,
+            max_depth: None,
+            max_file_size: None,
+            default_encoding: None,
+            mmap: None,
+            rules: None,
+            plugins: None,
+            overrides: None,
         };
 
         let config2 = Config {
-            format: Some("ci".to_string()),
+            extends: None,
+            format: Some(FormatConfig::Output("ci".to_string())),
             color: None,
             verbose: Some(true),
             quiet_success: Some(true),
@@ -292,14 +1822,25 @@ threads = 4
             ignore_warnings: Some(vec!["unused-variable".to_string()]),
             exclude: Some("node_modules".to_string()),
             replace_exclude: None,
-            cache: Some(true),
+            cache: Some(CacheConfig::Enabled(true)),
             threads: Some(8),
+            include_root: None,
+            virtual_paths: None,
+            extensions: None,
+            follow_symlinks: None,
+            max_depth: None,
+            max_file_size: None,
+            default_encoding: None,
+            mmap: None,
+            rules: None,
+            plugins: None,
+            overrides: None,
         };
 
         // config1 takes precedence over config2
         let merged = config1.merge(&config2);
 
-        assert_eq!(merged.format, Some("json".to_string())); // From config1
+        assert_eq!(merged.output_format(), Some("json".to_string())); // From config1
         assert_eq!(merged.color, Some(false)); // From config1
         assert_eq!(merged.verbose, Some(true)); // From config2
         assert_eq!(merged.quiet_success, Some(true)); // From config2
@@ -307,19 +1848,20 @@ threads = 4
 
         // Merged warnings from both
         assert!(merged.ignore_warnings.is_some());
-        let warnings = merged.ignore_warnings.unwrap();
+        let warnings = merged.ignore_warnings.as_ref().unwrap();
         assert!(warnings.contains(&"no-asp-tags".to_string()));
         assert!(warnings.contains(&"unused-variable".to_string()));
 
         assert_eq!(merged.exclude, Some("node_modules".to_string())); // From config2
-        assert_eq!(merged.cache, Some(true)); // From config2
+        assert_eq!(merged.cache_enabled(), Some(true)); // From config2
         assert_eq!(merged.threads, Some(4)); // From config1
     }
 
     #[test]
     fn test_apply_to_args() {
         let config = Config {
-            format: Some("json".to_string()),
+            extends: None,
+            format: Some(FormatConfig::Output("json".to_string())),
             color: Some(false),
             verbose: Some(true),
             quiet_success: None,
@@ -327,8 +1869,19 @@ threads = 4
             ignore_warnings: Some(vec!["no-asp-tags".to_string()]),
             exclude: None,
             replace_exclude: None,
-            cache: Some(true),
+            cache: Some(CacheConfig::Enabled(true)),
             threads: Some(4),
+            include_root: None,
+            virtual_paths: None,
+            extensions: None,
+            follow_symlinks: None,
+            max_depth: None,
+            max_file_size: None,
+            default_encoding: None,
+            mmap: None,
+            rules: None,
+            plugins: None,
+            overrides: None,
         };
 
         let mut args = HashMap::new();
@@ -351,9 +1904,9 @@ threads = 4
         );
 
         // These weren't in config, so shouldn't be in args
-        assert!(args.get("quiet-success").is_none());
-        assert!(args.get("exclude").is_none());
-        assert!(args.get("replace-exclude").is_none());
+        assert!(!args.contains_key("quiet-success"));
+        assert!(!args.contains_key("exclude"));
+        assert!(!args.contains_key("replace-exclude"));
 
         // Should be set from config
         assert_eq!(args.get("cache"), Some(&"true".to_string()));
@@ -375,5 +1928,605 @@ threads = 4
         assert!(config_str.contains("# replace_exclude ="));
         assert!(config_str.contains("# cache ="));
         assert!(config_str.contains("# threads ="));
+        assert!(config_str.contains("# [rules]"));
+    }
+
+    #[test]
+    fn test_rules_config() {
+        let general: Config = toml::from_str(
+            r#"
+[rules]
+unused-variable = "off"
+ASP023 = { level = "error", max_complexity = 15 }
+naming-convention = { variables = "hungarian" }
+"#,
+        )
+        .unwrap();
+        let specific: Config = toml::from_str(
+            r#"
+[rules]
+ASP023 = { max_complexity = 20 }
+undeclared-name = "warning"
+"#,
+        )
+        .unwrap();
+
+        let merged = specific.merge(&general);
+        let rules = merged.rules.as_ref().unwrap();
+        assert_eq!(rules["unused-variable"], RuleConfig::Level(RuleLevel::Off));
+        assert_eq!(rules["ASP023"].level(), Some(RuleLevel::Error));
+        assert_eq!(
+            rules["ASP023"].options()["max_complexity"].as_integer(),
+            Some(20)
+        );
+
+        let registry = merged.lint_registry().unwrap();
+        assert_eq!(registry.severity(DiagnosticCode::UnusedVariable), None);
+        assert_eq!(
+            registry.severity(DiagnosticCode::UndeclaredName),
+            Some(Severity::Warning)
+        );
+        assert_eq!(
+            registry.severity(DiagnosticCode::ComplexProcedure),
+            Some(Severity::Error)
+        );
+        assert_eq!(
+            registry.severity(DiagnosticCode::NamingConvention),
+            Some(Severity::Notice)
+        );
+    }
+
+    #[test]
+    fn test_rules_config_errors() {
+        let config: Config = toml::from_str("[rules]\nunknown-rule = \"off\"").unwrap();
+        assert!(
+            config
+                .lint_registry()
+                .err()
+                .unwrap()
+                .to_string()
+                .contains("unknown-rule")
+        );
+
+        let config: Config =
+            toml::from_str("[rules]\ncomplex-procedure = { max_depth = 3 }").unwrap();
+        assert!(
+            config
+                .lint_registry()
+                .err()
+                .unwrap()
+                .to_string()
+                .contains("max_depth")
+        );
+
+        assert!(toml::from_str::<Config>("[rules]\nunused-variable = \"loud\"").is_err());
+    }
+
+    #[test]
+    fn test_overrides_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asp-parser.toml");
+        fs::write(
+            &path,
+            r#"
+strict = true
+[rules]
+unused-variable = "error"
+complex-procedure = { max_complexity = 15 }
+
+[override."admin/**"]
+strict = false
+rules = { unused-variable = "off", complex-procedure = { level = "warning" } }
+
+[override."./admin/legacy/**".format]
+indent_width = 2
+"#,
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+
+        let page = dir.path().join("admin/legacy/page.asp");
+        assert_eq!(config.matching_overrides(&page).len(), 2);
+        let page_config = config.for_path(&page);
+        assert_eq!(page_config.strict, Some(false));
+        assert_eq!(page_config.format_options().indent_width, 2);
+        let rules = page_config.rules.unwrap();
+        assert_eq!(rules["unused-variable"].level(), Some(RuleLevel::Off));
+        // Rule options are merged with those of the `[rules]` table
+        assert_eq!(rules["complex-procedure"].level(), Some(RuleLevel::Warning));
+        assert_eq!(
+            rules["complex-procedure"].options()["max_complexity"].as_integer(),
+            Some(15)
+        );
+
+        // `*` does not match across directories, and other files are unchanged
+        let config = config.merge(&Config::default());
+        assert_eq!(
+            config.matching_overrides(&dir.path().join("admin/page.asp")),
+            vec![format!("{}/admin/**", dir.path().display())]
+        );
+        let page = dir.path().join("site/admin.asp");
+        assert!(config.matching_overrides(&page).is_empty());
+        assert_eq!(config.for_path(&page).strict, Some(true));
+
+        fs::write(&path, "[override.\"admin/[\"]\nstrict = true").unwrap();
+        assert!(matches!(
+            Config::from_file(&path),
+            Err(ConfigError::InvalidValue(_))
+        ));
+        fs::write(
+            &path,
+            "[override.\"admin/**\"]\nrules = { no-such-rule = \"off\", unused-variable = \"loud\" }",
+        )
+        .unwrap();
+        let problems = Config::check_file(&path).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("override 'admin/**': rules: rule 'unused-variable'"));
+        fs::write(
+            &path,
+            "[override.\"admin/**\"]\nrules = { no-such-rule = \"off\" }",
+        )
+        .unwrap();
+        let problems = Config::check_file(&path).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("override 'admin/**': rules: "));
+    }
+
+    #[test]
+    fn test_extends_config() {
+        for (name, _) in PRESETS {
+            let preset = Config::preset(name).expect("Presets should be valid");
+            assert!(preset.lint_registry().is_ok(), "{}", name);
+        }
+        assert!(Config::preset("lenient").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("shared")).unwrap();
+        fs::create_dir(dir.path().join("site")).unwrap();
+        fs::write(
+            dir.path().join("shared/base.toml"),
+            "format = \"json\"\nplugins = [\"rules/house\"]\n[rules]\nunused-variable = \"off\"\ncomplex-procedure = { max_complexity = 15 }",
+        )
+        .unwrap();
+        let path = dir.path().join("site/asp-parser.toml");
+        fs::write(
+            &path,
+            "extends = [\"strict\", \"../shared/base.toml\"]\n[rules]\ncomplex-procedure = \"error\"",
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+        // The file takes precedence over the files it extends, which take
+        // precedence over the presets before them
+        assert_eq!(config.strict, Some(true));
+        assert_eq!(config.output_format(), Some("json".to_string()));
+        let rules = config.rules.as_ref().unwrap();
+        assert_eq!(rules["unused-variable"].level(), Some(RuleLevel::Off));
+        assert_eq!(rules["unreachable-code"].level(), Some(RuleLevel::Error));
+        assert_eq!(rules["complex-procedure"].level(), Some(RuleLevel::Error));
+        assert_eq!(rules["complex-procedure"].options().len(), 1);
+        // Paths stay relative to the file setting them
+        assert_eq!(
+            Path::new(&config.plugins.unwrap()[0]),
+            dir.path().join("site/../shared/rules/house")
+        );
+
+        // Each preset and extended file is a layer of its own, before the file
+        let layers = Config::from_file_layers(&path).unwrap();
+        let sources: Vec<PathBuf> = layers.iter().map(|(path, _)| path.clone()).collect();
+        assert_eq!(
+            sources,
+            [
+                PathBuf::from("preset 'strict'"),
+                dir.path().join("site/../shared/base.toml"),
+                path.clone(),
+            ]
+        );
+        assert_eq!(layers[2].1.strict, None);
+
+        fs::write(&path, "extends = \"lenient\"").unwrap();
+        let error = Config::from_file(&path).err().unwrap().to_string();
+        assert!(error.contains("unknown preset 'lenient'"), "{}", error);
+        fs::write(&path, "extends = \"missing.toml\"").unwrap();
+        let error = Config::from_file(&path).err().unwrap().to_string();
+        assert!(error.contains("extends 'missing.toml'"), "{}", error);
+        fs::write(
+            dir.path().join("shared/base.toml"),
+            "extends = \"../site/asp-parser.toml\"",
+        )
+        .unwrap();
+        fs::write(&path, "extends = \"../shared/base.toml\"").unwrap();
+        let error = Config::from_file(&path).err().unwrap().to_string();
+        assert!(error.contains("circular extension"), "{}", error);
+    }
+
+    #[test]
+    fn test_config_json_schema() {
+        let schema = Config::json_schema();
+        // Every key of the files is described
+        let mut properties: Vec<&str> = schema["properties"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut keys = KEYS.to_vec();
+        properties.sort();
+        keys.sort();
+        assert_eq!(properties, keys);
+
+        let rules = &schema["definitions"]["rules"]["properties"];
+        for rule in Registry::default().rules() {
+            assert!(rules[rule.code().name()].is_object(), "{}", rule.code());
+            assert!(rules[rule.code().as_str()].is_object(), "{}", rule.code());
+        }
+        assert!(rules["unused-variable"]["description"].is_string());
+        let warnings = schema["definitions"]["ignore_warnings"]["items"]["enum"]
+            .as_array()
+            .unwrap();
+        assert!(warnings.contains(&serde_json::json!("no-asp-tags")));
+        assert!(warnings.contains(&serde_json::json!("ASP005")));
+        assert_eq!(
+            schema["definitions"]["extends"]["anyOf"][0]["enum"],
+            serde_json::json!(["recommended", "strict", "security"])
+        );
+    }
+
+    #[test]
+    fn test_env_config() {
+        let vars = |vars: &[(&str, &str)]| {
+            Config::from_env_vars(
+                vars.iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string())),
+            )
+        };
+        let config = vars(&[
+            ("ASP_PARSER_FORMAT", "json"),
+            ("ASP_PARSER_STRICT", "TRUE"),
+            ("ASP_PARSER_COLOR", "0"),
+            ("ASP_PARSER_THREADS", " 4 "),
+            ("ASP_PARSER_IGNORE_WARNINGS", "no-asp-tags, empty-file,"),
+            ("ASP_PARSER_CACHE_TTL", "2h"),
+            ("ASP_PARSER_CACHE_REMOTE", "https://cache.example.com"),
+            ("ASP_PARSER_EXCLUDE", ""),
+            ("ASP_PARSER_UNKNOWN", "x"),
+            ("PATH", "/usr/bin"),
+        ])
+        .unwrap();
+        assert_eq!(config.output_format(), Some("json".to_string()));
+        assert_eq!(config.strict, Some(true));
+        assert_eq!(config.color, Some(false));
+        assert_eq!(config.threads, Some(4));
+        assert_eq!(
+            config.ignore_warnings,
+            Some(vec!["no-asp-tags".to_string(), "empty-file".to_string()])
+        );
+        assert_eq!(config.cache_ttl().unwrap(), Some(Duration::from_secs(7200)));
+        assert_eq!(
+            config.remote_cache().unwrap(),
+            Some(("https://cache.example.com".to_string(), RemoteBackend::Http))
+        );
+        assert_eq!(config.exclude, None);
+        assert!(vars(&[("PATH", "/usr/bin")]).unwrap().is_empty());
+
+        // The variables take precedence over the files, setting by setting
+        let file = Config {
+            strict: Some(false),
+            verbose: Some(true),
+            cache: Some(CacheConfig::Enabled(false)),
+            ..Config::default()
+        };
+        let merged = config.merge(&file);
+        assert_eq!(merged.strict, Some(true));
+        assert_eq!(merged.verbose, Some(true));
+        assert_eq!(merged.cache_enabled(), Some(false));
+
+        let error = vars(&[("ASP_PARSER_THREADS", "many")]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("ASP_PARSER_THREADS: expected a number"),
+            "{}",
+            error
+        );
+        let error = vars(&[("ASP_PARSER_STRICT", "maybe")]).unwrap_err();
+        assert!(error.to_string().contains("got 'maybe'"), "{}", error);
+    }
+
+    #[test]
+    fn test_default_encoding_config() {
+        assert_eq!(
+            Config::default().default_encoding().unwrap(),
+            DefaultEncoding::Latin1
+        );
+        let config: Config = toml::from_str("default_encoding = \"Shift_JIS\"").unwrap();
+        assert_eq!(
+            config.default_encoding().unwrap(),
+            DefaultEncoding::Encoding(encoding_rs::SHIFT_JIS)
+        );
+        let env = Config::from_env_vars([(
+            "ASP_PARSER_DEFAULT_ENCODING".to_string(),
+            "auto".to_string(),
+        )])
+        .unwrap();
+        assert_eq!(
+            env.merge(&config).default_encoding().unwrap(),
+            DefaultEncoding::Detect
+        );
+
+        let config: Config = toml::from_str("default_encoding = \"klingon\"").unwrap();
+        assert_eq!(
+            config.default_encoding().unwrap_err().to_string(),
+            "Invalid configuration value: default_encoding: unknown encoding 'klingon'"
+        );
+    }
+
+    #[test]
+    fn test_scan_options_config() {
+        assert_eq!(
+            Config::default().scan_options().unwrap(),
+            ScanOptions {
+                max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+                ..ScanOptions::default()
+            }
+        );
+        let config: Config =
+            toml::from_str("follow_symlinks = true\nmax_depth = 2\nmax_file_size = \"512K\"")
+                .unwrap();
+        assert_eq!(
+            config.scan_options().unwrap(),
+            ScanOptions {
+                follow_symlinks: true,
+                max_depth: Some(2),
+                max_file_size: Some(512 * 1024),
+            }
+        );
+        let env =
+            Config::from_env_vars([("ASP_PARSER_MAX_FILE_SIZE".to_string(), "0".to_string())])
+                .unwrap();
+        assert_eq!(
+            env.merge(&config).scan_options().unwrap().max_file_size,
+            None
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asp-parser.toml");
+        fs::write(&path, "max_depth = 0\nmax_file_size = \"huge\"").unwrap();
+        assert_eq!(
+            Config::check_file(&path).unwrap(),
+            [
+                "max_depth: must be at least 1",
+                "max_file_size: invalid size 'huge', expected e.g. 512K, 10M or 1G"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cache_and_paths_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asp-parser.toml");
+        fs::write(
+            &path,
+            "threads = 2\ninclude_root = \"www\"\nextensions = [\".ASP\", \"inc\"]\n\n[cache]\nttl = \"12h\"\ndirectory = \".cache\"\n\n[virtual_paths]\n\"/lib\" = \"shared/lib\"",
+        )
+        .unwrap();
+        let config = Config::from_file(&path).unwrap();
+        assert_eq!(config.threads, Some(2));
+        assert_eq!(config.cache_enabled(), None);
+        assert_eq!(
+            config.cache_ttl().unwrap(),
+            Some(Duration::from_secs(12 * 3600))
+        );
+        assert_eq!(config.cache_directory(), Some(dir.path().join(".cache")));
+        assert_eq!(config.scan_extensions(), vec!["asp", "inc"]);
+        assert_eq!(
+            Config::default().scan_extensions(),
+            vec!["asp", "vbs", "asa"]
+        );
+
+        // Include paths are relative to the file, the web root given on the
+        // command line taking precedence over `include_root`
+        let page = dir.path().join("www/index.asp");
+        let directives = crate::includes::directives(
+            "<!--#include virtual=\"/lib/db.asp\" --><!--#include virtual=\"/inc/menu.asp\" -->",
+        );
+        let resolver = config.include_resolver(None);
+        assert_eq!(
+            resolver.resolve(&page, &directives[0]),
+            Some(dir.path().join("shared/lib/db.asp"))
+        );
+        assert_eq!(
+            resolver.resolve(&page, &directives[1]),
+            Some(dir.path().join("www/inc/menu.asp"))
+        );
+        let resolver = config.include_resolver(Some(&"/srv".to_string()));
+        assert_eq!(
+            resolver.resolve(&page, &directives[1]),
+            Some(PathBuf::from("/srv/inc/menu.asp"))
+        );
+
+        // The cache can also be a boolean, merged setting by setting with a table
+        let config = Config {
+            cache: Some(CacheConfig::Enabled(false)),
+            ..Config::default()
+        }
+        .merge(&Config::from_file(&path).unwrap());
+        assert_eq!(config.cache_enabled(), Some(false));
+        assert!(config.cache_directory().is_some());
+
+        fs::write(&path, "[cache]\nttl = \"soon\"").unwrap();
+        let error = Config::from_file(&path).unwrap().cache_ttl().unwrap_err();
+        assert!(error.to_string().contains("cache: ttl"), "{}", error);
+        fs::write(&path, "[cache]\nsize = 10").unwrap();
+        assert!(Config::from_file(&path).is_err());
+
+        // The remote cache is an HTTP URL, with a known backend
+        fs::write(
+            &path,
+            "[cache]\nremote = \"https://s3.example.com/bucket\"\nremote_backend = \"S3\"",
+        )
+        .unwrap();
+        assert_eq!(
+            Config::from_file(&path).unwrap().remote_cache().unwrap(),
+            Some((
+                "https://s3.example.com/bucket".to_string(),
+                RemoteBackend::S3
+            ))
+        );
+        fs::write(&path, "[cache]\nremote = \"s3://bucket\"").unwrap();
+        let error = Config::from_file(&path)
+            .unwrap()
+            .remote_cache()
+            .unwrap_err();
+        assert!(error.to_string().contains("cache: remote"), "{}", error);
+        fs::write(&path, "[cache]\nremote_backend = \"ftp\"").unwrap();
+        let error = Config::from_file(&path)
+            .unwrap()
+            .remote_cache()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("cache: remote_backend"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_plugins_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("asp-parser.toml");
+        fs::write(&path, "plugins = [\"rules/house\", \"/opt/rules/shared\"]").unwrap();
+        let config = Config::from_file(&path).unwrap();
+        let plugins = config.plugins.clone().unwrap();
+        assert_eq!(Path::new(&plugins[0]), dir.path().join("rules/house"));
+        assert_eq!(plugins[1], "/opt/rules/shared");
+        assert!(config.lint_registry().is_ok());
+
+        let merged = Config {
+            plugins: Some(vec!["/opt/rules/shared".to_string(), "local".to_string()]),
+            ..Config::default()
+        }
+        .merge(&config);
+        assert_eq!(merged.plugins.unwrap().len(), 3);
+
+        let config: Config = toml::from_str("plugins = [\"rules.wasm\"]").unwrap();
+        assert!(
+            config
+                .lint_registry()
+                .err()
+                .unwrap()
+                .to_string()
+                .contains("WebAssembly")
+        );
+    }
+
+    #[test]
+    fn test_format_config() {
+        let config: Config = toml::from_str(
+            r#"
+[format]
+output = "json"
+indent_style = "tabs"
+keyword_case = "lower"
+max_line_length = 100
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.output_format(), Some("json".to_string()));
+        let options = config.format_options();
+        assert_eq!(options.indent_style, IndentStyle::Tabs);
+        assert_eq!(options.indent_width, 4);
+        assert_eq!(options.keyword_case, KeywordCase::Lower);
+        assert_eq!(options.max_line_length, Some(100));
+        assert!(!options.align_dims);
+
+        // Closer files override the settings they set, even with the output
+        // format alone
+        let closer: Config = toml::from_str("[format]\nmax_line_length = 80").unwrap();
+        let options = closer.merge(&config).format_options();
+        assert_eq!(options.max_line_length, Some(80));
+        assert_eq!(options.indent_style, IndentStyle::Tabs);
+        let closer: Config = toml::from_str("format = \"ci\"").unwrap();
+        let merged = closer.merge(&config);
+        assert_eq!(merged.output_format(), Some("ci".to_string()));
+        assert_eq!(merged.format_options().keyword_case, KeywordCase::Lower);
+
+        assert_eq!(Config::default().format_options(), FormatOptions::default());
+        assert!(toml::from_str::<Config>("[format]\nindent = 2").is_err());
+        assert!(toml::from_str::<Config>("[format]\nkeyword_case = \"upper\"").is_err());
+    }
+
+    #[test]
+    fn test_config_check_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+format = "xml"
+strictness = true
+ignore_warnings = ["ASP005", "no-such-warning"]
+threads = 0
+exclude = "backup/**,[abc"
+
+[rules.unused-variable]
+max_count = 3
+"#
+        )
+        .unwrap();
+        let problems = Config::check_file(file.path()).unwrap();
+        assert_eq!(problems.len(), 6, "{:?}", problems);
+        assert_eq!(problems[0], "unknown key 'strictness'");
+        assert!(problems[1].starts_with("format: unknown output format 'xml'"));
+        assert_eq!(
+            problems[2],
+            "ignore_warnings: unknown warning 'no-such-warning'"
+        );
+        assert_eq!(problems[3], "threads: must be at least 1");
+        assert!(problems[4].starts_with("exclude: invalid exclude pattern '[abc'"));
+        assert!(problems[5].starts_with("rules: "));
+
+        // Values of the wrong type are reported with their key or line
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[format]\nindent_style = \"round\"").unwrap();
+        let problems = Config::check_file(file.path()).unwrap();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("format: unknown variant `round`"));
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "[rules]\nunused-variable = \"fatal\"").unwrap();
+        let problems = Config::check_file(file.path()).unwrap();
+        assert!(problems[0].starts_with("rules: rule 'unused-variable': unknown variant"));
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "strict = true\n\nthreads = \"four\"").unwrap();
+        let problems = Config::check_file(file.path()).unwrap();
+        assert!(problems[0].starts_with("line 3: "), "{}", problems[0]);
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "format = \"json\"\nstrict = true").unwrap();
+        assert!(Config::check_file(file.path()).unwrap().is_empty());
+        assert!(Config::check_file(Path::new("no/such/file.toml")).is_err());
+    }
+
+    #[test]
+    fn test_config_describe_merged() {
+        let root: Config =
+            toml::from_str("format = \"json\"\nstrict = true\n[rules]\nunused-variable = \"off\"")
+                .unwrap();
+        let closer: Config = toml::from_str("strict = false\n[format]\nindent_width = 2").unwrap();
+        let description = Config::describe_merged(&[
+            (PathBuf::from("root.toml"), root),
+            (PathBuf::from("site/closer.toml"), closer),
+        ]);
+        assert!(description.starts_with(
+            "# Configuration files, closest first:\n#   site/closer.toml\n#   root.toml\n"
+        ));
+        assert!(description.contains("\nstrict = false # from site/closer.toml, root.toml\n"));
+        assert!(description.contains("\n[format]\n"));
+        assert!(description.contains("\noutput = \"json\" # from root.toml\n"));
+        assert!(description.contains("\nindent_width = 2 # from site/closer.toml\n"));
+        assert!(description.contains("\n[rules]\nunused-variable = \"off\" # from root.toml\n"));
+
+        assert_eq!(
+            Config::describe_merged(&[]),
+            "# No configuration file found\n"
+        );
     }
 }
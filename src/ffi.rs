@@ -0,0 +1,135 @@
+//! C API
+//!
+//! The library is also built as a C dynamic library (`libasp_classic_parser.so`,
+//! `asp_classic_parser.dll` or `libasp_classic_parser.dylib`), so that tools written
+//! in other languages (.NET, Delphi, ...) can check pages without spawning the
+//! command-line tool. The functions are declared in `include/asp_classic_parser.h`.
+//!
+//! Results are returned as JSON strings allocated by the library, which must be
+//! released with [`asp_free_string`]:
+//!
+//! ```json
+//! {"file": "page.asp", "success": false, "diagnostics": [
+//!   {"line": 3, "column": 1, "code": "ASP002", "message": "...", "severity": "error"}
+//! ]}
+//! ```
+//!
+//! Diagnostics have the fields of the JSON output format. When a file cannot be
+//! read, or the parser fails unexpectedly, `success` is false and an `error` field
+//! describes the problem; panics never unwind into the caller.
+
+use std::ffi::{CStr, CString, c_char};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+
+use serde_json::json;
+
+use crate::file_utils::read_file_with_encoding;
+use crate::output_format::diagnostic_json;
+use crate::parser::{self, ParseOptions};
+
+/// Parses a file and returns the result as a JSON string
+///
+/// Returns a null pointer if `path` is null or not valid UTF-8.
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string. The returned string
+/// must be released with [`asp_free_string`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn asp_parse_file(path: *const c_char) -> *mut c_char {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees that `path` is a NUL-terminated string
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+        return ptr::null_mut();
+    };
+    let mut result = guarded(|| match read_file_with_encoding(Path::new(path)) {
+        Ok(source) => parse_result(&source),
+        Err(error) => json!({
+            "success": false,
+            "diagnostics": [],
+            "error": format!("Cannot read file: {}", error),
+        }),
+    });
+    result["file"] = json!(path);
+    into_c_string(result.to_string())
+}
+
+/// Parses ASP source code and returns the result as a JSON string, without a
+/// `file` field
+///
+/// Invalid UTF-8 sequences of the source are replaced. Returns a null pointer if
+/// `source` is null.
+///
+/// # Safety
+///
+/// `source` must be null or point to a NUL-terminated string. The returned string
+/// must be released with [`asp_free_string`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn asp_parse_source(source: *const c_char) -> *mut c_char {
+    if source.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees that `source` is a NUL-terminated string
+    let source = unsafe { CStr::from_ptr(source) }.to_string_lossy();
+    into_c_string(guarded(|| parse_result(&source)).to_string())
+}
+
+/// Releases a string returned by the library
+///
+/// Does nothing if `string` is null.
+///
+/// # Safety
+///
+/// `string` must be null or a string returned by this library that was not
+/// released yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn asp_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        // SAFETY: the string was allocated by `CString::into_raw` in this library
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Returns the version of the library, as a static string that must not be released
+#[unsafe(no_mangle)]
+pub extern "C" fn asp_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast()
+}
+
+/// Returns the `success` and `diagnostics` fields of the result of a page
+fn parse_result(source: &str) -> serde_json::Value {
+    let diagnostics = parser::parse_with_options(source, &ParseOptions::default())
+        .err()
+        .unwrap_or_default();
+    json!({
+        "success": diagnostics.is_empty(),
+        "diagnostics": diagnostics.iter().map(diagnostic_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Returns the result of `parse`, or a result with an `error` field if it panics,
+/// as unwinding out of an `extern "C"` function aborts the caller
+fn guarded(parse: impl FnOnce() -> serde_json::Value) -> serde_json::Value {
+    panic::catch_unwind(AssertUnwindSafe(parse)).unwrap_or_else(|payload| {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        json!({
+            "success": false,
+            "diagnostics": [],
+            "error": format!("Internal parser error: {}", message),
+        })
+    })
+}
+
+/// Hands a string over to the caller
+fn into_c_string(string: String) -> *mut c_char {
+    // JSON escapes control characters, so the string has no interior NUL byte
+    CString::new(string).map_or(ptr::null_mut(), CString::into_raw)
+}
@@ -1,6 +1,903 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs;
-use std::io::{self, Read};
+use std::io;
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chardetng::EncodingDetector;
+use encoding_rs::EncoderResult;
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::parser::parse_directives;
+
+/// Default patterns to exclude from file search
+pub fn default_exclude_patterns() -> Vec<String> {
+    vec![
+        // Version control systems
+        ".git".to_string(),
+        ".svn".to_string(),
+        ".hg".to_string(),
+        ".bzr".to_string(),
+        // IDE and editor files
+        ".idea".to_string(),
+        ".vscode".to_string(),
+        ".vs".to_string(),
+        // Build artifacts and dependencies
+        "node_modules".to_string(),
+        "vendor".to_string(),
+        "dist".to_string(),
+        "target".to_string(),
+        "build".to_string(),
+        "_build".to_string(),
+        // Package manager directories
+        "bower_components".to_string(),
+        "jspm_packages".to_string(),
+        // Other common directories to exclude
+        "coverage".to_string(),
+        "logs".to_string(),
+        "tmp".to_string(),
+        "temp".to_string(),
+    ]
+}
+
+/// Helper function to find ASP, VBScript and `global.asa` files recursively, respecting
+/// exclude patterns
+pub fn find_asp_files(dir: &Path, exclude_patterns: &[String]) -> io::Result<Vec<PathBuf>> {
+    find_files_with_extensions(dir, exclude_patterns, &["asp", "vbs", "asa"])
+}
+
+/// Maximum size of the files found in directories, unless configured otherwise:
+/// larger files are usually generated, and slow to parse for little benefit
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Options of the search of files in directories
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Follow symbolic links to files and directories; otherwise they are ignored.
+    /// A directory reached again through a link is only searched once
+    pub follow_symlinks: bool,
+    /// Number of directory levels searched: 1 for the files of the searched
+    /// directory only, all of them if `None`
+    pub max_depth: Option<usize>,
+    /// Size in bytes above which files are skipped, without limit if `None`
+    pub max_file_size: Option<u64>,
+}
+
+/// Files found in directories by [`scan_files`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScannedFiles {
+    /// Files with one of the extensions, not excluded
+    pub files: Vec<PathBuf>,
+    /// Files skipped for being larger than the maximum file size, with their size
+    pub too_large: Vec<(PathBuf, u64)>,
+}
+
+/// Finds the files with one of the given extensions recursively, ignoring case and
+/// respecting exclude patterns
+///
+/// Symbolic links are not followed, and files of any size are found; see
+/// [`scan_files`] for the other options.
+pub fn find_files_with_extensions(
+    dir: &Path,
+    exclude_patterns: &[String],
+    extensions: &[&str],
+) -> io::Result<Vec<PathBuf>> {
+    scan_files(dir, exclude_patterns, extensions, &ScanOptions::default())
+        .map(|scanned| scanned.files)
+}
+
+/// Finds the files with one of the given extensions recursively, ignoring case,
+/// respecting exclude patterns and the scan options
+///
+/// Exclude patterns are globs matched against the paths relative to `dir`, as
+/// described in [`ExcludePatterns`]; an invalid pattern is an
+/// [`InvalidInput`](io::ErrorKind::InvalidInput) error.
+pub fn scan_files(
+    dir: &Path,
+    exclude_patterns: &[String],
+    extensions: &[&str],
+    options: &ScanOptions,
+) -> io::Result<ScannedFiles> {
+    // Check for empty dirs early to avoid problems
+    if !dir.exists() || !dir.is_dir() {
+        return Ok(ScannedFiles::default());
+    }
+
+    let mut scanned = ScannedFiles::default();
+
+    // Prepare all exclusion patterns
+    let mut all_exclude_patterns = Vec::new();
+
+    // Check if the special --replace-exclude flag is present
+    let replace_defaults = exclude_patterns.contains(&"--replace-exclude".to_string());
+
+    // Add default exclusions if we're not replacing them
+    if !replace_defaults {
+        all_exclude_patterns.extend(default_exclude_patterns());
+    }
+
+    // Add custom exclusion patterns (except the special flag)
+    all_exclude_patterns.extend(
+        exclude_patterns
+            .iter()
+            .filter(|&p| p != "--replace-exclude")
+            .cloned(),
+    );
+    let exclude = ExcludePatterns::new(&all_exclude_patterns)?;
+
+    // Find all the files using a simpler, more direct approach
+    find_files_simple(dir, &mut scanned, &exclude, extensions, options)?;
+
+    Ok(scanned)
+}
+
+/// A simpler implementation to find ASP/VBS files that works reliably cross-platform
+fn find_files_simple(
+    dir: &Path,
+    scanned: &mut ScannedFiles,
+    exclude: &ExcludePatterns,
+    extensions: &[&str],
+    options: &ScanOptions,
+) -> io::Result<()> {
+    // Stack for iterative directory traversal (more reliable than recursion), with
+    // the depth of the directories
+    let mut dirs_to_process = vec![(dir.to_path_buf(), 0)];
+    // Directories already searched, to stop at symbolic link loops
+    let mut visited = HashSet::new();
+    if options.follow_symlinks {
+        visited.insert(fs::canonicalize(dir)?);
+    }
+
+    while let Some((current_dir, depth)) = dirs_to_process.pop() {
+        let depth = depth + 1;
+        if options.max_depth.is_some_and(|max_depth| depth > max_depth) {
+            continue;
+        }
+        // Process entries in this directory
+        if let Ok(entries) = fs::read_dir(&current_dir) {
+            // Use flatten() to simplify handling of Result<DirEntry>
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let relative = path.strip_prefix(dir).unwrap_or(&path);
+
+                let Ok(file_type) = entry.file_type() else {
+                    continue;
+                };
+                let metadata = if !file_type.is_symlink() {
+                    entry.metadata()
+                } else if options.follow_symlinks {
+                    fs::metadata(&path)
+                } else {
+                    continue;
+                };
+                // Broken links and entries removed meanwhile are ignored
+                let Ok(metadata) = metadata else {
+                    continue;
+                };
+
+                if metadata.is_dir() {
+                    // Files of excluded directories can only be included again by
+                    // a negated pattern
+                    if exclude.is_excluded(relative, true) && !exclude.has_negations() {
+                        continue;
+                    }
+                    if options.follow_symlinks
+                        && !fs::canonicalize(&path).is_ok_and(|target| visited.insert(target))
+                    {
+                        continue;
+                    }
+                    dirs_to_process.push((path, depth));
+                } else if has_extension(&path, extensions) && !exclude.is_excluded(relative, false)
+                {
+                    // Add ASP/VBS files that aren't excluded nor too large
+                    match options.max_file_size {
+                        Some(max_size) if metadata.len() > max_size => {
+                            scanned.too_large.push((path, metadata.len()))
+                        }
+                        _ => scanned.files.push(path),
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a size in bytes, with an optional `K`, `M` or `G` suffix for kibibytes,
+/// mebibytes or gibibytes (e.g. `512K`, `10MB`)
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let number = value.strip_suffix(['B', 'b']).unwrap_or(value);
+    let (number, unit) = match number.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&number[..index], 1024),
+        Some((index, 'm' | 'M')) => (&number[..index], 1024 * 1024),
+        Some((index, 'g' | 'G')) => (&number[..index], 1024 * 1024 * 1024),
+        _ => (number, 1),
+    };
+    number
+        .trim()
+        .parse::<u64>()
+        .map(|number| number.saturating_mul(unit))
+        .map_err(|_| format!("invalid size '{}', expected e.g. 512K, 10M or 1G", value))
+}
+
+/// Check if a path has one of the given extensions, ignoring case
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    if let Some(ext) = path.extension() {
+        let ext_str = ext.to_string_lossy().to_lowercase();
+        extensions.contains(&ext_str.as_str())
+    } else {
+        false
+    }
+}
+
+/// Exclude patterns of the file search, with glob semantics
+///
+/// - `*` matches any characters but `/`, `?` a single one, and `**` any number
+///   of directories; `\` separators are read as `/`
+/// - A pattern without `/`, such as `node_modules` or `*.bak`, matches the files
+///   and directories of that name at any depth
+/// - A pattern with a `/`, such as `old_code/**`, matches paths relative to the
+///   searched directory, or to any of its subdirectories unless it starts with `/`
+/// - A pattern ending with `/` only matches directories
+/// - Files in a matched directory are excluded with it
+/// - A pattern starting with `!` includes again the paths it matches, when it
+///   comes after the pattern excluding them
+#[derive(Debug, Clone)]
+pub struct ExcludePatterns {
+    patterns: Vec<ExcludePattern>,
+}
+
+/// A pattern of [`ExcludePatterns`]
+#[derive(Debug, Clone)]
+struct ExcludePattern {
+    /// Globs of the pattern, matching the name of a path or the path itself
+    globs: Vec<GlobMatcher>,
+    /// Whether the globs match the name of a path rather than the whole path
+    name_only: bool,
+    /// Whether the pattern only matches directories
+    directory_only: bool,
+    /// Whether the pattern includes the paths again
+    negated: bool,
+}
+
+impl ExcludePatterns {
+    /// Compiles exclude patterns
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<ExcludePatterns>` - The patterns, or an
+    ///   [`InvalidInput`](io::ErrorKind::InvalidInput) error naming the first
+    ///   invalid one
+    pub fn new(patterns: &[String]) -> io::Result<Self> {
+        let patterns = patterns
+            .iter()
+            .filter(|pattern| !pattern.trim().is_empty())
+            .map(|pattern| ExcludePattern::new(pattern))
+            .collect::<io::Result<_>>()?;
+        Ok(ExcludePatterns { patterns })
+    }
+
+    /// Returns whether some patterns include paths again
+    pub fn has_negations(&self) -> bool {
+        self.patterns.iter().any(|pattern| pattern.negated)
+    }
+
+    /// Returns whether a path is excluded: the last pattern matching the path or
+    /// one of its parent directories decides
+    ///
+    /// # Arguments
+    ///
+    /// * `relative` - The path, relative to the searched directory
+    /// * `is_dir` - Whether the path is a directory
+    pub fn is_excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        let components: Vec<String> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        // The path and its parent directories, as `/`-separated paths
+        let paths: Vec<(String, bool)> = (1..=components.len())
+            .map(|length| {
+                let path = components[..length].join("/");
+                (path, length < components.len() || is_dir)
+            })
+            .collect();
+        let mut excluded = false;
+        for pattern in &self.patterns {
+            let matches = paths.iter().any(|(path, is_dir)| {
+                (*is_dir || !pattern.directory_only) && pattern.matches(path)
+            });
+            if matches {
+                excluded = !pattern.negated;
+            }
+        }
+        excluded
+    }
+}
+
+impl ExcludePattern {
+    fn new(pattern: &str) -> io::Result<Self> {
+        let invalid = |e: globset::Error| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid exclude pattern '{}': {}", pattern, e.kind()),
+            )
+        };
+        let mut glob = pattern.trim().replace('\\', "/");
+        let negated = glob.starts_with('!');
+        if negated {
+            glob.remove(0);
+        }
+        let directory_only = glob.ends_with('/');
+        let glob = glob.trim_end_matches('/');
+        let name_only = !glob.contains('/');
+        let globs = if name_only || glob.starts_with("**/") {
+            vec![glob.to_string()]
+        } else if let Some(anchored) = glob.strip_prefix('/') {
+            vec![anchored.to_string()]
+        } else {
+            vec![glob.to_string(), format!("**/{}", glob)]
+        };
+        let globs = globs
+            .iter()
+            .map(|glob| {
+                GlobBuilder::new(glob)
+                    .literal_separator(true)
+                    .build()
+                    .map(|glob| glob.compile_matcher())
+                    .map_err(invalid)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(ExcludePattern {
+            globs,
+            name_only,
+            directory_only,
+            negated,
+        })
+    }
+
+    /// Returns whether the pattern matches a `/`-separated relative path
+    fn matches(&self, path: &str) -> bool {
+        let subject = if self.name_only {
+            path.rsplit('/').next().unwrap_or(path)
+        } else {
+            path
+        };
+        self.globs.iter().any(|glob| glob.is_match(subject))
+    }
+}
+
+/// Encoding of a file, as detected by [`decode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8 without byte order mark
+    Utf8,
+    /// UTF-8 with a byte order mark
+    Utf8Bom,
+    /// UTF-16, little-endian
+    Utf16Le,
+    /// UTF-16, big-endian
+    Utf16Be,
+    /// ISO-8859-1, assumed for files that are not valid UTF-8
+    Latin1,
+    /// Windows code page set by the `CodePage` attribute of the directive
+    /// starting the file, such as 932 for Shift-JIS
+    CodePage(u16),
+    /// Encoding assumed for a file that is not valid UTF-8, given by
+    /// `--default-encoding` or detected from its content
+    Assumed(&'static encoding_rs::Encoding),
+}
+
+impl Encoding {
+    /// Returns the name of the encoding, such as `UTF-16LE`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "ISO-8859-1",
+            Encoding::CodePage(code_page) => {
+                code_page_encoding(*code_page).map_or("unknown", |encoding| encoding.name())
+            }
+            Encoding::Assumed(encoding) => encoding.name(),
+        }
+    }
+
+    /// Returns whether the encoding is assumed rather than given by a byte order
+    /// mark or a `CodePage` directive, for a file that is not valid UTF-8
+    pub fn is_assumed(&self) -> bool {
+        matches!(self, Encoding::Latin1 | Encoding::Assumed(_))
+    }
+
+    /// Returns the byte order mark starting the files of this encoding, empty if
+    /// they have none
+    fn bom(&self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8Bom => UTF8_BOM,
+            Encoding::Utf16Le => UTF16LE_BOM,
+            Encoding::Utf16Be => UTF16BE_BOM,
+            Encoding::Utf8 | Encoding::Latin1 | Encoding::CodePage(_) | Encoding::Assumed(_) => &[],
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Encoding::CodePage(code_page) => write!(f, "{} (CodePage {})", self.name(), code_page),
+            _ if self.is_assumed() => write!(f, "{} (assumed)", self.name()),
+            _ => f.write_str(self.name()),
+        }
+    }
+}
+
+/// How files that are not valid UTF-8, without byte order mark nor `CodePage`
+/// directive, are decoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DefaultEncoding {
+    /// As ISO-8859-1, which is commonly used in legacy ASP Classic files
+    #[default]
+    Latin1,
+    /// In the encoding guessed from their content
+    Detect,
+    /// In a given encoding
+    Encoding(&'static encoding_rs::Encoding),
+}
+
+impl FromStr for DefaultEncoding {
+    type Err = String;
+
+    /// Parses `auto` for [`DefaultEncoding::Detect`], a Windows code page such as
+    /// `932`, or an encoding label such as `windows-1252` or `shift_jis`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(DefaultEncoding::Detect);
+        }
+        // encoding_rs reads ISO-8859-1 as Windows-1252, unlike IIS
+        if ["latin1", "iso-8859-1", "28591"]
+            .iter()
+            .any(|label| value.eq_ignore_ascii_case(label))
+        {
+            return Ok(DefaultEncoding::Latin1);
+        }
+        let encoding = match value.parse::<u16>() {
+            Ok(code_page) => code_page_encoding(code_page),
+            Err(_) => encoding_rs::Encoding::for_label(value.as_bytes()),
+        };
+        encoding
+            .map(DefaultEncoding::Encoding)
+            .ok_or_else(|| format!("unknown encoding '{}'", value))
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Decodes the content of a file, detecting its encoding
+///
+/// The encoding is given by the byte order mark, which is not part of the
+/// decoded content. Files without one are UTF-16 when their first character is
+/// ASCII encoded on two bytes, as pages saved as "Unicode" by old editors often
+/// are. Pages starting with a directive such as `<%@ CodePage=932 %>` are in the
+/// encoding of this Windows code page, as IIS reads them. Other files are UTF-8
+/// when they are valid UTF-8, and ISO-8859-1 otherwise, which is commonly used in
+/// legacy ASP Classic files. Invalid UTF-16 and code page sequences are replaced
+/// with U+FFFD.
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    decode_with_default(bytes, DefaultEncoding::Latin1)
+}
+
+/// Decodes the content of a file like [`decode`], in a default encoding when it is
+/// not valid UTF-8
+///
+/// [`DefaultEncoding::Detect`] guesses the encoding with chardetng, from the
+/// bytes of the whole file.
+pub fn decode_with_default(bytes: &[u8], default: DefaultEncoding) -> (String, Encoding) {
+    let (content, encoding) = decode_borrowing(bytes, default);
+    (content.into_owned(), encoding)
+}
+
+/// Decodes the content of a file like [`decode_with_default`], borrowing the
+/// content of UTF-8 files instead of copying it
+fn decode_borrowing(bytes: &[u8], default: DefaultEncoding) -> (Cow<'_, str>, Encoding) {
+    if let Some(content) = bytes.strip_prefix(UTF8_BOM) {
+        return match std::str::from_utf8(content) {
+            Ok(content) => (Cow::Borrowed(content), Encoding::Utf8Bom),
+            Err(_) => (Cow::Owned(latin1(bytes)), Encoding::Latin1),
+        };
+    }
+    if let Some(content) = bytes.strip_prefix(UTF16LE_BOM) {
+        return (
+            Cow::Owned(utf16(content, u16::from_le_bytes)),
+            Encoding::Utf16Le,
+        );
+    }
+    if let Some(content) = bytes.strip_prefix(UTF16BE_BOM) {
+        return (
+            Cow::Owned(utf16(content, u16::from_be_bytes)),
+            Encoding::Utf16Be,
+        );
+    }
+    match bytes {
+        [first, 0, ..] if first.is_ascii() && *first != 0 => {
+            return (
+                Cow::Owned(utf16(bytes, u16::from_le_bytes)),
+                Encoding::Utf16Le,
+            );
+        }
+        [0, second, ..] if second.is_ascii() && *second != 0 => {
+            return (
+                Cow::Owned(utf16(bytes, u16::from_be_bytes)),
+                Encoding::Utf16Be,
+            );
+        }
+        _ => {}
+    }
+    if let Some(code_page) = code_page_directive(bytes)
+        && let Some(encoding) = code_page_encoding(code_page)
+    {
+        let (content, _) = encoding.decode_without_bom_handling(bytes);
+        return (content, Encoding::CodePage(code_page));
+    }
+    if let Ok(content) = std::str::from_utf8(bytes) {
+        return (Cow::Borrowed(content), Encoding::Utf8);
+    }
+    let encoding = match default {
+        DefaultEncoding::Latin1 => return (Cow::Owned(latin1(bytes)), Encoding::Latin1),
+        DefaultEncoding::Detect => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(bytes, true);
+            detector.guess(None, false)
+        }
+        DefaultEncoding::Encoding(encoding) => encoding,
+    };
+    let (content, _) = encoding.decode_without_bom_handling(bytes);
+    (content, Encoding::Assumed(encoding))
+}
+
+/// Encodes content in an encoding, with its byte order mark
+///
+/// Characters that ISO-8859-1 or the code page cannot represent are written as
+/// `?`, and content in an unknown code page is written in UTF-8.
+pub fn encode(content: &str, encoding: Encoding) -> Vec<u8> {
+    let mut bytes = encoding.bom().to_vec();
+    match encoding {
+        Encoding::Utf8 | Encoding::Utf8Bom => bytes.extend_from_slice(content.as_bytes()),
+        Encoding::Utf16Le => bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes)),
+        Encoding::Utf16Be => bytes.extend(content.encode_utf16().flat_map(u16::to_be_bytes)),
+        Encoding::Latin1 => bytes.extend(
+            content
+                .chars()
+                .map(|c| u8::try_from(c as u32).unwrap_or(b'?')),
+        ),
+        Encoding::CodePage(code_page) => match code_page_encoding(code_page) {
+            Some(encoding) => encode_code_page(content, encoding, &mut bytes),
+            None => bytes.extend_from_slice(content.as_bytes()),
+        },
+        Encoding::Assumed(encoding) => encode_code_page(content, encoding, &mut bytes),
+    }
+    bytes
+}
+
+/// Returns the code page set by the `CodePage` attribute of the directive starting
+/// a page, if any
+fn code_page_directive(bytes: &[u8]) -> Option<u16> {
+    // Directives are ASCII, whatever the encoding of the page
+    let start = bytes.iter().position(|b| !b.is_ascii_whitespace())?;
+    let directive = bytes[start..].strip_prefix(b"<%@")?;
+    let end = directive.windows(2).position(|pair| pair == b"%>")?;
+    let directive = latin1(&bytes[start..start + end + 5]);
+    parse_directives(&directive)
+        .into_iter()
+        .find(|attribute| attribute.name.eq_ignore_ascii_case("CodePage"))?
+        .value
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Returns the encoding of a Windows code page
+///
+/// UTF-8 (65001) and ISO-8859-1 (28591) have none, files in these code pages
+/// being decoded as any other.
+fn code_page_encoding(code_page: u16) -> Option<&'static encoding_rs::Encoding> {
+    Some(match code_page {
+        866 => encoding_rs::IBM866,
+        874 => encoding_rs::WINDOWS_874,
+        932 => encoding_rs::SHIFT_JIS,
+        936 => encoding_rs::GBK,
+        949 => encoding_rs::EUC_KR,
+        950 => encoding_rs::BIG5,
+        1250 => encoding_rs::WINDOWS_1250,
+        1251 => encoding_rs::WINDOWS_1251,
+        1252 => encoding_rs::WINDOWS_1252,
+        1253 => encoding_rs::WINDOWS_1253,
+        1254 => encoding_rs::WINDOWS_1254,
+        1255 => encoding_rs::WINDOWS_1255,
+        1256 => encoding_rs::WINDOWS_1256,
+        1257 => encoding_rs::WINDOWS_1257,
+        1258 => encoding_rs::WINDOWS_1258,
+        10000 => encoding_rs::MACINTOSH,
+        10007 => encoding_rs::X_MAC_CYRILLIC,
+        20866 => encoding_rs::KOI8_R,
+        20932 | 51932 => encoding_rs::EUC_JP,
+        21866 => encoding_rs::KOI8_U,
+        28592 => encoding_rs::ISO_8859_2,
+        28593 => encoding_rs::ISO_8859_3,
+        28594 => encoding_rs::ISO_8859_4,
+        28595 => encoding_rs::ISO_8859_5,
+        28596 => encoding_rs::ISO_8859_6,
+        28597 => encoding_rs::ISO_8859_7,
+        28598 => encoding_rs::ISO_8859_8,
+        28603 => encoding_rs::ISO_8859_13,
+        28605 => encoding_rs::ISO_8859_15,
+        38598 => encoding_rs::ISO_8859_8_I,
+        50220..=50222 => encoding_rs::ISO_2022_JP,
+        54936 => encoding_rs::GB18030,
+        _ => return None,
+    })
+}
+
+/// Encodes content in the encoding of a code page, replacing the characters it
+/// cannot represent with `?`
+fn encode_code_page(content: &str, encoding: &'static encoding_rs::Encoding, bytes: &mut Vec<u8>) {
+    let mut encoder = encoding.new_encoder();
+    let mut rest = content;
+    loop {
+        let length = encoder
+            .max_buffer_length_from_utf8_without_replacement(rest.len())
+            .unwrap_or(rest.len());
+        bytes.reserve(length);
+        let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(rest, bytes, true);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => break,
+            EncoderResult::Unmappable(_) => bytes.push(b'?'),
+            EncoderResult::OutputFull => {}
+        }
+    }
+}
+
+/// Decodes ISO-8859-1, whose bytes are the first 256 Unicode code points
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Decodes UTF-16 code units of a given byte order; an odd last byte is dropped
+fn utf16(bytes: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| unit([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// Helper function to read file with encoding fallback (exposed for testing)
+///
+/// See [`decode`] for the detected encodings.
+pub fn read_file_with_encoding(path: &Path) -> io::Result<String> {
+    read_file_detecting_encoding(path, DefaultEncoding::Latin1).map(|(content, _)| content)
+}
+
+/// Reads a file, returning its content with the encoding it was decoded from
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `default` - How the file is decoded if it is not valid UTF-8, without byte
+///   order mark nor `CodePage` directive
+pub fn read_file_detecting_encoding(
+    path: &Path,
+    default: DefaultEncoding,
+) -> io::Result<(String, Encoding)> {
+    Ok(decode_with_default(&fs::read(path)?, default))
+}
+
+/// How the files to parse are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReadOptions {
+    /// How files that are not valid UTF-8, without byte order mark nor `CodePage`
+    /// directive, are decoded
+    pub default_encoding: DefaultEncoding,
+    /// Map the files in memory instead of reading them (see [`read_file_mapped`])
+    pub mmap: bool,
+}
+
+/// Text of a file read by [`read_source_file`]
+///
+/// The text of a file in UTF-8 read with [`read_file_mapped`] is the memory map of
+/// the file itself; other files are decoded in a string.
+pub struct SourceText(Source);
+
+enum Source {
+    /// Valid UTF-8 text, between two byte offsets of a mapped file
+    Mapped(MappedFile, usize, usize),
+    Decoded(String),
+}
+
+impl Deref for SourceText {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match &self.0 {
+            // SAFETY: the text was checked to be valid UTF-8 when the file was
+            // mapped, and `read_file_mapped` callers guarantee the file is not
+            // modified since
+            Source::Mapped(map, start, end) => unsafe {
+                std::str::from_utf8_unchecked(&map[*start..*end])
+            },
+            Source::Decoded(text) => text,
+        }
+    }
+}
+
+impl From<String> for SourceText {
+    fn from(text: String) -> Self {
+        SourceText(Source::Decoded(text))
+    }
+}
+
+impl fmt::Debug for SourceText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// Reads a file to parse, mapping it in memory if the options say so
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `options` - How the file is read and decoded
+///
+/// # Safety
+///
+/// When `options.mmap` is set, the file must not be modified nor truncated while
+/// the returned text is used (see [`read_file_mapped`]).
+pub unsafe fn read_source_file(
+    path: &Path,
+    options: ReadOptions,
+) -> io::Result<(SourceText, Encoding)> {
+    if options.mmap {
+        // SAFETY: upheld by the caller
+        unsafe { read_file_mapped(path, options.default_encoding) }
+    } else {
+        read_file_detecting_encoding(path, options.default_encoding)
+            .map(|(content, encoding)| (content.into(), encoding))
+    }
+}
+
+/// Reads a file by mapping it in memory, returning its text with the encoding it
+/// was decoded from
+///
+/// The text of a file in UTF-8 is not copied: it is read from the pages of the
+/// file, which the system can drop from memory and read again when needed, unlike
+/// the memory of a string. This lowers the memory used when many large files are
+/// parsed at once. Files in other encodings are decoded like
+/// [`read_file_detecting_encoding`] does, and are only mapped for the time of the
+/// decoding.
+///
+/// On platforms other than Unix, and for files that are not regular files, the
+/// file is read in memory instead.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `default` - How the file is decoded if it is not valid UTF-8, without byte
+///   order mark nor `CodePage` directive
+///
+/// # Safety
+///
+/// The file must not be modified nor truncated, by this process or another one,
+/// while the returned text is used. The text is only checked to be valid UTF-8
+/// when the file is mapped: a later change of the file can make it invalid, and
+/// reading the pages of a truncated file raises `SIGBUS`.
+pub unsafe fn read_file_mapped(
+    path: &Path,
+    default: DefaultEncoding,
+) -> io::Result<(SourceText, Encoding)> {
+    // SAFETY: upheld by the caller
+    let map = unsafe { MappedFile::open(path)? };
+    let (content, encoding) = decode_borrowing(&map, default);
+    let text = match content {
+        Cow::Borrowed(text) => {
+            let start = text.as_ptr() as usize - map.as_ptr() as usize;
+            let end = start + text.len();
+            Source::Mapped(map, start, end)
+        }
+        Cow::Owned(text) => Source::Decoded(text),
+    };
+    Ok((SourceText(text), encoding))
+}
+
+/// Read-only memory map of a file, or its content read in memory when it cannot be
+/// mapped
+#[cfg(unix)]
+enum MappedFile {
+    Map(memmap2::Mmap),
+    Read(Vec<u8>),
+}
+
+#[cfg(unix)]
+impl MappedFile {
+    /// # Safety
+    ///
+    /// The file must not be modified nor truncated while the map is used.
+    unsafe fn open(path: &Path) -> io::Result<Self> {
+        let file = fs::File::open(path)?;
+        let metadata = file.metadata()?;
+        // Pipes and devices cannot be mapped, and empty maps are not allowed
+        if !metadata.is_file() || metadata.len() == 0 {
+            return fs::read(path).map(MappedFile::Read);
+        }
+        // SAFETY: upheld by the caller
+        unsafe { memmap2::Mmap::map(&file) }.map(MappedFile::Map)
+    }
+}
+
+#[cfg(unix)]
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            MappedFile::Map(map) => map,
+            MappedFile::Read(bytes) => bytes,
+        }
+    }
+}
+
+/// Content of a file, read in memory on the platforms without memory maps
+#[cfg(not(unix))]
+struct MappedFile(Vec<u8>);
+
+#[cfg(not(unix))]
+impl MappedFile {
+    unsafe fn open(path: &Path) -> io::Result<Self> {
+        fs::read(path).map(MappedFile)
+    }
+}
+
+#[cfg(not(unix))]
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Writes a file in the encoding of its current content
+///
+/// Files are written in the encoding [`read_file_with_encoding`] detected, with
+/// the same byte order mark; files read as ISO-8859-1 are written in UTF-8 when
+/// `content` has characters ISO-8859-1 cannot represent. New files are written
+/// in UTF-8.
+pub fn write_file_with_encoding(path: &Path, content: &str) -> io::Result<()> {
+    let encoding = match fs::read(path) {
+        Ok(bytes) => decode(&bytes).1,
+        Err(_) => Encoding::Utf8,
+    };
+    write_file_in_encoding(path, content, encoding)
+}
+
+/// Writes a file in an encoding, such as the one it was read in
+///
+/// Content that ISO-8859-1 cannot represent is written in UTF-8 instead.
+pub fn write_file_in_encoding(path: &Path, content: &str, encoding: Encoding) -> io::Result<()> {
+    let encoding = if encoding == Encoding::Latin1 && content.chars().any(|c| (c as u32) > 255) {
+        Encoding::Utf8
+    } else {
+        encoding
+    };
+    fs::write(path, encode(content, encoding))
+}
 
 /// Unit tests for the file and encoding utilities
 /// These tests verify the behavior of the new features:
@@ -86,7 +983,7 @@ mod tests {
         // Create various directories that should be excluded
         for dir in &[".git", ".svn", ".hg", "node_modules", ".idea"] {
             fs::create_dir(temp_path.join(dir))
-                .expect(&format!("Failed to create {} directory", dir));
+                .unwrap_or_else(|_| panic!("Failed to create {} directory", dir));
 
             // Add an ASP file in each directory
             let file_path = temp_path.join(dir).join("excluded.asp");
@@ -196,6 +1093,172 @@ mod tests {
         );
     }
 
+    /// Test the glob semantics of exclude patterns
+    #[test]
+    fn test_exclude_globs() {
+        let temp_dir = tempdir().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        for file in [
+            "template.asp",
+            "tmp/cache.asp",
+            "templates/header.asp",
+            "old_code/legacy/page.asp",
+            "lib/old_code/page.asp",
+            "page.bak.asp",
+            "page1.asp",
+            "vendor/other.asp",
+            "vendor/ours/widget.asp",
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).expect("Failed to create directory");
+            fs::write(&path, "<% %>").expect("Failed to write file");
+        }
+        let found = |patterns: &[&str]| {
+            let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+            let mut found: Vec<String> = find_asp_files(root, &patterns)
+                .expect("Finding files failed")
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(root).unwrap();
+                    relative.to_string_lossy().replace('\\', "/")
+                })
+                .collect();
+            found.sort();
+            found
+        };
+
+        // Names are matched whole, not as substrings
+        let all = found(&[]);
+        assert!(all.contains(&"template.asp".to_string()));
+        assert!(all.contains(&"templates/header.asp".to_string()));
+        assert!(!all.contains(&"tmp/cache.asp".to_string()));
+        assert!(!all.iter().any(|file| file.starts_with("vendor/")));
+
+        assert_eq!(
+            found(&["--replace-exclude", "*.bak.asp", "page?.asp", "t*"]),
+            [
+                "lib/old_code/page.asp",
+                "old_code/legacy/page.asp",
+                "vendor/other.asp",
+                "vendor/ours/widget.asp"
+            ]
+        );
+        // Paths match at any depth, unless anchored with `/`
+        assert_eq!(
+            found(&[
+                "--replace-exclude",
+                "old_code/**",
+                "/vendor/",
+                "template*",
+                "page*"
+            ]),
+            ["tmp/cache.asp"]
+        );
+        assert_eq!(
+            found(&[
+                "--replace-exclude",
+                "/old_code",
+                "**/*.bak.*",
+                "t*",
+                "vendor"
+            ]),
+            ["lib/old_code/page.asp", "page1.asp"]
+        );
+        // Negated patterns include files again
+        assert_eq!(
+            found(&["--replace-exclude", "*", "!vendor/ours/**", "!page1.asp"]),
+            ["page1.asp", "vendor/ours/widget.asp"]
+        );
+
+        let error = find_asp_files(root, &["[abc".to_string()]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid exclude pattern '[abc'")
+        );
+    }
+
+    /// Test the depth, file size and symbolic link options of the file search
+    #[test]
+    fn test_scan_options() {
+        let temp_dir = tempdir().expect("Failed to create temp directory");
+        let root = temp_dir.path();
+        for (file, size) in [
+            ("index.asp", 10),
+            ("generated.asp", 2048),
+            ("lib/utils.asp", 10),
+            ("lib/deep/inner.asp", 10),
+        ] {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().unwrap()).expect("Failed to create directory");
+            fs::write(&path, "x".repeat(size)).expect("Failed to write file");
+        }
+        let relative = |paths: Vec<PathBuf>| {
+            let mut paths: Vec<String> = paths
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(root).unwrap();
+                    relative.to_string_lossy().replace('\\', "/")
+                })
+                .collect();
+            paths.sort();
+            paths
+        };
+        let scan = |options: &ScanOptions| {
+            scan_files(root, &[], &["asp"], options).expect("Finding files failed")
+        };
+
+        let options = ScanOptions {
+            max_depth: Some(2),
+            max_file_size: Some(1024),
+            ..ScanOptions::default()
+        };
+        let scanned = scan(&options);
+        assert_eq!(relative(scanned.files), ["index.asp", "lib/utils.asp"]);
+        assert_eq!(scanned.too_large, [(root.join("generated.asp"), 2048)]);
+        let options = ScanOptions {
+            max_depth: Some(1),
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            relative(scan(&options).files),
+            ["generated.asp", "index.asp"]
+        );
+
+        assert_eq!(parse_size("2048"), Ok(2048));
+        assert_eq!(parse_size("512K"), Ok(512 * 1024));
+        assert_eq!(parse_size(" 10MB "), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1024 * 1024 * 1024));
+        assert_eq!(
+            parse_size("big"),
+            Err("invalid size 'big', expected e.g. 512K, 10M or 1G".to_string())
+        );
+
+        // Links are only followed on demand, and loops are searched once
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::symlink;
+            symlink(root, root.join("lib/deep/loop")).expect("Failed to create link");
+            symlink(root.join("index.asp"), root.join("link.asp")).expect("Failed to create link");
+            let all = [
+                "generated.asp",
+                "index.asp",
+                "lib/deep/inner.asp",
+                "lib/utils.asp",
+            ];
+            assert_eq!(relative(scan(&ScanOptions::default()).files), all);
+            let options = ScanOptions {
+                follow_symlinks: true,
+                ..ScanOptions::default()
+            };
+            let mut expected = all.to_vec();
+            expected.push("link.asp");
+            expected.sort();
+            assert_eq!(relative(scan(&options).files), expected);
+        }
+    }
+
     /// Test reading files with different encodings
     #[test]
     fn test_read_file_with_encoding() {
@@ -228,182 +1291,156 @@ mod tests {
             "Latin-1 special chars should be correctly converted"
         );
     }
-}
-
-/// Default patterns to exclude from file search
-pub fn default_exclude_patterns() -> Vec<String> {
-    vec![
-        // Version control systems
-        ".git".to_string(),
-        ".svn".to_string(),
-        ".hg".to_string(),
-        ".bzr".to_string(),
-        // IDE and editor files
-        ".idea".to_string(),
-        ".vscode".to_string(),
-        ".vs".to_string(),
-        // Build artifacts and dependencies
-        "node_modules".to_string(),
-        "vendor".to_string(),
-        "dist".to_string(),
-        "target".to_string(),
-        "build".to_string(),
-        "_build".to_string(),
-        // Package manager directories
-        "bower_components".to_string(),
-        "jspm_packages".to_string(),
-        // Other common directories to exclude
-        "coverage".to_string(),
-        "logs".to_string(),
-        "tmp".to_string(),
-        "temp".to_string(),
-    ]
-}
-
-/// Helper function to find ASP and VBScript files recursively, respecting exclude patterns
-pub fn find_asp_files(dir: &Path, exclude_patterns: &[String]) -> io::Result<Vec<PathBuf>> {
-    // Check for empty dirs early to avoid problems
-    if !dir.exists() || !dir.is_dir() {
-        return Ok(Vec::new());
-    }
 
-    let mut asp_files = Vec::new();
+    /// Test decoding files with byte order marks and writing them back
+    #[test]
+    fn test_byte_order_marks() {
+        let page = "<% Response.Write \"café ✓\" %>";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            let bytes = encode(page, encoding);
+            assert_eq!(decode(&bytes), (page.to_string(), encoding), "{encoding}");
+        }
+        assert_eq!(&encode("<%", Encoding::Utf16Le)[..4], b"\xFF\xFE<\0");
 
-    // Prepare all exclusion patterns
-    let mut all_exclude_patterns = Vec::new();
+        // UTF-16 without byte order mark
+        let bytes: Vec<u8> = "<%".encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode(&bytes), ("<%".to_string(), Encoding::Utf16Be));
 
-    // Check if the special --replace-exclude flag is present
-    let replace_defaults = exclude_patterns.contains(&"--replace-exclude".to_string());
+        // Unpaired surrogates and odd lengths do not fail
+        assert_eq!(
+            decode(b"\xFF\xFE\x00\xD8a\x00b"),
+            ("\u{FFFD}a".to_string(), Encoding::Utf16Le)
+        );
 
-    // Add default exclusions if we're not replacing them
-    if !replace_defaults {
-        all_exclude_patterns.extend(default_exclude_patterns());
+        // Files keep their encoding when written
+        let temp_dir = tempdir().expect("Failed to create temp directory");
+        let path = temp_dir.path().join("unicode.asp");
+        fs::write(&path, encode(page, Encoding::Utf16Be)).expect("Failed to write file");
+        assert_eq!(
+            read_file_detecting_encoding(&path, DefaultEncoding::Latin1)
+                .expect("Failed to read file"),
+            (page.to_string(), Encoding::Utf16Be)
+        );
+        write_file_with_encoding(&path, "<% x = 1 %>").expect("Failed to write file");
+        assert_eq!(
+            fs::read(&path).expect("Failed to read file"),
+            encode("<% x = 1 %>", Encoding::Utf16Be)
+        );
     }
 
-    // Add custom exclusion patterns (except the special flag)
-    all_exclude_patterns.extend(
-        exclude_patterns
-            .iter()
-            .filter(|&p| p != "--replace-exclude")
-            .cloned(),
-    );
-
-    // Find all ASP and VBS files using a simpler, more direct approach
-    find_files_simple(dir, &mut asp_files, &all_exclude_patterns)?;
-
-    Ok(asp_files)
-}
-
-/// A simpler implementation to find ASP/VBS files that works reliably cross-platform
-fn find_files_simple(
-    dir: &Path,
-    files: &mut Vec<PathBuf>,
-    exclude_patterns: &[String],
-) -> io::Result<()> {
-    // Stack for iterative directory traversal (more reliable than recursion)
-    let mut dirs_to_process = vec![dir.to_path_buf()];
-
-    while let Some(current_dir) = dirs_to_process.pop() {
-        // Skip this directory if it should be excluded
-        if should_exclude(&current_dir, exclude_patterns) {
-            continue;
-        }
-
-        // Process entries in this directory
-        if let Ok(entries) = fs::read_dir(&current_dir) {
-            // Use flatten() to simplify handling of Result<DirEntry>
-            for entry in entries.flatten() {
-                let path = entry.path();
-
-                if path.is_dir() {
-                    // Add to stack for later processing if not excluded
-                    dirs_to_process.push(path);
-                } else if has_asp_extension(&path) && !should_exclude(&path, exclude_patterns) {
-                    // Add ASP/VBS files that aren't excluded
-                    files.push(path);
-                }
-            }
-        }
-    }
+    /// Test decoding pages in the code page of their `CodePage` directive
+    #[test]
+    fn test_code_page_directive() {
+        // "日本" in Shift-JIS
+        let bytes = b"<%@ Language=\"VBScript\" CodePage=932 %>\n<% x = \"\x93\xFA\x96\x7B\" %>";
+        let (content, encoding) = decode(bytes);
+        assert_eq!(encoding, Encoding::CodePage(932));
+        assert_eq!(encoding.to_string(), "Shift_JIS (CodePage 932)");
+        assert!(content.ends_with("x = \"日本\" %>"));
+        assert_eq!(encode(&content, encoding), bytes);
 
-    Ok(())
-}
+        // Windows-1252 is not ISO-8859-1, and the directive can follow blank lines
+        let (content, encoding) = decode(b"\r\n<%@ CODEPAGE=\"1252\" %>\x80");
+        assert_eq!(encoding, Encoding::CodePage(1252));
+        assert!(content.ends_with("%>€"));
+        assert_eq!(
+            encode("<%@ CodePage=1252 %>€✓", encoding),
+            b"<%@ CodePage=1252 %>\x80?"
+        );
 
-/// Check if a path has an ASP or VBS extension
-fn has_asp_extension(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_str = ext.to_string_lossy().to_lowercase();
-        ext_str == "asp" || ext_str == "vbs"
-    } else {
-        false
+        // UTF-8 and unknown code pages, or a directive that does not start the
+        // page, fall back to the detection of other files
+        assert_eq!(decode(b"<%@ CodePage=65001 %>\xC3\xA9").1, Encoding::Utf8);
+        assert_eq!(decode(b"<%@ CodePage=1 %>\xE9").1, Encoding::Latin1);
+        assert_eq!(
+            decode(b"<p><%@ CodePage=932 %>\x93\xFA").1,
+            Encoding::Latin1
+        );
     }
-}
 
-/// Check if a path should be excluded based on the patterns
-fn should_exclude(path: &Path, patterns: &[String]) -> bool {
-    // We need a simple, cross-platform approach that works reliably
-
-    // First, get the path as a string for easier comparison
-    let path_str = path.to_string_lossy().to_string();
-
-    // Next, check if the path contains any of the exclusion patterns
-    for pattern in patterns {
-        // Basic checks first - for directory name matching
-        if let Some(name) = path.file_name() {
-            let name_str = name.to_string_lossy();
-            if name_str == pattern.as_str() {
-                return true;
-            }
-        }
+    /// Test the default encoding of files that are not valid UTF-8
+    #[test]
+    fn test_default_encoding() {
+        assert_eq!("AUTO".parse(), Ok(DefaultEncoding::Detect));
+        assert_eq!("latin1".parse(), Ok(DefaultEncoding::Latin1));
+        assert_eq!(
+            "932".parse(),
+            Ok(DefaultEncoding::Encoding(encoding_rs::SHIFT_JIS))
+        );
+        assert_eq!(
+            " cp1251 ".parse(),
+            Ok(DefaultEncoding::Encoding(encoding_rs::WINDOWS_1251))
+        );
+        assert_eq!(
+            "klingon".parse::<DefaultEncoding>(),
+            Err("unknown encoding 'klingon'".to_string())
+        );
 
-        // For multi-part paths, normalize slashes and compare more broadly
-        let norm_pattern = pattern.replace('\\', "/");
-        let norm_path = path_str.replace('\\', "/");
+        // "こんにちは世界" in Shift-JIS
+        let bytes = b"<% x = \"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x90\xa2\x8a\x45\" %>";
+        let (content, encoding) = decode_with_default(bytes, DefaultEncoding::Detect);
+        assert_eq!(encoding, Encoding::Assumed(encoding_rs::SHIFT_JIS));
+        assert_eq!(encoding.to_string(), "Shift_JIS (assumed)");
+        assert_eq!(content, "<% x = \"こんにちは世界\" %>");
+        let (_, encoding) =
+            decode_with_default(bytes, DefaultEncoding::Encoding(encoding_rs::EUC_KR));
+        assert_eq!(encoding, Encoding::Assumed(encoding_rs::EUC_KR));
+        assert!(decode(bytes).1.is_assumed());
 
-        // Check various matching possibilities:
+        // Files that are valid UTF-8, or whose encoding is known, are not assumed
+        let (_, encoding) =
+            decode_with_default("<% x = \"é\" %>".as_bytes(), DefaultEncoding::Detect);
+        assert_eq!(encoding, Encoding::Utf8);
+        let (_, encoding) =
+            decode_with_default(b"<%@ CodePage=1252 %>\xE9", DefaultEncoding::Detect);
+        assert!(!encoding.is_assumed());
+    }
 
-        // 1. Exact match or path contains pattern
-        if norm_path.contains(&norm_pattern) {
-            return true;
-        }
+    /// Test reading files mapped in memory
+    #[test]
+    fn test_read_file_mapped() {
+        let temp_dir = tempdir().expect("Failed to create temp directory");
+        let read = |name: &str, bytes: &[u8]| {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, bytes).expect("Failed to write file");
+            let options = ReadOptions {
+                mmap: true,
+                ..ReadOptions::default()
+            };
+            // SAFETY: the temporary file is not modified while it is read
+            let (content, encoding) =
+                unsafe { read_source_file(&path, options) }.expect("Failed to read file");
+            assert_eq!(
+                (content.to_string(), encoding),
+                read_file_detecting_encoding(&path, DefaultEncoding::Latin1)
+                    .expect("Failed to read file"),
+                "{name}"
+            );
+            (content, encoding)
+        };
 
-        // 2. For patterns with slashes, try different combination with the path components
-        if norm_pattern.contains('/') {
-            // Split path into components for piecewise matching
-            let path_components: Vec<&str> = norm_path.split('/').collect();
-
-            // Look for consecutive components that match the pattern
-            for window_size in 2..=path_components.len() {
-                for i in 0..=path_components.len() - window_size {
-                    let path_segment = path_components[i..i + window_size].join("/");
-                    if path_segment == norm_pattern {
-                        return true;
-                    }
-                }
-            }
-        }
-    }
+        let page = "<% Response.Write \"café ✓\" %>";
+        let (content, _) = read("utf8.asp", page.as_bytes());
+        assert!(matches!(content.0, Source::Mapped(..)));
+        // The byte order mark is not part of the text
+        let (content, encoding) = read("bom.asp", &encode(page, Encoding::Utf8Bom));
+        assert_eq!((&*content, encoding), (page, Encoding::Utf8Bom));
+        assert!(matches!(content.0, Source::Mapped(..)));
 
-    false
-}
+        // Files in other encodings are decoded
+        let (content, _) = read("utf16.asp", &encode(page, Encoding::Utf16Le));
+        assert!(matches!(content.0, Source::Decoded(_)));
+        let (content, encoding) = read("latin1.asp", b"<p>\xE9t\xE9</p>");
+        assert_eq!((&*content, encoding), ("<p>été</p>", Encoding::Latin1));
 
-/// Helper function to read file with encoding fallback (exposed for testing)
-pub fn read_file_with_encoding(path: &Path) -> io::Result<String> {
-    // First try to read as UTF-8
-    match fs::read_to_string(path) {
-        Ok(content) => Ok(content),
-        Err(_) => {
-            // If UTF-8 reading fails, try with latin1 (ISO-8859-1) encoding
-            // which is commonly used in legacy ASP Classic files
-            let mut file = fs::File::open(path)?;
-            let mut buffer = Vec::new();
-            file.read_to_end(&mut buffer)?;
-
-            // Convert from Latin-1 (ISO-8859-1) to UTF-8
-            // Latin-1 has a direct 1:1 mapping for the first 256 Unicode code points
-            let content = buffer.iter().map(|&b| b as char).collect::<String>();
-
-            Ok(content)
-        }
+        let (content, _) = read("empty.asp", b"");
+        assert!(content.is_empty());
+        let missing = temp_dir.path().join("missing.asp");
+        assert!(unsafe { read_file_mapped(&missing, DefaultEncoding::Latin1) }.is_err());
     }
 }
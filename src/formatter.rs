@@ -0,0 +1,1089 @@
+//! Formatting of the VBScript code of ASP pages
+//!
+//! [`format`] rewrites the server code of a page in a consistent style, working
+//! on the token stream of [`tokenize`] so that pages with syntax errors can be
+//! formatted too:
+//!
+//! - statements are indented in each block (`If`, loops, procedures, classes,
+//!   `With` and `Select Case`, whose `Case` clauses are indented too), so that
+//!   `End If`, `Next` and `Loop` line up with the statement opening their block;
+//!   blocks may span several ASP blocks;
+//! - keywords and word operators are written in their usual casing (`End If`,
+//!   `ElseIf`, `And`), or in lowercase;
+//! - binary operators are surrounded by one space, and commas followed by one;
+//! - statements longer than the maximum line length, if any, are wrapped with
+//!   line continuations after a comma or before a binary operator.
+//!
+//! [`format_range`] formats only the lines of a selection, and
+//! [`normalize_casing`] only fixes the casing of keywords and predefined names.
+//! [`FormatOptions`] sets the indentation, keyword casing and line length.
+//!
+//! The HTML of the page, directives, comments and JScript code are left
+//! untouched, as are line breaks: statements are never joined, and code written
+//! on the line of its `<%` stays there. Formatting a formatted page does not
+//! change it.
+//!
+//! ```
+//! use asp_classic_parser::formatter;
+//!
+//! let page = "<ul>\n<%\nfor each item in items\nif item.visible then\nresponse.write \"<li>\"&item.name&\"</li>\"\nend if\nnext\n%>\n</ul>";
+//! assert_eq!(
+//!     formatter::format(page),
+//!     "<ul>\n<%\nFor Each item In items\n    If item.visible Then\n        response.write \"<li>\" & item.name & \"</li>\"\n    End If\nNext\n%>\n</ul>"
+//! );
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::builtins;
+use crate::parser::ast::Span;
+use crate::parser::lexer::{Token, TokenKind, vbscript_keyword};
+use crate::parser::{ScriptLanguage, detect_language, script_language_attribute, tokenize};
+
+/// Characters used to indent blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    /// `indent_width` spaces per level
+    #[default]
+    Spaces,
+    /// One tab per level
+    Tabs,
+}
+
+/// Casing of keywords and word operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCase {
+    /// `End If`, `ElseIf`, `And`
+    #[default]
+    Pascal,
+    /// `end if`, `elseif`, `and`
+    Lower,
+}
+
+/// Options of [`format_with_options`]
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::formatter::{self, FormatOptions, IndentStyle, KeywordCase};
+///
+/// let options = FormatOptions {
+///     indent_style: IndentStyle::Tabs,
+///     keyword_case: KeywordCase::Lower,
+///     ..FormatOptions::default()
+/// };
+/// assert_eq!(
+///     formatter::format_with_options("<%\nIf x Then\ny\nEnd If\n%>", &options),
+///     "<%\nif x then\n\ty\nend if\n%>"
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Characters used to indent blocks
+    pub indent_style: IndentStyle,
+    /// Number of spaces of an indentation level, and of columns of a tab when
+    /// measuring lines
+    pub indent_width: usize,
+    /// Casing of keywords and word operators
+    pub keyword_case: KeywordCase,
+    /// Length beyond which statements are wrapped, in columns; `None` to never
+    /// wrap them
+    pub max_line_length: Option<usize>,
+    /// Align the continuation lines of declarations (`Dim`, `Private`, `Const`,
+    /// ...) with the first declared name instead of indenting them one level
+    pub align_dims: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent_style: IndentStyle::Spaces,
+            indent_width: 4,
+            keyword_case: KeywordCase::Pascal,
+            max_line_length: None,
+            align_dims: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Returns the indentation of one level
+    fn indent(&self) -> String {
+        match self.indent_style {
+            IndentStyle::Spaces => " ".repeat(self.indent_width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+
+    /// Returns the number of columns of a text, tabs counting as `indent_width`
+    fn width(&self, text: &str) -> usize {
+        text.chars()
+            .map(|c| if c == '\t' { self.indent_width } else { 1 })
+            .sum()
+    }
+}
+
+/// Formats the VBScript code of a page with the default options
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+///
+/// # Returns
+///
+/// * `String` - The page with its server code formatted
+pub fn format(source: &str) -> String {
+    format_with_options(source, &FormatOptions::default())
+}
+
+/// Formats the VBScript code of a page
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+/// * `options` - The indentation, casing and line length to use
+///
+/// # Returns
+///
+/// * `String` - The page with its server code formatted
+pub fn format_with_options(source: &str, options: &FormatOptions) -> String {
+    let mut formatter = Formatter::new(options, source.len());
+    formatter.page(&tokenize(source), detect_language(source));
+    formatter.output
+}
+
+/// Formats the lines of a page covered by a span with the default options
+///
+/// See [`format_range_with_options`].
+pub fn format_range(source: &str, span: Span) -> String {
+    format_range_with_options(source, span, &FormatOptions::default())
+}
+
+/// Formats the lines of a page covered by a span, leaving the other lines
+/// untouched
+///
+/// The lines are indented according to the blocks opened before them, as when
+/// formatting the whole page.
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+/// * `span` - The part of the page to format; every line it touches is
+///   formatted
+/// * `options` - The indentation, casing and line length to use
+///
+/// # Returns
+///
+/// * `String` - The page with the server code of these lines formatted
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::formatter::{self, FormatOptions};
+/// use asp_classic_parser::parser::ast::LineIndex;
+///
+/// let page = "<%\nif x then\ny=1\nz=2\nend if\n%>";
+/// let span = LineIndex::new(page).span(17, 20);
+/// assert_eq!(
+///     formatter::format_range_with_options(page, span, &FormatOptions::default()),
+///     "<%\nif x then\ny=1\n    z = 2\nend if\n%>"
+/// );
+/// ```
+pub fn format_range_with_options(source: &str, span: Span, options: &FormatOptions) -> String {
+    let mut formatter = Formatter::new(options, source.len());
+    formatter.page(&tokenize(source), detect_language(source));
+
+    // Lines of the source, and their formatted text, covered by the span
+    let source_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+    let start = span.start.min(source.len());
+    let end = span.end.clamp(start, source.len());
+    let first = source_starts.partition_point(|&line_start| line_start <= start) - 1;
+    // A span ending at the start of a line does not cover it
+    let last = source_starts
+        .partition_point(|&line_start| line_start < end)
+        .max(first + 1);
+    let line_start =
+        |starts: &[usize], length: usize, line: usize| starts.get(line).copied().unwrap_or(length);
+    let output = &formatter.output;
+    let starts = &formatter.line_starts;
+    format!(
+        "{}{}{}",
+        &source[..source_starts[first]],
+        &output[line_start(starts, output.len(), first)..line_start(starts, output.len(), last)],
+        &source[line_start(&source_starts, source.len(), last)..]
+    )
+}
+
+/// Writes the keywords, word operators and predefined names of the VBScript
+/// code of a page in their usual casing, leaving everything else untouched
+///
+/// Keywords are written as in [`KeywordCase::Pascal`], functions, constants and
+/// ASP objects as documented (`UCase`, `vbCrLf`, `Response`), as are the
+/// members of the ASP objects (`Response.Write`, `Server.HTMLEncode`).
+/// Whitespace, line breaks, strings, comments and other names keep their text.
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+///
+/// # Returns
+///
+/// * `String` - The page with its keywords and predefined names recased
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::formatter;
+///
+/// assert_eq!(
+///     formatter::normalize_casing("<%\nif  ucase(name)=\"X\" then response.write vbcrlf\nend if\n%>"),
+///     "<%\nIf  UCase(name)=\"X\" Then Response.Write vbCrLf\nEnd If\n%>"
+/// );
+/// ```
+pub fn normalize_casing(source: &str) -> String {
+    let page_language = detect_language(source);
+    let mut output = String::with_capacity(source.len());
+    // Language of the current server block, `None` outside of them
+    let mut language = None;
+    // Predefined object named by the last code token
+    let mut object = None;
+    // Whether the last code token is a dot, and the predefined object before it
+    let mut member_of: Option<Option<&str>> = None;
+    for token in tokenize(source) {
+        match token.kind {
+            TokenKind::OpenTag | TokenKind::OpenExpressionTag => language = Some(page_language),
+            TokenKind::ScriptOpenTag => {
+                language = Some(script_language_attribute(token.text).unwrap_or(page_language));
+            }
+            TokenKind::OpenDirectiveTag | TokenKind::CloseTag | TokenKind::ScriptCloseTag => {
+                language = None;
+            }
+            _ => {}
+        }
+        if language != Some(ScriptLanguage::VBScript) || token.kind.is_trivia() {
+            output.push_str(token.text);
+            continue;
+        }
+        let name = match (member_of, token.kind) {
+            (Some(object), TokenKind::Identifier | TokenKind::Keyword) => {
+                object.and_then(|object| builtins::member_name(object, token.text))
+            }
+            (Some(_), _) => None,
+            (None, TokenKind::Keyword | TokenKind::Operator) => vbscript_keyword(token.text),
+            (None, TokenKind::Identifier) => builtins::builtin_name(token.text),
+            (None, _) => None,
+        };
+        output.push_str(name.unwrap_or(token.text));
+        let is_member = member_of.is_some();
+        member_of = (token.kind == TokenKind::Punctuation && token.text == ".").then_some(object);
+        object = name.filter(|name| !is_member && builtins::OBJECTS.contains(name));
+    }
+    output
+}
+
+/// A block of statements open at some point of a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    /// Body of an `If`, a loop, a procedure, a class or a `With`
+    Body,
+    /// `Select Case` statement, whose `Case` clauses are indented
+    Select,
+    /// Statements of a `Case` clause
+    Case,
+}
+
+/// Effect of a statement on the open blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Effect {
+    /// The statement opens a block
+    Open(Block),
+    /// The statement closes the current block (`End If`, `Next`, `Loop`, ...)
+    Close,
+    /// `End Select`, closing the last `Case` clause and the `Select Case`
+    EndSelect,
+    /// `Else` or `ElseIf`, closing a branch and opening the next one
+    Branch,
+    /// `Case` clause, closing the previous one
+    Case,
+    /// Any other statement
+    None,
+}
+
+/// A line of server code
+struct Line<'t, 'a> {
+    /// Tokens of the line, a line continuation included
+    tokens: &'t [Token<'a>],
+    /// Line break ending the line, empty on the last line of a block
+    newline: &'a str,
+}
+
+/// A line of code formatted without its indentation
+struct FormattedLine {
+    text: String,
+    /// Offsets of the spaces of `text` where the line may be wrapped
+    breaks: Vec<usize>,
+    /// Whether the line ends with a comment, and cannot be wrapped
+    has_comment: bool,
+}
+
+struct Formatter<'o> {
+    options: &'o FormatOptions,
+    /// Indentation of one level
+    indent: String,
+    output: String,
+    /// Blocks open at the current position
+    blocks: Vec<Block>,
+    /// Offset in `output` of the start of each line of the page
+    line_starts: Vec<usize>,
+}
+
+impl<'o> Formatter<'o> {
+    fn new(options: &'o FormatOptions, capacity: usize) -> Self {
+        Formatter {
+            options,
+            indent: options.indent(),
+            output: String::with_capacity(capacity),
+            blocks: Vec::new(),
+            line_starts: vec![0],
+        }
+    }
+
+    /// Writes text of the page, keeping track of its line breaks
+    fn copy(&mut self, text: &str) {
+        let offset = self.output.len();
+        self.line_starts.extend(
+            text.match_indices('\n')
+                .map(|(index, _)| offset + index + 1),
+        );
+        self.output.push_str(text);
+    }
+
+    fn page(&mut self, tokens: &[Token<'_>], language: ScriptLanguage) {
+        let mut index = 0;
+        while index < tokens.len() {
+            let open = &tokens[index];
+            let block_language = match open.kind {
+                TokenKind::OpenTag | TokenKind::OpenExpressionTag => language,
+                TokenKind::ScriptOpenTag => {
+                    script_language_attribute(open.text).unwrap_or(language)
+                }
+                _ => {
+                    self.copy(open.text);
+                    index += 1;
+                    continue;
+                }
+            };
+            let end = tokens[index + 1..]
+                .iter()
+                .position(|token| {
+                    matches!(token.kind, TokenKind::CloseTag | TokenKind::ScriptCloseTag)
+                })
+                .map_or(tokens.len(), |position| index + 1 + position);
+            let code = &tokens[index + 1..end];
+            let close = tokens.get(end).map_or("", |token| token.text);
+            if block_language == ScriptLanguage::VBScript {
+                self.block(open, code, close);
+            } else {
+                self.verbatim(open, code, close);
+            }
+            index = end + 1;
+        }
+    }
+
+    /// Copies an ASP block as it is
+    fn verbatim(&mut self, open: &Token<'_>, code: &[Token<'_>], close: &str) {
+        self.copy(open.text);
+        for token in code {
+            self.copy(token.text);
+        }
+        self.copy(close);
+    }
+
+    /// Formats an ASP block or server script block
+    ///
+    /// `close` is empty if the block is not closed.
+    fn block(&mut self, open: &Token<'_>, code: &[Token<'_>], close: &str) {
+        let is_expression = open.kind == TokenKind::OpenExpressionTag;
+        let lines = split_lines(code);
+        if code
+            .iter()
+            .all(|token| token.kind.is_trivia() && token.kind != TokenKind::Comment)
+            || (is_expression && lines.len() > 1)
+        {
+            self.verbatim(open, code, close);
+            return;
+        }
+        let depths = if is_expression {
+            vec![self.blocks.len()]
+        } else {
+            self.depths(&lines)
+        };
+
+        // Code on the line of the tags stays there
+        if lines.len() == 1 {
+            self.copy(open.text);
+            self.output.push(' ');
+            let line = self.format_line(lines[0].tokens, &mut None);
+            self.output.push_str(&line.text);
+            if !close.is_empty() {
+                self.output.push(' ');
+                self.copy(close);
+            }
+            return;
+        }
+
+        // Other lines are indented from the line of the opening tag
+        let line_start = self.output.rfind('\n').map_or(0, |index| index + 1);
+        let base: String = self.output[line_start..]
+            .chars()
+            .take_while(|c| matches!(c, ' ' | '\t'))
+            .collect();
+        let newline = lines
+            .iter()
+            .map(|line| line.newline)
+            .find(|newline| !newline.is_empty())
+            .unwrap_or("\n");
+        self.copy(open.text);
+        let mut previous = None;
+        // Indentation of the lines continuing the current statement
+        let mut continuation: Option<String> = None;
+        for (index, line) in lines.iter().enumerate() {
+            let formatted = self.format_line(line.tokens, &mut previous);
+            let is_last = index + 1 == lines.len();
+            let indent = match &continuation {
+                Some(indent) => indent.clone(),
+                None => format!("{}{}", base, self.indent.repeat(depths[index])),
+            };
+            let continuation_indent = match &continuation {
+                Some(indent) => indent.clone(),
+                None if index == 0 => format!("{}{}", base, self.indent.repeat(depths[0] + 1)),
+                None => self.continuation_indent(&indent, &formatted.text),
+            };
+            if index == 0 {
+                if !formatted.text.is_empty() {
+                    self.output.push(' ');
+                    self.output.push_str(&formatted.text);
+                }
+            } else if !formatted.text.is_empty() {
+                self.output.push_str(&indent);
+                for (piece, text) in self
+                    .wrap(&formatted, &indent, &continuation_indent)
+                    .into_iter()
+                    .enumerate()
+                {
+                    if piece > 0 {
+                        self.output.push_str(" _");
+                        self.output.push_str(newline);
+                        self.output.push_str(&continuation_indent);
+                    }
+                    self.output.push_str(text);
+                }
+                if is_last && !close.is_empty() {
+                    self.output.push(' ');
+                }
+            } else if is_last {
+                self.output.push_str(&base);
+            }
+            if is_last {
+                self.copy(close);
+            } else {
+                self.copy(line.newline);
+            }
+            let continues = line
+                .tokens
+                .last()
+                .is_some_and(|token| token.kind == TokenKind::LineContinuation);
+            continuation = (continues && index > 0).then_some(continuation_indent);
+        }
+    }
+
+    /// Returns the indentation of the lines continuing a statement, from the
+    /// indentation and text of its first line
+    fn continuation_indent(&self, indent: &str, text: &str) -> String {
+        if self.options.align_dims {
+            // Declared names start after the keywords of the declaration
+            let mut column = 0;
+            let mut words = text.split(' ');
+            let mut word = words.next();
+            while let Some(keyword) = word.filter(|word| {
+                ["dim", "redim", "preserve", "const", "public", "private"]
+                    .contains(&word.to_ascii_lowercase().as_str())
+            }) {
+                column += keyword.len() + 1;
+                word = words.next();
+            }
+            let is_procedure = word.is_some_and(|word| {
+                ["sub", "function", "property", "class", "default"]
+                    .contains(&word.to_ascii_lowercase().as_str())
+            });
+            if column > 0 && !is_procedure {
+                return format!("{}{}", indent, " ".repeat(column));
+            }
+        }
+        format!("{}{}", indent, self.indent)
+    }
+
+    /// Splits a line longer than the maximum length at its breaks, returning
+    /// the pieces of text to write on each line
+    ///
+    /// When no break leaves a short enough piece, the line is split at the first
+    /// break, so that formatting the result again gives the same lines.
+    fn wrap<'l>(&self, line: &'l FormattedLine, indent: &str, continuation: &str) -> Vec<&'l str> {
+        let Some(max) = self.options.max_line_length else {
+            return vec![&line.text];
+        };
+        if line.has_comment {
+            return vec![&line.text];
+        }
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        let mut width = self.options.width(indent);
+        loop {
+            let rest = &line.text[start..];
+            if width + self.options.width(rest) <= max {
+                pieces.push(rest);
+                break;
+            }
+            let breaks = line.breaks.iter().copied().filter(|&at| at > start);
+            let first = breaks.clone().next();
+            // The piece is followed by ` _`
+            let fitting = breaks
+                .take_while(|&at| width + self.options.width(&line.text[start..at]) + 2 <= max)
+                .last();
+            let Some(at) = fitting.or(first) else {
+                pieces.push(rest);
+                break;
+            };
+            pieces.push(&line.text[start..at]);
+            start = at + 1;
+            width = self.options.width(continuation);
+        }
+        pieces
+    }
+
+    /// Updates the open blocks with the statements of an ASP block, and returns
+    /// the indentation level of each of its lines
+    fn depths(&mut self, lines: &[Line<'_, '_>]) -> Vec<usize> {
+        let mut depths: Vec<Option<usize>> = vec![None; lines.len()];
+        let mut statement: Vec<&str> = Vec::new();
+        let mut start_line = 0;
+        for (index, line) in lines.iter().enumerate() {
+            for token in line.tokens {
+                if token.kind == TokenKind::Punctuation && token.text == ":" {
+                    self.statement(&statement, start_line, index, &mut depths);
+                    statement.clear();
+                } else if !token.kind.is_trivia() {
+                    if statement.is_empty() {
+                        start_line = index;
+                    }
+                    statement.push(token.text);
+                }
+            }
+            let continues = line
+                .tokens
+                .last()
+                .is_some_and(|token| token.kind == TokenKind::LineContinuation);
+            if !continues {
+                self.statement(&statement, start_line, index, &mut depths);
+                statement.clear();
+                // Blank lines and comments are indented like the next statement
+                depths[index].get_or_insert(self.blocks.len());
+            }
+        }
+        self.statement(&statement, start_line, lines.len() - 1, &mut depths);
+        let depth = self.blocks.len();
+        depths
+            .into_iter()
+            .map(|line_depth| line_depth.unwrap_or(depth))
+            .collect()
+    }
+
+    /// Updates the open blocks with a statement written from `start_line` to
+    /// `end_line`, and sets the indentation level of these lines
+    fn statement(
+        &mut self,
+        words: &[&str],
+        start_line: usize,
+        end_line: usize,
+        depths: &mut [Option<usize>],
+    ) {
+        if words.is_empty() {
+            return;
+        }
+        let effect = statement_effect(words);
+        match effect {
+            Effect::Close | Effect::Branch => {
+                self.blocks.pop();
+            }
+            Effect::EndSelect => {
+                if self.blocks.last() == Some(&Block::Case) {
+                    self.blocks.pop();
+                }
+                self.blocks.pop();
+            }
+            Effect::Case => {
+                if self.blocks.last() == Some(&Block::Case) {
+                    self.blocks.pop();
+                }
+            }
+            Effect::Open(_) | Effect::None => {}
+        }
+        let depth = *depths[start_line].get_or_insert(self.blocks.len());
+        // Continuation lines are indented one more level
+        for line_depth in &mut depths[start_line + 1..=end_line] {
+            *line_depth = Some(depth + 1);
+        }
+        match effect {
+            Effect::Open(block) => self.blocks.push(block),
+            Effect::Branch => self.blocks.push(Block::Body),
+            Effect::Case => self.blocks.push(Block::Case),
+            Effect::Close | Effect::EndSelect | Effect::None => {}
+        }
+    }
+}
+
+/// Splits the tokens of an ASP block into lines
+fn split_lines<'t, 'a>(code: &'t [Token<'a>]) -> Vec<Line<'t, 'a>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (index, token) in code.iter().enumerate() {
+        let newline = match token.kind {
+            TokenKind::Newline => token.text,
+            TokenKind::LineContinuation => token.text[1..].trim_start_matches([' ', '\t']),
+            _ => continue,
+        };
+        // Line breaks are kept out of the line, line continuations in it
+        let end = if token.kind == TokenKind::Newline {
+            index
+        } else {
+            index + 1
+        };
+        lines.push(Line {
+            tokens: &code[start..end],
+            newline,
+        });
+        start = index + 1;
+    }
+    lines.push(Line {
+        tokens: &code[start..],
+        newline: "",
+    });
+    lines
+}
+
+/// Returns the effect of a statement on the open blocks, from its words
+fn statement_effect(words: &[&str]) -> Effect {
+    let word = |index: usize| {
+        words
+            .get(index)
+            .map_or(String::new(), |word| word.to_ascii_lowercase())
+    };
+    // Procedures and classes may be preceded by modifiers
+    let first = words
+        .iter()
+        .position(|word| {
+            !["public", "private", "default"].contains(&word.to_ascii_lowercase().as_str())
+        })
+        .unwrap_or(0);
+    match word(first).as_str() {
+        "end" => match word(first + 1).as_str() {
+            "select" => Effect::EndSelect,
+            "if" | "sub" | "function" | "property" | "class" | "with" => Effect::Close,
+            _ => Effect::None,
+        },
+        "next" | "loop" | "wend" => Effect::Close,
+        "else" | "elseif" => Effect::Branch,
+        "case" => Effect::Case,
+        "select" => Effect::Open(Block::Select),
+        "sub" | "function" | "property" | "class" | "for" | "do" | "while" | "with" => {
+            Effect::Open(Block::Body)
+        }
+        // `If` opens a block unless its statements follow `Then` on the same line
+        "if" if words
+            .last()
+            .is_some_and(|word| word.eq_ignore_ascii_case("then")) =>
+        {
+            Effect::Open(Block::Body)
+        }
+        _ => Effect::None,
+    }
+}
+
+/// The last token written on a line
+struct Previous<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    /// Whether the token is a unary `-` or `+`
+    unary: bool,
+}
+
+impl Previous<'_> {
+    fn is(&self, punctuation: &str) -> bool {
+        self.kind == TokenKind::Punctuation && self.text == punctuation
+    }
+
+    /// Whether the token ends an operand, which may be followed by a binary
+    /// operator, a member or arguments
+    fn ends_operand(&self) -> bool {
+        match self.kind {
+            TokenKind::Identifier | TokenKind::String | TokenKind::Number | TokenKind::Date => true,
+            TokenKind::Keyword => ["true", "false", "nothing", "null", "empty"]
+                .contains(&self.text.to_ascii_lowercase().as_str()),
+            TokenKind::Punctuation => self.text == ")",
+            _ => false,
+        }
+    }
+}
+
+impl Formatter<'_> {
+    /// Formats the tokens of a line, without its indentation
+    ///
+    /// `previous` is the last token of the statement continued on the line, and
+    /// is updated with the last token of the line if its statement continues on
+    /// the next one.
+    fn format_line<'a>(
+        &self,
+        tokens: &[Token<'a>],
+        previous: &mut Option<Previous<'a>>,
+    ) -> FormattedLine {
+        let mut line = FormattedLine {
+            text: String::new(),
+            breaks: Vec::new(),
+            has_comment: false,
+        };
+        let text = &mut line.text;
+        let mut whitespace = "";
+        for token in tokens {
+            match token.kind {
+                TokenKind::Whitespace => {
+                    whitespace = token.text;
+                    continue;
+                }
+                // Comments keep their text and the space before them
+                TokenKind::Comment => {
+                    if !text.is_empty() {
+                        text.push_str(whitespace);
+                    }
+                    text.push_str(token.text.trim_end());
+                    line.has_comment = true;
+                    continue;
+                }
+                TokenKind::LineContinuation => {
+                    if !text.is_empty() {
+                        text.push(' ');
+                    }
+                    text.push('_');
+                    continue;
+                }
+                _ => {}
+            }
+            let after_dot = previous.as_ref().is_some_and(|previous| previous.is("."));
+            let keyword = match token.kind {
+                TokenKind::Keyword | TokenKind::Operator if !after_dot => {
+                    vbscript_keyword(token.text)
+                }
+                _ => None,
+            };
+            let unary = token.kind == TokenKind::Operator
+                && matches!(token.text, "-" | "+")
+                && previous
+                    .as_ref()
+                    .is_none_or(|previous| !previous.ends_operand());
+            if let Some(previous) = previous.as_ref()
+                && !text.is_empty()
+                && spaced(previous, token, !whitespace.is_empty())
+            {
+                // Lines are wrapped after commas and before binary operators,
+                // but not in assignments
+                let is_binary = token.kind == TokenKind::Operator && !unary && token.text != "=";
+                if previous.is(",") || is_binary {
+                    line.breaks.push(text.len());
+                }
+                text.push(' ');
+            }
+            match (keyword, self.options.keyword_case) {
+                (Some(keyword), KeywordCase::Pascal) => text.push_str(keyword),
+                (Some(keyword), KeywordCase::Lower) => text.push_str(&keyword.to_ascii_lowercase()),
+                (None, _) => text.push_str(token.text),
+            }
+            *previous = Some(Previous {
+                kind: token.kind,
+                text: token.text,
+                unary,
+            });
+            whitespace = "";
+        }
+        if tokens
+            .last()
+            .is_none_or(|token| token.kind != TokenKind::LineContinuation)
+        {
+            *previous = None;
+        }
+        line
+    }
+}
+
+/// Whether two tokens of a line are separated by a space, `whitespace` telling
+/// whether they were
+fn spaced(previous: &Previous<'_>, next: &Token<'_>, whitespace: bool) -> bool {
+    if next.kind == TokenKind::Punctuation {
+        match next.text {
+            "," | ")" => return false,
+            ":" => return true,
+            _ => {}
+        }
+    }
+    if previous.is("(") || previous.is(".") || previous.unary {
+        return false;
+    }
+    if previous.is(",") || previous.is(":") {
+        return true;
+    }
+    // A space before a member or arguments can change the meaning of the code
+    // (`Write .Name`, `Call Show (x)`), so it is kept
+    if next.kind == TokenKind::Punctuation && matches!(next.text, "." | "(") {
+        return !previous.ends_operand() || whitespace;
+    }
+    if previous.kind == TokenKind::Operator || next.kind == TokenKind::Operator {
+        return true;
+    }
+    let is_word = |kind: TokenKind| {
+        matches!(
+            kind,
+            TokenKind::Keyword
+                | TokenKind::Identifier
+                | TokenKind::String
+                | TokenKind::Number
+                | TokenKind::Date
+        )
+    };
+    if (is_word(previous.kind) || previous.is(")")) && is_word(next.kind) {
+        return true;
+    }
+    whitespace
+}
+
+/// Number of unchanged lines shown around the changes of a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Returns the changes made by formatting a file as a unified diff, empty if
+/// there are none
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, shown in the diff header
+/// * `original` - The content of the file
+/// * `formatted` - The formatted content
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::formatter;
+///
+/// let original = "<%\nif x then\ny\nend if\n%>\n";
+/// let diff = formatter::diff("page.asp", original, &formatter::format(original));
+/// assert_eq!(
+///     diff,
+///     "--- page.asp\n+++ page.asp\n@@ -1,5 +1,5 @@\n <%\n-if x then\n-y\n-end if\n+If x Then\n+    y\n+End If\n %>\n"
+/// );
+/// ```
+pub fn diff(path: &str, original: &str, formatted: &str) -> String {
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = formatted.split_inclusive('\n').collect();
+    let edits = edit_script(&old, &new);
+    if edits.iter().all(|edit| matches!(edit, Edit::Equal(..))) {
+        return String::new();
+    }
+
+    let mut output = format!("--- {}\n+++ {}\n", path, path);
+    let mut index = 0;
+    while let Some(first_change) = edits[index..]
+        .iter()
+        .position(|edit| !matches!(edit, Edit::Equal(..)))
+    {
+        // A hunk goes on while changes are separated by few unchanged lines
+        let start = (index + first_change).saturating_sub(DIFF_CONTEXT);
+        let mut end = index + first_change;
+        let mut unchanged = 0;
+        for (position, edit) in edits.iter().enumerate().skip(end) {
+            if matches!(edit, Edit::Equal(..)) {
+                unchanged += 1;
+                if unchanged > 2 * DIFF_CONTEXT {
+                    break;
+                }
+            } else {
+                unchanged = 0;
+                end = position + 1;
+            }
+        }
+        let end = (end + DIFF_CONTEXT).min(edits.len());
+        let hunk = &edits[start..end];
+
+        let (old_start, new_start) = match edits[start] {
+            Edit::Equal(old_line, new_line) => (old_line, new_line),
+            Edit::Delete(old_line) => (old_line, new_line_before(&edits[..start])),
+            Edit::Insert(new_line) => (old_line_before(&edits[..start]), new_line),
+        };
+        let old_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Insert(_)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|edit| !matches!(edit, Edit::Delete(_)))
+            .count();
+        output.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count)
+        ));
+        for edit in hunk {
+            let (prefix, line) = match *edit {
+                Edit::Equal(old_line, _) => (' ', old[old_line]),
+                Edit::Delete(old_line) => ('-', old[old_line]),
+                Edit::Insert(new_line) => ('+', new[new_line]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        index = end;
+    }
+    output
+}
+
+/// An operation turning a list of lines into another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// A line of both lists, by index in each
+    Equal(usize, usize),
+    /// A line of the first list only
+    Delete(usize),
+    /// A line of the second list only
+    Insert(usize),
+}
+
+/// Returns the index of the first line of the old file after some edits
+fn old_line_before(edits: &[Edit]) -> usize {
+    edits
+        .iter()
+        .filter(|edit| !matches!(edit, Edit::Insert(_)))
+        .count()
+}
+
+/// Returns the index of the first line of the new file after some edits
+fn new_line_before(edits: &[Edit]) -> usize {
+    edits
+        .iter()
+        .filter(|edit| !matches!(edit, Edit::Delete(_)))
+        .count()
+}
+
+/// Formats the range of lines of a hunk, from a 0-based index
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range is given by the line before it
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+/// Returns the shortest edit script turning `old` into `new` (Myers' algorithm)
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    // Common lines at both ends are not searched
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    // Furthest position reached on each diagonal k = x - y, for each number of
+    // edits
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let offset = n + m + 1;
+    let mut furthest = vec![0isize; 2 * offset as usize + 1];
+    let mut trace = Vec::new();
+    'search: for edits in 0..=n + m {
+        trace.push(furthest.clone());
+        for k in (-edits..=edits).step_by(2) {
+            let index = (k + offset) as usize;
+            let mut x = if k == -edits || (k != edits && furthest[index - 1] < furthest[index + 1])
+            {
+                furthest[index + 1]
+            } else {
+                furthest[index - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            furthest[index] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk back from the end through the recorded positions
+    let mut middle = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (edits, furthest) in trace.iter().enumerate().rev() {
+        let edits = edits as isize;
+        let k = x - y;
+        let index = (k + offset) as usize;
+        let previous_k = if k == -edits || (k != edits && furthest[index - 1] < furthest[index + 1])
+        {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = if edits == 0 {
+            0
+        } else {
+            furthest[(previous_k + offset) as usize]
+        };
+        let previous_y = previous_x - previous_k;
+        while x > previous_x.max(0) && y > previous_y.max(0) {
+            x -= 1;
+            y -= 1;
+            middle.push(Edit::Equal(x as usize, y as usize));
+        }
+        if edits > 0 {
+            if x == previous_x {
+                middle.push(Edit::Insert(previous_y as usize));
+            } else {
+                middle.push(Edit::Delete(previous_x as usize));
+            }
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    middle.reverse();
+
+    let shift = |edit: Edit| match edit {
+        Edit::Equal(x, y) => Edit::Equal(x + prefix, y + prefix),
+        Edit::Delete(x) => Edit::Delete(x + prefix),
+        Edit::Insert(y) => Edit::Insert(y + prefix),
+    };
+    (0..prefix)
+        .map(|line| Edit::Equal(line, line))
+        .chain(middle.into_iter().map(shift))
+        .chain(
+            (0..suffix)
+                .map(|line| Edit::Equal(old.len() - suffix + line, new.len() - suffix + line)),
+        )
+        .collect()
+}
@@ -0,0 +1,490 @@
+//! Resolution of `#include` directives
+//!
+//! ASP pages share code through server-side includes:
+//!
+//! - `<!--#include file="lib/db.asp" -->` is relative to the directory of the
+//!   including file;
+//! - `<!--#include virtual="/lib/db.asp" -->` is relative to the web root of the
+//!   site, or to the directory of a virtual directory such as `/lib`.
+//!
+//! [`IncludeGraph::build`] starts from a set of pages, loads every file they include,
+//! directly or not, and records which file includes which. Missing files, virtual
+//! includes without a web root and include cycles are reported as [`IncludeError`]s
+//! instead of stopping the resolution.
+//!
+//! ```no_run
+//! use asp_classic_parser::includes::{IncludeGraph, IncludeResolver};
+//! use std::path::PathBuf;
+//!
+//! let resolver = IncludeResolver::new().with_web_root("/var/www/site");
+//! let graph = IncludeGraph::build(&[PathBuf::from("/var/www/site/orders.asp")], &resolver);
+//! for file in graph.transitive_includes(0) {
+//!     println!("{}", graph.files[file].path.display());
+//! }
+//! for error in &graph.errors {
+//!     eprintln!("{}", error);
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::file_utils::read_file_with_encoding;
+use crate::parser::ast::{LineIndex, Span};
+use crate::parser::{self, lexer::TokenKind};
+
+/// How the path of an include directive is interpreted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum IncludeKind {
+    /// `file="..."`: relative to the directory of the including file
+    File,
+    /// `virtual="..."`: relative to the web root
+    Virtual,
+}
+
+/// An `<!--#include file="..." -->` or `<!--#include virtual="..." -->` directive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IncludeDirective {
+    /// Whether the path is relative to the file or to the web root
+    pub kind: IncludeKind,
+    /// Included path, as written
+    pub path: String,
+    /// Location of the whole directive
+    pub span: Span,
+}
+
+/// Returns the include directives of a page
+///
+/// Directives are searched in the HTML text only, so that directives in comments or
+/// strings of server code are skipped. Directives without a `file` or `virtual`
+/// attribute are ignored.
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+///
+/// # Returns
+///
+/// * `Vec<IncludeDirective>` - The include directives, in source order
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::includes::{self, IncludeKind};
+///
+/// let directives = includes::directives("<!--#include virtual=\"/lib/db.asp\" -->\n<% Connect %>");
+/// assert_eq!(directives[0].kind, IncludeKind::Virtual);
+/// assert_eq!(directives[0].path, "/lib/db.asp");
+/// ```
+pub fn directives(source: &str) -> Vec<IncludeDirective> {
+    let lines = LineIndex::new(source);
+    parser::tokenize(source)
+        .into_iter()
+        .filter(|token| token.kind == TokenKind::Html)
+        .flat_map(|token| {
+            let html = token.text.to_ascii_lowercase();
+            html.match_indices("<!--#include")
+                .filter_map(|(index, _)| {
+                    let start = token.span.start + index;
+                    let end = source[start..]
+                        .find("-->")
+                        .map_or(token.span.end, |end| start + end + 3);
+                    let (kind, path) = directive_path(&source[start..end])?;
+                    Some(IncludeDirective {
+                        kind,
+                        path,
+                        span: lines.span(start, end),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the kind and path of an include directive
+fn directive_path(directive: &str) -> Option<(IncludeKind, String)> {
+    let rest = directive["<!--#include".len()..].trim_start();
+    let (kind, rest) = if starts_with_ignore_case(rest, "file") {
+        (IncludeKind::File, &rest["file".len()..])
+    } else if starts_with_ignore_case(rest, "virtual") {
+        (IncludeKind::Virtual, &rest["virtual".len()..])
+    } else {
+        return None;
+    };
+    let rest = rest.trim_start().strip_prefix('=')?.trim_start();
+    let path = match rest.chars().next()? {
+        quote @ ('"' | '\'') => rest[1..].split(quote).next()?,
+        _ => rest.split(|c: char| c.is_whitespace() || c == '-').next()?,
+    };
+    (!path.is_empty()).then(|| (kind, path.to_string()))
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+}
+
+/// Errors found while resolving includes
+#[derive(Error, Debug, Clone, PartialEq, Serialize)]
+pub enum IncludeError {
+    #[error("{}:{}: included file not found: {path}", file.display(), span.line)]
+    NotFound {
+        /// Including file
+        file: PathBuf,
+        /// Included path, as written
+        path: String,
+        /// Location of the directive
+        span: Span,
+    },
+
+    #[error("{}:{}: virtual include without a web root: {path}", file.display(), span.line)]
+    NoWebRoot {
+        /// Including file
+        file: PathBuf,
+        /// Included path, as written
+        path: String,
+        /// Location of the directive
+        span: Span,
+    },
+
+    #[error("{}: failed to read file: {message}", file.display())]
+    Read {
+        /// File that could not be read
+        file: PathBuf,
+        /// Description of the error
+        message: String,
+    },
+
+    #[error("include cycle: {}", cycle.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join(" -> "))]
+    Cycle {
+        /// Files of the cycle, starting and ending with the same file
+        cycle: Vec<PathBuf>,
+    },
+}
+
+/// Finds the files named by include directives
+#[derive(Debug, Clone, Default)]
+pub struct IncludeResolver {
+    /// Directory `virtual` paths are relative to
+    web_root: Option<PathBuf>,
+    /// Virtual directories, as virtual paths without trailing slash and the
+    /// directories they are mapped to
+    virtual_paths: Vec<(String, PathBuf)>,
+}
+
+impl IncludeResolver {
+    /// Creates a resolver without a web root, which only resolves `file` includes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directory `virtual` includes are relative to
+    pub fn with_web_root(mut self, web_root: impl Into<PathBuf>) -> Self {
+        self.web_root = Some(web_root.into());
+        self
+    }
+
+    /// Maps a virtual directory, such as `/shared`, to a directory
+    ///
+    /// `virtual` includes under the virtual directory are relative to the
+    /// directory instead of the web root, the longest matching virtual directory
+    /// winning.
+    pub fn with_virtual_path(mut self, virtual_path: &str, directory: impl Into<PathBuf>) -> Self {
+        let virtual_path = format!("/{}", virtual_path.replace('\\', "/").trim_matches('/'));
+        self.virtual_paths.push((virtual_path, directory.into()));
+        self
+    }
+
+    /// Returns the web root, if any
+    pub fn web_root(&self) -> Option<&Path> {
+        self.web_root.as_deref()
+    }
+
+    /// Returns the path of the file named by a directive of `including_file`
+    ///
+    /// The path is not checked to exist. Returns `None` for a `virtual` include
+    /// outside of the virtual directories when there is no web root.
+    pub fn resolve(&self, including_file: &Path, directive: &IncludeDirective) -> Option<PathBuf> {
+        let path = directive.path.replace('\\', "/");
+        let path = match directive.kind {
+            IncludeKind::File => including_file
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(path.trim_start_matches('/')),
+            IncludeKind::Virtual => {
+                let path = format!("/{}", path.trim_start_matches('/'));
+                match self.virtual_directory(&path) {
+                    Some((directory, rest)) => directory.join(rest.trim_start_matches('/')),
+                    None => self.web_root.as_deref()?.join(&path[1..]),
+                }
+            }
+        };
+        Some(normalize(&path))
+    }
+
+    /// Returns the directory of the longest virtual directory containing a
+    /// virtual path, and the path relative to it
+    fn virtual_directory<'a>(&self, path: &'a str) -> Option<(&Path, &'a str)> {
+        self.virtual_paths
+            .iter()
+            .filter_map(|(virtual_path, directory)| {
+                // IIS virtual directories are not case sensitive
+                let rest = match path.get(..virtual_path.len()) {
+                    Some(prefix) if prefix.eq_ignore_ascii_case(virtual_path) => {
+                        &path[virtual_path.len()..]
+                    }
+                    _ => return None,
+                };
+                (rest.is_empty() || rest.starts_with('/') || virtual_path == "/").then_some((
+                    virtual_path.len(),
+                    directory.as_path(),
+                    rest,
+                ))
+            })
+            .max_by_key(|(length, _, _)| *length)
+            .map(|(_, directory, rest)| (directory, rest))
+    }
+}
+
+/// Returns the path identifying a file in include graphs and indexes
+///
+/// The path is canonicalized when the file exists, so that a file reached through
+/// different relative paths or symbolic links is loaded once; otherwise `.` and `..`
+/// components are removed.
+pub fn canonical_path(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| normalize(path))
+}
+
+/// Removes `.` and `..` components from a path, without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// A file of an include graph
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncludedFile {
+    /// Path of the file, canonicalized when possible
+    pub path: PathBuf,
+    /// Content of the file; empty if it could not be read
+    pub source: String,
+    /// Include directives of the file
+    pub directives: Vec<IncludeDirective>,
+}
+
+/// A file including another one
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IncludeEdge {
+    /// Index of the including file in [`IncludeGraph::files`]
+    pub from: usize,
+    /// Index of the included file in [`IncludeGraph::files`]
+    pub to: usize,
+    /// The include directive
+    pub directive: IncludeDirective,
+}
+
+/// Files of a project and the includes between them
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IncludeGraph {
+    /// The files, starting with the root pages in the given order
+    pub files: Vec<IncludedFile>,
+    /// The resolved includes, in the order of the files and of their directives
+    pub edges: Vec<IncludeEdge>,
+    /// Includes that could not be resolved, unreadable files and include cycles
+    pub errors: Vec<IncludeError>,
+}
+
+impl IncludeGraph {
+    /// Loads the given pages and every file they include, directly or not
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The pages to start from
+    /// * `resolver` - Resolves the paths of the include directives
+    ///
+    /// # Returns
+    ///
+    /// * `IncludeGraph` - The loaded files, the includes between them and the
+    ///   resolution errors
+    pub fn build(roots: &[PathBuf], resolver: &IncludeResolver) -> Self {
+        let mut graph = IncludeGraph::default();
+        let mut indices = HashMap::new();
+        for root in roots {
+            graph.add_file(root, &mut indices);
+        }
+
+        // Files are appended while their includers are processed
+        let mut next = 0;
+        while next < graph.files.len() {
+            let from = next;
+            next += 1;
+            let path = graph.files[from].path.clone();
+            for directive in graph.files[from].directives.clone() {
+                let Some(included) = resolver.resolve(&path, &directive) else {
+                    graph.errors.push(IncludeError::NoWebRoot {
+                        file: path.clone(),
+                        path: directive.path,
+                        span: directive.span,
+                    });
+                    continue;
+                };
+                if !included.is_file() {
+                    graph.errors.push(IncludeError::NotFound {
+                        file: path.clone(),
+                        path: directive.path,
+                        span: directive.span,
+                    });
+                    continue;
+                }
+                let to = graph.add_file(&included, &mut indices);
+                graph.edges.push(IncludeEdge {
+                    from,
+                    to,
+                    directive,
+                });
+            }
+        }
+
+        for cycle in graph.cycles() {
+            graph.errors.push(IncludeError::Cycle {
+                cycle: cycle
+                    .iter()
+                    .map(|&file| graph.files[file].path.clone())
+                    .collect(),
+            });
+        }
+        graph
+    }
+
+    /// Adds a file to the graph if it is not there yet, and returns its index
+    fn add_file(&mut self, path: &Path, indices: &mut HashMap<PathBuf, usize>) -> usize {
+        let path = canonical_path(path);
+        if let Some(&index) = indices.get(&path) {
+            return index;
+        }
+        let source = match read_file_with_encoding(&path) {
+            Ok(source) => source,
+            Err(error) => {
+                self.errors.push(IncludeError::Read {
+                    file: path.clone(),
+                    message: error.to_string(),
+                });
+                String::new()
+            }
+        };
+        let index = self.files.len();
+        indices.insert(path.clone(), index);
+        self.files.push(IncludedFile {
+            directives: directives(&source),
+            path,
+            source,
+        });
+        index
+    }
+
+    /// Returns the index of a file of the graph
+    pub fn file(&self, path: &Path) -> Option<usize> {
+        let path = canonical_path(path);
+        self.files.iter().position(|file| file.path == path)
+    }
+
+    /// Returns the files directly included by a file
+    pub fn includes_of(&self, file: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.from == file)
+            .map(|edge| edge.to)
+    }
+
+    /// Returns the files directly including a file
+    pub fn included_by(&self, file: usize) -> impl Iterator<Item = usize> + '_ {
+        self.edges
+            .iter()
+            .filter(move |edge| edge.to == file)
+            .map(|edge| edge.from)
+    }
+
+    /// Returns the files a file includes, directly or not, once each
+    ///
+    /// Files are listed in the order IIS inlines them: each included file comes
+    /// before the files it includes itself. The file itself is not listed, even
+    /// through a cycle.
+    pub fn transitive_includes(&self, file: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.files.len()];
+        seen[file] = true;
+        let mut result = Vec::new();
+        self.collect_includes(file, &mut seen, &mut result);
+        result
+    }
+
+    fn collect_includes(&self, file: usize, seen: &mut [bool], result: &mut Vec<usize>) {
+        for included in self.includes_of(file) {
+            if !std::mem::replace(&mut seen[included], true) {
+                result.push(included);
+                self.collect_includes(included, seen, result);
+            }
+        }
+    }
+
+    /// Returns the include cycles of the graph
+    ///
+    /// Each cycle lists its files starting and ending with the same file; a file
+    /// including itself gives a cycle of two entries.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Active,
+            Done,
+        }
+
+        fn visit(
+            graph: &IncludeGraph,
+            file: usize,
+            states: &mut [State],
+            stack: &mut Vec<usize>,
+            cycles: &mut Vec<Vec<usize>>,
+        ) {
+            states[file] = State::Active;
+            stack.push(file);
+            for included in graph.includes_of(file) {
+                match states[included] {
+                    State::New => visit(graph, included, states, stack, cycles),
+                    State::Active => {
+                        let start = stack.iter().position(|&f| f == included).unwrap_or(0);
+                        let mut cycle = stack[start..].to_vec();
+                        cycle.push(included);
+                        cycles.push(cycle);
+                    }
+                    State::Done => {}
+                }
+            }
+            stack.pop();
+            states[file] = State::Done;
+        }
+
+        let mut states = vec![State::New; self.files.len()];
+        let mut cycles = Vec::new();
+        for file in 0..self.files.len() {
+            if states[file] == State::New {
+                visit(self, file, &mut states, &mut Vec::new(), &mut cycles);
+            }
+        }
+        cycles
+    }
+}
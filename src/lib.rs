@@ -8,6 +8,21 @@
 // Export the parser module publicly
 pub mod parser;
 
+// Export the semantic analysis module
+pub mod analysis;
+
+// Export the include resolution module
+pub mod includes;
+
+// Export the lint module
+pub mod lint;
+
+// Export the code metrics module
+pub mod metrics;
+
+// Export the code formatter
+pub mod formatter;
+
 // Export the file utilities module
 pub mod file_utils;
 
@@ -20,8 +35,25 @@ pub mod output_format;
 // Export the caching utilities
 pub mod cache;
 
+// Export the remote cache backends
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote_cache;
+
 // Export the self-update utilities
+#[cfg(not(target_arch = "wasm32"))]
 pub mod updater;
 
 // Export the LSP server module
+#[cfg(not(target_arch = "wasm32"))]
 pub mod lsp;
+
+// Export the C API of the dynamic library
+pub mod ffi;
+
+// Export the WebAssembly bindings
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// Export the Python bindings
+#[cfg(feature = "python")]
+pub mod python;
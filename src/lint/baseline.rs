@@ -0,0 +1,186 @@
+//! Known problems to ignore, so that a legacy site can adopt the linter
+//! incrementally
+//!
+//! A baseline records the problems found at a point in time. Checking the site
+//! again with the baseline only reports the problems that are not in it. Problems
+//! are identified by their file, code and message, not by their position, so
+//! that editing a file does not make its known problems reappear; a file with
+//! more problems of a kind than recorded reports the extra ones.
+//!
+//! ```
+//! use asp_classic_parser::lint::{self, Registry};
+//! use asp_classic_parser::lint::baseline::Baseline;
+//! use std::path::Path;
+//!
+//! let registry = Registry::default();
+//! let page = Path::new("site/page.asp");
+//! let old = "<%\nOption Explicit\nx = 1\n%>";
+//!
+//! let mut baseline = Baseline::new(Path::new("site"));
+//! baseline.add(page, &lint::lint(old, &registry));
+//!
+//! let new = "<%\nOption Explicit\nResponse.Write \"\"\nx = 1\ny = 2\n%>";
+//! let diagnostics = baseline.filter(page, lint::lint(new, &registry));
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].message, "'y' is not declared");
+//! ```
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::parser::Diagnostic;
+
+/// Version of the baseline file format
+const VERSION: u32 = 1;
+
+/// A set of known problems
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline {
+    /// Directory the file paths are relative to
+    root: PathBuf,
+    /// Number of known problems of each kind, by file and by code and message
+    problems: BTreeMap<String, BTreeMap<(String, String), usize>>,
+}
+
+/// Content of a baseline file
+#[derive(Serialize, Deserialize)]
+struct BaselineFile {
+    version: u32,
+    problems: Vec<Problem>,
+}
+
+/// Known problems of a kind in a file
+#[derive(Serialize, Deserialize)]
+struct Problem {
+    /// Path of the file, relative to the directory of the baseline, with `/`
+    /// separators
+    file: String,
+    /// Diagnostic code (`ASP010`)
+    code: String,
+    /// Diagnostic message
+    message: String,
+    /// Number of occurrences
+    count: usize,
+}
+
+impl Baseline {
+    /// Creates an empty baseline whose file paths are relative to a directory,
+    /// usually the one of the baseline file
+    pub fn new(root: &Path) -> Self {
+        Baseline {
+            root: root.to_path_buf(),
+            problems: BTreeMap::new(),
+        }
+    }
+
+    /// Reads a baseline file; its paths are relative to its directory, made
+    /// absolute when the file exists
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<Baseline>` - The baseline, or an error if the file cannot be
+    ///   read or is not a baseline
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let file: BaselineFile = serde_json::from_str(&content)?;
+        if file.version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported baseline version {}", file.version),
+            ));
+        }
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mut baseline = Baseline::new(path.parent().unwrap_or(Path::new("")));
+        for problem in file.problems {
+            *baseline
+                .problems
+                .entry(problem.file)
+                .or_default()
+                .entry((problem.code, problem.message))
+                .or_default() += problem.count;
+        }
+        Ok(baseline)
+    }
+
+    /// Writes the baseline as JSON, sorted by file, code and message so that it
+    /// can be kept under version control
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let file = BaselineFile {
+            version: VERSION,
+            problems: self
+                .problems
+                .iter()
+                .flat_map(|(file, problems)| {
+                    problems.iter().map(|((code, message), &count)| Problem {
+                        file: file.clone(),
+                        code: code.clone(),
+                        message: message.clone(),
+                        count,
+                    })
+                })
+                .collect(),
+        };
+        fs::write(path, serde_json::to_string_pretty(&file)? + "\n")
+    }
+
+    /// Records the problems found in a file
+    pub fn add(&mut self, path: &Path, diagnostics: &[Diagnostic]) {
+        let problems = self.problems.entry(self.file(path)).or_default();
+        for diagnostic in diagnostics {
+            *problems.entry(kind(diagnostic)).or_default() += 1;
+        }
+    }
+
+    /// Returns the problems of a file that are not known, in their order
+    pub fn filter(&self, path: &Path, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        let Some(problems) = self.problems.get(&self.file(path)) else {
+            return diagnostics;
+        };
+        let mut remaining = problems.clone();
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| match remaining.get_mut(&kind(diagnostic)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Returns the number of known problems
+    pub fn len(&self) -> usize {
+        self.problems
+            .values()
+            .flat_map(|problems| problems.values())
+            .sum()
+    }
+
+    /// Whether the baseline has no known problems
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the path of a file relative to the root, with `/` separators
+    fn file(&self, path: &Path) -> String {
+        path.strip_prefix(&self.root)
+            .unwrap_or(path)
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Identifies the kind of a problem by its code and message
+fn kind(diagnostic: &Diagnostic) -> (String, String) {
+    (
+        diagnostic.code.as_str().to_string(),
+        diagnostic.message.clone(),
+    )
+}
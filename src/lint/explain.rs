@@ -0,0 +1,373 @@
+//! Explanations of the diagnostic codes
+//!
+//! [`explain`] describes what a code reports, why it matters, how to fix it, and
+//! the options of its rule, for `asp-classic-parser explain ASP014`:
+//!
+//! ```
+//! use asp_classic_parser::lint::explain::explain;
+//! use asp_classic_parser::parser::DiagnosticCode;
+//!
+//! let explanation = explain(DiagnosticCode::MissingSet);
+//! assert!(explanation.good.contains("Set cart = New ShoppingCart"));
+//! assert!(explanation.to_string().starts_with("ASP014 missing-set (error)"));
+//! ```
+
+use std::fmt;
+
+use super::rules;
+use crate::parser::DiagnosticCode;
+
+/// Description of a diagnostic code
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The code explained
+    pub code: DiagnosticCode,
+    /// What the code reports, in one sentence
+    pub summary: &'static str,
+    /// Why the problem matters and how to fix it
+    pub rationale: &'static str,
+    /// Code reported with this code
+    pub bad: &'static str,
+    /// The same code, fixed
+    pub good: &'static str,
+    /// Options of the rule in the `[rules]` table, with their description
+    pub options: &'static [(&'static str, &'static str)],
+}
+
+impl Explanation {
+    /// Whether the code is reported by a lint rule disabled by default
+    pub fn is_optional(&self) -> bool {
+        rules::all()
+            .iter()
+            .any(|rule| rule.code() == self.code && !rule.enabled_by_default())
+    }
+}
+
+impl fmt::Display for Explanation {
+    /// Formats the explanation as plain text for the terminal
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} ({}",
+            self.code.as_str(),
+            self.code.name(),
+            self.code.default_severity().as_str()
+        )?;
+        if self.is_optional() {
+            f.write_str(", disabled by default")?;
+        }
+        writeln!(f, ")\n\n{}\n\n{}", self.summary, self.rationale)?;
+        for (title, code) in [("Reported:", self.bad), ("Fixed:", self.good)] {
+            writeln!(f, "\n{}", title)?;
+            for line in code.lines() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        if !self.options.is_empty() {
+            writeln!(f, "\nOptions ([rules] table of asp-parser.toml):")?;
+            for (name, description) in self.options {
+                writeln!(f, "    {}: {}", name, description)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the explanation of a diagnostic code
+pub fn explain(code: DiagnosticCode) -> Explanation {
+    let (summary, rationale, bad, good, options): (_, _, _, _, &[_]) = match code {
+        DiagnosticCode::SyntaxError => (
+            "The code does not match the VBScript grammar.",
+            "The page would fail to compile on IIS and show an error instead of its content. \
+             The message tells what the parser expected.",
+            "<%\nIf total > 10\n  Response.Write \"big\"\nEnd If\n%>",
+            "<%\nIf total > 10 Then\n  Response.Write \"big\"\nEnd If\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnclosedBlock => (
+            "A block (If, For, Sub, ...) is never closed.",
+            "VBScript requires every block to end with its closing statement; the page does \
+             not compile otherwise. The diagnostic points at the opening statement.",
+            "<%\nFor i = 1 To 10\n  Response.Write i\n%>",
+            "<%\nFor i = 1 To 10\n  Response.Write i\nNext\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnmatchedBlockEnd => (
+            "A block end or branch does not match the innermost open block.",
+            "An `End If` closing a `For`, or a `Next` without `For`, usually means a block end \
+             is missing or misplaced above it.",
+            "<%\nIf ready Then\n  For i = 1 To 3\n  End If\nNext\n%>",
+            "<%\nIf ready Then\n  For i = 1 To 3\n  Next\nEnd If\n%>",
+            &[],
+        ),
+        DiagnosticCode::MisplacedStatement => (
+            "A valid statement appears where it is not allowed.",
+            "VBScript does not allow procedures inside procedures, classes inside classes, or \
+             `Option Explicit` after other code.",
+            "<%\nSub Outer()\n  Sub Inner()\n  End Sub\nEnd Sub\n%>",
+            "<%\nSub Outer()\nEnd Sub\nSub Inner()\nEnd Sub\n%>",
+            &[],
+        ),
+        DiagnosticCode::NoAspTags => (
+            "The file contains no ASP code.",
+            "A .asp file without `<% %>` blocks is plain HTML; it may have the wrong \
+             extension, or its code may have been lost.",
+            "<p>Hello</p>",
+            "<p>Hello <%= Session(\"name\") %></p>",
+            &[],
+        ),
+        DiagnosticCode::EmptyFile => (
+            "The file is empty or contains only whitespace.",
+            "Empty pages are usually leftovers that can be deleted.",
+            "",
+            "<% Response.Redirect \"/\" %>",
+            &[],
+        ),
+        DiagnosticCode::DisallowedLanguage => (
+            "The page is written in a language that is not allowed.",
+            "The parser understands VBScript; pages declaring another language with \
+             `<%@ Language=... %>` are reported when the options only allow VBScript.",
+            "<%@ Language=\"JScript\" %>",
+            "<%@ Language=\"VBScript\" %>",
+            &[],
+        ),
+        DiagnosticCode::ForbiddenInclude => (
+            "The page contains an #include directive while includes are forbidden.",
+            "Includes are forbidden by the parse options, for example when checking \
+             self-contained snippets.",
+            "<!--#include file=\"header.asp\" -->",
+            "<% Server.Execute \"header.asp\" %>",
+            &[],
+        ),
+        DiagnosticCode::UndeclaredName => (
+            "A name is used but declared nowhere, in a page with Option Explicit.",
+            "With `Option Explicit`, using an undeclared variable is a runtime error \
+             (\"Variable is undefined\"). It is often a typo. Declarations in included files \
+             are taken into account.",
+            "<%\nOption Explicit\nDim total\ntotl = 1\n%>",
+            "<%\nOption Explicit\nDim total\ntotal = 1\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnusedVariable => (
+            "A variable is declared but its value is never read.",
+            "Unused variables make code harder to read and often hide a typo or a forgotten \
+             step. Page-level variables are only reported when no included or including \
+             file reads them.",
+            "<%\nSub Render()\n  Dim count, unused\n  count = 1\n  Response.Write count\nEnd Sub\n%>",
+            "<%\nSub Render()\n  Dim count\n  count = 1\n  Response.Write count\nEnd Sub\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnusedParameter => (
+            "A procedure parameter is never used.",
+            "An unused parameter misleads callers about what the procedure depends on.",
+            "<%\nFunction Total(items, tax)\n  Total = items * 2\nEnd Function\n%>",
+            "<%\nFunction Total(items)\n  Total = items * 2\nEnd Function\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnreachableCode => (
+            "A statement follows an Exit, Response.End or Err.Raise in the same block.",
+            "The statement can never run; it is either dead code or a sign that the exit is \
+             misplaced.",
+            "<%\nSub Save()\n  Exit Sub\n  Response.Write \"saved\"\nEnd Sub\n%>",
+            "<%\nSub Save()\n  Response.Write \"saved\"\nEnd Sub\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnusedProcedure => (
+            "A sub or function is called nowhere in the include graph.",
+            "Dead procedures are kept up to date for nothing. Event handlers such as \
+             `Session_OnStart` are not reported.",
+            "<%\nFunction OldTotal()\nEnd Function\n%>",
+            "<%\nResponse.Write Total()\nFunction Total()\nEnd Function\n%>",
+            &[],
+        ),
+        DiagnosticCode::MissingSet => (
+            "An object is assigned without Set.",
+            "Assigning an object without `Set` assigns its default property, or fails with \
+             \"Object doesn't support this property or method\".",
+            "<%\ncart = New ShoppingCart\n%>",
+            "<%\nSet cart = New ShoppingCart\n%>",
+            &[],
+        ),
+        DiagnosticCode::SetOnValue => (
+            "Set is used to assign a value that is not an object.",
+            "`Set` only accepts objects; assigning a number or a string with it fails with \
+             \"Object required\".",
+            "<%\nSet count = 0\n%>",
+            "<%\ncount = 0\n%>",
+            &[],
+        ),
+        DiagnosticCode::DuplicateDeclaration => (
+            "A name is declared twice in the same scope.",
+            "VBScript reports \"Name redefined\" when the page compiles.",
+            "<%\nDim total\nDim total\n%>",
+            "<%\nDim total\n%>",
+            &[],
+        ),
+        DiagnosticCode::ShadowedVariable => (
+            "A local variable or parameter hides a page-level variable or a class field.",
+            "Inside the procedure, the name refers to the local variable, so assignments \
+             meant for the outer variable are lost.",
+            "<%\nDim title\nSub Render(title)\nEnd Sub\n%>",
+            "<%\nDim title\nSub Render(heading)\nEnd Sub\n%>",
+            &[],
+        ),
+        DiagnosticCode::NameConflict => (
+            "A procedure or class has the same name as a variable.",
+            "Only one of them can be used; VBScript reports \"Name redefined\" or calls the \
+             wrong one.",
+            "<%\nDim Total\nFunction Total()\nEnd Function\n%>",
+            "<%\nDim grandTotal\nFunction Total()\nEnd Function\n%>",
+            &[],
+        ),
+        DiagnosticCode::UncheckedError => (
+            "Errors are ignored by On Error Resume Next but Err.Number is never checked.",
+            "Every error after the statement is silently ignored, so failures go unnoticed. \
+             Check `Err.Number` after the statements that may fail, then restore error \
+             handling with `On Error GoTo 0`.",
+            "<%\nOn Error Resume Next\nconn.Open dsn\n%>",
+            "<%\nOn Error Resume Next\nconn.Open dsn\nIf Err.Number <> 0 Then Response.Write \"Database unavailable\"\nOn Error GoTo 0\n%>",
+            &[],
+        ),
+        DiagnosticCode::CrossSiteScripting => (
+            "A request value is written to the page without being encoded.",
+            "An attacker can put HTML and scripts in the query string or a form field and \
+             have them run in the browser of other users. Encode values with \
+             `Server.HTMLEncode` before writing them.",
+            "<%= Request.QueryString(\"name\") %>",
+            "<%= Server.HTMLEncode(Request.QueryString(\"name\")) %>",
+            &[(
+                "sanitizers",
+                "functions whose result is safe to write (default: Server.HTMLEncode, \
+                 Server.URLEncode and the numeric conversion functions)",
+            )],
+        ),
+        DiagnosticCode::HardcodedCredentials => (
+            "A password, an API key or the sa account is written in the code.",
+            "Secrets in the code end up in backups and version control, and are hard to \
+             rotate. Read them from the server configuration instead, and connect with an \
+             account with limited rights.",
+            "<%\nconn.Open \"Provider=SQLOLEDB;User ID=sa;Password=secret\"\n%>",
+            "<%\nconn.Open Application(\"ConnectionString\")\n%>",
+            &[],
+        ),
+        DiagnosticCode::DangerousFunction => (
+            "Code run from a string, a dangerous object or a function not supported by ASP.",
+            "Running code or pages whose name is built at runtime lets user input run any \
+             code; shell objects give pages access to the server; `MsgBox` and `Stop` do \
+             not work on a web server.",
+            "<%\nExecute Request(\"action\") & \"()\"\n%>",
+            "<%\nSelect Case Request(\"action\")\n  Case \"save\": Save\nEnd Select\n%>",
+            &[
+                ("dynamic_code", "functions running the code they are given"),
+                ("dynamic_paths", "methods running the page they are given"),
+                (
+                    "objects",
+                    "ProgIDs of the objects that should not be created",
+                ),
+                (
+                    "unsupported",
+                    "functions and statements reported wherever they are used",
+                ),
+            ],
+        ),
+        DiagnosticCode::ComplexProcedure => (
+            "A procedure has a cyclomatic complexity above the threshold.",
+            "The complexity counts the paths through the procedure: each If, ElseIf, Case, \
+             loop, And and Or adds one. Complex procedures are hard to test and change; split \
+             them into smaller ones.",
+            "<%\nFunction Price(item)\n  If item.Sale And item.Stock > 0 Then\n  ' ... many branches\n  End If\nEnd Function\n%>",
+            "<%\nFunction Price(item)\n  Price = BasePrice(item) - Discount(item)\nEnd Function\n%>",
+            &[("max_complexity", "highest complexity allowed (default 10)")],
+        ),
+        DiagnosticCode::LongProcedure => (
+            "A procedure has more lines than allowed.",
+            "Long procedures do too many things; split them into smaller ones.",
+            "<%\nSub Render()\n  ' ... 200 lines\nEnd Sub\n%>",
+            "<%\nSub Render()\n  RenderHeader\n  RenderItems\nEnd Sub\n%>",
+            &[("max_lines", "highest number of lines allowed (default 100)")],
+        ),
+        DiagnosticCode::DeepNesting => (
+            "A procedure has blocks nested deeper than allowed.",
+            "Deeply nested code is hard to follow; exit early or move inner blocks to their \
+             own procedures.",
+            "<%\nFor Each row In rows\n  If row.Visible Then\n    For Each cell In row.Cells\n      If cell.Visible Then\n        With cell\n        End With\n      End If\n    Next\n  End If\nNext\n%>",
+            "<%\nFor Each row In rows\n  If row.Visible Then RenderRow row\nNext\n%>",
+            &[(
+                "max_depth",
+                "highest number of nested blocks allowed (default 4)",
+            )],
+        ),
+        DiagnosticCode::NamingConvention => (
+            "A name does not follow the naming conventions.",
+            "Consistent names make code easier to read. The message suggests a name \
+             following the convention when one can be deduced.",
+            "<%\nDim user_name\nClass shopping_cart\n  Private total\nEnd Class\n%>",
+            "<%\nDim userName\nClass ShoppingCart\n  Private m_total\nEnd Class\n%>",
+            &[
+                (
+                    "variables",
+                    "style of variables and parameters (default \"camel-case\")",
+                ),
+                (
+                    "procedures",
+                    "style of subs, functions and properties (default \"pascal-case\")",
+                ),
+                ("classes", "style of classes (default \"pascal-case\")"),
+                ("constants", "style of constants (default \"off\")"),
+                (
+                    "member_prefix",
+                    "prefix of private class variables (default \"m_\")",
+                ),
+            ],
+        ),
+        DiagnosticCode::PluginRule => (
+            "A problem reported by an external plugin.",
+            "Plugins are programs listed in the `plugins` option of the configuration; they \
+             receive each page as JSON and report the house rules of a project. The message \
+             starts with the name of the plugin rule.",
+            "<%\nrs.Open \"SELECT * FROM users\", conn\n%>",
+            "<%\nSet rs = Users.All()\n%>",
+            &[],
+        ),
+        DiagnosticCode::EncodingAssumed => (
+            "The encoding of the file is assumed.",
+            "The file is not valid UTF-8 and has neither byte order mark nor `CodePage` \
+             directive, so it is read as ISO-8859-1, or in the encoding given by \
+             `--default-encoding`. Converting it to UTF-8, or declaring its code page, makes \
+             its strings the same for every tool.",
+            "<% Response.Write \"Caf\u{e9}\" %>",
+            "<%@ CodePage=1252 %>\n<% Response.Write \"Caf\u{e9}\" %>",
+            &[],
+        ),
+        DiagnosticCode::FileTooLarge => (
+            "A file found in a directory is too large to be checked.",
+            "Files larger than `--max-file-size` (10M by default) are skipped when searching \
+             directories: they are usually generated, and parsing them takes long for little \
+             benefit. Excluding them, or raising the limit, silences the notice; files given \
+             by name are always checked.",
+            "<%\n' Generated: 40 MB of lookup tables\nTable(0) = \"A\"\n%>",
+            "<%\n' The tables are read when needed\nSet table = LoadTable(\"tables.csv\")\n%>",
+            &[],
+        ),
+        DiagnosticCode::UnknownMember => (
+            "A member of an ASP or COM object that the object does not have.",
+            "The members of the ASP intrinsic objects (`Response`, `Request`, `Server`, \
+             `Session`, `Application`, `ObjectContext`, `ASPError`), of `Err` and of the \
+             common COM objects (ADODB, Scripting, MSXML2, CDO) are known, so a misspelled \
+             name such as `Response.Wrtie` only fails when the line runs. Variables are \
+             checked when every object assigned to them with `Set` comes from a known \
+             ProgID or member. The message suggests the closest member.",
+            "<%\nResponse.Wrtie \"Saved\"\nSet lastError = Server.GetLastError()\n%>",
+            "<%\nResponse.Write \"Saved\"\nSet lastError = Server.GetLastError()\n%>",
+            &[],
+        ),
+    };
+    Explanation {
+        code,
+        summary,
+        rationale,
+        bad,
+        good,
+        options,
+    }
+}
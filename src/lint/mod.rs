@@ -0,0 +1,441 @@
+//! Semantic checks of ASP Classic pages
+//!
+//! The parser only reports code that does not match the grammar. The lint rules
+//! look for code that parses but is likely wrong, such as a name used without being
+//! declared in a page with `Option Explicit`.
+//!
+//! Each check is a [`Rule`] identified by a [`DiagnosticCode`], from which it takes
+//! its name and its default severity. A [`Registry`] holds the rules to run and the
+//! severity each one reports with:
+//!
+//! ```
+//! use asp_classic_parser::lint::{self, Registry};
+//! use asp_classic_parser::parser::{DiagnosticCode, Severity};
+//!
+//! let source = "<%\nOption Explicit\nDim total\ntotal = count + 1\nResponse.Write total\n%>";
+//! let diagnostics = lint::lint(source, &Registry::default());
+//! assert_eq!(diagnostics.len(), 1);
+//! assert_eq!(diagnostics[0].code, DiagnosticCode::UndeclaredName);
+//! assert_eq!(diagnostics[0].span.line, 4);
+//!
+//! let mut registry = Registry::default();
+//! registry.set_severity(DiagnosticCode::UndeclaredName, Severity::Warning);
+//! assert_eq!(lint::lint(source, &registry)[0].severity, Severity::Warning);
+//! ```
+//!
+//! Rules only run on pages that parse; otherwise the syntax errors are returned.
+
+pub mod baseline;
+pub mod explain;
+pub mod plugin;
+pub mod rules;
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::analysis::scopes::blocks;
+use crate::analysis::{Bindings, IndexedFile, WorkspaceIndex, bindings};
+use crate::includes;
+use crate::parser::ast::{Expression, Program, Statement, VariableDeclarator};
+use crate::parser::{self, Diagnostic, DiagnosticCode, ParseOptions, Severity};
+
+/// A semantic check
+pub trait Rule: Send + Sync {
+    /// Returns the code of the diagnostics reported by the rule
+    fn code(&self) -> DiagnosticCode;
+
+    /// Returns a one-line description of what the rule checks
+    fn description(&self) -> &'static str;
+
+    /// Checks a page and returns the problems found
+    ///
+    /// The diagnostics should have the default severity of the rule code; the
+    /// registry applies the configured severity.
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic>;
+
+    /// Whether the rule runs in a [`Registry::default`]; optional rules return
+    /// `false` and must be enabled with [`Registry::set_severity`]
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    /// Sets the options of the rule from the `[rules]` table of the configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - An error describing the first unknown or invalid
+    ///   option; rules without options reject any option
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        match options.keys().next() {
+            Some(key) => Err(format!("unknown option '{}'", key)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reads the options of a rule into a structure deriving `Deserialize`, for
+/// [`Rule::configure`]
+///
+/// # Returns
+///
+/// * `Result<T, String>` - The options, or a message naming an unknown option or
+///   an option of the wrong type
+pub fn options<T: serde::de::DeserializeOwned>(options: &toml::Table) -> Result<T, String> {
+    toml::Value::Table(options.clone())
+        .try_into()
+        .map_err(|e: toml::de::Error| e.message().to_string())
+}
+
+/// A page being checked
+pub struct LintContext<'a> {
+    /// Content of the page
+    pub source: &'a str,
+    /// Syntax tree of the page
+    pub program: &'a Program,
+    /// Path of the page, when it is a file
+    pub path: Option<&'a Path>,
+    /// Index of the workspace, with the files the page includes
+    pub index: Option<&'a WorkspaceIndex>,
+    /// Resolved names of the page, computed on first use
+    bindings: OnceCell<Bindings>,
+    /// Resolved names of the other files, computed on first use
+    other_bindings: OnceCell<Option<Vec<Bindings>>>,
+}
+
+impl<'a> LintContext<'a> {
+    /// Creates the context of a page that is not part of a workspace
+    pub fn new(source: &'a str, program: &'a Program) -> Self {
+        LintContext {
+            source,
+            program,
+            path: None,
+            index: None,
+            bindings: OnceCell::new(),
+            other_bindings: OnceCell::new(),
+        }
+    }
+
+    /// Sets the path of the page and the index of its workspace
+    pub fn with_file(mut self, path: &'a Path, index: &'a WorkspaceIndex) -> Self {
+        self.path = Some(path);
+        self.index = Some(index);
+        self
+    }
+
+    /// Returns the resolved names of the page
+    pub fn bindings(&self) -> &Bindings {
+        self.bindings.get_or_init(|| bindings(self.program))
+    }
+
+    /// Returns the other files whose code runs with the page: the files it includes
+    /// and the pages including it, with their own includes
+    ///
+    /// Returns `None` when they are not all known, because the page has includes and
+    /// is not in the index, or has includes that cannot be resolved. Rules
+    /// looking for declarations or uses in other files should then not report
+    /// anything.
+    pub fn other_files(&self) -> Option<Vec<&'a IndexedFile>> {
+        let indexed = match (self.path, self.index) {
+            (Some(path), Some(index)) if index.file(path).is_some() => Some((path, index)),
+            _ => None,
+        };
+        match indexed {
+            Some((path, index)) if index.unresolved_includes(path).is_empty() => {
+                Some(index.visible_files(path).split_off(1))
+            }
+            None if includes::directives(self.source).is_empty() => Some(Vec::new()),
+            _ => None,
+        }
+    }
+
+    /// Returns the resolved names of each of the [`other_files`](Self::other_files),
+    /// or `None` if they are not all known
+    pub fn other_bindings(&self) -> Option<&[Bindings]> {
+        self.other_bindings
+            .get_or_init(|| {
+                self.other_files()
+                    .map(|files| files.iter().map(|file| bindings(&file.program)).collect())
+            })
+            .as_deref()
+    }
+}
+
+/// Rules to run, with their severities
+pub struct Registry {
+    /// Registered rules, in registration order
+    rules: Vec<Box<dyn Rule>>,
+    /// Severity of each rule whose severity was changed; `None` disables the rule
+    levels: HashMap<DiagnosticCode, Option<Severity>>,
+}
+
+impl Registry {
+    /// Creates a registry without rules
+    pub fn new() -> Self {
+        Registry {
+            rules: Vec::new(),
+            levels: HashMap::new(),
+        }
+    }
+
+    /// Adds a rule
+    pub fn register(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    /// Returns the registered rules, in registration order
+    pub fn rules(&self) -> impl Iterator<Item = &dyn Rule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Returns the rule reporting a code
+    pub fn rule(&self, code: DiagnosticCode) -> Option<&dyn Rule> {
+        self.rules().find(|rule| rule.code() == code)
+    }
+
+    /// Sets the options of the rule reporting a code
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - An error if no rule reports the code or if an
+    ///   option is not supported by the rule
+    pub fn configure(&mut self, code: DiagnosticCode, options: &toml::Table) -> Result<(), String> {
+        if options.is_empty() {
+            return Ok(());
+        }
+        match self.rules.iter_mut().find(|rule| rule.code() == code) {
+            Some(rule) => rule.configure(options),
+            None => Err(format!("{} is not a lint rule", code)),
+        }
+    }
+
+    /// Sets the severity of the diagnostics of a rule, enabling it if it was
+    /// disabled
+    pub fn set_severity(&mut self, code: DiagnosticCode, severity: Severity) {
+        self.levels.insert(code, Some(severity));
+    }
+
+    /// Disables a rule
+    pub fn disable(&mut self, code: DiagnosticCode) {
+        self.levels.insert(code, None);
+    }
+
+    /// Returns the severity of the diagnostics of a rule, or `None` if it is
+    /// disabled
+    pub fn severity(&self, code: DiagnosticCode) -> Option<Severity> {
+        match self.levels.get(&code) {
+            Some(level) => *level,
+            None => Some(code.default_severity()),
+        }
+    }
+
+    /// Runs the enabled rules on a page
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Diagnostic>` - The problems found, with their configured severity,
+    ///   sorted by position
+    pub fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for rule in &self.rules {
+            let configured = match self.levels.get(&rule.code()) {
+                Some(None) => continue,
+                Some(Some(severity)) => Some(*severity),
+                None => None,
+            };
+            diagnostics.extend(rule.check(context).into_iter().map(|mut diagnostic| {
+                if let Some(severity) = configured {
+                    diagnostic.severity = severity;
+                }
+                diagnostic
+            }));
+        }
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics
+    }
+}
+
+impl Default for Registry {
+    /// Creates a registry with every rule of [`rules::all`], with their default
+    /// severities, the optional rules being disabled
+    fn default() -> Self {
+        let mut registry = Self::new();
+        for rule in rules::all() {
+            if !rule.enabled_by_default() {
+                registry.disable(rule.code());
+            }
+            registry.register(rule);
+        }
+        registry
+    }
+}
+
+/// Calls a function on every statement of a list and on the statements nested in
+/// them, procedure and class bodies included, in source order
+pub fn walk_statements<'a>(statements: &'a [Statement], visit: &mut impl FnMut(&'a Statement)) {
+    for statement in statements {
+        visit(statement);
+        match statement {
+            Statement::Procedure(procedure) => walk_statements(&procedure.body, visit),
+            Statement::Class(class) => walk_statements(&class.body, visit),
+            statement => {
+                for body in blocks(statement) {
+                    walk_statements(body, visit);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the expressions of a statement, without those of the statements nested
+/// in it
+pub fn statement_expressions(statement: &Statement) -> Vec<&Expression> {
+    match statement {
+        Statement::Output(output) => vec![&output.value],
+        Statement::Dim(statement) => bounds(&statement.variables),
+        Statement::ReDim(statement) => bounds(&statement.variables),
+        Statement::Const(statement) => statement
+            .constants
+            .iter()
+            .map(|constant| &constant.value)
+            .collect(),
+        Statement::Assignment(assignment) => vec![&assignment.target, &assignment.value],
+        Statement::Call(call) => std::iter::once(&call.target)
+            .chain(call.arguments.iter().flatten())
+            .collect(),
+        Statement::If(statement) => std::iter::once(&statement.condition)
+            .chain(
+                statement
+                    .else_if_branches
+                    .iter()
+                    .map(|branch| &branch.condition),
+            )
+            .collect(),
+        Statement::Select(statement) => std::iter::once(&statement.subject)
+            .chain(statement.cases.iter().flat_map(|case| &case.values))
+            .collect(),
+        Statement::For(statement) => std::iter::once(&statement.start)
+            .chain(std::iter::once(&statement.end))
+            .chain(statement.step.as_ref())
+            .collect(),
+        Statement::ForEach(statement) => vec![&statement.collection],
+        Statement::DoLoop(statement) => statement
+            .pre_condition
+            .iter()
+            .chain(statement.post_condition.iter())
+            .map(|condition| &condition.condition)
+            .collect(),
+        Statement::While(statement) => vec![&statement.condition],
+        Statement::With(statement) => vec![&statement.object],
+        Statement::Randomize(statement) => statement.seed.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the array bounds of declared variables
+fn bounds(variables: &[VariableDeclarator]) -> Vec<&Expression> {
+    variables
+        .iter()
+        .flat_map(|variable| variable.bounds.iter().flatten())
+        .collect()
+}
+
+/// Calls a function on an expression and on every expression nested in it
+pub fn walk_expression<'a>(expression: &'a Expression, visit: &mut impl FnMut(&'a Expression)) {
+    visit(expression);
+    match expression {
+        Expression::Member(member) => {
+            if let Some(object) = &member.object {
+                walk_expression(object, visit);
+            }
+        }
+        Expression::Call(call) => {
+            walk_expression(&call.callee, visit);
+            for argument in call.arguments.iter().flatten() {
+                walk_expression(argument, visit);
+            }
+        }
+        Expression::Unary(unary) => walk_expression(&unary.operand, visit),
+        Expression::Binary(binary) => {
+            walk_expression(&binary.left, visit);
+            walk_expression(&binary.right, visit);
+        }
+        Expression::Parenthesized(inner) => walk_expression(&inner.expression, visit),
+        Expression::Literal(_)
+        | Expression::Identifier(_)
+        | Expression::New(_)
+        | Expression::Unparsed(_) => {}
+    }
+}
+
+/// Checks a page that is not part of a workspace
+///
+/// # Arguments
+///
+/// * `source` - The content of the page
+/// * `registry` - The rules to run
+///
+/// # Returns
+///
+/// * `Vec<Diagnostic>` - The syntax errors of the page if it does not parse,
+///   otherwise the problems found by the rules
+pub fn lint(source: &str, registry: &Registry) -> Vec<Diagnostic> {
+    match parser::parse_with_options(source, &ParseOptions::default()) {
+        Ok(program) => registry.check(&LintContext::new(source, &program)),
+        Err(diagnostics) => diagnostics,
+    }
+}
+
+/// Checks a file of a workspace
+///
+/// Declarations of the files the page includes are taken from the index, so a
+/// function defined in an included file is not reported as undeclared.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `source` - The content of the file
+/// * `registry` - The rules to run
+/// * `index` - The index of the workspace, with the file and its includes
+///
+/// # Returns
+///
+/// * `Vec<Diagnostic>` - The syntax errors of the page if it does not parse,
+///   otherwise the problems found by the rules
+pub fn lint_file(
+    path: &Path,
+    source: &str,
+    registry: &Registry,
+    index: &WorkspaceIndex,
+) -> Vec<Diagnostic> {
+    match parser::parse_with_options(source, &ParseOptions::default()) {
+        Ok(program) => lint_program(path, source, &program, registry, index),
+        Err(diagnostics) => diagnostics,
+    }
+}
+
+/// Checks a file of a workspace whose syntax tree was built already
+///
+/// Same as [`lint_file`] without parsing the page again, for a page without
+/// syntax errors.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file
+/// * `source` - The content of the file
+/// * `program` - The syntax tree of the file
+/// * `registry` - The rules to run
+/// * `index` - The index of the workspace, with the file and its includes
+///
+/// # Returns
+///
+/// * `Vec<Diagnostic>` - The problems found by the rules
+pub fn lint_program(
+    path: &Path,
+    source: &str,
+    program: &Program,
+    registry: &Registry,
+    index: &WorkspaceIndex,
+) -> Vec<Diagnostic> {
+    registry.check(&LintContext::new(source, program).with_file(path, index))
+}
@@ -0,0 +1,191 @@
+//! Lint rules provided by external programs
+//!
+//! A plugin is an executable, written in any language, listed in the `plugins`
+//! option of the configuration. For each checked page, it receives on its
+//! standard input a JSON object with the `path` of the page (or `null`), its
+//! `source` and its syntax tree (`program`), and writes on its standard output a
+//! JSON array of problems:
+//!
+//! ```json
+//! [{"line": 3, "column": 1, "message": "Use the data layer", "rule": "no-inline-sql"}]
+//! ```
+//!
+//! Each problem has a 1-based `line` and `column`, optionally an `end_line` and
+//! `end_column`, a `message`, and optionally the `rule` name of the plugin,
+//! written before the message, and a `severity` (`error`, `warning` or `notice`,
+//! default `warning`). Problems are reported with the
+//! [`PluginRule`](DiagnosticCode::PluginRule) code, whose level set in the
+//! configuration applies to every plugin.
+//!
+//! A plugin still running after [`DEFAULT_TIMEOUT`] is killed and reported as
+//! failed.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use super::{LintContext, Rule};
+use crate::parser::ast::{LineIndex, Program};
+use crate::parser::{Diagnostic, DiagnosticCode, Severity};
+
+/// Time a plugin may take to check a page before it is killed
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A rule run by an external program
+pub struct CommandPlugin {
+    /// Path of the program
+    pub command: PathBuf,
+    /// Time the program may take to check a page
+    pub timeout: Duration,
+}
+
+/// Input of a plugin
+#[derive(Serialize)]
+struct PluginInput<'a> {
+    path: Option<&'a Path>,
+    source: &'a str,
+    program: &'a Program,
+}
+
+/// A problem reported by a plugin
+#[derive(Deserialize)]
+struct PluginProblem {
+    line: usize,
+    column: usize,
+    end_line: Option<usize>,
+    end_column: Option<usize>,
+    message: String,
+    rule: Option<String>,
+    severity: Option<String>,
+}
+
+impl CommandPlugin {
+    /// Creates the rule running a program
+    pub fn new(command: impl Into<PathBuf>) -> Self {
+        CommandPlugin {
+            command: command.into(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the time the program may take to check a page
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs the program on a page and returns the problems it reports
+    fn run(&self, context: &LintContext<'_>) -> Result<Vec<PluginProblem>, String> {
+        let input = serde_json::to_vec(&PluginInput {
+            path: context.path,
+            source: context.source,
+            program: context.program,
+        })
+        .map_err(|e| e.to_string())?;
+        let mut child = Command::new(&self.command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| e.to_string())?;
+
+        // The input is written and the output read by their own threads, so that
+        // a plugin writing while it reads does not block on a full pipe. The
+        // plugin may stop reading early; its output tells whether it failed.
+        let stdin = child.stdin.take().map(|mut stdin| {
+            thread::spawn(move || {
+                let _ = stdin.write_all(&input);
+            })
+        });
+        let stdout = child.stdout.take().map(read_all);
+        let stderr = child.stderr.take().map(read_all);
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait().map_err(|e| e.to_string())? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    // Killing the plugin closes its pipes, ending the threads
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("timed out after {} s", self.timeout.as_secs_f32()));
+                }
+                None => thread::sleep(Duration::from_millis(10)),
+            }
+        };
+        let joined = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+            reader
+                .and_then(|reader| reader.join().ok())
+                .unwrap_or_default()
+        };
+        if let Some(stdin) = stdin {
+            let _ = stdin.join();
+        }
+        let (stdout, stderr) = (joined(stdout), joined(stderr));
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            return Err(format!("{} {}", status, stderr.trim()));
+        }
+        serde_json::from_slice(&stdout).map_err(|e| format!("invalid output: {}", e))
+    }
+}
+
+/// Reads a pipe of a plugin to its end in a thread
+fn read_all(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = pipe.read_to_end(&mut output);
+        output
+    })
+}
+
+impl Rule for CommandPlugin {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::PluginRule
+    }
+
+    fn description(&self) -> &'static str {
+        "Rule provided by an external plugin"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let index = LineIndex::new(context.source);
+        let problems = match self.run(context) {
+            Ok(problems) => problems,
+            Err(e) => {
+                let mut diagnostic = Diagnostic::new(
+                    self.code(),
+                    index.span(0, 0),
+                    format!("Plugin '{}' failed: {}", self.command.display(), e),
+                );
+                diagnostic.severity = Severity::Error;
+                return vec![diagnostic];
+            }
+        };
+        problems
+            .into_iter()
+            .map(|problem| {
+                let start = index.offset(problem.line, problem.column);
+                let end = match (problem.end_line, problem.end_column) {
+                    (Some(line), Some(column)) => index.offset(line, column).max(start),
+                    _ => start,
+                };
+                let message = match problem.rule {
+                    Some(rule) => format!("{}: {}", rule, problem.message),
+                    None => problem.message,
+                };
+                let mut diagnostic = Diagnostic::new(self.code(), index.span(start, end), message);
+                diagnostic.severity = match problem.severity.as_deref() {
+                    Some("error") => Severity::Error,
+                    Some("notice") => Severity::Notice,
+                    _ => Severity::Warning,
+                };
+                diagnostic
+            })
+            .collect()
+    }
+}
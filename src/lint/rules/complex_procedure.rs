@@ -0,0 +1,114 @@
+//! `ASP023`: procedures with too many paths through them
+
+use serde::Deserialize;
+
+use crate::analysis::scopes::blocks;
+use crate::lint::{self, LintContext, Rule, walk_expression, walk_statements};
+use crate::parser::ast::{BinaryOperator, Expression, Procedure, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the subs, functions and properties whose cyclomatic complexity is
+/// above a threshold
+///
+/// The complexity of a procedure is one more than its number of decisions: each
+/// `If` and `ElseIf`, each `Case` of a `Select Case`, each loop, and each `And` or
+/// `Or` in the conditions of `If` statements and loops.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ComplexProcedure {
+    /// Highest complexity allowed
+    pub max_complexity: usize,
+}
+
+impl Default for ComplexProcedure {
+    fn default() -> Self {
+        ComplexProcedure { max_complexity: 10 }
+    }
+}
+
+impl Rule for ComplexProcedure {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::ComplexProcedure
+    }
+
+    fn description(&self) -> &'static str {
+        "Sub or function whose cyclomatic complexity is too high"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Procedure(procedure) = statement {
+                let complexity = cyclomatic_complexity(procedure);
+                if complexity > self.max_complexity {
+                    diagnostics.push(Diagnostic::new(
+                        self.code(),
+                        procedure.name.span,
+                        format!(
+                            "'{}' has a cyclomatic complexity of {} (maximum {})",
+                            procedure.name.name, complexity, self.max_complexity
+                        ),
+                    ));
+                }
+            }
+        });
+        diagnostics
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
+
+/// Returns the cyclomatic complexity of a sub, function or property: one more
+/// than its number of decisions, as counted by [`ComplexProcedure`]
+pub fn cyclomatic_complexity(procedure: &Procedure) -> usize {
+    1 + decisions(&procedure.body)
+}
+
+/// Counts the decisions of a statement list and of the blocks nested in it
+fn decisions(statements: &[Statement]) -> usize {
+    statements
+        .iter()
+        .map(|statement| {
+            let own = match statement {
+                Statement::If(statement) => {
+                    1 + statement.else_if_branches.len()
+                        + conditions(&statement.condition)
+                        + statement
+                            .else_if_branches
+                            .iter()
+                            .map(|branch| conditions(&branch.condition))
+                            .sum::<usize>()
+                }
+                Statement::Select(statement) => statement.cases.len(),
+                Statement::DoLoop(statement) => {
+                    1 + statement
+                        .pre_condition
+                        .iter()
+                        .chain(statement.post_condition.iter())
+                        .map(|condition| conditions(&condition.condition))
+                        .sum::<usize>()
+                }
+                Statement::While(statement) => 1 + conditions(&statement.condition),
+                Statement::For(_) | Statement::ForEach(_) => 1,
+                _ => 0,
+            };
+            own + blocks(statement).into_iter().map(decisions).sum::<usize>()
+        })
+        .sum()
+}
+
+/// Counts the `And` and `Or` operators of a condition
+fn conditions(condition: &Expression) -> usize {
+    let mut count = 0;
+    walk_expression(condition, &mut |expression| {
+        if let Expression::Binary(binary) = expression
+            && matches!(binary.operator, BinaryOperator::And | BinaryOperator::Or)
+        {
+            count += 1;
+        }
+    });
+    count
+}
@@ -0,0 +1,203 @@
+//! `ASP020`: request values written to the page without encoding
+
+use std::collections::HashSet;
+
+use serde::Deserialize;
+
+use super::{dotted_name, is_member, is_method_call};
+use crate::analysis::scopes::blocks;
+use crate::lint::{self, LintContext, Rule, walk_statements};
+use crate::parser::ast::{BinaryOperator, Expression, Span, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Collections of the `Request` object holding values sent by the client
+const REQUEST_COLLECTIONS: [&str; 5] =
+    ["QueryString", "Form", "Cookies", "ServerVariables", "Item"];
+
+/// Reports values coming from the request (`Request("name")`,
+/// `Request.QueryString("id")`, `Request.Form`, ...) that are written to the page
+/// with `Response.Write` or `<%= %>` without being encoded, which lets an attacker
+/// inject scripts into the page
+///
+/// Variables assigned a request value are followed within their procedure (or the
+/// page-level code). A value passed through one of the
+/// [`sanitizers`](Self::sanitizers) is safe; functions of other names, such as
+/// `Trim`, keep it unsafe. The diagnostic points at the unsafe part of the written
+/// expression.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrossSiteScripting {
+    /// Functions returning a value that is safe to write, matched ignoring case by
+    /// their name as written (`Server.HTMLEncode`, `HtmlSafe`, ...)
+    pub sanitizers: Vec<String>,
+}
+
+impl Default for CrossSiteScripting {
+    /// Uses the encoding methods of the `Server` object and the numeric conversion
+    /// functions as sanitizers
+    fn default() -> Self {
+        let sanitizers = [
+            "Server.HTMLEncode",
+            "Server.URLEncode",
+            "CInt",
+            "CLng",
+            "CDbl",
+            "CSng",
+            "CByte",
+            "CCur",
+            "CBool",
+            "Len",
+        ];
+        CrossSiteScripting {
+            sanitizers: sanitizers.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+}
+
+impl Rule for CrossSiteScripting {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::CrossSiteScripting
+    }
+
+    fn description(&self) -> &'static str {
+        "Request value written to the page without HTML encoding"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.check_body(context, &context.program.body, &mut diagnostics);
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Procedure(procedure) = statement {
+                self.check_body(context, &procedure.body, &mut diagnostics);
+            }
+        });
+        diagnostics
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
+
+impl CrossSiteScripting {
+    /// Checks the body of a procedure or of the page
+    fn check_body(
+        &self,
+        context: &LintContext<'_>,
+        body: &[Statement],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let mut tainted = HashSet::new();
+        self.check_statements(context, body, &mut tainted, diagnostics);
+    }
+
+    /// Checks statements in source order, updating the set of variables holding a
+    /// request value
+    fn check_statements(
+        &self,
+        context: &LintContext<'_>,
+        statements: &[Statement],
+        tainted: &mut HashSet<String>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for statement in statements {
+            let written: Vec<&Expression> = match statement {
+                Statement::Output(output) => vec![&output.value],
+                Statement::Call(call) if is_method_call(call, "Response", "Write") => {
+                    match &call.target {
+                        Expression::Call(inner) => inner.arguments.iter().flatten().collect(),
+                        _ => call.arguments.iter().flatten().collect(),
+                    }
+                }
+                Statement::Assignment(assignment) => {
+                    if let Expression::Identifier(name) = &assignment.target {
+                        let name = name.name.to_ascii_lowercase();
+                        if self.tainted_span(&assignment.value, tainted).is_some() {
+                            tainted.insert(name);
+                        } else {
+                            tainted.remove(&name);
+                        }
+                    }
+                    Vec::new()
+                }
+                _ => Vec::new(),
+            };
+            for expression in written {
+                if let Some(span) = self.tainted_span(expression, tainted) {
+                    let text = context.source.get(span.start..span.end).unwrap_or("value");
+                    diagnostics.push(Diagnostic::new(
+                        self.code(),
+                        span,
+                        format!(
+                            "'{}' comes from the request and is written to the page without Server.HTMLEncode",
+                            text
+                        ),
+                    ));
+                }
+            }
+            for body in blocks(statement) {
+                self.check_statements(context, body, tainted, diagnostics);
+            }
+        }
+    }
+
+    /// Returns the location of the first request value of an expression that is
+    /// not sanitized
+    fn tainted_span(&self, expression: &Expression, tainted: &HashSet<String>) -> Option<Span> {
+        match expression {
+            Expression::Identifier(name) => tainted
+                .contains(&name.name.to_ascii_lowercase())
+                .then_some(name.span),
+            Expression::Member(_) if is_request(expression) => Some(expression.span()),
+            Expression::Member(member) => member
+                .object
+                .as_deref()
+                .and_then(|object| self.tainted_span(object, tainted)),
+            Expression::Call(call) => {
+                if is_request(&call.callee) {
+                    return Some(call.span);
+                }
+                if self.is_sanitizer(&call.callee) {
+                    return None;
+                }
+                self.tainted_span(&call.callee, tainted).or_else(|| {
+                    call.arguments
+                        .iter()
+                        .flatten()
+                        .find_map(|argument| self.tainted_span(argument, tainted))
+                })
+            }
+            Expression::Binary(binary)
+                if matches!(
+                    binary.operator,
+                    BinaryOperator::Concat | BinaryOperator::Add
+                ) =>
+            {
+                self.tainted_span(&binary.left, tainted)
+                    .or_else(|| self.tainted_span(&binary.right, tainted))
+            }
+            Expression::Parenthesized(inner) => self.tainted_span(&inner.expression, tainted),
+            _ => None,
+        }
+    }
+
+    fn is_sanitizer(&self, callee: &Expression) -> bool {
+        let Some(name) = dotted_name(callee) else {
+            return false;
+        };
+        self.sanitizers
+            .iter()
+            .any(|sanitizer| sanitizer.eq_ignore_ascii_case(&name))
+    }
+}
+
+/// Whether an expression is the `Request` object or one of its collections
+fn is_request(expression: &Expression) -> bool {
+    match expression {
+        Expression::Identifier(name) => name.name.eq_ignore_ascii_case("Request"),
+        expression => REQUEST_COLLECTIONS
+            .iter()
+            .any(|collection| is_member(expression, "Request", collection)),
+    }
+}
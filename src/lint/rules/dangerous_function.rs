@@ -0,0 +1,152 @@
+//! `ASP022`: calls that run dynamic code or are not supported by ASP
+
+use serde::Deserialize;
+
+use super::dotted_name;
+use crate::analysis::{Evaluator, Value};
+use crate::lint::{
+    self, LintContext, Rule, statement_expressions, walk_expression, walk_statements,
+};
+use crate::parser::ast::{Expression, Span, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Functions creating COM objects from their ProgID
+const OBJECT_FACTORIES: [&str; 2] = ["CreateObject", "Server.CreateObject"];
+
+/// Reports the use of dangerous or unsupported constructs:
+///
+/// - functions running code from a string, such as `Eval` or `Execute`, when the
+///   code is not a constant and may therefore come from the user;
+/// - methods running another page, such as `Server.Execute`, when the path is
+///   not a constant;
+/// - the creation of objects giving access to the server, such as `WScript.Shell`;
+/// - functions and statements that do not work in ASP, such as `MsgBox` or `Stop`.
+///
+/// Each list can be changed; names are matched ignoring case as written
+/// (`Server.Execute`).
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DangerousFunction {
+    /// Functions running the code passed as their first argument
+    pub dynamic_code: Vec<String>,
+    /// Methods running the page whose path is passed as their first argument
+    pub dynamic_paths: Vec<String>,
+    /// ProgIDs of the objects that should not be created
+    pub objects: Vec<String>,
+    /// Functions and statements reported wherever they are used
+    pub unsupported: Vec<String>,
+}
+
+impl Default for DangerousFunction {
+    fn default() -> Self {
+        let strings = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        DangerousFunction {
+            dynamic_code: strings(&["Eval", "Execute", "ExecuteGlobal"]),
+            dynamic_paths: strings(&["Server.Execute", "Server.Transfer"]),
+            objects: strings(&[
+                "WScript.Shell",
+                "Shell.Application",
+                "WScript.Network",
+                "MSScriptControl.ScriptControl",
+            ]),
+            unsupported: strings(&["MsgBox", "InputBox", "GetObject", "Stop"]),
+        }
+    }
+}
+
+impl Rule for DangerousFunction {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::DangerousFunction
+    }
+
+    fn description(&self) -> &'static str {
+        "Dynamic code, dangerous object or function not supported by ASP"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let evaluator = Evaluator::from_program(context.program);
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            match statement {
+                Statement::Stop { span } if contains(&self.unsupported, "Stop") => {
+                    diagnostics.push(Diagnostic::new(
+                        self.code(),
+                        *span,
+                        "'Stop' is not supported by ASP".to_string(),
+                    ));
+                }
+                // `Foo x` calls; `Foo(x)` ones are call expressions
+                Statement::Call(call) if !matches!(call.target, Expression::Call(_)) => {
+                    let arguments: Vec<_> = call.arguments.iter().flatten().collect();
+                    diagnostics.extend(self.check_call(
+                        &evaluator,
+                        &call.target,
+                        &arguments,
+                        call.span,
+                    ));
+                }
+                _ => {}
+            }
+            for expression in statement_expressions(statement) {
+                walk_expression(expression, &mut |expression| {
+                    if let Expression::Call(call) = expression {
+                        let arguments: Vec<_> = call.arguments.iter().flatten().collect();
+                        diagnostics.extend(self.check_call(
+                            &evaluator,
+                            &call.callee,
+                            &arguments,
+                            call.span,
+                        ));
+                    }
+                });
+            }
+        });
+        diagnostics
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
+
+impl DangerousFunction {
+    /// Checks a call of a function or method with its arguments
+    fn check_call(
+        &self,
+        evaluator: &Evaluator,
+        callee: &Expression,
+        arguments: &[&Expression],
+        span: Span,
+    ) -> Option<Diagnostic> {
+        let name = dotted_name(callee)?;
+        let first = arguments
+            .first()
+            .map(|argument| evaluator.evaluate(argument));
+        let message = if contains(&self.unsupported, &name) {
+            format!("'{}' is not supported by ASP", name)
+        } else if contains(&self.dynamic_code, &name) && matches!(first, Some(None)) {
+            format!(
+                "'{}' runs code built at runtime, which may come from the user",
+                name
+            )
+        } else if contains(&self.dynamic_paths, &name) && matches!(first, Some(None)) {
+            format!("'{}' runs a page whose path is built at runtime", name)
+        } else if OBJECT_FACTORIES
+            .iter()
+            .any(|factory| factory.eq_ignore_ascii_case(&name))
+            && let Some(Some(Value::String(prog_id))) = first
+            && contains(&self.objects, &prog_id)
+        {
+            format!("'{}' objects give pages access to the server", prog_id)
+        } else {
+            return None;
+        };
+        Some(Diagnostic::new(self.code(), span, message))
+    }
+}
+
+/// Whether a list holds a name, ignoring case
+fn contains(names: &[String], name: &str) -> bool {
+    names.iter().any(|item| item.eq_ignore_ascii_case(name))
+}
@@ -0,0 +1,83 @@
+//! `ASP025`: blocks nested too deeply in a procedure
+
+use serde::Deserialize;
+
+use crate::analysis::scopes::blocks;
+use crate::lint::{self, LintContext, Rule, walk_statements};
+use crate::parser::ast::Statement;
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the subs, functions and properties with blocks (`If`, `Select Case`,
+/// loops, `With`) nested deeper than a number of levels
+///
+/// A single diagnostic is reported for each procedure, on its first block that is
+/// too deep. The rule is optional: it is disabled in
+/// [`Registry::default`](crate::lint::Registry).
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeepNesting {
+    /// Highest number of nested blocks allowed
+    pub max_depth: usize,
+}
+
+impl Default for DeepNesting {
+    fn default() -> Self {
+        DeepNesting { max_depth: 4 }
+    }
+}
+
+impl Rule for DeepNesting {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::DeepNesting
+    }
+
+    fn description(&self) -> &'static str {
+        "Sub or function with blocks nested too deeply"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Procedure(procedure) = statement
+                && let Some(block) = self.too_deep(&procedure.body, 1)
+            {
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    block.span(),
+                    format!(
+                        "Blocks of '{}' are nested more than {} levels deep",
+                        procedure.name.name, self.max_depth
+                    ),
+                ));
+            }
+        });
+        diagnostics
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
+
+impl DeepNesting {
+    /// Returns the first block of a statement list at a depth above the maximum
+    fn too_deep<'a>(&self, statements: &'a [Statement], depth: usize) -> Option<&'a Statement> {
+        statements.iter().find_map(|statement| {
+            let nested = blocks(statement);
+            if nested.is_empty() {
+                None
+            } else if depth > self.max_depth {
+                Some(statement)
+            } else {
+                nested
+                    .into_iter()
+                    .find_map(|body| self.too_deep(body, depth + 1))
+            }
+        })
+    }
+}
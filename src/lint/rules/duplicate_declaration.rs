@@ -0,0 +1,89 @@
+//! `ASP016`: names declared twice in the same scope
+
+use std::collections::HashMap;
+
+use crate::analysis::{Declaration, DeclarationKind};
+use crate::lint::{LintContext, Rule};
+use crate::parser::ast::ProcedureKind;
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports names declared twice in the same scope, such as a variable declared
+/// with two `Dim` statements, a `Dim` of a parameter or two functions with the
+/// same name; VBScript refuses to run such pages with "Name redefined"
+///
+/// A `Property Get`, `Property Let` and `Property Set` may share their name.
+/// Procedures and classes named like a variable are reported by
+/// [`NameConflict`](super::NameConflict).
+pub struct DuplicateDeclaration;
+
+impl Rule for DuplicateDeclaration {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::DuplicateDeclaration
+    }
+
+    fn description(&self) -> &'static str {
+        "Name declared twice in the same scope"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let bindings = context.bindings();
+        let mut declared: HashMap<(usize, String), Vec<&Declaration>> = HashMap::new();
+        let mut diagnostics = Vec::new();
+        for declaration in &bindings.declarations {
+            if declaration.kind == DeclarationKind::Implicit {
+                continue;
+            }
+            let previous = declared
+                .entry((declaration.scope, declaration.name.to_ascii_lowercase()))
+                .or_default();
+            if let Some(first) = previous
+                .iter()
+                .find(|previous| is_duplicate(previous.kind, declaration.kind))
+            {
+                diagnostics.push(
+                    Diagnostic::new(
+                        self.code(),
+                        declaration.span,
+                        format!(
+                            "'{}' is already declared at line {}",
+                            declaration.name, first.span.line
+                        ),
+                    )
+                    .with_related(None, first.span, "first declared here"),
+                );
+            }
+            previous.push(declaration);
+        }
+        diagnostics
+    }
+}
+
+/// Whether two declarations of the same name in a scope redefine it
+fn is_duplicate(first: DeclarationKind, second: DeclarationKind) -> bool {
+    match (first, second) {
+        (DeclarationKind::Procedure(first), DeclarationKind::Procedure(second)) => {
+            first == second || !is_property(first) || !is_property(second)
+        }
+        (DeclarationKind::Class, DeclarationKind::Class) => true,
+        (first, second) => is_value(first) && is_value(second),
+    }
+}
+
+/// Whether a declaration declares a variable, a constant, a parameter or an
+/// object
+pub(super) fn is_value(kind: DeclarationKind) -> bool {
+    matches!(
+        kind,
+        DeclarationKind::Variable
+            | DeclarationKind::Constant
+            | DeclarationKind::Parameter
+            | DeclarationKind::Object
+    )
+}
+
+fn is_property(kind: ProcedureKind) -> bool {
+    matches!(
+        kind,
+        ProcedureKind::PropertyGet | ProcedureKind::PropertyLet | ProcedureKind::PropertySet
+    )
+}
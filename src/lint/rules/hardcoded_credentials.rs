@@ -0,0 +1,173 @@
+//! `ASP021`: passwords, keys and privileged accounts written in the code
+
+use crate::analysis::{Evaluator, Value};
+use crate::lint::{LintContext, Rule, statement_expressions, walk_statements};
+use crate::parser::ast::{BinaryOperator, Expression, Span, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Keys of connection strings and URLs whose value is a secret
+const SECRET_KEYS: [&str; 9] = [
+    "password",
+    "pwd",
+    "api_key",
+    "apikey",
+    "api-key",
+    "access_token",
+    "client_secret",
+    "secret",
+    "token",
+];
+
+/// Keys of connection strings naming the user
+const USER_KEYS: [&str; 4] = ["user id", "uid", "user", "username"];
+
+/// Parts of variable and constant names meant to hold a secret
+const SECRET_NAMES: [&str; 7] = [
+    "password", "passwd", "pwd", "apikey", "api_key", "secret", "token",
+];
+
+/// Reports credentials written in the code: connection strings and URLs with a
+/// password or an API key (`Password=...`, `?api_key=...`), connection strings
+/// using the SQL Server `sa` account, and string values assigned to variables or
+/// constants named like a password, key or token
+///
+/// String concatenations, including those split with line continuations, are
+/// checked as a whole when all their parts are known.
+pub struct HardcodedCredentials;
+
+impl Rule for HardcodedCredentials {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::HardcodedCredentials
+    }
+
+    fn description(&self) -> &'static str {
+        "Password, API key or sa account written in the code"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let evaluator = Evaluator::from_program(context.program);
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Some((name, value)) = assigned_secret(statement, &evaluator) {
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    value,
+                    format!("Hardcoded secret assigned to '{}'", name),
+                ));
+                return;
+            }
+            for expression in statement_expressions(statement) {
+                self.check_strings(expression, &evaluator, &mut diagnostics);
+            }
+        });
+        diagnostics
+    }
+}
+
+impl HardcodedCredentials {
+    /// Checks the longest string values that can be computed in an expression
+    fn check_strings(
+        &self,
+        expression: &Expression,
+        evaluator: &Evaluator,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        if is_string_expression(expression)
+            && let Some(Value::String(text)) = evaluator.evaluate(expression)
+        {
+            if let Some(problem) = credential_problem(&text) {
+                diagnostics.push(Diagnostic::new(self.code(), expression.span(), problem));
+            }
+            return;
+        }
+        match expression {
+            Expression::Member(member) => {
+                if let Some(object) = &member.object {
+                    self.check_strings(object, evaluator, diagnostics);
+                }
+            }
+            Expression::Call(call) => {
+                self.check_strings(&call.callee, evaluator, diagnostics);
+                for argument in call.arguments.iter().flatten() {
+                    self.check_strings(argument, evaluator, diagnostics);
+                }
+            }
+            Expression::Binary(binary) => {
+                self.check_strings(&binary.left, evaluator, diagnostics);
+                self.check_strings(&binary.right, evaluator, diagnostics);
+            }
+            Expression::Parenthesized(inner) => {
+                self.check_strings(&inner.expression, evaluator, diagnostics)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Whether an expression is a string literal or a concatenation, as opposed to a
+/// constant whose value is checked where it is declared
+fn is_string_expression(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(_) => true,
+        Expression::Binary(binary) => {
+            matches!(
+                binary.operator,
+                BinaryOperator::Concat | BinaryOperator::Add
+            )
+        }
+        Expression::Parenthesized(inner) => is_string_expression(&inner.expression),
+        _ => false,
+    }
+}
+
+/// Returns the name and the value location of a non-empty string assigned to a
+/// variable or constant named like a secret
+fn assigned_secret<'a>(statement: &'a Statement, evaluator: &Evaluator) -> Option<(&'a str, Span)> {
+    let assignments: Vec<(&str, &Expression)> = match statement {
+        Statement::Assignment(assignment) => match &assignment.target {
+            Expression::Identifier(name) => vec![(name.name.as_str(), &assignment.value)],
+            Expression::Member(member) => vec![(member.member.name.as_str(), &assignment.value)],
+            _ => Vec::new(),
+        },
+        Statement::Const(statement) => statement
+            .constants
+            .iter()
+            .map(|constant| (constant.name.name.as_str(), &constant.value))
+            .collect(),
+        _ => Vec::new(),
+    };
+    assignments.into_iter().find_map(|(name, value)| {
+        let lowercase = name.to_ascii_lowercase();
+        let is_secret = SECRET_NAMES.iter().any(|part| lowercase.contains(part));
+        match evaluator.evaluate(value) {
+            Some(Value::String(text))
+                if is_secret && is_string_expression(value) && !text.trim().is_empty() =>
+            {
+                Some((name, value.span()))
+            }
+            _ => None,
+        }
+    })
+}
+
+/// Describes the credentials found in a string, if any
+fn credential_problem(text: &str) -> Option<String> {
+    let pairs: Vec<(String, &str)> = text
+        .split([';', '&', '?'])
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.trim().to_ascii_lowercase(), value.trim()))
+        .collect();
+    if let Some((key, _)) = pairs
+        .iter()
+        .find(|(key, value)| SECRET_KEYS.contains(&key.as_str()) && !value.is_empty())
+    {
+        return Some(match key.as_str() {
+            "password" | "pwd" => "Connection string with an embedded password".to_string(),
+            key => format!("Hardcoded secret in the '{}' parameter", key),
+        });
+    }
+    pairs
+        .iter()
+        .any(|(key, value)| USER_KEYS.contains(&key.as_str()) && value.eq_ignore_ascii_case("sa"))
+        .then(|| "Connection string using the 'sa' account".to_string())
+}
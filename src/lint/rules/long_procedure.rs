@@ -0,0 +1,67 @@
+//! `ASP024`: procedures with too many lines
+
+use serde::Deserialize;
+
+use crate::lint::{self, LintContext, Rule, walk_statements};
+use crate::parser::ast::Statement;
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the subs, functions and properties longer than a number of lines,
+/// counted from the header to the `End` line
+///
+/// The rule is optional: it is disabled in [`Registry::default`](crate::lint::Registry).
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LongProcedure {
+    /// Highest number of lines allowed
+    pub max_lines: usize,
+}
+
+impl Default for LongProcedure {
+    fn default() -> Self {
+        LongProcedure { max_lines: 100 }
+    }
+}
+
+impl Rule for LongProcedure {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::LongProcedure
+    }
+
+    fn description(&self) -> &'static str {
+        "Sub or function with too many lines"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Procedure(procedure) = statement {
+                let text = context
+                    .source
+                    .get(procedure.span.start..procedure.span.end)
+                    .unwrap_or_default();
+                let lines = text.trim_end().matches('\n').count() + 1;
+                if lines > self.max_lines {
+                    diagnostics.push(Diagnostic::new(
+                        self.code(),
+                        procedure.name.span,
+                        format!(
+                            "'{}' has {} lines (maximum {})",
+                            procedure.name.name, lines, self.max_lines
+                        ),
+                    ));
+                }
+            }
+        });
+        diagnostics
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
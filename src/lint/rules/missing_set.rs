@@ -0,0 +1,69 @@
+//! `ASP014`: objects assigned without `Set`
+
+use super::is_member;
+use crate::lint::{LintContext, Rule, walk_statements};
+use crate::parser::ast::{AssignmentKind, Expression, LiteralValue, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Functions returning a new object reference
+const OBJECT_FUNCTIONS: [&str; 3] = ["CreateObject", "GetObject", "GetRef"];
+
+/// Reports assignments of an object without `Set`, such as
+/// `conn = Server.CreateObject("ADODB.Connection")`, which fail at run time or
+/// assign the default property of the object instead of the object
+///
+/// Values known to be objects are `New` expressions, `Nothing`, and the results of
+/// `CreateObject`, `Server.CreateObject`, `GetObject` and `GetRef`.
+pub struct MissingSet;
+
+impl Rule for MissingSet {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::MissingSet
+    }
+
+    fn description(&self) -> &'static str {
+        "Object assigned without the Set keyword"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Assignment(assignment) = statement
+                && assignment.kind == AssignmentKind::Let
+                && let Some(object) = object_description(&assignment.value)
+            {
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    assignment.span,
+                    format!("{} is assigned without 'Set'", object),
+                ));
+            }
+        });
+        diagnostics
+    }
+}
+
+/// Describes an expression whose value is always an object, for messages
+fn object_description(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::New(new) => Some(format!("New {}", new.class_name.name)),
+        Expression::Literal(literal) if literal.value == LiteralValue::Nothing => {
+            Some("Nothing".to_string())
+        }
+        Expression::Parenthesized(inner) => object_description(&inner.expression),
+        Expression::Call(call) => {
+            let name = match call.callee.as_ref() {
+                Expression::Identifier(name) => &name.name,
+                callee if is_member(callee, "Server", "CreateObject") => {
+                    return Some("Server.CreateObject result".to_string());
+                }
+                _ => return None,
+            };
+            OBJECT_FUNCTIONS
+                .iter()
+                .find(|function| function.eq_ignore_ascii_case(name))
+                .map(|function| format!("{} result", function))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,134 @@
+//! Built-in lint rules
+//!
+//! Each rule lives in its own module and is listed by [`all`].
+mod complex_procedure;
+mod cross_site_scripting;
+mod dangerous_function;
+mod deep_nesting;
+mod duplicate_declaration;
+mod hardcoded_credentials;
+mod long_procedure;
+mod missing_set;
+mod name_conflict;
+mod naming_convention;
+mod set_on_value;
+mod shadowed_variable;
+mod unchecked_error;
+mod undeclared_name;
+mod unknown_member;
+mod unreachable_code;
+mod unused_parameter;
+mod unused_procedure;
+mod unused_variable;
+
+pub use complex_procedure::{ComplexProcedure, cyclomatic_complexity};
+pub use cross_site_scripting::CrossSiteScripting;
+pub use dangerous_function::DangerousFunction;
+pub use deep_nesting::DeepNesting;
+pub use duplicate_declaration::DuplicateDeclaration;
+pub use hardcoded_credentials::HardcodedCredentials;
+pub use long_procedure::LongProcedure;
+pub use missing_set::MissingSet;
+pub use name_conflict::NameConflict;
+pub use naming_convention::{NamingConvention, NamingStyle};
+pub use set_on_value::SetOnValue;
+pub use shadowed_variable::ShadowedVariable;
+pub use unchecked_error::UncheckedError;
+pub use undeclared_name::UndeclaredName;
+pub use unknown_member::UnknownMember;
+pub use unreachable_code::UnreachableCode;
+pub use unused_parameter::UnusedParameter;
+pub use unused_procedure::UnusedProcedure;
+pub use unused_variable::UnusedVariable;
+
+use super::Rule;
+use crate::analysis::{DeclarationKind, SymbolKind};
+use crate::parser::ast::{CallStatement, Expression, ProcedureKind};
+
+/// Returns every built-in rule, in code order
+pub fn all() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(UndeclaredName),
+        Box::new(UnusedVariable),
+        Box::new(UnusedParameter),
+        Box::new(UnreachableCode),
+        Box::new(UnusedProcedure),
+        Box::new(MissingSet),
+        Box::new(SetOnValue),
+        Box::new(DuplicateDeclaration),
+        Box::new(ShadowedVariable),
+        Box::new(NameConflict),
+        Box::new(UncheckedError),
+        Box::new(CrossSiteScripting::default()),
+        Box::new(HardcodedCredentials),
+        Box::new(DangerousFunction::default()),
+        Box::new(ComplexProcedure::default()),
+        Box::new(LongProcedure::default()),
+        Box::new(DeepNesting::default()),
+        Box::new(NamingConvention::default()),
+        Box::new(UnknownMember),
+    ]
+}
+
+/// Returns the kind of a declaration as written in messages (`variable`,
+/// `function`, ...)
+fn declaration_kind_name(kind: DeclarationKind) -> &'static str {
+    match kind {
+        DeclarationKind::Variable | DeclarationKind::Implicit => "variable",
+        DeclarationKind::Constant => "constant",
+        DeclarationKind::Parameter => "parameter",
+        DeclarationKind::Procedure(ProcedureKind::Sub) => "sub",
+        DeclarationKind::Procedure(ProcedureKind::Function) => "function",
+        DeclarationKind::Procedure(_) => "property",
+        DeclarationKind::Class => "class",
+        DeclarationKind::Object => "object",
+    }
+}
+
+/// Returns the kind of a symbol as written in messages
+fn symbol_kind_name(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Variable => "variable",
+        SymbolKind::Constant => "constant",
+        SymbolKind::Sub => "sub",
+        SymbolKind::Function => "function",
+        SymbolKind::PropertyGet | SymbolKind::PropertyLet | SymbolKind::PropertySet => "property",
+        SymbolKind::Class => "class",
+        SymbolKind::Object => "object",
+    }
+}
+
+/// Whether an expression is a member of a built-in object, such as `Response.End`
+fn is_member(expression: &Expression, object: &str, member: &str) -> bool {
+    matches!(
+        expression,
+        Expression::Member(access)
+            if access.member.name.eq_ignore_ascii_case(member)
+                && matches!(
+                    access.object.as_deref(),
+                    Some(Expression::Identifier(name)) if name.name.eq_ignore_ascii_case(object)
+                )
+    )
+}
+
+/// Whether a call statement calls a method of a built-in object, such as
+/// `Response.End` or `Err.Raise 5`
+fn is_method_call(call: &CallStatement, object: &str, method: &str) -> bool {
+    let target = match &call.target {
+        Expression::Call(inner) => inner.callee.as_ref(),
+        target => target,
+    };
+    is_member(target, object, method)
+}
+
+/// Returns the name of a function or method as written (`Server.HTMLEncode`)
+fn dotted_name(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Identifier(name) => Some(name.name.clone()),
+        Expression::Member(member) => {
+            let object = dotted_name(member.object.as_deref()?)?;
+            Some(format!("{}.{}", object, member.member.name))
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,93 @@
+//! `ASP018`: procedures and classes named like a variable
+
+use std::collections::HashMap;
+
+use super::duplicate_declaration::is_value;
+use super::{declaration_kind_name, symbol_kind_name};
+use crate::analysis::{Declaration, DeclarationKind, ScopeKind, SymbolKind};
+use crate::lint::{LintContext, Rule};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports subs, functions, properties and classes with the same name as a
+/// variable or constant of their scope
+///
+/// Since included files are inlined into the pages including them, page-level
+/// procedures are also compared with the page-level variables of these files.
+pub struct NameConflict;
+
+impl Rule for NameConflict {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::NameConflict
+    }
+
+    fn description(&self) -> &'static str {
+        "Procedure or class with the same name as a variable"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let bindings = context.bindings();
+        let mut declared: HashMap<(usize, String), Vec<&Declaration>> = HashMap::new();
+        let mut diagnostics = Vec::new();
+        for declaration in &bindings.declarations {
+            if matches!(
+                declaration.kind,
+                DeclarationKind::Implicit | DeclarationKind::Parameter
+            ) {
+                continue;
+            }
+            let previous = declared
+                .entry((declaration.scope, declaration.name.to_ascii_lowercase()))
+                .or_default();
+            if let Some(first) = previous
+                .iter()
+                .find(|previous| is_value(previous.kind) != is_value(declaration.kind))
+            {
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!(
+                        "The {} '{}' has the same name as the {} at line {}",
+                        declaration_kind_name(declaration.kind),
+                        declaration.name,
+                        declaration_kind_name(first.kind),
+                        first.span.line
+                    ),
+                ));
+            } else if bindings.scopes[declaration.scope].kind == ScopeKind::Global
+                && let Some(files) = context.other_files()
+                && let Some((file, symbol)) = files.iter().find_map(|file| {
+                    file.symbols
+                        .get(&declaration.name)
+                        .filter(|symbol| is_value_symbol(symbol.kind) != is_value(declaration.kind))
+                        .map(|symbol| (file, symbol))
+                })
+            {
+                let file_name = file
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy())
+                    .unwrap_or_default();
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!(
+                        "The {} '{}' has the same name as the {} declared in {}",
+                        declaration_kind_name(declaration.kind),
+                        declaration.name,
+                        symbol_kind_name(symbol.kind),
+                        file_name
+                    ),
+                ));
+            }
+            previous.push(declaration);
+        }
+        diagnostics
+    }
+}
+
+fn is_value_symbol(kind: SymbolKind) -> bool {
+    matches!(
+        kind,
+        SymbolKind::Variable | SymbolKind::Constant | SymbolKind::Object
+    )
+}
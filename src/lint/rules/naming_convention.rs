@@ -0,0 +1,313 @@
+//! `ASP026`: names that do not follow the naming conventions of the project
+
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::unused_procedure::EVENT_HANDLERS;
+use crate::analysis::scopes::blocks;
+use crate::lint::{self, LintContext, Rule};
+use crate::parser::ast::{Identifier, Statement, Visibility};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Procedures whose names are imposed by VBScript
+const CLASS_EVENTS: [&str; 2] = ["Class_Initialize", "Class_Terminate"];
+
+/// A way of writing names
+///
+/// In the configuration, the styles are written in kebab-case (`"camel-case"`,
+/// `"hungarian"`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NamingStyle {
+    /// `userName`
+    CamelCase,
+    /// `UserName`
+    PascalCase,
+    /// `strUserName`: a lowercase type prefix of one to four letters followed by
+    /// a PascalCase name
+    Hungarian,
+    /// `user_name`
+    SnakeCase,
+    /// `USER_NAME`
+    UpperSnakeCase,
+}
+
+impl NamingStyle {
+    /// Whether a name is written in this style
+    pub fn matches(&self, name: &str) -> bool {
+        let first = name.chars().next().unwrap_or_default();
+        match self {
+            NamingStyle::CamelCase => first.is_ascii_lowercase() && !name.contains('_'),
+            NamingStyle::PascalCase => first.is_ascii_uppercase() && !name.contains('_'),
+            NamingStyle::Hungarian => {
+                let prefix = name.chars().take_while(char::is_ascii_lowercase).count();
+                (1..=4).contains(&prefix)
+                    && name[prefix..].starts_with(|c: char| c.is_ascii_uppercase())
+                    && !name.contains('_')
+            }
+            NamingStyle::SnakeCase => !name.chars().any(|c| c.is_ascii_uppercase()),
+            NamingStyle::UpperSnakeCase => !name.chars().any(|c| c.is_ascii_lowercase()),
+        }
+    }
+
+    /// Returns a name written in this style, or `None` when it cannot be deduced
+    /// (the type prefix of Hungarian notation)
+    pub fn convert(&self, name: &str) -> Option<String> {
+        let words = words(name);
+        let capitalized = || {
+            words.iter().map(|word| {
+                let mut chars = word.chars();
+                let first = chars.next().unwrap_or_default().to_ascii_uppercase();
+                std::iter::once(first).chain(chars).collect::<String>()
+            })
+        };
+        match self {
+            NamingStyle::CamelCase => {
+                let mut converted = words.first()?.clone();
+                converted.extend(capitalized().skip(1));
+                Some(converted)
+            }
+            NamingStyle::PascalCase => Some(capitalized().collect()),
+            NamingStyle::Hungarian => None,
+            NamingStyle::SnakeCase => Some(words.join("_")),
+            NamingStyle::UpperSnakeCase => Some(words.join("_").to_ascii_uppercase()),
+        }
+    }
+}
+
+impl fmt::Display for NamingStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            NamingStyle::CamelCase => "camelCase",
+            NamingStyle::PascalCase => "PascalCase",
+            NamingStyle::Hungarian => "Hungarian notation",
+            NamingStyle::SnakeCase => "snake_case",
+            NamingStyle::UpperSnakeCase => "UPPER_SNAKE_CASE",
+        })
+    }
+}
+
+/// Splits a name into lowercase words, on underscores and case changes
+/// (`XMLHttpRequest` gives `xml`, `http` and `request`)
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            words.push(std::mem::take(&mut word));
+            continue;
+        }
+        let previous = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_ascii_uppercase()
+            && match previous {
+                Some(previous) if previous.is_ascii_lowercase() || previous.is_ascii_digit() => {
+                    true
+                }
+                Some(previous) if previous.is_ascii_uppercase() => {
+                    next.is_some_and(|next| next.is_ascii_lowercase())
+                }
+                _ => false,
+            };
+        if boundary {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c.to_ascii_lowercase());
+    }
+    words.push(word);
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+/// Reports the names that do not follow the naming conventions of the project:
+/// a style for variables and parameters, for procedures, for classes and for
+/// constants, and a prefix for the private variables of classes
+///
+/// Each convention can be turned off with `None` (`"off"` in the configuration). The message suggests a name
+/// following the convention when one can be deduced. Event handlers, whose names
+/// are imposed (`Class_Initialize`, `Session_OnStart`, ...), are not reported.
+///
+/// The rule is optional: it is disabled in [`Registry::default`](crate::lint::Registry).
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NamingConvention {
+    /// Style of the variables and parameters
+    #[serde(deserialize_with = "setting")]
+    pub variables: Option<NamingStyle>,
+    /// Style of the subs, functions and properties
+    #[serde(deserialize_with = "setting")]
+    pub procedures: Option<NamingStyle>,
+    /// Style of the classes
+    #[serde(deserialize_with = "setting")]
+    pub classes: Option<NamingStyle>,
+    /// Style of the constants
+    #[serde(deserialize_with = "setting")]
+    pub constants: Option<NamingStyle>,
+    /// Prefix of the private variables of classes, which are not checked against
+    /// the [`variables`](Self::variables) style when it is set
+    #[serde(deserialize_with = "setting")]
+    pub member_prefix: Option<String>,
+}
+
+/// Reads a setting of the configuration, `"off"` turning the convention off
+fn setting<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    use serde::de::IntoDeserializer;
+
+    let value = String::deserialize(deserializer)?;
+    if value == "off" {
+        return Ok(None);
+    }
+    T::deserialize(value.into_deserializer()).map(Some)
+}
+
+impl Default for NamingConvention {
+    /// Uses camelCase for variables, PascalCase for procedures and classes, and
+    /// `m_` for private class variables
+    fn default() -> Self {
+        NamingConvention {
+            variables: Some(NamingStyle::CamelCase),
+            procedures: Some(NamingStyle::PascalCase),
+            classes: Some(NamingStyle::PascalCase),
+            constants: None,
+            member_prefix: Some("m_".to_string()),
+        }
+    }
+}
+
+impl Rule for NamingConvention {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::NamingConvention
+    }
+
+    fn description(&self) -> &'static str {
+        "Name not following the naming conventions"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.check_statements(&context.program.body, false, &mut diagnostics);
+        diagnostics
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        false
+    }
+
+    fn configure(&mut self, options: &toml::Table) -> Result<(), String> {
+        *self = lint::options(options)?;
+        Ok(())
+    }
+}
+
+impl NamingConvention {
+    /// Checks the declarations of a statement list and of the bodies nested in it
+    fn check_statements(
+        &self,
+        statements: &[Statement],
+        in_class: bool,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        for statement in statements {
+            match statement {
+                Statement::Dim(dim) => {
+                    let prefix = match (in_class, dim.visibility, &self.member_prefix) {
+                        (true, Some(Visibility::Private), Some(prefix)) => Some(prefix),
+                        _ => None,
+                    };
+                    for variable in &dim.variables {
+                        match prefix {
+                            Some(prefix) => self.check_prefix(&variable.name, prefix, diagnostics),
+                            None => self.check_style(
+                                "Variable",
+                                &variable.name,
+                                self.variables,
+                                diagnostics,
+                            ),
+                        }
+                    }
+                }
+                Statement::Const(statement) => {
+                    for constant in &statement.constants {
+                        self.check_style("Constant", &constant.name, self.constants, diagnostics);
+                    }
+                }
+                Statement::Procedure(procedure) => {
+                    let name = &procedure.name.name;
+                    let imposed = EVENT_HANDLERS
+                        .iter()
+                        .chain(CLASS_EVENTS.iter())
+                        .any(|handler| handler.eq_ignore_ascii_case(name));
+                    if !imposed {
+                        self.check_style(
+                            "Procedure",
+                            &procedure.name,
+                            self.procedures,
+                            diagnostics,
+                        );
+                    }
+                    for parameter in &procedure.parameters {
+                        self.check_style("Parameter", &parameter.name, self.variables, diagnostics);
+                    }
+                    self.check_statements(&procedure.body, false, diagnostics);
+                }
+                Statement::Class(class) => {
+                    self.check_style("Class", &class.name, self.classes, diagnostics);
+                    self.check_statements(&class.body, true, diagnostics);
+                }
+                statement => {
+                    for body in blocks(statement) {
+                        self.check_statements(body, in_class, diagnostics);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reports a name that is not written in a style
+    fn check_style(
+        &self,
+        kind: &str,
+        name: &Identifier,
+        style: Option<NamingStyle>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let Some(style) = style else {
+            return;
+        };
+        if style.matches(&name.name) {
+            return;
+        }
+        let message = match style.convert(&name.name) {
+            Some(suggestion) => format!(
+                "{} '{}' is not {}; rename it '{}'",
+                kind, name.name, style, suggestion
+            ),
+            None => format!("{} '{}' does not use {}", kind, name.name, style),
+        };
+        diagnostics.push(Diagnostic::new(self.code(), name.span, message));
+    }
+
+    /// Reports a private class variable without the member prefix
+    fn check_prefix(&self, name: &Identifier, prefix: &str, diagnostics: &mut Vec<Diagnostic>) {
+        let has_prefix = name
+            .name
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(prefix));
+        if !has_prefix {
+            diagnostics.push(Diagnostic::new(
+                self.code(),
+                name.span,
+                format!(
+                    "Private member '{}' does not start with '{}'; rename it '{}{}'",
+                    name.name, prefix, prefix, name.name
+                ),
+            ));
+        }
+    }
+}
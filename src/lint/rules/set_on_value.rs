@@ -0,0 +1,49 @@
+//! `ASP015`: `Set` used to assign a value that is not an object
+
+use crate::lint::{LintContext, Rule, walk_statements};
+use crate::parser::ast::{AssignmentKind, Expression, LiteralValue, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports `Set` assignments of a value that can never be an object, such as
+/// `Set count = 0` or `Set name = first & last`, which fail at run time with
+/// "Object required"
+///
+/// Literals other than `Nothing` and the results of operators are never objects.
+pub struct SetOnValue;
+
+impl Rule for SetOnValue {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::SetOnValue
+    }
+
+    fn description(&self) -> &'static str {
+        "Set used to assign a value that is not an object"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Assignment(assignment) = statement
+                && assignment.kind == AssignmentKind::Set
+                && is_value(&assignment.value)
+            {
+                diagnostics.push(Diagnostic::new(
+                    self.code(),
+                    assignment.span,
+                    "'Set' is used to assign a value that is not an object",
+                ));
+            }
+        });
+        diagnostics
+    }
+}
+
+/// Whether an expression can never evaluate to an object
+fn is_value(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(literal) => literal.value != LiteralValue::Nothing,
+        Expression::Unary(_) | Expression::Binary(_) => true,
+        Expression::Parenthesized(inner) => is_value(&inner.expression),
+        _ => false,
+    }
+}
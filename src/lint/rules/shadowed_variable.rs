@@ -0,0 +1,113 @@
+//! `ASP017`: local variables hiding a variable of an enclosing scope
+
+use super::duplicate_declaration::is_value;
+use super::{declaration_kind_name, symbol_kind_name};
+use crate::analysis::{DeclarationKind, ScopeKind, SymbolKind};
+use crate::lint::{LintContext, Rule};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the local variables and parameters of procedures with the same name as
+/// a field of their class or a page-level variable or constant
+///
+/// Assignments in the procedure then change the local variable while the rest of
+/// the page keeps using the other one. Page-level variables of the files the page
+/// includes, and of the pages including it, are taken into account.
+pub struct ShadowedVariable;
+
+impl Rule for ShadowedVariable {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::ShadowedVariable
+    }
+
+    fn description(&self) -> &'static str {
+        "Local variable hiding a page-level variable or a class field"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let bindings = context.bindings();
+        let mut diagnostics = Vec::new();
+        for declaration in &bindings.declarations {
+            let scope = &bindings.scopes[declaration.scope];
+            if scope.kind != ScopeKind::Procedure
+                || !matches!(
+                    declaration.kind,
+                    DeclarationKind::Variable | DeclarationKind::Parameter
+                )
+            {
+                continue;
+            }
+            // Look in the class of a method, then at the page level
+            let mut parent = scope.parent;
+            let mut shadowed = None;
+            while let Some(id) = parent {
+                shadowed = bindings.declarations.iter().find(|other| {
+                    other.scope == id
+                        && is_value(other.kind)
+                        && other.name.eq_ignore_ascii_case(&declaration.name)
+                });
+                if shadowed.is_some() {
+                    break;
+                }
+                parent = bindings.scopes[id].parent;
+            }
+            let diagnostic = match shadowed {
+                Some(other) => Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!(
+                        "The {} '{}' hides the {} declared at line {}",
+                        declaration_kind_name(declaration.kind),
+                        declaration.name,
+                        declaration_kind_name(other.kind),
+                        other.span.line
+                    ),
+                )
+                .with_related(
+                    None,
+                    other.span,
+                    format!("{} declared here", declaration_kind_name(other.kind)),
+                ),
+                None => {
+                    let Some((file, symbol)) = context.other_files().and_then(|files| {
+                        files.into_iter().find_map(|file| {
+                            file.symbols
+                                .get(&declaration.name)
+                                .filter(|symbol| {
+                                    matches!(
+                                        symbol.kind,
+                                        SymbolKind::Variable
+                                            | SymbolKind::Constant
+                                            | SymbolKind::Object
+                                    )
+                                })
+                                .map(|symbol| (file, symbol))
+                        })
+                    }) else {
+                        continue;
+                    };
+                    Diagnostic::new(
+                        self.code(),
+                        declaration.span,
+                        format!(
+                            "The {} '{}' hides the {} declared in {}",
+                            declaration_kind_name(declaration.kind),
+                            declaration.name,
+                            symbol_kind_name(symbol.kind),
+                            file.path
+                                .file_name()
+                                .map(|name| name.to_string_lossy())
+                                .unwrap_or_default()
+                        ),
+                    )
+                    .with_related(
+                        Some(file.path.clone()),
+                        symbol.name_span,
+                        format!("{} declared here", symbol_kind_name(symbol.kind)),
+                    )
+                }
+            };
+            diagnostics.push(diagnostic);
+        }
+        diagnostics
+    }
+}
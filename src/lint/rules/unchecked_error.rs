@@ -0,0 +1,122 @@
+//! `ASP019`: errors ignored by `On Error Resume Next` and never checked
+
+use crate::analysis::scopes::blocks;
+use crate::lint::{LintContext, Rule, statement_expressions, walk_expression, walk_statements};
+use crate::parser::ast::{Expression, OnErrorAction, Span, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports `On Error Resume Next` statements after which `Err.Number` is never
+/// checked before the next `On Error GoTo 0` or the end of the procedure (or of
+/// the page): the errors are silently swallowed
+///
+/// `Err.Number`, and `Err` alone in a condition or comparison (`If Err Then`,
+/// `Err <> 0`), count as checks.
+pub struct UncheckedError;
+
+/// What happens at a position of a procedure
+enum Event {
+    ResumeNext(Span),
+    GoToZero,
+    Check,
+}
+
+impl Rule for UncheckedError {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UncheckedError
+    }
+
+    fn description(&self) -> &'static str {
+        "On Error Resume Next without checking Err.Number"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.check_body(&context.program.body, &mut diagnostics);
+        walk_statements(&context.program.body, &mut |statement| {
+            if let Statement::Procedure(procedure) = statement {
+                self.check_body(&procedure.body, &mut diagnostics);
+            }
+        });
+        diagnostics
+    }
+}
+
+impl UncheckedError {
+    /// Checks the body of a procedure or of the page
+    fn check_body(&self, body: &[Statement], diagnostics: &mut Vec<Diagnostic>) {
+        let mut events = Vec::new();
+        collect_events(body, &mut events);
+        events.sort_by_key(|(position, _)| *position);
+
+        let mut unchecked: Option<Span> = None;
+        for (_, event) in events {
+            match event {
+                Event::ResumeNext(span) => {
+                    unchecked.get_or_insert(span);
+                }
+                Event::Check => unchecked = None,
+                Event::GoToZero => {
+                    if let Some(span) = unchecked.take() {
+                        diagnostics.push(self.diagnostic(span));
+                    }
+                }
+            }
+        }
+        if let Some(span) = unchecked {
+            diagnostics.push(self.diagnostic(span));
+        }
+    }
+
+    fn diagnostic(&self, span: Span) -> Diagnostic {
+        Diagnostic::new(
+            self.code(),
+            span,
+            "Errors are ignored by 'On Error Resume Next' but 'Err.Number' is never checked",
+        )
+    }
+}
+
+/// Lists the `On Error` statements and error checks of a body, without entering
+/// nested procedures
+fn collect_events(statements: &[Statement], events: &mut Vec<(usize, Event)>) {
+    for statement in statements {
+        if let Statement::OnError(on_error) = statement {
+            let event = match on_error.action {
+                OnErrorAction::ResumeNext => Event::ResumeNext(on_error.span),
+                OnErrorAction::GoToZero => Event::GoToZero,
+            };
+            events.push((on_error.span.start, event));
+        }
+        for expression in statement_expressions(statement) {
+            if is_err(expression) {
+                events.push((expression.span().start, Event::Check));
+            }
+            walk_expression(expression, &mut |expression| {
+                if is_check(expression) {
+                    events.push((expression.span().start, Event::Check));
+                }
+            });
+        }
+        for body in blocks(statement) {
+            collect_events(body, events);
+        }
+    }
+}
+
+/// Whether an expression reads the error number: `Err.Number`, or a comparison
+/// with `Err`
+fn is_check(expression: &Expression) -> bool {
+    match expression {
+        Expression::Member(member) => {
+            member.member.name.eq_ignore_ascii_case("Number")
+                && member.object.as_deref().is_some_and(is_err)
+        }
+        Expression::Binary(binary) => is_err(&binary.left) || is_err(&binary.right),
+        Expression::Unary(unary) => is_err(&unary.operand),
+        _ => false,
+    }
+}
+
+fn is_err(expression: &Expression) -> bool {
+    matches!(expression, Expression::Identifier(name) if name.name.eq_ignore_ascii_case("Err"))
+}
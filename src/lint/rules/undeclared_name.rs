@@ -0,0 +1,47 @@
+//! `ASP009`: names used but declared nowhere
+
+use crate::lint::{LintContext, Rule};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the uses of undeclared names in pages with `Option Explicit`, which
+/// fail at run time with "Variable is undefined"
+///
+/// Names declared in included files are found through the workspace index. Pages
+/// with includes that cannot be resolved are not checked, since any name could be
+/// declared in the missing files.
+pub struct UndeclaredName;
+
+impl Rule for UndeclaredName {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UndeclaredName
+    }
+
+    fn description(&self) -> &'static str {
+        "Name used but declared nowhere, in a page with Option Explicit"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let bindings = context.bindings();
+        if !bindings.option_explicit {
+            return Vec::new();
+        }
+        let Some(other_files) = context.other_files() else {
+            return Vec::new();
+        };
+        bindings
+            .undeclared()
+            .filter(|reference| {
+                other_files
+                    .iter()
+                    .all(|file| file.symbols.get(&reference.name).is_none())
+            })
+            .map(|reference| {
+                Diagnostic::new(
+                    self.code(),
+                    reference.span,
+                    format!("'{}' is not declared", reference.name),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,96 @@
+//! `ASP030`: members that ASP and COM objects do not have
+
+use crate::analysis::objects::{ObjectType, ObjectTypes};
+use crate::analysis::scopes::blocks;
+use crate::lint::{LintContext, Rule, statement_expressions, walk_expression};
+use crate::parser::ast::{Expression, Span, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the members of the ASP intrinsic objects, of `Err` and `ASPError`, and
+/// of the COM objects created from a known ProgID, that the object does not have,
+/// such as `Response.Wrtie` or `conn.Opne`, which parse but fail when the line runs
+///
+/// Members are checked on the objects themselves (`Response.Write`), on the objects
+/// returned by their members (`Server.GetLastError().Line`), on the variables the
+/// objects are assigned to (see [`ObjectTypes`]), and in `With` blocks on them.
+/// Names hiding an intrinsic object, such as a variable named `Server`, are not
+/// checked. The message suggests the closest member, if one is close enough.
+pub struct UnknownMember;
+
+impl Rule for UnknownMember {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UnknownMember
+    }
+
+    fn description(&self) -> &'static str {
+        "Member that an ASP or COM object does not have"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let types = ObjectTypes::new(context.program, context.bindings());
+        let mut diagnostics = Vec::new();
+        check_block(&context.program.body, None, &types, &mut diagnostics);
+        diagnostics
+    }
+}
+
+/// Checks a statement list and the blocks nested in it
+///
+/// `with` is the object of the enclosing `With` block, when the model describes it.
+fn check_block(
+    statements: &[Statement],
+    with: Option<&'static ObjectType>,
+    types: &ObjectTypes,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for statement in statements {
+        for expression in statement_expressions(statement) {
+            walk_expression(expression, &mut |expression| {
+                if let Expression::Member(access) = expression {
+                    let object = match access.object.as_deref() {
+                        Some(object) => types.expression_type(object),
+                        None => with,
+                    };
+                    if let Some(object) = object
+                        && object.member(&access.member.name).is_none()
+                    {
+                        diagnostics.push(unknown_member(
+                            object,
+                            &access.member.name,
+                            access.member.span,
+                        ));
+                    }
+                }
+            });
+        }
+        match statement {
+            Statement::Procedure(procedure) => {
+                check_block(&procedure.body, None, types, diagnostics)
+            }
+            Statement::Class(class) => check_block(&class.body, None, types, diagnostics),
+            Statement::With(block) => {
+                let object = match &block.object {
+                    Expression::Member(access) if access.object.is_none() => {
+                        with.and_then(|with| with.member_type(&access.member.name))
+                    }
+                    object => types.expression_type(object),
+                };
+                check_block(&block.body, object, types, diagnostics);
+            }
+            statement => {
+                for body in blocks(statement) {
+                    check_block(body, with, types, diagnostics);
+                }
+            }
+        }
+    }
+}
+
+/// Creates the diagnostic of a member that an object does not have
+fn unknown_member(object: &ObjectType, name: &str, span: Span) -> Diagnostic {
+    let mut message = format!("'{}' is not a member of the {} object", name, object.name);
+    if let Some(member) = object.closest_member(name) {
+        message.push_str(&format!("; did you mean '{}'?", member.name));
+    }
+    Diagnostic::new(DiagnosticCode::UnknownMember, span, message)
+}
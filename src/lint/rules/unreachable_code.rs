@@ -0,0 +1,108 @@
+//! `ASP012`: code that can never run
+
+use super::is_method_call;
+use crate::analysis::scopes::blocks;
+use crate::lint::{LintContext, Rule};
+use crate::parser::ast::{ExitKind, OnErrorAction, Statement};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the statements following, in the same block, a statement that always
+/// leaves it: `Exit Sub`, `Exit Function`, `Exit Property`, `Exit For`, `Exit Do`,
+/// `Response.End`, and `Err.Raise` when errors are not ignored by
+/// `On Error Resume Next`
+///
+/// Declarations (`Dim`, `Const`, `Sub`, `Class`, ...) apply to their whole scope
+/// and are not reported. A single diagnostic is reported for each block, on the
+/// first unreachable statement.
+pub struct UnreachableCode;
+
+impl Rule for UnreachableCode {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UnreachableCode
+    }
+
+    fn description(&self) -> &'static str {
+        "Code after Exit, Response.End or Err.Raise that can never run"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        check_block(&context.program.body, &mut false, &mut diagnostics);
+        diagnostics
+    }
+}
+
+/// Checks a statement list and the blocks nested in it
+///
+/// `resume_next` tells whether `On Error Resume Next` is in effect; it is updated
+/// by the `On Error` statements of the list.
+fn check_block(
+    statements: &[Statement],
+    resume_next: &mut bool,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut exit: Option<&'static str> = None;
+    let mut reported = false;
+    for statement in statements {
+        match (statement, exit) {
+            // Procedures declared after an exit can still be called
+            (Statement::Procedure(procedure), _) => {
+                check_block(&procedure.body, &mut false, diagnostics)
+            }
+            (Statement::Class(class), _) => check_block(&class.body, &mut false, diagnostics),
+            (statement, Some(exit)) => {
+                if !reported && is_executable(statement) {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticCode::UnreachableCode,
+                        statement.span(),
+                        format!("Unreachable code after '{}'", exit),
+                    ));
+                    reported = true;
+                }
+            }
+            (statement, None) => {
+                for body in blocks(statement) {
+                    check_block(body, resume_next, diagnostics);
+                }
+                exit = exit_of(statement, resume_next);
+            }
+        }
+    }
+}
+
+/// Returns the name of the statement if it always leaves its block, and updates
+/// the error handling mode on `On Error` statements
+fn exit_of(statement: &Statement, resume_next: &mut bool) -> Option<&'static str> {
+    match statement {
+        Statement::OnError(on_error) => {
+            *resume_next = on_error.action == OnErrorAction::ResumeNext;
+            None
+        }
+        Statement::Exit(statement) => Some(match statement.kind {
+            ExitKind::Do => "Exit Do",
+            ExitKind::For => "Exit For",
+            ExitKind::Function => "Exit Function",
+            ExitKind::Sub => "Exit Sub",
+            ExitKind::Property => "Exit Property",
+        }),
+        Statement::Call(call) if is_method_call(call, "Response", "End") => Some("Response.End"),
+        Statement::Call(call) if !*resume_next && is_method_call(call, "Err", "Raise") => {
+            Some("Err.Raise")
+        }
+        _ => None,
+    }
+}
+
+/// Whether a statement does something when it is reached
+fn is_executable(statement: &Statement) -> bool {
+    match statement {
+        Statement::Html(html) => !html.text.trim().is_empty(),
+        Statement::Procedure(_)
+        | Statement::Class(_)
+        | Statement::Dim(_)
+        | Statement::Const(_)
+        | Statement::OptionExplicit { .. }
+        | Statement::Directive(_) => false,
+        _ => true,
+    }
+}
@@ -0,0 +1,49 @@
+//! `ASP011`: procedure parameters that are never used
+
+use crate::analysis::DeclarationKind;
+use crate::lint::{LintContext, Rule};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the parameters of subs, functions and properties that are neither read
+/// nor assigned in the procedure body
+///
+/// Assigning a `ByRef` parameter returns a value to the caller, so it counts as a
+/// use.
+pub struct UnusedParameter;
+
+impl Rule for UnusedParameter {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UnusedParameter
+    }
+
+    fn description(&self) -> &'static str {
+        "Procedure parameter never used"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let bindings = context.bindings();
+        bindings
+            .declarations
+            .iter()
+            .enumerate()
+            .filter(|&(id, declaration)| {
+                declaration.kind == DeclarationKind::Parameter
+                    && bindings.references_to(id).next().is_none()
+            })
+            .map(|(_, declaration)| {
+                let procedure = bindings.scopes[declaration.scope]
+                    .name
+                    .as_deref()
+                    .unwrap_or_default();
+                Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!(
+                        "Parameter '{}' of '{}' is never used",
+                        declaration.name, procedure
+                    ),
+                )
+            })
+            .collect()
+    }
+}
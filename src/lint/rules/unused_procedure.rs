@@ -0,0 +1,82 @@
+//! `ASP013`: procedures that are never called
+
+use crate::analysis::{DeclarationKind, ScopeKind};
+use crate::lint::{LintContext, Rule};
+use crate::parser::ast::ProcedureKind;
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Event handlers called by IIS rather than by the code of the site
+pub(super) const EVENT_HANDLERS: [&str; 6] = [
+    "Application_OnStart",
+    "Application_OnEnd",
+    "Session_OnStart",
+    "Session_OnEnd",
+    "OnTransactionCommit",
+    "OnTransactionAbort",
+];
+
+/// Reports the page-level subs and functions that are called nowhere: neither in
+/// the page, except by themselves, nor in the files it includes or the pages
+/// including it
+///
+/// Pages whose includes are not all known are not checked. Class methods, which
+/// are called through objects, are not checked either.
+pub struct UnusedProcedure;
+
+impl Rule for UnusedProcedure {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UnusedProcedure
+    }
+
+    fn description(&self) -> &'static str {
+        "Sub or function never called in the include graph"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let Some(other_files) = context.other_bindings() else {
+            return Vec::new();
+        };
+        let bindings = context.bindings();
+        bindings
+            .declarations
+            .iter()
+            .enumerate()
+            .filter_map(|(id, declaration)| match declaration.kind {
+                DeclarationKind::Procedure(kind)
+                    if bindings.scopes[declaration.scope].kind == ScopeKind::Global =>
+                {
+                    Some((id, declaration, kind))
+                }
+                _ => None,
+            })
+            .filter(|(_, declaration, _)| {
+                !EVENT_HANDLERS
+                    .iter()
+                    .any(|handler| handler.eq_ignore_ascii_case(&declaration.name))
+            })
+            .filter(|&(id, declaration, _)| {
+                // Recursive calls and assignments of the function result do not count
+                let called_in_page = bindings
+                    .references_to(id)
+                    .any(|reference| bindings.scopes[reference.scope].declaration != Some(id));
+                let called_elsewhere = other_files
+                    .iter()
+                    .flat_map(|file| &file.references)
+                    .any(|reference| reference.name.eq_ignore_ascii_case(&declaration.name));
+                !called_in_page && !called_elsewhere
+            })
+            .map(|(_, declaration, kind)| {
+                let kind = match kind {
+                    ProcedureKind::Function => "Function",
+                    ProcedureKind::Sub => "Sub",
+                    _ => "Property",
+                };
+                Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!("{} '{}' is never called", kind, declaration.name),
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,58 @@
+//! `ASP010`: variables whose value is never read
+
+use crate::analysis::{DeclarationKind, ScopeKind};
+use crate::lint::{LintContext, Rule};
+use crate::parser::{Diagnostic, DiagnosticCode};
+
+/// Reports the variables declared with `Dim`, `Public`, `Private` or `ReDim` in a
+/// procedure or at the page level whose value is never read
+///
+/// Page-level variables may be read by the files the page includes or by the pages
+/// including it, so they are only reported when all these files are known and none
+/// of them reads the name. Class fields are not checked.
+pub struct UnusedVariable;
+
+impl Rule for UnusedVariable {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::UnusedVariable
+    }
+
+    fn description(&self) -> &'static str {
+        "Variable declared but never read"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        let page = context.bindings();
+        let other_files = context.other_bindings();
+        page.declarations
+            .iter()
+            .enumerate()
+            .filter(|(_, declaration)| declaration.kind == DeclarationKind::Variable)
+            .filter(|&(id, declaration)| {
+                if page.references_to(id).any(|reference| !reference.is_write) {
+                    return false;
+                }
+                match page.scopes[declaration.scope].kind {
+                    ScopeKind::Procedure => true,
+                    ScopeKind::Global => other_files.is_some_and(|files| {
+                        !files
+                            .iter()
+                            .flat_map(|file| &file.references)
+                            .any(|reference| {
+                                !reference.is_write
+                                    && reference.name.eq_ignore_ascii_case(&declaration.name)
+                            })
+                    }),
+                    ScopeKind::Class => false,
+                }
+            })
+            .map(|(_, declaration)| {
+                Diagnostic::new(
+                    self.code(),
+                    declaration.span,
+                    format!("Variable '{}' is declared but never read", declaration.name),
+                )
+            })
+            .collect()
+    }
+}
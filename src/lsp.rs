@@ -1,16 +1,26 @@
 #[allow(deprecated)]
 use dashmap::DashMap;
 use log;
+use ropey::Rope;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 use tokio::sync::Mutex;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
 
+use crate::analysis;
+use crate::config::Config;
+use crate::file_utils;
+use crate::formatter;
+use crate::includes::{self, IncludeResolver};
+use crate::lint;
 use crate::parser;
+use crate::parser::lexer::TokenKind;
 
 /// Structure representing a parser error with additional LSP-compatible information
 #[derive(Debug)]
@@ -25,6 +35,8 @@ pub struct ParseError {
     pub column_end: Option<usize>,
     /// The error type (error, warning, info, hint)
     pub error_type: String,
+    /// The diagnostic code (e.g. `ASP001`), if known
+    pub code: Option<String>,
 }
 
 /// Parses an ASP Classic file and returns any errors
@@ -39,79 +51,804 @@ pub struct ParseError {
 /// * `Ok(())` if parsing was successful, or
 /// * `Err(Vec<ParseError>)` containing the parser errors
 pub fn parse_asp_file(_file_path: &str, content: &str) -> std::result::Result<(), Vec<ParseError>> {
-    match parser::parse(content, false) {
-        Ok(_) => Ok(()),
-        Err(err) => {
-            if let Some(asp_err) = err.downcast_ref::<parser::AspParseError>() {
-                let error_type = if asp_err.is_no_asp_tags_error() {
-                    "warning"
-                } else {
-                    "error"
-                };
+    // Report every syntax error of the file, not only the first one
+    parser::parse_with_options(content, &parser::ParseOptions::default())
+        .map(|_| ())
+        .map_err(|diagnostics| {
+            diagnostics
+                .into_iter()
+                .map(|diagnostic| ParseError {
+                    message: diagnostic.to_string(),
+                    line: Some(diagnostic.span.line),
+                    column: Some(diagnostic.span.column),
+                    column_end: None,
+                    error_type: diagnostic.severity.as_str().to_string(),
+                    code: Some(diagnostic.code.as_str().to_string()),
+                })
+                .collect()
+        })
+}
 
-                // Create a ParseError from the AspParseError
-                // Extract line and column from the error message since we can't access private fields directly
-                let message = asp_err.to_string();
-                let mut line = None;
-                let mut column = None;
-
-                // Parse the error message to extract line and column
-                // Format is typically: "Parse error at line X, column Y: message"
-                if let Some(line_start) = message.find("line ") {
-                    let line_part = &message[line_start + 5..];
-                    if let Some(line_end) =
-                        line_part.find(|c: char| !c.is_ascii_digit() && c != ',' && c != ' ')
-                    {
-                        if let Ok(line_num) = line_part[..line_end]
-                            .trim_end_matches(',')
-                            .trim()
-                            .parse::<usize>()
-                        {
-                            line = Some(line_num);
-                        }
-                    }
+/// Extensions of the files the server parses, indexes and watches unless the
+/// `extensions` option of the configuration gives others: pages, `global.asa`,
+/// include files and VBScript files
+const SOURCE_EXTENSIONS: [&str; 4] = ["asp", "asa", "inc", "vbs"];
+
+/// Types of the semantic tokens, in the order of the legend
+const TOKEN_TYPES: &[SemanticTokenType] = &[
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::MACRO,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::CLASS,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::PROPERTY,
+];
+
+/// Modifiers of the semantic tokens, in the order of the legend
+const TOKEN_MODIFIERS: &[SemanticTokenModifier] = &[
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::READONLY,
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+];
+
+/// Bit of the `declaration` modifier
+const DECLARATION: u32 = 1;
+/// Bit of the `readonly` modifier
+const READONLY: u32 = 1 << 1;
+/// Bit of the `defaultLibrary` modifier
+const DEFAULT_LIBRARY: u32 = 1 << 2;
+
+/// A version of a page parsed once, whose syntax tree and names are shared by
+/// the requests of the client until the page changes
+#[derive(Debug)]
+pub struct ParsedDocument {
+    /// Content of the page
+    pub source: String,
+    /// Syntax tree of the page, partial if the page has syntax errors, and the
+    /// errors
+    pub parse: parser::RecoveredParse,
+    /// Declarations of the page and the references to them
+    pub bindings: analysis::Bindings,
+    /// Symbols declared by the page
+    pub symbols: analysis::SymbolTable,
+}
+
+impl ParsedDocument {
+    /// Parses a page, recovering from its syntax errors
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The content of the page
+    pub fn new(source: &str) -> Self {
+        ParsedDocument::from_parse(source, parser::parse_recovering(source))
+    }
+
+    /// Parses a new version of the page, parsing again only the ASP blocks around
+    /// the changes when the rest of the page is read the same
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The new content of the page
+    pub fn reparse(&self, source: &str) -> Self {
+        let parse = parser::reparse_recovering(&self.parse, &self.source, source);
+        ParsedDocument::from_parse(source, parse)
+    }
+
+    fn from_parse(source: &str, parse: parser::RecoveredParse) -> Self {
+        ParsedDocument {
+            source: source.to_string(),
+            bindings: analysis::bindings(&parse.program),
+            symbols: analysis::symbols(&parse.program),
+            parse,
+        }
+    }
+
+    /// Classifies the tokens of a page for semantic highlighting
+    ///
+    /// Keywords, operators, literals and comments are classified from the token
+    /// stream, so JScript blocks are highlighted too. Names of the VBScript code are
+    /// classified from their declaration: procedures, classes, variables, constants
+    /// (`readonly`) and parameters, with the `defaultLibrary` modifier for names
+    /// predefined by VBScript and ASP, and the `declaration` modifier where they are
+    /// declared. ASP tags and directives are `macro` tokens, while HTML text gets no
+    /// token but its comments.
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<SemanticToken>` - The tokens, relative to each other as sent to the
+    ///   client, split at line breaks
+    pub fn semantic_tokens(&self) -> Vec<SemanticToken> {
+        use analysis::{DeclarationKind, Resolution};
+        use parser::ast::ProcedureKind;
+
+        let declaration_token = |kind: DeclarationKind| match kind {
+            DeclarationKind::Variable | DeclarationKind::Implicit => {
+                (SemanticTokenType::VARIABLE, 0)
+            }
+            DeclarationKind::Constant => (SemanticTokenType::VARIABLE, READONLY),
+            DeclarationKind::Parameter => (SemanticTokenType::PARAMETER, 0),
+            DeclarationKind::Procedure(
+                ProcedureKind::PropertyGet
+                | ProcedureKind::PropertyLet
+                | ProcedureKind::PropertySet,
+            ) => (SemanticTokenType::PROPERTY, 0),
+            DeclarationKind::Procedure(_) => (SemanticTokenType::FUNCTION, 0),
+            DeclarationKind::Class => (SemanticTokenType::CLASS, 0),
+            DeclarationKind::Object => (SemanticTokenType::VARIABLE, 0),
+        };
+        let is_listed =
+            |names: &[&str], name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+
+        // Type and modifiers of the names of the code, by offset
+        let bindings = &self.bindings;
+        let mut names: HashMap<usize, (SemanticTokenType, u32)> = HashMap::new();
+        for declaration in &bindings.declarations {
+            let (token_type, modifiers) = declaration_token(declaration.kind);
+            let modifiers = match declaration.kind {
+                DeclarationKind::Implicit => modifiers,
+                _ => modifiers | DECLARATION,
+            };
+            names.insert(declaration.span.start, (token_type, modifiers));
+        }
+        for reference in &bindings.references {
+            let token = match reference.resolution {
+                Resolution::Declaration(id) => declaration_token(bindings.declarations[id].kind),
+                Resolution::Builtin
+                    if is_listed(analysis::builtins::FUNCTIONS, &reference.name) =>
+                {
+                    (SemanticTokenType::FUNCTION, DEFAULT_LIBRARY)
+                }
+                Resolution::Builtin
+                    if is_listed(analysis::builtins::CONSTANTS, &reference.name) =>
+                {
+                    (SemanticTokenType::VARIABLE, READONLY | DEFAULT_LIBRARY)
+                }
+                Resolution::Builtin => (SemanticTokenType::VARIABLE, DEFAULT_LIBRARY),
+                Resolution::Unresolved => (SemanticTokenType::VARIABLE, 0),
+            };
+            names.entry(reference.span.start).or_insert(token);
+        }
 
-                    if let Some(col_start) = line_part.find("column ") {
-                        let col_part = &line_part[col_start + 7..];
-                        if let Some(col_end) =
-                            col_part.find(|c: char| !c.is_ascii_digit() && c != ':' && c != ' ')
-                        {
-                            if let Ok(col_num) = col_part[..col_end]
-                                .trim_end_matches(':')
-                                .trim()
-                                .parse::<usize>()
-                            {
-                                column = Some(col_num);
-                            }
+        let lines = parser::ast::LineIndex::new(&self.source);
+        let mut tokens = Vec::new();
+        let mut previous = Position::default();
+        let mut in_directive = false;
+        let mut after_dot = false;
+        for token in parser::tokenize(&self.source) {
+            let classified = match token.kind {
+                TokenKind::Keyword => Some((SemanticTokenType::KEYWORD, 0)),
+                TokenKind::Operator => Some((SemanticTokenType::OPERATOR, 0)),
+                TokenKind::String => Some((SemanticTokenType::STRING, 0)),
+                TokenKind::Number | TokenKind::Date => Some((SemanticTokenType::NUMBER, 0)),
+                TokenKind::Comment | TokenKind::HtmlComment => {
+                    Some((SemanticTokenType::COMMENT, 0))
+                }
+                TokenKind::OpenTag
+                | TokenKind::OpenExpressionTag
+                | TokenKind::OpenDirectiveTag
+                | TokenKind::CloseTag
+                | TokenKind::ScriptOpenTag
+                | TokenKind::ScriptCloseTag => Some((SemanticTokenType::MACRO, 0)),
+                // Attributes of directives, and members of objects
+                TokenKind::Identifier if in_directive || after_dot => {
+                    Some((SemanticTokenType::PROPERTY, 0))
+                }
+                TokenKind::Identifier => Some(
+                    names
+                        .get(&token.span.start)
+                        .cloned()
+                        .unwrap_or((SemanticTokenType::VARIABLE, 0)),
+                ),
+                _ => None,
+            };
+            match token.kind {
+                TokenKind::OpenDirectiveTag => in_directive = true,
+                TokenKind::CloseTag => in_directive = false,
+                _ => {}
+            }
+            if !token.kind.is_trivia() {
+                after_dot = token.kind == TokenKind::Punctuation && token.text == ".";
+            }
+            let Some((token_type, modifiers)) = classified else {
+                continue;
+            };
+            let token_type = TOKEN_TYPES
+                .iter()
+                .position(|known| *known == token_type)
+                .unwrap_or(0) as u32;
+
+            // Tokens spanning several lines are sent line by line
+            let mut start = token.span.start;
+            for piece in token.text.split_inclusive('\n') {
+                let text = piece.trim_end_matches(['\r', '\n']);
+                let length = text.encode_utf16().count() as u32;
+                if length > 0 {
+                    let position = offset_position(&lines, start);
+                    tokens.push(SemanticToken {
+                        delta_line: position.line - previous.line,
+                        delta_start: if position.line == previous.line {
+                            position.character - previous.character
+                        } else {
+                            position.character
+                        },
+                        length,
+                        token_type,
+                        token_modifiers_bitset: modifiers,
+                    });
+                    previous = position;
+                }
+                start += piece.len();
+            }
+        }
+        tokens
+    }
+
+    /// Finds the occurrences in a page of the name at a position
+    ///
+    /// Occurrences are those of the same declaration, the declaration included, or
+    /// for names declared nowhere in the page, those of the same name. Declarations
+    /// and assignments are `WRITE` highlights, other uses `READ` highlights.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the name
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<DocumentHighlight>` - The occurrences, empty if there is no name at the
+    ///   position
+    pub fn document_highlights(&self, position: Position) -> Vec<DocumentHighlight> {
+        let lines = parser::ast::LineIndex::new(&self.source);
+        let offset = position_offset(&lines, position);
+        let bindings = &self.bindings;
+        let highlight = |span: parser::ast::Span, is_write: bool| DocumentHighlight {
+            range: span_range(&lines, span),
+            kind: Some(if is_write {
+                DocumentHighlightKind::WRITE
+            } else {
+                DocumentHighlightKind::READ
+            }),
+        };
+
+        // Predefined and undeclared names are matched by name
+        if let Some(reference) = bindings
+            .references
+            .iter()
+            .find(|reference| reference.span.start <= offset && offset <= reference.span.end)
+            && !matches!(reference.resolution, analysis::Resolution::Declaration(_))
+        {
+            return bindings
+                .references
+                .iter()
+                .filter(|other| {
+                    other.resolution == reference.resolution
+                        && other.name.eq_ignore_ascii_case(&reference.name)
+                })
+                .map(|other| highlight(other.span, other.is_write))
+                .collect();
+        }
+
+        let Some(id) = bindings.declaration_at(offset) else {
+            return Vec::new();
+        };
+        // Variables created by their first use have no declaration of their own
+        let declaration = &bindings.declarations[id];
+        (declaration.kind != analysis::DeclarationKind::Implicit)
+            .then(|| highlight(declaration.span, true))
+            .into_iter()
+            .chain(
+                bindings
+                    .references_to(id)
+                    .map(|reference| highlight(reference.span, reference.is_write)),
+            )
+            .collect()
+    }
+
+    /// Returns the ranges a selection at a position expands to, from the innermost
+    /// one
+    ///
+    /// The ranges are those of the expressions and statements containing the
+    /// position, procedure and class bodies included, then the whole page.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the cursor
+    ///
+    /// # Returns
+    ///
+    /// * `SelectionRange` - The innermost range, linked to the larger ones through
+    ///   its `parent`
+    pub fn selection_range(&self, position: Position) -> SelectionRange {
+        let lines = parser::ast::LineIndex::new(&self.source);
+        let offset = position_offset(&lines, position);
+        let program = &self.parse.program;
+
+        let mut spans = vec![lines.span(0, self.source.len())];
+        let contains = |span: &parser::ast::Span| span.start <= offset && offset <= span.end;
+        lint::walk_statements(&program.body, &mut |statement| {
+            if contains(&statement.span()) {
+                spans.push(statement.span());
+                for expression in lint::statement_expressions(statement) {
+                    lint::walk_expression(expression, &mut |expression| {
+                        if contains(&expression.span()) {
+                            spans.push(expression.span());
                         }
-                    }
+                    });
                 }
+            }
+        });
+
+        // From the largest range to the smallest
+        spans.sort_by_key(|span| (std::cmp::Reverse(span.end - span.start), span.start));
+        spans.dedup_by_key(|span| (span.start, span.end));
+        let mut selection: Option<SelectionRange> = None;
+        for span in spans {
+            selection = Some(SelectionRange {
+                range: span_range(&lines, span),
+                parent: selection.map(Box::new),
+            });
+        }
+        selection.unwrap_or(SelectionRange {
+            range: Range::new(position, position),
+            parent: None,
+        })
+    }
 
-                let parse_error = ParseError {
-                    message: asp_err.to_string(),
-                    line,
-                    column,
-                    column_end: None, // We don't have this information from the parser yet
-                    error_type: error_type.to_string(),
+    /// Returns the inlay hints of the arguments of the calls of a page
+    ///
+    /// Calls are matched by name to the procedures declared at the page level, in
+    /// the page or in the other given pages, such as its includes. Arguments named
+    /// like their parameter get no name hint. Parameters are passed by reference
+    /// unless declared `ByVal`, but only variables, without parentheses around
+    /// them, are changed by the procedure.
+    ///
+    /// # Arguments
+    ///
+    /// * `others` - The syntax trees of the other pages whose procedures the page
+    ///   can call
+    /// * `settings` - Which hints to return
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<InlayHint>` - The hints, in source order
+    pub fn inlay_hints(
+        &self,
+        others: &[&parser::ast::Program],
+        settings: InlayHintSettings,
+    ) -> Vec<InlayHint> {
+        use parser::ast::{Expression, ParameterPassing, Procedure, Statement};
+
+        if !settings.parameter_names && !settings.by_ref {
+            return Vec::new();
+        }
+        let program = &self.parse.program;
+        // Page-level procedures; methods are called on objects of unknown class
+        let mut procedures: Vec<&Procedure> = Vec::new();
+        for program in std::iter::once(program).chain(others.iter().copied()) {
+            let mut classes = Vec::new();
+            lint::walk_statements(&program.body, &mut |statement| match statement {
+                Statement::Class(class) => classes.push(class.span),
+                Statement::Procedure(procedure)
+                    if !classes.iter().any(|class| {
+                        class.start <= procedure.span.start && procedure.span.end <= class.end
+                    }) =>
+                {
+                    procedures.push(procedure)
+                }
+                _ => {}
+            });
+        }
+        let procedure = |callee: &Expression| match callee {
+            Expression::Identifier(name) => procedures
+                .iter()
+                .find(|procedure| procedure.name.name.eq_ignore_ascii_case(&name.name)),
+            _ => None,
+        };
+
+        let lines = parser::ast::LineIndex::new(&self.source);
+        let mut hints = Vec::new();
+        let mut hint_arguments = |procedure: &Procedure, arguments: &[Option<Expression>]| {
+            for (parameter, argument) in procedure.parameters.iter().zip(arguments) {
+                let Some(argument) = argument else {
+                    continue;
                 };
+                let mut label = Vec::new();
+                if settings.by_ref
+                    && parameter.passing != Some(ParameterPassing::ByVal)
+                    && matches!(argument, Expression::Identifier(_))
+                {
+                    label.push("ByRef".to_string());
+                }
+                if settings.parameter_names
+                    && !matches!(argument, Expression::Identifier(name)
+                        if name.name.eq_ignore_ascii_case(&parameter.name.name))
+                {
+                    label.push(format!("{}:", parameter.name.name));
+                }
+                if label.is_empty() {
+                    continue;
+                }
+                hints.push(InlayHint {
+                    position: offset_position(&lines, argument.span().start),
+                    label: InlayHintLabel::String(label.join(" ")),
+                    kind: Some(InlayHintKind::PARAMETER),
+                    text_edits: None,
+                    tooltip: None,
+                    padding_left: None,
+                    padding_right: Some(true),
+                    data: None,
+                });
+            }
+        };
+        lint::walk_statements(&program.body, &mut |statement| {
+            if let Statement::Call(call) = statement
+                && let Some(procedure) = procedure(&call.target)
+            {
+                hint_arguments(procedure, &call.arguments);
+            }
+            for expression in lint::statement_expressions(statement) {
+                lint::walk_expression(expression, &mut |expression| {
+                    if let Expression::Call(call) = expression
+                        && let Some(procedure) = procedure(&call.callee)
+                    {
+                        hint_arguments(procedure, &call.arguments);
+                    }
+                });
+            }
+        });
+        hints.sort_by_key(|hint| (hint.position.line, hint.position.character));
+        hints
+    }
 
-                Err(vec![parse_error])
-            } else {
-                // For other error types, create a generic error
-                let parse_error = ParseError {
-                    message: err.to_string(),
-                    line: None,
-                    column: None,
-                    column_end: None,
-                    error_type: "error".to_string(),
+    /// Returns the symbols declared by the page, class members nested in their
+    /// class
+    ///
+    /// Symbols come from the syntax tree, so that pages with syntax errors still
+    /// list the declarations of their valid statements.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let lines = parser::ast::LineIndex::new(&self.source);
+        self.symbols
+            .symbols
+            .iter()
+            .map(|symbol| document_symbol(&lines, symbol, false))
+            .collect()
+    }
+
+    /// Completes the member typed after `object.` at a position, when the object is
+    /// an ASP intrinsic object or a variable holding an object of a known type,
+    /// such as a COM object created from a known ProgID (see
+    /// [`analysis::objects::ObjectTypes`])
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - The position of the cursor, after the dot or in the member
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<CompletionItem>>` - The members starting with the letters
+    ///   typed, or `None` if the position does not follow the name of a known
+    ///   object and a dot
+    pub fn member_completions(&self, position: Position) -> Option<Vec<CompletionItem>> {
+        use analysis::objects::{self, MemberKind, ObjectTypes};
+
+        let lines = parser::ast::LineIndex::new(&self.source);
+        let offset = position_offset(&lines, position);
+        let trailing_word = |text: &str| {
+            let start = text
+                .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .map_or(0, |index| index + 1);
+            text.len() - start
+        };
+        let before = &self.source[..offset];
+        let typed = &before[before.len() - trailing_word(before)..];
+        let object = before[..before.len() - typed.len()].strip_suffix('.')?;
+        let name = &object[object.len() - trailing_word(object)..];
+        if name.is_empty() {
+            return None;
+        }
+
+        let object = match self
+            .bindings
+            .visible_declarations(offset)
+            .into_iter()
+            .find(|declaration| declaration.name.eq_ignore_ascii_case(name))
+        {
+            Some(declaration) => ObjectTypes::new(&self.parse.program, &self.bindings)
+                .variable_type(declaration.span.start)?,
+            None => objects::intrinsic_type(name)?,
+        };
+        let items = object
+            .members
+            .iter()
+            .filter(|member| {
+                member
+                    .name
+                    .get(..typed.len())
+                    .is_some_and(|start| start.eq_ignore_ascii_case(typed))
+            })
+            .map(|member| {
+                let (kind, kind_name) = match member.kind {
+                    MemberKind::Method => (CompletionItemKind::METHOD, "method"),
+                    MemberKind::Property => (CompletionItemKind::PROPERTY, "property"),
+                    MemberKind::Collection => (CompletionItemKind::PROPERTY, "collection"),
                 };
+                CompletionItem {
+                    label: member.name.to_string(),
+                    kind: Some(kind),
+                    detail: Some(format!("{} of the {} object", kind_name, object.name)),
+                    ..CompletionItem::default()
+                }
+            })
+            .collect();
+        Some(items)
+    }
+}
 
-                Err(vec![parse_error])
-            }
+/// Completes the member typed after `object.` at a position of a page, as
+/// [`ParsedDocument::member_completions`] does
+///
+/// # Arguments
+///
+/// * `content` - The content of the page
+/// * `position` - The position of the cursor, after the dot or in the member
+///
+/// # Returns
+///
+/// * `Option<Vec<CompletionItem>>` - The members starting with the letters typed,
+///   or `None` if the position does not follow the name of a known object and a dot
+pub fn member_completions(content: &str, position: Position) -> Option<Vec<CompletionItem>> {
+    ParsedDocument::new(content).member_completions(position)
+}
+
+/// Classifies the tokens of a page for semantic highlighting, as
+/// [`ParsedDocument::semantic_tokens`] does
+///
+/// # Arguments
+///
+/// * `content` - The content of the page
+///
+/// # Returns
+///
+/// * `Vec<SemanticToken>` - The tokens, relative to each other as sent to the
+///   client, split at line breaks
+pub fn semantic_tokens(content: &str) -> Vec<SemanticToken> {
+    ParsedDocument::new(content).semantic_tokens()
+}
+
+/// Finds the occurrences in a page of the name at a position, as
+/// [`ParsedDocument::document_highlights`] does
+///
+/// # Arguments
+///
+/// * `content` - The content of the page
+/// * `position` - The position of the name
+///
+/// # Returns
+///
+/// * `Vec<DocumentHighlight>` - The occurrences, empty if there is no name at the
+///   position
+pub fn document_highlights(content: &str, position: Position) -> Vec<DocumentHighlight> {
+    ParsedDocument::new(content).document_highlights(position)
+}
+
+/// Returns the ranges a selection at a position expands to, from the innermost
+/// one, as [`ParsedDocument::selection_range`] does
+///
+/// # Arguments
+///
+/// * `content` - The content of the page
+/// * `position` - The position of the cursor
+///
+/// # Returns
+///
+/// * `SelectionRange` - The innermost range, linked to the larger ones through
+///   its `parent`
+pub fn selection_range(content: &str, position: Position) -> SelectionRange {
+    ParsedDocument::new(content).selection_range(position)
+}
+
+/// Settings of the server, sent by the client in the initialization options
+/// and in configuration changes
+///
+/// ```json
+/// {"inlayHints": {"parameterNames": true, "byRef": false}, "enablePlugins": false}
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ServerSettings {
+    /// Which inlay hints are shown
+    pub inlay_hints: InlayHintSettings,
+    /// Whether the `plugins` of the configuration files are run on the
+    /// documents; off by default, since opening a page of an untrusted checkout
+    /// would otherwise run the programs its configuration names
+    pub enable_plugins: bool,
+}
+
+/// Inlay hints shown at the arguments of calls to the procedures of the pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintSettings {
+    /// Show the name of the parameter before each argument (`GetUser(id: 42)`)
+    pub parameter_names: bool,
+    /// Show `ByRef` before the variables the procedure receives by reference,
+    /// and may change
+    pub by_ref: bool,
+}
+
+impl Default for InlayHintSettings {
+    fn default() -> Self {
+        InlayHintSettings {
+            parameter_names: true,
+            by_ref: true,
         }
     }
 }
 
+/// Returns the inlay hints of the arguments of the calls of a page, as
+/// [`ParsedDocument::inlay_hints`] does
+///
+/// # Arguments
+///
+/// * `content` - The content of the page
+/// * `others` - The syntax trees of the other pages whose procedures the page
+///   can call
+/// * `settings` - Which hints to return
+///
+/// # Returns
+///
+/// * `Vec<InlayHint>` - The hints, in source order
+pub fn inlay_hints(
+    content: &str,
+    others: &[&parser::ast::Program],
+    settings: InlayHintSettings,
+) -> Vec<InlayHint> {
+    ParsedDocument::new(content).inlay_hints(others, settings)
+}
+
+/// Statements closing a block, re-indented when their last letter is typed
+const BLOCK_ENDS: [&str; 11] = [
+    "end if",
+    "end sub",
+    "end function",
+    "end property",
+    "end class",
+    "end select",
+    "end with",
+    "next",
+    "loop",
+    "wend",
+    "else",
+];
+
+/// Returns the characters, besides the line break, triggering on-type
+/// formatting: the last letters of the statements closing a block
+fn on_type_triggers() -> Vec<String> {
+    let mut triggers: Vec<String> = BLOCK_ENDS
+        .iter()
+        .filter_map(|end| end.chars().last())
+        .flat_map(|c| [c, c.to_ascii_uppercase()])
+        .map(String::from)
+        .collect();
+    triggers.sort();
+    triggers.dedup();
+    triggers
+}
+
+/// Returns the edits re-indenting lines as a character is typed
+///
+/// Typing the last letter of `End If`, `Next`, `Loop` or another statement
+/// closing a block re-indents its line to line up with the statement opening
+/// the block. A line break re-indents the line it ends and indents the new
+/// line as a statement written there would be. Only the indentation is
+/// changed, as [`formatter::format`] would write it.
+///
+/// # Arguments
+///
+/// * `content` - The content of the page, the typed character included
+/// * `position` - The position of the cursor after the typed character
+/// * `ch` - The typed character
+/// * `options` - The formatter settings, of which statements are never wrapped
+///   here
+///
+/// # Returns
+///
+/// * `Vec<TextEdit>` - The edits of the indentation of the lines, in order
+pub fn on_type_formatting(
+    content: &str,
+    position: Position,
+    ch: &str,
+    options: &formatter::FormatOptions,
+) -> Vec<TextEdit> {
+    let originals: Vec<&str> = content.split('\n').collect();
+    let line = position.line as usize;
+    let Some(&text) = originals.get(line) else {
+        return Vec::new();
+    };
+    let mut lines = originals.clone();
+    // An empty new line is indented as a statement written there would be,
+    // keeping its indentation outside of the code
+    let placeholder = format!("{}x", text.trim_end_matches('\r'));
+    let reindented = if ch == "\n" {
+        if text.trim().is_empty() {
+            lines[line] = &placeholder;
+        }
+        line.saturating_sub(1)..line + 1
+    } else {
+        let typed = text[..line_offset(text, position.character)]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .to_ascii_lowercase();
+        if !BLOCK_ENDS.contains(&typed.as_str()) {
+            return Vec::new();
+        }
+        line..line + 1
+    };
+    // Wrapping statements would move the lines below
+    let options = formatter::FormatOptions {
+        max_line_length: None,
+        ..options.clone()
+    };
+    let formatted = formatter::format_with_options(&lines.join("\n"), &options);
+    let formatted_lines: Vec<&str> = formatted.split('\n').collect();
+
+    let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let first_letter = |line: &str| {
+        line.trim_start_matches([' ', '\t'])
+            .chars()
+            .next()
+            .map(|c| c.to_ascii_lowercase())
+    };
+    let mut edits = Vec::new();
+    for index in reindented {
+        let target = formatted_lines.get(index).copied().unwrap_or_default();
+        // Lines starting with a tag or outside of the code keep their indentation
+        if first_letter(target).is_none_or(|c| !c.is_ascii_alphabetic())
+            || first_letter(target) != first_letter(lines[index])
+        {
+            continue;
+        }
+        let current = indentation(originals[index]);
+        let expected = &target[..indentation(target)];
+        if originals[index][..current] != *expected {
+            edits.push(TextEdit {
+                range: Range::new(
+                    Position::new(index as u32, 0),
+                    Position::new(index as u32, current as u32),
+                ),
+                new_text: expected.to_string(),
+            });
+        }
+    }
+    edits
+}
+
+/// Applies a change sent by the client to an open document
+///
+/// The range of the change is given in lines and UTF-16 code units, as required
+/// by the protocol; a change without range replaces the whole document.
+///
+/// # Arguments
+///
+/// * `document` - The content of the document
+/// * `change` - The change to apply
+pub fn apply_change(document: &mut Rope, change: TextDocumentContentChangeEvent) {
+    match change.range {
+        Some(range) => {
+            let start = position_char(document, range.start);
+            let end = position_char(document, range.end).max(start);
+            document.remove(start..end);
+            document.insert(start, &change.text);
+        }
+        None => *document = Rope::from_str(&change.text),
+    }
+}
+
 /// Structure representing an entry in the diagnostics cache
 #[derive(Debug, Clone)]
 struct DiagnosticCacheEntry {
@@ -123,15 +860,124 @@ struct DiagnosticCacheEntry {
     timestamp: Instant,
 }
 
+/// A document open in the client
+#[derive(Debug)]
+struct OpenDocument {
+    /// Content of the document in the editor
+    text: Rope,
+    /// Version of the content, increased by the client at each change
+    version: i32,
+    /// The last version of the content parsed by a request, and its number
+    parsed: Option<(i32, Arc<ParsedDocument>)>,
+}
+
+impl OpenDocument {
+    /// Creates an open document, parsed when a request needs it
+    fn new(text: &str, version: i32) -> Self {
+        OpenDocument {
+            text: Rope::from_str(text),
+            version,
+            parsed: None,
+        }
+    }
+}
+
+/// Symbol indexes of the workspace folders opened by the client
+///
+/// Each folder has its own index, resolving `virtual` includes from the folder,
+/// so that the sites of a repository do not see each other. Documents outside
+/// every folder share an index without web root.
+#[derive(Debug, Default)]
+struct Workspaces {
+    /// Workspace folders, with the index of their files
+    folders: Vec<(PathBuf, analysis::WorkspaceIndex)>,
+    /// Index of the documents outside the workspace folders
+    outside: analysis::WorkspaceIndex,
+}
+
+impl Workspaces {
+    /// Adds a workspace folder, with an empty index resolving includes with the
+    /// settings of its configuration
+    fn add(&mut self, root: PathBuf, config: &Config) {
+        if self.folders.iter().all(|(folder, _)| *folder != root) {
+            let index = analysis::WorkspaceIndex::new(folder_resolver(&root, config));
+            self.folders.push((root, index));
+        }
+    }
+
+    /// Removes a workspace folder and its index
+    fn remove(&mut self, root: &Path) {
+        self.folders.retain(|(folder, _)| folder != root);
+    }
+
+    /// Returns the position of the innermost workspace folder containing a file
+    fn folder(&self, path: &Path) -> Option<usize> {
+        self.folders
+            .iter()
+            .enumerate()
+            .filter(|(_, (root, _))| path.starts_with(root))
+            .max_by_key(|(_, (root, _))| root.components().count())
+            .map(|(position, _)| position)
+    }
+
+    /// Returns the root of the workspace folder containing a file
+    fn root(&self, path: &Path) -> Option<&Path> {
+        self.folder(path)
+            .map(|position| self.folders[position].0.as_path())
+    }
+
+    /// Returns the index of the workspace folder containing a file
+    fn index(&self, path: &Path) -> &analysis::WorkspaceIndex {
+        match self.folder(path) {
+            Some(position) => &self.folders[position].1,
+            None => &self.outside,
+        }
+    }
+
+    /// Returns the index of the workspace folder containing a file, to update it
+    fn index_mut(&mut self, path: &Path) -> &mut analysis::WorkspaceIndex {
+        match self.folder(path) {
+            Some(position) => &mut self.folders[position].1,
+            None => &mut self.outside,
+        }
+    }
+
+    /// Returns every index, those of the workspace folders first
+    fn indexes(&self) -> impl Iterator<Item = &analysis::WorkspaceIndex> {
+        self.folders
+            .iter()
+            .map(|(_, index)| index)
+            .chain(std::iter::once(&self.outside))
+    }
+}
+
 /// The ASP Classic Language Server
 #[derive(Debug)]
 pub struct AspLspServer {
     /// The client connection
     client: Client,
     /// Document store for currently open documents
-    documents: DashMap<Url, String>,
+    documents: Arc<DashMap<Url, OpenDocument>>,
     /// Cache of the last diagnostics results to avoid re-parsing unchanged files
     diagnostics_cache: Arc<Mutex<HashMap<PathBuf, DiagnosticCacheEntry>>>,
+    /// Symbols of the files of the workspace folders, of the validated
+    /// documents and of the files they include
+    workspaces: Arc<Mutex<Workspaces>>,
+    /// Whether the client shows the progress of server tasks
+    progress_supported: Arc<AtomicBool>,
+    /// Whether the client watches files for the server when asked to
+    watch_supported: Arc<AtomicBool>,
+    /// Whether the client was asked to watch the files of the workspace
+    watching: Arc<AtomicBool>,
+    /// Whether the client pulls the diagnostics of documents, instead of
+    /// receiving them when they change
+    pull_diagnostics: Arc<AtomicBool>,
+
+    /// Settings sent by the client
+    settings: Arc<Mutex<ServerSettings>>,
+    /// Configuration of each directory of the validated documents, merged from
+    /// the configuration files above it, until one of these files changes
+    configs: Arc<DashMap<PathBuf, Config>>,
 }
 
 impl AspLspServer {
@@ -139,8 +985,15 @@ impl AspLspServer {
     pub fn new(client: Client) -> Self {
         Self {
             client,
-            documents: DashMap::new(),
+            documents: Arc::new(DashMap::new()),
             diagnostics_cache: Arc::new(Mutex::new(HashMap::new())),
+            workspaces: Arc::new(Mutex::new(Workspaces::default())),
+            progress_supported: Arc::new(AtomicBool::new(false)),
+            watch_supported: Arc::new(AtomicBool::new(false)),
+            watching: Arc::new(AtomicBool::new(false)),
+            pull_diagnostics: Arc::new(AtomicBool::new(false)),
+            settings: Arc::new(Mutex::new(ServerSettings::default())),
+            configs: Arc::new(DashMap::new()),
         }
     }
 
@@ -149,17 +1002,25 @@ impl AspLspServer {
         uri.to_file_path().ok()
     }
 
-    /// Get the document content from an open document or file
+    /// Get the text of a document, copied from the open document or read from
+    /// its file
     async fn get_document_content(&self, uri: &Url) -> Option<String> {
-        if let Some(content) = self.documents.get(uri) {
+        self.get_document(uri)
+            .await
+            .map(|document| document.to_string())
+    }
+
+    /// Get a document from the open documents, sharing its rope, or from its file
+    async fn get_document(&self, uri: &Url) -> Option<Rope> {
+        if let Some(document) = self.documents.get(uri) {
             // Document is open, return the in-memory content
-            return Some(content.clone());
+            return Some(document.text.clone());
         }
 
         // Document not open, try to read from file
         if let Some(path) = self.uri_to_path(uri) {
             match tokio::fs::read_to_string(path).await {
-                Ok(content) => Some(content),
+                Ok(content) => Some(Rope::from_str(&content)),
                 Err(err) => {
                     log::error!("Failed to read file {}: {}", uri, err);
                     None
@@ -171,83 +1032,75 @@ impl AspLspServer {
         }
     }
 
-    /// Get the file extension from a URI
-    fn get_file_extension(&self, uri: &Url) -> Option<String> {
-        self.uri_to_path(uri).and_then(|path| {
-            path.extension()
-                .map(|ext| ext.to_string_lossy().to_string())
-        })
-    }
+    /// Get a document parsed, sharing the syntax tree of an open document
+    /// between requests until it changes, or parsing its file
+    async fn get_parsed_document(&self, uri: &Url) -> Option<Arc<ParsedDocument>> {
+        let open = self.documents.get(uri).map(|document| {
+            (
+                document.text.clone(),
+                document.version,
+                document.parsed.clone(),
+            )
+        });
+        let Some((text, version, parsed)) = open else {
+            let content = self.get_document_content(uri).await?;
+            return Some(Arc::new(ParsedDocument::new(&content)));
+        };
 
-    /// Check if a file should be parsed based on its extension
-    fn should_parse_file(&self, uri: &Url) -> bool {
-        if let Some(ext) = self.get_file_extension(uri) {
-            // Parse ASP and VBS files
-            ext.eq_ignore_ascii_case("asp") || ext.eq_ignore_ascii_case("vbs")
-        } else {
-            false
+        // Parsed without locking the documents, and kept unless the document
+        // changed meanwhile
+        let parsed = match parsed {
+            Some((parsed_version, parsed)) if parsed_version == version => return Some(parsed),
+            // Only the ASP blocks changed since the last parse are parsed again
+            Some((_, previous)) => Arc::new(previous.reparse(&text.to_string())),
+            None => Arc::new(ParsedDocument::new(&text.to_string())),
+        };
+        if let Some(mut document) = self.documents.get_mut(uri)
+            && document.version == version
+        {
+            document.parsed = Some((version, parsed.clone()));
         }
+        Some(parsed)
     }
 
-    /// Convert a parse error to a LSP diagnostic
-    fn parse_error_to_diagnostic(&self, error: ParseError, file_content: &str) -> Diagnostic {
-        // Get line number (1-based) from the error
-        let line_number = error.line.unwrap_or(1);
-
-        // Convert to 0-based line number for LSP
-        let line = line_number.saturating_sub(1);
-
-        // Get the line content to determine the column range
-        let file_lines: Vec<&str> = file_content.lines().collect();
-        let line_content = if line < file_lines.len() {
-            file_lines[line]
+    /// Returns the configuration applying to a document or directory
+    ///
+    /// The configuration files above a directory are read once, then again
+    /// only after one of them changes.
+    fn config(&self, path: &Path) -> Config {
+        let directory = if path.is_dir() {
+            path
         } else {
-            ""
-        };
-
-        // Set column range (if not available, highlight the whole line)
-        let (start_char, end_char) = match (error.column, error.column_end) {
-            (Some(col), Some(col_end)) => (col.saturating_sub(1), col_end),
-            (Some(col), None) => (col.saturating_sub(1), line_content.len()),
-            _ => (0, line_content.len()),
-        };
-
-        // Create the diagnostic range
-        let range = Range {
-            start: Position {
-                line: line as u32,
-                character: start_char as u32,
-            },
-            end: Position {
-                line: line as u32,
-                character: end_char as u32,
-            },
+            path.parent().unwrap_or(path)
         };
+        self.configs
+            .entry(directory.to_path_buf())
+            .or_insert_with(|| directory_config(directory))
+            .for_path(path)
+    }
 
-        // Determine the diagnostic severity based on error type
-        let severity = match error.error_type.as_str() {
-            "error" => Some(DiagnosticSeverity::ERROR),
-            "warning" => Some(DiagnosticSeverity::WARNING),
-            "hint" => Some(DiagnosticSeverity::HINT),
-            "info" => Some(DiagnosticSeverity::INFORMATION),
-            _ => Some(DiagnosticSeverity::ERROR), // Default to error
-        };
+    /// Returns the formatter options of a document, from the configuration
+    /// files above it and the indentation settings of the editor
+    fn document_format_options(
+        &self,
+        uri: &Url,
+        options: &FormattingOptions,
+    ) -> formatter::FormatOptions {
+        let config = self
+            .uri_to_path(uri)
+            .map(|path| self.config(&path))
+            .unwrap_or_default();
+        format_options(&config, options)
+    }
 
-        // Create the diagnostic
-        Diagnostic {
-            range,
-            severity,
-            code: None,
-            code_description: None,
-            source: Some("asp-classic-parser".to_string()),
-            message: error.message,
-            related_information: None,
-            tags: None,
-            data: None,
-        }
+    /// Check if a file should be parsed based on its extension and the
+    /// `extensions` option of the configuration files above it
+    fn should_parse_file(&self, uri: &Url) -> bool {
+        self.uri_to_path(uri)
+            .is_some_and(|path| is_source_file(&path, &source_extensions(&self.config(&path))))
     }
 
-    /// Parse a document and return diagnostics
+    /// Parse and lint a document and return diagnostics
     async fn parse_document(&self, uri: &Url) -> Vec<Diagnostic> {
         // Check if this is a file we should parse
         if !self.should_parse_file(uri) {
@@ -260,33 +1113,64 @@ impl AspLspServer {
             None => return Vec::new(),
         };
 
-        // Get document content
-        let content = match self.get_document_content(uri).await {
-            Some(content) => content,
-            None => return Vec::new(),
+        // Get the document, parsed once for all the requests on this version
+        let Some(parsed) = self.get_parsed_document(uri).await else {
+            return Vec::new();
         };
+        let content = parsed.source.clone();
 
         // Check the cache - if the content hasn't changed, return cached diagnostics
         {
             let cache = self.diagnostics_cache.lock().await;
-            if let Some(cached_entry) = cache.get(&file_path) {
-                if cached_entry.content == content {
-                    log::debug!("Using cached diagnostics for {}", uri);
-                    return cached_entry.diagnostics.clone();
-                }
+            if let Some(cached_entry) = cache.get(&file_path)
+                && cached_entry.content == content
+            {
+                log::debug!("Using cached diagnostics for {}", uri);
+                return cached_entry.diagnostics.clone();
             }
         }
 
-        // Convert the URI to a string path for parsing
-        let path_str = file_path.to_string_lossy();
-
-        // Parse the document
-        let parse_result = match parse_asp_file(&path_str, &content) {
-            Ok(_) => Vec::new(), // No errors
-            Err(errors) => errors
+        // Run the lint rules, with the settings of the configuration files
+        // above the document
+        let mut config = self.config(&file_path);
+        if !self.settings.lock().await.enable_plugins {
+            config.plugins = None;
+        }
+        let registry = match config.lint_registry() {
+            Ok(registry) => registry,
+            Err(e) => {
+                log::error!("Invalid configuration for {}: {}", file_path.display(), e);
+                lint::Registry::default()
+            }
+        };
+        let lines = parser::ast::LineIndex::new(&content);
+        let parse_result: Vec<Diagnostic> = {
+            let workspaces = self.workspaces.lock().await;
+            let index = workspaces.index(&file_path);
+            // Pages without ASP code are parsed again to report them as such
+            let has_code = parsed
+                .parse
+                .program
+                .body
+                .iter()
+                .any(|statement| !matches!(statement, parser::ast::Statement::Html(_)));
+            let diagnostics = if !parsed.parse.diagnostics.is_empty() {
+                parsed.parse.diagnostics.clone()
+            } else if has_code {
+                lint::lint_program(
+                    &file_path,
+                    &content,
+                    &parsed.parse.program,
+                    &registry,
+                    index,
+                )
+            } else {
+                lint::lint_file(&file_path, &content, &registry, index)
+            };
+            diagnostics
                 .into_iter()
-                .map(|err| self.parse_error_to_diagnostic(err, &content))
-                .collect(),
+                .map(|diagnostic| lint_diagnostic(uri, &lines, index, diagnostic))
+                .collect()
         };
 
         // Update the cache
@@ -305,8 +1189,260 @@ impl AspLspServer {
         parse_result
     }
 
+    /// Add a document and the files it includes to the symbol index, unless it
+    /// is indexed with its current content already
+    async fn index_document(&self, uri: &Url) {
+        if !self.should_parse_file(uri) {
+            return;
+        }
+        let (Some(path), Some(parsed)) =
+            (self.uri_to_path(uri), self.get_parsed_document(uri).await)
+        else {
+            return;
+        };
+        let mut workspaces = self.workspaces.lock().await;
+        let index = workspaces.index_mut(&path);
+        if index
+            .file(&path)
+            .is_some_and(|file| file.source == parsed.source)
+        {
+            return;
+        }
+        index.update_parsed(&path, &parsed.source, parsed.parse.program.clone());
+        index.load_includes(&path);
+    }
+
+    /// Add the pages, `global.asa`, include and VBScript files of a workspace
+    /// folder to its symbol index, reporting the progress to the client
+    ///
+    /// Open documents are indexed from their content in the editor instead.
+    async fn index_folder(&self, root: PathBuf) {
+        let token = NumberOrString::String(format!("asp-classic-parser/index/{}", root.display()));
+        let folder = root.clone();
+        let files = match tokio::task::spawn_blocking(move || {
+            let extensions = source_extensions(&document_config(&folder));
+            let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+            file_utils::find_files_with_extensions(&folder, &[], &extensions)
+        })
+        .await
+        {
+            Ok(Ok(files)) => files,
+            Ok(Err(err)) => {
+                log::error!("Failed to scan the workspace: {}", err);
+                return;
+            }
+            Err(err) => {
+                log::error!("Failed to scan the workspace: {}", err);
+                return;
+            }
+        };
+
+        let progress = self.progress_supported.load(Ordering::Relaxed)
+            && self
+                .client
+                .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                    token: token.clone(),
+                })
+                .await
+                .is_ok();
+        let report = |value: WorkDoneProgress| {
+            let token = token.clone();
+            async move {
+                if progress {
+                    self.client
+                        .send_notification::<notification::Progress>(ProgressParams {
+                            token,
+                            value: ProgressParamsValue::WorkDone(value),
+                        })
+                        .await;
+                }
+            }
+        };
+        report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: match root.file_name() {
+                Some(name) => format!("Indexing ASP files of {}", name.to_string_lossy()),
+                None => "Indexing ASP files".to_string(),
+            },
+            cancellable: Some(false),
+            message: Some(format!("0/{}", files.len())),
+            percentage: Some(0),
+        }))
+        .await;
+
+        let mut percentage = 0;
+        for (count, path) in files.iter().enumerate() {
+            let is_open =
+                Url::from_file_path(path).is_ok_and(|uri| self.documents.contains_key(&uri));
+            if !is_open {
+                match file_utils::read_file_with_encoding(path) {
+                    Ok(source) => {
+                        let mut workspaces = self.workspaces.lock().await;
+                        // The folder may have been closed, and nested folders
+                        // index their own files
+                        if workspaces.root(path) == Some(root.as_path()) {
+                            workspaces.index_mut(path).update(path, &source);
+                        }
+                    }
+                    Err(err) => log::warn!("Failed to read file {}: {}", path.display(), err),
+                }
+            }
+            let done = ((count + 1) * 100 / files.len()) as u32;
+            if done > percentage {
+                percentage = done;
+                report(WorkDoneProgress::Report(WorkDoneProgressReport {
+                    cancellable: Some(false),
+                    message: Some(format!("{}/{}", count + 1, files.len())),
+                    percentage: Some(percentage),
+                }))
+                .await;
+            }
+        }
+
+        log::info!("Indexed {} files of {}", files.len(), root.display());
+        report(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(format!("{} files indexed", files.len())),
+        }))
+        .await;
+    }
+
+    /// Ask the client to report the changes of the source files and
+    /// configuration files of the workspace, replacing the files watched before
+    ///
+    /// The source files have the extensions of the workspace folders (see
+    /// [`source_extensions`]).
+    async fn watch_files(&self) {
+        if !self.watch_supported.load(Ordering::Relaxed) {
+            return;
+        }
+        const WATCH_ID: &str = "asp-classic-parser/watch";
+        const WATCH_METHOD: &str = "workspace/didChangeWatchedFiles";
+        if self.watching.swap(false, Ordering::Relaxed) {
+            let unregistration = Unregistration {
+                id: WATCH_ID.to_string(),
+                method: WATCH_METHOD.to_string(),
+            };
+            if let Err(err) = self
+                .client
+                .unregister_capability(vec![unregistration])
+                .await
+            {
+                log::warn!("Failed to stop watching the workspace files: {}", err);
+            }
+        }
+
+        let roots: Vec<PathBuf> = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces
+                .folders
+                .iter()
+                .map(|(root, _)| root.clone())
+                .collect()
+        };
+        let mut extensions: Vec<String> = Vec::new();
+        for root in &roots {
+            for extension in source_extensions(&self.config(root)) {
+                if !extensions.contains(&extension) {
+                    extensions.push(extension);
+                }
+            }
+        }
+        if roots.is_empty() {
+            extensions = SOURCE_EXTENSIONS.map(String::from).to_vec();
+        }
+
+        let watcher = |pattern: &str| FileSystemWatcher {
+            glob_pattern: GlobPattern::String(pattern.to_string()),
+            kind: None,
+        };
+        let options = DidChangeWatchedFilesRegistrationOptions {
+            watchers: vec![
+                watcher(&format!("**/*.{{{}}}", extensions.join(","))),
+                watcher("**/{.asp-parser.toml,asp-parser.toml}"),
+            ],
+        };
+        let registration = Registration {
+            id: WATCH_ID.to_string(),
+            method: WATCH_METHOD.to_string(),
+            register_options: serde_json::to_value(options).ok(),
+        };
+        match self.client.register_capability(vec![registration]).await {
+            Ok(()) => self.watching.store(true, Ordering::Relaxed),
+            Err(err) => log::warn!("Failed to watch the workspace files: {}", err),
+        }
+    }
+
+    /// Validate again the open documents that see one of the given files,
+    /// through their includes or the pages including them, or every open
+    /// document if `all` is set
+    async fn revalidate_dependents(&self, changed: &[PathBuf], all: bool) {
+        let changed: Vec<PathBuf> = changed
+            .iter()
+            .map(|path| includes::canonical_path(path))
+            .collect();
+        let open: Vec<Url> = self
+            .documents
+            .iter()
+            .map(|document| document.key().clone())
+            .collect();
+        let mut refresh = false;
+        for uri in open {
+            let Some(path) = self.uri_to_path(&uri) else {
+                continue;
+            };
+            // Included files missing from the index are still listed by the
+            // files including them
+            let affected = all || {
+                let workspaces = self.workspaces.lock().await;
+                workspaces
+                    .index(&path)
+                    .visible_files(&path)
+                    .iter()
+                    .any(|file| {
+                        changed.contains(&file.path)
+                            || file.includes.iter().any(|path| changed.contains(path))
+                    })
+            };
+            if affected {
+                self.diagnostics_cache.lock().await.remove(&path);
+                self.validate_document(uri).await;
+                refresh = true;
+            }
+        }
+
+        // Clients pulling diagnostics do not know that other files changed them
+        if refresh
+            && self.pull_diagnostics.load(Ordering::Relaxed)
+            && let Err(err) = self
+                .client
+                .send_request::<request::WorkspaceDiagnosticRefresh>(())
+                .await
+        {
+            log::warn!("Failed to refresh the diagnostics: {}", err);
+        }
+    }
+
+    /// Replace the settings with those sent by the client, given either alone
+    /// or in an `asp-classic-parser` section
+    async fn update_settings(&self, value: serde_json::Value) {
+        let value = match value.get("asp-classic-parser") {
+            Some(section) => section.clone(),
+            None => value,
+        };
+        match serde_json::from_value::<ServerSettings>(value) {
+            Ok(settings) => *self.settings.lock().await = settings,
+            Err(err) => log::warn!("Invalid settings: {}", err),
+        }
+    }
+
     /// Validate a document and publish diagnostics
     async fn validate_document(&self, uri: Url) {
+        self.index_document(&uri).await;
+
+        // Clients pulling diagnostics ask for them when documents change
+        if self.pull_diagnostics.load(Ordering::Relaxed) {
+            return;
+        }
+
         // Parse the document to get diagnostics
         let diagnostics = self.parse_document(&uri).await;
 
@@ -345,140 +1481,15 @@ impl AspLspServer {
         }
     }
 
-    /// Convert a position (line, character) to an offset in the text
-    fn position_to_offset(&self, text: &str, position: Position) -> Option<usize> {
-        let mut lines = text.split('\n');
-        let mut offset = 0;
-
-        // Find the offset for the line
-        for _ in 0..position.line {
-            if let Some(line) = lines.next() {
-                offset += line.len() + 1; // +1 for the newline
-            } else {
-                return None;
-            }
-        }
-
-        // Add the character offset
-        if let Some(line) = lines.next() {
-            if position.character as usize <= line.len() {
-                offset += position.character as usize;
-                Some(offset)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    }
-
-    /// Get the word at a given position
-    fn get_word_at_position(&self, text: &str, position: Position) -> Option<String> {
-        // Get the line at the position
-        let lines: Vec<&str> = text.lines().collect();
-        let line = position.line as usize;
-
-        if line >= lines.len() {
-            return None;
-        }
-
-        let line_text = lines[line];
-        let character = position.character as usize;
-
-        if character >= line_text.len() {
-            return None;
-        }
-
-        // Find the word boundaries
-        let mut start = character;
-        let mut end = character;
-
-        // Move to the start of the word
-        while start > 0 {
-            let prev_char = line_text.chars().nth(start - 1).unwrap_or(' ');
-            if !prev_char.is_alphanumeric() && prev_char != '_' {
-                break;
-            }
-            start -= 1;
-        }
-
-        // Move to the end of the word
-        while end < line_text.len() {
-            let next_char = line_text.chars().nth(end).unwrap_or(' ');
-            if !next_char.is_alphanumeric() && next_char != '_' {
-                break;
-            }
-            end += 1;
-        }
-
-        // Extract the word
-        if start < end {
-            Some(line_text[start..end].to_string())
-        } else {
-            None
-        }
-    }
-
-    /// Check if a position is inside ASP tags
-    fn is_position_in_asp_tag(&self, text: &str, position: Position) -> bool {
-        // Get the line at the position
-        let lines: Vec<&str> = text.lines().collect();
-        let line = position.line as usize;
-
-        if line >= lines.len() {
-            return false;
-        }
-
-        // Try to find the nearest ASP tags
-        let mut in_asp_tag = false;
-        let mut open_pos = 0;
-
-        for (i, current_line) in lines.iter().enumerate().take(line + 1) {
-            // Check for ASP open tags
-            let mut line_offset = 0;
-            while let Some(pos) = current_line[line_offset..].find("<%") {
-                let absolute_pos = line_offset + pos;
-                open_pos = absolute_pos;
-                in_asp_tag = true;
-                line_offset = absolute_pos + 2;
-            }
-
-            // Check for ASP close tags
-            let mut line_offset = 0;
-            while let Some(pos) = current_line[line_offset..].find("%>") {
-                let absolute_pos = line_offset + pos;
-                if i == line && absolute_pos < position.character as usize && in_asp_tag {
-                    in_asp_tag = false;
-                }
-                line_offset = absolute_pos + 2;
-            }
-
-            // If we're on the current line, check position
-            if i == line {
-                if in_asp_tag {
-                    return position.character as usize > open_pos;
-                }
-                break;
-            }
-        }
-
-        in_asp_tag
-    }
-
     /// Generate code completions based on context
-    fn generate_completions(&self, text: &str, position: Position) -> Vec<CompletionItem> {
+    fn generate_completions(&self, document: &Rope, position: Position) -> Vec<CompletionItem> {
         let mut completions = Vec::new();
 
         // Get the line at the position
-        let lines: Vec<&str> = text.lines().collect();
-        let line = position.line as usize;
-
-        if line >= lines.len() {
+        let Some(line_text) = line_text(document, position.line as usize) else {
             return completions;
-        }
-
-        let line_text = lines[line];
-        let prefix = &line_text[..position.character as usize];
+        };
+        let prefix = &line_text[..line_offset(&line_text, position.character)];
 
         // Add VBScript keywords
         if !prefix.trim().is_empty() {
@@ -534,55 +1545,6 @@ impl AspLspServer {
             }
         }
 
-        // Add ASP built-in objects
-        if prefix.trim_end().ends_with(".") {
-            let object_prefix = prefix.trim_end().trim_end_matches(".");
-
-            // Response object methods
-            if object_prefix.eq_ignore_ascii_case("Response") {
-                let methods = [
-                    "Write",
-                    "End",
-                    "Flush",
-                    "Clear",
-                    "Redirect",
-                    "Buffer",
-                    "ContentType",
-                ];
-
-                for method in &methods {
-                    completions.push(CompletionItem {
-                        label: method.to_string(),
-                        kind: Some(CompletionItemKind::METHOD),
-                        detail: Some(format!("Response.{} - ASP Response object method", method)),
-                        insert_text: Some(method.to_string()),
-                        ..CompletionItem::default()
-                    });
-                }
-            }
-
-            // Request object methods
-            if object_prefix.eq_ignore_ascii_case("Request") {
-                let methods = [
-                    "QueryString",
-                    "Form",
-                    "Cookies",
-                    "ServerVariables",
-                    "TotalBytes",
-                ];
-
-                for method in &methods {
-                    completions.push(CompletionItem {
-                        label: method.to_string(),
-                        kind: Some(CompletionItemKind::METHOD),
-                        detail: Some(format!("Request.{} - ASP Request object method", method)),
-                        insert_text: Some(method.to_string()),
-                        ..CompletionItem::default()
-                    });
-                }
-            }
-        }
-
         // Add VBScript built-in functions
         let functions = [
             "Abs",
@@ -669,8 +1631,173 @@ impl AspLspServer {
                 });
             }
         }
-
-        completions
+
+        completions
+    }
+
+    /// Complete the names declared by the user that are visible at a position:
+    /// the variables, parameters and procedures of the enclosing scopes, and the
+    /// page-level symbols of the included files and of the pages including the
+    /// document
+    async fn symbol_completions(
+        &self,
+        uri: &Url,
+        parsed: &ParsedDocument,
+        position: Position,
+    ) -> Vec<CompletionItem> {
+        let content = parsed.source.as_str();
+        let lines = parser::ast::LineIndex::new(content);
+        let offset = position_offset(&lines, position);
+        let word: String = content[..offset]
+            .chars()
+            .rev()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect::<Vec<char>>()
+            .into_iter()
+            .rev()
+            .collect();
+        // Members are not names of the page
+        if content[..offset - word.len()].ends_with('.') {
+            return Vec::new();
+        }
+        let matches = |name: &str| {
+            name.get(..word.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(&word))
+        };
+
+        let bindings = &parsed.bindings;
+        let mut seen = std::collections::HashSet::new();
+        let mut items = Vec::new();
+        for declaration in bindings.visible_declarations(offset) {
+            if !matches(&declaration.name) || !seen.insert(declaration.name.to_lowercase()) {
+                continue;
+            }
+            items.push(CompletionItem {
+                label: declaration.name.clone(),
+                kind: Some(declaration_completion_kind(declaration.kind)),
+                ..CompletionItem::default()
+            });
+        }
+
+        // Symbols of the other files the document sees
+        let Some(path) = self.uri_to_path(uri) else {
+            return items;
+        };
+        let workspaces = self.workspaces.lock().await;
+        let index = workspaces.index(&path);
+        for file in index.visible_files(&path).into_iter().skip(1) {
+            let file_name = file
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            for symbol in &file.symbols.symbols {
+                if !matches(&symbol.name) || !seen.insert(symbol.name.to_lowercase()) {
+                    continue;
+                }
+                items.push(CompletionItem {
+                    label: symbol.name.clone(),
+                    kind: Some(completion_kind(symbol.kind)),
+                    detail: Some(format!("{} ({})", symbol.signature(), file_name)),
+                    ..CompletionItem::default()
+                });
+            }
+        }
+        items
+    }
+
+    /// Complete the path of an include directive, if the position is in one
+    ///
+    /// Returns `None` outside of include directives, and the files and
+    /// directories of the directory being typed otherwise.
+    async fn include_completions(
+        &self,
+        uri: &Url,
+        document: &Rope,
+        position: Position,
+    ) -> Option<Vec<CompletionItem>> {
+        let line = line_text(document, position.line as usize)?;
+        let (kind, typed) = include_path_at(&line[..line_offset(&line, position.character)])?;
+
+        // The directory typed so far, and the start of the name in it
+        let (directory, name) = match typed.rfind(['/', '\\']) {
+            Some(index) => (&typed[..=index], &typed[index + 1..]),
+            None => ("", typed),
+        };
+        let path = self.uri_to_path(uri)?;
+        let resolver = self.workspaces.lock().await.index(&path).resolver().clone();
+        let directive = includes::IncludeDirective {
+            kind,
+            path: directory.to_string(),
+            span: parser::ast::Span::default(),
+        };
+        let directory = resolver.resolve(&path, &directive)?;
+
+        // The typed name is replaced, as editors may not include dots in words
+        let range = Range {
+            start: Position {
+                line: position.line,
+                character: position.character - name.encode_utf16().count() as u32,
+            },
+            end: position,
+        };
+        let mut items = Vec::new();
+        for entry in std::fs::read_dir(directory).ok()?.flatten() {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            if entry_name.starts_with('.') {
+                continue;
+            }
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let new_text = if is_dir {
+                format!("{}/", entry_name)
+            } else {
+                entry_name.clone()
+            };
+            items.push(CompletionItem {
+                label: new_text.clone(),
+                kind: Some(if is_dir {
+                    CompletionItemKind::FOLDER
+                } else {
+                    CompletionItemKind::FILE
+                }),
+                filter_text: Some(entry_name),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text })),
+                ..CompletionItem::default()
+            });
+        }
+        items.sort_by(|a, b| a.label.cmp(&b.label));
+        Some(items)
+    }
+
+    /// Describe the symbol declared in the pages that the name at a position
+    /// refers to: its declaration, the comments above it and the file declaring
+    /// it if it is another one
+    async fn symbol_hover(&self, uri: &Url, position: Position) -> Option<String> {
+        let path = self.uri_to_path(uri)?;
+        // The index may not have the last changes of the document yet
+        self.index_document(uri).await;
+        let workspaces = self.workspaces.lock().await;
+        let index = workspaces.index(&path);
+        let file = index.file(&path)?;
+        let offset = position_offset(&parser::ast::LineIndex::new(&file.source), position);
+        let definition = index.definition_at(&path, offset)?;
+        let declaring = index.file(definition.path)?;
+        let symbol = declaring
+            .symbols
+            .iter()
+            .find(|symbol| symbol.name_span == definition.span)?;
+
+        let mut value = format!("```vbscript\n{}\n```", symbol.signature());
+        if let Some(comment) = analysis::doc_comment(&declaring.source, symbol.span) {
+            value.push_str("\n\n");
+            value.push_str(&comment);
+        }
+        if definition.path != path
+            && let Some(name) = definition.path.file_name()
+        {
+            value.push_str(&format!("\n\nDefined in `{}`", name.to_string_lossy()));
+        }
+        Some(value)
     }
 
     /// Provide hover content for common ASP/VBScript elements
@@ -692,238 +1819,438 @@ impl AspLspServer {
             _ => None,
         }
     }
+}
 
-    /// Extract document symbols from content
-    fn extract_document_symbols(&self, content: &str) -> Vec<DocumentSymbol> {
-        let mut symbols = Vec::new();
-        let lines: Vec<&str> = content.lines().collect();
+/// Converts a symbol of the symbol table into an LSP document symbol
+fn document_symbol(
+    lines: &parser::ast::LineIndex,
+    symbol: &analysis::Symbol,
+    is_member: bool,
+) -> DocumentSymbol {
+    let children: Vec<DocumentSymbol> = symbol
+        .members
+        .iter()
+        .map(|member| document_symbol(lines, member, true))
+        .collect();
+
+    DocumentSymbol {
+        name: symbol.name.clone(),
+        detail: Some(symbol.signature()),
+        kind: symbol_kind(symbol.kind, is_member),
+        range: span_range(lines, symbol.span),
+        selection_range: span_range(lines, symbol.name_span),
+        children: (!children.is_empty()).then_some(children),
+        tags: None,
+        deprecated: None,
+    }
+}
 
-        // Track function-level constructs
-        let mut function_stack: Vec<(DocumentSymbol, usize)> = Vec::new();
+/// Returns the LSP kind of a symbol of the symbol table
+fn symbol_kind(kind: analysis::SymbolKind, is_member: bool) -> SymbolKind {
+    use analysis::SymbolKind as Kind;
+
+    match kind {
+        Kind::Class => SymbolKind::CLASS,
+        Kind::Sub | Kind::Function if is_member => SymbolKind::METHOD,
+        Kind::Sub | Kind::Function => SymbolKind::FUNCTION,
+        Kind::PropertyGet | Kind::PropertyLet | Kind::PropertySet => SymbolKind::PROPERTY,
+        Kind::Constant => SymbolKind::CONSTANT,
+        Kind::Variable if is_member => SymbolKind::FIELD,
+        Kind::Variable => SymbolKind::VARIABLE,
+        Kind::Object => SymbolKind::OBJECT,
+    }
+}
 
-        // Regular expressions for matching syntax structures
-        let function_regex = regex::Regex::new(r"(?i)^\s*(function|sub)\s+([a-z0-9_]+)").unwrap();
-        let end_function_regex = regex::Regex::new(r"(?i)^\s*end\s+(function|sub)").unwrap();
-        let class_regex = regex::Regex::new(r"(?i)^\s*class\s+([a-z0-9_]+)").unwrap();
-        let end_class_regex = regex::Regex::new(r"(?i)^\s*end\s+class").unwrap();
-        let dim_regex = regex::Regex::new(r"(?i)^\s*dim\s+([a-z0-9_,\s]+)").unwrap();
+/// Returns the completion kind of a symbol of the symbol table
+fn completion_kind(kind: analysis::SymbolKind) -> CompletionItemKind {
+    use analysis::SymbolKind as Kind;
 
-        for (i, line) in lines.iter().enumerate() {
-            let line_trimmed = line.trim();
+    match kind {
+        Kind::Class => CompletionItemKind::CLASS,
+        Kind::Sub | Kind::Function => CompletionItemKind::FUNCTION,
+        Kind::PropertyGet | Kind::PropertyLet | Kind::PropertySet => CompletionItemKind::PROPERTY,
+        Kind::Constant => CompletionItemKind::CONSTANT,
+        Kind::Variable | Kind::Object => CompletionItemKind::VARIABLE,
+    }
+}
 
-            // Skip lines outside ASP tags
-            if !self.is_line_in_asp_tag(content, i) {
-                continue;
-            }
+/// Returns the completion kind of a declared name
+fn declaration_completion_kind(kind: analysis::DeclarationKind) -> CompletionItemKind {
+    use analysis::DeclarationKind as Kind;
+    use parser::ast::ProcedureKind;
 
-            // Check for Function/Sub declarations
-            if let Some(caps) = function_regex.captures(line_trimmed) {
-                let kind = caps.get(1).unwrap().as_str();
-                let name = caps.get(2).unwrap().as_str();
-
-                let symbol = DocumentSymbol {
-                    name: name.to_string(),
-                    detail: Some(format!("{} {}", kind, name)),
-                    kind: if kind.eq_ignore_ascii_case("sub") {
-                        SymbolKind::FUNCTION
-                    } else {
-                        SymbolKind::METHOD
-                    },
-                    range: Range {
-                        start: Position {
-                            line: i as u32,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: i as u32,
-                            character: line.len() as u32,
-                        },
-                    },
-                    selection_range: Range {
-                        start: Position {
-                            line: i as u32,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: i as u32,
-                            character: line.len() as u32,
-                        },
-                    },
-                    children: Some(Vec::new()),
-                    tags: None,
-                    deprecated: None,
-                };
+    match kind {
+        Kind::Class => CompletionItemKind::CLASS,
+        Kind::Procedure(ProcedureKind::Sub | ProcedureKind::Function) => {
+            CompletionItemKind::FUNCTION
+        }
+        Kind::Procedure(_) => CompletionItemKind::PROPERTY,
+        Kind::Constant => CompletionItemKind::CONSTANT,
+        Kind::Variable | Kind::Parameter | Kind::Object | Kind::Implicit => {
+            CompletionItemKind::VARIABLE
+        }
+    }
+}
 
-                function_stack.push((symbol, i));
-            }
+/// Returns the kind of include and the path typed so far, if a line ends in the
+/// path of an include directive (`<!--#include file="lib/d`)
+fn include_path_at(line: &str) -> Option<(includes::IncludeKind, &str)> {
+    let start = line.to_ascii_lowercase().rfind("#include")?;
+    let rest = line[start + "#include".len()..].trim_start();
+    let attribute_end = rest.find(|c: char| !c.is_ascii_alphabetic())?;
+    let kind = match rest[..attribute_end].to_ascii_lowercase().as_str() {
+        "file" => includes::IncludeKind::File,
+        "virtual" => includes::IncludeKind::Virtual,
+        _ => return None,
+    };
+    let value = rest[attribute_end..]
+        .trim_start()
+        .strip_prefix('=')?
+        .trim_start();
+    let path = value.strip_prefix(['"', '\''])?;
+    // The cursor is past the closing quote
+    if path.contains(['"', '\'']) {
+        return None;
+    }
+    Some((kind, path))
+}
 
-            // Check for End Function/Sub
-            if end_function_regex.captures(line_trimmed).is_some() {
-                if let Some((mut symbol, _)) = function_stack.pop() {
-                    // Update the end range
-                    symbol.range.end = Position {
-                        line: i as u32,
-                        character: line.len() as u32,
-                    };
-
-                    // Add to the parent or directly to the symbols list
-                    if let Some((parent, _)) = function_stack.last_mut() {
-                        if let Some(children) = &mut parent.children {
-                            children.push(symbol);
-                        }
-                    } else {
-                        symbols.push(symbol);
-                    }
-                }
-            }
+/// Lists the symbols of the index matching a workspace symbol query
+fn workspace_symbols(index: &analysis::WorkspaceIndex, query: &str) -> Vec<SymbolInformation> {
+    index
+        .search(query)
+        .into_iter()
+        .filter_map(|location| {
+            let file = index.file(location.path)?;
+            let lines = parser::ast::LineIndex::new(&file.source);
+            Some(SymbolInformation {
+                name: location.symbol.name.clone(),
+                kind: symbol_kind(location.symbol.kind, location.container.is_some()),
+                tags: None,
+                deprecated: None,
+                location: Location {
+                    uri: Url::from_file_path(location.path).ok()?,
+                    range: span_range(&lines, location.symbol.name_span),
+                },
+                container_name: location.container.map(|class| class.name.clone()),
+            })
+        })
+        .collect()
+}
 
-            // Check for Class declarations
-            if let Some(caps) = class_regex.captures(line_trimmed) {
-                let name = caps.get(1).unwrap().as_str();
-
-                let symbol = DocumentSymbol {
-                    name: name.to_string(),
-                    detail: Some(format!("Class {}", name)),
-                    kind: SymbolKind::CLASS,
-                    range: Range {
-                        start: Position {
-                            line: i as u32,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: i as u32,
-                            character: line.len() as u32,
-                        },
-                    },
-                    selection_range: Range {
-                        start: Position {
-                            line: i as u32,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: i as u32,
-                            character: line.len() as u32,
-                        },
-                    },
-                    children: Some(Vec::new()),
-                    tags: None,
-                    deprecated: None,
-                };
+/// Returns the formatter options of the `[format]` table of a configuration,
+/// with the indentation settings of the editor
+fn format_options(config: &Config, options: &FormattingOptions) -> formatter::FormatOptions {
+    formatter::FormatOptions {
+        indent_style: if options.insert_spaces {
+            formatter::IndentStyle::Spaces
+        } else {
+            formatter::IndentStyle::Tabs
+        },
+        indent_width: options.tab_size as usize,
+        ..config.format_options()
+    }
+}
 
-                function_stack.push((symbol, i));
-            }
+/// Returns the edit turning a document into its formatted text, replacing the
+/// lines that differ, or `None` if the text is unchanged
+fn formatting_edit(original: &str, formatted: &str) -> Option<TextEdit> {
+    if original == formatted {
+        return None;
+    }
+    /// Returns the length of the lines two sequences start with
+    fn common_lines<'a>(
+        old: impl Iterator<Item = &'a str>,
+        new: impl Iterator<Item = &'a str>,
+    ) -> usize {
+        old.zip(new)
+            .take_while(|(a, b)| a == b)
+            .map(|(line, _)| line.len())
+            .sum()
+    }
+    let prefix = common_lines(
+        original.split_inclusive('\n'),
+        formatted.split_inclusive('\n'),
+    );
+    // Lines kept at the end, which must not overlap the ones kept at the start
+    let suffix = common_lines(
+        original[prefix..].split_inclusive('\n').rev(),
+        formatted[prefix..].split_inclusive('\n').rev(),
+    );
+    let lines = parser::ast::LineIndex::new(original);
+    Some(TextEdit {
+        range: span_range(&lines, lines.span(prefix, original.len() - suffix)),
+        new_text: formatted[prefix..formatted.len() - suffix].to_string(),
+    })
+}
 
-            // Check for End Class
-            if end_class_regex.captures(line_trimmed).is_some() {
-                if let Some((mut symbol, _)) = function_stack.pop() {
-                    // Update the end range
-                    symbol.range.end = Position {
-                        line: i as u32,
-                        character: line.len() as u32,
-                    };
-
-                    // Add to the parent or directly to the symbols list
-                    if let Some((parent, _)) = function_stack.last_mut() {
-                        if let Some(children) = &mut parent.children {
-                            children.push(symbol);
-                        }
-                    } else {
-                        symbols.push(symbol);
-                    }
-                }
-            }
+/// Converts a name of an indexed file into an LSP location
+fn name_location(
+    index: &analysis::WorkspaceIndex,
+    name: analysis::NameLocation<'_>,
+) -> Option<Location> {
+    let file = index.file(name.path)?;
+    Some(Location {
+        uri: Url::from_file_path(name.path).ok()?,
+        range: span_range(&parser::ast::LineIndex::new(&file.source), name.span),
+    })
+}
 
-            // Check for variable declarations (Dim statements)
-            if let Some(caps) = dim_regex.captures(line_trimmed) {
-                let vars = caps.get(1).unwrap().as_str();
-
-                // Split variables (they may be comma-separated)
-                for var in vars.split(',') {
-                    let var_name = var.trim();
-                    if !var_name.is_empty() {
-                        let var_symbol = DocumentSymbol {
-                            name: var_name.to_string(),
-                            detail: Some(format!("Variable {}", var_name)),
-                            kind: SymbolKind::VARIABLE,
-                            range: Range {
-                                start: Position {
-                                    line: i as u32,
-                                    character: 0,
-                                },
-                                end: Position {
-                                    line: i as u32,
-                                    character: line.len() as u32,
-                                },
-                            },
-                            selection_range: Range {
-                                start: Position {
-                                    line: i as u32,
-                                    character: 0,
-                                },
-                                end: Position {
-                                    line: i as u32,
-                                    character: line.len() as u32,
-                                },
-                            },
-                            children: None,
-                            tags: None,
-                            deprecated: None,
-                        };
-
-                        // Add to the current function/class or directly to the symbols list
-                        if let Some((parent, _)) = function_stack.last_mut() {
-                            if let Some(children) = &mut parent.children {
-                                children.push(var_symbol);
-                            }
-                        } else {
-                            symbols.push(var_symbol);
-                        }
-                    }
-                }
-            }
+/// Converts an LSP position, whose character counts UTF-16 code units, into a
+/// byte offset
+///
+/// Positions past the end of a line give the end of the line, and positions
+/// past the last line give the end of the text.
+fn position_offset(lines: &parser::ast::LineIndex, position: Position) -> usize {
+    let line_start = lines.offset(position.line as usize + 1, 1);
+    let rest = &lines.source()[line_start..];
+    let line = rest.split('\n').next().unwrap_or_default();
+    line_start + line_offset(line.strip_suffix('\r').unwrap_or(line), position.character)
+}
+
+/// Converts a character of an LSP position, counted in UTF-16 code units, into
+/// a byte offset in a line
+fn line_offset(line: &str, character: u32) -> usize {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character as usize {
+            return offset;
         }
+        units += c.len_utf16();
+    }
+    line.len()
+}
 
-        symbols
+/// Converts a byte offset into an LSP position, whose character counts UTF-16
+/// code units
+fn offset_position(lines: &parser::ast::LineIndex, offset: usize) -> Position {
+    let (line, _) = lines.line_column(offset);
+    let line_start = lines.offset(line, 1);
+    Position {
+        line: (line - 1) as u32,
+        character: lines.source()[line_start..offset].encode_utf16().count() as u32,
     }
+}
 
-    /// Check if a line is within ASP tags
-    fn is_line_in_asp_tag(&self, content: &str, line_index: usize) -> bool {
-        let lines: Vec<&str> = content.lines().collect();
+/// Converts a span into an LSP range
+fn span_range(lines: &parser::ast::LineIndex, span: parser::ast::Span) -> Range {
+    Range {
+        start: offset_position(lines, span.start),
+        end: offset_position(lines, span.end),
+    }
+}
 
-        // Search for an open ASP tag before or on this line
-        let mut nearest_open = None;
-        let mut nearest_close = None;
+/// Returns the index of the character of a document at an LSP position
+///
+/// Positions past the end of a line give the end of the line, before its line
+/// break, and positions past the last line give the end of the document.
+fn position_char(document: &Rope, position: Position) -> usize {
+    let line = position.line as usize;
+    if line >= document.len_lines() {
+        return document.len_chars();
+    }
+    let start = document.line_to_char(line);
+    let text = document.line(line);
+    let mut length = text.len_chars();
+    while length > 0 && matches!(text.char(length - 1), '\n' | '\r') {
+        length -= 1;
+    }
+    let units = document.char_to_utf16_cu(start) + position.character as usize;
+    document
+        .utf16_cu_to_char(units.min(document.len_utf16_cu()))
+        .min(start + length)
+}
 
-        for (i, line) in lines.iter().enumerate().take(line_index + 1) {
-            if line.contains("<%") {
-                nearest_open = Some(i);
-            }
-            if line.contains("%>") {
-                nearest_close = Some(i);
-            }
-        }
+/// Returns the text of a line of a document, without its line break, or `None`
+/// past the last line
+fn line_text(document: &Rope, line: usize) -> Option<String> {
+    if line >= document.len_lines() {
+        return None;
+    }
+    let text = document.line(line).to_string();
+    Some(text.trim_end_matches(['\n', '\r']).to_string())
+}
 
-        // If we found an open tag, check if there's a close tag between it and the current line
-        if let Some(open_idx) = nearest_open {
-            if let Some(close_idx) = nearest_close {
-                // If close tag comes after open tag, check if our line is in between
-                if close_idx > open_idx {
-                    return line_index >= open_idx && line_index <= close_idx;
-                }
-            }
-            // If no close tag found or it's before the open tag, the line is inside ASP tags
-            return line_index >= open_idx;
+/// Returns the word of a document at an LSP position: the letters, digits and
+/// underscores around it
+///
+/// Only the line of the position is read from the document.
+pub fn word_at_position(document: &Rope, position: Position) -> Option<String> {
+    let line = line_text(document, position.line as usize)?;
+    let character = line_offset(&line, position.character);
+    if character >= line.len() {
+        return None;
+    }
+
+    // Find the word boundaries
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let start = line[..character]
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !is_word(c))
+        .map_or(0, |(index, c)| index + c.len_utf8());
+    let end = line[character..]
+        .find(|c: char| !is_word(c))
+        .map_or(line.len(), |index| character + index);
+
+    (start < end).then(|| line[start..end].to_string())
+}
+
+/// Returns whether an LSP position of a document is in ASP code, the last
+/// delimiter before it being `<%` rather than `%>`
+///
+/// The document is read backwards from the position, up to that delimiter.
+pub fn position_in_asp_code(document: &Rope, position: Position) -> bool {
+    let mut next = None;
+    for c in document
+        .chars_at(position_char(document, position))
+        .reversed()
+    {
+        match (c, next) {
+            ('<', Some('%')) => return true,
+            ('%', Some('>')) => return false,
+            _ => next = Some(c),
         }
+    }
+    false
+}
+
+/// Returns the extensions of the files the server parses, indexes and watches
+/// with a configuration: its `extensions` option, or [`SOURCE_EXTENSIONS`]
+pub fn source_extensions(config: &Config) -> Vec<String> {
+    match config.extensions {
+        Some(_) => config.scan_extensions(),
+        None => SOURCE_EXTENSIONS.map(String::from).to_vec(),
+    }
+}
+
+/// Returns whether the server parses, indexes and watches a file, given its
+/// extension and the extensions returned by [`source_extensions`]
+pub fn is_source_file(path: &Path, extensions: &[String]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extensions
+                .iter()
+                .any(|source| extension.eq_ignore_ascii_case(source))
+        })
+}
+
+/// Returns the resolver of the include directives of a workspace folder, with
+/// the `include_root` and `[virtual_paths]` of its configuration, and the folder
+/// as web root when `include_root` is not set
+fn folder_resolver(root: &Path, config: &Config) -> IncludeResolver {
+    let root = root.to_string_lossy().to_string();
+    config.include_resolver(config.include_root.is_none().then_some(&root))
+}
 
-        false
+/// Returns the configuration applying to a document, merged from the
+/// configuration files of its directory and its parents
+fn document_config(path: &Path) -> Config {
+    directory_config(path).for_path(path)
+}
+
+/// Returns the configuration of a directory, merged from its configuration
+/// files and those of its parents, without the overrides of its files
+fn directory_config(path: &Path) -> Config {
+    // From the most general to the most specific, as on the command line
+    Config::find_configs(path)
+        .into_iter()
+        .fold(Config::default(), |config, (_, cfg)| cfg.merge(&config))
+}
+
+/// Converts a parse error or lint problem of a document into an LSP diagnostic
+///
+/// Related locations in other files are placed with the sources of the index,
+/// and dropped for files it does not have.
+fn lint_diagnostic(
+    uri: &Url,
+    lines: &parser::ast::LineIndex,
+    index: &analysis::WorkspaceIndex,
+    diagnostic: parser::Diagnostic,
+) -> Diagnostic {
+    let severity = match diagnostic.severity {
+        parser::Severity::Error => DiagnosticSeverity::ERROR,
+        parser::Severity::Warning => DiagnosticSeverity::WARNING,
+        parser::Severity::Notice => DiagnosticSeverity::INFORMATION,
+    };
+    let related: Vec<DiagnosticRelatedInformation> = diagnostic
+        .related
+        .into_iter()
+        .filter_map(|related| {
+            let location = match &related.path {
+                None => Location::new(uri.clone(), span_range(lines, related.span)),
+                Some(path) => {
+                    let file = index.file(path)?;
+                    Location::new(
+                        Url::from_file_path(&file.path).ok()?,
+                        span_range(&parser::ast::LineIndex::new(&file.source), related.span),
+                    )
+                }
+            };
+            Some(DiagnosticRelatedInformation {
+                location,
+                message: related.message,
+            })
+        })
+        .collect();
+    Diagnostic {
+        range: span_range(lines, diagnostic.span),
+        severity: Some(severity),
+        code: Some(NumberOrString::String(diagnostic.code.as_str().to_string())),
+        source: Some("asp-classic-parser".to_string()),
+        message: diagnostic.message,
+        related_information: (!related.is_empty()).then_some(related),
+        ..Diagnostic::default()
     }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for AspLspServer {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
         log::info!("ASP Classic Language Server initialized");
 
+        if let Some(options) = params.initialization_options {
+            self.update_settings(options).await;
+        }
+
+        // Virtual includes are resolved from the root of each workspace folder,
+        // unless its configuration sets `include_root`
+        let roots: Vec<Url> = match params.workspace_folders {
+            Some(folders) if !folders.is_empty() => {
+                folders.into_iter().map(|folder| folder.uri).collect()
+            }
+            _ => params.root_uri.into_iter().collect(),
+        };
+        {
+            let mut workspaces = self.workspaces.lock().await;
+            for root in roots.iter().filter_map(|uri| uri.to_file_path().ok()) {
+                let config = self.config(&root);
+                workspaces.add(root, &config);
+            }
+        }
+        let progress_supported = params
+            .capabilities
+            .window
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        self.progress_supported
+            .store(progress_supported, Ordering::Relaxed);
+        let watch_supported = params
+            .capabilities
+            .workspace
+            .and_then(|workspace| workspace.did_change_watched_files)
+            .and_then(|watched_files| watched_files.dynamic_registration)
+            .unwrap_or(false);
+        self.watch_supported
+            .store(watch_supported, Ordering::Relaxed);
+        let pull_diagnostics = params
+            .capabilities
+            .text_document
+            .is_some_and(|text_document| text_document.diagnostic.is_some());
+        self.pull_diagnostics
+            .store(pull_diagnostics, Ordering::Relaxed);
+
         // Set up the server capabilities
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
@@ -933,7 +2260,11 @@ impl LanguageServer for AspLspServer {
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions {
                     resolve_provider: Some(false),
-                    trigger_characters: Some(vec![".".to_string(), "<".to_string()]),
+                    trigger_characters: Some(vec![
+                        ".".to_string(),
+                        "<".to_string(),
+                        "/".to_string(),
+                    ]),
                     work_done_progress_options: Default::default(),
                     all_commit_characters: None,
                     completion_item: None,
@@ -942,10 +2273,46 @@ impl LanguageServer for AspLspServer {
                 definition_provider: Some(OneOf::Left(true)),
                 references_provider: Some(OneOf::Left(true)),
                 document_formatting_provider: Some(OneOf::Left(true)),
+                document_range_formatting_provider: Some(OneOf::Left(true)),
+                document_on_type_formatting_provider: Some(DocumentOnTypeFormattingOptions {
+                    first_trigger_character: "\n".to_string(),
+                    more_trigger_character: Some(on_type_triggers()),
+                }),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: Some("asp-classic-parser".to_string()),
+                        // Changes of included files change the diagnostics
+                        inter_file_dependencies: true,
+                        workspace_diagnostics: false,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    },
+                )),
                 document_highlight_provider: Some(OneOf::Left(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
                 workspace_symbol_provider: Some(OneOf::Left(true)),
                 code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
                 rename_provider: Some(OneOf::Left(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            legend: SemanticTokensLegend {
+                                token_types: TOKEN_TYPES.to_vec(),
+                                token_modifiers: TOKEN_MODIFIERS.to_vec(),
+                            },
+                            full: Some(SemanticTokensFullOptions::Bool(true)),
+                            range: None,
+                            work_done_progress_options: Default::default(),
+                        },
+                    ),
+                ),
                 ..ServerCapabilities::default()
             },
             server_info: Some(ServerInfo {
@@ -958,6 +2325,25 @@ impl LanguageServer for AspLspServer {
     async fn initialized(&self, _: InitializedParams) {
         log::info!("ASP Classic Language Server is now fully initialized");
 
+        // Keep the index and diagnostics up to date with the files changed on disk
+        self.watch_files().await;
+
+        // Index the files of the workspace folders in the background
+        let roots: Vec<PathBuf> = self
+            .workspaces
+            .lock()
+            .await
+            .folders
+            .iter()
+            .map(|(root, _)| root.clone())
+            .collect();
+        for root in roots {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.index_folder(root).await;
+            });
+        }
+
         // Start a background task to periodically clean up the diagnostics cache
         let server = self.clone();
         tokio::spawn(async move {
@@ -981,7 +2367,10 @@ impl LanguageServer for AspLspServer {
         log::info!("Document opened: {}", uri);
 
         // Store the document
-        self.documents.insert(uri.clone(), text);
+        self.documents.insert(
+            uri.clone(),
+            OpenDocument::new(&text, params.text_document.version),
+        );
 
         // Validate the document
         self.validate_document(uri).await;
@@ -989,39 +2378,33 @@ impl LanguageServer for AspLspServer {
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
         let uri = params.text_document.uri;
+        let version = params.text_document.version;
 
         // Get the current document
         if let Some(mut document) = self.documents.get_mut(&uri) {
-            // Apply the changes to the document
+            // Apply the changes to the document, in order
             for change in params.content_changes {
-                if let Some(range) = change.range {
-                    // Convert the range to string indices
-                    let start_offset = self.position_to_offset(&document, range.start);
-                    let end_offset = self.position_to_offset(&document, range.end);
-
-                    if let (Some(start), Some(end)) = (start_offset, end_offset) {
-                        // Replace the text in the range
-                        let mut new_text = document[..start].to_string();
-                        new_text.push_str(&change.text);
-                        new_text.push_str(&document[end..]);
-                        *document = new_text;
-                    }
-                } else {
-                    // Full document update
-                    *document = change.text;
-                }
+                apply_change(&mut document.text, change);
             }
+            document.version = version;
         } else {
             log::warn!("Document not found in memory: {}", uri);
             return;
         }
 
-        // Validate the document with a small delay to avoid excessive parsing during typing
+        // Validate the document with a small delay to avoid excessive parsing
+        // during typing, once the changes stop
         let server_uri = uri.clone();
         let server = self.clone();
         tokio::spawn(async move {
             tokio::time::sleep(std::time::Duration::from_millis(300)).await;
-            server.validate_document(server_uri).await;
+            if server
+                .documents
+                .get(&server_uri)
+                .is_some_and(|document| document.version == version)
+            {
+                server.validate_document(server_uri).await;
+            }
         });
     }
 
@@ -1031,14 +2414,115 @@ impl LanguageServer for AspLspServer {
         log::info!("Document saved: {}", uri);
 
         // If text is provided, update the document
-        if let Some(text) = params.text {
-            self.documents.insert(uri.clone(), text);
+        if let Some(text) = params.text
+            && let Some(mut document) = self.documents.get_mut(&uri)
+            && document.text != text.as_str()
+        {
+            document.text = Rope::from_str(&text);
+            document.parsed = None;
         }
 
         // Validate the document immediately on save
         self.validate_document(uri).await;
     }
 
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let plugins_enabled = self.settings.lock().await.enable_plugins;
+        self.update_settings(params.settings).await;
+        if self.settings.lock().await.enable_plugins != plugins_enabled {
+            self.revalidate_dependents(&[], true).await;
+        }
+        if let Err(err) = self.client.inlay_hint_refresh().await {
+            log::warn!("Failed to refresh the inlay hints: {}", err);
+        }
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let mut added = Vec::new();
+        {
+            let mut workspaces = self.workspaces.lock().await;
+            for folder in params.event.removed {
+                if let Ok(root) = folder.uri.to_file_path() {
+                    log::info!("Workspace folder removed: {}", root.display());
+                    workspaces.remove(&root);
+                }
+            }
+            for folder in params.event.added {
+                if let Ok(root) = folder.uri.to_file_path() {
+                    log::info!("Workspace folder added: {}", root.display());
+                    workspaces.add(root.clone(), &self.config(&root));
+                    added.push(root);
+                }
+            }
+        }
+        for root in added {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.index_folder(root).await;
+            });
+        }
+        // The folders may configure other extensions
+        self.watch_files().await;
+
+        // Open documents may belong to another folder now
+        self.revalidate_dependents(&[], true).await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let mut changed = Vec::new();
+        let mut config_changed = false;
+        for event in params.changes {
+            let Some(path) = self.uri_to_path(&event.uri) else {
+                continue;
+            };
+            let file_name = path.file_name().and_then(|name| name.to_str());
+            if matches!(file_name, Some(".asp-parser.toml" | "asp-parser.toml")) {
+                config_changed = true;
+                continue;
+            }
+            log::info!("File changed on disk: {}", event.uri);
+            // Open documents are indexed from their content in the editor
+            if !self.documents.contains_key(&event.uri) {
+                let mut workspaces = self.workspaces.lock().await;
+                let index = workspaces.index_mut(&path);
+                if event.typ == FileChangeType::DELETED {
+                    index.remove(&path);
+                } else if let Ok(source) = file_utils::read_file_with_encoding(&path) {
+                    index.update(&path, &source);
+                    index.load_includes(&path);
+                }
+            }
+            changed.push(path);
+        }
+
+        // The extensions of the source files and the paths of the includes may
+        // have changed too
+        if config_changed {
+            self.configs.clear();
+            self.watch_files().await;
+            let roots: Vec<PathBuf> = {
+                let mut workspaces = self.workspaces.lock().await;
+                for (root, index) in &mut workspaces.folders {
+                    index.set_resolver(folder_resolver(root, &self.config(root)));
+                }
+                workspaces
+                    .folders
+                    .iter()
+                    .map(|(root, _)| root.clone())
+                    .collect()
+            };
+            for root in roots {
+                let server = self.clone();
+                tokio::spawn(async move {
+                    server.index_folder(root).await;
+                });
+            }
+        }
+
+        // Rule settings may have changed for every document
+        self.revalidate_dependents(&changed, config_changed).await;
+    }
+
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         let uri = params.text_document.uri;
 
@@ -1055,10 +2539,21 @@ impl LanguageServer for AspLspServer {
         let uri = params.text_document_position_params.text_document.uri;
         let position = params.text_document_position_params.position;
 
+        // Procedures, classes and variables of the pages
+        if let Some(value) = self.symbol_hover(&uri, position).await {
+            return Ok(Some(Hover {
+                contents: HoverContents::Markup(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value,
+                }),
+                range: None,
+            }));
+        }
+
         // Get document content
-        if let Some(content) = self.get_document_content(&uri).await {
+        if let Some(document) = self.get_document(&uri).await {
             // Find the word at the position
-            if let Some(word) = self.get_word_at_position(&content, position) {
+            if let Some(word) = word_at_position(&document, position) {
                 // Provide hover information based on the word
                 if let Some(hover_content) = self.get_hover_content(&word) {
                     return Ok(Some(Hover {
@@ -1080,11 +2575,32 @@ impl LanguageServer for AspLspServer {
         let position = params.text_document_position.position;
 
         // Get document content
-        if let Some(content) = self.get_document_content(&uri).await {
+        if let Some(document) = self.get_document(&uri).await {
+            // Paths of include directives
+            if let Some(items) = self.include_completions(&uri, &document, position).await {
+                return Ok((!items.is_empty()).then_some(CompletionResponse::Array(items)));
+            }
+            // Slashes only trigger the completion of paths
+            let trigger = params.context.and_then(|context| context.trigger_character);
+            if trigger.as_deref() == Some("/") {
+                return Ok(None);
+            }
+
             // Check if we're inside ASP tags
-            if self.is_position_in_asp_tag(&content, position) {
+            if position_in_asp_code(&document, position) {
+                // Members of the objects of known types
+                let parsed = self.get_parsed_document(&uri).await;
+                if let Some(items) = parsed
+                    .as_ref()
+                    .and_then(|parsed| parsed.member_completions(position))
+                {
+                    return Ok(Some(CompletionResponse::Array(items)));
+                }
                 // Generate completions based on context
-                let items = self.generate_completions(&content, position);
+                let mut items = self.generate_completions(&document, position);
+                if let Some(parsed) = parsed {
+                    items.extend(self.symbol_completions(&uri, &parsed, position).await);
+                }
                 if !items.is_empty() {
                     return Ok(Some(CompletionResponse::Array(items)));
                 }
@@ -1100,10 +2616,10 @@ impl LanguageServer for AspLspServer {
     ) -> Result<Option<DocumentSymbolResponse>> {
         let uri = params.text_document.uri;
 
-        // Get document content
-        if let Some(content) = self.get_document_content(&uri).await {
-            // Parse the document to extract symbols
-            let symbols = self.extract_document_symbols(&content);
+        // Get the parsed document
+        if let Some(parsed) = self.get_parsed_document(&uri).await {
+            // Symbols of the syntax tree
+            let symbols = parsed.document_symbols();
             if !symbols.is_empty() {
                 return Ok(Some(DocumentSymbolResponse::Nested(symbols)));
             }
@@ -1111,6 +2627,291 @@ impl LanguageServer for AspLspServer {
 
         Ok(None)
     }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> Result<Option<GotoDefinitionResponse>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let Some(path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+
+        // The index may not have the last changes of the document yet
+        self.index_document(&uri).await;
+        let workspaces = self.workspaces.lock().await;
+        let index = workspaces.index(&path);
+        let Some(file) = index.file(&path) else {
+            return Ok(None);
+        };
+        let offset = position_offset(&parser::ast::LineIndex::new(&file.source), position);
+        let location = index
+            .definition_at(&path, offset)
+            .and_then(|definition| name_location(index, definition));
+        Ok(location.map(GotoDefinitionResponse::Scalar))
+    }
+
+    async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let Some(path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+
+        self.index_document(&uri).await;
+        let workspaces = self.workspaces.lock().await;
+        let index = workspaces.index(&path);
+        let Some(file) = index.file(&path) else {
+            return Ok(None);
+        };
+        let offset = position_offset(&parser::ast::LineIndex::new(&file.source), position);
+        let locations: Vec<Location> = index
+            .references(&path, offset, params.context.include_declaration)
+            .into_iter()
+            .filter_map(|reference| name_location(index, reference))
+            .collect();
+        Ok((!locations.is_empty()).then_some(locations))
+    }
+
+    async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        let uri = params.text_document_position.text_document.uri;
+        let position = params.text_document_position.position;
+        let new_name = params.new_name;
+        let is_identifier = new_name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && new_name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier || parser::lexer::vbscript_keyword(&new_name).is_some() {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                "'{}' is not a valid VBScript name",
+                new_name
+            )));
+        }
+        let Some(path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+
+        self.index_document(&uri).await;
+        let workspaces = self.workspaces.lock().await;
+        let index = workspaces.index(&path);
+        let Some(file) = index.file(&path) else {
+            return Ok(None);
+        };
+        let offset = position_offset(&parser::ast::LineIndex::new(&file.source), position);
+        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        for reference in index.references(&path, offset, true) {
+            let Some(location) = name_location(index, reference) else {
+                continue;
+            };
+            // Names in strings (`GetRef("Name")`) and comments are never rewritten
+            let source = index.file(reference.path).map_or("", |file| &file.source);
+            if parser::tokenize(source).iter().any(|token| {
+                matches!(token.kind, TokenKind::String | TokenKind::Comment)
+                    && token.span.start <= reference.span.start
+                    && reference.span.end <= token.span.end
+            }) {
+                return Err(tower_lsp::jsonrpc::Error::invalid_params(format!(
+                    "'{}' is used in a string at {}:{}; rename it by hand",
+                    reference.span.as_str(source),
+                    reference.path.display(),
+                    reference.span.line
+                )));
+            }
+            changes.entry(location.uri).or_default().push(TextEdit {
+                range: location.range,
+                new_text: new_name.clone(),
+            });
+        }
+        Ok((!changes.is_empty()).then(|| WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        }))
+    }
+
+    async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let Some(content) = self.get_document_content(&uri).await else {
+            return Ok(None);
+        };
+        let options = self.document_format_options(&uri, &params.options);
+        let formatted = formatter::format_with_options(&content, &options);
+        Ok(formatting_edit(&content, &formatted).map(|edit| vec![edit]))
+    }
+
+    async fn range_formatting(
+        &self,
+        params: DocumentRangeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document.uri;
+        let Some(content) = self.get_document_content(&uri).await else {
+            return Ok(None);
+        };
+
+        // Every line touched by the selection is formatted
+        let lines = parser::ast::LineIndex::new(&content);
+        let span = lines.span(
+            position_offset(&lines, params.range.start),
+            position_offset(&lines, params.range.end),
+        );
+        let options = self.document_format_options(&uri, &params.options);
+        let formatted = formatter::format_range_with_options(&content, span, &options);
+        Ok(formatting_edit(&content, &formatted).map(|edit| vec![edit]))
+    }
+
+    async fn on_type_formatting(
+        &self,
+        params: DocumentOnTypeFormattingParams,
+    ) -> Result<Option<Vec<TextEdit>>> {
+        let uri = params.text_document_position.text_document.uri;
+        let Some(content) = self.get_document_content(&uri).await else {
+            return Ok(None);
+        };
+        let options = self.document_format_options(&uri, &params.options);
+        let edits = on_type_formatting(
+            &content,
+            params.text_document_position.position,
+            &params.ch,
+            &options,
+        );
+        Ok((!edits.is_empty()).then_some(edits))
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let kind = CodeActionKind::new("source.normalizeCasing");
+        if let Some(only) = &params.context.only
+            && !only
+                .iter()
+                .any(|requested| kind.as_str().starts_with(requested.as_str()))
+        {
+            return Ok(None);
+        }
+        let uri = params.text_document.uri;
+        let Some(content) = self.get_document_content(&uri).await else {
+            return Ok(None);
+        };
+
+        // Offered only when some keyword or built-in name is not in its usual casing
+        let Some(edit) = formatting_edit(&content, &formatter::normalize_casing(&content)) else {
+            return Ok(None);
+        };
+        let action = CodeAction {
+            title: "Normalize keyword and built-in casing".to_string(),
+            kind: Some(kind),
+            edit: Some(WorkspaceEdit {
+                changes: Some(HashMap::from([(uri, vec![edit])])),
+                ..WorkspaceEdit::default()
+            }),
+            ..CodeAction::default()
+        };
+        Ok(Some(vec![CodeActionOrCommand::CodeAction(action)]))
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri;
+        let Some(parsed) = self.get_parsed_document(&uri).await else {
+            return Ok(None);
+        };
+        Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: parsed.semantic_tokens(),
+        })))
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> Result<DocumentDiagnosticReportResult> {
+        let uri = params.text_document.uri;
+        self.index_document(&uri).await;
+        let items = self.parse_document(&uri).await;
+        Ok(DocumentDiagnosticReportResult::Report(
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id: None,
+                    items,
+                },
+            }),
+        ))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let Some(parsed) = self.get_parsed_document(&uri).await else {
+            return Ok(None);
+        };
+        let settings = self.settings.lock().await.inlay_hints;
+        let hints = match self.uri_to_path(&uri) {
+            Some(path) => {
+                // Procedures of the included files and of the including pages
+                self.index_document(&uri).await;
+                let workspaces = self.workspaces.lock().await;
+                let canonical = includes::canonical_path(&path);
+                let others: Vec<&parser::ast::Program> = workspaces
+                    .index(&path)
+                    .visible_files(&path)
+                    .into_iter()
+                    .filter(|file| file.path != canonical)
+                    .map(|file| &file.program)
+                    .collect();
+                parsed.inlay_hints(&others, settings)
+            }
+            None => parsed.inlay_hints(&[], settings),
+        };
+        let range = params.range;
+        Ok(Some(
+            hints
+                .into_iter()
+                .filter(|hint| range.start <= hint.position && hint.position <= range.end)
+                .collect(),
+        ))
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+        let Some(parsed) = self.get_parsed_document(&uri).await else {
+            return Ok(None);
+        };
+        let highlights = parsed.document_highlights(position);
+        Ok((!highlights.is_empty()).then_some(highlights))
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let uri = params.text_document.uri;
+        let Some(parsed) = self.get_parsed_document(&uri).await else {
+            return Ok(None);
+        };
+        Ok(Some(
+            params
+                .positions
+                .into_iter()
+                .map(|position| parsed.selection_range(position))
+                .collect(),
+        ))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<Vec<SymbolInformation>>> {
+        let workspaces = self.workspaces.lock().await;
+        let symbols: Vec<SymbolInformation> = workspaces
+            .indexes()
+            .flat_map(|index| workspace_symbols(index, &params.query))
+            .collect();
+        Ok((!symbols.is_empty()).then_some(symbols))
+    }
 }
 
 impl Clone for AspLspServer {
@@ -1119,6 +2920,13 @@ impl Clone for AspLspServer {
             client: self.client.clone(),
             documents: self.documents.clone(),
             diagnostics_cache: self.diagnostics_cache.clone(),
+            workspaces: self.workspaces.clone(),
+            progress_supported: self.progress_supported.clone(),
+            watch_supported: self.watch_supported.clone(),
+            watching: self.watching.clone(),
+            pull_diagnostics: self.pull_diagnostics.clone(),
+            settings: self.settings.clone(),
+            configs: self.configs.clone(),
         }
     }
 }
@@ -1,24 +1,65 @@
 use clap::{Arg, ArgAction, Command};
+use clap_complete::Shell;
 use rayon::prelude::*;
 use std::collections::HashMap;
-use std::io::{self, BufRead, Read};
-use std::path::PathBuf;
+use std::collections::hash_map::Entry;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, LineWriter, Read, Write};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-
-mod cache;
-mod config;
-mod file_utils;
-mod output_format;
-mod parser;
-mod updater;
-
-use cache::Cache;
+use std::time::Instant;
+
+use asp_classic_parser::analysis::{self, DuplicateFinder, WorkspaceIndex};
+use asp_classic_parser::includes::{IncludeError, IncludeGraph};
+use asp_classic_parser::lint;
+use asp_classic_parser::lint::baseline::Baseline;
+use asp_classic_parser::lint::explain::explain;
+use asp_classic_parser::{
+    cache, config, file_utils, formatter, metrics, output_format, parser, remote_cache, updater,
+};
+use cache::{Cache, SharedCache};
 use config::Config;
+use file_utils::{DefaultEncoding, Encoding, ReadOptions, ScanOptions};
 use output_format::{
-    OutputConfig, OutputFormat, format_error, format_success, format_summary, map_severity,
+    CodeQualityReport, JsonReport, OutputConfig, OutputFormat, format_ast, format_diagnostic,
+    format_error, format_file_result, format_success, format_summary, map_severity,
 };
+use remote_cache::RemoteCache;
+
+/// Exit code when a file has syntax errors
+const EXIT_SYNTAX_ERROR: i32 = 1;
+/// Exit code when the configuration, a baseline or an argument is invalid
+const EXIT_INVALID_CONFIG: i32 = 2;
+/// Exit code when a lint rule reports an error
+const EXIT_LINT_ERROR: i32 = 3;
+/// Exit code when a file cannot be read or written
+const EXIT_IO_ERROR: i32 = 4;
+/// Exit code when files are skipped or warnings reported, with `--error-on`,
+/// or when there are more warnings than `--max-warnings` allows
+const EXIT_WARNINGS: i32 = 5;
+
+/// Size from which the pages are checked piece by piece with
+/// [`parser::parse_stream`], without building their syntax tree, in bytes
+const STREAM_PARSE_SIZE: usize = 1024 * 1024;
+
+/// Returns the exit code of a run: the lowest code whose failure happened, or 0
+///
+/// # Arguments
+///
+/// * `failures` - Exit codes, and whether their failure happened
+/// * `exit_zero` - Whether `--exit-zero` was given, to always return 0
+fn exit_code(failures: &[(i32, bool)], exit_zero: bool) -> i32 {
+    failures
+        .iter()
+        .filter(|(_, failed)| *failed && !exit_zero)
+        .map(|(code, _)| *code)
+        .min()
+        .unwrap_or(0)
+}
 
 /// Represents the result of parsing a file
 enum ParseResult {
@@ -28,53 +69,322 @@ enum ParseResult {
     Skipped,
     /// The file had an error during parsing
     Error,
+    /// The file could not be read
+    Unreadable,
+}
+
+/// Report a problem of a file without diagnostic code: printed on the standard
+/// error (the standard output in the `jsonl` format), or added to the JSON report
+fn report_error(
+    output_config: &OutputConfig,
+    path: &str,
+    line: usize,
+    column: usize,
+    message: &str,
+    severity: &str,
+) {
+    if severity == "warning" {
+        output_config.warnings.fetch_add(1, Ordering::Relaxed);
+    }
+    match &output_config.report {
+        Some(report) => report
+            .lock()
+            .unwrap()
+            .add_error(path, line, column, message, severity),
+        None => print_problem(
+            output_config,
+            &format_error(output_config, path, line, column, message, severity),
+        ),
+    }
+}
+
+/// Report a diagnostic of a file: printed like [`report_error`], or added to the
+/// JSON report
+fn report_diagnostic(output_config: &OutputConfig, path: &str, diagnostic: &parser::Diagnostic) {
+    if diagnostic.severity == parser::Severity::Warning {
+        output_config.warnings.fetch_add(1, Ordering::Relaxed);
+    }
+    match &output_config.report {
+        Some(report) => report.lock().unwrap().add_diagnostic(path, diagnostic),
+        None => print_problem(
+            output_config,
+            &format_diagnostic(output_config, path, diagnostic),
+        ),
+    }
+}
+
+/// Print a formatted problem in the error output file, or the output file, if
+/// any; otherwise on the standard error, or on the standard output with the
+/// results of the files in the `jsonl` format
+fn print_problem(output_config: &OutputConfig, line: &str) {
+    match (&output_config.error_output, &output_config.output) {
+        (Some(file), _) | (None, Some(file)) => write_line(file, line),
+        (None, None) if output_config.format == OutputFormat::Jsonl => print_stdout(line),
+        (None, None) => eprintln!("{}", line),
+    }
+}
+
+/// Print a line on the standard output, exiting quietly once the reader closed
+/// it, such as `head` after its first lines
+fn print_stdout(line: &str) {
+    write_stdout(format!("{}\n", line).as_bytes());
+}
+
+/// Write bytes on the standard output, exiting quietly once the reader closed it
+fn write_stdout(bytes: &[u8]) {
+    let mut stdout = io::stdout().lock();
+    match stdout.write_all(bytes).and_then(|()| stdout.flush()) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => process::exit(0),
+        Err(e) => panic!("failed printing to stdout: {}", e),
+    }
+}
+
+/// Print a formatted result (success message, file result or summary) in the
+/// output file, if any, or on the standard output
+fn print_result(output_config: &OutputConfig, line: &str) {
+    match &output_config.output {
+        Some(file) => write_line(file, line),
+        None => print_stdout(line),
+    }
+}
+
+/// Write a line in an output file, or on the standard error if it fails
+fn write_line(file: &Mutex<LineWriter<File>>, line: &str) {
+    if writeln!(file.lock().unwrap(), "{}", line).is_err() {
+        eprintln!("{}", line);
+    }
+}
+
+/// Print the success message of a file, if configured to do so
+///
+/// In the `jsonl` format, the result of every file is printed by
+/// [`record_result`] instead.
+fn report_success(output_config: &OutputConfig, path: &Path) {
+    if output_config.show_success && output_config.format != OutputFormat::Jsonl {
+        print_result(output_config, &format_success(output_config, path));
+    }
+}
+
+/// Record the result and parsing time of a file in the JSON report, if any, or
+/// print it in the `jsonl` format
+fn record_result(
+    output_config: &OutputConfig,
+    path: &str,
+    result: &ParseResult,
+    duration: std::time::Duration,
+) {
+    let status = match result {
+        ParseResult::Success => "success",
+        ParseResult::Skipped => "skipped",
+        ParseResult::Error | ParseResult::Unreadable => "failed",
+    };
+    if let Some(report) = &output_config.report {
+        report.lock().unwrap().finish_file(path, status, duration);
+    } else if output_config.format == OutputFormat::Jsonl
+        && (output_config.show_success || !matches!(result, ParseResult::Success))
+    {
+        print_result(output_config, &format_file_result(path, status, duration));
+    }
 }
 
-/// Extract line and column information from a parsing error message
+/// Every syntax error of a file that failed to parse
+///
+/// The recovering parser reports all the errors of the file so that they can be
+/// fixed in a single run. The error returned by the parser is reported if it finds
+/// none.
+///
+/// # Arguments
 ///
-/// This function parses error messages to extract line and column numbers,
-/// providing a more robust way to handle error location information.
+/// * `content` - The content of the file
+/// * `error` - The error returned by the parser
+fn syntax_errors(content: &str, error: &(dyn Error + 'static)) -> Vec<parser::Diagnostic> {
+    let diagnostics = parser::parse_recovering(content).diagnostics;
+    if !diagnostics.is_empty() {
+        return diagnostics;
+    }
+    let diagnostic = match error.downcast_ref::<parser::AspParseError>() {
+        Some(asp_error) => asp_error.diagnostic(),
+        None => parser::Diagnostic::new(
+            parser::DiagnosticCode::SyntaxError,
+            parser::ast::Span::new(0, 0, 1, 1),
+            error.to_string(),
+        ),
+    };
+    vec![diagnostic]
+}
+
+/// Print every syntax error of a file that failed to parse, as found by
+/// [`syntax_errors`]
 ///
 /// # Arguments
 ///
-/// * `error_message` - The error message string to parse
+/// * `output_config` - The output configuration
+/// * `path` - The path of the file, as displayed
+/// * `content` - The content of the file
+/// * `error` - The error returned by the parser
+fn print_syntax_errors(
+    output_config: &OutputConfig,
+    path: &str,
+    content: &str,
+    error: &(dyn Error + 'static),
+) {
+    for diagnostic in syntax_errors(content, error) {
+        report_diagnostic(output_config, path, &diagnostic);
+    }
+}
+
+/// Problems of parsing a file: none if it parses, the `no-asp-tags` or
+/// `empty-file` warning of a file that is skipped, or every syntax error
+///
+/// Pages of [`STREAM_PARSE_SIZE`] or more are checked piece by piece, their
+/// unclosed blocks being reported after the other errors.
+fn parse_diagnostics(content: &str) -> Vec<parser::Diagnostic> {
+    let skipped = |code, message: &str| {
+        vec![parser::Diagnostic::new(
+            code,
+            parser::ast::Span::new(0, 0, 1, 1),
+            message,
+        )]
+    };
+    // Large pages are checked in pieces, on their decoded text
+    if content.len() >= STREAM_PARSE_SIZE {
+        let mut diagnostics = Vec::new();
+        let summary = parser::parse_stream(content.as_bytes(), |diagnostic| {
+            diagnostics.push(diagnostic)
+        })
+        .expect("reading from memory cannot fail");
+        if summary.code_blocks == 0 && diagnostics.is_empty() {
+            return if content.trim().is_empty() {
+                skipped(
+                    parser::DiagnosticCode::EmptyFile,
+                    "File is empty or contains only whitespace",
+                )
+            } else {
+                skipped(
+                    parser::DiagnosticCode::NoAspTags,
+                    "No ASP tags found in file",
+                )
+            };
+        }
+        return diagnostics;
+    }
+    let Err(e) = parser::parse(content) else {
+        return Vec::new();
+    };
+    if let Some(asp_error) = e.downcast_ref::<parser::AspParseError>() {
+        if asp_error.is_no_asp_tags_error() {
+            return skipped(
+                parser::DiagnosticCode::NoAspTags,
+                "No ASP tags found in file",
+            );
+        } else if asp_error.is_empty_file_error() {
+            return skipped(
+                parser::DiagnosticCode::EmptyFile,
+                "File is empty or contains only whitespace",
+            );
+        }
+    }
+    syntax_errors(content, e.as_ref())
+}
+
+/// Report the problems of parsing a file, found by [`parse_diagnostics`] or
+/// stored in the parsing cache
+///
+/// A file without ASP code, or empty, is skipped with a warning, unless it is
+/// ignored, or reported as an error in strict mode; the other problems are
+/// syntax errors. The success of a file without problems is left to the caller.
 ///
 /// # Returns
 ///
-/// A tuple of (line, column) with default values of (1, 1) if extraction fails
-fn extract_line_and_column(error_message: &str) -> (usize, usize) {
-    // Default values if we can't extract information
-    let default_position = (1, 1);
-
-    // Look for the line that contains position information
-    let position_line = error_message
-        .lines()
-        .find(|line| line.contains("--> line:"));
-
-    if let Some(line) = position_line {
-        // Extract line number
-        let line_num = line
-            .split("line:")
-            .nth(1)
-            .and_then(|pos| pos.split(':').next())
-            .and_then(|line_str| line_str.trim().parse::<usize>().ok());
-
-        // Extract column number
-        let col_num = line
-            .split(':')
-            .nth(2)
-            .and_then(|col_str| col_str.trim().parse::<usize>().ok());
-
-        match (line_num, col_num) {
-            (Some(line), Some(col)) => (line, col),
-            (Some(line), None) => (line, default_position.1),
-            _ => default_position,
+/// * `ParseResult` - `Success` without problems, `Skipped` for a skipped file,
+///   and `Error` otherwise
+fn report_parse_diagnostics(
+    output_config: &OutputConfig,
+    path: &Path,
+    diagnostics: &[parser::Diagnostic],
+    verbose: bool,
+    strict_mode: bool,
+    ignored_warnings: &[String],
+) -> ParseResult {
+    let path_str = path.display().to_string();
+    match diagnostics {
+        [] => ParseResult::Success,
+        [diagnostic]
+            if matches!(
+                diagnostic.code,
+                parser::DiagnosticCode::NoAspTags | parser::DiagnosticCode::EmptyFile
+            ) =>
+        {
+            // In strict mode, treat as error
+            if strict_mode {
+                let error = parser::Diagnostic {
+                    severity: parser::Severity::Error,
+                    ..diagnostic.clone()
+                };
+                report_diagnostic(output_config, &path_str, &error);
+                return ParseResult::Error;
+            }
+
+            // Otherwise, handle as a warning - unless ignored, and in verbose mode
+            // only when other warnings are ignored
+            let ignored = ignored_warnings
+                .iter()
+                .any(|warning| warning == diagnostic.code.name());
+            if !ignored && (verbose || ignored_warnings.is_empty()) {
+                let warning = parser::Diagnostic {
+                    message: format!("{} - skipping", diagnostic.message),
+                    ..diagnostic.clone()
+                };
+                report_diagnostic(output_config, &path_str, &warning);
+            }
+            ParseResult::Skipped
+        }
+        _ => {
+            for diagnostic in diagnostics {
+                report_diagnostic(output_config, &path_str, diagnostic);
+            }
+            ParseResult::Error
+        }
+    }
+}
+
+/// Print the result of a file that was parsed successfully
+///
+/// With the `ast-json` format, the syntax tree of the file is printed instead of the
+/// success message.
+///
+/// # Arguments
+///
+/// * `output_config` - The output configuration
+/// * `path` - The path of the file
+/// * `content` - The content of the file
+fn print_success(output_config: &OutputConfig, path: &Path, content: &str) {
+    if output_config.format == OutputFormat::AstJson {
+        if let Ok(program) = parser::parse_to_ast(content) {
+            print_result(
+                output_config,
+                &format_ast(&path.display().to_string(), &program),
+            );
         }
     } else {
-        default_position
+        report_success(output_config, path);
     }
 }
 
+/// Normalize the warnings given to `--ignore-warnings` to their names
+///
+/// Warnings can be given by name (`no-asp-tags`) or by code (`ASP005`); unknown
+/// values are kept as they are.
+fn normalize_ignored_warnings<'a>(warnings: impl Iterator<Item = &'a String>) -> Vec<String> {
+    warnings
+        .map(|warning| match parser::DiagnosticCode::from_name(warning) {
+            Some(code) => code.name().to_string(),
+            None => warning.clone(),
+        })
+        .collect()
+}
+
 /// Parse a single file and report results
 #[allow(clippy::too_many_arguments)]
 fn parse_file(
@@ -86,315 +396,109 @@ fn parse_file(
     cache_enabled: bool,
     cache: &mut Option<Cache>,
     options_hash: &str,
+    read_options: ReadOptions,
 ) -> ParseResult {
     if verbose {
         println!("Parsing file: {}", path.display());
     }
 
     // Check if file is in cache and the cache is valid
-    if cache_enabled && path.exists() {
-        if let Some(cache_obj) = cache {
-            match cache_obj.is_valid(path, options_hash) {
-                Ok(true) => {
-                    // File is in cache and hasn't changed
+    if cache_enabled
+        && path.exists()
+        && let Some(cache_obj) = cache
+    {
+        match cache_obj.is_valid(path, options_hash) {
+            Ok(true) => {
+                // File is in cache and hasn't changed
+                if let Some(diagnostics) = cache_obj.get_diagnostics(path) {
                     if verbose {
                         println!("Using cached result for: {}", path.display());
                     }
 
-                    if let Some(success) = cache_obj.was_successful(path) {
-                        if success {
-                            // Show success message if configured to do so
-                            if output_config.show_success {
-                                println!("{}", format_success(output_config, path));
-                            }
-                            return ParseResult::Success;
-                        } else {
-                            // Always show the warning/error message
-                            let path_str = path.display().to_string();
-
-                            // Check for skipped files (no-asp-tags)
-                            let cache_error_message = cache_obj.get_error_message(path);
-                            let is_parse_error = cache_error_message.is_some();
-
-                            if is_parse_error {
-                                // If we have a real parse error stored in cache, display it
-                                let error_message = cache_error_message.unwrap();
-                                let (line, column) = extract_line_and_column(&error_message);
-
-                                // Get the appropriate severity for this error
-                                let severity = map_severity("parse_error");
-
-                                // Format and print the error according to the selected output format
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        output_config,
-                                        &path_str,
-                                        line,
-                                        column,
-                                        &error_message,
-                                        severity
-                                    )
-                                );
-                                return ParseResult::Error;
-                            } else if !ignored_warnings.contains(&"no-asp-tags".to_string()) {
-                                let warning_msg = "No ASP tags found in file - skipping";
-
-                                if strict_mode {
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            "No ASP tags found in file",
-                                            "error"
-                                        )
-                                    );
-                                    return ParseResult::Error;
-                                } else {
-                                    // Show warning only if in verbose mode or not explicitly ignored
-                                    if verbose || ignored_warnings.is_empty() {
-                                        eprintln!(
-                                            "{}",
-                                            format_error(
-                                                output_config,
-                                                &path_str,
-                                                1,
-                                                1,
-                                                warning_msg,
-                                                "warning"
-                                            )
-                                        );
-                                    }
-                                    return ParseResult::Skipped;
-                                }
-                            }
-
-                            // Re-parse the file if not a skipped file
-                            if verbose {
-                                println!("Cache indicates error - re-parsing file");
-                            }
-                        }
+                    // The problems of the file are reported as when it was parsed
+                    if diagnostics.is_empty() {
+                        report_success(output_config, path);
+                        return ParseResult::Success;
                     }
+                    return report_parse_diagnostics(
+                        output_config,
+                        path,
+                        diagnostics,
+                        verbose,
+                        strict_mode,
+                        ignored_warnings,
+                    );
                 }
-                Ok(false) => {
-                    if verbose {
-                        println!("File or options changed since last run - re-parsing");
-                    }
+            }
+            Ok(false) => {
+                if verbose {
+                    println!("File or options changed since last run - re-parsing");
                 }
-                Err(e) => {
-                    if verbose {
-                        println!("Cache check failed: {} - parsing file directly", e);
-                    }
+            }
+            Err(e) => {
+                if verbose {
+                    println!("Cache check failed: {} - parsing file directly", e);
                 }
             }
         }
     }
 
     // Parse the file
-    match file_utils::read_file_with_encoding(path) {
-        Ok(content) => {
-            match parser::parse(&content, verbose) {
-                Ok(_) => {
-                    // Show success message if configured to do so
-                    if output_config.show_success {
-                        println!("{}", format_success(output_config, path));
-                    }
-
-                    // Update cache
-                    if cache_enabled && path.exists() {
-                        if let Some(cache_obj) = cache {
-                            if let Err(e) = cache_obj.update(path, true, options_hash) {
-                                if verbose {
-                                    println!("Failed to update cache: {}", e);
-                                }
-                            }
-                        }
-                    }
-
-                    ParseResult::Success
-                }
-                Err(e) => {
-                    // Try to downcast to AspParseError to check for special conditions
-                    if let Some(asp_error) = e.downcast_ref::<parser::AspParseError>() {
-                        // Check if this is a "no ASP tags" error
-                        if asp_error.is_no_asp_tags_error() {
-                            let path_str = path.display().to_string();
-
-                            // Update cache with skipped status
-                            if cache_enabled && path.exists() {
-                                if let Some(cache_obj) = cache {
-                                    if let Err(e) = cache_obj.update(path, false, options_hash) {
-                                        if verbose {
-                                            println!("Failed to update cache: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-
-                            // In strict mode, treat as error
-                            if strict_mode {
-                                let error_msg = "No ASP tags found in file";
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        output_config,
-                                        &path_str,
-                                        1,
-                                        1,
-                                        error_msg,
-                                        "error"
-                                    )
-                                );
-                                return ParseResult::Error;
-                            }
-
-                            // Otherwise, handle as a warning - unless ignored
-                            if !ignored_warnings.contains(&"no-asp-tags".to_string()) {
-                                // In verbose mode or if not explicitly ignored, show the warning
-                                if verbose || ignored_warnings.is_empty() {
-                                    let warning_msg = "No ASP tags found in file - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
-                                    );
-                                }
-                            }
-
-                            return ParseResult::Skipped;
-                        }
-                        // Check if this is an "empty file" error
-                        else if asp_error.is_empty_file_error() {
-                            let path_str = path.display().to_string();
-
-                            // Update cache with skipped status
-                            if cache_enabled && path.exists() {
-                                if let Some(cache_obj) = cache {
-                                    if let Err(e) = cache_obj.update(path, false, options_hash) {
-                                        if verbose {
-                                            println!("Failed to update cache: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-
-                            // In strict mode, treat as error
-                            if strict_mode {
-                                let error_msg = "File is empty or contains only whitespace";
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        output_config,
-                                        &path_str,
-                                        1,
-                                        1,
-                                        error_msg,
-                                        "error"
-                                    )
-                                );
-                                return ParseResult::Error;
-                            }
-
-                            // Otherwise, handle as a warning - unless ignored
-                            if !ignored_warnings.contains(&"empty-file".to_string()) {
-                                // In verbose mode or if not explicitly ignored, show the warning
-                                if verbose || ignored_warnings.is_empty() {
-                                    let warning_msg =
-                                        "File is empty or contains only whitespace - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
-                                    );
-                                }
-                            }
-
-                            return ParseResult::Skipped;
-                        }
-                    }
-
-                    // For other errors, handle as a regular error
-                    let error_message = e.to_string();
-                    let (line, column) = extract_line_and_column(&error_message);
-
-                    // Update cache with error status and message
-                    if cache_enabled && path.exists() {
-                        if let Some(cache_obj) = cache {
-                            if let Err(e) = cache_obj.update_with_error(
-                                path,
-                                false,
-                                options_hash,
-                                Some(error_message.clone()),
-                            ) {
-                                if verbose {
-                                    println!("Failed to update cache with error: {}", e);
-                                }
-                            }
-                        }
-                    }
-
-                    // Get the appropriate severity for this error
-                    let severity = map_severity("parse_error");
+    // SAFETY: `--mmap` is documented as requiring the files not to be modified
+    // while they are parsed
+    match unsafe { file_utils::read_source_file(path, read_options) } {
+        Ok((content, encoding)) => {
+            if verbose {
+                println!("Encoding: {}", encoding);
+            }
+            report_assumed_encoding(output_config, path, encoding, ignored_warnings);
+            // Files whose encoding is assumed are not cached, for the notice to be
+            // reported on every run
+            let cache_enabled = cache_enabled && !encoding.is_assumed();
+            let diagnostics = parse_diagnostics(&content);
+
+            // Update cache with the problems of the file
+            if cache_enabled
+                && path.exists()
+                && let Some(cache_obj) = cache
+                && let Err(e) = cache_obj.update_with_diagnostics(
+                    path,
+                    diagnostics.is_empty(),
+                    options_hash,
+                    diagnostics.clone(),
+                )
+                && verbose
+            {
+                println!("Failed to update cache: {}", e);
+            }
 
-                    // Format and print the error according to the selected output format
-                    let path_str = path.display().to_string();
-                    eprintln!(
-                        "{}",
-                        format_error(
-                            output_config,
-                            &path_str,
-                            line,
-                            column,
-                            &error_message,
-                            severity
-                        )
-                    );
-                    ParseResult::Error
-                }
+            if diagnostics.is_empty() {
+                // Show success message (or the syntax tree) if configured to do so
+                print_success(output_config, path, &content);
+                return ParseResult::Success;
             }
+            report_parse_diagnostics(
+                output_config,
+                path,
+                &diagnostics,
+                verbose,
+                strict_mode,
+                ignored_warnings,
+            )
         }
         Err(e) => {
             // Format file reading errors using the same format
             let path_str = path.display().to_string();
             let error_msg = format!("Cannot read file: {}", e);
-            eprintln!(
-                "{}",
-                format_error(output_config, &path_str, 1, 1, &error_msg, "error")
-            );
-
-            // Update cache with error status
-            if cache_enabled && path.exists() {
-                if let Some(cache_obj) = cache {
-                    if let Err(e) = cache_obj.update(path, false, options_hash) {
-                        if verbose {
-                            println!("Failed to update cache: {}", e);
-                        }
-                    }
-                }
-            }
-
-            ParseResult::Error
+            report_error(output_config, &path_str, 1, 1, &error_msg, "error");
+            ParseResult::Unreadable
         }
     }
 }
 
 /// Parse code content directly from standard input
 fn parse_stdin_content(
+    path_str: &str,
     verbose: bool,
     output_config: &OutputConfig,
     strict_mode: bool,
@@ -412,18 +516,10 @@ fn parse_stdin_content(
                 println!("Received {} bytes from stdin", content.len());
             }
 
-            // Use a pseudo-filename for better error reporting
-            let path_str = "<stdin>";
-
-            match parser::parse(&content, verbose) {
+            match parser::parse(&content) {
                 Ok(_) => {
-                    // Show success message if configured to do so
-                    if output_config.show_success {
-                        println!(
-                            "{}",
-                            format_success(output_config, &PathBuf::from(path_str))
-                        );
-                    }
+                    // Show success message (or the syntax tree) if configured to do so
+                    print_success(output_config, Path::new(path_str), &content);
                     ParseResult::Success
                 }
                 Err(e) => {
@@ -434,10 +530,7 @@ fn parse_stdin_content(
                             // In strict mode, treat as error
                             if strict_mode {
                                 let error_msg = "No ASP tags found in input";
-                                eprintln!(
-                                    "{}",
-                                    format_error(output_config, path_str, 1, 1, error_msg, "error")
-                                );
+                                report_error(output_config, path_str, 1, 1, error_msg, "error");
                                 return ParseResult::Error;
                             }
 
@@ -446,16 +539,13 @@ fn parse_stdin_content(
                                 // In verbose mode or if not explicitly ignored, show the warning
                                 if verbose || ignored_warnings.is_empty() {
                                     let warning_msg = "No ASP tags found in input - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            output_config,
-                                            path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
+                                    report_error(
+                                        output_config,
+                                        path_str,
+                                        1,
+                                        1,
+                                        warning_msg,
+                                        "warning",
                                     );
                                 }
                             }
@@ -467,10 +557,7 @@ fn parse_stdin_content(
                             // In strict mode, treat as error
                             if strict_mode {
                                 let error_msg = "Input is empty or contains only whitespace";
-                                eprintln!(
-                                    "{}",
-                                    format_error(output_config, path_str, 1, 1, error_msg, "error")
-                                );
+                                report_error(output_config, path_str, 1, 1, error_msg, "error");
                                 return ParseResult::Error;
                             }
 
@@ -480,16 +567,13 @@ fn parse_stdin_content(
                                 if verbose || ignored_warnings.is_empty() {
                                     let warning_msg =
                                         "Input is empty or contains only whitespace - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            output_config,
-                                            path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
+                                    report_error(
+                                        output_config,
+                                        path_str,
+                                        1,
+                                        1,
+                                        warning_msg,
+                                        "warning",
                                     );
                                 }
                             }
@@ -499,24 +583,8 @@ fn parse_stdin_content(
                     }
 
                     // For other errors, handle as a regular error
-                    let error_message = e.to_string();
-                    let (line, column) = extract_line_and_column(&error_message);
-
-                    // Get the appropriate severity for this error
-                    let severity = map_severity("parse_error");
-
-                    // Format and print the error according to the selected output format
-                    eprintln!(
-                        "{}",
-                        format_error(
-                            output_config,
-                            path_str,
-                            line,
-                            column,
-                            &error_message,
-                            severity
-                        )
-                    );
+                    // Print every syntax error of the input
+                    print_syntax_errors(output_config, path_str, &content, e.as_ref());
                     ParseResult::Error
                 }
             }
@@ -524,11 +592,8 @@ fn parse_stdin_content(
         Err(e) => {
             // Format stdin reading errors using the same format
             let error_msg = format!("Cannot read from stdin: {}", e);
-            eprintln!(
-                "{}",
-                format_error(output_config, "<stdin>", 1, 1, &error_msg, "error")
-            );
-            ParseResult::Error
+            report_error(output_config, path_str, 1, 1, &error_msg, "error");
+            ParseResult::Unreadable
         }
     }
 }
@@ -536,7 +601,8 @@ fn parse_stdin_content(
 /// Parse a single file and report results in a thread-safe manner
 ///
 /// This function is designed to be used in parallel processing environments,
-/// with shared resources protected by Arc<Mutex<T>>.
+/// with the output protected by Arc<Mutex<T>> and the cache shared without a
+/// global lock.
 ///
 #[allow(clippy::too_many_arguments)]
 fn parse_file_parallel(
@@ -546,9 +612,10 @@ fn parse_file_parallel(
     strict_mode: bool,
     ignored_warnings: Arc<Vec<String>>,
     cache_enabled: bool,
-    cache: Arc<Mutex<Option<Cache>>>,
+    cache: Option<Arc<SharedCache>>,
     options_hash: String,
     output_mutex: Arc<Mutex<()>>,
+    read_options: ReadOptions,
 ) -> ParseResult {
     // Use a mutex to avoid interleaved console output
     {
@@ -562,116 +629,38 @@ fn parse_file_parallel(
 
     // Check if file is in cache and the cache is valid
     if cache_enabled && path.exists() {
-        let cache_check_result = {
-            let cache_guard = cache.lock().unwrap();
-            if let Some(ref cache_obj) = *cache_guard {
-                cache_obj.is_valid(&path, &options_hash)
-            } else {
-                Ok(false)
-            }
+        let cache_check_result = match &cache {
+            Some(cache_obj) => cache_obj.is_valid(&path, &options_hash),
+            None => Ok(false),
         };
 
         match cache_check_result {
             Ok(true) => {
                 // File is in cache and hasn't changed
-                let mut cache_result = None;
+                let cached_diagnostics = cache
+                    .as_ref()
+                    .and_then(|cache_obj| cache_obj.get_diagnostics(&path));
 
-                {
-                    let cache_guard = cache.lock().unwrap();
-                    if let Some(ref cache_obj) = *cache_guard {
-                        cache_result = cache_obj.was_successful(&path);
-                    }
-                }
-
-                if let Some(success) = cache_result {
+                if let Some(diagnostics) = cached_diagnostics {
                     let _lock = output_mutex.lock().unwrap();
 
                     if verbose {
                         println!("Using cached result for: {}", path.display());
                     }
 
-                    if success {
-                        // Show success message if configured to do so
-                        if output_config.show_success {
-                            println!("{}", format_success(&output_config, &path));
-                        }
+                    // The problems of the file are reported as when it was parsed
+                    if diagnostics.is_empty() {
+                        report_success(&output_config, &path);
                         return ParseResult::Success;
-                    } else {
-                        // Always show the warning/error message
-                        let path_str = path.display().to_string();
-
-                        // Check for skipped files (no-asp-tags) or parse errors
-                        let mut cache_error_message = None;
-                        {
-                            let cache_guard = cache.lock().unwrap();
-                            if let Some(ref cache_obj) = *cache_guard {
-                                cache_error_message = cache_obj.get_error_message(&path);
-                            }
-                        }
-
-                        let is_parse_error = cache_error_message.is_some();
-
-                        if is_parse_error {
-                            // If we have a real parse error stored in cache, display it
-                            let error_message = cache_error_message.unwrap();
-                            let (line, column) = extract_line_and_column(&error_message);
-
-                            // Get the appropriate severity for this error
-                            let severity = map_severity("parse_error");
-
-                            // Format and print the error according to the selected output format
-                            eprintln!(
-                                "{}",
-                                format_error(
-                                    &output_config,
-                                    &path_str,
-                                    line,
-                                    column,
-                                    &error_message,
-                                    severity
-                                )
-                            );
-                            return ParseResult::Error;
-                        } else if !ignored_warnings.contains(&"no-asp-tags".to_string()) {
-                            let warning_msg = "No ASP tags found in file - skipping";
-
-                            if strict_mode {
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        &output_config,
-                                        &path_str,
-                                        1,
-                                        1,
-                                        "No ASP tags found in file",
-                                        "error"
-                                    )
-                                );
-                                return ParseResult::Error;
-                            } else {
-                                // Show warning only if in verbose mode or not explicitly ignored
-                                if verbose || ignored_warnings.is_empty() {
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            &output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
-                                    );
-                                }
-                                return ParseResult::Skipped;
-                            }
-                        }
-
-                        // Re-parse the file if not a skipped file
-                        if verbose {
-                            println!("Cache indicates error - re-parsing file");
-                        }
                     }
+                    return report_parse_diagnostics(
+                        &output_config,
+                        &path,
+                        &diagnostics,
+                        verbose,
+                        strict_mode,
+                        &ignored_warnings,
+                    );
                 }
             }
             Ok(false) => {
@@ -690,222 +679,1258 @@ fn parse_file_parallel(
     }
 
     // Parse the file
-    match file_utils::read_file_with_encoding(&path) {
-        Ok(content) => {
-            match parser::parse(&content, verbose) {
-                Ok(_) => {
-                    // Update cache
-                    if cache_enabled && path.exists() {
-                        let mut cache_guard = cache.lock().unwrap();
-                        if let Some(ref mut cache_obj) = *cache_guard {
-                            if let Err(e) = cache_obj.update(&path, true, &options_hash) {
-                                let _lock = output_mutex.lock().unwrap();
-                                if verbose {
-                                    println!("Failed to update cache: {}", e);
-                                }
-                            }
-                        }
-                    }
-
-                    // Show success message if configured to do so
-                    {
-                        let _lock = output_mutex.lock().unwrap();
-                        if output_config.show_success {
-                            println!("{}", format_success(&output_config, &path));
-                        }
-                    }
-
-                    ParseResult::Success
+    // SAFETY: `--mmap` is documented as requiring the files not to be modified
+    // while they are parsed
+    match unsafe { file_utils::read_source_file(&path, read_options) } {
+        Ok((content, encoding)) => {
+            {
+                let _lock = output_mutex.lock().unwrap();
+                if verbose {
+                    println!("Encoding: {}", encoding);
                 }
-                Err(e) => {
-                    // Lock for synchronized output
-                    let _lock = output_mutex.lock().unwrap();
-
-                    // Try to downcast to AspParseError to check for special conditions
-                    if let Some(asp_error) = e.downcast_ref::<parser::AspParseError>() {
-                        // Check if this is a "no ASP tags" error
-                        if asp_error.is_no_asp_tags_error() {
-                            let path_str = path.display().to_string();
-
-                            // Update cache with skipped status
-                            if cache_enabled && path.exists() {
-                                let mut cache_guard = cache.lock().unwrap();
-                                if let Some(ref mut cache_obj) = *cache_guard {
-                                    if let Err(e) = cache_obj.update(&path, false, &options_hash) {
-                                        if verbose {
-                                            println!("Failed to update cache: {}", e);
-                                        }
-                                    }
-                                }
-                            }
-
-                            // In strict mode, treat as error
-                            if strict_mode {
-                                let error_msg = "No ASP tags found in file";
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        &output_config,
-                                        &path_str,
-                                        1,
-                                        1,
-                                        error_msg,
-                                        "error"
-                                    )
-                                );
-                                return ParseResult::Error;
-                            }
+                report_assumed_encoding(&output_config, &path, encoding, &ignored_warnings);
+            }
+            // Files whose encoding is assumed are not cached, for the notice to be
+            // reported on every run
+            let cache_enabled = cache_enabled && !encoding.is_assumed();
+            let diagnostics = parse_diagnostics(&content);
+
+            // Update cache with the problems of the file
+            if cache_enabled
+                && path.exists()
+                && let Some(cache_obj) = &cache
+                && let Err(e) = cache_obj.update_with_diagnostics(
+                    &path,
+                    diagnostics.is_empty(),
+                    &options_hash,
+                    diagnostics.clone(),
+                )
+            {
+                let _lock = output_mutex.lock().unwrap();
+                if verbose {
+                    println!("Failed to update cache: {}", e);
+                }
+            }
 
-                            // Otherwise, handle as a warning - unless ignored
-                            if !ignored_warnings.contains(&"no-asp-tags".to_string()) {
-                                // In verbose mode or if not explicitly ignored, show the warning
-                                if verbose || ignored_warnings.is_empty() {
-                                    let warning_msg = "No ASP tags found in file - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            &output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
-                                    );
-                                }
-                            }
+            // Lock for synchronized output
+            let _lock = output_mutex.lock().unwrap();
+            if diagnostics.is_empty() {
+                // Show success message if configured to do so
+                print_success(&output_config, &path, &content);
+                return ParseResult::Success;
+            }
+            report_parse_diagnostics(
+                &output_config,
+                &path,
+                &diagnostics,
+                verbose,
+                strict_mode,
+                &ignored_warnings,
+            )
+        }
+        Err(e) => {
+            // Lock for synchronized output
+            let _lock = output_mutex.lock().unwrap();
 
-                            return ParseResult::Skipped;
-                        }
-                        // Check if this is an "empty file" error
-                        else if asp_error.is_empty_file_error() {
-                            let path_str = path.display().to_string();
-
-                            // Update cache with skipped status
-                            if cache_enabled && path.exists() {
-                                let mut cache_guard = cache.lock().unwrap();
-                                if let Some(ref mut cache_obj) = *cache_guard {
-                                    if let Err(e) = cache_obj.update(&path, false, &options_hash) {
-                                        if verbose {
-                                            println!("Failed to update cache: {}", e);
-                                        }
-                                    }
-                                }
-                            }
+            // Format file reading errors using the same format
+            let path_str = path.display().to_string();
+            let error_msg = format!("Cannot read file: {}", e);
+            report_error(&output_config, &path_str, 1, 1, &error_msg, "error");
+            ParseResult::Unreadable
+        }
+    }
+}
 
-                            // In strict mode, treat as error
-                            if strict_mode {
-                                let error_msg = "File is empty or contains only whitespace";
-                                eprintln!(
-                                    "{}",
-                                    format_error(
-                                        &output_config,
-                                        &path_str,
-                                        1,
-                                        1,
-                                        error_msg,
-                                        "error"
-                                    )
-                                );
-                                return ParseResult::Error;
-                            }
+/// Directory whose configuration files apply: the directory of the file named by
+/// `--stdin-filename`, if given, or the current directory
+fn config_directory(stdin_filename: Option<&String>) -> PathBuf {
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    match stdin_filename.and_then(|path| Path::new(path).parent()) {
+        Some(parent) => current_dir.join(parent),
+        None => current_dir,
+    }
+}
 
-                            // Otherwise, handle as a warning - unless ignored
-                            if !ignored_warnings.contains(&"empty-file".to_string()) {
-                                // In verbose mode or if not explicitly ignored, show the warning
-                                if verbose || ignored_warnings.is_empty() {
-                                    let warning_msg =
-                                        "File is empty or contains only whitespace - skipping";
-                                    eprintln!(
-                                        "{}",
-                                        format_error(
-                                            &output_config,
-                                            &path_str,
-                                            1,
-                                            1,
-                                            warning_msg,
-                                            "warning"
-                                        )
-                                    );
-                                }
-                            }
+/// Load the configuration from an explicit file, or from the configuration files
+/// of a directory and its parents, the closest ones taking precedence, and from
+/// the `ASP_PARSER_*` environment variables, which take precedence over them
+fn load_config(config_path: Option<&String>, verbose: bool, directory: &Path) -> Config {
+    let mut config = Config::default();
 
-                            return ParseResult::Skipped;
-                        }
+    // Check for explicit config file path
+    if let Some(config_path) = config_path {
+        let config_file_path = PathBuf::from(config_path);
+        if !config_file_path.exists() {
+            eprintln!(
+                "Warning: Configuration file '{}' does not exist",
+                config_path
+            );
+        } else {
+            match Config::from_file(&config_file_path) {
+                Ok(loaded_config) => {
+                    if verbose {
+                        println!("Loaded configuration from {}", config_path);
                     }
+                    config = loaded_config;
+                }
+                Err(e) => {
+                    eprintln!("Error loading configuration from '{}': {}", config_path, e);
+                }
+            }
+        }
+    } else {
+        // Look for configuration files in the directory and its parents
+        let configs = Config::find_configs(directory);
 
-                    // For other errors, handle as a regular error
-                    let error_message = e.to_string();
-                    let (line, column) = extract_line_and_column(&error_message);
-
-                    // Update cache with error status and message
-                    if cache_enabled && path.exists() {
-                        let mut cache_guard = cache.lock().unwrap();
-                        if let Some(ref mut cache_obj) = *cache_guard {
-                            if let Err(e) = cache_obj.update_with_error(
-                                &path,
-                                false,
-                                &options_hash,
-                                Some(error_message.clone()),
-                            ) {
-                                if verbose {
-                                    println!("Failed to update cache with error: {}", e);
-                                }
-                            }
-                        }
-                    }
+        if !configs.is_empty() && verbose {
+            println!("Found {} configuration file(s)", configs.len());
+        }
 
-                    // Get the appropriate severity for this error
-                    let severity = map_severity("parse_error");
+        // Apply configurations, starting from the most general to most specific
+        for (path, cfg) in configs {
+            if verbose {
+                println!("Applying configuration from {}", path.display());
+            }
+            config = cfg.merge(&config);
+        }
+    }
 
-                    // Format and print the error according to the selected output format
-                    let path_str = path.display().to_string();
-                    eprintln!(
-                        "{}",
-                        format_error(
-                            &output_config,
-                            &path_str,
-                            line,
-                            column,
-                            &error_message,
-                            severity
-                        )
-                    );
-                    ParseResult::Error
+    // The environment variables take precedence over the configuration files
+    match Config::from_env() {
+        Ok(env_config) => env_config.merge(&config),
+        Err(e) => {
+            eprintln!("Error in environment variables: {}", e);
+            config
+        }
+    }
+}
+
+/// Check configuration files for unknown keys and invalid values
+///
+/// Without a file, the configuration files of the current directory and its
+/// parents are checked, and the `ASP_PARSER_*` environment variables.
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 2 if a file or an environment variable has
+///   problems, 4 if a file cannot be read, 0 otherwise
+fn check_config(matches: &clap::ArgMatches) -> i32 {
+    let (paths, env_error) = match matches.get_one::<String>("file") {
+        Some(file) => (vec![PathBuf::from(file)], None),
+        None => (
+            Config::find_config_files(&config_directory(None)),
+            Config::from_env().err(),
+        ),
+    };
+    if paths.is_empty() && env_error.is_none() {
+        println!("No configuration file found");
+        return 0;
+    }
+
+    let mut problem_count = 0;
+    let mut unreadable = false;
+    for path in &paths {
+        match Config::check_file(path) {
+            Ok(problems) if problems.is_empty() => println!("{}: ok", path.display()),
+            Ok(problems) => {
+                problem_count += problems.len();
+                for problem in problems {
+                    println!("{}: {}", path.display(), problem);
                 }
             }
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                unreadable = true;
+            }
+        }
+    }
+    if let Some(e) = env_error {
+        problem_count += 1;
+        println!("environment: {}", e);
+    }
+    if problem_count > 0 {
+        println!(
+            "Found {} problem(s) in {} configuration file(s)",
+            problem_count,
+            paths.len()
+        );
+    }
+    exit_code(
+        &[
+            (EXIT_INVALID_CONFIG, problem_count > 0),
+            (EXIT_IO_ERROR, unreadable),
+        ],
+        false,
+    )
+}
+
+/// Print the effective configuration, merged from the configuration files of the
+/// current directory and its parents, or from the file given with `--config`, and
+/// from the `ASP_PARSER_*` environment variables
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 2 if the file given with `--config` cannot be
+///   loaded or an environment variable is invalid, 0 otherwise
+fn show_config(matches: &clap::ArgMatches) -> i32 {
+    let mut configs = match matches.get_one::<String>("config") {
+        Some(file) => match Config::from_file_layers(Path::new(file)) {
+            Ok(layers) => layers,
+            Err(e) => {
+                eprintln!("Error loading configuration from '{}': {}", file, e);
+                return EXIT_INVALID_CONFIG;
+            }
+        },
+        None => Config::find_configs(&config_directory(None)),
+    };
+    // The environment variables are shown as the closest configuration
+    match Config::from_env() {
+        Ok(env_config) if !env_config.is_empty() => {
+            configs.push((PathBuf::from("environment"), env_config));
         }
+        Ok(_) => {}
         Err(e) => {
-            // Lock for synchronized output
-            let _lock = output_mutex.lock().unwrap();
+            eprintln!("Error in environment variables: {}", e);
+            return EXIT_INVALID_CONFIG;
+        }
+    }
+    print!("{}", Config::describe_merged(&configs));
+    0
+}
 
-            // Format file reading errors using the same format
-            let path_str = path.display().to_string();
-            let error_msg = format!("Cannot read file: {}", e);
+/// Print the JSON Schema of the configuration files
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if the schema cannot be serialized, 0 otherwise
+fn print_config_schema() -> i32 {
+    match serde_json::to_string_pretty(&Config::json_schema()) {
+        Ok(schema) => {
+            println!("{}", schema);
+            0
+        }
+        Err(e) => {
+            eprintln!("Error serializing the schema: {}", e);
+            1
+        }
+    }
+}
+
+/// The `--default-encoding` option of the commands reading files
+fn default_encoding_arg() -> Arg {
+    Arg::new("default-encoding")
+        .long("default-encoding")
+        .help("Encoding of the files that are not valid UTF-8, without byte order mark nor CodePage directive: a label (windows-1252, shift_jis...), a Windows code page (932...), or 'auto' to guess it (default: iso-8859-1)")
+        .value_name("ENCODING")
+        .value_parser(|value: &str| DefaultEncoding::from_str(value))
+        .required(false)
+}
+
+/// Returns the encoding of the files that are not valid UTF-8, from
+/// `--default-encoding` or the configuration
+///
+/// # Returns
+///
+/// * `Result<DefaultEncoding, i32>` - The default encoding, or the exit code 2 if
+///   the one of the configuration is unknown
+fn default_encoding(matches: &clap::ArgMatches, config: &Config) -> Result<DefaultEncoding, i32> {
+    match matches.get_one::<DefaultEncoding>("default-encoding") {
+        Some(&default_encoding) => Ok(default_encoding),
+        None => config.default_encoding().map_err(|e| {
+            eprintln!("Error in configuration: {}", e);
+            EXIT_INVALID_CONFIG
+        }),
+    }
+}
+
+/// Report that the encoding of a file is assumed, unless the `encoding-assumed`
+/// notice is ignored
+fn report_assumed_encoding(
+    output_config: &OutputConfig,
+    path: &Path,
+    encoding: Encoding,
+    ignored_warnings: &[String],
+) {
+    let code = parser::DiagnosticCode::EncodingAssumed;
+    if !encoding.is_assumed()
+        || ignored_warnings
+            .iter()
+            .any(|warning| warning == code.name())
+    {
+        return;
+    }
+    let message = format!(
+        "File is not valid UTF-8 and has no byte order mark nor CodePage directive; read as {}",
+        encoding.name()
+    );
+    let diagnostic = parser::Diagnostic::new(code, parser::ast::Span::new(0, 0, 1, 1), message);
+    report_diagnostic(output_config, &path.display().to_string(), &diagnostic);
+}
+
+/// Options limiting the search of files in directories, for the main command
+/// and the subcommands taking directories
+fn scan_args() -> [Arg; 3] {
+    [
+        Arg::new("follow-symlinks")
+            .long("follow-symlinks")
+            .help("Follow symbolic links when looking for files in directories; a directory linked several times is searched once")
+            .action(ArgAction::SetTrue)
+            .required(false),
+        Arg::new("max-depth")
+            .long("max-depth")
+            .help("Number of directory levels searched (1: the files of the given directories only)")
+            .value_name("DEPTH")
+            .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+            .required(false),
+        Arg::new("max-file-size")
+            .long("max-file-size")
+            .help("Skip the files found in directories above this size, with a notice (e.g. 512K, 10M; 0 for no limit; default: 10M)")
+            .value_name("SIZE")
+            .value_parser(file_utils::parse_size)
+            .required(false),
+    ]
+}
+
+/// Returns the options of the search of files in directories, from the
+/// command-line options and the configuration
+///
+/// # Returns
+///
+/// * `Result<ScanOptions, i32>` - The options, or the exit code 2 if the maximum
+///   file size of the configuration is invalid
+fn scan_options(matches: &clap::ArgMatches, config: &Config) -> Result<ScanOptions, i32> {
+    let mut options = config.scan_options().map_err(|e| {
+        eprintln!("Error in configuration: {}", e);
+        EXIT_INVALID_CONFIG
+    })?;
+    if matches.get_flag("follow-symlinks") {
+        options.follow_symlinks = true;
+    }
+    if let Some(&max_depth) = matches.get_one::<usize>("max-depth") {
+        options.max_depth = Some(max_depth);
+    }
+    if let Some(&max_file_size) = matches.get_one::<u64>("max-file-size") {
+        options.max_file_size = (max_file_size > 0).then_some(max_file_size);
+    }
+    Ok(options)
+}
+
+/// Returns the message of a file skipped for being larger than the maximum file
+/// size
+fn file_too_large_message(size: u64, options: &ScanOptions) -> String {
+    format!(
+        "File skipped: its size of {} bytes exceeds the maximum file size of {} bytes (--max-file-size)",
+        size,
+        options.max_file_size.unwrap_or_default()
+    )
+}
+
+/// Report that a file found in a directory is skipped for its size, unless the
+/// `file-too-large` notice is ignored
+fn report_file_too_large(
+    output_config: &OutputConfig,
+    path: &Path,
+    size: u64,
+    options: &ScanOptions,
+    ignored_warnings: &[String],
+) {
+    let code = parser::DiagnosticCode::FileTooLarge;
+    if ignored_warnings
+        .iter()
+        .any(|warning| warning == code.name())
+    {
+        return;
+    }
+    let diagnostic = parser::Diagnostic::new(
+        code,
+        parser::ast::Span::new(0, 0, 1, 1),
+        file_too_large_message(size, options),
+    );
+    report_diagnostic(output_config, &path.display().to_string(), &diagnostic);
+}
+
+/// Option moving the parsing cache, for the main command and the `cache`
+/// subcommand
+fn cache_dir_arg() -> Arg {
+    Arg::new("cache-dir")
+        .long("cache-dir")
+        .help("Directory of the parsing cache (default: .asp-parser-cache in the directory of the outermost configuration file, or the user cache directory without one)")
+        .value_name("DIR")
+        .required(false)
+}
+
+/// Returns the path of the parsing cache file
+///
+/// The cache is in the directory given by `--cache-dir`, by the
+/// `ASP_PARSER_CACHE_DIR` environment variable or by the configuration, in that
+/// order; otherwise in the `.asp-parser-cache` directory of the project, next to
+/// its outermost configuration file, or in the user cache directory when there
+/// is none.
+fn cache_path(matches: &clap::ArgMatches, config: &Config) -> PathBuf {
+    if let Some(directory) = matches.get_one::<String>("cache-dir") {
+        return Path::new(directory).join(cache::CACHE_FILE_NAME);
+    }
+    let directory = config.cache_directory().or_else(|| {
+        Config::project_root(&config_directory(None))
+            .map(|root| root.join(cache::PROJECT_CACHE_DIRECTORY))
+    });
+    Cache::get_cache_path_in(directory.as_deref())
+}
+
+/// Load the parsing cache from a file, with the time to live of the
+/// configuration
+///
+/// # Returns
+///
+/// * `Result<Cache, i32>` - The cache, or the exit code 2 if the time to live
+///   of the configuration is invalid
+fn load_configured_cache(path: &Path, config: &Config) -> Result<Cache, i32> {
+    let ttl = config.cache_ttl().map_err(|e| {
+        eprintln!("Error in configuration: {}", e);
+        EXIT_INVALID_CONFIG
+    })?;
+    let mut cache = Cache::load_from(path);
+    if let Some(ttl) = ttl {
+        cache.set_max_age(ttl.as_secs());
+    }
+    Ok(cache)
+}
+
+/// Share the parsing cache with the remote cache of the configuration, if any
+///
+/// # Returns
+///
+/// * `Result<(), i32>` - The exit code 2 if the remote cache is misconfigured
+fn set_remote_cache(cache: &mut Cache, config: &Config, verbose: bool) -> Result<(), i32> {
+    let remote = config.remote_cache().map_err(|e| {
+        eprintln!("Error in configuration: {}", e);
+        EXIT_INVALID_CONFIG
+    })?;
+    let Some((url, backend)) = remote else {
+        return Ok(());
+    };
+    let remote = RemoteCache::new(&url, backend).map_err(|e| {
+        eprintln!("Error in configuration: {}", e);
+        EXIT_INVALID_CONFIG
+    })?;
+    if verbose {
+        println!("Using remote cache: {}", url);
+    }
+    cache.set_remote(remote);
+    Ok(())
+}
+
+/// Print the statistics of the parsing cache
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 2 if the cache configuration is invalid, 1 if the
+///   statistics cannot be serialized, 0 otherwise
+fn print_cache_stats(matches: &clap::ArgMatches, config: &Config) -> i32 {
+    let path = cache_path(matches, config);
+    let size = std::fs::metadata(&path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0);
+    let stats = match load_configured_cache(&path, config) {
+        Ok(cache) => cache.stats(),
+        Err(code) => return code,
+    };
+
+    if matches.get_one::<String>("format").map(String::as_str) == Some("json") {
+        let json = serde_json::json!({
+            "path": path,
+            "size": size,
+            "entries": stats.entries,
+            "successful": stats.successful,
+            "failed": stats.failed,
+            "expired": stats.expired,
+            "hits": stats.hits,
+            "misses": stats.misses,
+            "hit_rate": stats.hit_rate(),
+        });
+        match serde_json::to_string_pretty(&json) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing the statistics: {}", e);
+                return 1;
+            }
+        }
+        return 0;
+    }
+
+    println!("Cache file: {}", path.display());
+    println!("Size on disk: {} bytes", size);
+    println!(
+        "Entries: {} ({} successful, {} failed, {} expired)",
+        stats.entries, stats.successful, stats.failed, stats.expired
+    );
+    match stats.hit_rate() {
+        Some(rate) => println!(
+            "Hit rate: {:.1}% ({} hits, {} misses)",
+            rate * 100.0,
+            stats.hits,
+            stats.misses
+        ),
+        None => println!("Hit rate: no lookup yet"),
+    }
+    0
+}
+
+/// Delete the parsing cache, or only its entries older than `--older-than`
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 4 if the cache cannot be written or deleted, 0
+///   otherwise
+fn clear_cache(matches: &clap::ArgMatches, config: &Config) -> i32 {
+    let path = cache_path(matches, config);
+    if let Some(&age) = matches.get_one::<std::time::Duration>("older-than") {
+        let mut cache = Cache::load_from(&path);
+        let removed = cache.clean_entries_older_than(age);
+        if removed > 0
+            && let Err(e) = cache.save()
+        {
+            eprintln!("Failed to save cache: {}", e);
+            return EXIT_IO_ERROR;
+        }
+        println!(
+            "Removed {} entries from the cache, {} left",
+            removed,
+            cache.len()
+        );
+        return 0;
+    }
+
+    match Cache::clear(&path) {
+        Ok(true) => println!("Deleted {}", path.display()),
+        Ok(false) => println!("The cache is already empty"),
+        Err(e) => {
+            eprintln!("Failed to delete the cache: {}", e);
+            return EXIT_IO_ERROR;
+        }
+    }
+    0
+}
+
+/// Print the explanation of a diagnostic code, or the list of codes
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if the code is unknown, 0 otherwise
+fn explain_code(code: Option<&String>) -> i32 {
+    let Some(code) = code else {
+        for code in parser::DiagnosticCode::ALL {
+            println!(
+                "{} {:<24} {}",
+                code.as_str(),
+                code.name(),
+                explain(code).summary
+            );
+        }
+        return 0;
+    };
+    match parser::DiagnosticCode::from_name(code) {
+        Some(code) => {
+            print!("{}", explain(code));
+            0
+        }
+        None => {
             eprintln!(
-                "{}",
-                format_error(&output_config, &path_str, 1, 1, &error_msg, "error")
+                "Unknown diagnostic code '{}'; run `asp-classic-parser explain` to list them",
+                code
             );
+            1
+        }
+    }
+}
 
-            // Update cache with error status
-            if cache_enabled && path.exists() {
-                let mut cache_guard = cache.lock().unwrap();
-                if let Some(ref mut cache_obj) = *cache_guard {
-                    if let Err(e) = cache_obj.update(&path, false, &options_hash) {
-                        if verbose {
-                            println!("Failed to update cache: {}", e);
-                        }
+/// List the files given to a subcommand, looking for ASP files in directories,
+/// with the extensions and the scan options of the configuration
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>, i32>` - The files, or the exit code 2 if the scan
+///   options of the configuration are invalid
+fn collect_files(
+    matches: &clap::ArgMatches,
+    exclude_patterns: &[String],
+    config: &Config,
+) -> Result<Vec<PathBuf>, i32> {
+    let extensions = config.scan_extensions();
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let options = scan_options(matches, config)?;
+    let mut files = Vec::new();
+    for path in matches.get_many::<String>("files").into_iter().flatten() {
+        let path = PathBuf::from(path);
+        if path.is_dir() {
+            match file_utils::scan_files(&path, exclude_patterns, &extensions, &options) {
+                Ok(scanned) => {
+                    files.extend(scanned.files);
+                    for (path, size) in scanned.too_large {
+                        eprintln!(
+                            "Notice: {}: {}",
+                            path.display(),
+                            file_too_large_message(size, &options)
+                        );
                     }
                 }
+                Err(e) => eprintln!("Error scanning directory '{}': {}", path.display(), e),
             }
+        } else if path.exists() {
+            files.push(path);
+        } else {
+            eprintln!(
+                "Warning: Path '{}' does not exist, skipping",
+                path.display()
+            );
+        }
+    }
+    Ok(files)
+}
 
-            ParseResult::Error
+/// Run the lint rules on files and report the problems found
+///
+/// The files are indexed with the files they include, so that names declared in
+/// included files are known to the rules.
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if a file has syntax errors, 2 if the configuration
+///   or the baseline is invalid, 3 if a rule reported an error, 4 if a file cannot
+///   be read or the baseline written, 5 if there are more warnings than
+///   `--max-warnings` allows or warnings with `--error-on=warnings`, 0 otherwise
+///   or with `--exit-zero`; the lowest code applies
+fn lint_files(matches: &clap::ArgMatches) -> i32 {
+    let format = match matches.get_one::<String>("format") {
+        Some(format) => OutputFormat::from_str(format).unwrap_or(OutputFormat::Ascii),
+        None => OutputFormat::detect_format(),
+    };
+    let output_config = OutputConfig {
+        format,
+        use_colors: !matches.get_flag("no-color"),
+        show_success: !matches.get_flag("quiet-success"),
+        report: None,
+        output: None,
+        error_output: None,
+        warnings: Arc::default(),
+    };
+    let exclude_patterns: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let stdin_filename = matches.get_one::<String>("stdin-filename");
+    let config = load_config(
+        matches.get_one::<String>("config"),
+        false,
+        &config_directory(stdin_filename),
+    );
+    // Rules of the files, by the overrides of the configuration matching them
+    let mut registries: HashMap<Vec<String>, lint::Registry> = HashMap::new();
+    match config.lint_registry() {
+        Ok(registry) => registries.insert(Vec::new(), registry),
+        Err(e) => {
+            eprintln!("Error in configuration: {}", e);
+            return EXIT_INVALID_CONFIG;
+        }
+    };
+    let baseline = match matches.get_one::<String>("baseline") {
+        Some(baseline_path) => match Baseline::load(Path::new(baseline_path)) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                eprintln!("Error loading baseline from '{}': {}", baseline_path, e);
+                return EXIT_INVALID_CONFIG;
+            }
+        },
+        None => None,
+    };
+    let mut write_baseline = matches
+        .get_one::<String>("write-baseline")
+        .map(|baseline_path| {
+            let baseline_path = PathBuf::from(baseline_path);
+            let directory = match baseline_path.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+                _ => PathBuf::from("."),
+            };
+            let root = std::fs::canonicalize(&directory).unwrap_or(directory);
+            (baseline_path, Baseline::new(&root))
+        });
+
+    // The standard input is checked as the file named by --stdin-filename, whose
+    // includes are read from the disk
+    let stdin_source = if matches.get_flag("stdin") {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("Error reading from stdin: {}", e);
+            return EXIT_IO_ERROR;
+        }
+        Some(source)
+    } else {
+        None
+    };
+    let resolver = config.include_resolver(matches.get_one::<String>("web-root"));
+    let (files, index) = match &stdin_source {
+        Some(source) => {
+            let path = PathBuf::from(stdin_filename.map_or("<stdin>", String::as_str));
+            let mut index = WorkspaceIndex::new(resolver);
+            index.update(&path, source);
+            index.load_includes(&path);
+            (vec![path], index)
+        }
+        None => {
+            let files = match collect_files(matches, &exclude_patterns, &config) {
+                Ok(files) => files,
+                Err(code) => return code,
+            };
+            let graph = IncludeGraph::build(&files, &resolver);
+            (files, WorkspaceIndex::from_graph(&graph, resolver))
+        }
+    };
+
+    // The Code Quality report is written once every file is checked
+    let mut report = (format == OutputFormat::CodeClimate).then(CodeQualityReport::new);
+    let mut success_count = 0;
+    let mut fail_count = 0;
+    let mut syntax_error_count = 0;
+    let mut unreadable_count = 0;
+    let mut warning_count = 0;
+    let fail_fast = matches.get_flag("fail-fast");
+    for (checked_count, path) in files.iter().enumerate() {
+        if fail_fast && fail_count > 0 {
+            eprintln!(
+                "Stopped after the first failure (--fail-fast): {} of {} file(s) not checked",
+                files.len() - checked_count,
+                files.len()
+            );
+            break;
+        }
+        let display_path = path.display().to_string();
+        let read = match &stdin_source {
+            Some(source) => Ok(source.clone()),
+            None => file_utils::read_file_with_encoding(path),
+        };
+        let source = match read {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format_error(
+                        &output_config,
+                        &display_path,
+                        1,
+                        1,
+                        &format!("Cannot read file: {}", e),
+                        map_severity("io_error")
+                    )
+                );
+                fail_count += 1;
+                unreadable_count += 1;
+                continue;
+            }
+        };
+        let overrides: Vec<String> = config
+            .matching_overrides(path)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let registry = match registries.entry(overrides) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => match config.for_path(path).lint_registry() {
+                Ok(registry) => entry.insert(registry),
+                Err(e) => {
+                    eprintln!("Error in configuration of {}: {}", display_path, e);
+                    return EXIT_INVALID_CONFIG;
+                }
+            },
+        };
+        let diagnostics = lint::lint_file(path, &source, registry, &index);
+        // Baselines identify files by their path from the baseline directory
+        let absolute_path = std::fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+        if let Some((_, baseline)) = &mut write_baseline {
+            baseline.add(&absolute_path, &diagnostics);
+            continue;
+        }
+        let diagnostics = match &baseline {
+            Some(baseline) => baseline.filter(&absolute_path, diagnostics),
+            None => diagnostics,
+        };
+        warning_count += diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == parser::Severity::Warning)
+            .count();
+        for diagnostic in &diagnostics {
+            match &mut report {
+                Some(report) => report.add(&display_path, diagnostic),
+                None => eprintln!(
+                    "{}",
+                    format_diagnostic(&output_config, &display_path, diagnostic)
+                ),
+            }
+        }
+        let errors: Vec<_> = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == parser::Severity::Error)
+            .collect();
+        if !errors.is_empty() {
+            fail_count += 1;
+            if errors.iter().any(|diagnostic| !diagnostic.code.is_lint()) {
+                syntax_error_count += 1;
+            }
+        } else {
+            success_count += 1;
+            if diagnostics.is_empty() && output_config.show_success && report.is_none() {
+                println!("{}", format_success(&output_config, path));
+            }
         }
     }
+
+    if let Some((baseline_path, baseline)) = &write_baseline {
+        if let Err(e) = baseline.save(baseline_path) {
+            eprintln!(
+                "Error writing baseline to '{}': {}",
+                baseline_path.display(),
+                e
+            );
+            return EXIT_IO_ERROR;
+        }
+        println!(
+            "Baseline of {} problem(s) written to {}",
+            baseline.len(),
+            baseline_path.display()
+        );
+        return 0;
+    }
+
+    if let Some(report) = &report {
+        println!("{}", report.to_json());
+    }
+    let too_many_warnings = match matches.get_one::<usize>("max-warnings") {
+        Some(&max_warnings) if warning_count > max_warnings => {
+            eprintln!(
+                "Too many warnings: {} found, {} allowed by --max-warnings",
+                warning_count, max_warnings
+            );
+            true
+        }
+        _ => false,
+    };
+    if fail_count > 0 && report.is_none() {
+        println!(
+            "{}",
+            format_summary(&output_config, success_count, fail_count, 0)
+        );
+    }
+    let error_on_warnings = matches
+        .get_many::<String>("error-on")
+        .is_some_and(|mut values| values.any(|value| value == "warnings"));
+    exit_code(
+        &[
+            (EXIT_SYNTAX_ERROR, syntax_error_count > 0),
+            (
+                EXIT_LINT_ERROR,
+                fail_count > syntax_error_count + unreadable_count,
+            ),
+            (EXIT_IO_ERROR, unreadable_count > 0),
+            (
+                EXIT_WARNINGS,
+                too_many_warnings || (error_on_warnings && warning_count > 0),
+            ),
+        ],
+        matches.get_flag("exit-zero"),
+    )
+}
+
+/// Report the statement sequences duplicated in files
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if a file cannot be read, 2 if the configuration
+///   is invalid, 0 otherwise
+fn find_duplicates(matches: &clap::ArgMatches) -> i32 {
+    let exclude_patterns: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let min_tokens = matches
+        .get_one::<usize>("min-tokens")
+        .copied()
+        .unwrap_or(50);
+
+    let config = load_config(None, false, &config_directory(None));
+
+    let mut exit_code = 0;
+    let mut finder = DuplicateFinder::new(min_tokens);
+    let files = match collect_files(matches, &exclude_patterns, &config) {
+        Ok(files) => files,
+        Err(code) => return code,
+    };
+    for path in files {
+        match file_utils::read_file_with_encoding(&path) {
+            Ok(source) => finder.add_file(&path, &source),
+            Err(e) => {
+                eprintln!("Cannot read file '{}': {}", path.display(), e);
+                exit_code = 1;
+            }
+        }
+    }
+
+    let groups = finder.clone_groups();
+    if matches.get_one::<String>("format").map(String::as_str) == Some("json") {
+        match serde_json::to_string_pretty(&groups) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing clone groups: {}", e);
+                return 1;
+            }
+        }
+        return exit_code;
+    }
+    for group in &groups {
+        println!(
+            "Clone of {} tokens found {} times:",
+            group.tokens,
+            group.occurrences.len()
+        );
+        for occurrence in &group.occurrences {
+            println!(
+                "  {}:{}-{}",
+                occurrence.path.display(),
+                occurrence.span.line,
+                occurrence.end_line
+            );
+        }
+    }
+    println!("{} clone group(s) found", groups.len());
+    exit_code
+}
+
+/// List the symbols declared in files: procedures, classes and their members,
+/// constants and global variables
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if a file cannot be read, 2 if the configuration
+///   is invalid, 0 otherwise
+fn list_symbols(matches: &clap::ArgMatches) -> i32 {
+    let exclude_patterns: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let json = matches.get_one::<String>("format").map(String::as_str) == Some("json");
+    let config = load_config(None, false, &config_directory(None));
+
+    let mut exit_code = 0;
+    let mut entries = Vec::new();
+    let files = match collect_files(matches, &exclude_patterns, &config) {
+        Ok(files) => files,
+        Err(code) => return code,
+    };
+    for path in files {
+        let source = match file_utils::read_file_with_encoding(&path) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("Cannot read file '{}': {}", path.display(), e);
+                exit_code = 1;
+                continue;
+            }
+        };
+        let table = analysis::symbols(&parser::parse_recovering(&source).program);
+        let file = path.display().to_string();
+        for symbol in &table.symbols {
+            let declared = std::iter::once((symbol, None))
+                .chain(symbol.members.iter().map(|member| (member, Some(symbol))));
+            for (symbol, class) in declared {
+                if !json {
+                    let class = class
+                        .map(|class| format!(" (in {})", class.name))
+                        .unwrap_or_default();
+                    println!(
+                        "{}:{}:{}: {}{}",
+                        file,
+                        symbol.name_span.line,
+                        symbol.name_span.column,
+                        symbol.signature(),
+                        class
+                    );
+                    continue;
+                }
+                entries.push(serde_json::json!({
+                    "file": file,
+                    "line": symbol.name_span.line,
+                    "column": symbol.name_span.column,
+                    "name": symbol.name,
+                    "kind": symbol.kind,
+                    "visibility": symbol.visibility,
+                    "parameters": symbol.parameters,
+                    "class": class.map(|class| &class.name),
+                    "signature": symbol.signature(),
+                    "span": symbol.span,
+                }));
+            }
+        }
+    }
+
+    if json {
+        match serde_json::to_string_pretty(&entries) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing symbols: {}", e);
+                return 1;
+            }
+        }
+    }
+    exit_code
+}
+
+/// Report the size and complexity metrics of files
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if a file cannot be read, 2 if the configuration
+///   is invalid, 0 otherwise
+fn print_stats(matches: &clap::ArgMatches) -> i32 {
+    let exclude_patterns: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let config = load_config(None, false, &config_directory(None));
+    let files = match collect_files(matches, &exclude_patterns, &config) {
+        Ok(files) => files,
+        Err(code) => return code,
+    };
+    let resolver = config.include_resolver(matches.get_one::<String>("web-root"));
+    let graph = IncludeGraph::build(&files, &resolver);
+
+    let mut exit_code = 0;
+    for error in &graph.errors {
+        if let IncludeError::Read { file, message } = error
+            && files
+                .iter()
+                .any(|path| graph.file(path) == graph.file(file))
+        {
+            eprintln!("Cannot read file '{}': {}", file.display(), message);
+            exit_code = 1;
+        }
+    }
+    let project = metrics::project_metrics(&graph, &files);
+
+    if matches.get_one::<String>("format").map(String::as_str) == Some("json") {
+        match serde_json::to_string_pretty(&project) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing metrics: {}", e);
+                return 1;
+            }
+        }
+        return exit_code;
+    }
+    let width = project
+        .files
+        .iter()
+        .map(|file| file.path.display().to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("Total".len());
+    println!(
+        "{:<width$}  {:>6}  {:>8}  {:>6}  {:>9}  {:>5}  {:>10}  {:>7}  {:>8}  {:>11}  {:>14}",
+        "File",
+        "Lines",
+        "VBScript",
+        "HTML",
+        "Functions",
+        "Subs",
+        "Properties",
+        "Classes",
+        "Includes",
+        "Included by",
+        "Max complexity",
+    );
+    let row = |name: &str, file: &metrics::FileMetrics| {
+        println!(
+            "{:<width$}  {:>6}  {:>8}  {:>6}  {:>9}  {:>5}  {:>10}  {:>7}  {:>8}  {:>11}  {:>14}",
+            name,
+            file.lines,
+            file.vbscript_lines,
+            file.html_lines,
+            file.functions,
+            file.subs,
+            file.properties,
+            file.classes,
+            file.includes,
+            file.included_by,
+            file.max_complexity,
+        );
+    };
+    for file in &project.files {
+        row(&file.path.display().to_string(), file);
+    }
+    row("Total", &project.total);
+    let complexity = project.total.complexity;
+    println!(
+        "Procedures by cyclomatic complexity: {} low (1-5), {} moderate (6-10), {} high (11-20), {} very high (over 20)",
+        complexity.low, complexity.moderate, complexity.high, complexity.very_high
+    );
+    exit_code
+}
+
+/// Print the parse tree of a file, or of the standard input when no file is given
+///
+/// The tree is the concrete syntax tree of the page: its nodes are named after the
+/// grammar rules and carry their spans.
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if the file cannot be read or parsed, 0 otherwise
+fn print_ast(matches: &clap::ArgMatches) -> i32 {
+    let (name, source) = match matches.get_one::<String>("file") {
+        Some(file) => match file_utils::read_file_with_encoding(Path::new(file)) {
+            Ok(source) => (file.clone(), source),
+            Err(e) => {
+                eprintln!("Cannot read file '{}': {}", file, e);
+                return 1;
+            }
+        },
+        None => {
+            let mut source = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut source) {
+                eprintln!("Error reading from stdin: {}", e);
+                return 1;
+            }
+            ("<stdin>".to_string(), source)
+        }
+    };
+    let tree = match parser::parse_cst(&source) {
+        Ok(tree) => tree,
+        Err(e) => {
+            eprintln!("{}: {}", name, e);
+            return 1;
+        }
+    };
+
+    if matches.get_one::<String>("format").map(String::as_str) == Some("json") {
+        match serde_json::to_string_pretty(&tree) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Error serializing the tree: {}", e);
+                return 1;
+            }
+        }
+    } else {
+        print!("{}", tree.dump());
+    }
+    0
+}
+
+/// Format the server code of files, or of the standard input when no file is given
+///
+/// With `--check`, files are not written: those that are not formatted are
+/// listed, with their changes if `--diff` is given.
+///
+/// # Returns
+///
+/// * `i32` - The exit code: 1 if a file cannot be read or written, or is not
+///   formatted in check mode, 2 if the configuration is invalid, 0 otherwise
+fn format_files(matches: &clap::ArgMatches) -> i32 {
+    let stdin_filename = matches.get_one::<String>("stdin-filename");
+    let config = load_config(
+        matches.get_one::<String>("config"),
+        false,
+        &config_directory(stdin_filename),
+    );
+    let casing_only = matches.get_flag("casing-only");
+    // The overrides of the configuration matching a file can change its style
+    let format = |path: &Path, source: &str| {
+        if casing_only {
+            formatter::normalize_casing(source)
+        } else {
+            formatter::format_with_options(source, &config.for_path(path).format_options())
+        }
+    };
+    let check = matches.get_flag("check");
+    let show_diff = matches.get_flag("diff");
+    if matches.get_many::<String>("files").is_none() {
+        let mut source = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut source) {
+            eprintln!("Error reading from stdin: {}", e);
+            return 1;
+        }
+        let name = stdin_filename.map_or("<stdin>", String::as_str);
+        let formatted = format(Path::new(name), &source);
+        if !check {
+            print!("{}", formatted);
+            return 0;
+        }
+        if show_diff {
+            print!("{}", formatter::diff(name, &source, &formatted));
+        }
+        return i32::from(formatted != source);
+    }
+
+    let exclude_patterns: Vec<String> = matches
+        .get_many::<String>("exclude")
+        .map(|patterns| patterns.cloned().collect())
+        .unwrap_or_default();
+    let files = match collect_files(matches, &exclude_patterns, &config) {
+        Ok(files) => files,
+        Err(code) => return code,
+    };
+    let mut exit_code = 0;
+    let mut formatted_count = 0;
+    let default_encoding = match default_encoding(matches, &config) {
+        Ok(default_encoding) => default_encoding,
+        Err(code) => return code,
+    };
+    for path in &files {
+        let (source, encoding) =
+            match file_utils::read_file_detecting_encoding(path, default_encoding) {
+                Ok(read) => read,
+                Err(e) => {
+                    eprintln!("Cannot read file '{}': {}", path.display(), e);
+                    exit_code = 1;
+                    continue;
+                }
+            };
+        let formatted = format(path, &source);
+        if formatted == source {
+            continue;
+        }
+        if check {
+            println!("Would format {}", path.display());
+            if show_diff {
+                print!(
+                    "{}",
+                    formatter::diff(&path.display().to_string(), &source, &formatted)
+                );
+            }
+            formatted_count += 1;
+            exit_code = 1;
+            continue;
+        }
+        match file_utils::write_file_in_encoding(path, &formatted, encoding) {
+            Ok(()) => {
+                println!("Formatted {}", path.display());
+                formatted_count += 1;
+            }
+            Err(e) => {
+                eprintln!("Cannot write file '{}': {}", path.display(), e);
+                exit_code = 1;
+            }
+        }
+    }
+    println!(
+        "{} of {} file(s) {}",
+        formatted_count,
+        files.len(),
+        if check {
+            "would be formatted"
+        } else {
+            "formatted"
+        }
+    );
+    exit_code
 }
 
 fn main() {
-    let app = Command::new("ASP Classic Parser")
+    let mut app = Command::new("ASP Classic Parser")
         .version(env!("CARGO_PKG_VERSION"))
         .author("Sébastien Houzé")
         .about("Parse and analyze ASP Classic files")
@@ -914,27 +1939,415 @@ fn main() {
             Command::new("upgrade")
                 .about("Upgrade to the latest version (or a specific version)")
                 .arg(
-                    Arg::new("version")
-                        .short('v')
-                        .long("version")
-                        .value_name("VERSION")
-                        .help("Specific version to upgrade to (e.g. '0.1.9')")
+                    Arg::new("version")
+                        .short('v')
+                        .long("version")
+                        .value_name("VERSION")
+                        .help("Specific version to upgrade to (e.g. '0.1.9')")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("verbose")
+                        .long("verbose")
+                        .help("Show detailed output during upgrade")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .short('f')
+                        .help("Force downgrade to an older version")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("Check files for semantic problems, such as undeclared names")
+                .arg(
+                    Arg::new("files")
+                        .help("Files or directories to check")
+                        .action(ArgAction::Append)
+                        .required_unless_present("stdin"),
+                )
+                .arg(
+                    Arg::new("stdin")
+                        .long("stdin")
+                        .short('s')
+                        .help("Check the code given on the standard input")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("files")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("stdin-filename")
+                        .long("stdin-filename")
+                        .help("Path of the file whose content is given with --stdin, reported in the results and used to resolve its includes and find the configuration files")
+                        .value_name("PATH")
+                        .requires("stdin")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .help(
+                            "Output format: ascii (default), ci (GitHub Actions), json, codeclimate (GitLab Code Quality)",
+                        )
+                        .value_name("FORMAT")
+                        .value_parser(["ascii", "ci", "json", "codeclimate", "auto"])
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("no-color")
+                        .long("no-color")
+                        .help("Disable colored output in terminal")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("quiet-success")
+                        .long("quiet-success")
+                        .help("Don't show messages for files without problems")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .short('e')
+                        .help("Comma-separated list of glob patterns to exclude. Extends the default exclusions.")
+                        .value_name("PATTERNS")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .args(scan_args())
+                .arg(
+                    Arg::new("web-root")
+                        .long("web-root")
+                        .help("Directory of the site root, for `#include virtual` directives (default: current directory)")
+                        .value_name("DIR")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .short('c')
+                        .help("Path to configuration file (TOML format), whose [rules] table sets the rules")
+                        .value_name("FILE")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("baseline")
+                        .long("baseline")
+                        .help("Only report the problems that are not in this baseline file")
+                        .value_name("FILE")
+                        .conflicts_with("write-baseline")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("write-baseline")
+                        .long("write-baseline")
+                        .help("Record the problems found in a baseline file instead of reporting them")
+                        .value_name("FILE")
                         .required(false),
                 )
                 .arg(
-                    Arg::new("verbose")
-                        .long("verbose")
-                        .help("Show detailed output during upgrade")
+                    Arg::new("max-warnings")
+                        .long("max-warnings")
+                        .help("Exit with 5 if more than N warnings are reported, even without errors")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("fail-fast")
+                        .long("fail-fast")
+                        .help("Stop checking files after the first one with an error")
                         .action(ArgAction::SetTrue)
                         .required(false),
                 )
                 .arg(
-                    Arg::new("force")
-                        .long("force")
+                    Arg::new("exit-zero")
+                        .long("exit-zero")
+                        .help("Exit with 0 even if problems are found; an invalid configuration or baseline still fails")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("error-on")
+                        .long("error-on")
+                        .help("Comma-separated list of other results failing the run with exit code 5: warnings (warnings reported)")
+                        .value_name("RESULTS")
+                        .value_parser(["warnings"])
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("duplicates")
+                .about("Find statement sequences duplicated in files, to move into includes")
+                .arg(
+                    Arg::new("files")
+                        .help("Files or directories to compare")
+                        .action(ArgAction::Append)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("min-tokens")
+                        .long("min-tokens")
+                        .help("Minimum number of tokens of a reported sequence (default: 50)")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
                         .short('f')
-                        .help("Force downgrade to an older version")
+                        .help("Output format: ascii (default), json")
+                        .value_name("FORMAT")
+                        .value_parser(["ascii", "json"])
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .short('e')
+                        .help("Comma-separated list of glob patterns to exclude. Extends the default exclusions.")
+                        .value_name("PATTERNS")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .args(scan_args()),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Report the size and complexity metrics of files")
+                .arg(
+                    Arg::new("files")
+                        .help("Files or directories to measure")
+                        .action(ArgAction::Append)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .help("Output format: ascii (default), json")
+                        .value_name("FORMAT")
+                        .value_parser(["ascii", "json"])
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("web-root")
+                        .long("web-root")
+                        .help("Directory of the site root, for `#include virtual` directives (default: current directory)")
+                        .value_name("DIR")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .short('e')
+                        .help("Comma-separated list of glob patterns to exclude. Extends the default exclusions.")
+                        .value_name("PATTERNS")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .args(scan_args()),
+        )
+        .subcommand(
+            Command::new("symbols")
+                .about("List the functions, subs, classes, properties, constants and global variables declared in files")
+                .arg(
+                    Arg::new("files")
+                        .help("Files or directories to list")
+                        .action(ArgAction::Append)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .help("Output format: ascii (default), json")
+                        .value_name("FORMAT")
+                        .value_parser(["ascii", "json"])
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .short('e')
+                        .help("Comma-separated list of glob patterns to exclude. Extends the default exclusions.")
+                        .value_name("PATTERNS")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .args(scan_args()),
+        )
+        .subcommand(
+            Command::new("ast")
+                .about("Print the parse tree of a file, with the grammar rule and span of each node")
+                .arg(
+                    Arg::new("file")
+                        .help("File to parse (default: standard input)")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .help("Output format: tree (default), json")
+                        .value_name("FORMAT")
+                        .value_parser(["tree", "json"])
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("Format the VBScript code of files in place, or of the standard input")
+                .arg(
+                    Arg::new("files")
+                        .help("Files or directories to format (default: standard input to standard output)")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .help("Don't write the files; list those that are not formatted and exit with 1 if any")
+                        .action(ArgAction::SetTrue)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("diff")
+                        .long("diff")
+                        .help("With --check, print the changes formatting would make as unified diffs")
+                        .action(ArgAction::SetTrue)
+                        .requires("check")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("stdin-filename")
+                        .long("stdin-filename")
+                        .help("Path of the file given on the standard input, used to find the configuration files and in the diff")
+                        .value_name("PATH")
+                        .conflicts_with("files")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("casing-only")
+                        .long("casing-only")
+                        .help("Only write keywords and built-in names in their usual casing (End If, Response.Write), without other changes")
                         .action(ArgAction::SetTrue)
                         .required(false),
+                )
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .short('c')
+                        .help("Path to configuration file (TOML format), whose [format] table sets the style")
+                        .value_name("FILE")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("exclude")
+                        .long("exclude")
+                        .short('e')
+                        .help("Comma-separated list of glob patterns to exclude. Extends the default exclusions.")
+                        .value_name("PATTERNS")
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .args(scan_args())
+                .arg(default_encoding_arg()),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print the completion script of a shell, to install in its completions directory")
+                .arg(
+                    Arg::new("shell")
+                        .help("Shell to complete the commands of")
+                        .value_parser(clap::value_parser!(Shell))
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("config")
+                .about("Check configuration files, or show the effective configuration")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("check")
+                        .about("Report unknown keys and invalid values of configuration files")
+                        .arg(
+                            Arg::new("file")
+                                .help("Configuration file to check; defaults to those of the current directory and its parents")
+                                .value_name("FILE")
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print the merged configuration, with the files setting each option")
+                        .arg(
+                            Arg::new("config")
+                                .long("config")
+                                .short('c')
+                                .help("Show this configuration file instead of those of the current directory and its parents")
+                                .value_name("FILE")
+                                .required(false),
+                        ),
+                )
+                .subcommand(
+                    Command::new("schema")
+                        .about("Print the JSON Schema of the configuration files, for editors"),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Inspect, prune or delete the parsing cache")
+                .subcommand_required(true)
+                .arg(cache_dir_arg().global(true))
+                .subcommand(
+                    Command::new("stats")
+                        .about("Print the number of entries, the hit rate and the size of the cache")
+                        .arg(
+                            Arg::new("format")
+                                .long("format")
+                                .help("Output format of the statistics")
+                                .value_parser(["ascii", "json"])
+                                .default_value("ascii"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("clear")
+                        .about("Delete the cache, or only its old entries")
+                        .arg(
+                            Arg::new("older-than")
+                                .long("older-than")
+                                .value_name("AGE")
+                                .help("Only remove the entries older than AGE (e.g. 30d, 12h, 45m or 90s)")
+                                .value_parser(cache::parse_duration)
+                                .required(false),
+                        ),
+                )
+                .subcommand(Command::new("path").about("Print the path of the cache file")),
+        )
+        .subcommand(
+            Command::new("explain")
+                .about("Explain a diagnostic code, or list the codes when none is given")
+                .arg(
+                    Arg::new("code")
+                        .help("Code or name of the diagnostic (e.g. ASP014 or missing-set)")
+                        .required(false),
                 ),
         )
         .subcommand(
@@ -964,6 +2377,14 @@ fn main() {
                 .required(false)
                 .conflicts_with("files"),
         )
+        .arg(
+            Arg::new("stdin-filename")
+                .long("stdin-filename")
+                .help("Path of the file whose content is given with --stdin, reported in the results and used to find the configuration files")
+                .value_name("PATH")
+                .requires("stdin")
+                .required(false),
+        )
         .arg(
             Arg::new("verbose")
                 .long("verbose")
@@ -976,12 +2397,53 @@ fn main() {
             Arg::new("format")
                 .long("format")
                 .short('f')
-                .help("Output format: ascii (default), ci (GitHub Actions), json")
+                .help(
+                    "Output format: ascii (default), ci (GitHub Actions), json, jsonl (JSON Lines on stdout), ast-json (syntax tree of each file)",
+                )
                 .value_name("FORMAT")
-                .value_parser(["ascii", "ci", "json", "auto"])
+                .value_parser(["ascii", "ci", "json", "jsonl", "ast-json", "auto"])
                 .default_missing_value("auto")
                 .required(false),
         )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .help("Write the results to FILE instead of stdout and stderr (a single report with --format json)")
+                .value_name("FILE")
+                .required(false),
+        )
+        .arg(
+            Arg::new("error-output")
+                .long("error-output")
+                .help("Write the errors and warnings to FILE, apart from the other results")
+                .value_name("FILE")
+                .required(false),
+        )
+        .arg(
+            Arg::new("fail-fast")
+                .long("fail-fast")
+                .help("Stop checking files after the first one that fails")
+                .action(ArgAction::SetTrue)
+                .required(false),
+        )
+        .arg(
+            Arg::new("exit-zero")
+                .long("exit-zero")
+                .help("Exit with 0 even if problems are found; invalid arguments still fail")
+                .action(ArgAction::SetTrue)
+                .required(false),
+        )
+        .arg(
+            Arg::new("error-on")
+                .long("error-on")
+                .help("Comma-separated list of other results failing the run with exit code 5: skipped (files skipped), warnings (warnings reported)")
+                .value_name("RESULTS")
+                .value_parser(["skipped", "warnings"])
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .required(false),
+        )
         .arg(
             Arg::new("no-color")
                 .long("no-color")
@@ -1006,6 +2468,7 @@ fn main() {
                 .action(ArgAction::Append)
                 .required(false),
         )
+        .args(scan_args())
         .arg(
             Arg::new("replace-exclude")
                 .long("replace-exclude")
@@ -1044,6 +2507,7 @@ fn main() {
                 .action(ArgAction::SetTrue)
                 .required(false),
         )
+        .arg(cache_dir_arg())
         .arg(
             Arg::new("threads")
                 .long("threads")
@@ -1052,9 +2516,18 @@ fn main() {
                 .value_name("N")
                 .value_parser(clap::value_parser!(usize))
                 .required(false),
+        )
+        .arg(default_encoding_arg())
+        .arg(
+            Arg::new("mmap")
+                .long("mmap")
+                .help("Map the files to parse in memory instead of reading them, for a lower memory use when many large files are parsed at once; the files must not be modified during the run")
+                .action(ArgAction::SetTrue)
+                .required(false),
         );
 
-    let matches = app.get_matches();
+    // The command is kept to generate the shell completions
+    let matches = app.clone().get_matches();
 
     // Handle upgrade subcommand
     if let Some(upgrade_matches) = matches.subcommand_matches("upgrade") {
@@ -1075,6 +2548,75 @@ fn main() {
         }
     }
 
+    // Handle lint subcommand
+    if let Some(lint_matches) = matches.subcommand_matches("lint") {
+        process::exit(lint_files(lint_matches));
+    }
+
+    // Handle duplicates subcommand
+    if let Some(duplicates_matches) = matches.subcommand_matches("duplicates") {
+        process::exit(find_duplicates(duplicates_matches));
+    }
+
+    // Handle stats subcommand
+    if let Some(stats_matches) = matches.subcommand_matches("stats") {
+        process::exit(print_stats(stats_matches));
+    }
+
+    // Handle symbols subcommand
+    if let Some(symbols_matches) = matches.subcommand_matches("symbols") {
+        process::exit(list_symbols(symbols_matches));
+    }
+
+    // Handle ast subcommand
+    if let Some(ast_matches) = matches.subcommand_matches("ast") {
+        process::exit(print_ast(ast_matches));
+    }
+
+    // Handle fmt subcommand
+    if let Some(fmt_matches) = matches.subcommand_matches("fmt") {
+        process::exit(format_files(fmt_matches));
+    }
+
+    // Handle completions subcommand
+    if let Some(completions_matches) = matches.subcommand_matches("completions") {
+        if let Some(&shell) = completions_matches.get_one::<Shell>("shell") {
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut app, env!("CARGO_BIN_NAME"), &mut script);
+            write_stdout(&script);
+        }
+        process::exit(0);
+    }
+
+    // Handle config subcommand
+    if let Some(config_matches) = matches.subcommand_matches("config") {
+        process::exit(match config_matches.subcommand() {
+            Some(("check", check_matches)) => check_config(check_matches),
+            Some(("show", show_matches)) => show_config(show_matches),
+            Some(("schema", _)) => print_config_schema(),
+            _ => EXIT_INVALID_CONFIG,
+        });
+    }
+
+    // Handle cache subcommand
+    if let Some(cache_matches) = matches.subcommand_matches("cache") {
+        let config = load_config(None, false, &config_directory(None));
+        process::exit(match cache_matches.subcommand() {
+            Some(("stats", stats_matches)) => print_cache_stats(stats_matches, &config),
+            Some(("clear", clear_matches)) => clear_cache(clear_matches, &config),
+            Some(("path", path_matches)) => {
+                println!("{}", cache_path(path_matches, &config).display());
+                0
+            }
+            _ => EXIT_INVALID_CONFIG,
+        });
+    }
+
+    // Handle explain subcommand
+    if let Some(explain_matches) = matches.subcommand_matches("explain") {
+        process::exit(explain_code(explain_matches.get_one::<String>("code")));
+    }
+
     // Handle init-config subcommand
     if let Some(init_config_matches) = matches.subcommand_matches("init-config") {
         let config_template = Config::default_with_comments();
@@ -1103,47 +2645,12 @@ fn main() {
     let mut args_map: HashMap<String, String> = HashMap::new();
 
     // Load configuration files
-    let mut config = Config::default();
-    let config_verbose = matches.get_flag("verbose");
-
-    // Check for explicit config file path
-    if let Some(config_path) = matches.get_one::<String>("config") {
-        let config_file_path = PathBuf::from(config_path);
-        if !config_file_path.exists() {
-            eprintln!(
-                "Warning: Configuration file '{}' does not exist",
-                config_path
-            );
-        } else {
-            match Config::from_file(&config_file_path) {
-                Ok(loaded_config) => {
-                    if config_verbose {
-                        println!("Loaded configuration from {}", config_path);
-                    }
-                    config = loaded_config;
-                }
-                Err(e) => {
-                    eprintln!("Error loading configuration from '{}': {}", config_path, e);
-                }
-            }
-        }
-    } else {
-        // Look for configuration files in the current directory and parents
-        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        let configs = Config::find_configs(&current_dir);
-
-        if !configs.is_empty() && config_verbose {
-            println!("Found {} configuration file(s)", configs.len());
-        }
-
-        // Apply configurations, starting from the most general to most specific
-        for (path, cfg) in configs {
-            if config_verbose {
-                println!("Applying configuration from {}", path.display());
-            }
-            config = cfg.merge(&config);
-        }
-    }
+    let stdin_filename = matches.get_one::<String>("stdin-filename");
+    let config = load_config(
+        matches.get_one::<String>("config"),
+        matches.get_flag("verbose"),
+        &config_directory(stdin_filename),
+    );
 
     // Apply the configuration to args_map
     config.apply_to_args(&mut args_map);
@@ -1204,19 +2711,49 @@ fn main() {
         None => OutputFormat::detect_format(),
     };
 
+    // Files the results and the problems are written to; with the JSON format,
+    // the output file receives a single report instead of a line per result
+    let output_path = matches.get_one::<String>("output").map(PathBuf::from);
+    let error_output_path = matches.get_one::<String>("error-output").map(PathBuf::from);
+    let report_path = output_path.clone().filter(|_| format == OutputFormat::Json);
+    let create_output = |path: &PathBuf| match std::fs::File::create(path) {
+        Ok(file) => Arc::new(Mutex::new(LineWriter::new(file))),
+        Err(e) => {
+            eprintln!("Error: cannot write to '{}': {}", path.display(), e);
+            process::exit(1);
+        }
+    };
+    let output = match &output_path {
+        Some(path) if report_path.is_none() => Some(create_output(path)),
+        _ => None,
+    };
+    let error_output = match (&error_output_path, &output) {
+        (Some(path), Some(output)) if Some(path) == output_path.as_ref() => Some(output.clone()),
+        (Some(path), _) => Some(create_output(path)),
+        (None, _) => None,
+    };
+    let started = Instant::now();
+
     // Create output configuration
     let output_config = OutputConfig {
         format,
-        use_colors: !no_color,
-        show_success: !quiet_success,
+        // Colors are only for terminals
+        use_colors: !no_color && output_path.is_none() && error_output_path.is_none(),
+        show_success: !quiet_success && report_path.is_none(),
+        report: report_path
+            .as_ref()
+            .map(|_| Arc::new(Mutex::new(JsonReport::new()))),
+        output,
+        error_output,
+        warnings: Arc::default(),
     };
 
     let mut paths_to_parse: Vec<PathBuf> = Vec::new();
 
     // Get list of warnings to ignore
     let ignored_warnings: Vec<String> = match matches.get_many::<String>("ignore-warnings") {
-        Some(warnings) => warnings.cloned().collect(),
-        None => Vec::new(),
+        Some(warnings) => normalize_ignored_warnings(warnings),
+        None => normalize_ignored_warnings(config.ignore_warnings.iter().flatten()),
     };
 
     if verbose {
@@ -1226,9 +2763,11 @@ fn main() {
         }
     }
 
-    // Counters for success, failures, and skipped files
+    // Counters for success, failures, and skipped files; unreadable files are
+    // also failures
     let mut success_count = 0;
     let mut fail_count = 0;
+    let mut unreadable_count = 0;
     let mut skipped_count = 0;
 
     // Prepare exclusion patterns from arguments
@@ -1275,6 +2814,8 @@ fn main() {
             eprintln!("Error: No input files or directories specified.");
             eprintln!("Usage: asp-classic-parser [FILES/DIRECTORIES...] or - (for stdin)");
             eprintln!("       asp-classic-parser --stdin");
+            eprintln!("       asp-classic-parser lint [FILES/DIRECTORIES...]");
+            eprintln!("       asp-classic-parser explain [CODE]");
             eprintln!("       asp-classic-parser upgrade [--version VERSION]");
             process::exit(1);
         }
@@ -1282,6 +2823,9 @@ fn main() {
 
     // Process all specified paths
     let mut files_to_parse = Vec::new();
+    let extensions = config.scan_extensions();
+    let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+    let scan_options = scan_options(&matches, &config).unwrap_or_else(|code| process::exit(code));
 
     for path in paths_to_parse {
         if !path.exists() {
@@ -1294,29 +2838,18 @@ fn main() {
 
         if path.is_dir() {
             // For directories, find all ASP/VBS files recursively with exclusions
-
-            // Use a specific flag to disable exclusions in test environments
-            // We can detect the test environment by the path containing a tempdir pattern
-            let mut effective_exclude = exclude_patterns.clone();
-
-            // If this path looks like a temporary directory and no explicit exclude arguments were given,
-            // add the replace-exclude flag to avoid filtering test files
-            let path_str = path.to_string_lossy().to_string();
-            if path_str.contains("/tmp/")
-                || path_str.contains("\\Temp\\")
-                || path_str.contains("\\temp\\")
-                    && !matches.contains_id("exclude")
-                    && !matches.get_flag("replace-exclude")
-            {
-                effective_exclude.push("--replace-exclude".to_string());
-                if verbose {
-                    println!("Detected temporary directory, disabling default exclusions");
-                }
-            }
-
-            match file_utils::find_asp_files(&path, &effective_exclude) {
-                Ok(found_files) => {
-                    files_to_parse.extend(found_files);
+            match file_utils::scan_files(&path, &exclude_patterns, &extensions, &scan_options) {
+                Ok(scanned) => {
+                    files_to_parse.extend(scanned.files);
+                    for (path, size) in scanned.too_large {
+                        report_file_too_large(
+                            &output_config,
+                            &path,
+                            size,
+                            &scan_options,
+                            &ignored_warnings,
+                        );
+                    }
                 }
                 Err(e) => {
                     eprintln!("Error scanning directory '{}': {}", path.display(), e);
@@ -1335,7 +2868,8 @@ fn main() {
 
     // Initialize cache if enabled
     let no_cache_flag = matches.get_flag("no-cache");
-    let cache_enabled = if no_cache_flag {
+    // The syntax tree is not cached, so every file must be parsed to print it
+    let cache_enabled = if no_cache_flag || output_config.format == OutputFormat::AstJson {
         false
     } else if let Some(cache_str) = args_map.get("cache") {
         cache_str == "true"
@@ -1344,7 +2878,10 @@ fn main() {
     };
     let mut cache = if cache_enabled {
         // Load existing cache or create a new one
-        let mut cache_obj = Cache::load();
+        let mut cache_obj = load_configured_cache(&cache_path(&matches, &config), &config)
+            .unwrap_or_else(|code| process::exit(code));
+        set_remote_cache(&mut cache_obj, &config, verbose)
+            .unwrap_or_else(|code| process::exit(code));
 
         if verbose {
             println!("Cache initialized with {} entries", cache_obj.len());
@@ -1364,49 +2901,100 @@ fn main() {
         None
     };
 
+    let default_encoding =
+        default_encoding(&matches, &config).unwrap_or_else(|code| process::exit(code));
+    let read_options = ReadOptions {
+        default_encoding,
+        mmap: matches.get_flag("mmap") || config.mmap.unwrap_or(false),
+    };
+
     // Create a hash of the options that can affect parsing results
-    let mut options_to_hash = Vec::new();
+    let hash_options = |strict_mode: bool, ignored_warnings: &[String]| {
+        let mut options_to_hash = Vec::new();
 
-    // Add key options that affect parsing results
-    options_to_hash.push(format!("strict={}", strict_mode));
+        // Add key options that affect parsing results
+        options_to_hash.push(format!("strict={}", strict_mode));
+        options_to_hash.push(format!("default_encoding={:?}", default_encoding));
 
-    if !ignored_warnings.is_empty() {
-        options_to_hash.push(format!("ignore_warnings={}", ignored_warnings.join(",")));
-    }
+        if !ignored_warnings.is_empty() {
+            options_to_hash.push(format!("ignore_warnings={}", ignored_warnings.join(",")));
+        }
 
-    // Generate the options hash
-    let options_hash = Cache::hash_options(&options_to_hash);
+        // Generate the options hash
+        Cache::hash_options(&options_to_hash)
+    };
+    let options_hash = hash_options(strict_mode, &ignored_warnings);
 
     if verbose && cache_enabled {
         println!("Using options hash: {}", options_hash);
     }
 
+    // Strict mode, ignored warnings and options hash of a file, which the
+    // overrides of the configuration matching it can change, unless given on the
+    // command line
+    let file_options = |path: &Path| {
+        if config.matching_overrides(path).is_empty() {
+            return (strict_mode, ignored_warnings.clone(), options_hash.clone());
+        }
+        let file_config = config.for_path(path);
+        let strict_mode = matches.get_flag("strict") || file_config.strict.unwrap_or(false);
+        let ignored_warnings = match matches.get_many::<String>("ignore-warnings") {
+            Some(_) => ignored_warnings.clone(),
+            None => normalize_ignored_warnings(file_config.ignore_warnings.iter().flatten()),
+        };
+        let options_hash = hash_options(strict_mode, &ignored_warnings);
+        (strict_mode, ignored_warnings, options_hash)
+    };
+
     if matches.get_flag("stdin") {
-        match parse_stdin_content(verbose, &output_config, strict_mode, &ignored_warnings) {
+        let file_started = Instant::now();
+        let path_str = stdin_filename.map_or("<stdin>", String::as_str);
+        let (strict_mode, ignored_warnings, _) = file_options(Path::new(path_str));
+        let result = parse_stdin_content(
+            path_str,
+            verbose,
+            &output_config,
+            strict_mode,
+            &ignored_warnings,
+        );
+        record_result(&output_config, path_str, &result, file_started.elapsed());
+        match result {
             ParseResult::Success => success_count += 1,
             ParseResult::Skipped => skipped_count += 1,
             ParseResult::Error => fail_count += 1,
+            ParseResult::Unreadable => {
+                fail_count += 1;
+                unreadable_count += 1;
+            }
         }
     } else {
         // Initialize thread count
-        let thread_count = matches
-            .get_one::<usize>("threads")
-            .copied()
-            .unwrap_or_else(num_cpus::get);
+        let thread_count = match matches.get_one::<usize>("threads") {
+            Some(&threads) => threads,
+            None => args_map
+                .get("threads")
+                .and_then(|threads| threads.parse().ok())
+                .unwrap_or_else(num_cpus::get),
+        };
 
         if verbose {
             println!("Using {} thread(s) for parallel processing", thread_count);
         }
 
+        // With --fail-fast, no file is started once one has failed
+        let fail_fast = matches.get_flag("fail-fast");
+        let file_count = files_to_parse.len();
+        let mut checked_count = 0;
+
         // Process in parallel or sequential mode based on thread count
         if thread_count > 1 && files_to_parse.len() > 1 {
             // Parallel processing with rayon
 
             // Create thread-safe shared resources
             let output_config_arc = Arc::new(output_config.clone());
-            let ignored_warnings_arc = Arc::new(ignored_warnings.clone());
-            let cache_arc = Arc::new(Mutex::new(cache.take()));
+            let shared_cache = cache.take().map(|cache| Arc::new(SharedCache::from(cache)));
             let output_mutex = Arc::new(Mutex::new(()));
+            let failed = AtomicBool::new(false);
 
             // Configure the thread pool with the specified number of threads
             let thread_pool = rayon::ThreadPoolBuilder::new()
@@ -1418,36 +3006,58 @@ fn main() {
             let results: Vec<ParseResult> = thread_pool.install(|| {
                 files_to_parse
                     .into_par_iter()
-                    .map(|file_path| {
-                        parse_file_parallel(
+                    .filter_map(|file_path| {
+                        if fail_fast && failed.load(Ordering::Relaxed) {
+                            return None;
+                        }
+                        let file_started = Instant::now();
+                        let path_str = file_path.display().to_string();
+                        let (strict_mode, ignored_warnings, options_hash) =
+                            file_options(&file_path);
+                        let result = parse_file_parallel(
                             file_path,
                             verbose,
                             output_config_arc.clone(),
                             strict_mode,
-                            ignored_warnings_arc.clone(),
+                            Arc::new(ignored_warnings),
                             cache_enabled,
-                            cache_arc.clone(),
-                            options_hash.clone(),
+                            shared_cache.clone(),
+                            options_hash,
                             output_mutex.clone(),
-                        )
+                            read_options,
+                        );
+                        record_result(
+                            &output_config_arc,
+                            &path_str,
+                            &result,
+                            file_started.elapsed(),
+                        );
+                        if matches!(result, ParseResult::Error | ParseResult::Unreadable) {
+                            failed.store(true, Ordering::Relaxed);
+                        }
+                        Some(result)
                     })
                     .collect()
             });
 
             // Count results
+            checked_count = results.len();
             for result in results {
                 match result {
                     ParseResult::Success => success_count += 1,
                     ParseResult::Skipped => skipped_count += 1,
                     ParseResult::Error => fail_count += 1,
+                    ParseResult::Unreadable => {
+                        fail_count += 1;
+                        unreadable_count += 1;
+                    }
                 }
             }
 
-            // Retrieve the final cache state from the Arc<Mutex<>>
-            if cache_enabled {
-                let mut cache_guard = cache_arc.lock().unwrap();
-                cache = cache_guard.take();
-            }
+            // Retrieve the final cache state, the threads being done with it
+            cache = shared_cache
+                .and_then(Arc::into_inner)
+                .map(SharedCache::into_inner);
         } else {
             // Sequential processing for a single thread or single file
             if thread_count > 1 && verbose {
@@ -1455,7 +3065,9 @@ fn main() {
             }
 
             for file_path in files_to_parse {
-                match parse_file(
+                let file_started = Instant::now();
+                let (strict_mode, ignored_warnings, options_hash) = file_options(&file_path);
+                let result = parse_file(
                     &file_path,
                     verbose,
                     &output_config,
@@ -1464,40 +3076,114 @@ fn main() {
                     cache_enabled,
                     &mut cache,
                     &options_hash,
-                ) {
+                    read_options,
+                );
+                record_result(
+                    &output_config,
+                    &file_path.display().to_string(),
+                    &result,
+                    file_started.elapsed(),
+                );
+                match result {
                     ParseResult::Success => success_count += 1,
                     ParseResult::Skipped => skipped_count += 1,
                     ParseResult::Error => fail_count += 1,
+                    ParseResult::Unreadable => {
+                        fail_count += 1;
+                        unreadable_count += 1;
+                    }
+                }
+                checked_count += 1;
+                if fail_fast && fail_count > 0 {
+                    break;
                 }
             }
         }
+
+        if fail_fast && fail_count > 0 && checked_count < file_count {
+            eprintln!(
+                "Stopped after the first failure (--fail-fast): {} of {} file(s) not checked",
+                file_count - checked_count,
+                file_count
+            );
+        }
     }
 
     // Save cache if enabled
-    if cache_enabled {
-        if let Some(ref cache_obj) = cache {
-            if let Err(e) = cache_obj.save() {
-                if verbose {
-                    eprintln!("Failed to save cache: {}", e);
-                }
-            } else if verbose {
-                println!("Cache saved with {} entries", cache_obj.len());
+    if cache_enabled && let Some(ref cache_obj) = cache {
+        if let Some(e) = cache_obj.remote_error() {
+            eprintln!("Warning: Remote cache disabled after an error: {}", e);
+        }
+        if let Err(e) = cache_obj.save() {
+            if verbose {
+                eprintln!("Failed to save cache: {}", e);
             }
+        } else if verbose {
+            println!("Cache saved with {} entries", cache_obj.len());
         }
     }
 
+    // Write the JSON report, which has the summary
+    if let (Some(report_path), Some(report)) = (&report_path, &output_config.report) {
+        let report = report.lock().unwrap().to_json(started.elapsed());
+        if let Err(e) = std::fs::write(report_path, report) {
+            eprintln!("Error writing report to '{}': {}", report_path.display(), e);
+            process::exit(EXIT_IO_ERROR);
+        }
+        if verbose {
+            println!("Report written to {}", report_path.display());
+        }
+    }
     // Report summary
     // Always show summary if there are skipped files
     // or if in verbose mode or if there were failures
-    if verbose || fail_count > 0 || skipped_count > 0 {
-        println!(
+    else if verbose
+        || fail_count > 0
+        || skipped_count > 0
+        || output_config.format == OutputFormat::Jsonl
+    {
+        print_result(
+            &output_config,
+            &format_summary(&output_config, success_count, fail_count, skipped_count),
+        );
+    }
+    // The output file has the results; the summary is also shown as progress
+    if output_path.is_some() {
+        let progress_config = OutputConfig {
+            format: OutputFormat::Ascii,
+            use_colors: false,
+            show_success: false,
+            report: None,
+            output: None,
+            error_output: None,
+            warnings: Arc::default(),
+        };
+        eprintln!(
             "{}",
-            format_summary(&output_config, success_count, fail_count, skipped_count)
+            format_summary(&progress_config, success_count, fail_count, skipped_count)
         );
     }
 
-    // Return non-zero exit code if any file failed to parse
-    if fail_count > 0 {
-        process::exit(1);
+    // Return non-zero exit code if any file failed to parse, or for the problems
+    // given to --error-on
+    let error_on: Vec<&str> = matches
+        .get_many::<String>("error-on")
+        .map(|values| values.map(String::as_str).collect())
+        .unwrap_or_default();
+    let warning_count = output_config.warnings.load(Ordering::Relaxed);
+    let code = exit_code(
+        &[
+            (EXIT_SYNTAX_ERROR, fail_count > unreadable_count),
+            (EXIT_IO_ERROR, unreadable_count > 0),
+            (
+                EXIT_WARNINGS,
+                (error_on.contains(&"skipped") && skipped_count > 0)
+                    || (error_on.contains(&"warnings") && warning_count > 0),
+            ),
+        ],
+        matches.get_flag("exit-zero"),
+    );
+    if code != 0 {
+        process::exit(code);
     }
 }
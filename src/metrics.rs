@@ -0,0 +1,230 @@
+//! Size and complexity metrics of ASP pages
+//!
+//! [`file_metrics`] measures a page: its lines of VBScript and of HTML, its
+//! procedures and classes, its include directives and the cyclomatic complexity
+//! of its procedures. [`project_metrics`] measures the pages of an
+//! [`IncludeGraph`], counting the files including each of them, and sums them up
+//! in a [`ProjectMetrics`].
+//!
+//! ```
+//! use asp_classic_parser::metrics::file_metrics;
+//! use std::path::Path;
+//!
+//! let page = "<html>\n<!--#include file=\"lib.asp\" -->\n<%\nSub Greet(name)\n  If name <> \"\" Then Response.Write name\nEnd Sub\n%>\n</html>";
+//! let metrics = file_metrics(Path::new("page.asp"), page);
+//! assert_eq!((metrics.vbscript_lines, metrics.html_lines), (5, 3));
+//! assert_eq!((metrics.subs, metrics.includes), (1, 1));
+//! assert_eq!(metrics.complexity.low, 1);
+//! ```
+
+use std::collections::BTreeSet;
+use std::ops::AddAssign;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::includes::{self, IncludeGraph};
+use crate::lint::rules::cyclomatic_complexity;
+use crate::lint::walk_statements;
+use crate::parser::ast::{LineIndex, ProcedureKind, Statement};
+use crate::parser::lexer::TokenKind;
+use crate::parser::{
+    ScriptLanguage, detect_language, normalize_line_endings, parse_recovering,
+    script_language_attribute, tokenize,
+};
+
+/// Number of procedures by range of cyclomatic complexity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ComplexityDistribution {
+    /// Complexity from 1 to 5
+    pub low: usize,
+    /// Complexity from 6 to 10
+    pub moderate: usize,
+    /// Complexity from 11 to 20
+    pub high: usize,
+    /// Complexity above 20
+    pub very_high: usize,
+}
+
+impl ComplexityDistribution {
+    /// Counts a procedure of a given complexity
+    pub fn add(&mut self, complexity: usize) {
+        match complexity {
+            0..=5 => self.low += 1,
+            6..=10 => self.moderate += 1,
+            11..=20 => self.high += 1,
+            _ => self.very_high += 1,
+        }
+    }
+}
+
+impl AddAssign for ComplexityDistribution {
+    fn add_assign(&mut self, other: Self) {
+        self.low += other.low;
+        self.moderate += other.moderate;
+        self.high += other.high;
+        self.very_high += other.very_high;
+    }
+}
+
+/// Metrics of a page
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FileMetrics {
+    /// Path of the page
+    pub path: PathBuf,
+    /// Number of lines
+    pub lines: usize,
+    /// Lines with VBScript code, comments and ASP tags included
+    pub vbscript_lines: usize,
+    /// Lines with HTML text or comments, include directives included
+    pub html_lines: usize,
+    /// Number of `Function` procedures, class methods included
+    pub functions: usize,
+    /// Number of `Sub` procedures, class methods included
+    pub subs: usize,
+    /// Number of `Property Get`, `Let` and `Set` procedures
+    pub properties: usize,
+    /// Number of classes
+    pub classes: usize,
+    /// Number of include directives (fan-out)
+    pub includes: usize,
+    /// Number of files including the page (fan-in)
+    pub included_by: usize,
+    /// Highest cyclomatic complexity of the procedures, 0 without procedure
+    pub max_complexity: usize,
+    /// Procedures by cyclomatic complexity
+    pub complexity: ComplexityDistribution,
+}
+
+/// Metrics of a set of pages
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProjectMetrics {
+    /// Metrics of each page
+    pub files: Vec<FileMetrics>,
+    /// Sums of the metrics of the pages; `path` is empty, and `max_complexity`
+    /// is the highest of all
+    pub total: FileMetrics,
+}
+
+/// Measures a page
+///
+/// A line with both server code and HTML counts as a line of VBScript and as a
+/// line of HTML; blank lines and code in other languages count as neither.
+/// [`FileMetrics::included_by`] is left at 0, the page alone not telling which
+/// files include it.
+///
+/// # Arguments
+///
+/// * `path` - The path of the page
+/// * `source` - The content of the page
+///
+/// # Returns
+///
+/// * `FileMetrics` - The metrics of the page
+pub fn file_metrics(path: &Path, source: &str) -> FileMetrics {
+    // Lines may end with a lone `\r`, counted as a line feed as when parsing
+    let text = normalize_line_endings(source);
+    let mut metrics = FileMetrics {
+        path: path.to_path_buf(),
+        lines: LineIndex::new(&text).line_count(),
+        includes: includes::directives(source).len(),
+        ..FileMetrics::default()
+    };
+
+    // Lines of each kind, by number
+    let mut vbscript_lines = BTreeSet::new();
+    let mut html_lines = BTreeSet::new();
+    let page_language = detect_language(source);
+    // Language of the current server block, `None` outside of them
+    let mut language = None;
+    for token in tokenize(source) {
+        match token.kind {
+            TokenKind::OpenTag | TokenKind::OpenExpressionTag => language = Some(page_language),
+            TokenKind::ScriptOpenTag => {
+                language = Some(script_language_attribute(token.text).unwrap_or(page_language));
+            }
+            _ => {}
+        }
+        let lines = match token.kind {
+            TokenKind::Html | TokenKind::HtmlComment => &mut html_lines,
+            TokenKind::Whitespace | TokenKind::Newline => continue,
+            _ if language == Some(ScriptLanguage::VBScript)
+                || token.kind == TokenKind::OpenDirectiveTag =>
+            {
+                &mut vbscript_lines
+            }
+            _ => continue,
+        };
+        let token_text = &text[token.span.start..token.span.end];
+        for (offset, line) in token_text.split('\n').enumerate() {
+            if !line.trim().is_empty() {
+                lines.insert(token.span.line + offset);
+            }
+        }
+        if matches!(
+            token.kind,
+            TokenKind::CloseTag | TokenKind::ScriptCloseTag | TokenKind::OpenDirectiveTag
+        ) {
+            language = None;
+        }
+    }
+    metrics.vbscript_lines = vbscript_lines.len();
+    metrics.html_lines = html_lines.len();
+
+    let program = parse_recovering(source).program;
+    walk_statements(&program.body, &mut |statement| match statement {
+        Statement::Class(_) => metrics.classes += 1,
+        Statement::Procedure(procedure) => {
+            match procedure.kind {
+                ProcedureKind::Function => metrics.functions += 1,
+                ProcedureKind::Sub => metrics.subs += 1,
+                _ => metrics.properties += 1,
+            }
+            let complexity = cyclomatic_complexity(procedure);
+            metrics.max_complexity = metrics.max_complexity.max(complexity);
+            metrics.complexity.add(complexity);
+        }
+        _ => {}
+    });
+    metrics
+}
+
+/// Measures pages of an include graph
+///
+/// The files including each page are counted among all the files of the graph,
+/// so that a page included by files that are not measured still has them.
+///
+/// # Arguments
+///
+/// * `graph` - The include graph of the pages
+/// * `paths` - The pages to measure, which must be files of the graph
+///
+/// # Returns
+///
+/// * `ProjectMetrics` - The metrics of the pages found in the graph, in the
+///   given order, and their sums
+pub fn project_metrics(graph: &IncludeGraph, paths: &[PathBuf]) -> ProjectMetrics {
+    let mut project = ProjectMetrics::default();
+    for path in paths {
+        let Some(file) = graph.file(path) else {
+            continue;
+        };
+        let mut metrics = file_metrics(path, &graph.files[file].source);
+        metrics.included_by = graph.included_by(file).collect::<BTreeSet<_>>().len();
+
+        let total = &mut project.total;
+        total.lines += metrics.lines;
+        total.vbscript_lines += metrics.vbscript_lines;
+        total.html_lines += metrics.html_lines;
+        total.functions += metrics.functions;
+        total.subs += metrics.subs;
+        total.properties += metrics.properties;
+        total.classes += metrics.classes;
+        total.includes += metrics.includes;
+        total.included_by += metrics.included_by;
+        total.max_complexity = total.max_complexity.max(metrics.max_complexity);
+        total.complexity += metrics.complexity;
+        project.files.push(metrics);
+    }
+    project
+}
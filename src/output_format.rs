@@ -1,10 +1,18 @@
+use crate::parser::ast::Program;
+use crate::parser::{Diagnostic, Severity};
 use colored::*;
 use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::env;
 use std::fmt;
-use std::io::{self, IsTerminal};
+use std::fs::File;
+use std::io::{self, IsTerminal, LineWriter};
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 /// Available output formats for parsing errors
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -15,6 +23,13 @@ pub enum OutputFormat {
     Ci,
     /// JSON format for machine processing
     Json,
+    /// JSON Lines streamed on the standard output, one self-contained object per
+    /// diagnostic and per file
+    Jsonl,
+    /// JSON format that also emits the syntax tree of every parsed file
+    AstJson,
+    /// GitLab Code Quality report, a JSON array of the problems of every file
+    CodeClimate,
 }
 
 /// Configuration for output display settings
@@ -26,6 +41,16 @@ pub struct OutputConfig {
     pub use_colors: bool,
     /// Whether to show successful file parsing messages
     pub show_success: bool,
+    /// Report collecting the results of the files instead of printing them, when
+    /// written to a file with `--output`
+    pub report: Option<Arc<Mutex<JsonReport>>>,
+    /// File the results are written to instead of the standard output and the
+    /// standard error, with `--output`
+    pub output: Option<Arc<Mutex<LineWriter<File>>>>,
+    /// File the errors and warnings are written to, with `--error-output`
+    pub error_output: Option<Arc<Mutex<LineWriter<File>>>>,
+    /// Number of warnings reported, shared by the clones of the configuration
+    pub warnings: Arc<AtomicUsize>,
 }
 
 impl OutputConfig {
@@ -53,6 +78,9 @@ impl FromStr for OutputFormat {
             "ascii" => Ok(OutputFormat::Ascii),
             "ci" => Ok(OutputFormat::Ci),
             "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::Jsonl),
+            "ast-json" => Ok(OutputFormat::AstJson),
+            "codeclimate" => Ok(OutputFormat::CodeClimate),
             "auto" => Ok(OutputFormat::detect_format()),
             _ => Err(format!("Unknown output format: {}", s)),
         }
@@ -88,14 +116,35 @@ pub fn format_success(config: &OutputConfig, path: &Path) -> String {
             };
             format!("{} {} parsed successfully", prefix, path_str)
         }
-        OutputFormat::Ci => format!("::notice file={}::Parsed successfully", path_str),
-        OutputFormat::Json => format!(
+        OutputFormat::Ci => format!(
+            "::notice file={}::Parsed successfully",
+            escape_ci_property(&path_str)
+        ),
+        OutputFormat::Jsonl => json!({
+            "type": "file",
+            "file": path_str,
+            "status": "success"
+        })
+        .to_string(),
+        OutputFormat::Json | OutputFormat::AstJson | OutputFormat::CodeClimate => format!(
             "{{\"file\": \"{}\", \"status\": \"success\"}}",
             path_str.replace('\\', "\\\\").replace('\"', "\\\"")
         ),
     }
 }
 
+/// Format the syntax tree of a successfully parsed file
+///
+/// The tree is written on a single line as `{"file": ..., "ast": ...}`, so that the
+/// output of several files can be read as JSON Lines.
+pub fn format_ast(file_path: &str, program: &Program) -> String {
+    json!({
+        "file": file_path,
+        "ast": program
+    })
+    .to_string()
+}
+
 /// Format an error message for a file
 pub fn format_error(
     config: &OutputConfig,
@@ -142,14 +191,24 @@ pub fn format_error(
             format!(
                 "::{} file={},line={},col={},title=ASP Parse {}::{}",
                 severity.to_lowercase(),
-                file_path,
+                escape_ci_property(file_path),
                 line,
                 column,
                 severity.to_uppercase(),
-                message
+                escape_ci_message(message)
             )
         }
-        OutputFormat::Json => {
+        OutputFormat::Jsonl => json!({
+            "type": "diagnostic",
+            "file": file_path,
+            "line": line,
+            "column": column,
+            "code": null,
+            "message": message,
+            "severity": severity
+        })
+        .to_string(),
+        OutputFormat::Json | OutputFormat::AstJson | OutputFormat::CodeClimate => {
             let json_error = json!({
                 "file": file_path,
                 "line": line,
@@ -162,6 +221,279 @@ pub fn format_error(
     }
 }
 
+/// Escapes the message of a GitHub Actions workflow command, which must hold on
+/// a single line
+fn escape_ci_message(message: &str) -> String {
+    message
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escapes a property (`file=...`) of a GitHub Actions workflow command
+fn escape_ci_property(value: &str) -> String {
+    escape_ci_message(value)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
+/// Format a diagnostic reported by the parser for a file
+///
+/// The diagnostic code is shown before the message (e.g. `ASP002: ...`) in the ASCII
+/// and CI formats, and as a separate `code` field in the JSON format.
+pub fn format_diagnostic(
+    config: &OutputConfig,
+    file_path: &str,
+    diagnostic: &Diagnostic,
+) -> String {
+    let line = diagnostic.span.line;
+    let column = diagnostic.span.column;
+    let severity = diagnostic.severity.as_str();
+    match config.format {
+        OutputFormat::Json | OutputFormat::AstJson => {
+            let mut json_diagnostic = diagnostic_json(diagnostic);
+            json_diagnostic["file"] = json!(file_path);
+            json_diagnostic.to_string()
+        }
+        OutputFormat::Jsonl => {
+            let mut json_diagnostic = json!({"type": "diagnostic", "file": file_path});
+            if let (Some(object), serde_json::Value::Object(fields)) =
+                (json_diagnostic.as_object_mut(), diagnostic_json(diagnostic))
+            {
+                object.extend(fields);
+            }
+            json_diagnostic.to_string()
+        }
+        OutputFormat::CodeClimate => code_quality_issue(file_path, diagnostic, 0).to_string(),
+        _ => {
+            let message = format!("{}: {}", diagnostic.code, diagnostic.message);
+            format_error(config, file_path, line, column, &message, severity)
+        }
+    }
+}
+
+/// Format the result of a file in the `jsonl` format, with its status
+/// (`success`, `failed` or `skipped`) and parsing time
+pub fn format_file_result(file_path: &str, status: &str, duration: Duration) -> String {
+    json!({
+        "type": "file",
+        "file": file_path,
+        "status": status,
+        "duration_ms": duration.as_secs_f64() * 1000.0
+    })
+    .to_string()
+}
+
+/// Convert a diagnostic to the JSON object of the JSON output format, without the
+/// `file` field
+///
+/// The JSON output and the language bindings share this schema: `line`, `column`,
+/// `code`, `message` and `severity`.
+pub fn diagnostic_json(diagnostic: &Diagnostic) -> serde_json::Value {
+    json!({
+        "line": diagnostic.span.line,
+        "column": diagnostic.span.column,
+        "code": diagnostic.code.as_str(),
+        "message": diagnostic.message,
+        "severity": diagnostic.severity.as_str()
+    })
+}
+
+/// Version of the schema of the JSON report, increased when fields are removed or
+/// change meaning
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Results of a run, written as a single JSON document by `--format json
+/// --output FILE`
+///
+/// The document has the `schema_version` of the report, the `tool` that wrote
+/// it, the `files` sorted by path, each with its `status` (`success`, `failed`
+/// or `skipped`), parsing time in `duration_ms` and `diagnostics` in the schema
+/// of the JSON output format, and a `summary` counting the files by status with
+/// the `duration_ms` of the run.
+#[derive(Debug, Clone, Default)]
+pub struct JsonReport {
+    /// Results of the files whose parsing is finished
+    files: Vec<serde_json::Value>,
+    /// Diagnostics of each file, by path
+    diagnostics: HashMap<String, Vec<serde_json::Value>>,
+}
+
+impl JsonReport {
+    /// Creates an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a problem of a file that has no diagnostic code, such as a file that
+    /// cannot be read
+    pub fn add_error(
+        &mut self,
+        file_path: &str,
+        line: usize,
+        column: usize,
+        message: &str,
+        severity: &str,
+    ) {
+        self.diagnostics
+            .entry(file_path.to_string())
+            .or_default()
+            .push(json!({
+                "line": line,
+                "column": column,
+                "code": null,
+                "message": message,
+                "severity": severity
+            }));
+    }
+
+    /// Adds a diagnostic of a file
+    pub fn add_diagnostic(&mut self, file_path: &str, diagnostic: &Diagnostic) {
+        self.diagnostics
+            .entry(file_path.to_string())
+            .or_default()
+            .push(diagnostic_json(diagnostic));
+    }
+
+    /// Records the result of a file, with the problems added for it
+    pub fn finish_file(&mut self, file_path: &str, status: &str, duration: Duration) {
+        let diagnostics = self.diagnostics.remove(file_path).unwrap_or_default();
+        self.files.push(json!({
+            "file": file_path,
+            "status": status,
+            "duration_ms": duration.as_secs_f64() * 1000.0,
+            "diagnostics": diagnostics
+        }));
+    }
+
+    /// Returns the report as a JSON document, with the duration of the run
+    pub fn to_json(&self, duration: Duration) -> String {
+        let mut files = self.files.clone();
+        files.sort_by(|a, b| a["file"].as_str().cmp(&b["file"].as_str()));
+        let count = |status: &str| files.iter().filter(|file| file["status"] == status).count();
+        let summary = json!({
+            "total": files.len(),
+            "success": count("success"),
+            "failed": count("failed"),
+            "skipped": count("skipped"),
+            "duration_ms": duration.as_secs_f64() * 1000.0
+        });
+        let report = json!({
+            "schema_version": REPORT_SCHEMA_VERSION,
+            "tool": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION")
+            },
+            "files": files,
+            "summary": summary
+        });
+        serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string())
+    }
+}
+
+/// A GitLab Code Quality report, written by the `codeclimate` output format
+///
+/// The problems of the checked files are collected, then written as a single
+/// JSON array. The fingerprint of a problem hashes its file, code and message and
+/// its rank among the problems of the file with the same code and message, but
+/// not its position, so that merge requests tell new problems from existing ones
+/// when lines move.
+#[derive(Debug, Clone, Default)]
+pub struct CodeQualityReport {
+    issues: Vec<serde_json::Value>,
+    /// Number of problems of each file, code and message
+    occurrences: HashMap<(String, String, String), usize>,
+}
+
+impl CodeQualityReport {
+    /// Creates an empty report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a problem of a file
+    pub fn add(&mut self, file_path: &str, diagnostic: &Diagnostic) {
+        let path = report_path(file_path);
+        let occurrence = self
+            .occurrences
+            .entry((
+                path.clone(),
+                diagnostic.code.as_str().to_string(),
+                diagnostic.message.clone(),
+            ))
+            .or_insert(0);
+        self.issues
+            .push(code_quality_issue(&path, diagnostic, *occurrence));
+        *occurrence += 1;
+    }
+
+    /// Returns the number of problems of the report
+    pub fn len(&self) -> usize {
+        self.issues.len()
+    }
+
+    /// Returns whether the report has no problem
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Returns the report as a JSON array
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.issues).unwrap_or_else(|_| "[]".to_string())
+    }
+}
+
+/// Returns the path of a file as written in a Code Quality report: relative to
+/// the current directory, with `/` separators
+fn report_path(file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let path = env::current_dir()
+        .ok()
+        .and_then(|current_dir| path.strip_prefix(current_dir).ok())
+        .unwrap_or(path);
+    let path = path.to_string_lossy().replace('\\', "/");
+    path.strip_prefix("./").unwrap_or(&path).to_string()
+}
+
+/// Convert a diagnostic to an issue of a Code Quality report
+///
+/// `occurrence` is the rank of the diagnostic among those of the file with the
+/// same code and message, hashed in the fingerprint.
+pub fn code_quality_issue(
+    file_path: &str,
+    diagnostic: &Diagnostic,
+    occurrence: usize,
+) -> serde_json::Value {
+    let mut hasher = Sha256::new();
+    hasher.update(
+        format!(
+            "{}\n{}\n{}\n{}",
+            file_path,
+            diagnostic.code.as_str(),
+            diagnostic.message,
+            occurrence
+        )
+        .as_bytes(),
+    );
+    let (severity, category) = match diagnostic.severity {
+        Severity::Error => ("major", "Bug Risk"),
+        Severity::Warning => ("minor", "Bug Risk"),
+        Severity::Notice => ("info", "Style"),
+    };
+    json!({
+        "type": "issue",
+        "check_name": diagnostic.code.name(),
+        "description": format!("{}: {}", diagnostic.code, diagnostic.message),
+        "categories": [category],
+        "severity": severity,
+        "fingerprint": format!("{:x}", hasher.finalize()),
+        "location": {
+            "path": file_path,
+            "lines": {"begin": diagnostic.span.line}
+        }
+    })
+}
+
 /// Format a summary message at the end of parsing
 pub fn format_summary(
     config: &OutputConfig,
@@ -221,7 +553,15 @@ pub fn format_summary(
 
             summary
         }
-        OutputFormat::Json => {
+        OutputFormat::Jsonl => json!({
+            "type": "summary",
+            "total": success_count + fail_count + skipped_count,
+            "success": success_count,
+            "failed": fail_count,
+            "skipped": skipped_count
+        })
+        .to_string(),
+        OutputFormat::Json | OutputFormat::AstJson | OutputFormat::CodeClimate => {
             format!(
                 "{{\"summary\": {{\"total\": {}, \"success\": {}, \"failed\": {}, \"skipped\": {}, \"skipped_reason\": \"no ASP tags\"}}}}",
                 success_count + fail_count + skipped_count,
@@ -239,6 +579,9 @@ impl fmt::Display for OutputFormat {
             OutputFormat::Ascii => write!(f, "ascii"),
             OutputFormat::Ci => write!(f, "ci"),
             OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Jsonl => write!(f, "jsonl"),
+            OutputFormat::AstJson => write!(f, "ast-json"),
+            OutputFormat::CodeClimate => write!(f, "codeclimate"),
         }
     }
 }
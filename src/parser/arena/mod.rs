@@ -0,0 +1,634 @@
+//! Syntax tree allocated in an arena and borrowing its text from the page
+//!
+//! [`parse_in`](super::parse_in) builds the same tree as
+//! [`parse_to_ast`](super::parse_to_ast), but its nodes are allocated in a [`Bump`]
+//! arena, and the names, markup and literals are slices of the page instead of
+//! owned strings. Building the tree then takes a few large allocations instead of
+//! several per node, and dropping it frees the whole arena at once, which matters
+//! for pages of several megabytes parsed only to be checked or scanned once.
+//!
+//! The node types mirror those of the [`ast`](super::ast) module, with `&'a`
+//! references in place of boxes, vectors and strings, and serialize to the same
+//! JSON. Only two cases copy text into the arena: string literals with `""`
+//! escapes, and pages with classic Mac line endings (a lone `\r`).
+//!
+//! ```
+//! use asp_classic_parser::parser::{self, arena::{Bump, Expression, Statement}};
+//!
+//! let page = "<% Response.Write \"Hello\" %>";
+//! let arena = Bump::new();
+//! let program = parser::parse_in(page, &arena).unwrap();
+//! let Statement::Call(call) = program.body[0] else {
+//!     panic!("expected a call");
+//! };
+//! let Expression::Member(member) = call.target else {
+//!     panic!("expected a member access");
+//! };
+//! assert_eq!(member.member.name, "Write");
+//! assert!(page.as_bytes().as_ptr_range().contains(&member.member.name.as_ptr()));
+//! ```
+
+mod owned;
+
+pub use bumpalo::Bump;
+use serde::Serialize;
+
+use super::ScriptLanguage;
+use super::ast::{
+    AssignmentKind, BinaryOperator, ExitKind, LoopConditionKind, OnErrorAction, ParameterPassing,
+    ProcedureKind, Span, UnaryOperator, Visibility,
+};
+
+/// A parsed ASP page
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Program<'a> {
+    /// Server-side language of the page
+    pub language: ScriptLanguage,
+    /// Top-level statements, including markup, in source order
+    pub body: &'a [Statement<'a>],
+    /// Span of the whole page
+    pub span: Span,
+}
+
+/// A name as written in the source (without brackets for `[escaped names]`)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Identifier<'a> {
+    /// The name
+    pub name: &'a str,
+    /// Location of the name
+    pub span: Span,
+}
+
+/// A statement, or a piece of markup between statements
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Statement<'a> {
+    /// HTML text or comment outside of ASP blocks
+    Html(Html<'a>),
+    /// `<%= expression %>` output block
+    Output(Output<'a>),
+    /// `<%@ ... %>` processing directive
+    Directive(Directive<'a>),
+    /// `<script runat="server">` block
+    ServerScript(ServerScript<'a>),
+    /// `Option Explicit`
+    OptionExplicit {
+        /// Location of the statement
+        span: Span,
+    },
+    /// `Dim`, or `Public`/`Private` variable declarations
+    Dim(DimStatement<'a>),
+    /// `ReDim [Preserve]`
+    ReDim(ReDimStatement<'a>),
+    /// `Const` declarations
+    Const(ConstStatement<'a>),
+    /// `Erase` of arrays
+    Erase(EraseStatement<'a>),
+    /// `[Let] target = value` or `Set target = value`
+    Assignment(Assignment<'a>),
+    /// Sub or method call, with or without the `Call` keyword
+    Call(CallStatement<'a>),
+    /// `If` block or single-line `If`
+    If(IfStatement<'a>),
+    /// `Select Case` block
+    Select(SelectStatement<'a>),
+    /// `For counter = start To end [Step step]` loop
+    For(ForStatement<'a>),
+    /// `For Each item In collection` loop
+    ForEach(ForEachStatement<'a>),
+    /// `Do [While|Until] ... Loop [While|Until]` loop
+    DoLoop(DoLoopStatement<'a>),
+    /// `While ... Wend` loop
+    While(WhileStatement<'a>),
+    /// `With object` block
+    With(WithStatement<'a>),
+    /// `Sub`, `Function` or `Property` definition
+    Procedure(Procedure<'a>),
+    /// `Class` definition
+    Class(ClassDeclaration<'a>),
+    /// `Exit Do`, `Exit For`, `Exit Sub`, ...
+    Exit(ExitStatement),
+    /// `On Error Resume Next` or `On Error GoTo 0`
+    OnError(OnErrorStatement),
+    /// `Stop`
+    Stop {
+        /// Location of the statement
+        span: Span,
+    },
+    /// `Randomize [seed]`
+    Randomize(RandomizeStatement<'a>),
+    /// Code the parser does not understand, kept as text (e.g. JScript code)
+    Unparsed(Unparsed<'a>),
+}
+
+impl Statement<'_> {
+    /// Returns the location of the statement
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Html(node) => node.span,
+            Statement::Output(node) => node.span,
+            Statement::Directive(node) => node.span,
+            Statement::ServerScript(node) => node.span,
+            Statement::OptionExplicit { span } | Statement::Stop { span } => *span,
+            Statement::Dim(node) => node.span,
+            Statement::ReDim(node) => node.span,
+            Statement::Const(node) => node.span,
+            Statement::Erase(node) => node.span,
+            Statement::Assignment(node) => node.span,
+            Statement::Call(node) => node.span,
+            Statement::If(node) => node.span,
+            Statement::Select(node) => node.span,
+            Statement::For(node) => node.span,
+            Statement::ForEach(node) => node.span,
+            Statement::DoLoop(node) => node.span,
+            Statement::While(node) => node.span,
+            Statement::With(node) => node.span,
+            Statement::Procedure(node) => node.span,
+            Statement::Class(node) => node.span,
+            Statement::Exit(node) => node.span,
+            Statement::OnError(node) => node.span,
+            Statement::Randomize(node) => node.span,
+            Statement::Unparsed(node) => node.span,
+        }
+    }
+}
+
+/// HTML text or comment
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Html<'a> {
+    /// The markup, as written in the source
+    pub text: &'a str,
+    /// Location of the markup
+    pub span: Span,
+}
+
+/// `<%= expression %>` output block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Output<'a> {
+    /// The written expression
+    pub value: Expression<'a>,
+    /// Location of the whole block, delimiters included
+    pub span: Span,
+}
+
+/// A single `Name=Value` attribute of a processing directive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DirectiveAttribute<'a> {
+    /// Attribute name as written in the source (e.g. `Language`)
+    pub name: &'a str,
+    /// Attribute value without surrounding quotes (e.g. `VBScript`)
+    pub value: &'a str,
+}
+
+/// `<%@ ... %>` processing directive
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Directive<'a> {
+    /// The `Name=Value` attributes of the directive
+    pub attributes: &'a [DirectiveAttribute<'a>],
+    /// Location of the whole directive, delimiters included
+    pub span: Span,
+}
+
+/// `<script runat="server">` block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ServerScript<'a> {
+    /// Language of the block (from its `language` attribute, or the page language)
+    pub language: ScriptLanguage,
+    /// Statements of the block
+    pub body: &'a [Statement<'a>],
+    /// Location of the whole block, tags included
+    pub span: Span,
+}
+
+/// A declared variable, optionally an array
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct VariableDeclarator<'a> {
+    /// Name of the variable
+    pub name: Identifier<'a>,
+    /// Array bounds: `None` for a scalar, an empty list for a dynamic array (`a()`)
+    pub bounds: Option<&'a [Expression<'a>]>,
+    /// Location of the declarator
+    pub span: Span,
+}
+
+/// `Dim a, b(10)` or `Public a` / `Private b` declarations
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DimStatement<'a> {
+    /// `None` for `Dim`, the visibility for `Public`/`Private` declarations
+    pub visibility: Option<Visibility>,
+    /// Declared variables
+    pub variables: &'a [VariableDeclarator<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `ReDim [Preserve] a(10)`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ReDimStatement<'a> {
+    /// Whether `Preserve` was specified
+    pub preserve: bool,
+    /// Resized arrays
+    pub variables: &'a [VariableDeclarator<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A single `Name = value` constant
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ConstantDeclarator<'a> {
+    /// Name of the constant
+    pub name: Identifier<'a>,
+    /// Value of the constant
+    pub value: Expression<'a>,
+    /// Location of the declarator
+    pub span: Span,
+}
+
+/// `[Public|Private] Const A = 1, B = 2`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ConstStatement<'a> {
+    /// Visibility, if specified
+    pub visibility: Option<Visibility>,
+    /// Declared constants
+    pub constants: &'a [ConstantDeclarator<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `Erase a, b`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EraseStatement<'a> {
+    /// Erased arrays
+    pub arrays: &'a [Identifier<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `[Let] target = value` or `Set target = value`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Assignment<'a> {
+    /// Value or object assignment
+    pub kind: AssignmentKind,
+    /// Assigned variable, property or array element
+    pub target: Expression<'a>,
+    /// Assigned value
+    pub value: Expression<'a>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Sub or method call statement
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CallStatement<'a> {
+    /// Called sub or method (e.g. `Response.Write`)
+    pub target: Expression<'a>,
+    /// Arguments; `None` for an omitted optional argument (`Foo a, , c`)
+    pub arguments: &'a [Option<Expression<'a>>],
+    /// Whether the `Call` keyword was used
+    pub explicit: bool,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// An `ElseIf condition Then` branch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ElseIfBranch<'a> {
+    /// Condition of the branch
+    pub condition: Expression<'a>,
+    /// Statements of the branch
+    pub body: &'a [Statement<'a>],
+    /// Location of the branch, from `ElseIf` to the next branch or `End If`
+    pub span: Span,
+}
+
+/// `If` block or single-line `If`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct IfStatement<'a> {
+    /// Condition of the `If`
+    pub condition: Expression<'a>,
+    /// Statements executed when the condition is true
+    pub then_branch: &'a [Statement<'a>],
+    /// `ElseIf` branches
+    pub else_if_branches: &'a [ElseIfBranch<'a>],
+    /// Statements of the `Else` branch, if any
+    pub else_branch: Option<&'a [Statement<'a>]>,
+    /// Whether this is a single-line `If` (`If x Then y Else z`)
+    pub single_line: bool,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A `Case value1, value2` clause
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CaseClause<'a> {
+    /// Values matched by the clause
+    pub values: &'a [Expression<'a>],
+    /// Statements of the clause
+    pub body: &'a [Statement<'a>],
+    /// Location of the clause
+    pub span: Span,
+}
+
+/// `Select Case` block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SelectStatement<'a> {
+    /// Tested expression
+    pub subject: Expression<'a>,
+    /// `Case` clauses
+    pub cases: &'a [CaseClause<'a>],
+    /// Statements of the `Case Else` clause, if any
+    pub else_branch: Option<&'a [Statement<'a>]>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `For counter = start To end [Step step]` loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ForStatement<'a> {
+    /// Loop counter
+    pub variable: Identifier<'a>,
+    /// Initial value
+    pub start: Expression<'a>,
+    /// Final value
+    pub end: Expression<'a>,
+    /// Increment, if specified
+    pub step: Option<Expression<'a>>,
+    /// Loop body
+    pub body: &'a [Statement<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `For Each item In collection` loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ForEachStatement<'a> {
+    /// Loop variable
+    pub variable: Identifier<'a>,
+    /// Iterated collection or array
+    pub collection: Expression<'a>,
+    /// Loop body
+    pub body: &'a [Statement<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `While condition` or `Until condition` of a `Do` loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LoopCondition<'a> {
+    /// `While` or `Until`
+    pub kind: LoopConditionKind,
+    /// The condition
+    pub condition: Expression<'a>,
+    /// Location of the condition, keyword included
+    pub span: Span,
+}
+
+/// `Do ... Loop` loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DoLoopStatement<'a> {
+    /// Condition tested before each iteration (`Do While x`)
+    pub pre_condition: Option<LoopCondition<'a>>,
+    /// Condition tested after each iteration (`Loop Until x`)
+    pub post_condition: Option<LoopCondition<'a>>,
+    /// Loop body
+    pub body: &'a [Statement<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `While condition ... Wend` loop
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WhileStatement<'a> {
+    /// Loop condition
+    pub condition: Expression<'a>,
+    /// Loop body
+    pub body: &'a [Statement<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `With object ... End With` block
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct WithStatement<'a> {
+    /// Object referenced by `.Member` expressions in the body
+    pub object: Expression<'a>,
+    /// Statements of the block
+    pub body: &'a [Statement<'a>],
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A procedure parameter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Parameter<'a> {
+    /// Name of the parameter
+    pub name: Identifier<'a>,
+    /// Passing mode, if specified
+    pub passing: Option<ParameterPassing>,
+    /// Whether the parameter is declared as an array (`values()`)
+    pub is_array: bool,
+    /// Location of the parameter
+    pub span: Span,
+}
+
+/// `Sub`, `Function` or `Property` definition
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Procedure<'a> {
+    /// Kind of procedure
+    pub kind: ProcedureKind,
+    /// Name of the procedure
+    pub name: Identifier<'a>,
+    /// Visibility, if specified
+    pub visibility: Option<Visibility>,
+    /// Whether the procedure is the `Default` member of its class
+    pub is_default: bool,
+    /// Parameters
+    pub parameters: &'a [Parameter<'a>],
+    /// Statements of the procedure
+    pub body: &'a [Statement<'a>],
+    /// Location of the definition, from the header to the `End` line
+    pub span: Span,
+}
+
+/// `Class` definition
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ClassDeclaration<'a> {
+    /// Name of the class
+    pub name: Identifier<'a>,
+    /// Members of the class (variables, constants and procedures)
+    pub body: &'a [Statement<'a>],
+    /// Location of the definition
+    pub span: Span,
+}
+
+/// `Exit Do`, `Exit For`, `Exit Function`, `Exit Sub` or `Exit Property`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ExitStatement {
+    /// Exited block
+    pub kind: ExitKind,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `On Error Resume Next` or `On Error GoTo 0`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OnErrorStatement {
+    /// Selected error handling mode
+    pub action: OnErrorAction,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `Randomize [seed]`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RandomizeStatement<'a> {
+    /// Seed, if specified
+    pub seed: Option<Expression<'a>>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Source text the parser does not understand
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Unparsed<'a> {
+    /// The text, as written in the source
+    pub text: &'a str,
+    /// Location of the text
+    pub span: Span,
+}
+
+/// An expression
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Expression<'a> {
+    /// String, number, date, boolean, `Nothing`, `Null` or `Empty`
+    Literal(Literal<'a>),
+    /// Variable, constant or procedure name
+    Identifier(Identifier<'a>),
+    /// `object.Member`, or `.Member` inside a `With` block
+    Member(MemberExpression<'a>),
+    /// Call or array indexing: `Foo(1)`, `arr(0)`, `rs.Fields("name")`
+    Call(CallExpression<'a>),
+    /// `-x`, `+x` or `Not x`
+    Unary(UnaryExpression<'a>),
+    /// Binary operation
+    Binary(BinaryExpression<'a>),
+    /// `(expression)`
+    Parenthesized(ParenthesizedExpression<'a>),
+    /// `New ClassName`
+    New(NewExpression<'a>),
+    /// Expression the parser does not understand, kept as text
+    Unparsed(Unparsed<'a>),
+}
+
+impl Expression<'_> {
+    /// Returns the location of the expression
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal(node) => node.span,
+            Expression::Identifier(node) => node.span,
+            Expression::Member(node) => node.span,
+            Expression::Call(node) => node.span,
+            Expression::Unary(node) => node.span,
+            Expression::Binary(node) => node.span,
+            Expression::Parenthesized(node) => node.span,
+            Expression::New(node) => node.span,
+            Expression::Unparsed(node) => node.span,
+        }
+    }
+}
+
+/// Value of a literal
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum LiteralValue<'a> {
+    /// String, with `""` escapes resolved
+    String(&'a str),
+    /// Integer, including `&H` hexadecimal and `&O` octal literals
+    Integer(i64),
+    /// Floating-point number
+    Float(f64),
+    /// Date literal, without the surrounding `#`
+    Date(&'a str),
+    /// `True` or `False`
+    Boolean(bool),
+    /// `Nothing`
+    Nothing,
+    /// `Null`
+    Null,
+    /// `Empty`
+    Empty,
+}
+
+/// A literal value
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Literal<'a> {
+    /// The value
+    pub value: LiteralValue<'a>,
+    /// Location of the literal
+    pub span: Span,
+}
+
+/// `object.Member`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MemberExpression<'a> {
+    /// Accessed object; `None` for `.Member` inside a `With` block
+    pub object: Option<&'a Expression<'a>>,
+    /// Name of the member
+    pub member: Identifier<'a>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Call or array indexing
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CallExpression<'a> {
+    /// Called function or indexed array
+    pub callee: &'a Expression<'a>,
+    /// Arguments; `None` for an omitted optional argument
+    pub arguments: &'a [Option<Expression<'a>>],
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Unary operation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct UnaryExpression<'a> {
+    /// The operator
+    pub operator: UnaryOperator,
+    /// The operand
+    pub operand: &'a Expression<'a>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Binary operation
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BinaryExpression<'a> {
+    /// Left operand
+    pub left: &'a Expression<'a>,
+    /// The operator
+    pub operator: BinaryOperator,
+    /// Right operand
+    pub right: &'a Expression<'a>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// `(expression)`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ParenthesizedExpression<'a> {
+    /// The inner expression
+    pub expression: &'a Expression<'a>,
+    /// Location of the expression, parentheses included
+    pub span: Span,
+}
+
+/// `New ClassName`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct NewExpression<'a> {
+    /// Instantiated class
+    pub class_name: Identifier<'a>,
+    /// Location of the expression
+    pub span: Span,
+}
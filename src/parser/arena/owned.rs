@@ -0,0 +1,298 @@
+//! Conversion of the arena syntax tree into the owned one
+//!
+//! The [builder](super::super::builder) only builds arena trees; the owned tree
+//! returned by [`parse_to_ast`](super::super::parse_to_ast) and
+//! [`parse_recovering`](super::super::parse_recovering) is copied from it node by
+//! node before the arena is dropped.
+
+use super::*;
+use crate::parser::ast;
+
+/// Converts a slice of arena nodes into a vector of owned nodes
+fn owned<T: Copy, U: From<T>>(nodes: &[T]) -> Vec<U> {
+    nodes.iter().map(|&node| node.into()).collect()
+}
+
+/// Converts an arena expression into a boxed owned expression
+fn boxed(expression: &Expression<'_>) -> Box<ast::Expression> {
+    Box::new((*expression).into())
+}
+
+impl From<Program<'_>> for ast::Program {
+    fn from(program: Program<'_>) -> Self {
+        ast::Program {
+            language: program.language,
+            body: owned(program.body),
+            span: program.span,
+        }
+    }
+}
+
+impl From<Identifier<'_>> for ast::Identifier {
+    fn from(identifier: Identifier<'_>) -> Self {
+        ast::Identifier {
+            name: identifier.name.to_string(),
+            span: identifier.span,
+        }
+    }
+}
+
+impl From<Statement<'_>> for ast::Statement {
+    fn from(statement: Statement<'_>) -> Self {
+        match statement {
+            Statement::Html(node) => ast::Statement::Html(ast::Html {
+                text: node.text.to_string(),
+                span: node.span,
+            }),
+            Statement::Output(node) => ast::Statement::Output(ast::Output {
+                value: node.value.into(),
+                span: node.span,
+            }),
+            Statement::Directive(node) => ast::Statement::Directive(ast::Directive {
+                attributes: node
+                    .attributes
+                    .iter()
+                    .map(|attribute| crate::parser::DirectiveAttribute {
+                        name: attribute.name.to_string(),
+                        value: attribute.value.to_string(),
+                    })
+                    .collect(),
+                span: node.span,
+            }),
+            Statement::ServerScript(node) => ast::Statement::ServerScript(ast::ServerScript {
+                language: node.language,
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::OptionExplicit { span } => ast::Statement::OptionExplicit { span },
+            Statement::Dim(node) => ast::Statement::Dim(ast::DimStatement {
+                visibility: node.visibility,
+                variables: owned(node.variables),
+                span: node.span,
+            }),
+            Statement::ReDim(node) => ast::Statement::ReDim(ast::ReDimStatement {
+                preserve: node.preserve,
+                variables: owned(node.variables),
+                span: node.span,
+            }),
+            Statement::Const(node) => ast::Statement::Const(ast::ConstStatement {
+                visibility: node.visibility,
+                constants: owned(node.constants),
+                span: node.span,
+            }),
+            Statement::Erase(node) => ast::Statement::Erase(ast::EraseStatement {
+                arrays: owned(node.arrays),
+                span: node.span,
+            }),
+            Statement::Assignment(node) => ast::Statement::Assignment(ast::Assignment {
+                kind: node.kind,
+                target: node.target.into(),
+                value: node.value.into(),
+                span: node.span,
+            }),
+            Statement::Call(node) => ast::Statement::Call(ast::CallStatement {
+                target: node.target.into(),
+                arguments: arguments(node.arguments),
+                explicit: node.explicit,
+                span: node.span,
+            }),
+            Statement::If(node) => ast::Statement::If(ast::IfStatement {
+                condition: node.condition.into(),
+                then_branch: owned(node.then_branch),
+                else_if_branches: node
+                    .else_if_branches
+                    .iter()
+                    .map(|branch| ast::ElseIfBranch {
+                        condition: branch.condition.into(),
+                        body: owned(branch.body),
+                        span: branch.span,
+                    })
+                    .collect(),
+                else_branch: node.else_branch.map(owned),
+                single_line: node.single_line,
+                span: node.span,
+            }),
+            Statement::Select(node) => ast::Statement::Select(ast::SelectStatement {
+                subject: node.subject.into(),
+                cases: node
+                    .cases
+                    .iter()
+                    .map(|case| ast::CaseClause {
+                        values: owned(case.values),
+                        body: owned(case.body),
+                        span: case.span,
+                    })
+                    .collect(),
+                else_branch: node.else_branch.map(owned),
+                span: node.span,
+            }),
+            Statement::For(node) => ast::Statement::For(ast::ForStatement {
+                variable: node.variable.into(),
+                start: node.start.into(),
+                end: node.end.into(),
+                step: node.step.map(Into::into),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::ForEach(node) => ast::Statement::ForEach(ast::ForEachStatement {
+                variable: node.variable.into(),
+                collection: node.collection.into(),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::DoLoop(node) => ast::Statement::DoLoop(ast::DoLoopStatement {
+                pre_condition: node.pre_condition.map(Into::into),
+                post_condition: node.post_condition.map(Into::into),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::While(node) => ast::Statement::While(ast::WhileStatement {
+                condition: node.condition.into(),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::With(node) => ast::Statement::With(ast::WithStatement {
+                object: node.object.into(),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::Procedure(node) => ast::Statement::Procedure(ast::Procedure {
+                kind: node.kind,
+                name: node.name.into(),
+                visibility: node.visibility,
+                is_default: node.is_default,
+                parameters: node
+                    .parameters
+                    .iter()
+                    .map(|parameter| ast::Parameter {
+                        name: parameter.name.into(),
+                        passing: parameter.passing,
+                        is_array: parameter.is_array,
+                        span: parameter.span,
+                    })
+                    .collect(),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::Class(node) => ast::Statement::Class(ast::ClassDeclaration {
+                name: node.name.into(),
+                body: owned(node.body),
+                span: node.span,
+            }),
+            Statement::Exit(node) => ast::Statement::Exit(ast::ExitStatement {
+                kind: node.kind,
+                span: node.span,
+            }),
+            Statement::OnError(node) => ast::Statement::OnError(ast::OnErrorStatement {
+                action: node.action,
+                span: node.span,
+            }),
+            Statement::Stop { span } => ast::Statement::Stop { span },
+            Statement::Randomize(node) => ast::Statement::Randomize(ast::RandomizeStatement {
+                seed: node.seed.map(Into::into),
+                span: node.span,
+            }),
+            Statement::Unparsed(node) => ast::Statement::Unparsed(node.into()),
+        }
+    }
+}
+
+impl From<VariableDeclarator<'_>> for ast::VariableDeclarator {
+    fn from(declarator: VariableDeclarator<'_>) -> Self {
+        ast::VariableDeclarator {
+            name: declarator.name.into(),
+            bounds: declarator.bounds.map(owned),
+            span: declarator.span,
+        }
+    }
+}
+
+impl From<ConstantDeclarator<'_>> for ast::ConstantDeclarator {
+    fn from(declarator: ConstantDeclarator<'_>) -> Self {
+        ast::ConstantDeclarator {
+            name: declarator.name.into(),
+            value: declarator.value.into(),
+            span: declarator.span,
+        }
+    }
+}
+
+impl From<LoopCondition<'_>> for ast::LoopCondition {
+    fn from(condition: LoopCondition<'_>) -> Self {
+        ast::LoopCondition {
+            kind: condition.kind,
+            condition: condition.condition.into(),
+            span: condition.span,
+        }
+    }
+}
+
+impl From<Unparsed<'_>> for ast::Unparsed {
+    fn from(unparsed: Unparsed<'_>) -> Self {
+        ast::Unparsed {
+            text: unparsed.text.to_string(),
+            span: unparsed.span,
+        }
+    }
+}
+
+impl From<Expression<'_>> for ast::Expression {
+    fn from(expression: Expression<'_>) -> Self {
+        match expression {
+            Expression::Literal(node) => ast::Expression::Literal(ast::Literal {
+                value: match node.value {
+                    LiteralValue::String(value) => ast::LiteralValue::String(value.to_string()),
+                    LiteralValue::Integer(value) => ast::LiteralValue::Integer(value),
+                    LiteralValue::Float(value) => ast::LiteralValue::Float(value),
+                    LiteralValue::Date(value) => ast::LiteralValue::Date(value.to_string()),
+                    LiteralValue::Boolean(value) => ast::LiteralValue::Boolean(value),
+                    LiteralValue::Nothing => ast::LiteralValue::Nothing,
+                    LiteralValue::Null => ast::LiteralValue::Null,
+                    LiteralValue::Empty => ast::LiteralValue::Empty,
+                },
+                span: node.span,
+            }),
+            Expression::Identifier(node) => ast::Expression::Identifier(node.into()),
+            Expression::Member(node) => ast::Expression::Member(ast::MemberExpression {
+                object: node.object.map(boxed),
+                member: node.member.into(),
+                span: node.span,
+            }),
+            Expression::Call(node) => ast::Expression::Call(ast::CallExpression {
+                callee: boxed(node.callee),
+                arguments: arguments(node.arguments),
+                span: node.span,
+            }),
+            Expression::Unary(node) => ast::Expression::Unary(ast::UnaryExpression {
+                operator: node.operator,
+                operand: boxed(node.operand),
+                span: node.span,
+            }),
+            Expression::Binary(node) => ast::Expression::Binary(ast::BinaryExpression {
+                left: boxed(node.left),
+                operator: node.operator,
+                right: boxed(node.right),
+                span: node.span,
+            }),
+            Expression::Parenthesized(node) => {
+                ast::Expression::Parenthesized(ast::ParenthesizedExpression {
+                    expression: boxed(node.expression),
+                    span: node.span,
+                })
+            }
+            Expression::New(node) => ast::Expression::New(ast::NewExpression {
+                class_name: node.class_name.into(),
+                span: node.span,
+            }),
+            Expression::Unparsed(node) => ast::Expression::Unparsed(node.into()),
+        }
+    }
+}
+
+/// Converts the arguments of a call, keeping the omitted ones
+fn arguments(arguments: &[Option<Expression<'_>>]) -> Vec<Option<ast::Expression>> {
+    arguments
+        .iter()
+        .map(|argument| argument.map(Into::into))
+        .collect()
+}
@@ -0,0 +1,962 @@
+//! Typed syntax tree of ASP Classic pages
+//!
+//! A [`Program`] is the sequence of statements of a page, in source order. Markup
+//! (HTML, `<%= ... %>` output blocks, directives) is part of the statement list, so
+//! a block statement such as `If` can contain HTML when it spans several
+//! `<% ... %>` blocks:
+//!
+//! ```text
+//! <% If loggedIn Then %>
+//!     <p>Welcome back!</p>
+//! <% End If %>
+//! ```
+//!
+//! Every node carries the [`Span`] of the source text it was built from.
+//!
+//! The tree implements [`serde::Serialize`]. Statements and expressions are
+//! serialized as objects with a `type` field naming their variant:
+//!
+//! ```
+//! use asp_classic_parser::parser;
+//!
+//! let program = parser::parse_to_ast("<% x = 1 %>").unwrap();
+//! let json = serde_json::to_value(&program).unwrap();
+//! assert_eq!(json["body"][0]["type"], "Assignment");
+//! assert_eq!(json["body"][0]["value"]["value"]["type"], "Integer");
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use super::{DirectiveAttribute, ScriptLanguage};
+
+/// Location of a node in the source text
+///
+/// Offsets are in bytes; `line` and `column` are 1-based and locate the start of the
+/// node, the column being counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct Span {
+    /// Offset of the first byte of the node
+    pub start: usize,
+    /// Offset just past the last byte of the node
+    pub end: usize,
+    /// Line of the first character of the node
+    pub line: usize,
+    /// Column of the first character of the node
+    pub column: usize,
+}
+
+impl Span {
+    /// Creates a span from byte offsets and the position of its start
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Span {
+            start,
+            end,
+            line,
+            column,
+        }
+    }
+
+    /// Returns the source text covered by the span
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::ast::Span;
+    ///
+    /// assert_eq!(Span::new(3, 8, 1, 4).as_str("<% Hello %>"), "Hello");
+    /// ```
+    pub fn as_str<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+
+    /// Returns the smallest span covering both spans
+    pub fn to(&self, other: Span) -> Span {
+        let first = if other.start < self.start {
+            other
+        } else {
+            *self
+        };
+        Span {
+            end: self.end.max(other.end),
+            ..first
+        }
+    }
+}
+
+/// Converts byte offsets of a source text into lines and columns
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::ast::LineIndex;
+///
+/// let index = LineIndex::new("<%\nx = \"é\" : y = 1\n%>");
+/// assert_eq!(index.line_column(0), (1, 1));
+/// assert_eq!(index.line_column(14), (2, 11));
+///
+/// let span = index.span(14, 19);
+/// assert_eq!(span.as_str(index.source()), "y = 1");
+/// assert_eq!((span.line, span.column), (2, 11));
+/// assert_eq!(index.end_line_column(span), (2, 16));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+    tab_width: usize,
+    /// Byte offset of the source in the page it is a piece of
+    start: usize,
+    /// Line of the page the source starts
+    first_line: usize,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes the line starts of a source text
+    pub fn new(source: &'a str) -> Self {
+        LineIndex::with_tab_width(source, 1)
+    }
+
+    /// Indexes the line starts of a source text whose tabs advance the column to the
+    /// next multiple of `tab_width`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::ast::LineIndex;
+    ///
+    /// assert_eq!(LineIndex::new("\tx").line_column(1), (1, 2));
+    /// assert_eq!(LineIndex::with_tab_width("\tx", 4).line_column(1), (1, 5));
+    /// assert_eq!(LineIndex::with_tab_width("ab\tx", 4).line_column(3), (1, 5));
+    /// ```
+    pub fn with_tab_width(source: &'a str, tab_width: usize) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        LineIndex {
+            source,
+            line_starts,
+            tab_width: tab_width.max(1),
+            start: 0,
+            first_line: 1,
+        }
+    }
+
+    /// Indexes a piece of a page, starting at the byte `start` of the page and at
+    /// the start of its line `first_line`
+    ///
+    /// Offsets given to the index are in the piece, while the lines it returns and
+    /// the spans it creates are those of the page.
+    pub(crate) fn for_piece(
+        source: &'a str,
+        tab_width: usize,
+        start: usize,
+        first_line: usize,
+    ) -> Self {
+        LineIndex {
+            start,
+            first_line: first_line.max(1),
+            ..LineIndex::with_tab_width(source, tab_width)
+        }
+    }
+
+    /// Returns the indexed source text
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Returns the number of lines of the source, a line feed at its end not
+    /// starting another line
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::ast::LineIndex;
+    ///
+    /// assert_eq!(LineIndex::new("").line_count(), 0);
+    /// assert_eq!(LineIndex::new("<%\r\nx = 1\r\n%>").line_count(), 3);
+    /// assert_eq!(LineIndex::new("<%\nx = 1\n%>\n").line_count(), 3);
+    /// ```
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len() - usize::from(self.source.is_empty() || self.source.ends_with('\n'))
+    }
+
+    /// Returns the 1-based line and column (in characters) of a byte offset
+    pub fn line_column(&self, offset: usize) -> (usize, usize) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let width = self.tab_width;
+        let column = self.source[line_start..offset]
+            .chars()
+            .fold(0, |column, c| match c {
+                '\t' => (column / width + 1) * width,
+                _ => column + 1,
+            });
+        (self.first_line + line - 1, column + 1)
+    }
+
+    /// Returns the line and column just past the end of a span
+    pub fn end_line_column(&self, span: Span) -> (usize, usize) {
+        self.line_column(span.end.saturating_sub(self.start))
+    }
+
+    /// Returns the byte offset of a 1-based line and column, the inverse of
+    /// [`line_column`](Self::line_column)
+    ///
+    /// Positions past the end of a line give the end of the line, and positions
+    /// past the last line give the end of the source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::ast::LineIndex;
+    ///
+    /// let index = LineIndex::new("<%\nx = \"é\" : y = 1\n%>");
+    /// assert_eq!(index.offset(2, 11), 14);
+    /// assert_eq!(index.offset(2, 99), 19);
+    /// assert_eq!(index.offset(9, 1), 22);
+    /// ```
+    pub fn offset(&self, line: usize, column: usize) -> usize {
+        let index = line.max(self.first_line) - self.first_line;
+        let Some(&line_start) = self.line_starts.get(index) else {
+            return self.source.len();
+        };
+        let line_end = self.source[line_start..]
+            .find('\n')
+            .map_or(self.source.len(), |end| line_start + end);
+        let width = self.tab_width;
+        let mut current = 1;
+        for (index, c) in self.source[line_start..line_end].char_indices() {
+            if current >= column {
+                return line_start + index;
+            }
+            current = match c {
+                '\t' => ((current - 1) / width + 1) * width + 1,
+                _ => current + 1,
+            };
+        }
+        line_end
+    }
+
+    /// Creates the span covering the given byte offsets
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        let (line, column) = self.line_column(start);
+        Span::new(self.start + start, self.start + end, line, column)
+    }
+}
+
+/// A parsed ASP page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Program {
+    /// Server-side language of the page
+    pub language: ScriptLanguage,
+    /// Top-level statements, including markup, in source order
+    pub body: Vec<Statement>,
+    /// Span of the whole page
+    pub span: Span,
+}
+
+/// A name as written in the source (without brackets for `[escaped names]`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Identifier {
+    /// The name
+    pub name: String,
+    /// Location of the name
+    pub span: Span,
+}
+
+/// A statement, or a piece of markup between statements
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Statement {
+    /// HTML text or comment outside of ASP blocks
+    Html(Html),
+    /// `<%= expression %>` output block
+    Output(Output),
+    /// `<%@ ... %>` processing directive
+    Directive(Directive),
+    /// `<script runat="server">` block
+    ServerScript(ServerScript),
+    /// `Option Explicit`
+    OptionExplicit {
+        /// Location of the statement
+        span: Span,
+    },
+    /// `Dim`, or `Public`/`Private` variable declarations
+    Dim(DimStatement),
+    /// `ReDim [Preserve]`
+    ReDim(ReDimStatement),
+    /// `Const` declarations
+    Const(ConstStatement),
+    /// `Erase` of arrays
+    Erase(EraseStatement),
+    /// `[Let] target = value` or `Set target = value`
+    Assignment(Assignment),
+    /// Sub or method call, with or without the `Call` keyword
+    Call(CallStatement),
+    /// `If` block or single-line `If`
+    If(IfStatement),
+    /// `Select Case` block
+    Select(SelectStatement),
+    /// `For counter = start To end [Step step]` loop
+    For(ForStatement),
+    /// `For Each item In collection` loop
+    ForEach(ForEachStatement),
+    /// `Do [While|Until] ... Loop [While|Until]` loop
+    DoLoop(DoLoopStatement),
+    /// `While ... Wend` loop
+    While(WhileStatement),
+    /// `With object` block
+    With(WithStatement),
+    /// `Sub`, `Function` or `Property` definition
+    Procedure(Procedure),
+    /// `Class` definition
+    Class(ClassDeclaration),
+    /// `Exit Do`, `Exit For`, `Exit Sub`, ...
+    Exit(ExitStatement),
+    /// `On Error Resume Next` or `On Error GoTo 0`
+    OnError(OnErrorStatement),
+    /// `Stop`
+    Stop {
+        /// Location of the statement
+        span: Span,
+    },
+    /// `Randomize [seed]`
+    Randomize(RandomizeStatement),
+    /// Code the parser does not understand, kept as text (e.g. JScript code)
+    Unparsed(Unparsed),
+}
+
+impl Statement {
+    /// Returns the location of the statement
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::Html(node) => node.span,
+            Statement::Output(node) => node.span,
+            Statement::Directive(node) => node.span,
+            Statement::ServerScript(node) => node.span,
+            Statement::OptionExplicit { span } | Statement::Stop { span } => *span,
+            Statement::Dim(node) => node.span,
+            Statement::ReDim(node) => node.span,
+            Statement::Const(node) => node.span,
+            Statement::Erase(node) => node.span,
+            Statement::Assignment(node) => node.span,
+            Statement::Call(node) => node.span,
+            Statement::If(node) => node.span,
+            Statement::Select(node) => node.span,
+            Statement::For(node) => node.span,
+            Statement::ForEach(node) => node.span,
+            Statement::DoLoop(node) => node.span,
+            Statement::While(node) => node.span,
+            Statement::With(node) => node.span,
+            Statement::Procedure(node) => node.span,
+            Statement::Class(node) => node.span,
+            Statement::Exit(node) => node.span,
+            Statement::OnError(node) => node.span,
+            Statement::Randomize(node) => node.span,
+            Statement::Unparsed(node) => node.span,
+        }
+    }
+}
+
+/// HTML text or comment
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Html {
+    /// The markup, as written in the source
+    pub text: String,
+    /// Location of the markup
+    pub span: Span,
+}
+
+/// `<%= expression %>` output block
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Output {
+    /// The written expression
+    pub value: Expression,
+    /// Location of the whole block, delimiters included
+    pub span: Span,
+}
+
+/// `<%@ ... %>` processing directive
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Directive {
+    /// The `Name=Value` attributes of the directive
+    pub attributes: Vec<DirectiveAttribute>,
+    /// Location of the whole directive, delimiters included
+    pub span: Span,
+}
+
+/// `<script runat="server">` block
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServerScript {
+    /// Language of the block (from its `language` attribute, or the page language)
+    pub language: ScriptLanguage,
+    /// Statements of the block
+    pub body: Vec<Statement>,
+    /// Location of the whole block, tags included
+    pub span: Span,
+}
+
+/// Visibility of a declaration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Visibility {
+    /// `Public`
+    Public,
+    /// `Private`
+    Private,
+}
+
+/// A declared variable, optionally an array
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct VariableDeclarator {
+    /// Name of the variable
+    pub name: Identifier,
+    /// Array bounds: `None` for a scalar, an empty list for a dynamic array (`a()`)
+    pub bounds: Option<Vec<Expression>>,
+    /// Location of the declarator
+    pub span: Span,
+}
+
+/// `Dim a, b(10)` or `Public a` / `Private b` declarations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DimStatement {
+    /// `None` for `Dim`, the visibility for `Public`/`Private` declarations
+    pub visibility: Option<Visibility>,
+    /// Declared variables
+    pub variables: Vec<VariableDeclarator>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `ReDim [Preserve] a(10)`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReDimStatement {
+    /// Whether `Preserve` was specified
+    pub preserve: bool,
+    /// Resized arrays
+    pub variables: Vec<VariableDeclarator>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A single `Name = value` constant
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConstantDeclarator {
+    /// Name of the constant
+    pub name: Identifier,
+    /// Value of the constant
+    pub value: Expression,
+    /// Location of the declarator
+    pub span: Span,
+}
+
+/// `[Public|Private] Const A = 1, B = 2`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConstStatement {
+    /// Visibility, if specified
+    pub visibility: Option<Visibility>,
+    /// Declared constants
+    pub constants: Vec<ConstantDeclarator>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `Erase a, b`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EraseStatement {
+    /// Erased arrays
+    pub arrays: Vec<Identifier>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Kind of assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AssignmentKind {
+    /// Value assignment: `x = 1` or `Let x = 1`
+    Let,
+    /// Object reference assignment: `Set x = obj`
+    Set,
+}
+
+/// `[Let] target = value` or `Set target = value`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Assignment {
+    /// Value or object assignment
+    pub kind: AssignmentKind,
+    /// Assigned variable, property or array element
+    pub target: Expression,
+    /// Assigned value
+    pub value: Expression,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Sub or method call statement
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallStatement {
+    /// Called sub or method (e.g. `Response.Write`)
+    pub target: Expression,
+    /// Arguments; `None` for an omitted optional argument (`Foo a, , c`)
+    pub arguments: Vec<Option<Expression>>,
+    /// Whether the `Call` keyword was used
+    pub explicit: bool,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// An `ElseIf condition Then` branch
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ElseIfBranch {
+    /// Condition of the branch
+    pub condition: Expression,
+    /// Statements of the branch
+    pub body: Vec<Statement>,
+    /// Location of the branch, from `ElseIf` to the next branch or `End If`
+    pub span: Span,
+}
+
+/// `If` block or single-line `If`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IfStatement {
+    /// Condition of the `If`
+    pub condition: Expression,
+    /// Statements executed when the condition is true
+    pub then_branch: Vec<Statement>,
+    /// `ElseIf` branches
+    pub else_if_branches: Vec<ElseIfBranch>,
+    /// Statements of the `Else` branch, if any
+    pub else_branch: Option<Vec<Statement>>,
+    /// Whether this is a single-line `If` (`If x Then y Else z`)
+    pub single_line: bool,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// A `Case value1, value2` clause
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CaseClause {
+    /// Values matched by the clause
+    pub values: Vec<Expression>,
+    /// Statements of the clause
+    pub body: Vec<Statement>,
+    /// Location of the clause
+    pub span: Span,
+}
+
+/// `Select Case` block
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SelectStatement {
+    /// Tested expression
+    pub subject: Expression,
+    /// `Case` clauses
+    pub cases: Vec<CaseClause>,
+    /// Statements of the `Case Else` clause, if any
+    pub else_branch: Option<Vec<Statement>>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `For counter = start To end [Step step]` loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForStatement {
+    /// Loop counter
+    pub variable: Identifier,
+    /// Initial value
+    pub start: Expression,
+    /// Final value
+    pub end: Expression,
+    /// Increment, if specified
+    pub step: Option<Expression>,
+    /// Loop body
+    pub body: Vec<Statement>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `For Each item In collection` loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ForEachStatement {
+    /// Loop variable
+    pub variable: Identifier,
+    /// Iterated collection or array
+    pub collection: Expression,
+    /// Loop body
+    pub body: Vec<Statement>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Kind of `Do` loop condition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum LoopConditionKind {
+    /// Loop while the condition is true
+    While,
+    /// Loop until the condition is true
+    Until,
+}
+
+/// `While condition` or `Until condition` of a `Do` loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoopCondition {
+    /// `While` or `Until`
+    pub kind: LoopConditionKind,
+    /// The condition
+    pub condition: Expression,
+    /// Location of the condition, keyword included
+    pub span: Span,
+}
+
+/// `Do ... Loop` loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DoLoopStatement {
+    /// Condition tested before each iteration (`Do While x`)
+    pub pre_condition: Option<LoopCondition>,
+    /// Condition tested after each iteration (`Loop Until x`)
+    pub post_condition: Option<LoopCondition>,
+    /// Loop body
+    pub body: Vec<Statement>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `While condition ... Wend` loop
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WhileStatement {
+    /// Loop condition
+    pub condition: Expression,
+    /// Loop body
+    pub body: Vec<Statement>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `With object ... End With` block
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WithStatement {
+    /// Object referenced by `.Member` expressions in the body
+    pub object: Expression,
+    /// Statements of the block
+    pub body: Vec<Statement>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Kind of procedure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ProcedureKind {
+    /// `Sub`
+    Sub,
+    /// `Function`
+    Function,
+    /// `Property Get`
+    PropertyGet,
+    /// `Property Let`
+    PropertyLet,
+    /// `Property Set`
+    PropertySet,
+}
+
+/// How an argument is passed to a procedure
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ParameterPassing {
+    /// `ByVal`
+    ByVal,
+    /// `ByRef` (the default when not specified)
+    ByRef,
+}
+
+/// A procedure parameter
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Parameter {
+    /// Name of the parameter
+    pub name: Identifier,
+    /// Passing mode, if specified
+    pub passing: Option<ParameterPassing>,
+    /// Whether the parameter is declared as an array (`values()`)
+    pub is_array: bool,
+    /// Location of the parameter
+    pub span: Span,
+}
+
+/// `Sub`, `Function` or `Property` definition
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Procedure {
+    /// Kind of procedure
+    pub kind: ProcedureKind,
+    /// Name of the procedure
+    pub name: Identifier,
+    /// Visibility, if specified
+    pub visibility: Option<Visibility>,
+    /// Whether the procedure is the `Default` member of its class
+    pub is_default: bool,
+    /// Parameters
+    pub parameters: Vec<Parameter>,
+    /// Statements of the procedure
+    pub body: Vec<Statement>,
+    /// Location of the definition, from the header to the `End` line
+    pub span: Span,
+}
+
+/// `Class` definition
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClassDeclaration {
+    /// Name of the class
+    pub name: Identifier,
+    /// Members of the class (variables, constants and procedures)
+    pub body: Vec<Statement>,
+    /// Location of the definition
+    pub span: Span,
+}
+
+/// Block exited by an `Exit` statement
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExitKind {
+    /// `Exit Do`
+    Do,
+    /// `Exit For`
+    For,
+    /// `Exit Function`
+    Function,
+    /// `Exit Sub`
+    Sub,
+    /// `Exit Property`
+    Property,
+}
+
+/// `Exit Do`, `Exit For`, `Exit Function`, `Exit Sub` or `Exit Property`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExitStatement {
+    /// Exited block
+    pub kind: ExitKind,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Error handling mode set by `On Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum OnErrorAction {
+    /// `On Error Resume Next`
+    ResumeNext,
+    /// `On Error GoTo 0`
+    GoToZero,
+}
+
+/// `On Error Resume Next` or `On Error GoTo 0`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OnErrorStatement {
+    /// Selected error handling mode
+    pub action: OnErrorAction,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// `Randomize [seed]`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RandomizeStatement {
+    /// Seed, if specified
+    pub seed: Option<Expression>,
+    /// Location of the statement
+    pub span: Span,
+}
+
+/// Source text the parser does not understand
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unparsed {
+    /// The text, as written in the source
+    pub text: String,
+    /// Location of the text
+    pub span: Span,
+}
+
+/// An expression
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type")]
+pub enum Expression {
+    /// String, number, date, boolean, `Nothing`, `Null` or `Empty`
+    Literal(Literal),
+    /// Variable, constant or procedure name
+    Identifier(Identifier),
+    /// `object.Member`, or `.Member` inside a `With` block
+    Member(MemberExpression),
+    /// Call or array indexing: `Foo(1)`, `arr(0)`, `rs.Fields("name")`
+    Call(CallExpression),
+    /// `-x`, `+x` or `Not x`
+    Unary(UnaryExpression),
+    /// Binary operation
+    Binary(BinaryExpression),
+    /// `(expression)`
+    Parenthesized(ParenthesizedExpression),
+    /// `New ClassName`
+    New(NewExpression),
+    /// Expression the parser does not understand, kept as text
+    Unparsed(Unparsed),
+}
+
+impl Expression {
+    /// Returns the location of the expression
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Literal(node) => node.span,
+            Expression::Identifier(node) => node.span,
+            Expression::Member(node) => node.span,
+            Expression::Call(node) => node.span,
+            Expression::Unary(node) => node.span,
+            Expression::Binary(node) => node.span,
+            Expression::Parenthesized(node) => node.span,
+            Expression::New(node) => node.span,
+            Expression::Unparsed(node) => node.span,
+        }
+    }
+}
+
+/// Value of a literal
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", content = "value")]
+pub enum LiteralValue {
+    /// String, with `""` escapes resolved
+    String(String),
+    /// Integer, including `&H` hexadecimal and `&O` octal literals
+    Integer(i64),
+    /// Floating-point number
+    Float(f64),
+    /// Date literal, without the surrounding `#`
+    Date(String),
+    /// `True` or `False`
+    Boolean(bool),
+    /// `Nothing`
+    Nothing,
+    /// `Null`
+    Null,
+    /// `Empty`
+    Empty,
+}
+
+/// A literal value
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Literal {
+    /// The value
+    pub value: LiteralValue,
+    /// Location of the literal
+    pub span: Span,
+}
+
+/// `object.Member`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MemberExpression {
+    /// Accessed object; `None` for `.Member` inside a `With` block
+    pub object: Option<Box<Expression>>,
+    /// Name of the member
+    pub member: Identifier,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Call or array indexing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CallExpression {
+    /// Called function or indexed array
+    pub callee: Box<Expression>,
+    /// Arguments; `None` for an omitted optional argument
+    pub arguments: Vec<Option<Expression>>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Unary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum UnaryOperator {
+    /// `-`
+    Negate,
+    /// `+`
+    Plus,
+    /// `Not`
+    Not,
+}
+
+/// Unary operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnaryExpression {
+    /// The operator
+    pub operator: UnaryOperator,
+    /// The operand
+    pub operand: Box<Expression>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// Binary operators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum BinaryOperator {
+    /// `^`
+    Power,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `\`
+    IntegerDivide,
+    /// `Mod`
+    Modulo,
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `&`
+    Concat,
+    /// `=`
+    Equal,
+    /// `<>`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `>`
+    Greater,
+    /// `<=`
+    LessOrEqual,
+    /// `>=`
+    GreaterOrEqual,
+    /// `Is`
+    Is,
+    /// `And`
+    And,
+    /// `Or`
+    Or,
+    /// `Xor`
+    Xor,
+    /// `Eqv`
+    Eqv,
+    /// `Imp`
+    Imp,
+}
+
+/// Binary operation
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BinaryExpression {
+    /// Left operand
+    pub left: Box<Expression>,
+    /// The operator
+    pub operator: BinaryOperator,
+    /// Right operand
+    pub right: Box<Expression>,
+    /// Location of the expression
+    pub span: Span,
+}
+
+/// `(expression)`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParenthesizedExpression {
+    /// The inner expression
+    pub expression: Box<Expression>,
+    /// Location of the expression, parentheses included
+    pub span: Span,
+}
+
+/// `New ClassName`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NewExpression {
+    /// Instantiated class
+    pub class_name: Identifier,
+    /// Location of the expression
+    pub span: Span,
+}
@@ -0,0 +1,1955 @@
+//! Conversion of the Pest parse tree into the typed syntax tree
+//!
+//! The grammar describes blocks (`If`, `For`, `Sub`, ...) line by line, since a block
+//! can span several `<% ... %>` blocks with HTML in between. The builder converts
+//! each line, then nests the statements found between the header and the end line
+//! of each block, reporting unbalanced blocks as parse errors.
+//!
+//! The tree is built in an [arena](super::arena), and the owned tree of
+//! [`ast`](super::ast) is copied from it, so that both trees come from the same
+//! builder.
+
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use bumpalo::collections::Vec as BumpVec;
+use pest::Parser;
+use pest::error::{ErrorVariant, InputLocation};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
+
+use super::arena::*;
+use super::ast::{
+    self, AssignmentKind, BinaryOperator, ExitKind, LineIndex, LoopConditionKind, OnErrorAction,
+    ParameterPassing, ProcedureKind, Span, UnaryOperator, Visibility,
+};
+use super::jscript::{self, JScriptParser};
+use super::{
+    AspParseError, AspParser, Diagnostic, DiagnosticCode, ParseOptions, Rule, ScriptLanguage,
+    directive_attribute_texts,
+};
+
+type Pair<'a> = pest::iterators::Pair<'a, Rule>;
+
+/// Operator precedence of VBScript, from the lowest to the highest
+pub(super) static PRATT_PARSER: LazyLock<PrattParser<Rule>> = LazyLock::new(|| {
+    PrattParser::new()
+        .op(Op::infix(Rule::op_imp, Assoc::Left))
+        .op(Op::infix(Rule::op_eqv, Assoc::Left))
+        .op(Op::infix(Rule::op_xor, Assoc::Left))
+        .op(Op::infix(Rule::op_or, Assoc::Left))
+        .op(Op::infix(Rule::op_and, Assoc::Left))
+        .op(Op::prefix(Rule::op_not))
+        .op(Op::infix(Rule::op_eq, Assoc::Left)
+            | Op::infix(Rule::op_ne, Assoc::Left)
+            | Op::infix(Rule::op_lt, Assoc::Left)
+            | Op::infix(Rule::op_gt, Assoc::Left)
+            | Op::infix(Rule::op_le, Assoc::Left)
+            | Op::infix(Rule::op_ge, Assoc::Left)
+            | Op::infix(Rule::op_is, Assoc::Left))
+        .op(Op::infix(Rule::op_concat, Assoc::Left))
+        .op(Op::infix(Rule::op_add, Assoc::Left) | Op::infix(Rule::op_sub, Assoc::Left))
+        .op(Op::infix(Rule::op_mod, Assoc::Left))
+        .op(Op::infix(Rule::op_int_div, Assoc::Left))
+        .op(Op::infix(Rule::op_mul, Assoc::Left) | Op::infix(Rule::op_div, Assoc::Left))
+        .op(Op::prefix(Rule::op_neg) | Op::prefix(Rule::op_pos))
+        .op(Op::infix(Rule::op_pow, Assoc::Left))
+});
+
+/// Parses a page and builds its syntax tree
+///
+/// Returns the program and the number of ASP blocks (code, expression and directive
+/// blocks, and server-side script blocks) it contains.
+pub(crate) fn build_program(
+    source: &str,
+    language: ScriptLanguage,
+    options: &ParseOptions,
+) -> Result<(ast::Program, usize), AspParseError> {
+    let lines = &LineIndex::with_tab_width(source, options.tab_width);
+    let arena = Bump::new();
+    build_tree(source, lines, language, &arena, false)
+        .map(|(program, code_blocks, _)| (program.into(), code_blocks))
+        .map_err(|error| error.into_parse_error(lines))
+}
+
+/// Parses a page and builds its syntax tree in `arena`, stopping at the first error
+///
+/// Returns the program and the number of ASP blocks it contains, like
+/// [`build_program`].
+pub(super) fn build_program_in<'a>(
+    text: &'a str,
+    language: ScriptLanguage,
+    arena: &'a Bump,
+) -> Result<(Program<'a>, usize), AspParseError> {
+    let lines = &LineIndex::new(text);
+    build_tree(text, lines, language, arena, false)
+        .map(|(program, code_blocks, _)| (program, code_blocks))
+        .map_err(|error| error.into_parse_error(lines))
+}
+
+/// Parses a page, collecting every syntax error instead of stopping at the first one
+///
+/// After a grammar error, the statement containing it is blanked out (keeping byte
+/// offsets and line breaks) and the page is parsed again, until
+/// [`ParseOptions::max_errors`] errors are found. Unbalanced blocks are reported
+/// without stopping the construction of the tree.
+pub(crate) fn build_program_recovering(
+    source: &str,
+    language: ScriptLanguage,
+    options: &ParseOptions,
+) -> (ast::Program, Vec<Diagnostic>) {
+    let lines = &LineIndex::with_tab_width(source, options.tab_width);
+    let max_errors = options.max_errors.max(1);
+    let (program, mut diagnostics) = build_tree_recovering(source, lines, language, max_errors);
+    let mut program = program.unwrap_or_else(|| empty_program(lines, language));
+
+    program.span = lines.span(0, source.len());
+    diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+    diagnostics.truncate(max_errors);
+    (program, diagnostics)
+}
+
+/// Builds the syntax tree of `source`, blanking out the statements containing
+/// syntax errors as [`build_program_recovering`] does
+///
+/// Returns no tree when the errors stop its construction: after `max_errors`
+/// errors, or after an error that blanking out cannot remove. The diagnostics are
+/// in the order they were found.
+pub(super) fn build_tree_recovering(
+    source: &str,
+    lines: &LineIndex,
+    language: ScriptLanguage,
+    max_errors: usize,
+) -> (Option<ast::Program>, Vec<Diagnostic>) {
+    let (built, mut diagnostics) = recover(source, lines, max_errors, |text| {
+        let arena = Bump::new();
+        let (program, code_blocks, structure_errors) =
+            build_tree(text, lines, language, &arena, true)?;
+        Ok((ast::Program::from(program), code_blocks, structure_errors))
+    });
+    let program = built.map(|(program, _, structure_errors)| {
+        diagnostics.extend(structure_errors);
+        program
+    });
+    (program, diagnostics)
+}
+
+/// Runs `build` on `source`, then again after blanking out the statement
+/// containing each syntax error it reports, until it succeeds
+///
+/// Returns the result of `build` and the syntax errors, in the order they were
+/// found, or no result when the errors stop the construction (see
+/// [`build_tree_recovering`]).
+fn recover<T>(
+    source: &str,
+    lines: &LineIndex,
+    max_errors: usize,
+    mut build: impl FnMut(&str) -> Result<T, BuildError>,
+) -> (Option<T>, Vec<Diagnostic>) {
+    let mut text = source.to_string();
+    let mut diagnostics = Vec::new();
+
+    let built = loop {
+        let failure = match build(&text) {
+            Ok(built) => break Some(built),
+            Err(BuildError::Structure(diagnostic)) => {
+                diagnostics.push(diagnostic);
+                break None;
+            }
+            Err(BuildError::Syntax(failure)) => failure,
+        };
+
+        let range = statement_range(&text, failure.offset);
+        diagnostics.push(Diagnostic::new(
+            DiagnosticCode::SyntaxError,
+            lines.span(failure.offset, range.end.max(failure.offset)),
+            failure.message,
+        ));
+
+        // Stop when blanking out the statement would not change the text
+        if diagnostics.len() >= max_errors || text[range.clone()].trim().is_empty() {
+            break None;
+        }
+        let blank: String = text[range.clone()]
+            .chars()
+            .map(|c| match c {
+                '\n' | '\r' => c.to_string(),
+                _ => " ".repeat(c.len_utf8()),
+            })
+            .collect();
+        text.replace_range(range, &blank);
+    };
+    (built, diagnostics)
+}
+
+/// Checks a page parsed piece by piece, keeping only the blocks left open by the
+/// pieces already parsed
+///
+/// Each piece is made of whole lines of the page, cut between two of its items
+/// (markup, comments and ASP blocks). The statements containing syntax errors are
+/// blanked out as in [`build_program_recovering`], piece by piece, and checking
+/// stops after [`ParseOptions::max_errors`] errors, or after an error that blanking
+/// out cannot remove.
+pub(super) struct PageChecker {
+    /// Blocks left open by the pieces already parsed, without their statements
+    open_blocks: Vec<(Block, Span)>,
+    language: ScriptLanguage,
+    /// Number of errors that may still be reported, 0 once checking stopped
+    remaining_errors: usize,
+    code_blocks: usize,
+}
+
+impl PageChecker {
+    pub(super) fn new(language: ScriptLanguage, options: &ParseOptions) -> Self {
+        PageChecker {
+            open_blocks: vec![(Block::Root, Span::default())],
+            language,
+            remaining_errors: options.max_errors.max(1),
+            code_blocks: 0,
+        }
+    }
+
+    /// Parses the next piece of the page, whose positions are computed with
+    /// `lines`, and returns its errors in source order
+    pub(super) fn check(&mut self, piece: &str, lines: &LineIndex) -> Vec<Diagnostic> {
+        if self.remaining_errors == 0 {
+            return Vec::new();
+        }
+        // The blocks are only updated once the piece is parsed, each attempt
+        // starting from the blocks left open by the previous pieces
+        let (built, mut diagnostics) = recover(piece, lines, self.remaining_errors, |text| {
+            let arena = Bump::new();
+            let mut blocks = BlockBuilder::resume(&arena, &self.open_blocks);
+            let code_blocks = add_items(text, lines, self.language, &arena, &mut blocks)?;
+            Ok((blocks.open_blocks(), blocks.diagnostics, code_blocks))
+        });
+        let stopped = built.is_none();
+        if let Some((open_blocks, mut structure_errors, code_blocks)) = built {
+            diagnostics.append(&mut structure_errors);
+            self.open_blocks = open_blocks;
+            self.code_blocks += code_blocks;
+        }
+
+        diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+        diagnostics.truncate(self.remaining_errors);
+        self.remaining_errors -= diagnostics.len();
+        if stopped {
+            self.remaining_errors = 0;
+        }
+        diagnostics
+    }
+
+    /// Returns the number of ASP blocks of the page, and the errors of the blocks
+    /// left open at its end
+    pub(super) fn finish(self) -> (usize, Vec<Diagnostic>) {
+        if self.remaining_errors == 0 {
+            return (self.code_blocks, Vec::new());
+        }
+        let arena = Bump::new();
+        let mut diagnostics = match BlockBuilder::resume(&arena, &self.open_blocks).finish() {
+            Ok((_, diagnostics)) => diagnostics,
+            Err(diagnostic) => vec![diagnostic],
+        };
+        diagnostics.truncate(self.remaining_errors);
+        (self.code_blocks, diagnostics)
+    }
+}
+
+fn empty_program(lines: &LineIndex, language: ScriptLanguage) -> ast::Program {
+    ast::Program {
+        language,
+        body: Vec::new(),
+        span: lines.span(0, lines.source().len()),
+    }
+}
+
+/// Returns the byte range of the statement containing a syntax error
+///
+/// The statement extends from the start of its line (or the `<%` or `%>` tag before
+/// it on the same line) to the end of the line or the next `%>` tag.
+fn statement_range(text: &str, offset: usize) -> Range<usize> {
+    let mut offset = offset.min(text.len());
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+
+    let line_start = text[..offset].rfind('\n').map_or(0, |index| index + 1);
+    let before = &text[line_start..offset];
+    let start = [before.rfind("<%"), before.rfind("%>")]
+        .into_iter()
+        .flatten()
+        .max()
+        .map_or(line_start, |index| line_start + index + 2);
+
+    let after = &text[offset..];
+    let end = [after.find('\n'), after.find("%>")]
+        .into_iter()
+        .flatten()
+        .min()
+        .map_or(text.len(), |index| offset + index);
+
+    start..end
+}
+
+/// Grammar failure, independent of the grammar used
+pub(super) struct SyntaxFailure {
+    /// What the grammar expected, on a single line (e.g. `expected expression`)
+    message: String,
+    /// Byte offset of the error
+    offset: usize,
+}
+
+impl<R: pest::RuleType> From<pest::error::Error<R>> for SyntaxFailure {
+    fn from(error: pest::error::Error<R>) -> Self {
+        let offset = match error.location {
+            InputLocation::Pos(offset) => offset,
+            InputLocation::Span((start, _)) => start,
+        };
+        SyntaxFailure {
+            message: syntax_message(&error.variant),
+            offset,
+        }
+    }
+}
+
+/// Describes a grammar error with readable names instead of grammar rule names
+///
+/// Rules with the same readable name are listed once, so that
+/// `expected postfix_expression or parenthesized_expression` becomes
+/// `expected expression`.
+fn syntax_message<R: pest::RuleType>(variant: &ErrorVariant<R>) -> String {
+    match variant {
+        ErrorVariant::ParsingError {
+            positives,
+            negatives,
+        } => {
+            let names = |rules: &[R]| {
+                let mut names: Vec<String> = Vec::new();
+                for name in rules.iter().map(|rule| readable_rule(&format!("{rule:?}"))) {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                enumerate(&names)
+            };
+            match (positives.is_empty(), negatives.is_empty()) {
+                (false, false) => format!(
+                    "unexpected {}; expected {}",
+                    names(negatives),
+                    names(positives)
+                ),
+                (false, true) => format!("expected {}", names(positives)),
+                (true, false) => format!("unexpected {}", names(negatives)),
+                (true, true) => "unknown syntax error".to_string(),
+            }
+        }
+        ErrorVariant::CustomError { message } => message.replace(['\r', '\n'], " "),
+    }
+}
+
+/// Joins names as `a`, `a or b` or `a, b, or c`
+fn enumerate(names: &[String]) -> String {
+    match names {
+        [] => String::new(),
+        [name] => name.clone(),
+        [first, second] => format!("{first} or {second}"),
+        [rest @ .., last] => format!("{}, or {last}", rest.join(", ")),
+    }
+}
+
+/// Readable name of a rule of the ASP, VBScript or JScript grammars
+fn readable_rule(rule: &str) -> String {
+    let name = match rule {
+        "EOI" => "end of file",
+        "asp_close_tag" => "'%>'",
+        "asp_open_tag" => "'<%'",
+        "identifier" | "member_name" => "identifier",
+        "call_target" => "variable",
+        "string_literal" => "string",
+        "number_literal" => "number",
+        "date_literal" => "date",
+        "literal" | "boolean_literal" | "special_literal" => "literal",
+        "expression"
+        | "term"
+        | "postfix_expression"
+        | "postfix_head"
+        | "parenthesized_expression"
+        | "new_expression"
+        | "raw_expression"
+        | "output_expression"
+        | "argument" => "expression",
+        "statement" | "inline_statement" | "other_statement" => "statement",
+        "statement_end" | "statement_terminator" | "statement_separator" => "end of statement",
+        "call_arguments" | "argument_list" => "arguments",
+        "argument_separator" => "','",
+        "operator" | "unary_operator" => "operator",
+        _ if rule.starts_with("op_") => "operator",
+        "elseif_keyword" => "'ElseIf'",
+        "redim_keyword" => "'ReDim'",
+        "byval_keyword" => "'ByVal'",
+        "byref_keyword" => "'ByRef'",
+        "goto_keyword" => "'GoTo'",
+        _ => {
+            if let Some(keyword) = rule.strip_suffix("_keyword") {
+                let mut chars = keyword.chars();
+                let first = chars.next().map(|c| c.to_ascii_uppercase());
+                return format!(
+                    "'{}{}'",
+                    first.into_iter().collect::<String>(),
+                    chars.as_str()
+                );
+            }
+            return rule.replace('_', " ");
+        }
+    };
+    name.to_string()
+}
+
+/// Error stopping the construction of a syntax tree
+enum BuildError {
+    /// The text does not match the grammar
+    Syntax(SyntaxFailure),
+    /// The blocks are not properly nested
+    Structure(Diagnostic),
+}
+
+impl From<Diagnostic> for BuildError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        BuildError::Structure(diagnostic)
+    }
+}
+
+impl BuildError {
+    fn into_parse_error(self, lines: &LineIndex) -> AspParseError {
+        match self {
+            BuildError::Syntax(failure) => pest_error(lines, failure),
+            BuildError::Structure(diagnostic) => diagnostic.into(),
+        }
+    }
+}
+
+/// Result of building a tree: the program, its number of ASP blocks and the
+/// structural errors reported in recovering mode
+type Built<'a> = (Program<'a>, usize, Vec<Diagnostic>);
+
+/// Builds the syntax tree of `text` in `arena`, computing positions with `lines`
+fn build_tree<'a>(
+    text: &'a str,
+    lines: &LineIndex,
+    language: ScriptLanguage,
+    arena: &'a Bump,
+    recover: bool,
+) -> Result<Built<'a>, BuildError> {
+    let mut blocks = BlockBuilder::new(arena, recover);
+    let code_blocks = add_items(text, lines, language, arena, &mut blocks)?;
+    let (body, diagnostics) = blocks.finish()?;
+    Ok((
+        Program {
+            language,
+            body,
+            span: lines.span(0, text.len()),
+        },
+        code_blocks,
+        diagnostics,
+    ))
+}
+
+/// Parses `text` and adds its markup, ASP blocks and lines of code to `blocks`,
+/// returning its number of ASP blocks
+fn add_items<'a>(
+    text: &'a str,
+    lines: &LineIndex,
+    language: ScriptLanguage,
+    arena: &'a Bump,
+    blocks: &mut BlockBuilder<'a>,
+) -> Result<usize, BuildError> {
+    let tree = TreeBuilder { arena, lines };
+    match language {
+        ScriptLanguage::VBScript => tree.add_vbscript_items(text, blocks),
+        ScriptLanguage::JScript => tree.add_jscript_items(text, blocks),
+    }
+}
+
+/// Converts a grammar error into a parse error located at the error position
+pub(super) fn pest_error(lines: &LineIndex, failure: SyntaxFailure) -> AspParseError {
+    let span = lines.span(failure.offset, failure.offset);
+    Diagnostic::new(DiagnosticCode::SyntaxError, span, failure.message).into()
+}
+
+/// Returns the code of a server-side script block to parse with
+/// [`Rule::server_script_code`]
+///
+/// The text before the block is blanked out instead of parsing the content alone,
+/// so that positions (and error locations) are those of the page.
+pub(super) fn script_code(text: &str, content_start: usize, content: &str) -> String {
+    let mut code: String = text[..content_start]
+        .chars()
+        .map(|c| match c {
+            '\n' => "\n".to_string(),
+            _ => " ".repeat(c.len_utf8()),
+        })
+        .collect();
+    code.push_str(content);
+    code
+}
+
+/// Returns the language given by the `language` attribute of a `<script>` tag
+pub(crate) fn script_language_attribute(open_tag: &str) -> Option<ScriptLanguage> {
+    let pairs = AspParser::parse(Rule::server_script_open, open_tag).ok()?;
+    pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::html_attribute)
+        .find_map(|attribute| {
+            let mut parts = attribute.into_inner();
+            let name = parts.next()?;
+            let value = parts.next()?;
+            if !name.as_str().eq_ignore_ascii_case("language") {
+                return None;
+            }
+            ScriptLanguage::from_name(value.as_str().trim_matches(|c| c == '"' || c == '\''))
+        })
+}
+
+/// Returns the first child of a pair matching a rule
+fn first_child(pair: Pair<'_>, rule: Rule) -> Pair<'_> {
+    pair.into_inner()
+        .find(|child| child.as_rule() == rule)
+        .unwrap_or_else(|| panic!("grammar guarantees a {:?} child", rule))
+}
+
+fn binary_operator(rule: Rule) -> BinaryOperator {
+    match rule {
+        Rule::op_pow => BinaryOperator::Power,
+        Rule::op_mul => BinaryOperator::Multiply,
+        Rule::op_div => BinaryOperator::Divide,
+        Rule::op_int_div => BinaryOperator::IntegerDivide,
+        Rule::op_mod => BinaryOperator::Modulo,
+        Rule::op_add => BinaryOperator::Add,
+        Rule::op_sub => BinaryOperator::Subtract,
+        Rule::op_concat => BinaryOperator::Concat,
+        Rule::op_eq => BinaryOperator::Equal,
+        Rule::op_ne => BinaryOperator::NotEqual,
+        Rule::op_lt => BinaryOperator::Less,
+        Rule::op_gt => BinaryOperator::Greater,
+        Rule::op_le => BinaryOperator::LessOrEqual,
+        Rule::op_ge => BinaryOperator::GreaterOrEqual,
+        Rule::op_is => BinaryOperator::Is,
+        Rule::op_and => BinaryOperator::And,
+        Rule::op_or => BinaryOperator::Or,
+        Rule::op_xor => BinaryOperator::Xor,
+        Rule::op_eqv => BinaryOperator::Eqv,
+        _ => BinaryOperator::Imp,
+    }
+}
+
+/// Returns the value of a number literal
+///
+/// Hexadecimal and octal literals follow VBScript typing: without a trailing `&`,
+/// values up to `&HFFFF` are 16-bit integers (`&HFFFF` is -1), larger values are
+/// 32-bit integers; with a trailing `&` they are 32-bit integers.
+fn number_value(text: &str) -> LiteralValue<'static> {
+    let radix_digits = match text.get(..2) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("&h") => Some((&text[2..], 16)),
+        Some(prefix) if prefix.eq_ignore_ascii_case("&o") => Some((&text[2..], 8)),
+        _ => None,
+    };
+
+    if let Some((digits, radix)) = radix_digits {
+        let is_long = digits.ends_with('&');
+        return match u64::from_str_radix(digits.trim_end_matches('&'), radix) {
+            Ok(value) if !is_long && value <= 0xFFFF => LiteralValue::Integer(value as i16 as i64),
+            Ok(value) if value <= 0xFFFF_FFFF => LiteralValue::Integer(value as i32 as i64),
+            Ok(value) => LiteralValue::Float(value as f64),
+            Err(_) => LiteralValue::Float(f64::INFINITY),
+        };
+    }
+
+    if !text.contains(['.', 'e', 'E'])
+        && let Ok(value) = text.parse::<i64>()
+    {
+        return LiteralValue::Integer(value);
+    }
+    LiteralValue::Float(text.parse::<f64>().unwrap_or(f64::INFINITY))
+}
+
+fn has_child(pair: &Pair<'_>, rule: Rule) -> bool {
+    pair.clone().into_inner().any(|p| p.as_rule() == rule)
+}
+
+/// Allocates the nodes built from the pairs of a page
+struct TreeBuilder<'a, 'l> {
+    arena: &'a Bump,
+    lines: &'l LineIndex<'l>,
+}
+
+impl<'a> TreeBuilder<'a, '_> {
+    fn add_vbscript_items(
+        &self,
+        text: &'a str,
+        blocks: &mut BlockBuilder<'a>,
+    ) -> Result<usize, BuildError> {
+        let file = AspParser::parse(Rule::file, text)
+            .map_err(|e| BuildError::Syntax(e.into()))?
+            .next()
+            .expect("the file rule always produces a pair");
+
+        let mut code_blocks = 0;
+
+        for item in file.into_inner() {
+            let item_span = self.span_of(&item);
+            match item.as_rule() {
+                Rule::html_content | Rule::html_comment => {
+                    blocks.add(Statement::Html(Html {
+                        text: item.as_str(),
+                        span: item_span,
+                    }))?;
+                }
+                Rule::asp_expression_block => {
+                    code_blocks += 1;
+                    let value = item
+                        .into_inner()
+                        .find(|p| {
+                            matches!(p.as_rule(), Rule::output_expression | Rule::raw_expression)
+                        })
+                        .map(|p| match p.as_rule() {
+                            Rule::output_expression => {
+                                self.expression(first_child(p, Rule::expression))
+                            }
+                            _ => Expression::Unparsed(self.unparsed(&p)),
+                        })
+                        .expect("expression blocks have a content");
+                    blocks.add(Statement::Output(Output {
+                        value,
+                        span: item_span,
+                    }))?;
+                }
+                Rule::asp_directive_block => {
+                    code_blocks += 1;
+                    blocks.add(self.directive(item.as_str(), item_span))?;
+                }
+                Rule::server_script_block => {
+                    code_blocks += 1;
+                    let script =
+                        self.server_script(text, item, ScriptLanguage::VBScript, blocks)?;
+                    blocks.add(script)?;
+                }
+                Rule::asp_script_block => {
+                    code_blocks += 1;
+                    for statement in first_child(item, Rule::script_body).into_inner() {
+                        if statement.as_rule() == Rule::statement {
+                            blocks.add_line(self.line(statement))?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(code_blocks)
+    }
+
+    fn add_jscript_items(
+        &self,
+        text: &'a str,
+        blocks: &mut BlockBuilder<'a>,
+    ) -> Result<usize, BuildError> {
+        let file = JScriptParser::parse(jscript::Rule::file, text)
+            .map_err(|e| BuildError::Syntax(e.into()))?
+            .next()
+            .expect("the file rule always produces a pair");
+
+        let mut code_blocks = 0;
+
+        for item in file.into_inner() {
+            let item_span = self.span_of(&item);
+            match item.as_rule() {
+                jscript::Rule::html_content | jscript::Rule::html_comment => {
+                    blocks.add(Statement::Html(Html {
+                        text: item.as_str(),
+                        span: item_span,
+                    }))?;
+                }
+                jscript::Rule::asp_expression_block => {
+                    code_blocks += 1;
+                    let value = item
+                        .into_inner()
+                        .find(|p| p.as_rule() == jscript::Rule::output_expression)
+                        .map(|p| Expression::Unparsed(self.unparsed(&p)))
+                        .expect("expression blocks have a content");
+                    blocks.add(Statement::Output(Output {
+                        value,
+                        span: item_span,
+                    }))?;
+                }
+                jscript::Rule::asp_directive_block => {
+                    code_blocks += 1;
+                    blocks.add(self.directive(item.as_str(), item_span))?;
+                }
+                jscript::Rule::server_script_block => {
+                    code_blocks += 1;
+                    let script = self.server_script(text, item, ScriptLanguage::JScript, blocks)?;
+                    blocks.add(script)?;
+                }
+                jscript::Rule::asp_script_block => {
+                    code_blocks += 1;
+                    let code = item
+                        .into_inner()
+                        .find(|p| p.as_rule() == jscript::Rule::script_body)
+                        .expect("code blocks have a body");
+                    if !code.as_str().trim().is_empty() {
+                        blocks.add(Statement::Unparsed(self.unparsed(&code)))?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(code_blocks)
+    }
+
+    fn span_of<R: pest::RuleType>(&self, pair: &pest::iterators::Pair<'a, R>) -> Span {
+        self.lines
+            .span(pair.as_span().start(), pair.as_span().end())
+    }
+
+    fn unparsed<R: pest::RuleType>(&self, pair: &pest::iterators::Pair<'a, R>) -> Unparsed<'a> {
+        Unparsed {
+            text: pair.as_str(),
+            span: self.span_of(pair),
+        }
+    }
+
+    fn directive(&self, block: &'a str, span: Span) -> Statement<'a> {
+        let attributes = directive_attribute_texts(block)
+            .into_iter()
+            .map(|(name, value)| DirectiveAttribute { name, value });
+        Statement::Directive(Directive {
+            attributes: self.slice(attributes),
+            span,
+        })
+    }
+
+    /// Builds a server-side script block, parsing its content with the language
+    /// given by its `language` attribute, or the page language
+    fn server_script<R: pest::RuleType>(
+        &self,
+        text: &'a str,
+        block: pest::iterators::Pair<'a, R>,
+        page_language: ScriptLanguage,
+        page: &mut BlockBuilder<'a>,
+    ) -> Result<Statement<'a>, BuildError> {
+        let span = self.span_of(&block);
+        let mut parts = block.into_inner();
+        let open_tag = parts
+            .next()
+            .expect("server script blocks have an opening tag");
+        let content = parts.next().expect("server script blocks have a content");
+
+        let language = script_language_attribute(open_tag.as_str()).unwrap_or(page_language);
+        let content_span = self.span_of(&content);
+
+        let body: &'a [Statement<'a>] = match language {
+            ScriptLanguage::VBScript => {
+                // The blanked out code is kept in the arena for the names to borrow
+                let code =
+                    self.arena
+                        .alloc_str(&script_code(text, content_span.start, content.as_str()));
+                let pairs = AspParser::parse(Rule::server_script_code, code)
+                    .map_err(|e| BuildError::Syntax(e.into()))?;
+                let mut blocks = BlockBuilder::new(self.arena, page.recover);
+                for statement in pairs.flatten() {
+                    if statement.as_rule() == Rule::statement {
+                        blocks.add_line(self.line(statement))?;
+                    }
+                }
+                let (body, diagnostics) = blocks.finish()?;
+                page.diagnostics.extend(diagnostics);
+                body
+            }
+            ScriptLanguage::JScript if !content.as_str().trim().is_empty() => {
+                self.arena.alloc_slice_copy(&[Statement::Unparsed(Unparsed {
+                    text: content.as_str(),
+                    span: content_span,
+                })])
+            }
+            ScriptLanguage::JScript => &[],
+        };
+
+        Ok(Statement::ServerScript(ServerScript {
+            language,
+            body,
+            span,
+        }))
+    }
+
+    /// Allocates the nodes of an iterator as a slice of the arena
+    fn slice<T>(&self, nodes: impl IntoIterator<Item = T>) -> &'a [T] {
+        BumpVec::from_iter_in(nodes, self.arena).into_bump_slice()
+    }
+
+    fn identifier(&self, pair: Pair<'a>) -> Identifier<'a> {
+        let text = pair.as_str();
+        let name = text
+            .strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+            .unwrap_or(text);
+        Identifier {
+            name,
+            span: self.span_of(&pair),
+        }
+    }
+
+    fn expression(&self, pair: Pair<'a>) -> Expression<'a> {
+        // The Pratt parser boxes its operator callbacks, which most expressions,
+        // made of a single term, do not need
+        let mut parts = pair.clone().into_inner();
+        if let (Some(term), None) = (parts.next(), parts.next()) {
+            return self.term(term);
+        }
+
+        PRATT_PARSER
+            .map_primary(|p| self.term(p))
+            .map_prefix(|operator, operand| {
+                let span = self.span_of(&operator).to(operand.span());
+                let operator = match operator.as_rule() {
+                    Rule::op_neg => UnaryOperator::Negate,
+                    Rule::op_pos => UnaryOperator::Plus,
+                    _ => UnaryOperator::Not,
+                };
+                Expression::Unary(UnaryExpression {
+                    operator,
+                    operand: self.arena.alloc(operand),
+                    span,
+                })
+            })
+            .map_infix(|left, operator, right| {
+                let span = left.span().to(right.span());
+                Expression::Binary(BinaryExpression {
+                    left: self.arena.alloc(left),
+                    operator: binary_operator(operator.as_rule()),
+                    right: self.arena.alloc(right),
+                    span,
+                })
+            })
+            .parse(pair.into_inner())
+    }
+
+    fn term(&self, pair: Pair<'a>) -> Expression<'a> {
+        let inner = pair.into_inner().next().expect("terms have a child");
+        match inner.as_rule() {
+            Rule::literal => self.literal(inner),
+            Rule::new_expression => {
+                let span = self.span_of(&inner);
+                Expression::New(NewExpression {
+                    class_name: self.identifier(first_child(inner, Rule::identifier)),
+                    span,
+                })
+            }
+            _ => self.postfix(inner),
+        }
+    }
+
+    fn literal(&self, pair: Pair<'a>) -> Expression<'a> {
+        let span = self.span_of(&pair);
+        let inner = pair.into_inner().next().expect("literals have a child");
+        let text = inner.as_str();
+        let value = match inner.as_rule() {
+            Rule::string_literal => {
+                let value = &text[1..text.len() - 1];
+                if value.contains("\"\"") {
+                    LiteralValue::String(self.arena.alloc_str(&value.replace("\"\"", "\"")))
+                } else {
+                    LiteralValue::String(value)
+                }
+            }
+            Rule::number_literal => number_value(text),
+            Rule::date_literal => LiteralValue::Date(&text[1..text.len() - 1]),
+            Rule::boolean_literal => LiteralValue::Boolean(text.eq_ignore_ascii_case("true")),
+            _ => match text.to_ascii_lowercase().as_str() {
+                "nothing" => LiteralValue::Nothing,
+                "null" => LiteralValue::Null,
+                _ => LiteralValue::Empty,
+            },
+        };
+        Expression::Literal(Literal { value, span })
+    }
+
+    /// Builds a chain of member accesses and calls (`postfix_expression` or
+    /// `call_target`)
+    fn postfix(&self, pair: Pair<'a>) -> Expression<'a> {
+        let mut parts = pair.into_inner();
+        let head = parts.next().expect("postfix expressions have a head");
+        let mut result = match head.as_rule() {
+            Rule::identifier => Expression::Identifier(self.identifier(head)),
+            Rule::parenthesized_expression => {
+                let span = self.span_of(&head);
+                let expression = self.expression(first_child(head, Rule::expression));
+                Expression::Parenthesized(ParenthesizedExpression {
+                    expression: self.arena.alloc(expression),
+                    span,
+                })
+            }
+            _ => self.member(None, head),
+        };
+
+        for part in parts {
+            result = match part.as_rule() {
+                Rule::member_access => self.member(Some(result), part),
+                _ => {
+                    let span = result.span().to(self.span_of(&part));
+                    Expression::Call(CallExpression {
+                        callee: self.arena.alloc(result),
+                        arguments: self.call_arguments(part),
+                        span,
+                    })
+                }
+            };
+        }
+
+        result
+    }
+
+    fn member(&self, object: Option<Expression<'a>>, access: Pair<'a>) -> Expression<'a> {
+        let access_span = self.span_of(&access);
+        let span = object
+            .as_ref()
+            .map_or(access_span, |object| object.span().to(access_span));
+        Expression::Member(MemberExpression {
+            object: object.map(|object| &*self.arena.alloc(object)),
+            member: self.identifier(first_child(access, Rule::member_name)),
+            span,
+        })
+    }
+
+    fn call_arguments(&self, pair: Pair<'a>) -> &'a [Option<Expression<'a>>] {
+        pair.into_inner()
+            .find(|p| p.as_rule() == Rule::argument_list)
+            .map_or(&[], |p| self.argument_list(p))
+    }
+
+    fn argument_list(&self, pair: Pair<'a>) -> &'a [Option<Expression<'a>>] {
+        let mut arguments = BumpVec::new_in(self.arena);
+        let mut current = None;
+        for part in pair.into_inner() {
+            match part.as_rule() {
+                Rule::argument => {
+                    current = Some(self.expression(first_child(part, Rule::expression)))
+                }
+                _ => arguments.push(current.take()),
+            }
+        }
+        arguments.push(current);
+        arguments.into_bump_slice()
+    }
+
+    fn expressions(&self, pair: Pair<'a>) -> &'a [Expression<'a>] {
+        self.slice(
+            pair.into_inner()
+                .filter(|p| p.as_rule() == Rule::expression)
+                .map(|p| self.expression(p)),
+        )
+    }
+
+    fn identifiers(&self, pair: Pair<'a>) -> &'a [Identifier<'a>] {
+        self.slice(
+            pair.into_inner()
+                .filter(|p| p.as_rule() == Rule::identifier)
+                .map(|p| self.identifier(p)),
+        )
+    }
+
+    fn variable_declarators(&self, pair: Pair<'a>) -> &'a [VariableDeclarator<'a>] {
+        self.slice(
+            pair.into_inner()
+                .filter(|p| p.as_rule() == Rule::variable_declarator)
+                .map(|declarator| {
+                    let span = self.span_of(&declarator);
+                    let mut parts = declarator.into_inner();
+                    let name = self.identifier(parts.next().expect("declarators have a name"));
+                    VariableDeclarator {
+                        name,
+                        bounds: parts.next().map(|p| self.expressions(p)),
+                        span,
+                    }
+                }),
+        )
+    }
+
+    fn loop_condition(&self, pair: Pair<'a>) -> LoopCondition<'a> {
+        let span = self.span_of(&pair);
+        let kind = if has_child(&pair, Rule::until_keyword) {
+            LoopConditionKind::Until
+        } else {
+            LoopConditionKind::While
+        };
+        LoopCondition {
+            kind,
+            condition: self.expression(first_child(pair, Rule::expression)),
+            span,
+        }
+    }
+
+    /// Builds a call statement; a call target ending with an argument list and no
+    /// other arguments (`Foo(1, 2)` or `Call Foo(1, 2)`) is split into the called
+    /// procedure and its arguments
+    fn call_statement(
+        &self,
+        target: Expression<'a>,
+        arguments: Option<&'a [Option<Expression<'a>>]>,
+        explicit: bool,
+        span: Span,
+    ) -> Statement<'a> {
+        let (target, arguments) = match (target, arguments) {
+            (Expression::Call(call), None) => (*call.callee, call.arguments),
+            (target, arguments) => (target, arguments.unwrap_or_default()),
+        };
+        Statement::Call(CallStatement {
+            target,
+            arguments,
+            explicit,
+            span,
+        })
+    }
+
+    /// Converts a `statement` pair into a line
+    fn line(&self, statement: Pair<'a>) -> Line<'a> {
+        let pair = statement
+            .into_inner()
+            .next()
+            .expect("statements have a child");
+        let span = self.span_of(&pair);
+
+        match pair.as_rule() {
+            Rule::procedure_header | Rule::property_header => {
+                Line::Procedure(self.procedure_header(pair))
+            }
+            Rule::class_header => Line::Class(ClassDeclaration {
+                name: self.identifier(first_child(pair, Rule::identifier)),
+                body: &[],
+                span,
+            }),
+            Rule::if_header => Line::If(IfStatement {
+                condition: self.expression(first_child(pair, Rule::expression)),
+                then_branch: &[],
+                else_if_branches: &[],
+                else_branch: None,
+                single_line: false,
+                span,
+            }),
+            Rule::elseif_header => Line::ElseIf {
+                condition: self.expression(first_child(pair, Rule::expression)),
+                span,
+            },
+            Rule::else_header => Line::Else { span },
+            Rule::select_header => Line::Select(SelectStatement {
+                subject: self.expression(first_child(pair, Rule::expression)),
+                cases: &[],
+                else_branch: None,
+                span,
+            }),
+            Rule::case_header => Line::Case {
+                values: self.expressions(pair),
+                span,
+            },
+            Rule::case_else_header => Line::CaseElse { span },
+            Rule::for_header => {
+                let mut parts = pair
+                    .into_inner()
+                    .filter(|p| matches!(p.as_rule(), Rule::identifier | Rule::expression));
+                let variable = self.identifier(parts.next().expect("For has a counter"));
+                let start = self.expression(parts.next().expect("For has a start value"));
+                let end = self.expression(parts.next().expect("For has an end value"));
+                Line::For(ForStatement {
+                    variable,
+                    start,
+                    end,
+                    step: parts.next().map(|p| self.expression(p)),
+                    body: &[],
+                    span,
+                })
+            }
+            Rule::for_each_header => {
+                let mut parts = pair
+                    .into_inner()
+                    .filter(|p| matches!(p.as_rule(), Rule::identifier | Rule::expression));
+                let variable = self.identifier(parts.next().expect("For Each has a variable"));
+                Line::ForEach(ForEachStatement {
+                    variable,
+                    collection: self.expression(parts.next().expect("For Each has a collection")),
+                    body: &[],
+                    span,
+                })
+            }
+            Rule::next_statement => Line::Next {
+                variables: self.identifiers(pair).len(),
+                span,
+            },
+            Rule::do_header => Line::Do(DoLoopStatement {
+                pre_condition: pair
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::loop_condition)
+                    .map(|p| self.loop_condition(p)),
+                post_condition: None,
+                body: &[],
+                span,
+            }),
+            Rule::loop_statement => Line::Loop {
+                condition: pair
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::loop_condition)
+                    .map(|p| self.loop_condition(p)),
+                span,
+            },
+            Rule::while_header => Line::While(WhileStatement {
+                condition: self.expression(first_child(pair, Rule::expression)),
+                body: &[],
+                span,
+            }),
+            Rule::wend_statement => Line::Wend { span },
+            Rule::with_header => Line::With(WithStatement {
+                object: self.expression(first_child(pair, Rule::expression)),
+                body: &[],
+                span,
+            }),
+            Rule::end_statement => Line::End {
+                block: pair
+                    .into_inner()
+                    .nth(1)
+                    .map(|keyword| keyword.as_rule())
+                    .expect("End is followed by a block keyword"),
+                span,
+            },
+            _ => Line::Statement(self.simple_statement(pair)),
+        }
+    }
+
+    /// Converts a statement that is complete on its own
+    fn simple_statement(&self, pair: Pair<'a>) -> Statement<'a> {
+        let span = self.span_of(&pair);
+
+        match pair.as_rule() {
+            Rule::inline_statement => self.simple_statement(
+                pair.into_inner()
+                    .next()
+                    .expect("inline statements have a child"),
+            ),
+            Rule::option_explicit_statement => Statement::OptionExplicit { span },
+            Rule::dim_statement => Statement::Dim(DimStatement {
+                visibility: visibility(&pair),
+                variables: self.variable_declarators(pair),
+                span,
+            }),
+            Rule::redim_statement => Statement::ReDim(ReDimStatement {
+                preserve: has_child(&pair, Rule::preserve_keyword),
+                variables: self.variable_declarators(pair),
+                span,
+            }),
+            Rule::const_statement => Statement::Const(ConstStatement {
+                visibility: visibility(&pair),
+                constants: self.slice(
+                    pair.into_inner()
+                        .filter(|p| p.as_rule() == Rule::constant_declarator)
+                        .map(|declarator| {
+                            let span = self.span_of(&declarator);
+                            let mut parts = declarator.into_inner();
+                            let name =
+                                self.identifier(parts.next().expect("constants have a name"));
+                            ConstantDeclarator {
+                                name,
+                                value: self
+                                    .expression(parts.next().expect("constants have a value")),
+                                span,
+                            }
+                        }),
+                ),
+                span,
+            }),
+            Rule::erase_statement => Statement::Erase(EraseStatement {
+                arrays: self.identifiers(pair),
+                span,
+            }),
+            Rule::assignment | Rule::set_statement => {
+                let kind = if pair.as_rule() == Rule::set_statement {
+                    AssignmentKind::Set
+                } else {
+                    AssignmentKind::Let
+                };
+                let mut parts = pair
+                    .into_inner()
+                    .filter(|p| matches!(p.as_rule(), Rule::call_target | Rule::expression));
+                let target = self.postfix(parts.next().expect("assignments have a target"));
+                Statement::Assignment(Assignment {
+                    kind,
+                    target,
+                    value: self.expression(parts.next().expect("assignments have a value")),
+                    span,
+                })
+            }
+            Rule::explicit_call_statement => self.call_statement(
+                self.postfix(first_child(pair, Rule::postfix_expression)),
+                None,
+                true,
+                span,
+            ),
+            Rule::call_statement => {
+                let mut parts = pair.into_inner();
+                let target = self.postfix(parts.next().expect("calls have a target"));
+                let arguments = parts.next().map(|p| self.argument_list(p));
+                self.call_statement(target, arguments, false, span)
+            }
+            Rule::exit_statement => {
+                let kind = match pair.into_inner().nth(1).map(|keyword| keyword.as_rule()) {
+                    Some(Rule::do_keyword) => ExitKind::Do,
+                    Some(Rule::for_keyword) => ExitKind::For,
+                    Some(Rule::function_keyword) => ExitKind::Function,
+                    Some(Rule::sub_keyword) => ExitKind::Sub,
+                    _ => ExitKind::Property,
+                };
+                Statement::Exit(ExitStatement { kind, span })
+            }
+            Rule::on_error_statement => Statement::OnError(OnErrorStatement {
+                action: if has_child(&pair, Rule::resume_next) {
+                    OnErrorAction::ResumeNext
+                } else {
+                    OnErrorAction::GoToZero
+                },
+                span,
+            }),
+            Rule::stop_statement => Statement::Stop { span },
+            Rule::randomize_statement => Statement::Randomize(RandomizeStatement {
+                seed: pair
+                    .into_inner()
+                    .find(|p| p.as_rule() == Rule::expression)
+                    .map(|p| self.expression(p)),
+                span,
+            }),
+            Rule::single_line_if => {
+                let mut condition = None;
+                let mut branches = Vec::new();
+                for part in pair.into_inner() {
+                    match part.as_rule() {
+                        Rule::expression => condition = Some(self.expression(part)),
+                        Rule::inline_statements => branches.push(
+                            self.slice(
+                                part.into_inner()
+                                    .filter(|p| p.as_rule() == Rule::inline_statement)
+                                    .map(|p| self.simple_statement(p)),
+                            ),
+                        ),
+                        _ => {}
+                    }
+                }
+                let mut branches = branches.into_iter();
+                Statement::If(IfStatement {
+                    condition: condition.expect("If has a condition"),
+                    then_branch: branches.next().unwrap_or_default(),
+                    else_if_branches: &[],
+                    else_branch: branches.next(),
+                    single_line: true,
+                    span,
+                })
+            }
+            _ => Statement::Unparsed(self.unparsed(&pair)),
+        }
+    }
+
+    fn procedure_header(&self, pair: Pair<'a>) -> Procedure<'a> {
+        let span = self.span_of(&pair);
+        let visibility = visibility(&pair);
+        let is_default = has_child(&pair, Rule::default_keyword);
+        let mut kind = ProcedureKind::Sub;
+        let mut name = None;
+        let mut parameters: &'a [Parameter<'a>] = &[];
+
+        for part in pair.into_inner() {
+            match part.as_rule() {
+                Rule::procedure_kind | Rule::property_accessor => {
+                    kind = match part.into_inner().next().map(|keyword| keyword.as_rule()) {
+                        Some(Rule::function_keyword) => ProcedureKind::Function,
+                        Some(Rule::get_keyword) => ProcedureKind::PropertyGet,
+                        Some(Rule::let_keyword) => ProcedureKind::PropertyLet,
+                        Some(Rule::set_keyword) => ProcedureKind::PropertySet,
+                        _ => ProcedureKind::Sub,
+                    }
+                }
+                Rule::identifier => name = Some(self.identifier(part)),
+                Rule::parameter_list => {
+                    parameters = self.slice(part.into_inner().map(|parameter| {
+                        let span = self.span_of(&parameter);
+                        let is_array = parameter.as_str().trim_end().ends_with(')');
+                        let mut passing = None;
+                        let mut name = None;
+                        for piece in parameter.into_inner() {
+                            match piece.as_rule() {
+                                Rule::byval_keyword => passing = Some(ParameterPassing::ByVal),
+                                Rule::byref_keyword => passing = Some(ParameterPassing::ByRef),
+                                _ => name = Some(self.identifier(piece)),
+                            }
+                        }
+                        Parameter {
+                            name: name.expect("parameters have a name"),
+                            passing,
+                            is_array,
+                            span,
+                        }
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        Procedure {
+            kind,
+            name: name.expect("procedures have a name"),
+            visibility,
+            is_default,
+            parameters,
+            body: &[],
+            span,
+        }
+    }
+}
+
+fn visibility(pair: &Pair<'_>) -> Option<Visibility> {
+    pair.clone()
+        .into_inner()
+        .find(|p| p.as_rule() == Rule::visibility)
+        .map(|p| match p.into_inner().next().map(|k| k.as_rule()) {
+            Some(Rule::private_keyword) => Visibility::Private,
+            _ => Visibility::Public,
+        })
+}
+
+/// A line of code: a complete statement, or the header, middle or end line of a block
+enum Line<'a> {
+    Statement(Statement<'a>),
+    If(IfStatement<'a>),
+    ElseIf {
+        condition: Expression<'a>,
+        span: Span,
+    },
+    Else {
+        span: Span,
+    },
+    Select(SelectStatement<'a>),
+    Case {
+        values: &'a [Expression<'a>],
+        span: Span,
+    },
+    CaseElse {
+        span: Span,
+    },
+    For(ForStatement<'a>),
+    ForEach(ForEachStatement<'a>),
+    Next {
+        /// Number of loop variables
+        variables: usize,
+        span: Span,
+    },
+    Do(DoLoopStatement<'a>),
+    Loop {
+        condition: Option<LoopCondition<'a>>,
+        span: Span,
+    },
+    While(WhileStatement<'a>),
+    Wend {
+        span: Span,
+    },
+    With(WithStatement<'a>),
+    Procedure(Procedure<'a>),
+    Class(ClassDeclaration<'a>),
+    End {
+        block: Rule,
+        span: Span,
+    },
+}
+
+/// Branch of an If or Select Case block receiving the current statements
+enum Section<'a> {
+    Then,
+    ElseIf(Expression<'a>, Span),
+    Case(&'a [Expression<'a>], Span),
+    Else,
+}
+
+impl Section<'_> {
+    fn branch(&self) -> Branch {
+        match self {
+            Section::Then => Branch::Then,
+            Section::ElseIf(..) => Branch::ElseIf,
+            Section::Case(..) => Branch::Case,
+            Section::Else => Branch::Else,
+        }
+    }
+}
+
+/// Kind of the branch of an If or Select Case block receiving the current
+/// statements
+#[derive(Clone, Copy, PartialEq)]
+enum Branch {
+    Then,
+    ElseIf,
+    Case,
+    Else,
+}
+
+/// A block being built, waiting for its end line, as its middle and end lines are
+/// checked against
+///
+/// Unlike its [`Header`], it does not borrow the page, so that the blocks left
+/// open by a piece of a page can be resumed by the next one.
+#[derive(Clone, Copy)]
+enum Block {
+    Root,
+    If(Branch),
+    /// No branch until the first `Case`
+    Select(Option<Branch>),
+    For,
+    ForEach,
+    Do {
+        /// Whether the `Do` line has a condition
+        pre_condition: bool,
+    },
+    While,
+    With,
+    Procedure(ProcedureKind),
+    Class,
+}
+
+impl Block {
+    /// Name of the block, as written in its header
+    fn name(&self) -> &'static str {
+        match self {
+            Block::Root => "page",
+            Block::If(_) => "If",
+            Block::Select(_) => "Select Case",
+            Block::For => "For",
+            Block::ForEach => "For Each",
+            Block::Do { .. } => "Do",
+            Block::While => "While",
+            Block::With => "With",
+            Block::Procedure(kind) => procedure_name(*kind),
+            Block::Class => "Class",
+        }
+    }
+
+    /// Line closing the block
+    fn end_keyword(&self) -> &'static str {
+        match self {
+            Block::Root => "end of file",
+            Block::If(_) => "End If",
+            Block::Select(_) => "End Select",
+            Block::For | Block::ForEach => "Next",
+            Block::Do { .. } => "Loop",
+            Block::While => "Wend",
+            Block::With => "End With",
+            Block::Procedure(kind) => match kind {
+                ProcedureKind::Sub => "End Sub",
+                ProcedureKind::Function => "End Function",
+                _ => "End Property",
+            },
+            Block::Class => "End Class",
+        }
+    }
+}
+
+fn procedure_name(kind: ProcedureKind) -> &'static str {
+    match kind {
+        ProcedureKind::Sub => "Sub",
+        ProcedureKind::Function => "Function",
+        _ => "Property",
+    }
+}
+
+/// Header node of a block being built, with the branches of If and Select Case
+/// blocks found so far
+enum Header<'a> {
+    If(IfStatement<'a>, BumpVec<'a, ElseIfBranch<'a>>, Section<'a>),
+    Select(
+        SelectStatement<'a>,
+        BumpVec<'a, CaseClause<'a>>,
+        Option<Section<'a>>,
+    ),
+    For(ForStatement<'a>),
+    ForEach(ForEachStatement<'a>),
+    Do(DoLoopStatement<'a>),
+    While(WhileStatement<'a>),
+    With(WithStatement<'a>),
+    Procedure(Procedure<'a>),
+    Class(ClassDeclaration<'a>),
+}
+
+struct Frame<'a> {
+    block: Block,
+    /// Span of the block header
+    span: Span,
+    /// Header node of the block, none for the root frame and for the blocks
+    /// resumed from a previous piece of the page
+    header: Option<Header<'a>>,
+    body: BumpVec<'a, Statement<'a>>,
+}
+
+/// Nests statements into the blocks opened by header lines
+///
+/// In recovering mode, structural errors are collected instead of being returned,
+/// and blocks left open at the end of the page are closed implicitly.
+struct BlockBuilder<'a> {
+    arena: &'a Bump,
+    stack: Vec<Frame<'a>>,
+    recover: bool,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> BlockBuilder<'a> {
+    fn new(arena: &'a Bump, recover: bool) -> Self {
+        let mut blocks = BlockBuilder::resume(arena, &[(Block::Root, Span::default())]);
+        blocks.recover = recover;
+        blocks
+    }
+
+    /// Resumes, in recovering mode, the blocks left open by the previous pieces of
+    /// a page, as returned by [`BlockBuilder::open_blocks`]
+    ///
+    /// The statements of the previous pieces are not kept: closing a resumed block
+    /// only checks where it ends.
+    fn resume(arena: &'a Bump, open_blocks: &[(Block, Span)]) -> Self {
+        let stack = open_blocks
+            .iter()
+            .map(|&(block, span)| Frame {
+                block,
+                span,
+                header: None,
+                body: BumpVec::new_in(arena),
+            })
+            .collect();
+        BlockBuilder {
+            arena,
+            stack,
+            recover: true,
+            diagnostics: Vec::new(),
+        }
+    }
+
+    /// Returns the blocks left open, from the root of the page to the innermost one
+    fn open_blocks(&self) -> Vec<(Block, Span)> {
+        self.stack
+            .iter()
+            .map(|frame| (frame.block, frame.span))
+            .collect()
+    }
+
+    fn top(&mut self) -> &mut Frame<'a> {
+        self.stack
+            .last_mut()
+            .expect("the root frame is never removed")
+    }
+
+    fn current(&self) -> &Frame<'a> {
+        self.stack.last().expect("the root frame is never removed")
+    }
+
+    /// Adds a statement, collecting the error in recovering mode
+    fn add(&mut self, statement: Statement<'a>) -> Result<(), Diagnostic> {
+        let result = self.push(statement);
+        self.report(result)
+    }
+
+    /// Processes a line of code, collecting the error in recovering mode
+    fn add_line(&mut self, line: Line<'a>) -> Result<(), Diagnostic> {
+        let result = self.line(line);
+        self.report(result)
+    }
+
+    fn report(&mut self, result: Result<(), Diagnostic>) -> Result<(), Diagnostic> {
+        match result {
+            Err(diagnostic) if self.recover => {
+                self.diagnostics.push(diagnostic);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Adds a statement to the innermost open block
+    fn push(&mut self, statement: Statement<'a>) -> Result<(), Diagnostic> {
+        // Only blank markup may appear between Select Case and the first Case
+        if let Block::Select(None) = self.current().block
+            && let Statement::Html(html) = &statement
+            && html.text.trim().is_empty()
+        {
+            return Ok(());
+        }
+        self.check_placement(statement.span())?;
+        self.top().body.push(statement);
+        Ok(())
+    }
+
+    /// Checks that a statement may be added to the innermost open block
+    fn check_placement(&self, span: Span) -> Result<(), Diagnostic> {
+        if let Block::Select(None) = self.current().block {
+            return Err(Diagnostic::new(
+                DiagnosticCode::MisplacedStatement,
+                span,
+                "Statements are not allowed between 'Select Case' and the first 'Case'".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Adds a closed block to its parent
+    fn push_block(&mut self, frame: Frame<'a>, end: Span) -> Result<(), Diagnostic> {
+        let span = frame.span.to(end);
+        match close_frame(frame, end) {
+            Some(statement) => self.push(statement),
+            None => self.check_placement(span),
+        }
+    }
+
+    fn open(&mut self, block: Block, span: Span, header: Header<'a>) {
+        let body = BumpVec::new_in(self.arena);
+        self.stack.push(Frame {
+            block,
+            span,
+            header: Some(header),
+            body,
+        });
+    }
+
+    /// Processes a line of code
+    fn line(&mut self, line: Line<'a>) -> Result<(), Diagnostic> {
+        match line {
+            Line::Statement(statement) => self.push(statement)?,
+            Line::If(statement) => {
+                let branches = BumpVec::new_in(self.arena);
+                let header = Header::If(statement, branches, Section::Then);
+                self.open(Block::If(Branch::Then), statement.span, header)
+            }
+            Line::Select(statement) => {
+                let cases = BumpVec::new_in(self.arena);
+                let header = Header::Select(statement, cases, None);
+                self.open(Block::Select(None), statement.span, header)
+            }
+            Line::For(statement) => self.open(Block::For, statement.span, Header::For(statement)),
+            Line::ForEach(statement) => {
+                self.open(Block::ForEach, statement.span, Header::ForEach(statement))
+            }
+            Line::Do(statement) => {
+                let block = Block::Do {
+                    pre_condition: statement.pre_condition.is_some(),
+                };
+                self.open(block, statement.span, Header::Do(statement))
+            }
+            Line::While(statement) => {
+                self.open(Block::While, statement.span, Header::While(statement))
+            }
+            Line::With(statement) => {
+                self.open(Block::With, statement.span, Header::With(statement))
+            }
+            Line::Procedure(procedure) => {
+                if !matches!(self.current().block, Block::Root | Block::Class) {
+                    return Err(Diagnostic::new(
+                        DiagnosticCode::MisplacedStatement,
+                        procedure.span,
+                        format!(
+                            "'{}' definitions are only allowed at the top level or in a class",
+                            procedure_name(procedure.kind)
+                        ),
+                    ));
+                }
+                let block = Block::Procedure(procedure.kind);
+                self.open(block, procedure.span, Header::Procedure(procedure));
+            }
+            Line::Class(class) => {
+                if !matches!(self.current().block, Block::Root) {
+                    return Err(Diagnostic::new(
+                        DiagnosticCode::MisplacedStatement,
+                        class.span,
+                        "'Class' definitions are only allowed at the top level".to_string(),
+                    ));
+                }
+                self.open(Block::Class, class.span, Header::Class(class));
+            }
+            Line::ElseIf { condition, span } => {
+                self.next_section("ElseIf", span, Section::ElseIf(condition, span))?
+            }
+            Line::Else { span } => self.next_section("Else", span, Section::Else)?,
+            Line::Case { values, span } => {
+                self.next_section("Case", span, Section::Case(values, span))?
+            }
+            Line::CaseElse { span } => self.next_section("Case Else", span, Section::Else)?,
+            Line::Next { variables, span } => {
+                // `Next i, j` closes two loops
+                for _ in 0..variables.max(1) {
+                    self.close("Next", span, |block| {
+                        matches!(block, Block::For | Block::ForEach)
+                    })?;
+                }
+            }
+            Line::Loop { condition, span } => {
+                let mut frame =
+                    self.pop("Loop", span, |block| matches!(block, Block::Do { .. }))?;
+                let has_both_conditions = condition.is_some()
+                    && matches!(
+                        frame.block,
+                        Block::Do {
+                            pre_condition: true
+                        }
+                    );
+                if let Some(Header::Do(statement)) = &mut frame.header {
+                    statement.post_condition = condition;
+                }
+                self.push_block(frame, span)?;
+                if has_both_conditions {
+                    return Err(Diagnostic::new(
+                        DiagnosticCode::SyntaxError,
+                        span,
+                        "A 'Do' loop cannot have a condition on both 'Do' and 'Loop'".to_string(),
+                    ));
+                }
+            }
+            Line::Wend { span } => {
+                self.close("Wend", span, |block| matches!(block, Block::While))?
+            }
+            Line::End { block, span } => {
+                let (keyword, closes): (&str, fn(&Block) -> bool) = match block {
+                    Rule::if_keyword => ("End If", |b| matches!(b, Block::If(_))),
+                    Rule::select_keyword => ("End Select", |b| matches!(b, Block::Select(_))),
+                    Rule::with_keyword => ("End With", |b| matches!(b, Block::With)),
+                    Rule::sub_keyword => ("End Sub", |b| {
+                        matches!(b, Block::Procedure(ProcedureKind::Sub))
+                    }),
+                    Rule::function_keyword => ("End Function", |b| {
+                        matches!(b, Block::Procedure(ProcedureKind::Function))
+                    }),
+                    Rule::property_keyword => (
+                        "End Property",
+                        |b| matches!(b, Block::Procedure(kind) if !matches!(kind, ProcedureKind::Sub | ProcedureKind::Function)),
+                    ),
+                    _ => ("End Class", |b| matches!(b, Block::Class)),
+                };
+                self.close(keyword, span, closes)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches an If or Select Case block to its next branch
+    fn next_section(
+        &mut self,
+        keyword: &str,
+        span: Span,
+        section: Section<'a>,
+    ) -> Result<(), Diagnostic> {
+        let is_case = matches!(section, Section::Case(..)) || keyword == "Case Else";
+        let message = match (self.current().block, is_case) {
+            (Block::If(Branch::Else), false) => {
+                Some(format!("'{}' cannot follow the 'Else' branch", keyword))
+            }
+            (Block::Select(Some(Branch::Else)), true) => Some(format!(
+                "'{}' cannot follow the 'Case Else' branch",
+                keyword
+            )),
+            (Block::If(_), false) | (Block::Select(_), true) => None,
+            (_, true) => Some(format!("'{}' without a matching 'Select Case'", keyword)),
+            (_, false) => Some(format!("'{}' without a matching 'If'", keyword)),
+        };
+        if let Some(message) = message {
+            return Err(Diagnostic::new(
+                DiagnosticCode::UnmatchedBlockEnd,
+                span,
+                message,
+            ));
+        }
+
+        let arena = self.arena;
+        let frame = self.top();
+        let body = std::mem::replace(&mut frame.body, BumpVec::new_in(arena));
+        match &mut frame.block {
+            Block::If(branch) => *branch = section.branch(),
+            Block::Select(branch) => *branch = Some(section.branch()),
+            _ => unreachable!("checked above"),
+        }
+        match &mut frame.header {
+            Some(Header::If(statement, branches, current)) => {
+                let previous = std::mem::replace(current, section);
+                flush_if_section(statement, branches, previous, body, span.start);
+            }
+            Some(Header::Select(statement, cases, current)) => {
+                if let Some(previous) = current.replace(section) {
+                    flush_case_section(statement, cases, previous, body, span.start);
+                }
+            }
+            // The previous branches of a resumed block are not kept
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Closes the innermost block and adds it to its parent
+    fn close(
+        &mut self,
+        keyword: &str,
+        span: Span,
+        closes: fn(&Block) -> bool,
+    ) -> Result<(), Diagnostic> {
+        let frame = self.pop(keyword, span, closes)?;
+        self.push_block(frame, span)
+    }
+
+    /// Removes the innermost block if `keyword` closes it
+    fn pop(
+        &mut self,
+        keyword: &str,
+        span: Span,
+        closes: fn(&Block) -> bool,
+    ) -> Result<Frame<'a>, Diagnostic> {
+        let frame = self.current();
+        if closes(&frame.block) {
+            return Ok(self.stack.pop().expect("checked above"));
+        }
+
+        let message = match frame.block {
+            Block::Root => format!("'{}' without a matching block", keyword),
+            block => format!(
+                "'{}' found where '{}' was expected to close the '{}' block at line {}",
+                keyword,
+                block.end_keyword(),
+                block.name(),
+                frame.span.line
+            ),
+        };
+        Err(Diagnostic::new(
+            DiagnosticCode::UnmatchedBlockEnd,
+            span,
+            message,
+        ))
+    }
+
+    /// Returns the top-level statements and the collected errors, checking that
+    /// every block was closed
+    fn finish(mut self) -> Result<(&'a [Statement<'a>], Vec<Diagnostic>), Diagnostic> {
+        while self.stack.len() > 1 {
+            let frame = self.current();
+            let message = format!(
+                "'{}' block is not closed: expected '{}'",
+                frame.block.name(),
+                frame.block.end_keyword()
+            );
+            let error = Diagnostic::new(DiagnosticCode::UnclosedBlock, frame.span, message);
+            if !self.recover {
+                return Err(error);
+            }
+            self.diagnostics.push(error);
+
+            // Close the block after its last statement
+            let frame = self.stack.pop().expect("checked above");
+            let end = frame
+                .body
+                .last()
+                .map_or(frame.span, |statement| statement.span());
+            let result = self.push_block(frame, end);
+            self.report(result)?;
+        }
+
+        let frame = self.stack.pop().expect("the root frame is never removed");
+        Ok((frame.body.into_bump_slice(), self.diagnostics))
+    }
+}
+
+/// Builds the statement of a block, ending with the `end` span, or nothing for a
+/// block resumed without its header
+fn close_frame(frame: Frame<'_>, end: Span) -> Option<Statement<'_>> {
+    let body = frame.body;
+    let statement = match frame.header? {
+        Header::If(mut statement, mut branches, section) => {
+            flush_if_section(&mut statement, &mut branches, section, body, end.start);
+            statement.else_if_branches = branches.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::If(statement)
+        }
+        Header::Select(mut statement, mut cases, section) => {
+            if let Some(section) = section {
+                flush_case_section(&mut statement, &mut cases, section, body, end.start);
+            }
+            statement.cases = cases.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::Select(statement)
+        }
+        Header::For(mut statement) => {
+            statement.body = body.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::For(statement)
+        }
+        Header::ForEach(mut statement) => {
+            statement.body = body.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::ForEach(statement)
+        }
+        Header::Do(mut statement) => {
+            statement.body = body.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::DoLoop(statement)
+        }
+        Header::While(mut statement) => {
+            statement.body = body.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::While(statement)
+        }
+        Header::With(mut statement) => {
+            statement.body = body.into_bump_slice();
+            statement.span = statement.span.to(end);
+            Statement::With(statement)
+        }
+        Header::Procedure(mut procedure) => {
+            procedure.body = body.into_bump_slice();
+            procedure.span = procedure.span.to(end);
+            Statement::Procedure(procedure)
+        }
+        Header::Class(mut class) => {
+            class.body = body.into_bump_slice();
+            class.span = class.span.to(end);
+            Statement::Class(class)
+        }
+    };
+    Some(statement)
+}
+
+/// Stores the statements of the current branch of an If block
+fn flush_if_section<'a>(
+    statement: &mut IfStatement<'a>,
+    branches: &mut BumpVec<'a, ElseIfBranch<'a>>,
+    section: Section<'a>,
+    body: BumpVec<'a, Statement<'a>>,
+    end: usize,
+) {
+    let body = body.into_bump_slice();
+    match section {
+        Section::Then => statement.then_branch = body,
+        Section::ElseIf(condition, header) => branches.push(ElseIfBranch {
+            condition,
+            body,
+            span: Span { end, ..header },
+        }),
+        Section::Else => statement.else_branch = Some(body),
+        Section::Case(..) => unreachable!("If blocks have no Case branch"),
+    }
+}
+
+/// Stores the statements of the current clause of a Select Case block
+fn flush_case_section<'a>(
+    statement: &mut SelectStatement<'a>,
+    cases: &mut BumpVec<'a, CaseClause<'a>>,
+    section: Section<'a>,
+    body: BumpVec<'a, Statement<'a>>,
+    end: usize,
+) {
+    let body = body.into_bump_slice();
+    match section {
+        Section::Case(values, header) => cases.push(CaseClause {
+            values,
+            body,
+            span: Span { end, ..header },
+        }),
+        Section::Else => statement.else_branch = Some(body),
+        _ => {}
+    }
+}
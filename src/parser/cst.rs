@@ -0,0 +1,581 @@
+//! Lossless concrete syntax tree of ASP Classic pages
+//!
+//! Unlike the [`ast`](super::ast), the concrete syntax tree keeps every byte of the
+//! source: whitespace, newlines, comments and line continuations are kept as
+//! [`Trivia`] attached to the neighbouring [`SyntaxToken`], so that printing the tree
+//! gives back the input exactly. This is the representation to use for formatters
+//! and refactoring tools.
+//!
+//! Nodes follow the structure of the grammar, one statement or block line at a time:
+//! the kind of a node is the name of the grammar rule it was built from (such as
+//! `if_header` or `assignment`), and blocks are not nested as in the syntax tree.
+//!
+//! Trivia following a token on the same line is its trailing trivia; the rest
+//! (newlines and the indentation of the next line) is the leading trivia of the
+//! next token. Trivia at the end of the page is attached to the final `EOI` token.
+//!
+//! ```
+//! use asp_classic_parser::parser;
+//!
+//! let page = "<%\nx = 1 ' counter\n%>";
+//! let tree = parser::parse_cst(page).unwrap();
+//! assert_eq!(tree.to_string(), page);
+//!
+//! let one = tree.root.tokens().into_iter().find(|t| t.text == "1").unwrap();
+//! assert_eq!(one.trailing_trivia[1].text, "' counter");
+//! ```
+
+use std::fmt;
+
+use pest::Parser;
+use pest::iterators::Pair;
+use serde::Serialize;
+
+use super::ast::{LineIndex, Span};
+use super::builder::{pest_error, script_code, script_language_attribute};
+use super::jscript::{self, JScriptParser};
+use super::lexer::{self, TokenKind};
+use super::{AspParseError, AspParser, Rule, ScriptLanguage};
+
+/// Kinds of trivia
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TriviaKind {
+    /// Spaces and tabs
+    Whitespace,
+    /// A line break (`\n`, `\r\n` or `\r`)
+    Newline,
+    /// A comment, without the line break ending it
+    Comment,
+    /// A VBScript line continuation (`_` and the line break following it)
+    LineContinuation,
+}
+
+/// Source text that does not change the meaning of the code
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Trivia {
+    /// Kind of trivia
+    pub kind: TriviaKind,
+    /// The text, as written in the source
+    pub text: String,
+    /// Location of the text
+    pub span: Span,
+}
+
+/// A token and the trivia around it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxToken {
+    /// Name of the grammar rule of the token, or the [`name`](TokenKind::name) of
+    /// its token kind (`keyword`, `operator`, ...) for the keywords and symbols the
+    /// grammar does not name
+    pub kind: String,
+    /// The text of the token, as written in the source
+    pub text: String,
+    /// Location of the token, trivia excluded
+    pub span: Span,
+    /// Trivia before the token
+    pub leading_trivia: Vec<Trivia>,
+    /// Trivia after the token, up to the end of its line
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+/// A node of the tree, built from a grammar rule
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxNode {
+    /// Name of the grammar rule of the node
+    pub kind: String,
+    /// Location of the node, leading and trailing trivia excluded
+    pub span: Span,
+    /// Child nodes and tokens, in source order
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A child of a node
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "element")]
+pub enum SyntaxElement {
+    /// A node with children
+    Node(SyntaxNode),
+    /// A token
+    Token(SyntaxToken),
+}
+
+/// Concrete syntax tree of a page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxTree {
+    /// Server-side language of the page
+    pub language: ScriptLanguage,
+    /// Root node, of kind `file`
+    pub root: SyntaxNode,
+}
+
+impl SyntaxNode {
+    /// Returns the tokens of the node and of its descendants, in source order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+        collect_tokens(self, &mut tokens);
+        tokens
+    }
+}
+
+impl SyntaxTree {
+    /// Writes the tree as indented text, one node or token per line
+    ///
+    /// Each line gives the kind of the element, its byte range and the line and
+    /// column of its start; tokens are followed by their text. Trivia is left out.
+    ///
+    /// ```
+    /// use asp_classic_parser::parser;
+    ///
+    /// let tree = parser::parse_cst("<%\nx = 1\n%>").unwrap();
+    /// let dump = tree.dump();
+    /// assert!(dump.starts_with("file@0..11 [1:1]\n"));
+    /// assert!(dump.contains("\n        assignment@3..8 [2:1]\n"));
+    /// assert!(dump.contains("\n    asp_close_tag@9..11 [3:1] \"%>\"\n"));
+    /// ```
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        dump_node(&self.root, 0, &mut out);
+        out
+    }
+}
+
+fn dump_node(node: &SyntaxNode, depth: usize, out: &mut String) {
+    let span = node.span;
+    out.push_str(&format!(
+        "{:indent$}{}@{}..{} [{}:{}]\n",
+        "",
+        node.kind,
+        span.start,
+        span.end,
+        span.line,
+        span.column,
+        indent = depth * 2
+    ));
+    for child in &node.children {
+        match child {
+            SyntaxElement::Node(child) => dump_node(child, depth + 1, out),
+            SyntaxElement::Token(token) => {
+                let span = token.span;
+                out.push_str(&format!(
+                    "{:indent$}{}@{}..{} [{}:{}] {:?}\n",
+                    "",
+                    token.kind,
+                    span.start,
+                    span.end,
+                    span.line,
+                    span.column,
+                    token.text,
+                    indent = (depth + 1) * 2
+                ));
+            }
+        }
+    }
+}
+
+fn collect_tokens<'a>(node: &'a SyntaxNode, tokens: &mut Vec<&'a SyntaxToken>) {
+    for child in &node.children {
+        match child {
+            SyntaxElement::Node(node) => collect_tokens(node, tokens),
+            SyntaxElement::Token(token) => tokens.push(token),
+        }
+    }
+}
+
+impl fmt::Display for SyntaxToken {
+    /// Writes the token with its trivia
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for trivia in &self.leading_trivia {
+            f.write_str(&trivia.text)?;
+        }
+        f.write_str(&self.text)?;
+        for trivia in &self.trailing_trivia {
+            f.write_str(&trivia.text)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    /// Writes the source text of the node, trivia included
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in self.tokens() {
+            write!(f, "{}", token)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SyntaxTree {
+    /// Writes the source text of the page, identical to the parsed input
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root)
+    }
+}
+
+/// Grammar rules, as seen by the tree builder
+trait CstRule: pest::RuleType {
+    /// Language whose comments and strings appear between the rules
+    const LANGUAGE: ScriptLanguage;
+
+    /// Whether the text of the rule is a single token, even if it contains spaces
+    /// or comment characters (strings, HTML, unparsed code, ...)
+    fn is_opaque(self) -> bool;
+
+    /// Whether the rule is the content of a `<script runat="server">` block
+    fn is_script_content(self) -> bool;
+
+    /// Whether the rule is the end of the input
+    fn is_end_of_input(self) -> bool;
+}
+
+impl CstRule for Rule {
+    const LANGUAGE: ScriptLanguage = ScriptLanguage::VBScript;
+
+    fn is_opaque(self) -> bool {
+        matches!(
+            self,
+            Rule::string_literal
+                | Rule::quoted_text
+                | Rule::identifier
+                | Rule::member_name
+                | Rule::date_literal
+                | Rule::other_statement
+                | Rule::raw_expression
+                | Rule::html_content
+                | Rule::html_comment
+                | Rule::html_attribute_name
+                | Rule::html_attribute_value
+                | Rule::directive_value_text
+                | Rule::directive_single_quoted_text
+                | Rule::directive_bare_value
+                | Rule::server_script_content
+        )
+    }
+
+    fn is_script_content(self) -> bool {
+        self == Rule::server_script_content
+    }
+
+    fn is_end_of_input(self) -> bool {
+        self == Rule::EOI
+    }
+}
+
+impl CstRule for jscript::Rule {
+    const LANGUAGE: ScriptLanguage = ScriptLanguage::JScript;
+
+    fn is_opaque(self) -> bool {
+        use jscript::Rule;
+        matches!(
+            self,
+            Rule::string_literal
+                | Rule::html_content
+                | Rule::html_comment
+                | Rule::html_attribute_name
+                | Rule::html_attribute_value
+                | Rule::directive_value_text
+                | Rule::directive_single_quoted_text
+                | Rule::directive_bare_value
+                | Rule::server_script_content
+        )
+    }
+
+    fn is_script_content(self) -> bool {
+        self == jscript::Rule::server_script_content
+    }
+
+    fn is_end_of_input(self) -> bool {
+        self == jscript::Rule::EOI
+    }
+}
+
+/// Node of the tree before trivia is attached to tokens
+enum Raw {
+    Node {
+        kind: String,
+        start: usize,
+        end: usize,
+        children: Vec<Raw>,
+    },
+    Token {
+        kind: String,
+        start: usize,
+        end: usize,
+    },
+    Trivia {
+        kind: TriviaKind,
+        start: usize,
+        end: usize,
+    },
+}
+
+/// Parses a page into its concrete syntax tree
+///
+/// `text` is the page with normalized line endings, used for parsing; the text of
+/// tokens and trivia is taken from `source`, which has the same byte offsets.
+pub(super) fn build_syntax_tree(
+    source: &str,
+    text: &str,
+    language: ScriptLanguage,
+) -> Result<SyntaxTree, AspParseError> {
+    let builder = CstBuilder {
+        source,
+        text,
+        lines: LineIndex::new(text),
+        language,
+    };
+    let root = match language {
+        ScriptLanguage::VBScript => {
+            let file = AspParser::parse(Rule::file, text)
+                .map_err(|e| pest_error(&builder.lines, e.into()))?
+                .next()
+                .expect("the file rule always produces a pair");
+            builder.pair(file, 0)?
+        }
+        ScriptLanguage::JScript => {
+            let file = JScriptParser::parse(jscript::Rule::file, text)
+                .map_err(|e| pest_error(&builder.lines, e.into()))?
+                .next()
+                .expect("the file rule always produces a pair");
+            builder.pair(file, 0)?
+        }
+    };
+
+    let attachments = attach_trivia(&root);
+    let mut tokens = attachments.into_iter();
+    match builder.element(root, &mut tokens) {
+        SyntaxElement::Node(root) => Ok(SyntaxTree { language, root }),
+        SyntaxElement::Token(_) => unreachable!("the file rule has children"),
+    }
+}
+
+struct CstBuilder<'a> {
+    source: &'a str,
+    text: &'a str,
+    lines: LineIndex<'a>,
+    language: ScriptLanguage,
+}
+
+impl CstBuilder<'_> {
+    /// Converts a pair, ignoring the text before `floor`
+    fn pair<R: CstRule>(&self, pair: Pair<'_, R>, floor: usize) -> Result<Raw, AspParseError> {
+        let rule = pair.as_rule();
+        let kind = format!("{:?}", rule);
+        let start = pair.as_span().start().max(floor);
+        let end = pair.as_span().end().max(start);
+
+        let mut children = Vec::new();
+        let mut position = start;
+        let mut previous_text = "";
+        for child in pair.into_inner() {
+            let child_start = child.as_span().start();
+            // Skip the end of input of server script code, parsed separately
+            if child.as_rule().is_end_of_input() && floor > 0 {
+                continue;
+            }
+            lex::<R>(
+                self.text,
+                position,
+                child_start.max(position),
+                &mut children,
+            );
+            position = child.as_span().end().max(position);
+            let text = child.as_str();
+            if child.as_rule().is_script_content() {
+                children.push(self.script_content(child, previous_text)?);
+            } else {
+                children.push(self.pair(child, floor)?);
+            }
+            previous_text = text;
+        }
+
+        if children.is_empty() {
+            if rule.is_opaque() {
+                return Ok(Raw::Token { kind, start, end });
+            }
+            // Rules made of literals only (`<%`, `:`, ...) are a single token
+            lex::<R>(self.text, start, end, &mut children);
+            if !children
+                .iter()
+                .any(|child| matches!(child, Raw::Trivia { .. }))
+            {
+                return Ok(Raw::Token { kind, start, end });
+            }
+        } else {
+            lex::<R>(self.text, position, end, &mut children);
+        }
+
+        Ok(Raw::Node {
+            kind,
+            start,
+            end,
+            children,
+        })
+    }
+
+    /// Converts the content of a `<script runat="server">` block, parsing it when it
+    /// is VBScript code
+    fn script_content<R: CstRule>(
+        &self,
+        content: Pair<'_, R>,
+        open_tag: &str,
+    ) -> Result<Raw, AspParseError> {
+        let language = script_language_attribute(open_tag).unwrap_or(self.language);
+        let start = content.as_span().start();
+        let end = content.as_span().end();
+        if language != ScriptLanguage::VBScript {
+            return Ok(Raw::Token {
+                kind: format!("{:?}", content.as_rule()),
+                start,
+                end,
+            });
+        }
+
+        let code = script_code(self.text, start, content.as_str());
+        let script = AspParser::parse(Rule::server_script_code, &code)
+            .map_err(|e| pest_error(&self.lines, e.into()))?
+            .next()
+            .expect("the server script rule always produces a pair");
+        let children = match self.pair(script, start)? {
+            Raw::Node { children, .. } => children,
+            token => vec![token],
+        };
+        Ok(Raw::Node {
+            kind: format!("{:?}", content.as_rule()),
+            start,
+            end,
+            children,
+        })
+    }
+
+    /// Converts a raw node, taking the trivia of its tokens from `attachments`
+    fn element(
+        &self,
+        raw: Raw,
+        attachments: &mut impl Iterator<Item = Attachment>,
+    ) -> SyntaxElement {
+        match raw {
+            Raw::Node {
+                kind,
+                start,
+                end,
+                children,
+            } => SyntaxElement::Node(SyntaxNode {
+                kind,
+                span: self.lines.span(start, end),
+                children: children
+                    .into_iter()
+                    .filter(|child| !matches!(child, Raw::Trivia { .. }))
+                    .map(|child| self.element(child, attachments))
+                    .collect(),
+            }),
+            Raw::Token { kind, start, end } => {
+                let (leading, trailing) =
+                    attachments.next().expect("every token has an attachment");
+                SyntaxElement::Token(SyntaxToken {
+                    kind,
+                    text: self.source[start..end].to_string(),
+                    span: self.lines.span(start, end),
+                    leading_trivia: self.trivia(leading),
+                    trailing_trivia: self.trivia(trailing),
+                })
+            }
+            Raw::Trivia { .. } => unreachable!("trivia is filtered out"),
+        }
+    }
+
+    fn trivia(&self, pieces: Vec<(TriviaKind, usize, usize)>) -> Vec<Trivia> {
+        pieces
+            .into_iter()
+            .map(|(kind, start, end)| Trivia {
+                kind,
+                text: self.source[start..end].to_string(),
+                span: self.lines.span(start, end),
+            })
+            .collect()
+    }
+}
+
+/// Leading and trailing trivia of a token, as `(kind, start, end)` ranges
+type Attachment = (
+    Vec<(TriviaKind, usize, usize)>,
+    Vec<(TriviaKind, usize, usize)>,
+);
+
+/// Decides which token each trivia belongs to, returning the trivia of each token
+/// in source order
+fn attach_trivia(root: &Raw) -> Vec<Attachment> {
+    let mut leaves = Vec::new();
+    flatten(root, &mut leaves);
+
+    let mut attachments: Vec<Attachment> = Vec::new();
+    let mut pending = Vec::new();
+    let mut same_line = false;
+    for leaf in leaves {
+        match *leaf {
+            Raw::Token { .. } => {
+                attachments.push((std::mem::take(&mut pending), Vec::new()));
+                same_line = true;
+            }
+            Raw::Trivia { kind, start, end } => {
+                match attachments.last_mut() {
+                    Some((_, trailing)) if same_line && kind != TriviaKind::Newline => {
+                        trailing.push((kind, start, end))
+                    }
+                    _ => pending.push((kind, start, end)),
+                }
+                if matches!(kind, TriviaKind::Newline | TriviaKind::LineContinuation) {
+                    same_line = false;
+                }
+            }
+            Raw::Node { .. } => unreachable!("nodes are flattened"),
+        }
+    }
+
+    // Trivia after the last token (there is always an end of input token)
+    if let Some((_, trailing)) = attachments.last_mut() {
+        trailing.append(&mut pending);
+    }
+    attachments
+}
+
+fn flatten<'a>(raw: &'a Raw, leaves: &mut Vec<&'a Raw>) {
+    match raw {
+        Raw::Node { children, .. } => {
+            for child in children {
+                flatten(child, leaves);
+            }
+        }
+        leaf => leaves.push(leaf),
+    }
+}
+
+/// Splits the text between two rules into trivia and unnamed tokens (keywords and
+/// symbols written as literals in the grammar)
+fn lex<R: CstRule>(text: &str, start: usize, end: usize, out: &mut Vec<Raw>) {
+    let mut position = start;
+    while position < end {
+        let (length, kind) = lexer::code_token(&text[position..end], R::LANGUAGE);
+        let piece_end = position + length;
+        let trivia = match kind {
+            TokenKind::Whitespace => Some(TriviaKind::Whitespace),
+            TokenKind::Newline => Some(TriviaKind::Newline),
+            TokenKind::Comment => Some(TriviaKind::Comment),
+            TokenKind::LineContinuation => Some(TriviaKind::LineContinuation),
+            _ => None,
+        };
+        out.push(match trivia {
+            Some(kind) => Raw::Trivia {
+                kind,
+                start: position,
+                end: piece_end,
+            },
+            None => Raw::Token {
+                kind: kind.name().to_string(),
+                start: position,
+                end: piece_end,
+            },
+        });
+        position = piece_end;
+    }
+}
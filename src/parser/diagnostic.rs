@@ -0,0 +1,415 @@
+//! Diagnostics reported by the parser
+//!
+//! [`parse_recovering`](super::parse_recovering) reports every syntax error of a page
+//! as a [`Diagnostic`], instead of stopping at the first one like
+//! [`parse`](super::parse).
+//!
+//! Each diagnostic carries a stable [`DiagnosticCode`] (`ASP001`, `ASP002`, ...) that
+//! tools can match on instead of parsing the message, and that users can refer to
+//! to suppress a specific kind of diagnostic.
+//!
+//! Diagnostics implement [`serde::Serialize`] and [`serde::Deserialize`], their
+//! codes being written as in reports (`ASP001`), so that they can be stored, as
+//! in the parsing cache.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::ast::Span;
+
+/// Stable identifier of a kind of diagnostic
+///
+/// Codes are never reused: a code keeps its meaning across releases, while the
+/// wording of messages may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticCode {
+    /// `ASP001`: the code does not match the language grammar
+    SyntaxError,
+    /// `ASP002`: a block (`If`, `For`, `Sub`, ...) is never closed
+    UnclosedBlock,
+    /// `ASP003`: a block end or branch (`End If`, `Next`, `Else`, `Case`, ...) does
+    /// not match the innermost open block
+    UnmatchedBlockEnd,
+    /// `ASP004`: a valid statement appears where it is not allowed, such as a `Sub`
+    /// nested in another procedure
+    MisplacedStatement,
+    /// `ASP005`: the file contains no ASP code
+    NoAspTags,
+    /// `ASP006`: the file is empty or contains only whitespace
+    EmptyFile,
+    /// `ASP007`: the page is written in a language that is not allowed by the
+    /// [`ParseOptions`](super::ParseOptions)
+    DisallowedLanguage,
+    /// `ASP008`: the page contains an `#include` directive while includes are
+    /// forbidden by the [`ParseOptions`](super::ParseOptions)
+    ForbiddenInclude,
+    /// `ASP009`: a name is used but declared nowhere, in a page with
+    /// `Option Explicit`
+    UndeclaredName,
+    /// `ASP010`: a variable is declared but its value is never read
+    UnusedVariable,
+    /// `ASP011`: a procedure parameter is never used
+    UnusedParameter,
+    /// `ASP012`: a statement follows an `Exit`, `Response.End` or `Err.Raise` in
+    /// the same block and can never run
+    UnreachableCode,
+    /// `ASP013`: a sub or function is called nowhere in the include graph
+    UnusedProcedure,
+    /// `ASP014`: an object is assigned without `Set`
+    MissingSet,
+    /// `ASP015`: `Set` is used to assign a value that is not an object
+    SetOnValue,
+    /// `ASP016`: a name is declared twice in the same scope
+    DuplicateDeclaration,
+    /// `ASP017`: a local variable or parameter hides a page-level variable or a
+    /// class field
+    ShadowedVariable,
+    /// `ASP018`: a procedure or class has the same name as a variable
+    NameConflict,
+    /// `ASP019`: errors are ignored by `On Error Resume Next` but never checked
+    UncheckedError,
+    /// `ASP020`: a request value is written to the page without being encoded
+    CrossSiteScripting,
+    /// `ASP021`: a password, an API key or the `sa` account is written in the code
+    HardcodedCredentials,
+    /// `ASP022`: code run from a string, dangerous object or function not supported by ASP
+    DangerousFunction,
+    /// `ASP023`: a procedure has a cyclomatic complexity above the threshold
+    ComplexProcedure,
+    /// `ASP024`: a procedure has more lines than allowed
+    LongProcedure,
+    /// `ASP025`: a procedure has blocks nested deeper than allowed
+    DeepNesting,
+    /// `ASP026`: a name does not follow the naming conventions
+    NamingConvention,
+    /// `ASP027`: a problem reported by an external plugin
+    PluginRule,
+    /// `ASP028`: the file is not valid UTF-8 and has neither byte order mark nor
+    /// `CodePage` directive, so its encoding is assumed
+    EncodingAssumed,
+    /// `ASP029`: a file found in a directory is larger than the maximum file size,
+    /// so it is skipped
+    FileTooLarge,
+    /// `ASP030`: a member of an ASP object that the object does not have
+    UnknownMember,
+}
+
+impl DiagnosticCode {
+    /// Every diagnostic code, in code order
+    pub const ALL: [DiagnosticCode; 30] = [
+        DiagnosticCode::SyntaxError,
+        DiagnosticCode::UnclosedBlock,
+        DiagnosticCode::UnmatchedBlockEnd,
+        DiagnosticCode::MisplacedStatement,
+        DiagnosticCode::NoAspTags,
+        DiagnosticCode::EmptyFile,
+        DiagnosticCode::DisallowedLanguage,
+        DiagnosticCode::ForbiddenInclude,
+        DiagnosticCode::UndeclaredName,
+        DiagnosticCode::UnusedVariable,
+        DiagnosticCode::UnusedParameter,
+        DiagnosticCode::UnreachableCode,
+        DiagnosticCode::UnusedProcedure,
+        DiagnosticCode::MissingSet,
+        DiagnosticCode::SetOnValue,
+        DiagnosticCode::DuplicateDeclaration,
+        DiagnosticCode::ShadowedVariable,
+        DiagnosticCode::NameConflict,
+        DiagnosticCode::UncheckedError,
+        DiagnosticCode::CrossSiteScripting,
+        DiagnosticCode::HardcodedCredentials,
+        DiagnosticCode::DangerousFunction,
+        DiagnosticCode::ComplexProcedure,
+        DiagnosticCode::LongProcedure,
+        DiagnosticCode::DeepNesting,
+        DiagnosticCode::NamingConvention,
+        DiagnosticCode::PluginRule,
+        DiagnosticCode::EncodingAssumed,
+        DiagnosticCode::FileTooLarge,
+        DiagnosticCode::UnknownMember,
+    ];
+
+    /// Returns the code as written in reports (e.g. `ASP001`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiagnosticCode::SyntaxError => "ASP001",
+            DiagnosticCode::UnclosedBlock => "ASP002",
+            DiagnosticCode::UnmatchedBlockEnd => "ASP003",
+            DiagnosticCode::MisplacedStatement => "ASP004",
+            DiagnosticCode::NoAspTags => "ASP005",
+            DiagnosticCode::EmptyFile => "ASP006",
+            DiagnosticCode::DisallowedLanguage => "ASP007",
+            DiagnosticCode::ForbiddenInclude => "ASP008",
+            DiagnosticCode::UndeclaredName => "ASP009",
+            DiagnosticCode::UnusedVariable => "ASP010",
+            DiagnosticCode::UnusedParameter => "ASP011",
+            DiagnosticCode::UnreachableCode => "ASP012",
+            DiagnosticCode::UnusedProcedure => "ASP013",
+            DiagnosticCode::MissingSet => "ASP014",
+            DiagnosticCode::SetOnValue => "ASP015",
+            DiagnosticCode::DuplicateDeclaration => "ASP016",
+            DiagnosticCode::ShadowedVariable => "ASP017",
+            DiagnosticCode::NameConflict => "ASP018",
+            DiagnosticCode::UncheckedError => "ASP019",
+            DiagnosticCode::CrossSiteScripting => "ASP020",
+            DiagnosticCode::HardcodedCredentials => "ASP021",
+            DiagnosticCode::DangerousFunction => "ASP022",
+            DiagnosticCode::ComplexProcedure => "ASP023",
+            DiagnosticCode::LongProcedure => "ASP024",
+            DiagnosticCode::DeepNesting => "ASP025",
+            DiagnosticCode::NamingConvention => "ASP026",
+            DiagnosticCode::PluginRule => "ASP027",
+            DiagnosticCode::EncodingAssumed => "ASP028",
+            DiagnosticCode::FileTooLarge => "ASP029",
+            DiagnosticCode::UnknownMember => "ASP030",
+        }
+    }
+
+    /// Returns the human-readable name of the code (e.g. `no-asp-tags`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            DiagnosticCode::SyntaxError => "syntax-error",
+            DiagnosticCode::UnclosedBlock => "unclosed-block",
+            DiagnosticCode::UnmatchedBlockEnd => "unmatched-block-end",
+            DiagnosticCode::MisplacedStatement => "misplaced-statement",
+            DiagnosticCode::NoAspTags => "no-asp-tags",
+            DiagnosticCode::EmptyFile => "empty-file",
+            DiagnosticCode::DisallowedLanguage => "disallowed-language",
+            DiagnosticCode::ForbiddenInclude => "forbidden-include",
+            DiagnosticCode::UndeclaredName => "undeclared-name",
+            DiagnosticCode::UnusedVariable => "unused-variable",
+            DiagnosticCode::UnusedParameter => "unused-parameter",
+            DiagnosticCode::UnreachableCode => "unreachable-code",
+            DiagnosticCode::UnusedProcedure => "unused-procedure",
+            DiagnosticCode::MissingSet => "missing-set",
+            DiagnosticCode::SetOnValue => "set-on-value",
+            DiagnosticCode::DuplicateDeclaration => "duplicate-declaration",
+            DiagnosticCode::ShadowedVariable => "shadowed-variable",
+            DiagnosticCode::NameConflict => "name-conflict",
+            DiagnosticCode::UncheckedError => "unchecked-error",
+            DiagnosticCode::CrossSiteScripting => "cross-site-scripting",
+            DiagnosticCode::HardcodedCredentials => "hardcoded-credentials",
+            DiagnosticCode::DangerousFunction => "dangerous-function",
+            DiagnosticCode::ComplexProcedure => "complex-procedure",
+            DiagnosticCode::LongProcedure => "long-procedure",
+            DiagnosticCode::DeepNesting => "deep-nesting",
+            DiagnosticCode::NamingConvention => "naming-convention",
+            DiagnosticCode::PluginRule => "plugin-rule",
+            DiagnosticCode::EncodingAssumed => "encoding-assumed",
+            DiagnosticCode::FileTooLarge => "file-too-large",
+            DiagnosticCode::UnknownMember => "unknown-member",
+        }
+    }
+
+    /// Looks up a diagnostic code from its code or its name, ignoring case
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - A code such as `ASP005` or a name such as `no-asp-tags`
+    ///
+    /// # Returns
+    ///
+    /// * `Option<DiagnosticCode>` - The matching code, or `None` if unknown
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::diagnostic::DiagnosticCode;
+    ///
+    /// assert_eq!(DiagnosticCode::from_name("asp005"), Some(DiagnosticCode::NoAspTags));
+    /// assert_eq!(DiagnosticCode::from_name("empty-file"), Some(DiagnosticCode::EmptyFile));
+    /// assert_eq!(DiagnosticCode::from_name("ASP999"), None);
+    /// ```
+    pub fn from_name(value: &str) -> Option<Self> {
+        let value = value.trim();
+        DiagnosticCode::ALL.into_iter().find(|code| {
+            code.as_str().eq_ignore_ascii_case(value) || code.name().eq_ignore_ascii_case(value)
+        })
+    }
+
+    /// Returns true for the codes reported by the lint rules rather than by the
+    /// parser
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::diagnostic::DiagnosticCode;
+    ///
+    /// assert!(DiagnosticCode::MissingSet.is_lint());
+    /// assert!(!DiagnosticCode::UnclosedBlock.is_lint());
+    /// ```
+    pub fn is_lint(&self) -> bool {
+        !matches!(
+            self,
+            DiagnosticCode::SyntaxError
+                | DiagnosticCode::UnclosedBlock
+                | DiagnosticCode::UnmatchedBlockEnd
+                | DiagnosticCode::MisplacedStatement
+                | DiagnosticCode::NoAspTags
+                | DiagnosticCode::EmptyFile
+                | DiagnosticCode::DisallowedLanguage
+                | DiagnosticCode::ForbiddenInclude
+                | DiagnosticCode::EncodingAssumed
+                | DiagnosticCode::FileTooLarge
+        )
+    }
+
+    /// Returns the severity of diagnostics with this code
+    pub fn default_severity(&self) -> Severity {
+        match self {
+            DiagnosticCode::NoAspTags
+            | DiagnosticCode::EmptyFile
+            | DiagnosticCode::UnusedVariable
+            | DiagnosticCode::UnusedParameter
+            | DiagnosticCode::UnreachableCode
+            | DiagnosticCode::UnusedProcedure
+            | DiagnosticCode::DuplicateDeclaration
+            | DiagnosticCode::ShadowedVariable
+            | DiagnosticCode::NameConflict
+            | DiagnosticCode::UncheckedError
+            | DiagnosticCode::CrossSiteScripting
+            | DiagnosticCode::HardcodedCredentials
+            | DiagnosticCode::DangerousFunction
+            | DiagnosticCode::ComplexProcedure
+            | DiagnosticCode::LongProcedure
+            | DiagnosticCode::DeepNesting
+            | DiagnosticCode::PluginRule
+            | DiagnosticCode::UnknownMember => Severity::Warning,
+            DiagnosticCode::NamingConvention
+            | DiagnosticCode::EncodingAssumed
+            | DiagnosticCode::FileTooLarge => Severity::Notice,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for DiagnosticCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for DiagnosticCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        DiagnosticCode::from_name(&code)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown diagnostic code '{}'", code)))
+    }
+}
+
+/// How serious a diagnostic is
+///
+/// The levels match the severities of the command-line output formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Code that cannot be parsed or would not run
+    Error,
+    /// Potential issue, such as a file without ASP code
+    Warning,
+    /// Style or best practice suggestion
+    Notice,
+}
+
+impl Severity {
+    /// Returns the lowercase name of the severity (`error`, `warning` or `notice`)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Notice => "notice",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A problem found in a page, with its location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    /// Stable code identifying the kind of problem
+    pub code: DiagnosticCode,
+    /// Description of the problem
+    pub message: String,
+    /// Location of the problem; its line and column are those of the problem start
+    pub span: Span,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Other locations involved in the problem, such as the declaration a name
+    /// hides
+    pub related: Vec<RelatedLocation>,
+}
+
+/// A location related to a diagnostic
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelatedLocation {
+    /// File of the location, or `None` for the page of the diagnostic
+    pub path: Option<PathBuf>,
+    /// The location
+    pub span: Span,
+    /// What the location is, such as `variable declared here`
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with the default severity of its code
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The kind of problem
+    /// * `span` - The location of the problem
+    /// * `message` - The description of the problem
+    pub fn new(code: DiagnosticCode, span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            code,
+            message: message.into(),
+            span,
+            severity: code.default_severity(),
+            related: Vec::new(),
+        }
+    }
+
+    /// Adds a location related to the problem
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file of the location, or `None` for the page of the
+    ///   diagnostic
+    /// * `span` - The location
+    /// * `message` - What the location is
+    pub fn with_related(
+        mut self,
+        path: Option<PathBuf>,
+        span: Span,
+        message: impl Into<String>,
+    ) -> Self {
+        self.related.push(RelatedLocation {
+            path,
+            span,
+            message: message.into(),
+        });
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Parse error at line {}, column {}: {}",
+            self.span.line, self.span.column, self.message
+        )
+    }
+}
@@ -0,0 +1,432 @@
+//! Incremental parsing of edited pages
+//!
+//! The edit is found by comparing the page before and after it. The top-level
+//! statements between the HTML text before the edit and the HTML text after it
+//! (the ASP blocks containing the edit) are parsed again alone, and replace their
+//! previous statements in the syntax tree; the statements after them are only
+//! moved by the length of the edit.
+//!
+//! The region is parsed with the lines of the page before it replaced by empty
+//! lines, and with the rest of its last line, so that the positions and messages
+//! of its syntax errors are those of a parse of the whole page.
+
+use super::ast::*;
+use super::builder;
+use super::diagnostic::{Diagnostic, DiagnosticCode};
+use super::options::ParseOptions;
+use super::{RecoveredParse, ScriptLanguage};
+
+/// Parses a page again after an edit, from the syntax tree of its previous content
+///
+/// Returns `None` when the edit may change how the rest of the page is read, and
+/// the whole page must be parsed again: the page is not written in VBScript, its
+/// blocks are unbalanced, the edit opens or closes a tag or an HTML comment, or it
+/// moves the syntax errors found after it to other lines.
+pub(super) fn reparse(
+    previous: &RecoveredParse,
+    previous_source: &str,
+    source: &str,
+    options: &ParseOptions,
+) -> Option<RecoveredParse> {
+    let max_errors = options.max_errors.max(1);
+    let body = &previous.program.body;
+    if previous.program.language != ScriptLanguage::VBScript
+        || super::detect_language(source) != ScriptLanguage::VBScript
+        || previous.diagnostics.len() >= max_errors
+        || previous
+            .diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.code != DiagnosticCode::SyntaxError)
+        // A page abandoned after an error has no statements
+        || (body.is_empty() && !previous.diagnostics.is_empty())
+    {
+        return None;
+    }
+    let (start, old_end, new_end) = edited_range(previous_source, source);
+    if old_end == start && new_end == start {
+        return Some(previous.clone());
+    }
+
+    // Top-level statements between the HTML text around the edit
+    let is_html = |statement: &Statement| matches!(statement, Statement::Html(_));
+    let before = body
+        .iter()
+        .rposition(|statement| is_html(statement) && statement.span().end < start);
+    let after = body
+        .iter()
+        .position(|statement| is_html(statement) && statement.span().start > old_end);
+    let region_start = before.map_or(0, |index| body[index].span().end);
+    let old_region_end = after.map_or(previous_source.len(), |index| body[index].span().start);
+    let region_end = old_region_end - old_end + new_end;
+    let region = &source[region_start..region_end];
+    let old_region = &previous_source[region_start..old_region_end];
+    // An HTML comment left open before the region may now end in it
+    if !is_self_contained(region)
+        || !is_self_contained(old_region)
+        || region.matches("-->").count() != old_region.matches("-->").count()
+    {
+        return None;
+    }
+
+    // The region, after empty lines standing for the lines before it, and with the
+    // rest of its last line
+    let line_start = source[..region_start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line_end = source[region_end..]
+        .find('\n')
+        .map_or(source.len(), |index| region_end + index + 1);
+    let line_breaks = source.as_bytes()[..line_start]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count();
+    let text = "\n".repeat(line_breaks) + &source[line_start..line_end];
+    let base = line_start - line_breaks;
+    let (text_start, text_end) = (region_start - base, region_end - base);
+    let lines = LineIndex::with_tab_width(&text, options.tab_width);
+    let (program, diagnostics) =
+        builder::build_tree_recovering(&text, &lines, ScriptLanguage::VBScript, max_errors);
+    let program = program?;
+
+    // The region must be read as in the whole page: between HTML text, that it
+    // does not run into
+    let html_ends = |offset: usize| {
+        program
+            .body
+            .iter()
+            .any(|statement| is_html(statement) && statement.span().end == offset)
+    };
+    let html_starts = |offset: usize| {
+        program
+            .body
+            .iter()
+            .any(|statement| is_html(statement) && statement.span().start == offset)
+    };
+    if before.is_some() && (!html_ends(text_start) || html_starts(text_start))
+        || after.is_some() && (!html_starts(text_end) || html_ends(text_end))
+    {
+        return None;
+    }
+    let mut statements = Vec::new();
+    for mut statement in program.body {
+        let span = statement.span();
+        if span.end <= text_start || span.start >= text_end {
+            continue;
+        }
+        if span.start < text_start || span.end > text_end {
+            return None;
+        }
+        shift_statement(&mut statement, &mut |span| {
+            span.start += base;
+            span.end += base;
+        });
+        statements.push(statement);
+    }
+
+    // Statements and errors after the region move with the edit
+    let delta = new_end as isize - old_end as isize;
+    let (first_line, _) = lines.line_column(text_start);
+    let (last_line, _) = lines.line_column(text_end);
+    let old_last_line = after.map_or(last_line, |index| body[index].span().line);
+    let line_delta = last_line as isize - old_last_line as isize;
+    let mut move_span = |span: &mut Span| {
+        span.start = span.start.wrapping_add_signed(delta);
+        span.end = span.end.wrapping_add_signed(delta);
+        if span.line == old_last_line {
+            span.column = lines.line_column(span.start - base).1;
+        }
+        span.line = span.line.wrapping_add_signed(line_delta);
+    };
+
+    // Messages of syntax errors quote their line and its number
+    let errors_before: Vec<&Diagnostic> = previous
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.span.start < region_start)
+        .collect();
+    let errors_after: Vec<&Diagnostic> = previous
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.span.start >= old_region_end)
+        .collect();
+    if errors_before
+        .iter()
+        .any(|diagnostic| diagnostic.span.line == first_line)
+        || errors_after
+            .iter()
+            .any(|diagnostic| line_delta != 0 || diagnostic.span.line == old_last_line)
+    {
+        return None;
+    }
+    let mut all_diagnostics: Vec<Diagnostic> = errors_before.into_iter().cloned().collect();
+    for mut diagnostic in diagnostics {
+        if (text_start..text_end).contains(&diagnostic.span.start) {
+            diagnostic.span.start += base;
+            diagnostic.span.end += base;
+            all_diagnostics.push(diagnostic);
+        }
+    }
+    for diagnostic in errors_after {
+        let mut diagnostic = diagnostic.clone();
+        move_span(&mut diagnostic.span);
+        all_diagnostics.push(diagnostic);
+    }
+    if all_diagnostics.len() >= max_errors {
+        return None;
+    }
+    all_diagnostics.sort_by_key(|diagnostic| diagnostic.span.start);
+
+    let first = before.map_or(0, |index| index + 1);
+    let last = after.unwrap_or(body.len());
+    let mut new_body = body[..first].to_vec();
+    new_body.extend(statements);
+    for statement in &body[last..] {
+        let mut statement = statement.clone();
+        shift_statement(&mut statement, &mut move_span);
+        new_body.push(statement);
+    }
+    Some(RecoveredParse {
+        program: Program {
+            language: ScriptLanguage::VBScript,
+            body: new_body,
+            span: Span::new(0, source.len(), 1, 1),
+        },
+        diagnostics: all_diagnostics,
+    })
+}
+
+/// Returns the byte range that differs between two texts: its start, and its end
+/// in the first and in the second text
+fn edited_range(before: &str, after: &str) -> (usize, usize, usize) {
+    let mut start = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut common_end = before[start..]
+        .bytes()
+        .rev()
+        .zip(after[start..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - common_end) {
+        common_end -= 1;
+    }
+    (start, before.len() - common_end, after.len() - common_end)
+}
+
+/// Returns whether reading a text does not depend on what follows it: every ASP
+/// block and HTML comment opened in it is closed in it, and it has no directive
+/// or script tag, whose quoted attributes may run past its end
+fn is_self_contained(text: &str) -> bool {
+    let text = text.to_ascii_lowercase();
+    !text.contains("<%@")
+        && !text.contains("<script")
+        && [("<%", "%>"), ("<!--", "-->")]
+            .into_iter()
+            .all(|(open, close)| {
+                text.rfind(open)
+                    .is_none_or(|last| text[last + open.len()..].contains(close))
+            })
+}
+
+/// Applies a change to the spans of a statement and of the nodes it contains
+fn shift_statement(statement: &mut Statement, shift: &mut impl FnMut(&mut Span)) {
+    match statement {
+        Statement::Html(Html { span, .. })
+        | Statement::Directive(Directive { span, .. })
+        | Statement::OptionExplicit { span }
+        | Statement::Stop { span }
+        | Statement::Exit(ExitStatement { span, .. })
+        | Statement::OnError(OnErrorStatement { span, .. })
+        | Statement::Unparsed(Unparsed { span, .. }) => shift(span),
+        Statement::Output(output) => {
+            shift_expression(&mut output.value, shift);
+            shift(&mut output.span);
+        }
+        Statement::ServerScript(script) => {
+            shift_statements(&mut script.body, shift);
+            shift(&mut script.span);
+        }
+        Statement::Dim(DimStatement {
+            variables, span, ..
+        })
+        | Statement::ReDim(ReDimStatement {
+            variables, span, ..
+        }) => {
+            for variable in variables {
+                shift_identifier(&mut variable.name, shift);
+                for bound in variable.bounds.iter_mut().flatten() {
+                    shift_expression(bound, shift);
+                }
+                shift(&mut variable.span);
+            }
+            shift(span);
+        }
+        Statement::Const(constants) => {
+            for constant in &mut constants.constants {
+                shift_identifier(&mut constant.name, shift);
+                shift_expression(&mut constant.value, shift);
+                shift(&mut constant.span);
+            }
+            shift(&mut constants.span);
+        }
+        Statement::Erase(erase) => {
+            for array in &mut erase.arrays {
+                shift_identifier(array, shift);
+            }
+            shift(&mut erase.span);
+        }
+        Statement::Assignment(assignment) => {
+            shift_expression(&mut assignment.target, shift);
+            shift_expression(&mut assignment.value, shift);
+            shift(&mut assignment.span);
+        }
+        Statement::Call(call) => {
+            shift_expression(&mut call.target, shift);
+            shift_arguments(&mut call.arguments, shift);
+            shift(&mut call.span);
+        }
+        Statement::If(statement) => {
+            shift_expression(&mut statement.condition, shift);
+            shift_statements(&mut statement.then_branch, shift);
+            for branch in &mut statement.else_if_branches {
+                shift_expression(&mut branch.condition, shift);
+                shift_statements(&mut branch.body, shift);
+                shift(&mut branch.span);
+            }
+            if let Some(body) = &mut statement.else_branch {
+                shift_statements(body, shift);
+            }
+            shift(&mut statement.span);
+        }
+        Statement::Select(select) => {
+            shift_expression(&mut select.subject, shift);
+            for case in &mut select.cases {
+                for value in &mut case.values {
+                    shift_expression(value, shift);
+                }
+                shift_statements(&mut case.body, shift);
+                shift(&mut case.span);
+            }
+            if let Some(body) = &mut select.else_branch {
+                shift_statements(body, shift);
+            }
+            shift(&mut select.span);
+        }
+        Statement::For(statement) => {
+            shift_identifier(&mut statement.variable, shift);
+            shift_expression(&mut statement.start, shift);
+            shift_expression(&mut statement.end, shift);
+            if let Some(step) = &mut statement.step {
+                shift_expression(step, shift);
+            }
+            shift_statements(&mut statement.body, shift);
+            shift(&mut statement.span);
+        }
+        Statement::ForEach(statement) => {
+            shift_identifier(&mut statement.variable, shift);
+            shift_expression(&mut statement.collection, shift);
+            shift_statements(&mut statement.body, shift);
+            shift(&mut statement.span);
+        }
+        Statement::DoLoop(statement) => {
+            for condition in [&mut statement.pre_condition, &mut statement.post_condition]
+                .into_iter()
+                .flatten()
+            {
+                shift_expression(&mut condition.condition, shift);
+                shift(&mut condition.span);
+            }
+            shift_statements(&mut statement.body, shift);
+            shift(&mut statement.span);
+        }
+        Statement::While(statement) => {
+            shift_expression(&mut statement.condition, shift);
+            shift_statements(&mut statement.body, shift);
+            shift(&mut statement.span);
+        }
+        Statement::With(statement) => {
+            shift_expression(&mut statement.object, shift);
+            shift_statements(&mut statement.body, shift);
+            shift(&mut statement.span);
+        }
+        Statement::Procedure(procedure) => {
+            shift_identifier(&mut procedure.name, shift);
+            for parameter in &mut procedure.parameters {
+                shift_identifier(&mut parameter.name, shift);
+                shift(&mut parameter.span);
+            }
+            shift_statements(&mut procedure.body, shift);
+            shift(&mut procedure.span);
+        }
+        Statement::Class(class) => {
+            shift_identifier(&mut class.name, shift);
+            shift_statements(&mut class.body, shift);
+            shift(&mut class.span);
+        }
+        Statement::Randomize(statement) => {
+            if let Some(seed) = &mut statement.seed {
+                shift_expression(seed, shift);
+            }
+            shift(&mut statement.span);
+        }
+    }
+}
+
+fn shift_statements(statements: &mut [Statement], shift: &mut impl FnMut(&mut Span)) {
+    for statement in statements {
+        shift_statement(statement, shift);
+    }
+}
+
+fn shift_expression(expression: &mut Expression, shift: &mut impl FnMut(&mut Span)) {
+    match expression {
+        Expression::Literal(Literal { span, .. }) | Expression::Unparsed(Unparsed { span, .. }) => {
+            shift(span)
+        }
+        Expression::Identifier(identifier) => shift_identifier(identifier, shift),
+        Expression::Member(member) => {
+            if let Some(object) = &mut member.object {
+                shift_expression(object, shift);
+            }
+            shift_identifier(&mut member.member, shift);
+            shift(&mut member.span);
+        }
+        Expression::Call(call) => {
+            shift_expression(&mut call.callee, shift);
+            shift_arguments(&mut call.arguments, shift);
+            shift(&mut call.span);
+        }
+        Expression::Unary(unary) => {
+            shift_expression(&mut unary.operand, shift);
+            shift(&mut unary.span);
+        }
+        Expression::Binary(binary) => {
+            shift_expression(&mut binary.left, shift);
+            shift_expression(&mut binary.right, shift);
+            shift(&mut binary.span);
+        }
+        Expression::Parenthesized(parenthesized) => {
+            shift_expression(&mut parenthesized.expression, shift);
+            shift(&mut parenthesized.span);
+        }
+        Expression::New(new) => {
+            shift_identifier(&mut new.class_name, shift);
+            shift(&mut new.span);
+        }
+    }
+}
+
+fn shift_arguments(arguments: &mut [Option<Expression>], shift: &mut impl FnMut(&mut Span)) {
+    for argument in arguments.iter_mut().flatten() {
+        shift_expression(argument, shift);
+    }
+}
+
+fn shift_identifier(identifier: &mut Identifier, shift: &mut impl FnMut(&mut Span)) {
+    shift(&mut identifier.span);
+}
@@ -0,0 +1,17 @@
+/// Server-side JScript Parser Module
+///
+/// ASP pages declaring `<%@ Language="JScript" %>` contain JScript code instead of
+/// VBScript. This module provides a parser for such pages, sharing the markup rules
+/// of the VBScript grammar (delimiters, directives, HTML). JScript code blocks are
+/// checked for terminated strings and comments and for balanced parentheses
+/// and brackets.
+use pest_derive::Parser;
+
+/// The parser for ASP Classic pages written in JScript
+///
+/// This struct implements the Parser trait from the Pest library,
+/// utilizing the grammar defined in the markup.pest and jscript.pest files.
+#[derive(Parser)]
+#[grammar = "parser/markup.pest"]
+#[grammar = "parser/jscript.pest"]
+pub struct JScriptParser;
@@ -0,0 +1,608 @@
+//! Token stream of ASP Classic pages
+//!
+//! [`tokenize`](super::tokenize) splits a page into a flat list of tokens without
+//! parsing it: markup, ASP delimiters, and the keywords, names, literals, operators
+//! and comments of the server code. It is much faster than a full parse and never
+//! fails, which makes it suitable for syntax highlighting and quick heuristics.
+//!
+//! The stream is lossless: whitespace and newlines are tokens too, so the texts of
+//! the tokens put end to end give back the page.
+
+use pest::Parser;
+use serde::Serialize;
+
+use super::ast::{LineIndex, Span};
+use super::builder::script_language_attribute;
+use super::{AspParser, Rule, ScriptLanguage};
+
+/// Kinds of tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum TokenKind {
+    /// HTML text outside of server code
+    Html,
+    /// `<!-- ... -->` HTML comment
+    HtmlComment,
+    /// `<%`
+    OpenTag,
+    /// `<%=`
+    OpenExpressionTag,
+    /// `<%@`
+    OpenDirectiveTag,
+    /// `%>`
+    CloseTag,
+    /// `<script runat="server">` opening tag
+    ScriptOpenTag,
+    /// `</script>` closing a server-side script block
+    ScriptCloseTag,
+    /// Reserved word of the language (`If`, `Dim`, `function`, ...)
+    Keyword,
+    /// Variable, procedure, class or member name
+    Identifier,
+    /// String literal, quotes included
+    String,
+    /// Number literal
+    Number,
+    /// VBScript date literal (`#2024-01-31#`)
+    Date,
+    /// Operator, including word operators such as `And` and `Mod`
+    Operator,
+    /// Any other character (parentheses, commas, dots, ...)
+    Punctuation,
+    /// Comment, without the line break ending it
+    Comment,
+    /// Spaces and tabs
+    Whitespace,
+    /// A line break (`\n`, `\r\n` or `\r`)
+    Newline,
+    /// A VBScript line continuation (`_` and the line break following it)
+    LineContinuation,
+}
+
+impl TokenKind {
+    /// Returns true for tokens that do not change the meaning of the code:
+    /// whitespace, newlines, comments and line continuations
+    ///
+    /// Newlines end VBScript statements, but are still reported as trivia.
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace
+                | TokenKind::Newline
+                | TokenKind::Comment
+                | TokenKind::LineContinuation
+        )
+    }
+
+    /// Returns the name of the kind in snake case (e.g. `open_expression_tag`)
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Html => "html",
+            TokenKind::HtmlComment => "html_comment",
+            TokenKind::OpenTag => "open_tag",
+            TokenKind::OpenExpressionTag => "open_expression_tag",
+            TokenKind::OpenDirectiveTag => "open_directive_tag",
+            TokenKind::CloseTag => "close_tag",
+            TokenKind::ScriptOpenTag => "script_open_tag",
+            TokenKind::ScriptCloseTag => "script_close_tag",
+            TokenKind::Keyword => "keyword",
+            TokenKind::Identifier => "identifier",
+            TokenKind::String => "string",
+            TokenKind::Number => "number",
+            TokenKind::Date => "date",
+            TokenKind::Operator => "operator",
+            TokenKind::Punctuation => "punctuation",
+            TokenKind::Comment => "comment",
+            TokenKind::Whitespace => "whitespace",
+            TokenKind::Newline => "newline",
+            TokenKind::LineContinuation => "line_continuation",
+        }
+    }
+}
+
+/// A token of a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Token<'a> {
+    /// Kind of token
+    pub kind: TokenKind,
+    /// The text of the token, as written in the source
+    pub text: &'a str,
+    /// Location of the token
+    pub span: Span,
+}
+
+/// VBScript reserved words
+const VBSCRIPT_KEYWORDS: &[&str] = &[
+    "ByRef",
+    "ByVal",
+    "Call",
+    "Case",
+    "Class",
+    "Const",
+    "Default",
+    "Dim",
+    "Do",
+    "Each",
+    "Else",
+    "ElseIf",
+    "Empty",
+    "End",
+    "Erase",
+    "Error",
+    "Exit",
+    "Explicit",
+    "False",
+    "For",
+    "Function",
+    "Get",
+    "GoTo",
+    "If",
+    "In",
+    "Let",
+    "Loop",
+    "New",
+    "Next",
+    "Nothing",
+    "Null",
+    "On",
+    "Option",
+    "Preserve",
+    "Private",
+    "Property",
+    "Public",
+    "Randomize",
+    "ReDim",
+    "Rem",
+    "Resume",
+    "Select",
+    "Set",
+    "Step",
+    "Stop",
+    "Sub",
+    "Then",
+    "To",
+    "True",
+    "Until",
+    "Wend",
+    "While",
+    "With",
+];
+
+/// VBScript operators written as words
+const VBSCRIPT_WORD_OPERATORS: &[&str] = &["And", "Eqv", "Imp", "Is", "Mod", "Not", "Or", "Xor"];
+
+/// VBScript symbol operators, longest first
+const VBSCRIPT_OPERATORS: &[&str] = &[
+    "<=", ">=", "<>", "=", "<", ">", "+", "-", "*", "/", "\\", "^", "&",
+];
+
+/// Returns the usual spelling of a VBScript keyword or word operator
+/// (`ElseIf`, `ReDim`, `And`), or `None` if `word` is not one
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::lexer::vbscript_keyword;
+///
+/// assert_eq!(vbscript_keyword("elseif"), Some("ElseIf"));
+/// assert_eq!(vbscript_keyword("MOD"), Some("Mod"));
+/// assert_eq!(vbscript_keyword("Response"), None);
+/// ```
+pub fn vbscript_keyword(word: &str) -> Option<&'static str> {
+    VBSCRIPT_KEYWORDS
+        .iter()
+        .chain(VBSCRIPT_WORD_OPERATORS)
+        .find(|keyword| keyword.eq_ignore_ascii_case(word))
+        .copied()
+}
+
+/// JScript reserved words
+const JSCRIPT_KEYWORDS: &[&str] = &[
+    "break",
+    "case",
+    "catch",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "in",
+    "instanceof",
+    "new",
+    "null",
+    "return",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+];
+
+/// JScript operators, longest first
+const JSCRIPT_OPERATORS: &[&str] = &[
+    ">>>=", "===", "!==", ">>>", "<<=", ">>=", "==", "!=", "<=", ">=", "&&", "||", "++", "--",
+    "+=", "-=", "*=", "/=", "%=", "&=", "|=", "^=", "<<", ">>", "=", "<", ">", "+", "-", "*", "/",
+    "%", "&", "|", "^", "!", "~", "?",
+];
+
+/// Splits a page into tokens
+///
+/// `text` is the page with normalized line endings, used for scanning and line
+/// numbers; the text of tokens is taken from `source`, which has the same byte
+/// offsets.
+pub(super) fn tokenize<'a>(
+    source: &'a str,
+    text: &str,
+    language: ScriptLanguage,
+) -> Vec<Token<'a>> {
+    let mut lexer = Lexer {
+        source,
+        text,
+        lines: LineIndex::new(text),
+        language,
+        tokens: Vec::new(),
+    };
+    lexer.page();
+    lexer.tokens
+}
+
+struct Lexer<'a, 't> {
+    source: &'a str,
+    text: &'t str,
+    lines: LineIndex<'t>,
+    language: ScriptLanguage,
+    tokens: Vec<Token<'a>>,
+}
+
+impl Lexer<'_, '_> {
+    fn push(&mut self, kind: TokenKind, start: usize, end: usize) {
+        self.tokens.push(Token {
+            kind,
+            text: &self.source[start..end],
+            span: self.lines.span(start, end),
+        });
+    }
+
+    fn page(&mut self) {
+        let text = self.text;
+        let mut html_start = 0;
+        let mut position = 0;
+        while position < text.len() {
+            let rest = &text[position..];
+            if rest.starts_with('<')
+                && let Some(end) = self.markup(html_start, position)
+            {
+                html_start = end;
+                position = end;
+                continue;
+            }
+            position += rest.chars().next().map_or(1, char::len_utf8);
+        }
+        self.html(html_start, text.len());
+    }
+
+    fn html(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.push(TokenKind::Html, start, end);
+        }
+    }
+
+    /// Tokenizes the ASP block, HTML comment or server script block starting at
+    /// `start`, after the HTML starting at `html_start`, and returns its end
+    ///
+    /// Returns `None` if the text at `start` is plain HTML.
+    fn markup(&mut self, html_start: usize, start: usize) -> Option<usize> {
+        let text = self.text;
+        let rest = &text[start..];
+
+        if rest.starts_with("<%") {
+            let (kind, length) = if rest.starts_with("<%=") {
+                (TokenKind::OpenExpressionTag, 3)
+            } else if rest.starts_with("<%@") {
+                (TokenKind::OpenDirectiveTag, 3)
+            } else {
+                (TokenKind::OpenTag, 2)
+            };
+            let code_start = start + length;
+            let close = text[code_start..]
+                .find("%>")
+                .map(|index| code_start + index);
+            let code_end = close.unwrap_or(text.len());
+
+            self.html(html_start, start);
+            self.push(kind, start, code_start);
+            self.code(self.language, code_start, code_end);
+            return Some(match close {
+                Some(close) => {
+                    self.push(TokenKind::CloseTag, close, close + 2);
+                    close + 2
+                }
+                None => code_end,
+            });
+        }
+
+        if rest.starts_with("<!--") && !rest.starts_with("<!--#") {
+            let end = start + 4 + rest[4..].find("-->")? + 3;
+            self.html(html_start, start);
+            self.push(TokenKind::HtmlComment, start, end);
+            return Some(end);
+        }
+
+        let open_tag = AspParser::parse(Rule::server_script_open, rest).ok()?;
+        let content_start = start + open_tag.as_str().len();
+        let language = script_language_attribute(open_tag.as_str()).unwrap_or(self.language);
+        let close = script_close_tag(&text[content_start..])
+            .map(|(index, length)| (content_start + index, content_start + index + length));
+        let content_end = close.map_or(text.len(), |(close_start, _)| close_start);
+
+        self.html(html_start, start);
+        self.push(TokenKind::ScriptOpenTag, start, content_start);
+        self.code(language, content_start, content_end);
+        Some(match close {
+            Some((close_start, close_end)) => {
+                self.push(TokenKind::ScriptCloseTag, close_start, close_end);
+                close_end
+            }
+            None => content_end,
+        })
+    }
+
+    /// Tokenizes server code
+    fn code(&mut self, language: ScriptLanguage, start: usize, end: usize) {
+        let mut position = start;
+        while position < end {
+            let (length, kind) = code_token(&self.text[position..end], language);
+            self.push(kind, position, position + length);
+            position += length;
+        }
+    }
+}
+
+/// Finds the `</script>` tag closing a server script block, returning its offset
+/// and length
+fn script_close_tag(content: &str) -> Option<(usize, usize)> {
+    let lowercase = content.to_ascii_lowercase();
+    lowercase.match_indices("</script").find_map(|(index, _)| {
+        let tag = AspParser::parse(Rule::server_script_close, &content[index..]).ok()?;
+        Some((index, tag.as_str().len()))
+    })
+}
+
+/// Returns the length and kind of the token of server code starting `text`
+///
+/// `text` must not be empty.
+pub(super) fn code_token(text: &str, language: ScriptLanguage) -> (usize, TokenKind) {
+    if let Some(token) = whitespace_token(text) {
+        return token;
+    }
+    match language {
+        ScriptLanguage::VBScript => vbscript_token(text),
+        ScriptLanguage::JScript => jscript_token(text),
+    }
+}
+
+/// Recognizes the whitespace and line breaks shared by both languages
+fn whitespace_token(text: &str) -> Option<(usize, TokenKind)> {
+    if let Some(length) = newline_length(text) {
+        return Some((length, TokenKind::Newline));
+    }
+    let spaces = text.len() - text.trim_start_matches([' ', '\t']).len();
+    (spaces > 0).then_some((spaces, TokenKind::Whitespace))
+}
+
+fn vbscript_token(text: &str) -> (usize, TokenKind) {
+    let first = text.chars().next().expect("text is not empty");
+
+    if first == '_' {
+        let after = text[1..].trim_start_matches([' ', '\t']);
+        if let Some(newline) = newline_length(after) {
+            let length = text.len() - after.len() + newline;
+            return (length, TokenKind::LineContinuation);
+        }
+    }
+    let is_rem = text
+        .get(..3)
+        .is_some_and(|keyword| keyword.eq_ignore_ascii_case("rem"))
+        && text[3..]
+            .chars()
+            .next()
+            .is_none_or(|c| matches!(c, ' ' | '\t' | '\r' | '\n' | '%'));
+    if first == '\'' || is_rem {
+        return (comment_length(text), TokenKind::Comment);
+    }
+    if first == '"' {
+        return (string_length(text, '"', false), TokenKind::String);
+    }
+    if first == '#'
+        && let Some(length) = date_length(text)
+    {
+        return (length, TokenKind::Date);
+    }
+    if first == '['
+        && let Some(index) = text.find([']', '\r', '\n'])
+        && text[index..].starts_with(']')
+    {
+        return (index + 1, TokenKind::Identifier);
+    }
+    if first == '&' {
+        let radix = text[1..].chars().next().map(|c| c.to_ascii_uppercase());
+        let digits = match radix {
+            Some('H') => digits_length(&text[2..], |c| c.is_ascii_hexdigit()),
+            Some('O') => digits_length(&text[2..], |c| matches!(c, '0'..='7')),
+            _ => 0,
+        };
+        if digits > 0 {
+            // Hexadecimal and octal literals may end with a `&` (Long) suffix
+            let suffix = usize::from(text[2 + digits..].starts_with('&'));
+            return (2 + digits + suffix, TokenKind::Number);
+        }
+    }
+    if starts_number(text) {
+        return (number_length(text), TokenKind::Number);
+    }
+    if first.is_alphabetic() || first == '_' {
+        let length = word_length(text, |c| c.is_alphanumeric() || c == '_');
+        let word = &text[..length];
+        let is = |words: &[&str]| words.iter().any(|w| w.eq_ignore_ascii_case(word));
+        let kind = if is(VBSCRIPT_WORD_OPERATORS) {
+            TokenKind::Operator
+        } else if is(VBSCRIPT_KEYWORDS) {
+            TokenKind::Keyword
+        } else {
+            TokenKind::Identifier
+        };
+        return (length, kind);
+    }
+    symbol_token(text, VBSCRIPT_OPERATORS)
+}
+
+fn jscript_token(text: &str) -> (usize, TokenKind) {
+    let first = text.chars().next().expect("text is not empty");
+
+    if text.starts_with("//") {
+        return (comment_length(text), TokenKind::Comment);
+    }
+    if let Some(comment) = text.strip_prefix("/*") {
+        let length = comment.find("*/").map_or(text.len(), |index| index + 4);
+        return (length, TokenKind::Comment);
+    }
+    if first == '"' || first == '\'' {
+        return (string_length(text, first, true), TokenKind::String);
+    }
+    if text.starts_with("0x") || text.starts_with("0X") {
+        let digits = digits_length(&text[2..], |c| c.is_ascii_hexdigit());
+        if digits > 0 {
+            return (2 + digits, TokenKind::Number);
+        }
+    }
+    if starts_number(text) {
+        return (number_length(text), TokenKind::Number);
+    }
+    if first.is_alphabetic() || first == '_' || first == '$' {
+        let length = word_length(text, |c| c.is_alphanumeric() || c == '_' || c == '$');
+        let kind = if JSCRIPT_KEYWORDS.contains(&&text[..length]) {
+            TokenKind::Keyword
+        } else {
+            TokenKind::Identifier
+        };
+        return (length, kind);
+    }
+    symbol_token(text, JSCRIPT_OPERATORS)
+}
+
+/// Recognizes an operator, or a single punctuation character
+fn symbol_token(text: &str, operators: &[&str]) -> (usize, TokenKind) {
+    match operators
+        .iter()
+        .find(|operator| text.starts_with(**operator))
+    {
+        Some(operator) => (operator.len(), TokenKind::Operator),
+        None => {
+            let first = text.chars().next().expect("text is not empty");
+            (first.len_utf8(), TokenKind::Punctuation)
+        }
+    }
+}
+
+fn word_length(text: &str, is_word_char: fn(char) -> bool) -> usize {
+    text.find(|c: char| !is_word_char(c)).unwrap_or(text.len())
+}
+
+fn digits_length(text: &str, is_digit: fn(char) -> bool) -> usize {
+    text.find(|c: char| !is_digit(c)).unwrap_or(text.len())
+}
+
+/// Whether `text` starts with a decimal number (`12`, `.5`, ...)
+fn starts_number(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some('.') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => c.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Length of a decimal number, with its optional fraction and exponent
+fn number_length(text: &str) -> usize {
+    let is_digit = |c: char| c.is_ascii_digit();
+    let mut length = digits_length(text, is_digit);
+    if text[length..].starts_with('.') {
+        let fraction = digits_length(&text[length + 1..], is_digit);
+        if fraction > 0 || length > 0 {
+            length += 1 + fraction;
+        }
+    }
+    let rest = &text[length..];
+    if rest.starts_with(['e', 'E']) {
+        let sign = usize::from(rest[1..].starts_with(['+', '-']));
+        let exponent = digits_length(&rest[1 + sign..], is_digit);
+        if exponent > 0 {
+            length += 1 + sign + exponent;
+        }
+    }
+    length
+}
+
+/// Length of a VBScript date literal (`#1/31/2024#`), if `text` starts with one
+fn date_length(text: &str) -> Option<usize> {
+    let content = &text[1..];
+    let index = content.find('#')?;
+    let date = &content[..index];
+    let is_date = !date.trim().is_empty()
+        && date
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '/' | '-' | ':' | ',' | '.'));
+    is_date.then_some(index + 2)
+}
+
+fn newline_length(text: &str) -> Option<usize> {
+    if text.starts_with("\r\n") {
+        Some(2)
+    } else if text.starts_with(['\n', '\r']) {
+        Some(1)
+    } else {
+        None
+    }
+}
+
+/// Length of a comment, which ends at the end of the line or at `%>`
+fn comment_length(text: &str) -> usize {
+    [text.find(['\r', '\n']), text.find("%>")]
+        .into_iter()
+        .flatten()
+        .min()
+        .unwrap_or(text.len())
+}
+
+/// Length of a string literal, which cannot span lines
+fn string_length(text: &str, quote: char, backslash_escapes: bool) -> usize {
+    let mut chars = text.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if backslash_escapes => {
+                chars.next();
+            }
+            '\r' | '\n' => return index,
+            c if c == quote => {
+                // VBScript escapes quotes by doubling them
+                if !backslash_escapes && text[index + 1..].starts_with(quote) {
+                    chars.next();
+                } else {
+                    return index + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
@@ -3,10 +3,38 @@
 /// This module provides functionality for parsing ASP Classic (VBScript) syntax using
 /// the Pest parsing library. It handles the basic syntax elements of ASP Classic
 /// including ASP tags, comments, statements, and expressions.
+///
+/// Pages declaring `<%@ Language="JScript" %>` are parsed with the JScript grammar
+/// from the [`jscript`] module instead.
+///
+/// [`parse_to_ast`] returns the typed syntax tree defined in the [`ast`] module, and
+/// [`parse_cst`] the lossless concrete syntax tree defined in the [`cst`] module.
+/// [`parse_in`] builds the same syntax tree in an arena, borrowing its text from the
+/// page (see the [`arena`] module), and [`parse_stream`] checks a page piece by piece
+/// without building its tree (see the [`stream`] module).
+pub mod arena;
+pub mod ast;
+mod builder;
+pub mod cst;
+pub mod diagnostic;
+mod incremental;
+pub mod jscript;
+pub mod lexer;
+pub mod options;
+pub mod stream;
+
+pub use diagnostic::{Diagnostic, DiagnosticCode, RelatedLocation, Severity};
+pub use options::{IncludeHandling, ParseOptions};
+
+pub(crate) use builder::script_language_attribute;
+
 use pest::Parser;
 use pest_derive::Parser;
+use serde::Serialize;
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
+use std::io::BufRead;
 
 /// Error types for ASP parsing
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -23,33 +51,92 @@ pub enum AspErrorKind {
 #[derive(Debug)]
 pub struct AspParseError {
     message: String,
-    line: Option<usize>,
-    column: Option<usize>,
-    kind: AspErrorKind,
+    span: Option<ast::Span>,
+    code: DiagnosticCode,
 }
 
 impl AspParseError {
+    /// Creates an error that applies to the whole file rather than a location
+    fn file_error(code: DiagnosticCode, message: &str) -> Self {
+        AspParseError {
+            message: message.to_string(),
+            span: None,
+            code,
+        }
+    }
+
+    /// Returns the category of this error
+    pub fn kind(&self) -> AspErrorKind {
+        match self.code {
+            DiagnosticCode::NoAspTags => AspErrorKind::NoAspTags,
+            DiagnosticCode::EmptyFile => AspErrorKind::EmptyFile,
+            _ => AspErrorKind::ParseError,
+        }
+    }
+
+    /// Returns the stable code of this error
+    pub fn code(&self) -> DiagnosticCode {
+        self.code
+    }
+
+    /// Returns the location of this error, or `None` if it applies to the whole file
+    pub fn span(&self) -> Option<ast::Span> {
+        self.span
+    }
+
+    /// Returns this error as a diagnostic
+    ///
+    /// Errors that apply to the whole file are located at the start of the file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::{self, diagnostic::DiagnosticCode};
+    ///
+    /// let error = parser::parse_to_ast("<%\nIf x Then\n%>").unwrap_err();
+    /// let diagnostic = error.diagnostic();
+    /// assert_eq!(diagnostic.code, DiagnosticCode::UnclosedBlock);
+    /// assert_eq!(diagnostic.code.as_str(), "ASP002");
+    /// assert_eq!((diagnostic.span.line, diagnostic.span.column), (2, 1));
+    /// ```
+    pub fn diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            self.code,
+            self.span.unwrap_or(ast::Span::new(0, 0, 1, 1)),
+            self.message.clone(),
+        )
+    }
+
     /// Returns true if this error represents a file with no ASP tags
     pub fn is_no_asp_tags_error(&self) -> bool {
-        self.kind == AspErrorKind::NoAspTags
+        self.kind() == AspErrorKind::NoAspTags
     }
 
     /// Returns true if this error represents an empty file
     pub fn is_empty_file_error(&self) -> bool {
-        self.kind == AspErrorKind::EmptyFile
+        self.kind() == AspErrorKind::EmptyFile
+    }
+}
+
+impl From<Diagnostic> for AspParseError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        AspParseError {
+            message: diagnostic.message,
+            span: Some(diagnostic.span),
+            code: diagnostic.code,
+        }
     }
 }
 
 impl fmt::Display for AspParseError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match (self.line, self.column) {
-            (Some(line), Some(column)) => write!(
+        match self.span {
+            Some(span) => write!(
                 f,
                 "Parse error at line {}, column {}: {}",
-                line, column, self.message
+                span.line, span.column, self.message
             ),
-            (Some(line), None) => write!(f, "Parse error at line {}: {}", line, self.message),
-            _ => write!(f, "Parse error: {}", self.message),
+            None => write!(f, "Parse error: {}", self.message),
         }
     }
 }
@@ -59,22 +146,356 @@ impl Error for AspParseError {}
 /// The main parser for ASP Classic files
 ///
 /// This struct implements the Parser trait from the Pest library,
-/// utilizing the grammar defined in the grammar.pest file.
+/// utilizing the grammar defined in the markup.pest and grammar.pest files.
 #[derive(Parser)]
+#[grammar = "parser/markup.pest"]
 #[grammar = "parser/grammar.pest"]
 pub struct AspParser;
 
+/// Server-side scripting language of an ASP page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub enum ScriptLanguage {
+    /// VBScript, the default language of ASP pages
+    #[default]
+    VBScript,
+    /// JScript, Microsoft's implementation of JavaScript
+    JScript,
+}
+
+impl ScriptLanguage {
+    /// Returns the language matching a `Language` directive value, if it is known
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use asp_classic_parser::parser::ScriptLanguage;
+    ///
+    /// assert_eq!(ScriptLanguage::from_name("jscript"), Some(ScriptLanguage::JScript));
+    /// assert_eq!(ScriptLanguage::from_name("VBScript"), Some(ScriptLanguage::VBScript));
+    /// assert_eq!(ScriptLanguage::from_name("PerlScript"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "vbscript" | "vbs" => Some(ScriptLanguage::VBScript),
+            "jscript" | "javascript" | "ecmascript" => Some(ScriptLanguage::JScript),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ScriptLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptLanguage::VBScript => write!(f, "VBScript"),
+            ScriptLanguage::JScript => write!(f, "JScript"),
+        }
+    }
+}
+
+/// Detects the server-side language of a page from its `Language` directive
+///
+/// ASP only honors the processing directive when it is the first tag of the page.
+/// Pages without a directive, or with an unknown language, default to VBScript.
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{self, ScriptLanguage};
+///
+/// let page = "<%@ Language=\"JScript\" %>\n<% var x = 1; %>";
+/// assert_eq!(parser::detect_language(page), ScriptLanguage::JScript);
+/// assert_eq!(parser::detect_language("<% Dim x %>"), ScriptLanguage::VBScript);
+/// ```
+pub fn detect_language(input: &str) -> ScriptLanguage {
+    let page = input.trim_start_matches('\u{feff}').trim_start();
+    directive_attributes_at(page)
+        .into_iter()
+        .find(|attribute| attribute.name.eq_ignore_ascii_case("Language"))
+        .and_then(|attribute| ScriptLanguage::from_name(&attribute.value))
+        .unwrap_or_default()
+}
+
+/// Parses an ASP Classic file and returns its syntax tree
+///
+/// The page language is detected from its `Language` directive (see
+/// [`detect_language`]). VBScript code is fully analyzed, while JScript code
+/// blocks are kept as [`ast::Unparsed`] text.
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to parse
+///
+/// # Returns
+///
+/// * `Result<ast::Program, AspParseError>` - The syntax tree of the page, or an error
+///   if parsing failed or a block (`If`, `For`, `Sub`, ...) is not properly closed
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{self, ast::Statement};
+///
+/// let program = parser::parse_to_ast("<% If x > 1 Then %>big<% End If %>").unwrap();
+/// match &program.body[0] {
+///     Statement::If(statement) => assert_eq!(statement.then_branch.len(), 1),
+///     other => panic!("unexpected statement: {:?}", other),
+/// }
+/// ```
+pub fn parse_to_ast(input: &str) -> Result<ast::Program, AspParseError> {
+    parse_program(input).map(|(program, _)| program)
+}
+
+/// Parses an ASP Classic file into a syntax tree allocated in an arena
+///
+/// The tree is the one of [`parse_to_ast`], but its nodes are allocated in `arena`
+/// and its names, markup and literals borrow the text of `input`, so that building
+/// it does not allocate for each node. It is dropped with the arena.
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to parse
+/// * `arena` - The arena receiving the nodes of the tree
+///
+/// # Returns
+///
+/// * `Result<arena::Program, AspParseError>` - The syntax tree of the page, or the
+///   same error as [`parse_to_ast`]
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{self, arena::{Bump, Statement}};
+///
+/// let arena = Bump::new();
+/// let program = parser::parse_in("<% If x > 1 Then %>big<% End If %>", &arena).unwrap();
+/// match program.body[0] {
+///     Statement::If(statement) => assert_eq!(statement.then_branch.len(), 1),
+///     other => panic!("unexpected statement: {:?}", other),
+/// }
+/// ```
+pub fn parse_in<'a>(
+    input: &'a str,
+    arena: &'a arena::Bump,
+) -> Result<arena::Program<'a>, AspParseError> {
+    let text = match normalize_line_endings(input) {
+        Cow::Borrowed(text) => text,
+        Cow::Owned(text) => arena.alloc_str(&text),
+    };
+    builder::build_program_in(text, detect_language(text), arena).map(|(program, _)| program)
+}
+
+/// Result of [`parse_recovering`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveredParse {
+    /// Syntax tree of the page, without the statements containing syntax errors
+    pub program: ast::Program,
+    /// Every syntax error found in the page, in source order
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses an ASP Classic file and reports all its syntax errors
+///
+/// Unlike [`parse_to_ast`], parsing does not stop at the first error: the statement
+/// containing a syntax error is skipped and parsing resumes at the next statement,
+/// while unclosed or mismatched blocks are reported without stopping the
+/// construction of the tree.
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to parse
+///
+/// # Returns
+///
+/// * `RecoveredParse` - The syntax tree built from the valid statements, and the
+///   list of errors (empty if the page is valid)
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser;
+///
+/// let page = "<%\nDim\nx = 1\nElse\n%>";
+/// let result = parser::parse_recovering(page);
+/// assert_eq!(result.diagnostics.len(), 2);
+/// assert_eq!(result.diagnostics[0].span.line, 2);
+/// assert_eq!(result.diagnostics[1].span.line, 4);
+/// ```
+pub fn parse_recovering(input: &str) -> RecoveredParse {
+    let input = normalize_line_endings(input);
+    let (program, diagnostics) = builder::build_program_recovering(
+        &input,
+        detect_language(&input),
+        &ParseOptions::default(),
+    );
+    RecoveredParse {
+        program,
+        diagnostics,
+    }
+}
+
+/// Parses a page again after an edit, reusing the syntax tree of its previous
+/// content
+///
+/// Only the ASP blocks around the edit, between the HTML text before and after it,
+/// are parsed again, so that parsing an edited page does not take longer as the
+/// page grows. The whole page is parsed again when the edit may change how the
+/// rest of it is read, such as when it opens or closes a tag, or when the page has
+/// unbalanced blocks. Either way, the result is the one of [`parse_recovering`].
+///
+/// # Arguments
+///
+/// * `previous` - The result of [`parse_recovering`], or of this function, for the
+///   page before the edit
+/// * `previous_input` - The content of the page before the edit
+/// * `input` - The content of the page after the edit
+///
+/// # Returns
+///
+/// * `RecoveredParse` - The syntax tree of the edited page, and its syntax errors
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser;
+///
+/// let page = "<p><% x = 1 %></p>\n<p><% y = 2 %></p>";
+/// let edited = "<p><% x = 10 %></p>\n<p><% y = 2 %></p>";
+/// let previous = parser::parse_recovering(page);
+/// let result = parser::reparse_recovering(&previous, page, edited);
+/// assert_eq!(result, parser::parse_recovering(edited));
+/// ```
+pub fn reparse_recovering(
+    previous: &RecoveredParse,
+    previous_input: &str,
+    input: &str,
+) -> RecoveredParse {
+    let previous_input = normalize_line_endings(previous_input);
+    let input = normalize_line_endings(input);
+    incremental::reparse(previous, &previous_input, &input, &ParseOptions::default())
+        .unwrap_or_else(|| parse_recovering(&input))
+}
+
+/// Parses an ASP Classic page read from a reader, reporting its errors as they are
+/// found
+///
+/// The page is read and parsed in pieces of about
+/// [`stream::DEFAULT_PIECE_SIZE`] bytes, without building its syntax tree, so that
+/// very large pages are checked without holding them in memory. The errors are
+/// those of [`parse_recovering`], except for the blocks left open at the end of the
+/// page, reported last (see [`stream::parse_in_pieces`]).
+///
+/// # Arguments
+///
+/// * `reader` - The reader of the page, in UTF-8
+/// * `on_diagnostic` - Called with each error, as soon as it is found
+///
+/// # Returns
+///
+/// * `io::Result<stream::StreamSummary>` - The language and number of ASP blocks of
+///   the page and its number of errors, or the error reading it
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser;
+///
+/// let page = "<p>Report</p>\n<% For Each row In rows %>\n<td><%= row(0) %></td>\n";
+/// let mut errors = Vec::new();
+/// let summary = parser::parse_stream(page.as_bytes(), |error| errors.push(error)).unwrap();
+/// assert_eq!(summary.code_blocks, 2);
+/// assert_eq!(errors[0].message, "'For Each' block is not closed: expected 'Next'");
+/// ```
+pub fn parse_stream(
+    reader: impl BufRead,
+    on_diagnostic: impl FnMut(Diagnostic),
+) -> std::io::Result<stream::StreamSummary> {
+    stream::parse_in_pieces(reader, stream::DEFAULT_PIECE_SIZE, on_diagnostic)
+}
+
+/// Parses an ASP Classic page into its lossless concrete syntax tree
+///
+/// The tree keeps comments, whitespace and line continuations as trivia attached to
+/// its tokens, so that printing it gives back the input byte for byte. Only grammar
+/// errors are reported: unclosed or mismatched blocks are not checked.
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to parse
+///
+/// # Returns
+///
+/// * `Result<cst::SyntaxTree, AspParseError>` - The concrete syntax tree of the page,
+///   or the first grammar error
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser;
+///
+/// let page = "<%\r\nIf  x Then _\r\n   y = 1 ' set y\r\n%>";
+/// let tree = parser::parse_cst(page).unwrap();
+/// assert_eq!(tree.to_string(), page);
+/// ```
+pub fn parse_cst(input: &str) -> Result<cst::SyntaxTree, AspParseError> {
+    let text = normalize_line_endings(input);
+    cst::build_syntax_tree(input, &text, detect_language(&text))
+}
+
+/// Splits an ASP Classic page into a flat stream of tokens
+///
+/// Tokenizing does not parse the page and never fails: it only recognizes markup,
+/// ASP delimiters and the keywords, names, literals, operators and comments of the
+/// server code. Whitespace and newlines are tokens too, so the texts of the tokens
+/// put end to end give back the input.
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to tokenize
+///
+/// # Returns
+///
+/// * `Vec<lexer::Token>` - The tokens of the page, in source order
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{self, lexer::TokenKind};
+///
+/// let tokens = parser::tokenize("<b><% If x Then y = 1 %></b>");
+/// let kinds: Vec<TokenKind> = tokens
+///     .iter()
+///     .filter(|token| !token.kind.is_trivia())
+///     .map(|token| token.kind)
+///     .collect();
+/// assert_eq!(kinds[..4], [TokenKind::Html, TokenKind::OpenTag, TokenKind::Keyword, TokenKind::Identifier]);
+/// ```
+pub fn tokenize(input: &str) -> Vec<lexer::Token<'_>> {
+    let text = normalize_line_endings(input);
+    lexer::tokenize(input, &text, detect_language(&text))
+}
+
+/// Parses a page and returns its syntax tree with its number of ASP blocks
+fn parse_program(input: &str) -> Result<(ast::Program, usize), AspParseError> {
+    // The grammar accepts LF, CRLF and CR-only line endings, but error positions
+    // are only reported correctly when every line ends with a line feed
+    let input = normalize_line_endings(input);
+    builder::build_program(&input, detect_language(&input), &ParseOptions::default())
+}
+
 /// Parses an ASP Classic file and returns the result
 ///
+/// This is a pass/fail wrapper around [`parse_to_ast`], which also reports empty
+/// files and files without ASP tags as errors. It uses the default
+/// [`ParseOptions`]; see [`parse_with_options`] to change them.
+///
 /// # Arguments
 ///
 /// * `input` - A string slice containing the ASP Classic code to parse
-/// * `verbose` - Optional flag to enable verbose output (default: false)
 ///
 /// # Returns
 ///
-/// * `Result<(), Box<dyn Error>>` - Ok(()) if parsing was successful, or an error
-///   if parsing failed
+/// * `Result<(), Box<dyn Error>>` - Ok(()) if parsing was successful, or an
+///   [`AspParseError`] if parsing failed
 ///
 /// # Examples
 ///
@@ -82,90 +503,258 @@ pub struct AspParser;
 /// use asp_classic_parser::parser;
 ///
 /// let asp_code = "<%\nResponse.Write \"Hello, World!\"\n%>";
-/// match parser::parse(asp_code, false) {
+/// match parser::parse(asp_code) {
 ///     Ok(_) => println!("ASP code parsed successfully!"),
 ///     Err(e) => eprintln!("Error parsing ASP code: {}", e),
 /// }
 /// ```
-pub fn parse(input: &str, verbose: bool) -> Result<(), Box<dyn Error>> {
+pub fn parse(input: &str) -> Result<(), Box<dyn Error>> {
+    parse_checked(input, &ParseOptions::default())?;
+    Ok(())
+}
+
+/// Parses an ASP Classic file with the given options and reports all its errors
+///
+/// Like [`parse`], empty files and files without ASP code are errors (unless
+/// [`ParseOptions::strict_html`] is false), and so are pages in a language missing
+/// from [`ParseOptions::allowed_languages`]. When the page has syntax errors, all of
+/// them are reported as with [`parse_recovering`], up to
+/// [`ParseOptions::max_errors`].
+///
+/// # Arguments
+///
+/// * `input` - A string slice containing the ASP Classic code to parse
+/// * `options` - The options controlling which pages are accepted
+///
+/// # Returns
+///
+/// * `Result<ast::Program, Vec<Diagnostic>>` - The syntax tree of the page, or the
+///   errors found in it (never empty)
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{self, DiagnosticCode, ParseOptions, ScriptLanguage};
+///
+/// let options = ParseOptions {
+///     allowed_languages: vec![ScriptLanguage::VBScript],
+///     ..ParseOptions::default()
+/// };
+/// let errors = parser::parse_with_options("<%@ Language=JScript %><% var x; %>", &options)
+///     .unwrap_err();
+/// assert_eq!(errors[0].code, DiagnosticCode::DisallowedLanguage);
+///
+/// let options = ParseOptions { strict_html: false, ..ParseOptions::default() };
+/// assert!(parser::parse_with_options("<p>Static page</p>", &options).is_ok());
+/// ```
+pub fn parse_with_options(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<ast::Program, Vec<Diagnostic>> {
+    parse_checked(input, options).map_err(|error| {
+        let mut diagnostics = match error.code() {
+            DiagnosticCode::SyntaxError
+            | DiagnosticCode::UnclosedBlock
+            | DiagnosticCode::UnmatchedBlockEnd
+            | DiagnosticCode::MisplacedStatement => {
+                let text = normalize_line_endings(input);
+                builder::build_program_recovering(&text, detect_language(&text), options).1
+            }
+            _ => Vec::new(),
+        };
+        if diagnostics.is_empty() {
+            diagnostics.push(error.diagnostic());
+        }
+        diagnostics
+    })
+}
+
+/// Parses a page and checks it against the options, stopping at the first error
+fn parse_checked(input: &str, options: &ParseOptions) -> Result<ast::Program, AspParseError> {
     // Check if the file is empty or contains only whitespace
     if input.trim().is_empty() {
-        return Err(Box::new(AspParseError {
-            message: "File is empty or contains only whitespace".to_string(),
-            line: None,
-            column: None,
-            kind: AspErrorKind::EmptyFile,
-        }));
-    }
-
-    // Parse the input with the file rule
-    match AspParser::parse(Rule::file, input) {
-        Ok(pairs) => {
-            // Do some basic validation on the parse result
-            let mut tag_count = 0;
-            for pair in pairs {
-                // Only show rule details in verbose mode
-                if verbose {
-                    println!("Rule: {:?}", pair.as_rule());
-                }
+        return Err(AspParseError::file_error(
+            DiagnosticCode::EmptyFile,
+            "File is empty or contains only whitespace",
+        ));
+    }
 
-                // Count ASP tags to ensure we have balanced tags
-                for inner_pair in pair.into_inner() {
-                    match inner_pair.as_rule() {
-                        Rule::asp_script_block | Rule::asp_expression_block => {
-                            tag_count += 1;
-                        }
-                        _ => {}
-                    }
-                }
+    let text = normalize_line_endings(input);
+    let lines = ast::LineIndex::with_tab_width(&text, options.tab_width);
+    let language = detect_language(&text);
+    if !options.allowed_languages.contains(&language) {
+        // Point at the directive selecting the language, if any
+        let span = match text.find("<%@") {
+            Some(start) => {
+                let end = text[start..]
+                    .find("%>")
+                    .map_or(text.len(), |end| start + end + 2);
+                lines.span(start, end)
             }
+            None => lines.span(0, 0),
+        };
+        return Err(Diagnostic::new(
+            DiagnosticCode::DisallowedLanguage,
+            span,
+            format!("{} pages are not allowed", language),
+        )
+        .into());
+    }
 
-            // For validation purposes, ensure we have at least one ASP tag
-            // This helps catch some types of invalid syntax
-            if tag_count == 0 {
-                return Err(Box::new(AspParseError {
-                    message: "No valid ASP tags found in the file".to_string(),
-                    line: None,
-                    column: None,
-                    kind: AspErrorKind::NoAspTags,
-                }));
-            }
+    let (program, tag_count) = builder::build_program(&text, language, options)?;
 
-            Ok(())
-        }
-        Err(e) => {
-            // Convert Pest error into our custom error with location info
-            let message = format!("{}", e);
-
-            // Extract line and column from the error message or use None
-            // Message format is typically: "--> line:column"
-            let (line, column) = extract_position_from_error(&message);
-
-            Err(Box::new(AspParseError {
-                message,
-                line,
-                column,
-                kind: AspErrorKind::ParseError,
-            }))
+    let includes = match options.include_handling {
+        IncludeHandling::Ignore => Vec::new(),
+        IncludeHandling::CountAsCode | IncludeHandling::Forbid => {
+            include_directives(input, &text, language)
         }
+    };
+    if options.include_handling == IncludeHandling::Forbid
+        && let Some(&(start, end)) = includes.first()
+    {
+        return Err(Diagnostic::new(
+            DiagnosticCode::ForbiddenInclude,
+            lines.span(start, end),
+            "Include directives are not allowed",
+        )
+        .into());
     }
+
+    // For validation purposes, ensure we have at least one ASP tag
+    // This helps catch some types of invalid syntax
+    if tag_count == 0 && includes.is_empty() && options.strict_html {
+        return Err(AspParseError::file_error(
+            DiagnosticCode::NoAspTags,
+            "No valid ASP tags found in the file",
+        ));
+    }
+
+    Ok(program)
 }
 
-/// Helper function to extract position information from a Pest error message
-fn extract_position_from_error(error_msg: &str) -> (Option<usize>, Option<usize>) {
-    // Look for patterns like "--> 1:5" in the error message
-    if let Some(pos_index) = error_msg.find("-->") {
-        if let Some(line_col) = error_msg[pos_index + 3..].split_whitespace().next() {
-            if let Some((line_str, col_str)) = line_col.split_once(':') {
-                if let (Ok(line), Ok(column)) =
-                    (line_str.parse::<usize>(), col_str.parse::<usize>())
-                {
-                    return (Some(line), Some(column));
+/// Returns the byte ranges of the `#include` directives of a page
+///
+/// Directives are searched in the HTML text only, so that commented out directives
+/// and strings containing `<!--#include` are skipped.
+fn include_directives(source: &str, text: &str, language: ScriptLanguage) -> Vec<(usize, usize)> {
+    lexer::tokenize(source, text, language)
+        .into_iter()
+        .filter(|token| token.kind == lexer::TokenKind::Html)
+        .flat_map(|token| {
+            let html = token.text.to_ascii_lowercase();
+            html.match_indices("<!--#include")
+                .map(|(index, _)| {
+                    let start = token.span.start + index;
+                    let end = text[start..]
+                        .find("-->")
+                        .map_or(token.span.end, |end| start + end + 3);
+                    (start, end)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// A single `Name=Value` attribute of an ASP processing directive
+///
+/// Directives such as `<%@ Language="VBScript" CodePage=65001 LCID=1036 %>` usually
+/// appear at the top of a page and configure how IIS processes the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DirectiveAttribute {
+    /// Attribute name as written in the source (e.g. `Language`)
+    pub name: String,
+    /// Attribute value without surrounding quotes (e.g. `VBScript`)
+    pub value: String,
+}
+
+/// Extracts the attributes of all processing directives (`<%@ ... %>`) in a file
+///
+/// Returns an empty list if the file contains no directive or cannot be parsed.
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser;
+///
+/// let attributes = parser::parse_directives("<%@ Language=\"VBScript\" CodePage=65001 %>");
+/// assert_eq!(attributes[0].name, "Language");
+/// assert_eq!(attributes[0].value, "VBScript");
+/// assert_eq!(attributes[1].value, "65001");
+/// ```
+pub fn parse_directives(input: &str) -> Vec<DirectiveAttribute> {
+    // Directives are markup, so they are found the same way whatever the page language
+    input
+        .match_indices("<%@")
+        .flat_map(|(index, _)| directive_attributes_at(&input[index..]))
+        .collect()
+}
+
+/// Returns the attributes of the directive starting at the beginning of `input`
+///
+/// Returns an empty list if `input` does not start with a valid directive.
+fn directive_attributes_at(input: &str) -> Vec<DirectiveAttribute> {
+    directive_attribute_texts(input)
+        .into_iter()
+        .map(|(name, value)| DirectiveAttribute {
+            name: name.to_string(),
+            value: value.to_string(),
+        })
+        .collect()
+}
+
+/// Returns the names and values of the attributes of the directive starting at the
+/// beginning of `input`, as slices of it
+fn directive_attribute_texts(input: &str) -> Vec<(&str, &str)> {
+    let mut attributes = Vec::new();
+
+    if let Ok(pairs) = AspParser::parse(Rule::asp_directive_block, input) {
+        for pair in pairs.flatten() {
+            if pair.as_rule() != Rule::directive_attribute {
+                continue;
+            }
+
+            let mut name = "";
+            let mut value = "";
+            for part in pair.into_inner().flatten() {
+                match part.as_rule() {
+                    Rule::directive_name => name = part.as_str(),
+                    Rule::directive_value_text
+                    | Rule::directive_single_quoted_text
+                    | Rule::directive_bare_value => value = part.as_str(),
+                    _ => {}
                 }
             }
+
+            attributes.push((name, value));
         }
     }
 
-    // Unable to extract position info
-    (None, None)
+    attributes
+}
+
+/// Replaces classic Mac line endings (a lone `\r`) with `\n`
+///
+/// `\r\n` sequences are left untouched and each replacement keeps the same byte
+/// length, so byte offsets in the normalized text match the original input.
+pub(crate) fn normalize_line_endings(input: &str) -> Cow<'_, str> {
+    let bytes = input.as_bytes();
+    let has_lone_cr = bytes
+        .iter()
+        .enumerate()
+        .any(|(i, &b)| b == b'\r' && bytes.get(i + 1) != Some(&b'\n'));
+
+    if !has_lone_cr {
+        return Cow::Borrowed(input);
+    }
+
+    let mut normalized = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\r' && chars.peek() != Some(&'\n') {
+            normalized.push('\n');
+        } else {
+            normalized.push(c);
+        }
+    }
+    Cow::Owned(normalized)
 }
@@ -0,0 +1,65 @@
+//! Options controlling how pages are parsed
+//!
+//! [`parse_with_options`](super::parse_with_options) takes a [`ParseOptions`] to
+//! decide which pages are accepted and how errors are reported. The default options
+//! match the behavior of [`parse`](super::parse).
+
+use super::ScriptLanguage;
+
+/// How `<!--#include file="..." -->` and `<!--#include virtual="..." -->` directives
+/// are treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IncludeHandling {
+    /// Include directives are part of the HTML text of the page
+    #[default]
+    Ignore,
+    /// Include directives count as server code, so that a page made only of
+    /// includes is not reported as having no ASP code
+    CountAsCode,
+    /// Include directives are reported as errors (`ASP008`)
+    Forbid,
+}
+
+/// Options of [`parse_with_options`](super::parse_with_options)
+///
+/// # Examples
+///
+/// ```
+/// use asp_classic_parser::parser::{ParseOptions, ScriptLanguage};
+///
+/// let options = ParseOptions {
+///     allowed_languages: vec![ScriptLanguage::VBScript],
+///     tab_width: 4,
+///     ..ParseOptions::default()
+/// };
+/// assert!(options.strict_html);
+/// assert_eq!(options.max_errors, 100);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Report pages without any server code (plain HTML) as errors (`ASP005`);
+    /// when false, such pages are valid
+    pub strict_html: bool,
+    /// Server-side languages a page may be written in; pages in another language
+    /// are reported as errors (`ASP007`)
+    pub allowed_languages: Vec<ScriptLanguage>,
+    /// Maximum number of errors reported for a page
+    pub max_errors: usize,
+    /// How include directives are treated
+    pub include_handling: IncludeHandling,
+    /// Number of columns between tab stops, used to compute the columns of spans
+    /// and diagnostics; with the default of 1, a tab counts as one character
+    pub tab_width: usize,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            strict_html: true,
+            allowed_languages: vec![ScriptLanguage::VBScript, ScriptLanguage::JScript],
+            max_errors: 100,
+            include_handling: IncludeHandling::default(),
+            tab_width: 1,
+        }
+    }
+}
@@ -0,0 +1,274 @@
+//! Parsing of pages read piece by piece
+//!
+//! Generated pages, such as reports, can be tens of megabytes long.
+//! [`parse_in_pieces`] reads a page line by line and parses it in pieces of whole
+//! lines, cut before a line starting with an ASP block once the piece is large
+//! enough. No syntax tree is built: only the blocks left open by the pieces already
+//! parsed (`If`, `For`, `Sub`, ...) are kept, and the errors of each piece are
+//! reported as soon as it is parsed.
+//!
+//! The text read is scanned for the ends of ASP blocks, HTML comments and
+//! server-side script blocks, so that a piece never ends inside one of them; a
+//! piece holds the whole of such an item, however long.
+//!
+//! # Examples
+//!
+//! ```
+//! use asp_classic_parser::parser::stream;
+//!
+//! let page = "<% For i = 1 To 3 %>\n<p><%= i %></p>\n<% Next %>\n<% Dim %>\n";
+//! let mut errors = Vec::new();
+//! // Pieces of a single line, when possible
+//! let summary =
+//!     stream::parse_in_pieces(page.as_bytes(), 1, |error| errors.push(error)).unwrap();
+//! assert_eq!(summary.code_blocks, 4);
+//! assert_eq!(errors.len(), 1);
+//! assert_eq!(errors[0].span.line, 4);
+//! ```
+
+use std::io::{self, BufRead};
+
+use pest::Parser;
+use pest::error::InputLocation;
+
+use super::ast::LineIndex;
+use super::builder::PageChecker;
+use super::{
+    AspParser, Diagnostic, ParseOptions, Rule, ScriptLanguage, detect_language,
+    normalize_line_endings,
+};
+
+/// Size from which a piece is cut by [`parse_stream`](super::parse_stream), in bytes
+pub const DEFAULT_PIECE_SIZE: usize = 64 * 1024;
+
+/// Summary of a page parsed piece by piece
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamSummary {
+    /// Server-side language of the page, detected in its first piece
+    pub language: ScriptLanguage,
+    /// Number of ASP blocks (code, expression and directive blocks, and server-side
+    /// script blocks) in the page
+    pub code_blocks: usize,
+    /// Number of errors reported
+    pub errors: usize,
+}
+
+/// Parses a page read from `reader` piece by piece, reporting its errors as they
+/// are found
+///
+/// The errors are those of [`parse_recovering`](super::parse_recovering), reported
+/// in source order piece by piece, except for the blocks left open at the end of
+/// the page, reported last.
+///
+/// # Arguments
+///
+/// * `reader` - The reader of the page, in UTF-8
+/// * `piece_size` - The size from which a piece is cut, in bytes
+/// * `on_diagnostic` - Called with each error, as soon as it is found
+///
+/// # Returns
+///
+/// * `io::Result<StreamSummary>` - The summary of the page, or the error reading it
+pub fn parse_in_pieces(
+    reader: impl BufRead,
+    piece_size: usize,
+    mut on_diagnostic: impl FnMut(Diagnostic),
+) -> io::Result<StreamSummary> {
+    let options = ParseOptions::default();
+    let mut pieces = Pieces::new(reader, piece_size);
+    let mut language = ScriptLanguage::default();
+    let mut checker = None;
+    let mut errors = 0;
+    // Position of the piece in the page
+    let (mut start, mut line) = (0, 1);
+
+    while let Some(piece) = pieces.next_piece()? {
+        let text = normalize_line_endings(&piece);
+        let checker = checker.get_or_insert_with(|| {
+            language = detect_language(&text);
+            PageChecker::new(language, &options)
+        });
+        let lines = LineIndex::for_piece(&text, options.tab_width, start, line);
+        for diagnostic in checker.check(&text, &lines) {
+            errors += 1;
+            on_diagnostic(diagnostic);
+        }
+        start += text.len();
+        line += text.bytes().filter(|&byte| byte == b'\n').count();
+    }
+
+    let code_blocks = match checker {
+        Some(checker) => {
+            let (code_blocks, diagnostics) = checker.finish();
+            for diagnostic in diagnostics {
+                errors += 1;
+                on_diagnostic(diagnostic);
+            }
+            code_blocks
+        }
+        None => 0,
+    };
+    Ok(StreamSummary {
+        language,
+        code_blocks,
+        errors,
+    })
+}
+
+/// Reads a page in pieces of whole lines
+struct Pieces<R> {
+    reader: R,
+    piece_size: usize,
+    /// Text read and not returned yet
+    buffer: String,
+    scanner: Scanner,
+}
+
+impl<R: BufRead> Pieces<R> {
+    fn new(reader: R, piece_size: usize) -> Self {
+        Pieces {
+            reader,
+            piece_size,
+            buffer: String::new(),
+            scanner: Scanner::default(),
+        }
+    }
+
+    /// Returns the next piece of the page, or `None` at its end
+    ///
+    /// A piece is cut before a line starting with `<%` once it has `piece_size`
+    /// bytes, if it ends in markup. The first piece has the text of the page up to
+    /// its first line starting with `<%` at least, for its language directive.
+    fn next_piece(&mut self) -> io::Result<Option<String>> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                let piece = std::mem::take(&mut self.buffer);
+                return Ok((!piece.is_empty()).then_some(piece));
+            }
+            if line.starts_with("<%")
+                && self.buffer.len() >= self.piece_size
+                && !self.buffer.trim_start_matches('\u{feff}').trim().is_empty()
+                && self.scanner.ends_in_markup(&self.buffer)
+            {
+                self.scanner = Scanner::default();
+                return Ok(Some(std::mem::replace(&mut self.buffer, line)));
+            }
+            self.buffer.push_str(&line);
+        }
+    }
+}
+
+/// What the text scanned ends in
+#[derive(Debug, Default, PartialEq)]
+enum State {
+    /// Markup, where an ASP block, an HTML comment or a script block may start
+    #[default]
+    Markup,
+    /// ASP block, up to the next `%>`
+    Code,
+    /// HTML comment, up to the next `-->`
+    Comment,
+    /// Server-side script block, up to the next `</script>`
+    Script,
+}
+
+/// Scanner of the items of the text read, as the grammar reads them
+#[derive(Debug, Default)]
+struct Scanner {
+    /// Byte offset scanned up to
+    position: usize,
+    state: State,
+}
+
+impl Scanner {
+    /// Scans `text` from the position reached, and returns whether it ends in
+    /// markup, between two items of the page
+    ///
+    /// The text ends with a line break, and more text may follow: an HTML comment
+    /// whose end is not read yet, or a `<script` tag cut by a line break, may
+    /// still end in it.
+    fn ends_in_markup(&mut self, text: &str) -> bool {
+        while self.position < text.len() {
+            let rest = &text[self.position..];
+            let (state, length) = match self.state {
+                State::Code => match rest.find("%>") {
+                    Some(index) => (State::Markup, index + 2),
+                    None => (State::Code, rest.len()),
+                },
+                State::Comment => match rest.find("-->") {
+                    Some(index) => (State::Markup, index + 3),
+                    None => (State::Comment, rest.len()),
+                },
+                State::Script => match script_close(rest) {
+                    Ok(end) => (State::Markup, end),
+                    Err(length) => {
+                        self.position += length;
+                        return false;
+                    }
+                },
+                State::Markup => {
+                    let Some(index) = rest.find('<') else {
+                        self.position = text.len();
+                        break;
+                    };
+                    let tag = &rest[index..];
+                    if tag.starts_with("<%") {
+                        (State::Code, index + 2)
+                    } else if tag.starts_with("<!--") && !tag[4..].starts_with('#') {
+                        (State::Comment, index + 4)
+                    } else if starts_with_ignore_case(tag, "<script") {
+                        match AspParser::parse(Rule::server_script_open, tag) {
+                            Ok(mut pairs) => {
+                                let open = pairs.next().expect("the rule produces a pair");
+                                (State::Script, index + open.as_str().len())
+                            }
+                            // The tag goes on in the text not read yet
+                            Err(error) if error.location == InputLocation::Pos(tag.len()) => {
+                                self.position += index;
+                                return false;
+                            }
+                            Err(_) => (State::Markup, index + 1),
+                        }
+                    } else {
+                        (State::Markup, index + 1)
+                    }
+                }
+            };
+            self.state = state;
+            self.position += length;
+        }
+        self.state == State::Markup
+    }
+}
+
+/// Returns the end of the first `</script>` tag of a text, or the length of the
+/// text known not to have its start when it has none
+///
+/// A `</script` tag followed by whitespace only may end in the text not read yet.
+fn script_close(text: &str) -> Result<usize, usize> {
+    let mut from = 0;
+    while let Some(index) = find_ignore_case(&text[from..], "</script") {
+        let start = from + index + "</script".len();
+        let tag = text[start..].trim_start_matches([' ', '\t', '\r', '\n']);
+        if tag.starts_with('>') {
+            return Ok(text.len() - tag.len() + 1);
+        } else if tag.is_empty() {
+            return Err(from + index);
+        }
+        from = start;
+    }
+    Err(text.len())
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.as_bytes()
+        .get(..prefix.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(prefix.as_bytes()))
+}
+
+fn find_ignore_case(text: &str, pattern: &str) -> Option<usize> {
+    text.as_bytes()
+        .windows(pattern.len())
+        .position(|window| window.eq_ignore_ascii_case(pattern.as_bytes()))
+}
@@ -0,0 +1,115 @@
+//! Python bindings
+//!
+//! Built with the `python` feature, the library is a Python extension module named
+//! `asp_classic_parser`, usually built and installed with
+//! [maturin](https://www.maturin.rs/) (`maturin develop --release`):
+//!
+//! ```python
+//! import asp_classic_parser
+//!
+//! result = asp_classic_parser.parse('<% Response.Write "Hello" %>')
+//! print(result["ast"]["body"][0]["type"])
+//! for diagnostic in asp_classic_parser.lint("default.asp"):
+//!     print(diagnostic["line"], diagnostic["code"], diagnostic["message"])
+//! ```
+//!
+//! Diagnostics are dictionaries with the fields of the JSON output format.
+
+use pyo3::exceptions::PyIOError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use serde_json::{Value, json};
+use std::path::Path;
+
+use crate::analysis::WorkspaceIndex;
+use crate::file_utils::read_file_with_encoding;
+use crate::lint::{Registry, lint_file};
+use crate::output_format::diagnostic_json;
+use crate::parser::{self, ParseOptions};
+
+/// Parses a page
+///
+/// Returns a dictionary with the syntax tree of the page (`ast`, partial if the page
+/// has syntax errors) and its `diagnostics`.
+#[pyfunction]
+fn parse(py: Python<'_>, source: &str) -> PyResult<PyObject> {
+    let (program, diagnostics) = match parser::parse_with_options(source, &ParseOptions::default())
+    {
+        Ok(program) => (program, Vec::new()),
+        Err(diagnostics) => (parser::parse_recovering(source).program, diagnostics),
+    };
+    let diagnostics: Vec<Value> = diagnostics.iter().map(diagnostic_json).collect();
+    to_python(
+        py,
+        &json!({
+            "ast": program,
+            "diagnostics": diagnostics,
+        }),
+    )
+}
+
+/// Checks a file with the lint rules and returns the list of its diagnostics
+///
+/// Syntax errors are returned instead if the file does not parse. The files it
+/// includes are read to know the names they declare. Raises `OSError` if the file
+/// cannot be read.
+#[pyfunction]
+fn lint(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let file = Path::new(path);
+    let source = read_file_with_encoding(file)
+        .map_err(|error| PyIOError::new_err(format!("Cannot read file {}: {}", path, error)))?;
+    let mut index = WorkspaceIndex::default();
+    index.update(file, &source);
+    index.load_includes(file);
+    let diagnostics = lint_file(file, &source, &Registry::default(), &index);
+    let diagnostics: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let mut json_diagnostic = diagnostic_json(diagnostic);
+            json_diagnostic["file"] = json!(path);
+            json_diagnostic
+        })
+        .collect();
+    to_python(py, &Value::Array(diagnostics))
+}
+
+/// Converts a JSON value to Python dictionaries, lists and scalars
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(value) => value.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => integer.into_pyobject(py)?.into_any().unbind(),
+            None => number
+                .as_f64()
+                .unwrap_or(f64::NAN)
+                .into_pyobject(py)?
+                .into_any()
+                .unbind(),
+        },
+        Value::String(string) => string.into_pyobject(py)?.into_any().unbind(),
+        Value::Array(values) => {
+            let list = PyList::empty(py);
+            for value in values {
+                list.append(to_python(py, value)?)?;
+            }
+            list.into_any().unbind()
+        }
+        Value::Object(entries) => {
+            let dict = PyDict::new(py);
+            for (key, value) in entries {
+                dict.set_item(key, to_python(py, value)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+/// The `asp_classic_parser` Python module
+#[pymodule]
+fn asp_classic_parser(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(lint, module)?)?;
+    module.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    Ok(())
+}
@@ -0,0 +1,468 @@
+//! Remote cache of parsing results
+//!
+//! The results of the files parsed are stored in an HTTP server or an
+//! S3-compatible blob store, keyed by the hashes of the file content and of the
+//! parsing options, so that CI jobs running on several machines reuse the results
+//! of the files that did not change. The remote cache only extends the local
+//! cache: once a request fails, it is disabled for the rest of the run and the
+//! files are parsed.
+
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::cache::{
+    Cache, CacheEntry, CacheError, CacheResult, RemoteBackend, decode_versioned, encode_versioned,
+};
+use crate::parser::Diagnostic;
+
+/// Environment variable with the bearer token of the HTTP backend
+pub const TOKEN_VAR: &str = "ASP_PARSER_CACHE_REMOTE_TOKEN";
+
+/// Time after which a request to the remote cache fails
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result of a file stored in the remote cache
+#[derive(Debug, Serialize, Deserialize)]
+struct RemoteEntry {
+    /// Whether the file was successfully parsed
+    success: bool,
+    /// Problems reported when the file was parsed
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// AWS credentials signing the requests to an S3-compatible store
+#[derive(Clone)]
+pub struct S3Credentials {
+    /// Access key ID
+    pub access_key_id: String,
+    /// Secret access key
+    pub secret_access_key: String,
+    /// Session token of temporary credentials
+    pub session_token: Option<String>,
+    /// Region of the bucket, `us-east-1` for most S3-compatible stores
+    pub region: String,
+}
+
+impl fmt::Debug for S3Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("S3Credentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("region", &self.region)
+            .finish_non_exhaustive()
+    }
+}
+
+impl S3Credentials {
+    /// Read the credentials from the `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY`,
+    /// `AWS_SESSION_TOKEN` and `AWS_REGION` (or `AWS_DEFAULT_REGION`)
+    /// environment variables, the region being `us-east-1` by default
+    pub fn from_env() -> CacheResult<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let (Some(access_key_id), Some(secret_access_key)) =
+            (var("AWS_ACCESS_KEY_ID"), var("AWS_SECRET_ACCESS_KEY"))
+        else {
+            return Err(CacheError::RemoteError(
+                "the s3 backend needs the AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables".to_string(),
+            ));
+        };
+        Ok(Self {
+            access_key_id,
+            secret_access_key,
+            session_token: var("AWS_SESSION_TOKEN"),
+            region: var("AWS_REGION")
+                .or_else(|| var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| "us-east-1".to_string()),
+        })
+    }
+
+    /// Compute the headers signing a request with AWS Signature Version 4
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The method of the request
+    /// * `url` - The URL of the request, without query
+    /// * `payload_hash` - The SHA-256 hash of the body, in hexadecimal
+    /// * `now` - The time of the request
+    fn sign(
+        &self,
+        method: &Method,
+        url: &Url,
+        payload_hash: &str,
+        now: SystemTime,
+    ) -> Vec<(&'static str, String)> {
+        let amz_date = amz_date(now);
+        let date = &amz_date[..8];
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", payload_hash.to_string()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value))
+            .collect();
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            method,
+            url.path(),
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{:x}",
+            amz_date,
+            scope,
+            Sha256::digest(canonical_request.as_bytes())
+        );
+        let key = [date, self.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.secret_access_key).into_bytes(),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature: String = hmac_sha256(&key, string_to_sign.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        // The host header is set by the HTTP client
+        headers.remove(0);
+        headers.push((
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key_id, scope, signed_headers, signature
+            ),
+        ));
+        headers
+    }
+}
+
+/// Computes the HMAC-SHA256 of a message
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Formats a time as the `x-amz-date` header, such as `20240115T120000Z`
+fn amz_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs_of_day) = (secs / 86400, secs % 86400);
+
+    // Civil date of a number of days since 1970-01-01, in the proleptic
+    // Gregorian calendar
+    let days = days as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// How the requests to the remote cache are authenticated
+enum Auth {
+    /// Without authentication, or with a bearer token
+    Token(Option<String>),
+    /// Signed with AWS credentials
+    S3(S3Credentials),
+}
+
+/// Remote cache shared by the machines running the parser
+pub struct RemoteCache {
+    /// URL of the directory of the entries, ending with a slash
+    url: Url,
+    /// How the requests are authenticated
+    auth: Auth,
+    /// HTTP client, reused by all requests
+    client: Client,
+    /// Error that disabled the remote cache
+    error: Mutex<Option<String>>,
+}
+
+impl fmt::Debug for RemoteCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteCache")
+            .field("url", &self.url.as_str())
+            .field("error", &self.error())
+            .finish_non_exhaustive()
+    }
+}
+
+impl RemoteCache {
+    /// Create the remote cache of a URL, with the credentials of the
+    /// environment: the bearer token of the `ASP_PARSER_CACHE_REMOTE_TOKEN`
+    /// variable for the HTTP backend, or the AWS credentials for the S3 backend
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The HTTP or HTTPS URL of the directory of the entries, such as
+    ///   `https://s3.example.com/bucket/asp-parser` for the S3 backend
+    /// * `backend` - The kind of store
+    pub fn new(url: &str, backend: RemoteBackend) -> CacheResult<Self> {
+        let auth = match backend {
+            RemoteBackend::Http => Auth::Token(
+                std::env::var(TOKEN_VAR)
+                    .ok()
+                    .filter(|token| !token.is_empty()),
+            ),
+            RemoteBackend::S3 => Auth::S3(S3Credentials::from_env()?),
+        };
+        Self::with_auth(url, auth)
+    }
+
+    /// Create the remote cache of an S3-compatible store with given credentials
+    pub fn with_s3_credentials(url: &str, credentials: S3Credentials) -> CacheResult<Self> {
+        Self::with_auth(url, Auth::S3(credentials))
+    }
+
+    fn with_auth(url: &str, auth: Auth) -> CacheResult<Self> {
+        let invalid = |reason: &str| {
+            CacheError::RemoteError(format!("invalid remote cache URL '{}': {}", url, reason))
+        };
+        let mut url = Url::parse(url).map_err(|e| invalid(&e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid("expected an http or https URL"));
+        }
+        if !url.path().ends_with('/') {
+            url.set_path(&format!("{}/", url.path()));
+        }
+        let client = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| CacheError::RemoteError(e.to_string()))?;
+        Ok(Self {
+            url,
+            auth,
+            client,
+            error: Mutex::new(None),
+        })
+    }
+
+    /// Compute the key of the result of a file, from the hashes of its content
+    /// and of the parsing options
+    pub fn key(content_hash: &str, options_hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(content_hash.as_bytes());
+        hasher.update(b":");
+        hasher.update(options_hash.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Look up the result of a file, returned as a cache entry
+    ///
+    /// # Returns
+    ///
+    /// * `CacheResult<Option<CacheEntry>>` - The entry, `None` if the result is
+    ///   not stored or the remote cache is disabled, or an error if the file
+    ///   cannot be read
+    pub fn fetch(&self, path: &Path, options_hash: &str) -> CacheResult<Option<CacheEntry>> {
+        if self.error().is_some() {
+            return Ok(None);
+        }
+        let content_hash = Cache::hash_file(path)?;
+        let body = match self.get(&Self::key(&content_hash, options_hash)) {
+            Ok(Some(body)) => body,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                self.disable(e);
+                return Ok(None);
+            }
+        };
+        // Entries of another format or damaged are ignored, to be stored again
+        let Ok(Some(remote_entry)) = decode_versioned::<RemoteEntry>(&body) else {
+            return Ok(None);
+        };
+        Ok(Some(CacheEntry {
+            file_path: path.to_path_buf(),
+            content_hash,
+            timestamp: SystemTime::now(),
+            success: remote_entry.success,
+            options_hash: options_hash.to_string(),
+            diagnostics: remote_entry.diagnostics,
+        }))
+    }
+
+    /// Store the result of a file, disabling the remote cache if it fails
+    pub fn store(&self, entry: &CacheEntry) {
+        if self.error().is_some() {
+            return;
+        }
+        let remote_entry = RemoteEntry {
+            success: entry.success,
+            diagnostics: entry.diagnostics.clone(),
+        };
+        let result = encode_versioned(&remote_entry)
+            .map_err(|e| e.to_string())
+            .and_then(|body| self.put(&Self::key(&entry.content_hash, &entry.options_hash), body));
+        if let Err(e) = result {
+            self.disable(e);
+        }
+    }
+
+    /// Get the error that disabled the remote cache, if any
+    pub fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Disable the remote cache after an error, keeping the first one
+    fn disable(&self, error: String) {
+        self.error.lock().unwrap().get_or_insert(error);
+    }
+
+    /// Get the body of an entry, `None` if it is not stored
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let response = self.send(Method::GET, key, Vec::new())?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => response
+                .bytes()
+                .map(|body| Some(body.to_vec()))
+                .map_err(|e| e.to_string()),
+            status => Err(format!("GET {} returned {}", self.url, status)),
+        }
+    }
+
+    /// Store the body of an entry
+    fn put(&self, key: &str, body: Vec<u8>) -> Result<(), String> {
+        let response = self.send(Method::PUT, key, body)?;
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("PUT {} returned {}", self.url, response.status()))
+        }
+    }
+
+    /// Send an authenticated request for an entry
+    fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Vec<u8>,
+    ) -> Result<reqwest::blocking::Response, String> {
+        let url = self.url.join(key).map_err(|e| e.to_string())?;
+        let mut request = self.client.request(method.clone(), url.clone());
+        match &self.auth {
+            Auth::Token(Some(token)) => request = request.bearer_auth(token),
+            Auth::Token(None) => {}
+            Auth::S3(credentials) => {
+                let payload_hash = format!("{:x}", Sha256::digest(&body));
+                for (name, value) in
+                    credentials.sign(&method, &url, &payload_hash, SystemTime::now())
+                {
+                    request = request.header(name, value);
+                }
+            }
+        }
+        if method == Method::PUT {
+            request = request.body(body);
+        }
+        request.send().map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amz_date() {
+        assert_eq!(amz_date(UNIX_EPOCH), "19700101T000000Z");
+        let time = UNIX_EPOCH + Duration::from_secs(1_705_320_000);
+        assert_eq!(amz_date(time), "20240115T120000Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_251_199);
+        assert_eq!(amz_date(leap_day), "20240229T235959Z");
+    }
+
+    #[test]
+    fn test_sign() {
+        let credentials = S3Credentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: Some("session-token".to_string()),
+            region: "eu-west-1".to_string(),
+        };
+        let url = Url::parse("https://s3.example.com:9000/asp-cache/ci/0123abcd").unwrap();
+        let payload_hash = format!("{:x}", Sha256::digest(b"cached result"));
+        let time = UNIX_EPOCH + Duration::from_secs(1_705_320_000);
+
+        // Signature computed by the AWS SDK for the same request
+        let headers = credentials.sign(&Method::PUT, &url, &payload_hash, time);
+        assert_eq!(
+            headers,
+            vec![
+                ("x-amz-content-sha256", payload_hash.clone()),
+                ("x-amz-date", "20240115T120000Z".to_string()),
+                ("x-amz-security-token", "session-token".to_string()),
+                (
+                    "authorization",
+                    "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20240115/eu-west-1/s3/aws4_request, \
+                     SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token, \
+                     Signature=fbdcf853c79ed3c2df16dff2bf5fc297c2a2e3490284e562e9121b42e9ce70c7"
+                        .to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        assert!(RemoteCache::new("ftp://cache.example.com", RemoteBackend::Http).is_err());
+        assert!(RemoteCache::new("not a url", RemoteBackend::Http).is_err());
+
+        let cache = RemoteCache::new("http://cache.example.com/asp", RemoteBackend::Http).unwrap();
+        assert_eq!(cache.url.as_str(), "http://cache.example.com/asp/");
+    }
+
+    #[test]
+    fn test_key() {
+        let key = RemoteCache::key("content", "options");
+        assert_eq!(key.len(), 64);
+        assert_ne!(key, RemoteCache::key("content", "other options"));
+        assert_ne!(key, RemoteCache::key("other content", "options"));
+    }
+}
@@ -181,12 +181,11 @@ fn extract_asset_url(
     // Find the asset with matching name
     if let Some(assets) = release_data["assets"].as_array() {
         for asset in assets {
-            if let Some(name) = asset["name"].as_str() {
-                if name == asset_name {
-                    if let Some(url) = asset["browser_download_url"].as_str() {
-                        return Ok((url.to_string(), version.to_string()));
-                    }
-                }
+            if let Some(name) = asset["name"].as_str()
+                && name == asset_name
+                && let Some(url) = asset["browser_download_url"].as_str()
+            {
+                return Ok((url.to_string(), version.to_string()));
             }
         }
     }
@@ -444,8 +443,7 @@ pub fn self_update(
             "Self-update unavailable in development mode. Use `cargo build` instead.",
             true,
         );
-        return Err(UpdateError::IoError(io::Error::new(
-            io::ErrorKind::Other,
+        return Err(UpdateError::IoError(io::Error::other(
             "Cannot self-update in development mode",
         )));
     }
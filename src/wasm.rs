@@ -0,0 +1,66 @@
+//! WebAssembly bindings
+//!
+//! Built with the `wasm` feature for the `wasm32-unknown-unknown` target, this module
+//! exports a JavaScript API with `wasm-bindgen`, so that pages can be checked in a
+//! browser playground or in VS Code for the web:
+//!
+//! ```text
+//! cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/asp_classic_parser.wasm
+//! ```
+//!
+//! ```js
+//! import init, { parse, lint } from "./pkg/asp_classic_parser.js";
+//!
+//! await init();
+//! const { ast, diagnostics } = parse('<% Response.Write "Hello" %>');
+//! for (const d of lint(source)) console.log(`${d.line}:${d.column} ${d.code} ${d.message}`);
+//! ```
+//!
+//! Diagnostics are plain objects with the fields of the JSON output format: `line`,
+//! `column`, `code`, `message` and `severity`.
+
+use serde::Serialize;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use crate::lint::Registry;
+use crate::output_format::diagnostic_json;
+use crate::parser::{self, Diagnostic, ParseOptions};
+
+/// Parses a page
+///
+/// Returns an object with the syntax tree of the page (`ast`, partial if the page
+/// has syntax errors) and its `diagnostics`.
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<JsValue, JsError> {
+    let (program, diagnostics) = match parser::parse_with_options(source, &ParseOptions::default())
+    {
+        Ok(program) => (program, Vec::new()),
+        Err(diagnostics) => (parser::parse_recovering(source).program, diagnostics),
+    };
+    to_js(&json!({
+        "ast": program,
+        "diagnostics": diagnostics_json(&diagnostics),
+    }))
+}
+
+/// Checks a page with the lint rules and returns its diagnostics
+///
+/// Syntax errors are returned instead if the page does not parse.
+#[wasm_bindgen]
+pub fn lint(source: &str) -> Result<JsValue, JsError> {
+    let diagnostics = crate::lint::lint(source, &Registry::default());
+    to_js(&diagnostics_json(&diagnostics))
+}
+
+fn diagnostics_json(diagnostics: &[Diagnostic]) -> serde_json::Value {
+    diagnostics.iter().map(diagnostic_json).collect()
+}
+
+/// Converts a value to plain JavaScript objects and arrays
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|error| JsError::new(&error.to_string()))
+}
@@ -0,0 +1,112 @@
+use std::fs;
+
+use asp_classic_parser::parser::arena::{Bump, Expression, LiteralValue, Statement};
+use asp_classic_parser::parser::{self, DiagnosticCode};
+
+/// Checks that the arena tree of a page serializes like its owned tree
+fn assert_same_tree(page: &str) {
+    let arena = Bump::new();
+    let tree = parser::parse_in(page, &arena).expect("the page should parse");
+    let expected = parser::parse_to_ast(page).expect("the page should parse");
+    assert_eq!(
+        serde_json::to_value(tree).unwrap(),
+        serde_json::to_value(&expected).unwrap(),
+        "{:?}",
+        page
+    );
+}
+
+#[test]
+fn test_arena_tree_matches_fixtures() {
+    for entry in fs::read_dir("fixtures/passing").expect("Failed to read fixtures") {
+        let path = entry.expect("Failed to read fixture").path();
+        let page = fs::read_to_string(&path).expect("Failed to read fixture");
+        assert_same_tree(&page);
+    }
+}
+
+#[test]
+fn test_arena_tree_matches_statements() {
+    for page in [
+        "<% Option Explicit\nDim a, b(10), c()\nReDim Preserve c(5)\nPrivate Const X = 1, Y = \"y\" %>",
+        "<% Erase a, b : Set o = New Cart : o.Add(1, , 3) : Call Show(\"a\") %>",
+        "<% If x Then %>a<% ElseIf y Then %>b<% ElseIf z Then %>c<% Else %>d<% End If %>",
+        "<% If x Then y = 1 Else z = 2 %>",
+        "<% Select Case n %>\n<% Case 1, 2 %>low<% Case Else %>high<% End Select %>",
+        "<% For i = 1 To 10 Step -1\nFor Each k In d.Keys\nNext\nNext %>",
+        "<% For i = 1 To 2 : For j = 1 To 2 : Next j, i %>",
+        "<% Do While x < 3\nx = x + 1\nLoop\nDo\nExit Do\nLoop Until x %>",
+        "<% While Not rs.EOF : rs.MoveNext : Wend %>",
+        "<% With Response\n.Write Now()\n.Flush\nEnd With %>",
+        "<% Class Cart\nPrivate items\nPublic Default Property Get Item(ByVal i, ByRef list())\nEnd Property\nEnd Class %>",
+        "<% Function Half(n) : Half = n / 2 : Exit Function : End Function %>",
+        "<% On Error Resume Next : Stop : Randomize 42 : On Error GoTo 0 %>",
+        "<% x = -&HFFFF + &O17& * 1.5E3 \\ 2 Mod 3 ^ 2 & \"a\"\"b\" %>",
+        "<% d = #2024-01-31# : n = Nothing : e = Empty : u = Null : t = True Xor False Imp [my var] %>",
+        "<%@ Language=\"VBScript\" CodePage=65001 %>\n<p><%= Server.HTMLEncode(name) %></p>",
+        "<script runat=\"server\" language=\"VBScript\">\nSub Init() : x = 1 : End Sub\n</script><% Init %>",
+        "<script runat=\"server\" language=\"JScript\">function f() { return 1; }</script><% f %>",
+        "<%@ Language=\"JScript\" %>\n<% var x = 1; %><%= x %>\n<script runat=\"server\">var y;</script>",
+        "<p>\r<% x = 1\r%>\r</p>",
+    ] {
+        assert_same_tree(page);
+    }
+}
+
+#[test]
+fn test_arena_tree_borrows_the_page() {
+    let page = "<p>Hello</p><% name = \"Ada\" : message = \"Say \"\"hi\"\"\" %>";
+    let arena = Bump::new();
+    let program = parser::parse_in(page, &arena).unwrap();
+    let in_page = |text: &str| page.as_bytes().as_ptr_range().contains(&text.as_ptr());
+
+    let Statement::Html(html) = program.body[0] else {
+        panic!("expected markup");
+    };
+    assert!(in_page(html.text));
+
+    let strings: Vec<&str> = program.body[1..]
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Assignment(assignment) => Some(assignment.value),
+            _ => None,
+        })
+        .filter_map(|value| match value {
+            Expression::Literal(literal) => match literal.value {
+                LiteralValue::String(text) => Some(text),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(strings, ["Ada", "Say \"hi\""]);
+    // Escaped quotes are resolved in a copy
+    assert!(in_page(strings[0]));
+    assert!(!in_page(strings[1]));
+}
+
+#[test]
+fn test_arena_errors_match_owned_tree() {
+    let invalid = fs::read_to_string("fixtures/failing/invalid_syntax.asp").unwrap();
+    let jscript = fs::read_to_string("fixtures/failing/jscript_unbalanced.asp").unwrap();
+    for page in [
+        invalid.as_str(),
+        jscript.as_str(),
+        "<% If x Then %>open",
+        "<% End If %>",
+        "<% Select Case x\ny = 1\nCase 1\nEnd Select %>",
+        "<% If x Then\nSub Inner()\nEnd Sub\nEnd If %>",
+        "<% Do While x\nLoop Until y %>",
+        "<% For i = 1 To 2\nWend %>",
+    ] {
+        let arena = Bump::new();
+        let error = parser::parse_in(page, &arena).unwrap_err();
+        let expected = parser::parse_to_ast(page).unwrap_err();
+        assert_eq!(error.to_string(), expected.to_string(), "{:?}", page);
+        assert_eq!(error.code(), expected.code());
+    }
+
+    let arena = Bump::new();
+    let error = parser::parse_in("<% Next %>", &arena).unwrap_err();
+    assert_eq!(error.code(), DiagnosticCode::UnmatchedBlockEnd);
+}
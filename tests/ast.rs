@@ -0,0 +1,333 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::ast::*;
+use asp_classic_parser::parser::{self, ScriptLanguage};
+
+/// Parses ASP code and returns the statements of the page, without HTML
+fn statements(asp_code: &str) -> Vec<Statement> {
+    parser::parse_to_ast(asp_code)
+        .expect("ASP code should parse")
+        .body
+        .into_iter()
+        .filter(|statement| !matches!(statement, Statement::Html(_)))
+        .collect()
+}
+
+/// Returns the value assigned by the first statement of the given code
+fn assigned_value(asp_code: &str) -> Expression {
+    match statements(asp_code).remove(0) {
+        Statement::Assignment(assignment) => assignment.value,
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_control_structures_fixture() {
+    let fixture_path = Path::new("fixtures/passing/control_structures.asp");
+    let content =
+        fs::read_to_string(fixture_path).expect("Failed to read control structures fixture");
+
+    let program = parser::parse_to_ast(&content).expect("Fixture should parse");
+    let kinds: Vec<&str> = program
+        .body
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Class(_) => Some("class"),
+            Statement::Procedure(_) => Some("procedure"),
+            Statement::For(_) => Some("for"),
+            Statement::ForEach(_) => Some("for each"),
+            Statement::DoLoop(_) => Some("do"),
+            Statement::With(_) => Some("with"),
+            Statement::If(_) => Some("if"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        vec!["class", "procedure", "for", "for each", "do", "with", "if"]
+    );
+}
+
+#[test]
+fn test_operator_precedence() {
+    // 1 + 2 * 3 ^ 2 parses as 1 + (2 * (3 ^ 2))
+    let Expression::Binary(sum) = assigned_value("<% x = 1 + 2 * 3 ^ 2 %>") else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(sum.operator, BinaryOperator::Add);
+    let Expression::Binary(product) = *sum.right else {
+        panic!("expected a product");
+    };
+    assert_eq!(product.operator, BinaryOperator::Multiply);
+    let Expression::Binary(power) = *product.right else {
+        panic!("expected a power");
+    };
+    assert_eq!(power.operator, BinaryOperator::Power);
+
+    // Not binds looser than comparisons, and And looser than Not
+    let Expression::Binary(and) = assigned_value("<% x = Not a = b And c %>") else {
+        panic!("expected a binary expression");
+    };
+    assert_eq!(and.operator, BinaryOperator::And);
+    let Expression::Unary(not) = *and.left else {
+        panic!("expected a Not expression");
+    };
+    assert_eq!(not.operator, UnaryOperator::Not);
+    assert!(matches!(
+        *not.operand,
+        Expression::Binary(BinaryExpression {
+            operator: BinaryOperator::Equal,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_literal_values() {
+    let values: Vec<LiteralValue> = [
+        "\"say \"\"hi\"\"\"",
+        "42",
+        "1.5e3",
+        "&HFF",
+        "&HFFFF",
+        "#2024-01-31#",
+        "True",
+        "Nothing",
+    ]
+    .iter()
+    .map(
+        |literal| match assigned_value(&format!("<% x = {} %>", literal)) {
+            Expression::Literal(literal) => literal.value,
+            other => panic!("expected a literal, got {:?}", other),
+        },
+    )
+    .collect();
+
+    assert_eq!(
+        values,
+        vec![
+            LiteralValue::String("say \"hi\"".to_string()),
+            LiteralValue::Integer(42),
+            LiteralValue::Float(1500.0),
+            LiteralValue::Integer(255),
+            LiteralValue::Integer(-1),
+            LiteralValue::Date("2024-01-31".to_string()),
+            LiteralValue::Boolean(true),
+            LiteralValue::Nothing,
+        ]
+    );
+}
+
+#[test]
+fn test_if_block_spanning_asp_blocks() {
+    let code = "<% If a Then %>one<% ElseIf b Then %>two<% Else %>three<% End If %>";
+    let program = parser::parse_to_ast(code).expect("If block should parse");
+    assert_eq!(program.language, ScriptLanguage::VBScript);
+    assert_eq!(program.body.len(), 1);
+
+    let Statement::If(statement) = &program.body[0] else {
+        panic!("expected an If statement");
+    };
+    assert!(!statement.single_line);
+    assert_eq!(
+        statement.span.as_str(code),
+        code.trim_end_matches(" %>").trim_start_matches("<% ")
+    );
+
+    let html = |body: &[Statement]| match body {
+        [Statement::Html(html)] => html.text.clone(),
+        other => panic!("expected a single HTML node, got {:?}", other),
+    };
+    assert_eq!(html(&statement.then_branch), "one");
+    assert_eq!(statement.else_if_branches.len(), 1);
+    assert_eq!(html(&statement.else_if_branches[0].body), "two");
+    assert_eq!(html(statement.else_branch.as_deref().unwrap()), "three");
+}
+
+#[test]
+fn test_single_line_if() {
+    let Statement::If(statement) =
+        statements("<% If x Then y = 1 : z = 2 Else Exit Sub %>").remove(0)
+    else {
+        panic!("expected an If statement");
+    };
+    assert!(statement.single_line);
+    assert_eq!(statement.then_branch.len(), 2);
+    assert!(matches!(
+        statement.else_branch.as_deref(),
+        Some([Statement::Exit(ExitStatement {
+            kind: ExitKind::Sub,
+            ..
+        })])
+    ));
+}
+
+#[test]
+fn test_procedures_and_parameters() {
+    let code =
+        "<%\nPrivate Function Sum(ByVal a, ByRef b, values())\n  Sum = a + b\nEnd Function\n%>";
+    let Statement::Procedure(procedure) = statements(code).remove(0) else {
+        panic!("expected a procedure");
+    };
+    assert_eq!(procedure.kind, ProcedureKind::Function);
+    assert_eq!(procedure.name.name, "Sum");
+    assert_eq!(procedure.visibility, Some(Visibility::Private));
+    assert_eq!(procedure.body.len(), 1);
+
+    let parameters: Vec<(&str, Option<ParameterPassing>, bool)> = procedure
+        .parameters
+        .iter()
+        .map(|p| (p.name.name.as_str(), p.passing, p.is_array))
+        .collect();
+    assert_eq!(
+        parameters,
+        vec![
+            ("a", Some(ParameterPassing::ByVal), false),
+            ("b", Some(ParameterPassing::ByRef), false),
+            ("values", None, true),
+        ]
+    );
+}
+
+#[test]
+fn test_loops() {
+    let code =
+        "<%\nFor i = 10 To 1 Step -1\nNext\nDo\n  n = n + 1\nLoop Until n > 5\nWhile x\nWend\n%>";
+    let statements = statements(code);
+
+    let Statement::For(for_statement) = &statements[0] else {
+        panic!("expected a For loop");
+    };
+    assert_eq!(for_statement.variable.name, "i");
+    assert!(for_statement.step.is_some());
+
+    let Statement::DoLoop(do_loop) = &statements[1] else {
+        panic!("expected a Do loop");
+    };
+    assert!(do_loop.pre_condition.is_none());
+    assert_eq!(
+        do_loop.post_condition.as_ref().map(|c| c.kind),
+        Some(LoopConditionKind::Until)
+    );
+    assert_eq!(do_loop.body.len(), 1);
+
+    assert!(matches!(statements[2], Statement::While(_)));
+}
+
+#[test]
+fn test_select_case() {
+    let code = "<%\nSelect Case x\n  Case 1, 2\n    y = 1\n  Case Else\n    y = 2\nEnd Select\n%>";
+    let Statement::Select(select) = statements(code).remove(0) else {
+        panic!("expected a Select Case statement");
+    };
+    assert_eq!(select.cases.len(), 1);
+    assert_eq!(select.cases[0].values.len(), 2);
+    assert_eq!(select.else_branch.map(|body| body.len()), Some(1));
+}
+
+#[test]
+fn test_call_and_set_statements() {
+    let statements = statements(
+        "<%\nSet rs = Server.CreateObject(\"ADODB.Recordset\")\nCall Log(\"a\", , 3)\nResponse.Write x, y\n%>",
+    );
+
+    let Statement::Assignment(assignment) = &statements[0] else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(assignment.kind, AssignmentKind::Set);
+    assert!(matches!(assignment.value, Expression::Call(_)));
+
+    let Statement::Call(call) = &statements[1] else {
+        panic!("expected a call");
+    };
+    assert!(call.explicit);
+    assert_eq!(call.arguments.len(), 3);
+    assert!(call.arguments[1].is_none());
+
+    let Statement::Call(call) = &statements[2] else {
+        panic!("expected a call");
+    };
+    assert!(!call.explicit);
+    assert!(matches!(&call.target, Expression::Member(member) if member.member.name == "Write"));
+    assert_eq!(call.arguments.len(), 2);
+}
+
+#[test]
+fn test_unclosed_block_is_an_error() {
+    let error = parser::parse_to_ast("<% If x Then %>\n<p>never closed</p>").unwrap_err();
+    assert!(error.to_string().contains("End If"), "{}", error);
+    assert!(error.to_string().contains("line 1"), "{}", error);
+}
+
+#[test]
+fn test_mismatched_end_is_an_error() {
+    let error = parser::parse_to_ast("<%\nFor i = 1 To 2\nEnd If\n%>").unwrap_err();
+    assert!(error.to_string().contains("line 3"), "{}", error);
+    assert!(error.to_string().contains("Next"), "{}", error);
+
+    assert!(parser::parse("<% Next %>").is_err());
+    assert!(parser::parse("<% Sub A\nSub B\nEnd Sub\nEnd Sub %>").is_err());
+}
+
+#[test]
+fn test_jscript_code_is_unparsed() {
+    let program =
+        parser::parse_to_ast("<%@ Language=\"JScript\" %>\n<% var x = 1; %><%= x %>").unwrap();
+    assert_eq!(program.language, ScriptLanguage::JScript);
+    assert!(program
+        .body
+        .iter()
+        .any(|statement| matches!(statement, Statement::Unparsed(code) if code.text.trim() == "var x = 1;")));
+    assert!(program.body.iter().any(|statement| matches!(
+        statement,
+        Statement::Output(Output {
+            value: Expression::Unparsed(_),
+            ..
+        })
+    )));
+}
+
+#[test]
+fn test_spans_have_line_and_column() {
+    let code = "<p>é</p>\n<%\nDim total\ntotal = price * 2\n%>";
+    let statements = statements(code);
+
+    let Statement::Dim(dim) = &statements[0] else {
+        panic!("expected a Dim statement");
+    };
+    assert_eq!((dim.span.line, dim.span.column), (3, 1));
+    assert_eq!(dim.variables[0].name.span.as_str(code), "total");
+
+    let Statement::Assignment(assignment) = &statements[1] else {
+        panic!("expected an assignment");
+    };
+    let Expression::Binary(product) = &assignment.value else {
+        panic!("expected a product");
+    };
+    assert_eq!((product.span.line, product.span.column), (4, 9));
+    assert_eq!(product.right.span().as_str(code), "2");
+    assert_eq!(
+        (product.right.span().line, product.right.span().column),
+        (4, 17)
+    );
+}
+
+#[test]
+fn test_ast_serializes_to_json() {
+    let program = parser::parse_to_ast("<% If x > 1 Then y = \"a\" %>").unwrap();
+    let json = serde_json::to_value(&program).unwrap();
+
+    assert_eq!(json["language"], "VBScript");
+    let statement = &json["body"][0];
+    assert_eq!(statement["type"], "If");
+    assert_eq!(statement["condition"]["type"], "Binary");
+    assert_eq!(statement["condition"]["operator"], "Greater");
+    assert_eq!(statement["span"]["line"], 1);
+    assert_eq!(statement["span"]["column"], 4);
+
+    let assignment = &statement["then_branch"][0];
+    assert_eq!(assignment["type"], "Assignment");
+    assert_eq!(assignment["value"]["value"]["type"], "String");
+    assert_eq!(assignment["value"]["value"]["value"], "a");
+}
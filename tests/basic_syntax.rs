@@ -13,7 +13,7 @@ fn test_basic_syntax_parsing() {
     let content = fs::read_to_string(fixture_path).expect("Failed to read test fixture file");
 
     // Parse the content (with verbose=false)
-    let result = parser::parse(&content, false);
+    let result = parser::parse(&content);
 
     // Check if parsing completes without errors
     assert!(
@@ -29,7 +29,7 @@ fn test_response_write_parsing() {
     let asp_code = "<%\nResponse.Write \"Hello, World!\"\n%>";
 
     // Parse the content (with verbose=false)
-    let result = parser::parse(asp_code, false);
+    let result = parser::parse(asp_code);
 
     // Check if parsing completes without errors
     assert!(
@@ -52,7 +52,7 @@ fn test_mixed_html_asp_content() {
 </html>"#;
 
     // Parse the content (with verbose=false)
-    let result = parser::parse(mixed_content, false);
+    let result = parser::parse(mixed_content);
 
     // Check if parsing completes without errors
     assert!(
@@ -72,7 +72,7 @@ fn test_mixed_content_fixture_parsing() {
         fs::read_to_string(fixture_path).expect("Failed to read mixed content fixture file");
 
     // Parse the content (with verbose=false)
-    let result = parser::parse(&content, false);
+    let result = parser::parse(&content);
 
     // Check if parsing completes without errors
     assert!(
@@ -92,7 +92,7 @@ fn test_invalid_syntax_parsing() {
         fs::read_to_string(fixture_path).expect("Failed to read invalid test fixture file");
 
     // Parse the content (with verbose=false)
-    let result = parser::parse(&content, false);
+    let result = parser::parse(&content);
 
     // Check that parsing fails for invalid syntax
     assert!(
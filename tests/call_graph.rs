@@ -0,0 +1,100 @@
+use asp_classic_parser::analysis::{self, CallGraph, CallKind};
+use asp_classic_parser::parser;
+
+fn call_graph(asp_code: &str) -> CallGraph {
+    let program = parser::parse_to_ast(asp_code)
+        .unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", asp_code, e));
+    analysis::call_graph(&program)
+}
+
+/// Returns the caller name (or `None` for page-level code), callee name and kind of each call
+fn edges(graph: &CallGraph) -> Vec<(Option<&str>, &str, CallKind)> {
+    graph
+        .calls
+        .iter()
+        .map(|call| {
+            (
+                call.caller.map(|i| graph.procedures[i].name.as_str()),
+                graph.procedures[call.callee].name.as_str(),
+                call.kind,
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_call_kinds() {
+    let graph = call_graph(
+        "<%\nSub Run()\n  Call Step(1)\n  Step 2\n  Step(3)\n  x = Compute(4) + Compute\n  Set handler = GetRef(\"Step\")\nEnd Sub\nSub Step(n)\nEnd Sub\nFunction Compute(n)\n  Compute = n\nEnd Function\n%>",
+    );
+    assert_eq!(
+        edges(&graph),
+        vec![
+            (Some("Run"), "Step", CallKind::Call),
+            (Some("Run"), "Step", CallKind::Statement),
+            (Some("Run"), "Step", CallKind::Statement),
+            (Some("Run"), "Compute", CallKind::Expression),
+            (Some("Run"), "Compute", CallKind::Expression),
+            (Some("Run"), "Step", CallKind::GetRef),
+        ]
+    );
+    // The assignment of the return value is not a call
+    assert!(!graph.is_called(graph.procedure("Run").unwrap()));
+}
+
+#[test]
+fn test_get_ref_span_is_the_name() {
+    let source = "<%\nSub Handler()\nEnd Sub\nSet f = GetRef(\"Handler\")\n%>";
+    let graph = call_graph(source);
+    assert_eq!(graph.calls.len(), 1);
+    let call = graph.calls[0];
+    assert_eq!(call.caller, None);
+    assert_eq!(call.kind, CallKind::GetRef);
+    assert_eq!(&source[call.span.start..call.span.end], "Handler");
+}
+
+#[test]
+fn test_locals_shadowing_procedures_are_not_calls() {
+    let graph = call_graph(
+        "<%\nFunction Total()\nEnd Function\nSub Report()\n  Dim Total\n  Total = 1\n  Response.Write Total\nEnd Sub\n%>",
+    );
+    assert!(graph.calls.is_empty());
+}
+
+#[test]
+fn test_class_methods() {
+    let graph = call_graph(
+        "<%\nClass Cart\n  Private Sub Recalculate()\n  End Sub\n  Public Sub Add(item)\n    Recalculate\n  End Sub\nEnd Class\nSub Recalculate()\nEnd Sub\n%>",
+    );
+    let names: Vec<(&str, Option<&str>)> = graph
+        .procedures
+        .iter()
+        .map(|p| (p.name.as_str(), p.class.as_deref()))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("Recalculate", Some("Cart")),
+            ("Add", Some("Cart")),
+            ("Recalculate", None),
+        ]
+    );
+    // The method is found before the global sub
+    assert_eq!(graph.callers(0).collect::<Vec<_>>(), [1]);
+    assert!(!graph.is_called(2));
+}
+
+#[test]
+fn test_callers_and_callees_are_deduplicated() {
+    let graph = call_graph(
+        "<%\nSub A()\n  B\n  C\n  B\nEnd Sub\nSub B()\n  A\n  B\nEnd Sub\nSub C()\nEnd Sub\nA\n%>",
+    );
+    let a = graph.procedure("a").unwrap();
+    let b = graph.procedure("b").unwrap();
+    let c = graph.procedure("c").unwrap();
+    assert_eq!(graph.callees(a).collect::<Vec<_>>(), [b, c]);
+    assert_eq!(graph.callers(b).collect::<Vec<_>>(), [a, b]);
+    // Recursion alone does not make a procedure called
+    assert!(graph.is_called(b));
+    assert!(graph.is_called(c));
+}
@@ -1,6 +1,6 @@
 use std::fs;
 use std::io::Write;
-use std::process::Command;
+use std::process::{Command, Stdio};
 use tempfile::tempdir;
 
 // Test the CLI functionality for detecting and processing ASP files
@@ -158,6 +158,232 @@ fn test_cli_encoding_fallback() {
         "Latin-1 file should parse successfully in summary, got: {}",
         stdout
     );
+    assert!(stdout.contains("Encoding: ISO-8859-1"), "got: {}", stdout);
+}
+
+// Test the notice of the files whose encoding is assumed, and --default-encoding
+#[test]
+fn test_cli_default_encoding() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let path = temp_dir.path().join("shift_jis.asp");
+    // "こんにちは世界" in Shift-JIS, with the indentation `fmt` removes
+    let source = b"<%\n    x = \"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x90\xa2\x8a\x45\"\n%>\n";
+    fs::write(&path, source).expect("Failed to write Shift-JIS file");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .arg(&path)
+            .args(["--format=ascii", "--no-color"])
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // Files are read as ISO-8859-1 by default, with a notice
+    let output = run(&["--no-cache"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "got: {}", stderr);
+    assert!(stderr.contains("notice - ASP028"), "got: {}", stderr);
+    assert!(stderr.contains("read as ISO-8859-1"), "got: {}", stderr);
+
+    // The notice is reported on every run, the file not being cached
+    run(&[]);
+    let output = run(&[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("ASP028"), "got: {}", stderr);
+
+    let output = run(&["--no-cache", "--default-encoding", "auto", "--verbose"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stdout.contains("Encoding: Shift_JIS (assumed)"),
+        "got: {}",
+        stdout
+    );
+    assert!(stderr.contains("read as Shift_JIS"), "got: {}", stderr);
+
+    let output = run(&["--no-cache", "--ignore-warnings", "encoding-assumed"]);
+    assert!(output.stderr.is_empty());
+    let output = run(&["--default-encoding", "klingon"]);
+    assert_eq!(output.status.code(), Some(2));
+
+    // Formatted files are written back in the encoding they were read in
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "--default-encoding", "932"])
+        .arg(&path)
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read(&path).expect("Failed to read file"),
+        b"<%\nx = \"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x90\xa2\x8a\x45\"\n%>\n"
+    );
+}
+
+// Test --mmap, which maps the files in memory without changing the results
+#[test]
+fn test_cli_mmap() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    fs::write(temp_dir.path().join("valid.asp"), "<% x = \"café\" %>")
+        .expect("Failed to write file");
+    fs::write(
+        temp_dir.path().join("invalid.asp"),
+        "\u{feff}<p>\n<% If x Then %>",
+    )
+    .expect("Failed to write file");
+    fs::write(temp_dir.path().join("latin1.asp"), b"<% Dim %>\xE9").expect("Failed to write file");
+    fs::write(temp_dir.path().join("empty.asp"), "").expect("Failed to write file");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .arg(temp_dir.path())
+            .args([
+                "--no-cache",
+                "--format=ascii",
+                "--no-color",
+                "--replace-exclude",
+            ])
+            .args(args)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    for threads in ["1", "4"] {
+        let expected = run(&["--threads", threads]);
+        let output = run(&["--threads", threads, "--mmap"]);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(1), "got: {}", stderr);
+        assert!(stderr.contains("invalid.asp"), "got: {}", stderr);
+        assert!(stderr.contains("latin1.asp"), "got: {}", stderr);
+        assert_eq!(output.status, expected.status);
+        // Files parsed in parallel are reported in any order
+        let lines = |stderr: &str| {
+            let mut lines: Vec<String> = stderr.lines().map(str::to_string).collect();
+            lines.sort();
+            lines
+        };
+        assert_eq!(
+            lines(&stderr),
+            lines(&String::from_utf8_lossy(&expected.stderr))
+        );
+    }
+}
+
+// Test large pages, checked piece by piece after being decoded
+#[test]
+fn test_cli_large_pages() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let row = "<tr><td><%= rs(\"name\") %></td><td>Réf. 0123456789</td></tr>\n";
+    let rows = 1100 * 1024 / row.len();
+    let mut page = String::from("<% If report Then %>\n<table>\n");
+    page.push_str(&row.repeat(rows));
+    page.push_str("</table>\n<% Dim %>\n");
+    // In UTF-16, so that the page is only valid once decoded
+    let mut bytes = vec![0xFF, 0xFE];
+    bytes.extend(page.encode_utf16().flat_map(u16::to_le_bytes));
+    let path = temp_dir.path().join("report.asp");
+    fs::write(&path, bytes).expect("Failed to write file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(&path)
+        .args(["--no-cache", "--format=ascii", "--no-color"])
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "got: {}", stderr);
+    let errors: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.contains("error"))
+        .collect();
+    assert_eq!(errors.len(), 2, "got: {}", stderr);
+    assert!(
+        errors[0].contains(&format!("report.asp:{}:8: error - ASP001", rows + 4)),
+        "got: {}",
+        stderr
+    );
+    assert!(
+        errors[1].contains("report.asp:1:4: error - ASP002: 'If' block is not closed"),
+        "got: {}",
+        stderr
+    );
+}
+
+// Test UTF-16 files, which are decoded according to their byte order mark
+#[test]
+fn test_cli_utf16_files() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let utf16 = |path: &str, page: &str, little_endian: bool| {
+        let path = temp_dir.path().join(path);
+        let mut bytes = if little_endian {
+            vec![0xFF, 0xFE]
+        } else {
+            vec![0xFE, 0xFF]
+        };
+        for unit in page.encode_utf16() {
+            if little_endian {
+                bytes.extend(unit.to_le_bytes());
+            } else {
+                bytes.extend(unit.to_be_bytes());
+            }
+        }
+        fs::write(&path, bytes).expect("Failed to write UTF-16 file");
+        path
+    };
+    let valid = utf16("valid.asp", "<%\nResponse.Write \"Grüße\"\n%>", true);
+    let invalid = utf16("invalid.asp", "<%\nIf x Then\n%>", false);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(&valid)
+        .arg("--verbose")
+        .arg("--format=ascii")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "got: {}", stdout);
+    assert!(stdout.contains("Encoding: UTF-16LE"), "got: {}", stdout);
+
+    // Errors are reported at their location in the decoded page
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(&invalid)
+        .arg("--verbose")
+        .arg("--format=ascii")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "got: {}", stdout);
+    assert!(stdout.contains("Encoding: UTF-16BE"), "got: {}", stdout);
+}
+
+// Test pages decoded in the code page of their `CodePage` directive
+#[test]
+fn test_cli_code_page_files() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let path = temp_dir.path().join("shift_jis.asp");
+    // "日本" in Shift-JIS, then a `Next` without loop
+    fs::write(
+        &path,
+        b"<%@ CodePage=932 %>\n<% x = \"\x93\xFA\x96\x7B\" : Next %>",
+    )
+    .expect("Failed to write Shift-JIS file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(&path)
+        .arg("--verbose")
+        .arg("--format=ascii")
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Encoding: Shift_JIS (CodePage 932)"),
+        "got: {}",
+        stdout
+    );
+    // Columns count the characters of the page, not its bytes
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("{}:2:15:", path.display())),
+        "got: {}",
+        stderr
+    );
 }
 
 // Test the stdin input method with hyphen (-)
@@ -906,8 +1132,8 @@ fn test_cli_cache_functionality() {
         .env_clear()
         .env("PATH", std::env::var("PATH").unwrap_or_default())
         .env(
-            &cache_dir_var.split('=').next().unwrap(),
-            &cache_dir_var.split('=').nth(1).unwrap(),
+            cache_dir_var.split('=').next().unwrap(),
+            cache_dir_var.split('=').nth(1).unwrap(),
         )
         .output()
         .expect("Failed to execute CLI");
@@ -928,7 +1154,7 @@ fn test_cli_cache_functionality() {
     );
 
     // Verify cache file was created
-    let cache_file = test_cache_dir.join("parse_cache.json");
+    let cache_file = test_cache_dir.join("parse_cache.bin");
     assert!(
         cache_file.exists(),
         "Cache file should exist after first run"
@@ -942,8 +1168,8 @@ fn test_cli_cache_functionality() {
         .env_clear()
         .env("PATH", std::env::var("PATH").unwrap_or_default())
         .env(
-            &cache_dir_var.split('=').next().unwrap(),
-            &cache_dir_var.split('=').nth(1).unwrap(),
+            cache_dir_var.split('=').next().unwrap(),
+            cache_dir_var.split('=').nth(1).unwrap(),
         )
         .output()
         .expect("Failed to execute CLI for second run");
@@ -972,8 +1198,8 @@ fn test_cli_cache_functionality() {
         .env_clear()
         .env("PATH", std::env::var("PATH").unwrap_or_default())
         .env(
-            &cache_dir_var.split('=').next().unwrap(),
-            &cache_dir_var.split('=').nth(1).unwrap(),
+            cache_dir_var.split('=').next().unwrap(),
+            cache_dir_var.split('=').nth(1).unwrap(),
         )
         .output()
         .expect("Failed to execute CLI with --no-cache");
@@ -1007,8 +1233,8 @@ fn test_cli_cache_functionality() {
         .env_clear()
         .env("PATH", std::env::var("PATH").unwrap_or_default())
         .env(
-            &cache_dir_var.split('=').next().unwrap(),
-            &cache_dir_var.split('=').nth(1).unwrap(),
+            cache_dir_var.split('=').next().unwrap(),
+            cache_dir_var.split('=').nth(1).unwrap(),
         )
         .output()
         .expect("Failed to execute CLI after file modification");
@@ -1023,6 +1249,60 @@ fn test_cli_cache_functionality() {
     );
 }
 
+/// Test that cached results report the same problems as parsing the files
+#[test]
+fn test_cli_cached_diagnostics() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("errors.asp"),
+        "<%\nIf x Then\nNext\nz = (1 +\n%>",
+    )
+    .expect("Failed to write errors.asp");
+    fs::write(temp_path.join("empty.asp"), "  \n").expect("Failed to write empty.asp");
+    fs::write(temp_path.join("static.asp"), "<p>Hello</p>").expect("Failed to write static.asp");
+    let run = |threads: &str| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(["errors.asp", "empty.asp", "static.asp", "--format=ascii"])
+            .arg(format!("--threads={}", threads))
+            .current_dir(temp_path)
+            .env("ASP_PARSER_CACHE_DIR", temp_path.join("cache"))
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    let parsed = run("1");
+    let parsed_stderr = String::from_utf8_lossy(&parsed.stderr);
+    assert_eq!(parsed.status.code(), Some(1), "got: {}", parsed_stderr);
+    assert!(
+        parsed_stderr.matches("errors.asp:").count() >= 2,
+        "got: {}",
+        parsed_stderr
+    );
+    assert!(
+        parsed_stderr.contains("File is empty or contains only whitespace - skipping"),
+        "got: {}",
+        parsed_stderr
+    );
+    assert!(
+        parsed_stderr.contains("No ASP tags found in file - skipping"),
+        "got: {}",
+        parsed_stderr
+    );
+
+    // Sequential and parallel runs replay the problems from the cache
+    for threads in ["1", "4"] {
+        let cached = run(threads);
+        let mut expected: Vec<&str> = parsed_stderr.lines().collect();
+        let stderr = String::from_utf8_lossy(&cached.stderr);
+        let mut lines: Vec<&str> = stderr.lines().collect();
+        expected.sort();
+        lines.sort();
+        assert_eq!(lines, expected);
+        assert_eq!(cached.status.code(), Some(1));
+    }
+}
+
 /// Test that parallel processing works as expected
 #[test]
 fn test_cli_parallel_processing() {
@@ -1106,7 +1386,7 @@ fn test_cache_preserves_errors() {
         "First run should detect a parse error"
     );
     assert!(
-        stderr1.contains("incomplete expression"),
+        stderr1.contains("end of statement"),
         "First run should include the error details"
     );
 
@@ -1124,12 +1404,12 @@ fn test_cache_preserves_errors() {
         "Second run should still show the error from cache"
     );
     assert!(
-        stderr2.contains("incomplete expression"),
+        stderr2.contains("end of statement"),
         "Second run should include the error details from cache"
     );
 
     // Check that we're actually using the cache on the second run
-    let cache_file = cache_dir.join("parse_cache.json");
+    let cache_file = cache_dir.join("parse_cache.bin");
     assert!(
         cache_file.exists(),
         "Cache file should exist after first run"
@@ -1146,3 +1426,1688 @@ fn test_cache_preserves_errors() {
         }
     }
 }
+
+/// Test that every syntax error of a file is reported in a single run
+#[test]
+fn test_reports_all_syntax_errors() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_file_path = temp_dir.path().join("two_errors.asp");
+    std::fs::write(&test_file_path, "<%\nDim\nx = 1\nErase\n%>\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(test_file_path.to_str().unwrap())
+        .arg("--format=ascii")
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute parser");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let path = test_file_path.display();
+    assert!(
+        stderr.contains(&format!("{}:2:4", path)),
+        "Should report the error on line 2: {}",
+        stderr
+    );
+    assert!(
+        stderr.contains(&format!("{}:4:6", path)),
+        "Should report the error on line 4: {}",
+        stderr
+    );
+    assert_eq!(output.status.code(), Some(1), "Should exit with an error");
+}
+
+#[test]
+fn test_errors_show_their_code() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_file_path = temp_dir.path().join("unclosed.asp");
+    std::fs::write(&test_file_path, "<%\nIf x Then\n%>\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(test_file_path.to_str().unwrap())
+        .arg("--format=json")
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute parser");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error: serde_json::Value =
+        serde_json::from_str(stderr.lines().next().unwrap()).expect("Error should be JSON");
+    assert_eq!(error["code"], "ASP002");
+    assert_eq!(error["severity"], "error");
+    assert_eq!(error["line"], 2);
+    assert_eq!(output.status.code(), Some(1), "Should exit with an error");
+}
+
+#[test]
+fn test_ignore_warnings_accepts_codes() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let html_file_path = temp_dir.path().join("no_asp_tags.html");
+    std::fs::write(&html_file_path, "<html><body></body></html>").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(html_file_path.to_str().unwrap())
+        .arg("--format=ascii")
+        .arg("--no-cache")
+        .arg("--ignore-warnings=ASP005")
+        .output()
+        .expect("Failed to execute parser");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stderr.contains("No ASP tags found"),
+        "Should not show the warning ignored by its code: {}",
+        stderr
+    );
+    assert!(stdout.contains("1 skipped"), "{}", stdout);
+}
+
+#[test]
+fn test_ast_json_format_prints_the_syntax_tree() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let test_file_path = temp_dir.path().join("page.asp");
+    std::fs::write(&test_file_path, "<h1><%= title %></h1>\n<% Dim x %>\n").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(test_file_path.to_str().unwrap())
+        .arg("--format=ast-json")
+        .output()
+        .expect("Failed to execute parser");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let tree: serde_json::Value =
+        serde_json::from_str(stdout.lines().next().unwrap()).expect("Output should be JSON");
+    assert_eq!(tree["file"], test_file_path.to_str().unwrap());
+    let types: Vec<&str> = tree["ast"]["body"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|statement| statement["type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["Html", "Output", "Html", "Dim", "Html"]);
+}
+
+// Test the depth and file size limits of the search of files in directories
+#[test]
+fn test_cli_scan_limits() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::create_dir_all(temp_path.join("sub")).expect("Failed to create directory");
+    fs::write(temp_path.join("index.asp"), "<% x = 1 %>").expect("Failed to write file");
+    fs::write(temp_path.join("sub/page.asp"), "<% x = 2 %>").expect("Failed to write file");
+    let generated = format!("<%\n{}%>", "x = 1\n".repeat(400));
+    fs::write(temp_path.join("generated.asp"), generated).expect("Failed to write file");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .arg(temp_path)
+            .args(["--no-cache", "--format=ascii", "--no-color", "--verbose"])
+            .args(args)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    let output = run(&["--max-depth=1", "--max-file-size=1K"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "got: {}", stderr);
+    assert!(stdout.contains("Found 1 files to parse"), "got: {}", stdout);
+    assert!(stderr.contains("generated.asp"), "got: {}", stderr);
+    assert!(stderr.contains("notice - ASP029"), "got: {}", stderr);
+    assert!(
+        stderr.contains("its size of 2405 bytes exceeds the maximum file size of 1024 bytes"),
+        "got: {}",
+        stderr
+    );
+
+    // The limit can be lifted, and the notice ignored
+    let output = run(&["--max-file-size=0"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 3 files to parse"), "got: {}", stdout);
+    let output = run(&["--max-file-size=1K", "--ignore-warnings=file-too-large"]);
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("ASP029"));
+
+    // Subcommands taking directories have the same options
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["stats", "--max-file-size=1K", "--format=json"])
+        .arg(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "got: {}", stderr);
+    assert!(stderr.starts_with("Notice: "), "got: {}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("generated.asp"));
+}
+
+// Test the lint subcommand
+#[test]
+fn test_cli_lint() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    fs::write(
+        temp_path.join("lib.asp"),
+        "<%\nFunction Query(sql)\nEnd Function\n%>",
+    )
+    .expect("Failed to write lib.asp");
+    let clean_path = temp_path.join("clean.asp");
+    fs::write(
+        &clean_path,
+        "<!--#include file=\"lib.asp\" -->\n<%\nOption Explicit\nDim rs\nrs = Query(\"SELECT 1\")\nResponse.Write rs\n%>",
+    )
+    .expect("Failed to write clean.asp");
+    let page_path = temp_path.join("page.asp");
+    fs::write(
+        &page_path,
+        "<%\nOption Explicit\nDim total\ntotal = count + 1\n%>",
+    )
+    .expect("Failed to write page.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("lint")
+        .arg(&clean_path)
+        .arg(&page_path)
+        .arg("--format=json")
+        .output()
+        .expect("Failed to execute CLI");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("\"code\":\"ASP009\"") && stderr.contains("'count' is not declared"),
+        "Should report the undeclared name, got: {}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("clean.asp"),
+        "Names declared in included files should be known, got: {}",
+        stderr
+    );
+    assert!(
+        stdout.contains("\"failed\": 1"),
+        "Should report one failed file, got: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_cli_lint_max_warnings() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("page.asp"),
+        "<%\nSub Greet(name)\n  Dim unused\n  Response.Write \"Hello\"\nEnd Sub\nGreet \"you\"\n%>",
+    )
+    .expect("Failed to write page.asp");
+
+    let lint = |max_warnings: &str| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(["lint", "page.asp", "--max-warnings", max_warnings])
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    let output = lint("2");
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = lint("1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(5), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("Too many warnings: 2 found, 1 allowed by --max-warnings"),
+        "got: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_exit_codes() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("invalid.asp"), "<%\nIf x Then\n%>")
+        .expect("Failed to write invalid.asp");
+    fs::write(temp_path.join("static.asp"), "<p>No code</p>").expect("Failed to write static.asp");
+    fs::write(
+        temp_path.join("unused.asp"),
+        "<%\nSub Greet()\n  Dim unused\nEnd Sub\nGreet\n%>",
+    )
+    .expect("Failed to write unused.asp");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .arg("--no-cache")
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+            .status
+            .code()
+    };
+    assert_eq!(run(&["static.asp"]), Some(0));
+    assert_eq!(run(&["static.asp", "--error-on=skipped"]), Some(5));
+    assert_eq!(run(&["static.asp", "--error-on=warnings"]), Some(5));
+    assert_eq!(
+        run(&[
+            "static.asp",
+            "--error-on=warnings",
+            "--ignore-warnings=no-asp-tags"
+        ]),
+        Some(0)
+    );
+    assert_eq!(
+        run(&["invalid.asp", "static.asp", "--error-on=skipped"]),
+        Some(1)
+    );
+    assert_eq!(run(&["invalid.asp", "--exit-zero"]), Some(0));
+
+    let lint = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .arg("lint")
+            .args(args)
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+            .status
+            .code()
+    };
+    assert_eq!(lint(&["invalid.asp"]), Some(1));
+    assert_eq!(lint(&["unused.asp"]), Some(0));
+    assert_eq!(lint(&["unused.asp", "--error-on=warnings"]), Some(5));
+    assert_eq!(
+        lint(&["unused.asp", "--error-on=warnings", "--exit-zero"]),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_cli_fail_fast() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("a.asp"), "<%\nIf x Then\n%>").expect("Failed to write a.asp");
+    fs::write(temp_path.join("b.asp"), "<% x = 1 %>").expect("Failed to write b.asp");
+    fs::write(temp_path.join("c.asp"), "<% y = 2 %>").expect("Failed to write c.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args([
+            "a.asp",
+            "b.asp",
+            "c.asp",
+            "--fail-fast",
+            "--threads=1",
+            "--no-cache",
+        ])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr
+            .contains("Stopped after the first failure (--fail-fast): 2 of 3 file(s) not checked"),
+        "got: {}",
+        stderr
+    );
+    assert!(!stdout.contains("b.asp"), "got: {}", stdout);
+
+    fs::write(
+        temp_path.join("a.asp"),
+        "<%\nOption Explicit\nResponse.Write count\n%>",
+    )
+    .expect("Failed to write a.asp");
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["lint", "a.asp", "b.asp", "--fail-fast"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(
+        stderr
+            .contains("Stopped after the first failure (--fail-fast): 1 of 2 file(s) not checked"),
+        "got: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_stdin_filename() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let site_path = temp_path.join("site");
+    fs::create_dir(&site_path).expect("Failed to create site directory");
+    fs::write(site_path.join(".asp-parser.toml"), "strict = true\n")
+        .expect("Failed to write the configuration");
+    fs::write(
+        site_path.join("lib.asp"),
+        "<%\nFunction Query(sql)\nEnd Function\n%>",
+    )
+    .expect("Failed to write lib.asp");
+
+    let run = |args: &[&str], input: &str| {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(temp_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to spawn CLI process");
+        child
+            .stdin
+            .take()
+            .expect("Failed to open stdin")
+            .write_all(input.as_bytes())
+            .expect("Failed to write to stdin");
+        child.wait_with_output().expect("Failed to wait for CLI")
+    };
+
+    // Problems carry the path, and the configuration of its directory applies
+    let output = run(
+        &["--stdin", "--stdin-filename", "site/page.asp"],
+        "<%\nIf x Then\n%>",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("site/page.asp") && !stderr.contains("<stdin>"),
+        "got: {}",
+        stderr
+    );
+    let output = run(&["--stdin"], "<p>No code</p>");
+    assert_eq!(output.status.code(), Some(0));
+    let output = run(
+        &["--stdin", "--stdin-filename", "site/page.asp"],
+        "<p>No code</p>",
+    );
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "The strict mode of site/.asp-parser.toml should apply"
+    );
+
+    // Includes are resolved from the directory of the path
+    let output = run(
+        &["lint", "--stdin", "--stdin-filename", "site/page.asp"],
+        "<!--#include file=\"lib.asp\" -->\n<%\nOption Explicit\nResponse.Write Query(\"SELECT 1\") & Missing\n%>",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("site/page.asp")
+            && stderr.contains("'Missing' is not declared")
+            && !stderr.contains("'Query'"),
+        "got: {}",
+        stderr
+    );
+    let output = run(
+        &["lint", "--stdin"],
+        "<%\nOption Explicit\nResponse.Write Missing\n%>",
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(stderr.contains("<stdin>"), "got: {}", stderr);
+}
+
+#[test]
+fn test_cli_lint_rules_config() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+
+    let page_path = temp_path.join("page.asp");
+    fs::write(
+        &page_path,
+        "<%\nOption Explicit\nDim total\ntotal = count + 1\n%>",
+    )
+    .expect("Failed to write page.asp");
+    let config_path = temp_path.join("asp-parser.toml");
+    fs::write(
+        &config_path,
+        "[rules]\nundeclared-name = \"warning\"\nunused-variable = \"off\"\n",
+    )
+    .expect("Failed to write asp-parser.toml");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("lint")
+        .arg(&page_path)
+        .arg("--format=json")
+        .arg("--config")
+        .arg(&config_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("\"code\":\"ASP009\"") && stderr.contains("\"severity\":\"warning\""),
+        "Should report the undeclared name as a warning, got: {}",
+        stderr
+    );
+    assert!(
+        !stderr.contains("ASP010"),
+        "Disabled rules should not run, got: {}",
+        stderr
+    );
+
+    fs::write(&config_path, "[rules]\nno-such-rule = \"off\"\n")
+        .expect("Failed to write asp-parser.toml");
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("lint")
+        .arg(&page_path)
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(2), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("unknown rule 'no-such-rule'"),
+        "got: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_lint_baseline() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let page_path = temp_path.join("page.asp");
+    fs::write(&page_path, "<%\nOption Explicit\ntotal = 1\n%>").expect("Failed to write page.asp");
+    let baseline_path = temp_path.join("baseline.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("lint")
+        .arg(&page_path)
+        .arg("--write-baseline")
+        .arg(&baseline_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "stdout: {}", stdout);
+    assert!(
+        stdout.contains("Baseline of 1 problem(s)"),
+        "got: {}",
+        stdout
+    );
+
+    fs::write(&page_path, "<%\nOption Explicit\ntotal = 1\ncount = 2\n%>")
+        .expect("Failed to write page.asp");
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("lint")
+        .arg("page.asp")
+        .arg("--baseline=baseline.json")
+        .arg("--format=json")
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(
+        stderr.contains("'count' is not declared") && !stderr.contains("'total'"),
+        "Only new problems should be reported, got: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_json_report() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("valid.asp"),
+        "<% Response.Write \"Hello\" %>",
+    )
+    .expect("Failed to write valid.asp");
+    fs::write(temp_path.join("invalid.asp"), "<%\nIf x Then\n%>")
+        .expect("Failed to write invalid.asp");
+    fs::write(temp_path.join("static.asp"), "<p>No code</p>").expect("Failed to write static.asp");
+    let report_path = temp_path.join("report.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(temp_path.to_str().unwrap())
+        .arg("--format=json")
+        .arg("--output")
+        .arg(&report_path)
+        .arg("--no-cache")
+        .arg("--threads=2")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stdout.is_empty() && !stderr.contains("ASP002"),
+        "Results should only be written to the report, got: {}{}",
+        stdout,
+        stderr
+    );
+
+    let report: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&report_path).expect("Report should exist"))
+            .expect("Report should be a JSON document");
+    assert_eq!(report["schema_version"], 1);
+    let files = report["files"]
+        .as_array()
+        .expect("files should be an array");
+    let statuses: Vec<(String, &str)> = files
+        .iter()
+        .map(|file| {
+            let path = std::path::Path::new(file["file"].as_str().unwrap());
+            (
+                path.file_name().unwrap().to_string_lossy().to_string(),
+                file["status"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            ("invalid.asp".to_string(), "failed"),
+            ("static.asp".to_string(), "skipped"),
+            ("valid.asp".to_string(), "success")
+        ]
+    );
+    assert_eq!(files[0]["diagnostics"][0]["code"], "ASP002");
+    assert!(files[2]["duration_ms"].is_number());
+    assert_eq!(files[2]["diagnostics"], serde_json::json!([]));
+    assert_eq!(report["summary"]["failed"], 1);
+    assert_eq!(report["summary"]["total"], 3);
+}
+
+#[test]
+fn test_cli_output_files() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("valid.asp"),
+        "<% Response.Write \"Hello\" %>",
+    )
+    .expect("Failed to write valid.asp");
+    fs::write(temp_path.join("invalid.asp"), "<%\nIf x Then\n%>")
+        .expect("Failed to write invalid.asp");
+    let output_path = temp_path.join("results.txt");
+    let error_path = temp_path.join("errors.txt");
+
+    // Results and problems in the same file, the summary as progress on stderr
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(temp_path.join("valid.asp"))
+        .arg(temp_path.join("invalid.asp"))
+        .arg("--format=ci")
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(output.stdout.is_empty());
+    assert!(
+        stderr.contains("Parsing complete: 1 succeeded, 1 failed"),
+        "got: {}",
+        stderr
+    );
+    let results = fs::read_to_string(&output_path).expect("Output file should exist");
+    assert!(results.contains("::notice file="), "got: {}", results);
+    assert!(results.contains("::error file="), "got: {}", results);
+    assert!(results.contains("files failed"), "got: {}", results);
+
+    // Problems apart
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(temp_path.join("valid.asp"))
+        .arg(temp_path.join("invalid.asp"))
+        .arg("--format=ci")
+        .arg("--output")
+        .arg(&output_path)
+        .arg("--error-output")
+        .arg(&error_path)
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(1));
+    let results = fs::read_to_string(&output_path).expect("Output file should exist");
+    let errors = fs::read_to_string(&error_path).expect("Error output file should exist");
+    assert!(results.contains("::notice file=") && !results.contains("::error"));
+    assert!(errors.contains("::error file=") && !errors.contains("::notice"));
+}
+
+#[test]
+fn test_cli_ci_annotations_hold_on_one_line() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let file_path = temp_dir.path().join("call.asp");
+    fs::write(&file_path, "<html>\n<% Call %>\n<% If x Then %>").expect("Failed to write call.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(&file_path)
+        .arg("--format=ci")
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute CLI");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("::error"))
+        .collect();
+    assert!(
+        errors.iter().any(|line| line
+            .ends_with("line=2,col=9,title=ASP Parse ERROR::ASP001: expected expression")),
+        "got: {}",
+        stderr
+    );
+    assert!(
+        stderr.lines().all(|line| line.starts_with("::")),
+        "got: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_cli_jsonl_output() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("valid.asp"),
+        "<% Response.Write \"Hello\" %>",
+    )
+    .expect("Failed to write valid.asp");
+    fs::write(temp_path.join("invalid.asp"), "<%\nIf x Then\n%>")
+        .expect("Failed to write invalid.asp");
+    fs::write(temp_path.join("static.asp"), "<p>Hello</p>").expect("Failed to write static.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg(temp_path.to_str().unwrap())
+        .arg("--format=jsonl")
+        .arg("--no-cache")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "stderr: {}", stderr);
+    assert!(
+        stderr.is_empty(),
+        "Everything should go to stdout, got: {}",
+        stderr
+    );
+
+    let objects: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("Each line should be a JSON object"))
+        .collect();
+    let diagnostic = objects
+        .iter()
+        .find(|object| object["type"] == "diagnostic")
+        .expect("Should report the syntax error");
+    assert!(
+        diagnostic["file"]
+            .as_str()
+            .unwrap()
+            .ends_with("invalid.asp")
+    );
+    assert_eq!(diagnostic["code"], "ASP002");
+    // Skipped files keep the code of the reason
+    let skipped = objects
+        .iter()
+        .find(|object| object["type"] == "diagnostic" && object["severity"] == "warning")
+        .expect("Should report the skipped file");
+    assert!(skipped["file"].as_str().unwrap().ends_with("static.asp"));
+    assert_eq!(skipped["code"], "ASP005");
+    let mut statuses: Vec<&str> = objects
+        .iter()
+        .filter(|object| object["type"] == "file")
+        .map(|object| object["status"].as_str().unwrap())
+        .collect();
+    statuses.sort();
+    assert_eq!(statuses, ["failed", "skipped", "success"]);
+    let summary = objects.last().unwrap();
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["total"], 3);
+}
+
+/// Test that closing the standard output early, as `head` does, stops the
+/// command without a panic
+#[test]
+fn test_cli_closed_stdout() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    // More results than a pipe holds
+    for index in 0..1000 {
+        fs::write(
+            temp_path.join(format!("page{}.asp", index)),
+            "<%\nIf x Then\n%>",
+        )
+        .expect("Failed to write page");
+    }
+
+    for args in [
+        vec![temp_path.to_str().unwrap(), "--format=jsonl", "--no-cache"],
+        vec!["completions", "bash"],
+    ] {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to execute CLI");
+        drop(child.stdout.take());
+        let output = child.wait_with_output().expect("Failed to wait for CLI");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(!stderr.contains("panicked"), "{:?}: {}", args, stderr);
+        assert_eq!(output.status.code(), Some(0), "{:?}: {}", args, stderr);
+    }
+}
+
+#[test]
+fn test_cli_lint_codeclimate() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let page_path = temp_path.join("page.asp");
+    let lint = |source: &str| -> Vec<serde_json::Value> {
+        fs::write(&page_path, source).expect("Failed to write page.asp");
+        let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .arg("lint")
+            .arg("page.asp")
+            .arg("--format=codeclimate")
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert_eq!(output.status.code(), Some(3), "stdout: {}", stdout);
+        let issues: Vec<serde_json::Value> =
+            serde_json::from_str(&stdout).expect("Output should be a JSON array");
+        issues
+            .into_iter()
+            .filter(|issue| issue["check_name"] == "undeclared-name")
+            .collect()
+    };
+
+    let issues = lint("<%\nOption Explicit\ntotal = 1\n%>");
+    assert_eq!(issues.len(), 1, "got: {:?}", issues);
+    assert_eq!(issues[0]["location"]["path"], "page.asp");
+    assert_eq!(issues[0]["location"]["lines"]["begin"], 3);
+    assert_eq!(issues[0]["severity"], "major");
+
+    // Moving a problem keeps its fingerprint; a new one gets another
+    let moved = lint("<%\nOption Explicit\nDim count\ncount = 0\ntotal = 1\ntotal = 2\n%>");
+    assert_eq!(moved.len(), 2, "got: {:?}", moved);
+    assert_eq!(moved[0]["location"]["lines"]["begin"], 5);
+    assert_eq!(moved[0]["fingerprint"], issues[0]["fingerprint"]);
+    assert_ne!(moved[1]["fingerprint"], issues[0]["fingerprint"]);
+}
+
+#[test]
+fn test_cli_explain() {
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["explain", "missing-set"])
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout.starts_with("ASP014 missing-set (error)"),
+        "got: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("Set cart = New ShoppingCart"),
+        "got: {}",
+        stdout
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("explain")
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.starts_with("ASP020 cross-site-scripting"))
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["explain", "ASP999"])
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_completions() {
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["completions", "bash"])
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout
+            .lines()
+            .any(|line| line.trim_start().starts_with("complete -F")
+                && line.ends_with(" asp-classic-parser")),
+        "got: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("lint") && stdout.contains("--fail-fast"),
+        "Subcommands and options should be completed, got: {}",
+        stdout
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["completions", "tcsh"])
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_ast() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("page.asp"), "<%\nx = 1\n%>").expect("Failed to write page.asp");
+    fs::write(temp_path.join("broken.asp"), "<% x = 1").expect("Failed to write broken.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["ast", "page.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout.starts_with("file@0..11 [1:1]\n"), "got: {}", stdout);
+    assert!(
+        stdout.contains("\n        assignment@3..8 [2:1]\n"),
+        "got: {}",
+        stdout
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["ast", "page.asp", "--format", "json"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let tree: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    assert_eq!(tree["root"]["kind"], "file");
+    let open_tag = &tree["root"]["children"][0]["children"][0];
+    assert_eq!(open_tag["kind"], "asp_open_tag");
+    assert_eq!(open_tag["span"]["end"], 2);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["ast", "broken.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("broken.asp: Parse error"));
+}
+
+#[test]
+fn test_cli_symbols() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("cart.asp"),
+        "<%\nDim total\nClass Cart\n  Private items\n  Public Property Get Count()\n  End Property\nEnd Class\nFunction Add(a, b)\n  Dim sum\nEnd Function\n%>",
+    )
+    .expect("Failed to write cart.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["symbols", "cart.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "cart.asp:2:5: Dim total",
+            "cart.asp:3:7: Class Cart",
+            "cart.asp:4:11: Dim items (in Cart)",
+            "cart.asp:5:23: Property Get Count() (in Cart)",
+            "cart.asp:8:10: Function Add(a, b)",
+        ]
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["symbols", "--format=json", "cart.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let symbols: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be JSON");
+    let symbols = symbols.as_array().unwrap();
+    assert_eq!(symbols.len(), 5);
+    assert_eq!(symbols[3]["kind"], "PropertyGet");
+    assert_eq!(symbols[3]["class"], "Cart");
+    assert_eq!(symbols[3]["file"], "cart.asp");
+    assert_eq!(symbols[4]["parameters"], serde_json::json!(["a", "b"]));
+    assert_eq!(symbols[4]["line"], 8);
+    assert_eq!(symbols[0]["class"], serde_json::Value::Null);
+}
+
+#[test]
+fn test_cli_duplicates() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let code = "<%\nFor i = 1 To 10\n    Response.Write \"<td>\" & Server.HTMLEncode(items(i)) & \"</td>\"\nNext\n%>";
+    fs::write(temp_path.join("a.asp"), code).expect("Failed to write a.asp");
+    fs::write(temp_path.join("b.asp"), format!("<h1>B</h1>\n{}", code))
+        .expect("Failed to write b.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["duplicates", "--min-tokens=10", "a.asp", "b.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "stdout: {}", stdout);
+    assert!(stdout.contains("found 2 times:"), "got: {}", stdout);
+    assert!(
+        stdout.contains("  a.asp:2-4\n  b.asp:3-5\n"),
+        "got: {}",
+        stdout
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["duplicates", "--format=json", "a.asp", "b.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let groups: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output is not JSON");
+    assert_eq!(groups, serde_json::json!([]));
+}
+
+#[test]
+fn test_cli_fmt() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("a.asp"), "<%\nif x then\ny=1\nend if\n%>")
+        .expect("Failed to write a.asp");
+    fs::write(temp_path.join("b.asp"), "<% Dim x %>").expect("Failed to write b.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "."])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0), "stdout: {}", stdout);
+    assert!(stdout.contains("a.asp"), "got: {}", stdout);
+    assert!(!stdout.contains("b.asp"), "got: {}", stdout);
+    assert!(
+        stdout.contains("1 of 2 file(s) formatted"),
+        "got: {}",
+        stdout
+    );
+    assert_eq!(
+        fs::read_to_string(temp_path.join("a.asp")).unwrap(),
+        "<%\nIf x Then\n    y = 1\nEnd If\n%>"
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .arg("fmt")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to execute CLI");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"<% response.write  x %>")
+        .unwrap();
+    let output = child.wait_with_output().expect("Failed to wait for CLI");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "<% response.write x %>"
+    );
+}
+
+#[test]
+fn test_cli_fmt_check() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let page = "<html>\n<%\nif x then\ny = 1\nend if\n%>\n</html>\n";
+    fs::write(temp_path.join("a.asp"), page).expect("Failed to write a.asp");
+    fs::write(temp_path.join("b.asp"), "<% Dim x %>").expect("Failed to write b.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "--check", "--diff", "a.asp", "b.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "stdout: {}", stdout);
+    assert!(stdout.contains("Would format a.asp\n"), "got: {}", stdout);
+    assert!(!stdout.contains("b.asp"), "got: {}", stdout);
+    assert!(
+        stdout.contains("--- a.asp\n+++ a.asp\n@@ -1,7 +1,7 @@\n <html>\n <%\n-if x then\n-y = 1\n-end if\n+If x Then\n+    y = 1\n+End If\n %>\n"),
+        "got: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains("1 of 2 file(s) would be formatted"),
+        "got: {}",
+        stdout
+    );
+    // The files are not written
+    assert_eq!(fs::read_to_string(temp_path.join("a.asp")).unwrap(), page);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "--check", "b.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn test_cli_fmt_config() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("asp-parser.toml"),
+        "[format]\nindent_width = 2\nkeyword_case = \"lower\"\n",
+    )
+    .expect("Failed to write asp-parser.toml");
+    fs::write(temp_path.join("a.asp"), "<%\nIf x Then\ny = 1\nEnd If\n%>")
+        .expect("Failed to write a.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "a.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(temp_path.join("a.asp")).unwrap(),
+        "<%\nif x then\n  y = 1\nend if\n%>"
+    );
+}
+
+#[test]
+fn test_cli_fmt_casing_only() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("a.asp"),
+        "<%\nif x then\nresponse.write  \"<b>\"&x\nend if\n%>",
+    )
+    .expect("Failed to write a.asp");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["fmt", "--casing-only", "a.asp"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(temp_path.join("a.asp")).unwrap(),
+        "<%\nIf x Then\nResponse.Write  \"<b>\"&x\nEnd If\n%>"
+    );
+}
+
+#[test]
+fn test_cli_config() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let site_path = temp_path.join("site");
+    fs::create_dir(&site_path).expect("Failed to create site directory");
+    fs::write(
+        temp_path.join(".asp-parser.toml"),
+        "format = \"json\"\nstrict = true\n",
+    )
+    .expect("Failed to write the configuration");
+    fs::write(site_path.join(".asp-parser.toml"), "strict = false\n")
+        .expect("Failed to write the configuration");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(&site_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The closest file wins, and each setting names the files setting it
+    let output = run(&["config", "show"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+    let site_config = site_path.join(".asp-parser.toml").display().to_string();
+    let root_config = temp_path.join(".asp-parser.toml").display().to_string();
+    assert!(
+        stdout.contains(&format!(
+            "strict = false # from {}, {}",
+            site_config, root_config
+        )),
+        "got: {}",
+        stdout
+    );
+    assert!(
+        stdout.contains(&format!("format = \"json\" # from {}", root_config)),
+        "got: {}",
+        stdout
+    );
+
+    let output = run(&["config", "check"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stdout).contains(": ok"));
+
+    // Unknown keys and invalid values are reported
+    fs::write(
+        site_path.join(".asp-parser.toml"),
+        "strictness = false\nformat = \"xml\"\n",
+    )
+    .expect("Failed to write the configuration");
+    let output = run(&["config", "check"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "stdout: {}", stdout);
+    assert!(stdout.contains(&format!("{}: unknown key 'strictness'", site_config)));
+    assert!(stdout.contains("unknown output format 'xml'"));
+
+    let output = run(&["config", "check", "missing.toml"]);
+    assert_eq!(output.status.code(), Some(4));
+
+    let output = run(&["config", "schema"]);
+    assert_eq!(output.status.code(), Some(0));
+    let schema: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("The schema should be JSON");
+    assert_eq!(schema["title"], "asp-parser.toml");
+    assert!(schema["properties"]["rules"].is_object());
+}
+
+#[test]
+fn test_cli_config_show_extends() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::create_dir(temp_path.join("shared")).expect("Failed to create shared directory");
+    fs::write(
+        temp_path.join("shared/base.toml"),
+        "format = \"json\"\n[rules]\nundeclared-name = \"error\"\n",
+    )
+    .expect("Failed to write the extended configuration");
+    fs::write(
+        temp_path.join("asp-parser.toml"),
+        "extends = [\"recommended\", \"shared/base.toml\"]\nstrict = true\n",
+    )
+    .expect("Failed to write the configuration");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["config", "show"])
+        .current_dir(temp_path)
+        .output()
+        .expect("Failed to execute CLI");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(0));
+
+    // Inherited settings name the file or preset they come from
+    let config = temp_path.join("asp-parser.toml").display().to_string();
+    let base = temp_path.join("shared/base.toml").display().to_string();
+    assert!(
+        stdout.starts_with(&format!(
+            "# Configuration files, closest first:\n#   {}\n#   {}\n#   preset 'recommended'\n",
+            config, base
+        )),
+        "got: {}",
+        stdout
+    );
+    for line in [
+        format!("strict = true # from {}", config),
+        format!("format = \"json\" # from {}", base),
+        format!("undeclared-name = \"error\" # from {}", base),
+        "deep-nesting = \"warning\" # from preset 'recommended'".to_string(),
+    ] {
+        assert!(stdout.contains(&line), "missing {}, got: {}", line, stdout);
+    }
+}
+
+#[test]
+fn test_cli_cache_subcommand() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    let cache_dir = temp_path.join("cache");
+    let page = temp_path.join("page.asp");
+    fs::write(&page, "<% Response.Write \"Cached\" %>").expect("Failed to write page.asp");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .env("ASP_PARSER_CACHE_DIR", &cache_dir)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+    let cache_file = cache_dir.join("parse_cache.bin");
+
+    let output = run(&["cache", "path"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        cache_file.display().to_string()
+    );
+
+    // The second run finds the entry of the first one
+    run(&[page.to_str().unwrap()]);
+    run(&[page.to_str().unwrap()]);
+    let output = run(&["cache", "stats", "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    let stats: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Statistics should be JSON");
+    assert_eq!(stats["entries"], 1);
+    assert_eq!(
+        (stats["hits"].as_u64(), stats["misses"].as_u64()),
+        (Some(1), Some(1))
+    );
+    assert!(stats["size"].as_u64().unwrap() > 0);
+    let output = run(&["cache", "stats"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Hit rate: 50.0%"));
+
+    // Recent entries are kept when pruning, and clearing deletes the file
+    let output = run(&["cache", "clear", "--older-than", "7d"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed 0 entries"));
+    assert!(cache_file.exists());
+    let output = run(&["cache", "clear"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!cache_file.exists());
+
+    let output = run(&["cache", "clear", "--older-than", "soon"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_project_cache() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let root = fs::canonicalize(temp_dir.path()).expect("Failed to resolve temp directory");
+    let site = root.join("site");
+    fs::create_dir_all(&site).expect("Failed to create site directory");
+    fs::write(root.join("asp-parser.toml"), "strict = false\n").expect("Failed to write config");
+    fs::write(site.join("asp-parser.toml"), "").expect("Failed to write config");
+    fs::write(site.join("page.asp"), "<% x = 1 %>").expect("Failed to write page.asp");
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(&site)
+            .env_remove("ASP_PARSER_CACHE_DIR")
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The cache of a project is next to its outermost configuration file
+    let project_cache = root.join(".asp-parser-cache/parse_cache.bin");
+    let output = run(&["cache", "path"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        project_cache.display().to_string()
+    );
+    run(&["page.asp"]);
+    assert!(project_cache.exists());
+
+    // --cache-dir takes precedence over the environment and the configuration
+    let ci_cache = root.join("ci-cache/parse_cache.bin");
+    let output = Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+        .args(["page.asp", "--cache-dir", "../ci-cache"])
+        .current_dir(&site)
+        .env("ASP_PARSER_CACHE_DIR", root.join("env-cache"))
+        .output()
+        .expect("Failed to execute CLI");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(ci_cache.exists());
+    assert!(!root.join("env-cache").exists());
+    let output = run(&[
+        "cache",
+        "stats",
+        "--cache-dir",
+        "../ci-cache",
+        "--format",
+        "json",
+    ]);
+    let stats: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Statistics should be JSON");
+    assert_eq!(stats["entries"], 1);
+}
+
+/// In-memory blob store served over HTTP by a test thread
+struct BlobStore {
+    /// URL of the directory of the blobs
+    url: String,
+    /// Bodies stored by path
+    blobs: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, Vec<u8>>>>,
+    /// Authorization headers received
+    authorizations: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+/// Serve an in-memory blob store over HTTP: `PUT` stores the body of a path and
+/// `GET` returns it, recording the authorization headers received
+fn start_blob_store() -> BlobStore {
+    use std::io::{BufRead, BufReader, Read};
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+    let url = format!("http://{}/asp-cache", listener.local_addr().unwrap());
+    let blobs = std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new()));
+    let authorizations = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let (store, received) = (blobs.clone(), authorizations.clone());
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut parts = request_line.split_whitespace();
+            let (method, path) = (
+                parts.next().unwrap_or("").to_string(),
+                parts.next().unwrap_or("").to_string(),
+            );
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').unwrap();
+                match name.to_lowercase().as_str() {
+                    "content-length" => content_length = value.trim().parse().unwrap(),
+                    "authorization" => received.lock().unwrap().push(value.trim().to_string()),
+                    _ => {}
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let (status, body) = match method.as_str() {
+                "PUT" => {
+                    store.lock().unwrap().insert(path, body);
+                    ("200 OK", Vec::new())
+                }
+                _ => match store.lock().unwrap().get(&path) {
+                    Some(blob) => ("200 OK", blob.clone()),
+                    None => ("404 Not Found", Vec::new()),
+                },
+            };
+            let header = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body);
+        }
+    });
+    BlobStore {
+        url,
+        blobs,
+        authorizations,
+    }
+}
+
+#[test]
+fn test_cli_remote_cache() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("page.asp"), "<%\nIf x Then\nNext\n%>")
+        .expect("Failed to write page.asp");
+    let BlobStore {
+        url,
+        blobs,
+        authorizations,
+    } = start_blob_store();
+    let run = |remote: &str, cache_dir: &str| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args([
+                "page.asp",
+                "--verbose",
+                "--format=ascii",
+                "--cache-dir",
+                cache_dir,
+            ])
+            .current_dir(temp_path)
+            .env("ASP_PARSER_CACHE_REMOTE", remote)
+            .env("ASP_PARSER_CACHE_REMOTE_TOKEN", "secret")
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The first machine parses the file and stores its result
+    let output = run(&url, "first");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(blobs.lock().unwrap().len(), 1);
+    assert!(
+        authorizations
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|auth| auth == "Bearer secret")
+    );
+
+    // Another machine, with an empty local cache, replays it
+    let output = run(&url, "second");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stdout.contains("Using cached result for"),
+        "stdout: {}",
+        stdout
+    );
+    assert!(stderr.contains("page.asp"), "stderr: {}", stderr);
+
+    // An unreachable remote cache is disabled, and the file parsed
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let output = run(&format!("http://127.0.0.1:{}/", port), "third");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(
+        stderr.contains("Remote cache disabled"),
+        "stderr: {}",
+        stderr
+    );
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("Using cached result"));
+
+    // The remote cache must be an HTTP URL
+    let output = run("s3://bucket", "fourth");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_config_overrides() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::create_dir(temp_path.join("admin")).expect("Failed to create admin directory");
+    fs::write(
+        temp_path.join(".asp-parser.toml"),
+        "strict = true\n[rules]\nunused-variable = \"error\"\n\n[override.\"admin/**\"]\nstrict = false\nignore_warnings = [\"no-asp-tags\"]\nrules = { unused-variable = \"off\" }\n",
+    )
+    .expect("Failed to write the configuration");
+    for path in ["page.asp", "admin/page.asp"] {
+        fs::write(temp_path.join(path), "<%\nDim unused\n%>").expect("Failed to write page");
+    }
+    for path in ["page.html", "admin/page.html"] {
+        fs::write(temp_path.join(path), "<p>No code</p>").expect("Failed to write page");
+    }
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The rules of the override apply to the files of admin/ only
+    let output = run(&["lint", "--format", "ascii", "page.asp", "admin/page.asp"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(stderr.contains("page.asp") && !stderr.contains("admin/page.asp"));
+
+    // So do its strict mode and ignored warnings
+    let output = run(&["--no-cache", "admin/page.html"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!stderr.contains("No ASP tags"), "stderr: {}", stderr);
+    let output = run(&["--no-cache", "page.html"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_cli_config_extends() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(
+        temp_path.join("page.asp"),
+        "<%\nDim password\npassword = \"hunter22\"\nResponse.Write password\n%>",
+    )
+    .expect("Failed to write page.asp");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The credentials are a warning by default, and an error with the preset
+    let output = run(&["lint", "--format", "ascii", "page.asp"]);
+    assert_eq!(output.status.code(), Some(0));
+    fs::write(
+        temp_path.join(".asp-parser.toml"),
+        "extends = \"security\"\n",
+    )
+    .expect("Failed to write the configuration");
+    let output = run(&["lint", "--format", "ascii", "page.asp"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(stderr.contains("ASP021"), "stderr: {}", stderr);
+
+    fs::write(
+        temp_path.join(".asp-parser.toml"),
+        "extends = \"paranoid\"\n",
+    )
+    .expect("Failed to write the configuration");
+    let output = run(&["config", "check"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        stdout.contains("unknown preset 'paranoid'"),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_cli_config_paths() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::create_dir_all(temp_path.join("shared/lib")).expect("Failed to create shared/lib");
+    fs::create_dir_all(temp_path.join("site")).expect("Failed to create site");
+    fs::write(
+        temp_path.join("shared/lib/db.asp"),
+        "<%\nFunction Query(sql)\nEnd Function\n%>",
+    )
+    .expect("Failed to write db.asp");
+    fs::write(
+        temp_path.join("site/page.asp"),
+        "<!--#include virtual=\"/lib/db.asp\" -->\n<%\nOption Explicit\nResponse.Write Query(\"SELECT 1\") & total\n%>",
+    )
+    .expect("Failed to write page.asp");
+    fs::write(
+        temp_path.join("site/menu.inc"),
+        "<%\nOption Explicit\nResponse.Write missing\n%>",
+    )
+    .expect("Failed to write menu.inc");
+
+    let run = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // Names are not checked while an include is missing
+    let output = run(&["lint", "--format", "ascii", "site/page.asp"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "stderr: {}", stderr);
+
+    fs::write(
+        temp_path.join(".asp-parser.toml"),
+        "include_root = \"site\"\nextensions = [\"inc\"]\n\n[cache]\ndirectory = \"build/cache\"\n\n[virtual_paths]\n\"/lib\" = \"shared/lib\"\n",
+    )
+    .expect("Failed to write the configuration");
+    let output = run(&["lint", "--format", "ascii", "site/page.asp"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(stderr.contains("'total'"), "stderr: {}", stderr);
+    assert!(!stderr.contains("'Query'"), "stderr: {}", stderr);
+
+    // Only the files with the configured extensions are found in directories
+    let output = run(&["lint", "--format", "ascii", "site"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "stderr: {}", stderr);
+    assert!(stderr.contains("menu.inc"), "stderr: {}", stderr);
+    assert!(!stderr.contains("page.asp"), "stderr: {}", stderr);
+
+    let output = run(&["cache", "path"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.trim(),
+        temp_path
+            .join("build/cache/parse_cache.bin")
+            .to_string_lossy()
+    );
+}
+
+#[test]
+fn test_cli_env_config() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let temp_path = temp_dir.path();
+    fs::write(temp_path.join("page.asp"), "<%\nx = 1\n%>").expect("Failed to write page.asp");
+    fs::write(temp_path.join("asp-parser.toml"), "format = \"ascii\"\n")
+        .expect("Failed to write the configuration");
+
+    let run = |vars: &[(&str, &str)], args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_asp-classic-parser"))
+            .args(args)
+            .envs(vars.iter().copied())
+            .current_dir(temp_path)
+            .output()
+            .expect("Failed to execute CLI")
+    };
+
+    // The environment takes precedence over the configuration file, and the
+    // command line over the environment
+    let env = [("ASP_PARSER_FORMAT", "json")];
+    let output = run(&env, &["--no-cache", "page.asp"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("\"status\": \"success\""),
+        "got: {}",
+        stdout
+    );
+    let output = run(&env, &["--no-cache", "--format", "ascii", "page.asp"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("parsed successfully"), "got: {}", stdout);
+
+    let output = run(&env, &["config", "show"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("format = \"json\" # from environment"),
+        "got: {}",
+        stdout
+    );
+
+    let output = run(&[("ASP_PARSER_THREADS", "all")], &["config", "check"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(2), "stdout: {}", stdout);
+    assert!(
+        stdout.contains("environment: Invalid configuration value: ASP_PARSER_THREADS"),
+        "stdout: {}",
+        stdout
+    );
+}
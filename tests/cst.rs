@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{
+    self, ScriptLanguage,
+    cst::{SyntaxElement, SyntaxNode, SyntaxToken, TriviaKind},
+};
+
+/// Returns the token with the given text
+fn token<'a>(root: &'a SyntaxNode, text: &str) -> &'a SyntaxToken {
+    root.tokens()
+        .into_iter()
+        .find(|token| token.text == text)
+        .unwrap_or_else(|| panic!("No token {:?}", text))
+}
+
+/// Returns the first node of a kind, searching depth first
+fn find_node<'a>(node: &'a SyntaxNode, kind: &str) -> Option<&'a SyntaxNode> {
+    if node.kind == kind {
+        return Some(node);
+    }
+    node.children.iter().find_map(|child| match child {
+        SyntaxElement::Node(child) => find_node(child, kind),
+        SyntaxElement::Token(_) => None,
+    })
+}
+
+#[test]
+fn test_passing_fixtures_round_trip() {
+    let mut count = 0;
+    for entry in fs::read_dir(Path::new("fixtures/passing")).unwrap() {
+        let path = entry.unwrap().path();
+        let content = fs::read_to_string(&path).unwrap();
+        let tree = parser::parse_cst(&content)
+            .unwrap_or_else(|e| panic!("{} failed to parse: {}", path.display(), e));
+        assert_eq!(
+            tree.to_string(),
+            content,
+            "{} does not round-trip",
+            path.display()
+        );
+        count += 1;
+    }
+    assert!(count > 0, "No fixtures found");
+}
+
+#[test]
+fn test_trivia_is_attached_to_tokens() {
+    let page = "<%\n  x = 1 ' first\n  y = _\n    2\n%>";
+    let tree = parser::parse_cst(page).unwrap();
+    assert_eq!(tree.to_string(), page);
+
+    let one = token(&tree.root, "1");
+    let kinds: Vec<TriviaKind> = one.trailing_trivia.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec![TriviaKind::Whitespace, TriviaKind::Comment]);
+    assert_eq!(one.trailing_trivia[1].text, "' first");
+    assert_eq!(one.trailing_trivia[1].span.line, 2);
+
+    let y = token(&tree.root, "y");
+    let kinds: Vec<TriviaKind> = y.leading_trivia.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, vec![TriviaKind::Newline, TriviaKind::Whitespace]);
+
+    // The line continuation ends the line of the `=` before it
+    let tokens = tree.root.tokens();
+    let equals = tokens
+        .iter()
+        .rfind(|token| token.text == "=")
+        .expect("The second assignment has an equal sign");
+    let kinds: Vec<TriviaKind> = equals.trailing_trivia.iter().map(|t| t.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![TriviaKind::Whitespace, TriviaKind::LineContinuation]
+    );
+    assert_eq!(equals.trailing_trivia[1].text, "_\n");
+    assert_eq!(token(&tree.root, "2").leading_trivia[0].text, "    ");
+}
+
+#[test]
+fn test_nodes_follow_the_grammar() {
+    let tree = parser::parse_cst("<% If x Then %><b>hi</b><% End If %>").unwrap();
+    assert_eq!(tree.root.kind, "file");
+    assert!(find_node(&tree.root, "asp_script_block").is_some());
+    assert_eq!(token(&tree.root, "<%").kind, "asp_open_tag");
+    assert_eq!(token(&tree.root, "If").kind, "if_keyword");
+    assert_eq!(token(&tree.root, "<b>hi</b>").kind, "html_content");
+    assert_eq!(token(&tree.root, "End").kind, "end_keyword");
+    let last = tree.root.tokens().pop().unwrap();
+    assert_eq!(last.kind, "EOI");
+}
+
+#[test]
+fn test_line_endings_are_kept() {
+    for page in ["<%\r\nx = 1\r\n%>", "<%\rx = 1 'a\ry = 2\r%>"] {
+        let tree = parser::parse_cst(page).unwrap();
+        assert_eq!(tree.to_string(), page);
+        assert_eq!(token(&tree.root, "x").span.line, 2);
+    }
+}
+
+#[test]
+fn test_server_script_code_is_parsed() {
+    let page = "<script language=\"VBScript\" runat=\"server\">\nSub Hello()\n  Response.Write \"Hi\" ' greet\nEnd Sub\n</script>";
+    let tree = parser::parse_cst(page).unwrap();
+    assert_eq!(tree.to_string(), page);
+
+    let content = find_node(&tree.root, "server_script_content").expect("Script is parsed");
+    assert!(content.tokens().iter().any(|t| t.kind == "sub_keyword"));
+    let greeting = token(&tree.root, "\"Hi\"");
+    assert_eq!(greeting.span.line, 3);
+    assert_eq!(greeting.trailing_trivia[1].kind, TriviaKind::Comment);
+}
+
+#[test]
+fn test_jscript_comments_are_trivia() {
+    let page = "<%@ Language=\"JScript\" %>\n<% var x = 1; /* one */ // done\n%>";
+    let tree = parser::parse_cst(page).unwrap();
+    assert_eq!(tree.language, ScriptLanguage::JScript);
+    assert_eq!(tree.to_string(), page);
+
+    let semicolon = token(&tree.root, ";");
+    let comments: Vec<&str> = semicolon
+        .trailing_trivia
+        .iter()
+        .filter(|t| t.kind == TriviaKind::Comment)
+        .map(|t| t.text.as_str())
+        .collect();
+    assert_eq!(comments, vec!["/* one */", "// done"]);
+}
+
+#[test]
+fn test_grammar_errors_are_reported() {
+    let error = parser::parse_cst("<%\nx = 1\nDim\n%>").unwrap_err();
+    assert_eq!(error.span().map(|span| span.line), Some(3));
+}
@@ -0,0 +1,99 @@
+use asp_classic_parser::parser::{self, AspParseError, DiagnosticCode, Severity};
+
+/// Returns the code of each error reported by the recovering parser
+fn error_codes(asp_code: &str) -> Vec<&'static str> {
+    parser::parse_recovering(asp_code)
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.code.as_str())
+        .collect()
+}
+
+/// Returns the error reported by `parse` for a page
+fn parse_error(asp_code: &str) -> AspParseError {
+    let error = parser::parse(asp_code).unwrap_err();
+    match error.downcast::<AspParseError>() {
+        Ok(error) => *error,
+        Err(error) => panic!("Unexpected error type: {}", error),
+    }
+}
+
+#[test]
+fn test_grammar_errors_are_syntax_errors() {
+    assert_eq!(error_codes("<%\nDim\n%>"), vec!["ASP001"]);
+}
+
+#[test]
+fn test_syntax_errors_name_what_was_expected() {
+    let diagnostics = parser::parse_recovering("<html>\n<% Call %>").diagnostics;
+    assert_eq!(diagnostics[0].message, "expected expression");
+
+    let diagnostics = parser::parse_recovering("<%\nFor i = 1\n%>").diagnostics;
+    assert_eq!(diagnostics[0].message, "expected 'To' or operator");
+}
+
+#[test]
+fn test_block_errors_have_their_own_codes() {
+    assert_eq!(error_codes("<%\nIf x Then\n%>"), vec!["ASP002"]);
+    assert_eq!(error_codes("<%\nEnd If\n%>"), vec!["ASP003"]);
+    assert_eq!(
+        error_codes("<%\nFor i = 1 To 2\nEnd If\nNext\n%>"),
+        vec!["ASP003"]
+    );
+    assert_eq!(error_codes("<%\nElse\n%>"), vec!["ASP003"]);
+    assert_eq!(error_codes("<%\nSub A\nSub B\nEnd Sub\n%>"), vec!["ASP004"]);
+}
+
+#[test]
+fn test_errors_have_a_severity() {
+    let diagnostics = parser::parse_recovering("<%\nDim\n%>").diagnostics;
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+
+    assert_eq!(
+        DiagnosticCode::NoAspTags.default_severity(),
+        Severity::Warning
+    );
+    assert_eq!(
+        DiagnosticCode::EmptyFile.default_severity(),
+        Severity::Warning
+    );
+}
+
+#[test]
+fn test_strict_errors_are_located_without_parsing_messages() {
+    let error = parse_error("<%\nx = 1\nDim\n%>");
+    let span = error.span().expect("Syntax errors have a location");
+    assert_eq!((span.line, span.column), (3, 4));
+    assert_eq!(error.code(), DiagnosticCode::SyntaxError);
+
+    let diagnostic = error.diagnostic();
+    assert_eq!(diagnostic.span, span);
+    assert_eq!(diagnostic.to_string(), error.to_string());
+}
+
+#[test]
+fn test_file_errors_have_codes() {
+    let error = parse_error("<html></html>");
+    assert!(error.is_no_asp_tags_error());
+    assert_eq!(error.code(), DiagnosticCode::NoAspTags);
+    assert_eq!(error.span(), None);
+    assert_eq!(error.diagnostic().severity, Severity::Warning);
+    assert_eq!(error.diagnostic().span.line, 1);
+
+    let error = parse_error("  \n");
+    assert!(error.is_empty_file_error());
+    assert_eq!(error.code().as_str(), "ASP006");
+}
+
+#[test]
+fn test_codes_are_found_by_code_or_name() {
+    for code in DiagnosticCode::ALL {
+        assert_eq!(DiagnosticCode::from_name(code.as_str()), Some(code));
+        assert_eq!(DiagnosticCode::from_name(code.name()), Some(code));
+    }
+    assert_eq!(
+        DiagnosticCode::from_name("No-Asp-Tags"),
+        Some(DiagnosticCode::NoAspTags)
+    );
+    assert_eq!(DiagnosticCode::from_name("unknown-rule"), None);
+}
@@ -0,0 +1,66 @@
+use asp_classic_parser::analysis::duplicates::DuplicateFinder;
+use std::path::Path;
+
+const LIST: &str = r#"<html>
+<%
+Dim rs
+Set rs = conn.Execute("SELECT id, name FROM products")
+Do While Not rs.EOF
+    Response.Write "<li>" & Server.HTMLEncode(rs("name")) & "</li>"
+    rs.MoveNext
+Loop
+rs.Close
+%>
+</html>"#;
+
+const ADMIN: &str = r#"<%
+' Same loop, formatted differently
+title = "Admin"
+SET RS = CONN.EXECUTE("SELECT id, name FROM products")
+do while not rs.EOF
+  response.write "<li>" & _
+    server.htmlencode(rs("name")) & "</li>"
+  rs.movenext : loop
+rs.Close
+%>"#;
+
+#[test]
+fn test_clones_across_files() {
+    let mut finder = DuplicateFinder::new(20);
+    finder.add_file(Path::new("list.asp"), LIST);
+    finder.add_file(Path::new("admin.asp"), ADMIN);
+
+    let groups = finder.clone_groups();
+    assert_eq!(groups.len(), 1, "got: {:?}", groups);
+    let group = &groups[0];
+    assert_eq!(group.occurrences.len(), 2);
+    // From the Set statement to rs.Close, the Dim and title lines differing
+    let places: Vec<_> = group
+        .occurrences
+        .iter()
+        .map(|o| (o.path.to_str().unwrap(), o.span.line, o.end_line))
+        .collect();
+    assert_eq!(places, [("list.asp", 4, 9), ("admin.asp", 4, 9)]);
+    assert_eq!(group.tokens, 38);
+}
+
+#[test]
+fn test_minimum_length() {
+    let mut finder = DuplicateFinder::new(50);
+    finder.add_file(Path::new("list.asp"), LIST);
+    finder.add_file(Path::new("admin.asp"), ADMIN);
+    assert!(finder.clone_groups().is_empty());
+
+    // Statements repeated in the same file, but not overlapping
+    let code = "<%\nx = a + b + c\ny = 1\nx = a + b + c\nz = 2\nx = a + b + c\n%>";
+    let mut finder = DuplicateFinder::new(7);
+    finder.add_file(Path::new("page.asp"), code);
+    let groups = finder.clone_groups();
+    assert_eq!(groups.len(), 1, "got: {:?}", groups);
+    let lines: Vec<_> = groups[0].occurrences.iter().map(|o| o.span.line).collect();
+    assert_eq!(lines, [2, 4, 6]);
+
+    let mut finder = DuplicateFinder::new(8);
+    finder.add_file(Path::new("page.asp"), code);
+    assert!(finder.clone_groups().is_empty());
+}
@@ -0,0 +1,112 @@
+use asp_classic_parser::analysis::{self, Evaluator, Value};
+use asp_classic_parser::parser::{self, ast::Statement};
+
+/// Evaluates the value assigned by the last statement of a page
+fn evaluate_last(asp_code: &str) -> Option<Value> {
+    let program = parser::parse_to_ast(asp_code)
+        .unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", asp_code, e));
+    let Some(Statement::Assignment(assignment)) = program.body.last() else {
+        panic!("No assignment at the end of {:?}", asp_code);
+    };
+    Evaluator::from_program(&program).evaluate(&assignment.value)
+}
+
+fn evaluate(expression: &str) -> Option<Value> {
+    evaluate_last(&format!("<% x = {} %>", expression))
+}
+
+fn string(value: &str) -> Option<Value> {
+    Some(Value::String(value.to_string()))
+}
+
+#[test]
+fn test_arithmetic() {
+    assert_eq!(evaluate("1 + 2 * 3"), Some(Value::Integer(7)));
+    assert_eq!(evaluate("(1 + 2) * 3"), Some(Value::Integer(9)));
+    assert_eq!(evaluate("7 / 2"), Some(Value::Float(3.5)));
+    assert_eq!(evaluate("7 \\ 2"), Some(Value::Integer(3)));
+    assert_eq!(evaluate("7 Mod 2"), Some(Value::Integer(1)));
+    assert_eq!(evaluate("2 ^ 10"), Some(Value::Float(1024.0)));
+    assert_eq!(evaluate("-&HFF"), Some(Value::Integer(-255)));
+    assert_eq!(evaluate("\"2\" * 3"), Some(Value::Float(6.0)));
+    assert_eq!(evaluate("1 / 0"), None);
+    assert_eq!(evaluate("\"a\" * 2"), None);
+}
+
+#[test]
+fn test_concatenation() {
+    assert_eq!(evaluate("\"a\" & 1 & True"), string("a1True"));
+    assert_eq!(evaluate("\"a\" + \"b\""), string("ab"));
+    assert_eq!(evaluate("\"x\" & Null"), string("x"));
+    assert_eq!(evaluate("Null & Null"), Some(Value::Null));
+    assert_eq!(evaluate("Null + 1"), Some(Value::Null));
+    assert_eq!(evaluate("\"n = \" & 1.5 * 2"), string("n = 3"));
+    assert_eq!(evaluate("\"a\" & vbCrLf & \"b\""), string("a\r\nb"));
+}
+
+#[test]
+fn test_comparisons_and_logic() {
+    assert_eq!(
+        evaluate("1 < 2 And \"b\" > \"a\""),
+        Some(Value::Boolean(true))
+    );
+    assert_eq!(evaluate("Not (1 = 1)"), Some(Value::Boolean(false)));
+    assert_eq!(evaluate("6 And 3"), Some(Value::Integer(2)));
+    assert_eq!(evaluate("Not 0"), Some(Value::Integer(-1)));
+    assert_eq!(evaluate("\"1\" = 1"), None);
+}
+
+#[test]
+fn test_builtin_functions() {
+    assert_eq!(evaluate("Chr(65) & ChrW(66)"), string("AB"));
+    assert_eq!(evaluate("Len(\"hello\")"), Some(Value::Integer(5)));
+    assert_eq!(
+        evaluate("UCase(Left(\"hello\", 2)) & Mid(\"hello\", 3)"),
+        string("HEllo")
+    );
+    assert_eq!(evaluate("Right(\"hello\", 10)"), string("hello"));
+    assert_eq!(
+        evaluate("Replace(\"a-b-c\", \"-\", \"/\")"),
+        string("a/b/c")
+    );
+    assert_eq!(
+        evaluate("Trim(\"  x  \") & Space(2) & String(3, \"*\")"),
+        string("x  ***")
+    );
+    assert_eq!(evaluate("InStr(\"hello\", \"l\")"), Some(Value::Integer(3)));
+    assert_eq!(evaluate("Hex(255)"), string("FF"));
+    assert_eq!(evaluate("CStr(12) & CInt(2.5) & CInt(3.5)"), string("1224"));
+    assert_eq!(evaluate("Len(name)"), None);
+    assert_eq!(evaluate("MyFunction(1)"), None);
+    assert_eq!(evaluate("Request.QueryString(\"id\")"), None);
+}
+
+#[test]
+fn test_page_constants() {
+    assert_eq!(
+        evaluate_last(
+            "<%\nConst BASE = \"/app\"\nConst LOGIN = BASE & \"/login.asp\"\nx = LOGIN & \"?next=\" & Server.URLEncode(page)\ny = LOGIN & \"?from=home\"\n%>"
+        ),
+        string("/app/login.asp?from=home")
+    );
+    // Constants local to a procedure are not page-level constants
+    assert_eq!(
+        evaluate_last("<%\nSub Test()\n  Const A = 1\nEnd Sub\nx = A\n%>"),
+        None
+    );
+}
+
+#[test]
+fn test_evaluate_without_page_constants() {
+    let program = parser::parse_to_ast("<%\nConst A = 1\nx = A + vbTrue\n%>").unwrap();
+    let Statement::Assignment(assignment) = &program.body[1] else {
+        panic!("No assignment");
+    };
+    assert_eq!(analysis::evaluate(&assignment.value), None);
+    let mut evaluator = Evaluator::new();
+    evaluator.define("a", Value::Integer(2));
+    assert_eq!(
+        evaluator.evaluate(&assignment.value),
+        Some(Value::Integer(1))
+    );
+}
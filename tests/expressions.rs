@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, AspParser, Rule};
+use pest::Parser;
+
+/// Parses the given ASP code and returns the number of pairs matching `rule`
+fn count_rule(asp_code: &str, rule: Rule) -> usize {
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("ASP code should parse");
+    pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == rule)
+        .count()
+}
+
+/// Returns the rule of the first statement of the given ASP code
+fn first_statement_rule(asp_code: &str) -> Rule {
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("ASP code should parse");
+    pairs
+        .flatten()
+        .find(|pair| pair.as_rule() == Rule::statement)
+        .and_then(|pair| pair.into_inner().next())
+        .map(|pair| pair.as_rule())
+        .expect("ASP code should contain a statement")
+}
+
+#[test]
+fn test_chained_access_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/chained_access.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read chained access fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing chained access fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_member_access_after_call() {
+    let asp_code = "<% value = rs.Fields(\"name\").Value %>";
+    assert_eq!(first_statement_rule(asp_code), Rule::assignment);
+    assert_eq!(count_rule(asp_code, Rule::member_access), 2);
+    assert_eq!(count_rule(asp_code, Rule::call_arguments), 1);
+}
+
+#[test]
+fn test_index_after_call() {
+    let asp_code = "<% first = dict.Items()(0) %>";
+    assert_eq!(first_statement_rule(asp_code), Rule::assignment);
+    assert_eq!(count_rule(asp_code, Rule::call_arguments), 2);
+}
+
+#[test]
+fn test_call_statement_on_chained_target() {
+    let asp_code = "<% Server.CreateObject(\"ADODB.Recordset\").Open sql, conn %>";
+    assert_eq!(first_statement_rule(asp_code), Rule::call_statement);
+    assert_eq!(count_rule(asp_code, Rule::argument), 3);
+}
+
+#[test]
+fn test_arbitrary_depth_chains() {
+    let asp_code = "<% x = a.b(1).c(2)(3).d.e(f.g(h(4)).i) %>";
+    assert_eq!(first_statement_rule(asp_code), Rule::assignment);
+    assert_eq!(count_rule(asp_code, Rule::member_access), 6);
+    assert_eq!(count_rule(asp_code, Rule::call_arguments), 6);
+}
+
+#[test]
+fn test_assignment_to_chained_target() {
+    let asp_code = "<% rs.Fields(\"name\").Value = \"x\" : arr(1, 2) = 3 %>";
+    assert_eq!(count_rule(asp_code, Rule::assignment), 2);
+}
+
+#[test]
+fn test_parenthesized_first_argument_of_call_statement() {
+    // The parentheses belong to the argument, not to the call target
+    let asp_code = "<% DoSomething (a + b) * 2, c %>";
+    assert_eq!(first_statement_rule(asp_code), Rule::call_statement);
+    assert_eq!(count_rule(asp_code, Rule::argument), 2);
+}
+
+#[test]
+fn test_chained_output_expression() {
+    let asp_code = "<p><%= rs.Fields(0).Name %></p>";
+    assert_eq!(count_rule(asp_code, Rule::output_expression), 1);
+    assert_eq!(count_rule(asp_code, Rule::raw_expression), 0);
+}
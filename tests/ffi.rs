@@ -0,0 +1,67 @@
+use asp_classic_parser::ffi::{asp_free_string, asp_parse_file, asp_parse_source, asp_version};
+use std::ffi::{CStr, CString, c_char};
+use std::fs;
+use tempfile::tempdir;
+
+/// Takes a string returned by the library and releases it
+fn take_json(string: *mut c_char) -> serde_json::Value {
+    assert!(!string.is_null());
+    let json = unsafe { CStr::from_ptr(string) }
+        .to_str()
+        .expect("Result is not UTF-8")
+        .to_string();
+    unsafe { asp_free_string(string) };
+    serde_json::from_str(&json).expect("Result is not JSON")
+}
+
+#[test]
+fn test_parse_source() {
+    let source = CString::new("<% Response.Write \"Hello\" %>").unwrap();
+    let result = take_json(unsafe { asp_parse_source(source.as_ptr()) });
+    assert_eq!(result["success"], true);
+    assert_eq!(result["diagnostics"], serde_json::json!([]));
+    assert!(result.get("file").is_none());
+
+    let source = CString::new("<%\nIf x Then\n%>").unwrap();
+    let result = take_json(unsafe { asp_parse_source(source.as_ptr()) });
+    assert_eq!(result["success"], false);
+    let diagnostic = &result["diagnostics"][0];
+    assert!(diagnostic["code"].as_str().unwrap().starts_with("ASP"));
+    assert_eq!(diagnostic["severity"], "error");
+    assert!(diagnostic["line"].as_u64().unwrap() >= 1);
+}
+
+#[test]
+fn test_parse_file() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let path = dir.path().join("page.asp");
+    fs::write(&path, "<% Dim x %>").expect("Failed to write file");
+
+    let c_path = CString::new(path.to_str().unwrap()).unwrap();
+    let result = take_json(unsafe { asp_parse_file(c_path.as_ptr()) });
+    assert_eq!(result["file"], path.to_str().unwrap());
+    assert_eq!(result["success"], true);
+
+    let missing = CString::new(dir.path().join("missing.asp").to_str().unwrap()).unwrap();
+    let result = take_json(unsafe { asp_parse_file(missing.as_ptr()) });
+    assert_eq!(result["success"], false);
+    assert!(
+        result["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Cannot read file")
+    );
+}
+
+#[test]
+fn test_null_arguments() {
+    assert!(unsafe { asp_parse_file(std::ptr::null()) }.is_null());
+    assert!(unsafe { asp_parse_source(std::ptr::null()) }.is_null());
+    unsafe { asp_free_string(std::ptr::null_mut()) };
+}
+
+#[test]
+fn test_version() {
+    let version = unsafe { CStr::from_ptr(asp_version()) };
+    assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+}
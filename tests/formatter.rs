@@ -0,0 +1,374 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::formatter::{self, FormatOptions, IndentStyle, KeywordCase};
+use asp_classic_parser::parser::{self, ast::LineIndex, lexer::TokenKind};
+
+/// Returns the code tokens of a page, ignoring case and whitespace
+fn code_tokens(page: &str) -> Vec<(TokenKind, String)> {
+    parser::tokenize(page)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline))
+        .map(|token| (token.kind, token.text.trim().to_ascii_lowercase()))
+        .collect()
+}
+
+#[test]
+fn test_indentation() {
+    let page = "<%
+Class Cart
+Private m_items
+Public Property Get Count()
+Count = m_items.Count
+End Property
+End Class
+
+Function Label(value)
+Select Case value
+Case 0
+Label = \"none\"
+Case Else
+If value > 10 Then
+Label = \"many\"
+ElseIf value > 1 Then
+Label = \"some\"
+Else
+Label = \"one\"
+End If
+End Select
+End Function
+%>";
+    let expected = "<%
+Class Cart
+    Private m_items
+    Public Property Get Count()
+        Count = m_items.Count
+    End Property
+End Class
+
+Function Label(value)
+    Select Case value
+        Case 0
+            Label = \"none\"
+        Case Else
+            If value > 10 Then
+                Label = \"many\"
+            ElseIf value > 1 Then
+                Label = \"some\"
+            Else
+                Label = \"one\"
+            End If
+    End Select
+End Function
+%>";
+    assert_eq!(formatter::format(page), expected);
+}
+
+#[test]
+fn test_blocks_across_asp_blocks() {
+    let page = "<table>
+  <% For Each row In rows %>
+  <tr>
+  <%
+  If row.highlighted Then
+  Response.Write \"<td class=hl>\"
+  End If
+  %>
+  </tr>
+  <% Next %>
+</table>";
+    let expected = "<table>
+  <% For Each row In rows %>
+  <tr>
+  <%
+      If row.highlighted Then
+          Response.Write \"<td class=hl>\"
+      End If
+  %>
+  </tr>
+  <% Next %>
+</table>";
+    assert_eq!(formatter::format(page), expected);
+}
+
+#[test]
+fn test_spacing_and_casing() {
+    let page = "<%
+dim a,b
+a=-1+b*(2-c)   ' keep  this
+if a<>0 and not b then x=f(a,-b) else x=0
+response.write(\"<b>\"&x&\"</b>\")
+Call Show (x)
+with rs
+.movenext : response.write .fields(\"name\").value
+end with
+total = total _
++ 1
+%>
+<p><%=Server.HTMLEncode( name )%></p>";
+    let expected = "<%
+Dim a, b
+a = -1 + b * (2 - c)   ' keep  this
+If a <> 0 And Not b Then x = f(a, -b) Else x = 0
+response.write(\"<b>\" & x & \"</b>\")
+Call Show (x)
+With rs
+    .movenext : response.write .fields(\"name\").value
+End With
+total = total _
+    + 1
+%>
+<p><%= Server.HTMLEncode(name) %></p>";
+    assert_eq!(formatter::format(page), expected);
+}
+
+#[test]
+fn test_untouched_code() {
+    // JScript, directives, HTML and empty blocks are kept as they are
+    let page = "<%@ Language=\"JScript\" %>\n<div  class=x>\n<%   var x=1;\n%>\n<% %></div>";
+    assert_eq!(formatter::format(page), page);
+
+    let page = "<script runat=\"server\" language=\"JScript\">\nfunction f(){return 1}\n</script>\r\n<%\r\nif x then\r\ny\r\nend if\r\n%>";
+    assert_eq!(
+        formatter::format(page),
+        "<script runat=\"server\" language=\"JScript\">\nfunction f(){return 1}\n</script>\r\n<%\r\nIf x Then\r\n    y\r\nEnd If\r\n%>"
+    );
+}
+
+#[test]
+fn test_fixtures_are_formatted_idempotently() {
+    let mut count = 0;
+    for entry in fs::read_dir(Path::new("fixtures/passing")).unwrap() {
+        let path = entry.unwrap().path();
+        let content = fs::read_to_string(&path).unwrap();
+        let formatted = formatter::format(&content);
+        assert_eq!(
+            formatter::format(&formatted),
+            formatted,
+            "{} is not formatted idempotently",
+            path.display()
+        );
+        assert_eq!(
+            code_tokens(&formatted),
+            code_tokens(&content),
+            "formatting {} changed its code",
+            path.display()
+        );
+        if parser::parse_to_ast(&content).is_ok() {
+            assert!(
+                parser::parse_to_ast(&formatted).is_ok(),
+                "{} does not parse once formatted:\n{}",
+                path.display(),
+                formatted
+            );
+        }
+        count += 1;
+    }
+    assert!(count > 0, "No fixtures found");
+}
+
+#[test]
+fn test_diff() {
+    assert_eq!(formatter::diff("page.asp", "<% x %>", "<% x %>"), "");
+
+    // Distant changes are shown in separate hunks, with three lines of context
+    let original: String = (1..=20).map(|line| format!("line {}\n", line)).collect();
+    let changed = original
+        .replace("line 2\n", "line two\n")
+        .replace("line 15\n", "")
+        .replace("line 20\n", "line 20");
+    assert_eq!(
+        formatter::diff("page.asp", &original, &changed),
+        "--- page.asp
++++ page.asp
+@@ -1,5 +1,5 @@
+ line 1
+-line 2
++line two
+ line 3
+ line 4
+ line 5
+@@ -12,9 +12,8 @@
+ line 12
+ line 13
+ line 14
+-line 15
+ line 16
+ line 17
+ line 18
+ line 19
+-line 20
++line 20
+\\ No newline at end of file
+"
+    );
+}
+
+#[test]
+fn test_indentation_options() {
+    let page = "<%\nSub Show()\nIf x Then\nResponse.Write x\nEnd If\nEnd Sub\n%>";
+    let options = FormatOptions {
+        indent_width: 2,
+        keyword_case: KeywordCase::Lower,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        formatter::format_with_options(page, &options),
+        "<%\nsub Show()\n  if x then\n    Response.Write x\n  end if\nend sub\n%>"
+    );
+    let options = FormatOptions {
+        indent_style: IndentStyle::Tabs,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        formatter::format_with_options(page, &options),
+        "<%\nSub Show()\n\tIf x Then\n\t\tResponse.Write x\n\tEnd If\nEnd Sub\n%>"
+    );
+}
+
+#[test]
+fn test_line_wrapping() {
+    let options = FormatOptions {
+        max_line_length: Some(40),
+        ..FormatOptions::default()
+    };
+    let page = "<%
+If True Then
+    html = \"<tr><td>\" & name & \"</td><td>\" & price & \"</td></tr>\"
+    Call Save(firstName, lastName, email, phone)
+    message = \"This string literal is too long to fit on a line\" ' comment
+End If
+%>";
+    let expected = "<%
+If True Then
+    html = \"<tr><td>\" & name _
+        & \"</td><td>\" & price _
+        & \"</td></tr>\"
+    Call Save(firstName, lastName, _
+        email, phone)
+    message = \"This string literal is too long to fit on a line\" ' comment
+End If
+%>";
+    let formatted = formatter::format_with_options(page, &options);
+    assert_eq!(formatted, expected);
+    assert_eq!(
+        formatter::format_with_options(&formatted, &options),
+        formatted
+    );
+
+    // Unbreakable pieces are kept whole
+    let page = "<%\nx = \"a string literal longer than the maximum\" & y\n%>";
+    let expected = "<%\nx = \"a string literal longer than the maximum\" _\n    & y\n%>";
+    assert_eq!(formatter::format_with_options(page, &options), expected);
+    assert_eq!(formatter::format_with_options(expected, &options), expected);
+}
+
+#[test]
+fn test_dim_alignment() {
+    let options = FormatOptions {
+        max_line_length: Some(30),
+        align_dims: true,
+        ..FormatOptions::default()
+    };
+    let page = "<%
+Class Order
+Private m_id, m_customer, m_lines, m_total
+End Class
+Dim first, _
+second
+total = firstValue + secondValue + thirdValue
+%>";
+    let expected = "<%
+Class Order
+    Private m_id, _
+            m_customer, _
+            m_lines, m_total
+End Class
+Dim first, _
+    second
+total = firstValue _
+    + secondValue + thirdValue
+%>";
+    let formatted = formatter::format_with_options(page, &options);
+    assert_eq!(formatted, expected);
+    assert_eq!(
+        formatter::format_with_options(&formatted, &options),
+        formatted
+    );
+}
+
+#[test]
+fn test_range_formatting() {
+    let page = "<ul>
+<%for each item in items%>
+<%
+if item.visible then
+response.write \"<li>\"&item.name&\"</li>\"
+end if
+%>
+<%next%>
+</ul>";
+    let lines = LineIndex::new(page);
+
+    // Only the selected lines change, indented from the blocks opened before
+    let start = page.find("response").unwrap();
+    let span = lines.span(start, start + 8);
+    let expected = page.replace(
+        "response.write \"<li>\"&item.name&\"</li>\"",
+        "        response.write \"<li>\" & item.name & \"</li>\"",
+    );
+    assert_eq!(formatter::format_range(page, span), expected);
+
+    // A selection ending at the start of a line does not include it
+    let start = page.find("<%for").unwrap();
+    let span = lines.span(start, page.find("<%\nif").unwrap());
+    let expected = page.replace("<%for each item in items%>", "<% For Each item In items %>");
+    assert_eq!(formatter::format_range(page, span), expected);
+
+    assert_eq!(
+        formatter::format_range(page, lines.span(0, page.len())),
+        formatter::format(page)
+    );
+
+    // Wrapped lines replace the selected line
+    let options = FormatOptions {
+        max_line_length: Some(30),
+        ..FormatOptions::default()
+    };
+    let page = "<%\nx = 1\nCall Save(firstName, lastName, email)\ny = 2\n%>";
+    let start = page.find("Call").unwrap();
+    assert_eq!(
+        formatter::format_range_with_options(
+            page,
+            LineIndex::new(page).span(start, start),
+            &options
+        ),
+        "<%\nx = 1\nCall Save(firstName, _\n    lastName, email)\ny = 2\n%>"
+    );
+}
+
+#[test]
+fn test_casing_normalization() {
+    let page = "<%@ language=\"vbscript\" %>
+<p>response.write</p>
+<%
+dim rs : set rs=server.createobject(\"ADODB.Recordset\")
+if not rs.eof AND err.number=0 then
+  response.write  server.htmlencode(rs(\"name\")) & vbcrlf ' response.write
+  rs.movenext : x.response.write \"end if\"
+end if
+%>
+<script runat=\"server\" language=\"jscript\">response.write(\"x\")</script>";
+    let expected = "<%@ language=\"vbscript\" %>
+<p>response.write</p>
+<%
+Dim rs : Set rs=Server.CreateObject(\"ADODB.Recordset\")
+If Not rs.eof And Err.Number=0 Then
+  Response.Write  Server.HTMLEncode(rs(\"name\")) & vbCrLf ' response.write
+  rs.movenext : x.response.write \"end if\"
+End If
+%>
+<script runat=\"server\" language=\"jscript\">response.write(\"x\")</script>";
+    assert_eq!(formatter::normalize_casing(page), expected);
+    assert_eq!(formatter::normalize_casing(expected), expected);
+}
@@ -0,0 +1,109 @@
+use asp_classic_parser::analysis::global_asa::{self, ApplicationEvent, ObjectScope};
+use asp_classic_parser::analysis::{self, DeclarationKind, Resolution, SymbolKind};
+use asp_classic_parser::parser;
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const GLOBAL_ASA: &str = "<OBJECT RUNAT=Server SCOPE=Session ID=MyInfo PROGID=\"MSWC.MyInfo\">\n</OBJECT>\n<object runat=\"server\" scope='Application' id=\"Tools\"\n  classid=\"clsid:0061350c-9091-11d0-8d3b-00c04fd7d7a3\"></object>\n<!-- <object runat=\"server\" id=\"Commented\"></object> -->\n<object id=\"Client\" classid=\"clsid:1\"></object>\n<SCRIPT LANGUAGE=VBScript RUNAT=Server>\nSub Application_OnStart\n  Application(\"visits\") = 0\nEnd Sub\n\nSub session_onstart\n  MyInfo.Visits = MyInfo.Visits + 1\nEnd Sub\n\nSub Helper\nEnd Sub\n</SCRIPT>";
+
+#[test]
+fn test_global_asa_declarations() {
+    let program = parser::parse_recovering(GLOBAL_ASA).program;
+    let global = global_asa::global_asa(&program);
+
+    // Client-side and commented objects are not declarations
+    let objects: Vec<(&str, ObjectScope)> = global
+        .objects
+        .iter()
+        .map(|object| (object.id.as_str(), object.scope))
+        .collect();
+    assert_eq!(
+        objects,
+        [
+            ("MyInfo", ObjectScope::Session),
+            ("Tools", ObjectScope::Application)
+        ]
+    );
+    let info = &global.objects[0];
+    assert_eq!(info.prog_id.as_deref(), Some("MSWC.MyInfo"));
+    assert_eq!(info.class_id, None);
+    assert_eq!(info.id_span.as_str(GLOBAL_ASA), "MyInfo");
+    assert_eq!((info.id_span.line, info.id_span.column), (1, 39));
+    let tools = &global.objects[1];
+    assert_eq!(
+        tools.class_id.as_deref(),
+        Some("clsid:0061350c-9091-11d0-8d3b-00c04fd7d7a3")
+    );
+    assert_eq!((tools.id_span.line, tools.id_span.column), (3, 48));
+    assert!(tools.span.as_str(GLOBAL_ASA).starts_with("<object"));
+    assert!(tools.span.as_str(GLOBAL_ASA).ends_with("a3\">"));
+
+    // Event handlers are found whatever their case
+    let events: Vec<(ApplicationEvent, &str)> = global
+        .events
+        .iter()
+        .map(|handler| (handler.event, handler.symbol.name.as_str()))
+        .collect();
+    assert_eq!(
+        events,
+        [
+            (ApplicationEvent::ApplicationOnStart, "Application_OnStart"),
+            (ApplicationEvent::SessionOnStart, "session_onstart"),
+        ]
+    );
+    assert_eq!(global.scripts.len(), 1);
+    assert_eq!(
+        ApplicationEvent::from_name("SESSION_ONEND"),
+        Some(ApplicationEvent::SessionOnEnd)
+    );
+    assert_eq!(ApplicationEvent::from_name("Helper"), None);
+}
+
+#[test]
+fn test_objects_are_declarations() {
+    // Objects are symbols of the file and resolve the names using them
+    let program = parser::parse_recovering(GLOBAL_ASA).program;
+    let table = analysis::symbols(&program);
+    let info = table.get("myinfo").unwrap();
+    assert_eq!(info.kind, SymbolKind::Object);
+    assert_eq!(info.signature(), "Object MyInfo");
+
+    let bindings = analysis::bindings(&program);
+    let reference = bindings
+        .references
+        .iter()
+        .find(|reference| reference.name == "MyInfo")
+        .unwrap();
+    let Resolution::Declaration(id) = reference.resolution else {
+        panic!("MyInfo should be declared, got {:?}", reference.resolution);
+    };
+    assert_eq!(bindings.declarations[id].kind, DeclarationKind::Object);
+
+    // Pages can declare objects too, for the page only
+    let page = "<object runat=\"server\" id=\"browser\" progid=\"MSWC.BrowserType\"></object>\n<%\nOption Explicit\nResponse.Write browser.Browser\n%>";
+    let program = parser::parse_recovering(page).program;
+    assert_eq!(global_asa::objects(&program)[0].scope, ObjectScope::Page);
+    assert_eq!(analysis::bindings(&program).undeclared().count(), 0);
+}
+
+#[test]
+fn test_find_global_asa() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    fs::create_dir_all(root.join("shop/admin")).expect("Failed to create directories");
+    fs::write(root.join("Global.asa"), GLOBAL_ASA).expect("Failed to write Global.asa");
+    fs::write(root.join("shop/global.asa"), GLOBAL_ASA).expect("Failed to write global.asa");
+
+    assert!(global_asa::is_global_asa(Path::new("/site/GLOBAL.ASA")));
+    assert!(!global_asa::is_global_asa(Path::new("/site/global.asp")));
+    // The closest one is the one of the application
+    assert_eq!(
+        global_asa::find_global_asa(&root.join("shop/admin/index.asp")),
+        Some(root.join("shop/global.asa"))
+    );
+    assert_eq!(
+        global_asa::find_global_asa(&root.join("index.asp")),
+        Some(root.join("Global.asa"))
+    );
+}
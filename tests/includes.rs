@@ -0,0 +1,208 @@
+use asp_classic_parser::includes::{
+    self, IncludeError, IncludeGraph, IncludeKind, IncludeResolver,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tempfile::tempdir;
+
+/// Writes files given as (relative path, content) pairs under a directory
+fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create directory");
+        fs::write(path, content).expect("Failed to write file");
+    }
+}
+
+/// Returns the paths of files of the graph, relative to a directory
+fn relative_paths(graph: &IncludeGraph, root: &Path, files: &[usize]) -> Vec<String> {
+    let root = fs::canonicalize(root).unwrap();
+    files
+        .iter()
+        .map(|&file| {
+            graph.files[file]
+                .path
+                .strip_prefix(&root)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect()
+}
+
+#[test]
+fn test_directives() {
+    let directives = includes::directives(
+        "<!-- #include file=\"header.asp\" -->\n<!--#INCLUDE Virtual='/lib/db.asp'-->\n<% ' <!--#include file=\"commented.asp\" --> %>\n<!--#include file=inc/bare.asp -->",
+    );
+    let found: Vec<(IncludeKind, &str, usize)> = directives
+        .iter()
+        .map(|d| (d.kind, d.path.as_str(), d.span.line))
+        .collect();
+    // `<!-- #include` with a space is a plain HTML comment for IIS
+    assert_eq!(
+        found,
+        [
+            (IncludeKind::Virtual, "/lib/db.asp", 2),
+            (IncludeKind::File, "inc/bare.asp", 4),
+        ]
+    );
+}
+
+#[test]
+fn test_resolve_file_and_virtual() {
+    let directives = includes::directives(
+        "<!--#include file=\"../lib/db.asp\" --><!--#include virtual=\"/lib/db.asp\" -->",
+    );
+    let page = PathBuf::from("/site/orders/list.asp");
+
+    let resolver = IncludeResolver::new();
+    assert_eq!(
+        resolver.resolve(&page, &directives[0]),
+        Some(PathBuf::from("/site/lib/db.asp"))
+    );
+    assert_eq!(resolver.resolve(&page, &directives[1]), None);
+
+    let resolver = IncludeResolver::new().with_web_root("/site");
+    assert_eq!(
+        resolver.resolve(&page, &directives[1]),
+        Some(PathBuf::from("/site/lib/db.asp"))
+    );
+}
+
+#[test]
+fn test_resolve_virtual_paths() {
+    let page = PathBuf::from("/site/orders/list.asp");
+    let resolve = |resolver: &IncludeResolver, path: &str| {
+        let directives = includes::directives(&format!("<!--#include virtual=\"{}\" -->", path));
+        resolver.resolve(&page, &directives[0])
+    };
+
+    // The longest virtual directory wins, whatever the case of the path
+    let resolver = IncludeResolver::new()
+        .with_web_root("/site")
+        .with_virtual_path("/lib", "/shared/lib")
+        .with_virtual_path("/lib/legacy/", "/old/lib");
+    assert_eq!(
+        resolve(&resolver, "/LIB/db.asp"),
+        Some(PathBuf::from("/shared/lib/db.asp"))
+    );
+    assert_eq!(
+        resolve(&resolver, "lib/legacy/db.asp"),
+        Some(PathBuf::from("/old/lib/db.asp"))
+    );
+    // Only whole path segments match
+    assert_eq!(
+        resolve(&resolver, "/library/db.asp"),
+        Some(PathBuf::from("/site/library/db.asp"))
+    );
+
+    // Without a web root, only the virtual directories resolve
+    let resolver = IncludeResolver::new().with_virtual_path("/lib", "/shared/lib");
+    assert_eq!(
+        resolve(&resolver, "/lib/db.asp"),
+        Some(PathBuf::from("/shared/lib/db.asp"))
+    );
+    assert_eq!(resolve(&resolver, "/db.asp"), None);
+}
+
+#[test]
+fn test_build_graph() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[
+            (
+                "orders.asp",
+                "<!--#include file=\"inc/header.asp\" -->\n<!--#include virtual=\"/lib/db.asp\" -->\n<% ListOrders %>",
+            ),
+            (
+                "inc/header.asp",
+                "<!--#include file=\"../lib/db.asp\" -->\n<h1>Shop</h1>",
+            ),
+            ("lib/db.asp", "<% Sub Connect() : End Sub %>"),
+        ],
+    );
+
+    let resolver = IncludeResolver::new().with_web_root(root);
+    let graph = IncludeGraph::build(&[root.join("orders.asp")], &resolver);
+    assert!(graph.errors.is_empty(), "{:?}", graph.errors);
+    assert_eq!(
+        relative_paths(&graph, root, &[0, 1, 2]),
+        ["orders.asp", "inc/header.asp", "lib/db.asp"]
+    );
+    assert_eq!(graph.edges.len(), 3);
+    assert_eq!(graph.includes_of(0).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(graph.included_by(2).collect::<Vec<_>>(), [0, 1]);
+    assert_eq!(graph.transitive_includes(0), [1, 2]);
+    assert_eq!(graph.file(&root.join("lib/../lib/db.asp")), Some(2));
+    assert!(graph.files[2].source.contains("Connect"));
+}
+
+#[test]
+fn test_missing_includes_are_reported() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[(
+            "page.asp",
+            "<!--#include file=\"missing.asp\" -->\n<!--#include virtual=\"/lib/db.asp\" -->",
+        )],
+    );
+
+    let graph = IncludeGraph::build(&[root.join("page.asp")], &IncludeResolver::new());
+    assert_eq!(graph.files.len(), 1);
+    assert!(graph.edges.is_empty());
+    assert!(matches!(
+        &graph.errors[..],
+        [
+            IncludeError::NotFound { path: missing, span, .. },
+            IncludeError::NoWebRoot { path: virtual_path, .. },
+        ] if missing == "missing.asp" && span.line == 1 && virtual_path == "/lib/db.asp"
+    ));
+}
+
+#[test]
+fn test_cycles_are_detected() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[
+            ("a.asp", "<!--#include file=\"b.asp\" -->"),
+            ("b.asp", "<!--#include file=\"c.asp\" -->"),
+            ("c.asp", "<!--#include file=\"a.asp\" -->"),
+            ("self.asp", "<!--#include file=\"self.asp\" -->"),
+        ],
+    );
+
+    let graph = IncludeGraph::build(
+        &[root.join("a.asp"), root.join("self.asp")],
+        &IncludeResolver::new(),
+    );
+    let cycles = graph.cycles();
+    assert_eq!(cycles.len(), 2);
+    assert_eq!(
+        relative_paths(&graph, root, &cycles[0]),
+        ["a.asp", "b.asp", "c.asp", "a.asp"]
+    );
+    assert_eq!(
+        relative_paths(&graph, root, &cycles[1]),
+        ["self.asp", "self.asp"]
+    );
+    assert_eq!(
+        graph
+            .errors
+            .iter()
+            .filter(|error| matches!(error, IncludeError::Cycle { .. }))
+            .count(),
+        2
+    );
+    // Following includes terminates despite the cycles
+    assert_eq!(
+        relative_paths(&graph, root, &graph.transitive_includes(0)),
+        ["b.asp", "c.asp"]
+    );
+}
@@ -0,0 +1,89 @@
+use std::fs;
+
+use asp_classic_parser::parser;
+
+/// Pages mixing HTML, code blocks spanning several ASP blocks, syntax errors,
+/// tabs and multi-byte characters
+const PAGES: [&str; 5] = [
+    "<html>\n<% Dim total\ntotal = 0 %>\n<p>Café</p>\n<% For i = 1 To 3\n  total = total + i %>\n<li><%= i %></li>\n<% Next %>\n<!-- footer -->\n<p><% Response.Write total %></p>\n</html>",
+    "<%@ Language=\"VBScript\" %>\n<p>\t<% If x Then %>yes<% Else %>no<% End If %></p>\n<% Sub Show(name)\n\tResponse.Write \"é\" & name\nEnd Sub %>\n<% Call Show(\"a\") %>",
+    "<p>start</p>\n<% x = %>\n<p>middle</p>\n<% y = 1 : z = (2 %>\n<p>end</p>\n<% Dim %>\n",
+    "<% Class Cart\n  Private items\n  Public Sub Add(item)\n    items = items & item\n  End Sub\nEnd Class %>\r\n<div><% Set cart = New Cart %></div>\r\n<% cart.Add \"x\" %>\r\n",
+    "<p><!-- open\n<% x = 1 %>\n<p>a</p><% y = \"%>\" %>\n<% ' note %>tail<%= y %>\n<% Select Case y %>\n<% Case 1 %>one<% End Select %>",
+];
+
+/// Returns the pages obtained by inserting or removing text at each position of
+/// a page
+fn edits(page: &str) -> Vec<String> {
+    let boundaries: Vec<usize> = (0..=page.len())
+        .filter(|&offset| page.is_char_boundary(offset))
+        .collect();
+    let mut edited = Vec::new();
+    for &offset in &boundaries {
+        for text in ["x", " + 1", "\n", "\"", "%>", "<%", "-->", "End If"] {
+            edited.push(format!("{}{}{}", &page[..offset], text, &page[offset..]));
+        }
+    }
+    for pair in boundaries.windows(2) {
+        edited.push(format!("{}{}", &page[..pair[0]], &page[pair[1]..]));
+    }
+    for window in boundaries.windows(6) {
+        edited.push(format!("{}{}", &page[..window[0]], &page[window[5]..]));
+    }
+    edited
+}
+
+#[test]
+fn test_reparse_matches_full_parse() {
+    for page in PAGES {
+        let previous = parser::parse_recovering(page);
+        for edited in edits(page) {
+            assert_eq!(
+                parser::reparse_recovering(&previous, page, &edited),
+                parser::parse_recovering(&edited),
+                "{:?} edited into {:?}",
+                page,
+                edited
+            );
+        }
+    }
+}
+
+#[test]
+fn test_reparse_after_several_edits() {
+    // Each result is the previous parse of the next edit
+    let mut page = PAGES[0].to_string();
+    let mut result = parser::parse_recovering(&page);
+    for (find, replace) in [
+        ("total = 0", "total = 10"),
+        ("total + i", "total + i * 2\n  count = count + 1"),
+        ("Café", "Menu"),
+        ("Response.Write total", "Response.Write total & \"!\""),
+        ("<% Next %>", "<% Next\nDone %>"),
+    ] {
+        let edited = page.replacen(find, replace, 1);
+        result = parser::reparse_recovering(&result, &page, &edited);
+        assert_eq!(result, parser::parse_recovering(&edited), "{:?}", edited);
+        page = edited;
+    }
+}
+
+#[test]
+fn test_reparse_fixtures() {
+    for entry in fs::read_dir("fixtures/passing").expect("Failed to read fixtures") {
+        let path = entry.expect("Failed to read fixture").path();
+        let page = fs::read_to_string(&path).expect("Failed to read fixture");
+        let previous = parser::parse_recovering(&page);
+        // A statement added at the end of each block
+        for (offset, _) in page.match_indices("%>") {
+            let edited = format!("{}\nx = 1\n{}", &page[..offset], &page[offset..]);
+            assert_eq!(
+                parser::reparse_recovering(&previous, &page, &edited),
+                parser::parse_recovering(&edited),
+                "{} edited at {}",
+                path.display(),
+                offset
+            );
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use asp_classic_parser::analysis::{SymbolKind, WorkspaceIndex};
+use asp_classic_parser::includes::{IncludeGraph, IncludeResolver};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+/// Writes files given as (relative path, content) pairs under a directory
+fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (path, content) in files {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create directory");
+        fs::write(path, content).expect("Failed to write file");
+    }
+}
+
+/// Returns the file name of a path
+fn file_name(path: &Path) -> &str {
+    path.file_name().unwrap().to_str().unwrap()
+}
+
+const ORDERS: &str = "<%@ Language=\"VBScript\" %>\n<!--#include virtual=\"/lib/db.asp\" -->\n<%\nOption Explicit\nDim rs\nSet rs = Query(\"SELECT * FROM orders\")\nRender rs\nMissing\n%>";
+const DB: &str = "<!--#include file=\"config.asp\" -->\n<%\nFunction Query(sql)\n  Set Query = OpenConnection(CONNECTION).Execute(sql)\nEnd Function\n%>";
+const CONFIG: &str = "<%\nConst CONNECTION = \"Provider=SQLOLEDB\"\nFunction OpenConnection(connection)\nEnd Function\n%>";
+
+#[test]
+fn test_definitions_in_included_files() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(root, &[("lib/db.asp", DB), ("lib/config.asp", CONFIG)]);
+
+    let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root(root));
+    let page = root.join("orders.asp");
+    index.update(&page, ORDERS);
+    index.load_includes(&page);
+    assert_eq!(index.files().count(), 3);
+
+    let query = index.definition(&page, "query").unwrap();
+    assert_eq!(file_name(query.path), "db.asp");
+    assert_eq!(query.symbol.kind, SymbolKind::Function);
+    let connection = index.definition(&page, "CONNECTION").unwrap();
+    assert_eq!(file_name(connection.path), "config.asp");
+    assert!(index.definition(&page, "Missing").is_none());
+
+    let undeclared: Vec<String> = index
+        .undeclared(&page)
+        .into_iter()
+        .map(|reference| reference.name)
+        .collect();
+    assert_eq!(undeclared, ["Render", "Missing"]);
+}
+
+#[test]
+fn test_definition_at() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(root, &[("lib/db.asp", DB), ("lib/config.asp", CONFIG)]);
+
+    let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root(root));
+    let page = root.join("orders.asp");
+    index.update(&page, ORDERS);
+    index.load_includes(&page);
+
+    // Returns the file and text of the definition of the name at an offset
+    let definition = |path: &Path, offset: usize| {
+        index.definition_at(path, offset).map(|location| {
+            let source = &index.file(location.path).unwrap().source;
+            (
+                file_name(location.path).to_string(),
+                location.span.as_str(source).to_string(),
+            )
+        })
+    };
+    let db = root.join("lib/db.asp");
+
+    let query = ORDERS.find("Query(").unwrap() + 2;
+    assert_eq!(
+        definition(&page, query),
+        Some(("db.asp".to_string(), "Query".to_string()))
+    );
+    let rs = ORDERS.rfind("rs\n").unwrap();
+    assert_eq!(
+        definition(&page, rs),
+        Some(("orders.asp".to_string(), "rs".to_string()))
+    );
+    assert_eq!(definition(&page, ORDERS.find("Missing").unwrap()), None);
+    assert_eq!(
+        definition(&page, ORDERS.find("#include").unwrap()),
+        Some(("db.asp".to_string(), String::new()))
+    );
+
+    // Parameters, and constants of the files included by an include
+    let sql = DB.rfind("sql)").unwrap();
+    assert_eq!(
+        definition(&db, sql),
+        Some(("db.asp".to_string(), "sql".to_string()))
+    );
+    let connection = DB.find("CONNECTION").unwrap();
+    assert_eq!(
+        definition(&db, connection),
+        Some(("config.asp".to_string(), "CONNECTION".to_string()))
+    );
+}
+
+#[test]
+fn test_references() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(root, &[("lib/db.asp", DB), ("lib/config.asp", CONFIG)]);
+    let report = "<!--#include virtual=\"/lib/db.asp\" -->\n<%\nSet r = query(\"SELECT 1\")\nSet f = GetRef(\"Query\")\n%>";
+    let other = "<%\nFunction Query(sql)\nEnd Function\nQuery \"x\"\n%>";
+    write_files(
+        root,
+        &[
+            ("orders.asp", ORDERS),
+            ("report.asp", report),
+            ("other.asp", other),
+        ],
+    );
+
+    let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root(root));
+    for page in ["orders.asp", "report.asp", "other.asp"] {
+        let page = root.join(page);
+        index.update(&page, &fs::read_to_string(&page).unwrap());
+        index.load_includes(&page);
+    }
+
+    // Returns the file, line and text of the references to the name at an offset
+    let references = |path: &Path, offset: usize, include_declaration: bool| {
+        index
+            .references(path, offset, include_declaration)
+            .into_iter()
+            .map(|location| {
+                let source = &index.file(location.path).unwrap().source;
+                (
+                    file_name(location.path).to_string(),
+                    location.span.line,
+                    location.span.as_str(source).to_string(),
+                )
+            })
+            .collect::<Vec<_>>()
+    };
+    let reference =
+        |file: &str, line: usize, text: &str| (file.to_string(), line, text.to_string());
+
+    // The function of db.asp, used by the pages including it but not by the
+    // page declaring its own
+    let db = root.join("lib/db.asp");
+    let mut found = references(&db, DB.find("Query(").unwrap(), true);
+    found.sort();
+    assert_eq!(
+        found,
+        [
+            reference("db.asp", 3, "Query"),
+            reference("db.asp", 4, "Query"),
+            reference("orders.asp", 6, "Query"),
+            reference("report.asp", 3, "query"),
+            reference("report.asp", 4, "Query"),
+        ]
+    );
+    let page = root.join("orders.asp");
+    assert_eq!(
+        references(&page, ORDERS.find("Query(").unwrap(), false).len(),
+        4
+    );
+
+    // Parameters are only used in their procedure
+    assert_eq!(
+        references(&db, DB.find("sql").unwrap(), true),
+        [reference("db.asp", 3, "sql"), reference("db.asp", 4, "sql")]
+    );
+    assert!(references(&page, ORDERS.find("Missing").unwrap(), true).is_empty());
+}
+
+#[test]
+fn test_included_files_see_their_includers() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[
+            (
+                "page.asp",
+                "<!--#include file=\"inc/render.asp\" -->\n<% Sub Log(message) : End Sub %>",
+            ),
+            (
+                "inc/render.asp",
+                "<% Option Explicit\nSub Render()\n  Log \"render\"\nEnd Sub %>",
+            ),
+        ],
+    );
+
+    let graph = IncludeGraph::build(&[root.join("page.asp")], &IncludeResolver::new());
+    let index = WorkspaceIndex::from_graph(&graph, IncludeResolver::new());
+    let include = root.join("inc/render.asp");
+    let visible: Vec<&str> = index
+        .visible_files(&include)
+        .iter()
+        .map(|file| file_name(&file.path))
+        .collect();
+    assert_eq!(visible, ["render.asp", "page.asp"]);
+    assert_eq!(
+        file_name(index.definition(&include, "Log").unwrap().path),
+        "page.asp"
+    );
+    assert!(index.undeclared(&include).is_empty());
+}
+
+#[test]
+fn test_search_and_update() {
+    let mut index = WorkspaceIndex::default();
+    let page = Path::new("/site/cart.asp");
+    index.update(
+        page,
+        "<%\nClass Cart\n  Public Function Total()\n  End Function\nEnd Class\nFunction CartTotal()\nEnd Function\n%>",
+    );
+
+    let found: Vec<(&str, Option<&str>)> = index
+        .search("total")
+        .iter()
+        .map(|location| {
+            (
+                location.symbol.name.as_str(),
+                location.container.map(|class| class.name.as_str()),
+            )
+        })
+        .collect();
+    assert_eq!(found, [("Total", Some("Cart")), ("CartTotal", None)]);
+
+    index.update(page, "<% Sub Checkout() : End Sub %>");
+    assert!(index.search("total").is_empty());
+    assert_eq!(index.search("").len(), 1);
+
+    index.remove(page);
+    assert_eq!(index.files().count(), 0);
+}
+
+#[test]
+fn test_global_asa_objects() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[
+            (
+                "global.asa",
+                "<object runat=\"server\" scope=\"Session\" id=\"Cart\" progid=\"Shop.Cart\"></object>\n<script language=\"VBScript\" runat=\"server\">\nSub Session_OnStart\nEnd Sub\n</script>",
+            ),
+            ("admin/global.asa", "<script runat=\"server\">\n</script>"),
+        ],
+    );
+
+    // The global.asa of the application is loaded with the includes
+    let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root(root));
+    let page = root.join("checkout.asp");
+    index.update(&page, "<%\nOption Explicit\nCart.Add 1\nBasket.Add 1\n%>");
+    index.load_includes(&page);
+    assert_eq!(index.files().count(), 2);
+    let cart = index.definition(&page, "cart").unwrap();
+    assert_eq!(file_name(cart.path), "global.asa");
+    assert_eq!(cart.symbol.kind, SymbolKind::Object);
+    let undeclared: Vec<String> = index
+        .undeclared(&page)
+        .into_iter()
+        .map(|reference| reference.name)
+        .collect();
+    assert_eq!(undeclared, ["Basket"]);
+
+    // Pages of a nested application only see their own global.asa
+    let admin_page = root.join("admin/users.asp");
+    index.update(&admin_page, "<% Cart.Add 1 %>");
+    index.load_includes(&admin_page);
+    assert_eq!(index.files().count(), 4);
+    assert_eq!(
+        index.application_file(&admin_page).unwrap().path,
+        fs::canonicalize(root.join("admin/global.asa")).unwrap()
+    );
+    assert!(index.definition(&admin_page, "Cart").is_none());
+
+    // Removing the nested global.asa makes the outer one apply again
+    index.remove(&root.join("admin/global.asa"));
+    assert_eq!(
+        index.application_file(&admin_page).unwrap().path,
+        fs::canonicalize(root.join("global.asa")).unwrap()
+    );
+    assert!(index.definition(&admin_page, "Cart").is_some());
+}
+
+#[test]
+fn test_set_resolver() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    write_files(
+        root,
+        &[("shared/lib/db.asp", DB), ("shared/lib/config.asp", CONFIG)],
+    );
+
+    let mut index = WorkspaceIndex::new(IncludeResolver::new().with_web_root(root));
+    let page = root.join("orders.asp");
+    index.update(&page, ORDERS);
+    index.load_includes(&page);
+    assert_eq!(index.unresolved_includes(&page).len(), 1);
+
+    // Mapping the virtual directory resolves the include of the indexed page
+    index.set_resolver(
+        IncludeResolver::new()
+            .with_web_root(root)
+            .with_virtual_path("/lib", root.join("shared/lib")),
+    );
+    index.load_includes(&page);
+    assert!(index.unresolved_includes(&page).is_empty());
+    assert_eq!(
+        file_name(index.definition(&page, "Query").unwrap().path),
+        "db.asp"
+    );
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, ScriptLanguage};
+
+#[test]
+fn test_jscript_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/jscript.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read JScript fixture");
+
+    assert_eq!(parser::detect_language(&content), ScriptLanguage::JScript);
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing JScript fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_jscript_unbalanced_brackets_fail() {
+    let fixture_path = Path::new("fixtures/failing/jscript_unbalanced.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read invalid JScript fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_err(),
+        "Unbalanced JScript brackets were parsed successfully, but should have failed"
+    );
+}
+
+#[test]
+fn test_jscript_unterminated_string_fails() {
+    let result = parser::parse("<%@ Language=JScript %>\n<% var s = \"open; %>");
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_language_directive_selects_grammar() {
+    let code = "<% /* comment */ var x = { a: [1, 2] }; %>";
+
+    let jscript_page = format!("<%@ Language=\"JScript\" %>\n{}", code);
+    assert!(parser::parse(&jscript_page).is_ok());
+
+    let vbscript_page = format!("<%@ Language=\"VBScript\" %>\n{}", code);
+    assert_eq!(
+        parser::detect_language(&vbscript_page),
+        ScriptLanguage::VBScript
+    );
+}
+
+#[test]
+fn test_language_detection() {
+    assert_eq!(
+        parser::detect_language("<%@ LANGUAGE=javascript %>"),
+        ScriptLanguage::JScript
+    );
+    assert_eq!(
+        parser::detect_language("  \n<%@ CodePage=65001 Language='JScript' %>"),
+        ScriptLanguage::JScript
+    );
+    // The directive is only honored as the first tag of the page
+    assert_eq!(
+        parser::detect_language("<html><%@ Language=\"JScript\" %>"),
+        ScriptLanguage::VBScript
+    );
+    assert_eq!(
+        parser::detect_language("<% Response.Write \"x\" %>"),
+        ScriptLanguage::VBScript
+    );
+}
@@ -0,0 +1,178 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{
+    self,
+    lexer::{Token, TokenKind},
+};
+
+/// Returns the kind and text of the tokens of a page, whitespace and newlines
+/// excluded
+fn significant(page: &str) -> Vec<(TokenKind, &str)> {
+    parser::tokenize(page)
+        .into_iter()
+        .filter(|token| !matches!(token.kind, TokenKind::Whitespace | TokenKind::Newline))
+        .map(|token| (token.kind, token.text))
+        .collect()
+}
+
+fn concatenate(tokens: &[Token]) -> String {
+    tokens.iter().map(|token| token.text).collect()
+}
+
+#[test]
+fn test_fixtures_round_trip() {
+    let mut count = 0;
+    for directory in ["fixtures/passing", "fixtures/failing"] {
+        for entry in fs::read_dir(Path::new(directory)).unwrap() {
+            let path = entry.unwrap().path();
+            let content = fs::read_to_string(&path).unwrap();
+            let tokens = parser::tokenize(&content);
+            assert_eq!(
+                concatenate(&tokens),
+                content,
+                "{} does not round-trip",
+                path.display()
+            );
+            count += 1;
+        }
+    }
+    assert!(count > 0, "No fixtures found");
+}
+
+#[test]
+fn test_vbscript_tokens() {
+    use TokenKind::*;
+
+    let tokens = significant(
+        "<% If x <> &HFF And d = #1/31/2024# Then [my var] = \"a \"\"b\"\" c\" & 1.5E3 ' done %>",
+    );
+    assert_eq!(
+        tokens,
+        vec![
+            (OpenTag, "<%"),
+            (Keyword, "If"),
+            (Identifier, "x"),
+            (Operator, "<>"),
+            (Number, "&HFF"),
+            (Operator, "And"),
+            (Identifier, "d"),
+            (Operator, "="),
+            (Date, "#1/31/2024#"),
+            (Keyword, "Then"),
+            (Identifier, "[my var]"),
+            (Operator, "="),
+            (String, "\"a \"\"b\"\" c\""),
+            (Operator, "&"),
+            (Number, "1.5E3"),
+            (Comment, "' done "),
+            (CloseTag, "%>"),
+        ]
+    );
+}
+
+#[test]
+fn test_markup_tokens() {
+    use TokenKind::*;
+
+    let page = "<%@ Language=\"VBScript\" %>\n<!-- <% x %> --><p><%= Name %></p><!--#include file=\"a.asp\"-->";
+    let tokens = significant(page);
+    assert_eq!(
+        tokens,
+        vec![
+            (OpenDirectiveTag, "<%@"),
+            (Identifier, "Language"),
+            (Operator, "="),
+            (String, "\"VBScript\""),
+            (CloseTag, "%>"),
+            (Html, "\n"),
+            (HtmlComment, "<!-- <% x %> -->"),
+            (Html, "<p>"),
+            (OpenExpressionTag, "<%="),
+            (Identifier, "Name"),
+            (CloseTag, "%>"),
+            (Html, "</p><!--#include file=\"a.asp\"-->"),
+        ]
+    );
+}
+
+#[test]
+fn test_trivia_tokens_and_spans() {
+    let page = "<%\r\nx = _\r\n  1 REM one\n%>";
+    let tokens = parser::tokenize(page);
+    assert_eq!(concatenate(&tokens), page);
+
+    let kinds: Vec<TokenKind> = tokens.iter().map(|token| token.kind).collect();
+    assert!(kinds.contains(&TokenKind::LineContinuation));
+    assert_eq!(kinds[1], TokenKind::Newline);
+    assert_eq!(tokens[1].text, "\r\n");
+
+    let one = tokens.iter().find(|token| token.text == "1").unwrap();
+    assert_eq!((one.span.line, one.span.column), (3, 3));
+    let comment = tokens.iter().find(|token| token.kind == TokenKind::Comment);
+    assert_eq!(comment.map(|token| token.text), Some("REM one"));
+}
+
+#[test]
+fn test_jscript_tokens() {
+    use TokenKind::*;
+
+    let tokens = significant(
+        "<%@ Language=\"JScript\" %><% var s = 'it\\'s'; if (n !== 0x1F) { n++; } // end\n%>",
+    );
+    assert_eq!(
+        tokens[5..],
+        [
+            (OpenTag, "<%"),
+            (Keyword, "var"),
+            (Identifier, "s"),
+            (Operator, "="),
+            (String, "'it\\'s'"),
+            (Punctuation, ";"),
+            (Keyword, "if"),
+            (Punctuation, "("),
+            (Identifier, "n"),
+            (Operator, "!=="),
+            (Number, "0x1F"),
+            (Punctuation, ")"),
+            (Punctuation, "{"),
+            (Identifier, "n"),
+            (Operator, "++"),
+            (Punctuation, ";"),
+            (Punctuation, "}"),
+            (Comment, "// end"),
+            (CloseTag, "%>"),
+        ]
+    );
+}
+
+#[test]
+fn test_server_script_blocks() {
+    use TokenKind::*;
+
+    let page = "<script>var a;</script>\n<SCRIPT language=\"JScript\" runat=\"server\">\nvar n = 1;\n</Script >\n<script runat=server>Dim x</script>";
+    let tokens = significant(page);
+    assert_eq!(tokens[0], (Html, "<script>var a;</script>\n"));
+    assert_eq!(
+        tokens[1],
+        (
+            ScriptOpenTag,
+            "<SCRIPT language=\"JScript\" runat=\"server\">"
+        )
+    );
+    assert_eq!(tokens[2], (Keyword, "var"));
+    assert_eq!(tokens[7], (ScriptCloseTag, "</Script >"));
+    assert_eq!(tokens[10..12], [(Keyword, "Dim"), (Identifier, "x")]);
+    assert_eq!(tokens[12], (ScriptCloseTag, "</script>"));
+}
+
+#[test]
+fn test_unterminated_code_is_tokenized() {
+    let page = "<p><% x = \"open";
+    let tokens = parser::tokenize(page);
+    assert_eq!(concatenate(&tokens), page);
+    assert_eq!(
+        tokens.last().map(|token| token.kind),
+        Some(TokenKind::String)
+    );
+}
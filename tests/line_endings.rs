@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, AspParser, Rule};
+use pest::Parser;
+
+/// Returns the rules of all statements recognized in the given ASP code
+fn statement_rules(asp_code: &str) -> Vec<Rule> {
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("ASP code should parse");
+    pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::statement)
+        .filter_map(|pair| pair.into_inner().next().map(|inner| inner.as_rule()))
+        .collect()
+}
+
+fn read_fixture(name: &str) -> String {
+    let fixture_path = Path::new("fixtures/passing").join(name);
+    fs::read_to_string(&fixture_path)
+        .unwrap_or_else(|_| panic!("Failed to read fixture {}", fixture_path.display()))
+}
+
+#[test]
+fn test_line_ending_fixtures_parsing() {
+    for name in [
+        "line_endings_crlf.asp",
+        "line_endings_cr.asp",
+        "line_endings_mixed.asp",
+    ] {
+        let content = read_fixture(name);
+        let result = parser::parse(&content);
+        assert!(
+            result.is_ok(),
+            "Parsing {} failed with error: {:?}",
+            name,
+            result.err()
+        );
+    }
+}
+
+#[test]
+fn test_line_endings_parse_identically() {
+    let lf = read_fixture("line_endings_crlf.asp").replace("\r\n", "\n");
+    let expected = statement_rules(&lf);
+    assert!(!expected.is_empty());
+
+    for name in [
+        "line_endings_crlf.asp",
+        "line_endings_cr.asp",
+        "line_endings_mixed.asp",
+    ] {
+        assert_eq!(
+            statement_rules(&read_fixture(name)),
+            expected,
+            "{} should produce the same statements as the LF version",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_fixtures_keep_their_line_endings() {
+    // Guard against line ending conversion on checkout
+    assert!(read_fixture("line_endings_crlf.asp").contains("\r\n"));
+    assert!(!read_fixture("line_endings_cr.asp").contains('\n'));
+}
+
+#[test]
+fn test_error_line_with_cr_only_line_endings() {
+    let asp_code = "<%\rDim x\rx = 1\r<%\r%>";
+    let error = parser::parse(asp_code).expect_err("Nested <% should fail");
+    assert!(
+        error.to_string().contains("line 4"),
+        "Error should be reported on line 4: {}",
+        error
+    );
+}
@@ -0,0 +1,863 @@
+use asp_classic_parser::analysis::WorkspaceIndex;
+use asp_classic_parser::includes::{IncludeGraph, IncludeResolver};
+use asp_classic_parser::lint::{self, LintContext, Registry, Rule};
+use asp_classic_parser::parser::{self, Diagnostic, DiagnosticCode, Severity};
+use std::fs;
+use tempfile::tempdir;
+
+/// Returns the names reported as undeclared in a page
+fn undeclared(source: &str) -> Vec<String> {
+    lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == DiagnosticCode::UndeclaredName)
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn test_undeclared_names() {
+    let source = "<%\nOption Explicit\nDim total\nSub Add(amount)\n  total = total + amount + tax\nEnd Sub\nAdd count\nResponse.Write Len(total)\n%>";
+    assert_eq!(
+        undeclared(source),
+        ["'tax' is not declared", "'count' is not declared"]
+    );
+
+    // Without Option Explicit, names are declared by their first use
+    assert!(undeclared("<%\nDim total\ntotal = count + 1\n%>").is_empty());
+    // Names may be declared in an include that cannot be read
+    assert!(
+        undeclared("<!--#include file=\"lib.asp\" -->\n<%\nOption Explicit\nConnect\n%>")
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_syntax_errors_are_returned() {
+    let diagnostics = lint::lint("<%\nOption Explicit\nIf x Then\n%>", &Registry::default());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::UnclosedBlock);
+}
+
+#[test]
+fn test_registry_severities() {
+    let source = "<%\nOption Explicit\nx = 1\n%>";
+    let mut registry = Registry::default();
+    assert!(registry.rule(DiagnosticCode::UndeclaredName).is_some());
+    assert_eq!(lint::lint(source, &registry)[0].severity, Severity::Error);
+
+    registry.set_severity(DiagnosticCode::UndeclaredName, Severity::Notice);
+    assert_eq!(lint::lint(source, &registry)[0].severity, Severity::Notice);
+
+    registry.disable(DiagnosticCode::UndeclaredName);
+    assert_eq!(registry.severity(DiagnosticCode::UndeclaredName), None);
+    assert!(lint::lint(source, &registry).is_empty());
+}
+
+/// Reports every `Response.End` call
+struct NoResponseEnd;
+
+impl Rule for NoResponseEnd {
+    fn code(&self) -> DiagnosticCode {
+        DiagnosticCode::MisplacedStatement
+    }
+
+    fn description(&self) -> &'static str {
+        "Response.End stops the page"
+    }
+
+    fn check(&self, context: &LintContext<'_>) -> Vec<Diagnostic> {
+        context
+            .source
+            .match_indices("Response.End")
+            .map(|(start, text)| {
+                let span = asp_classic_parser::parser::ast::Span {
+                    start,
+                    end: start + text.len(),
+                    line: context.source[..start].matches('\n').count() + 1,
+                    column: 1,
+                };
+                Diagnostic::new(self.code(), span, self.description())
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_custom_rule() {
+    let mut registry = Registry::new();
+    registry.register(Box::new(NoResponseEnd));
+    assert_eq!(registry.rules().count(), 1);
+
+    let diagnostics = lint::lint("<%\nx = 1\nResponse.End\n%>", &registry);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].span.line, 3);
+    assert_eq!(diagnostics[0].message, "Response.End stops the page");
+}
+
+#[test]
+fn test_lint_file_with_includes() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    let page = root.join("orders.asp");
+    fs::create_dir(root.join("lib")).expect("Failed to create directory");
+    fs::write(
+        root.join("lib/db.asp"),
+        "<%\nFunction Query(sql)\nEnd Function\n%>",
+    )
+    .expect("Failed to write file");
+    let source = "<!--#include virtual=\"/lib/db.asp\" -->\n<%\nOption Explicit\nDim rs\nrs = Query(\"SELECT 1\")\nMissing rs\n%>";
+    fs::write(&page, source).expect("Failed to write file");
+
+    let resolver = IncludeResolver::new().with_web_root(root);
+    let graph = IncludeGraph::build(std::slice::from_ref(&page), &resolver);
+    let index = WorkspaceIndex::from_graph(&graph, resolver);
+    assert!(index.unresolved_includes(&page).is_empty());
+
+    let diagnostics = lint::lint_file(&page, source, &Registry::default(), &index);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "'Missing' is not declared");
+    assert_eq!(diagnostics[0].span.line, 6);
+
+    // The syntax tree of the editor gives the same problems
+    let parse = parser::parse_recovering(source);
+    assert!(parse.diagnostics.is_empty());
+    let from_program =
+        lint::lint_program(&page, source, &parse.program, &Registry::default(), &index);
+    assert_eq!(from_program, diagnostics);
+}
+
+/// Returns the messages of the diagnostics with a code
+fn messages(source: &str, code: DiagnosticCode) -> Vec<String> {
+    lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == code)
+        .map(|diagnostic| diagnostic.message)
+        .collect()
+}
+
+#[test]
+fn test_unused_variables() {
+    let source = "<%\nDim shown, written : Dim unused\nwritten = 1\nResponse.Write shown\nSub Render(items)\n  Dim item, count, rows(10)\n  For Each item In items\n    count = count + 1\n  Next\n  rows(0) = 1\nEnd Sub\nClass Cart\n  Private total\nEnd Class\n%>";
+    let diagnostics: Vec<Diagnostic> = lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == DiagnosticCode::UnusedVariable)
+        .collect();
+    let names: Vec<(&str, usize, usize)> = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), d.span.line, d.span.column))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("Variable 'written' is declared but never read", 2, 12),
+            ("Variable 'unused' is declared but never read", 2, 26),
+            ("Variable 'item' is declared but never read", 6, 7),
+            ("Variable 'rows' is declared but never read", 6, 20),
+        ]
+    );
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+
+    // Page-level variables may be read by an included file
+    assert!(
+        messages(
+            "<!--#include file=\"footer.asp\" -->\n<%\nDim title\ntitle = \"Home\"\n%>",
+            DiagnosticCode::UnusedVariable
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_unused_parameters() {
+    let source = "<%\nFunction Format(value, unused)\n  Format = value\nEnd Function\nSub Load(ByRef result, options)\n  result = 1\nEnd Sub\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::UnusedParameter),
+        [
+            "Parameter 'unused' of 'Format' is never used",
+            "Parameter 'options' of 'Load' is never used",
+        ]
+    );
+}
+
+#[test]
+fn test_page_variables_read_by_included_files() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    let page = root.join("default.asp");
+    fs::write(root.join("header.asp"), "<title><%= title %></title>")
+        .expect("Failed to write file");
+    let source = "<%\nDim title, unused\ntitle = \"Home\"\nunused = 1\n%>\n<!--#include file=\"header.asp\" -->";
+    fs::write(&page, source).expect("Failed to write file");
+
+    let resolver = IncludeResolver::new();
+    let graph = IncludeGraph::build(std::slice::from_ref(&page), &resolver);
+    let index = WorkspaceIndex::from_graph(&graph, resolver);
+    let diagnostics = lint::lint_file(&page, source, &Registry::default(), &index);
+    let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+    assert_eq!(messages, ["Variable 'unused' is declared but never read"]);
+}
+
+#[test]
+fn test_unreachable_code() {
+    let source = "<%\nSub Save(record)\n  If record Is Nothing Then\n    Exit Sub\n    record.Reset\n  End If\n  record.Update\nEnd Sub\nFunction Check(value)\n  Err.Raise 5\n  Dim ignored\n  Check = value\nEnd Function\nSub Tolerant()\n  On Error Resume Next\n  Err.Raise 5\n  Response.Write Err.Number\nEnd Sub\nIf Request(\"id\") = \"\" Then\n  Response.Redirect \"/\"\n  Response.End\nEnd If\nResponse.End\n%>\n<p>Never sent</p>";
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|diagnostic| diagnostic.code == DiagnosticCode::UnreachableCode)
+        .map(|diagnostic| (diagnostic.message, diagnostic.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            ("Unreachable code after 'Exit Sub'".to_string(), 5),
+            ("Unreachable code after 'Err.Raise'".to_string(), 12),
+            ("Unreachable code after 'Response.End'".to_string(), 24),
+        ]
+    );
+}
+
+#[test]
+fn test_unused_procedures() {
+    let source = "<%\nSub Main()\n  Render\nEnd Sub\nSub Render()\nEnd Sub\nFunction Factorial(n)\n  Factorial = n * Factorial(n - 1)\nEnd Function\nSub Session_OnStart()\nEnd Sub\nMain\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::UnusedProcedure),
+        ["Function 'Factorial' is never called"]
+    );
+
+    // Procedures of a page with includes may be called by the included files
+    assert!(
+        messages(
+            "<!--#include file=\"lib.asp\" -->\n<%\nSub Callback()\nEnd Sub\n%>",
+            DiagnosticCode::UnusedProcedure
+        )
+        .is_empty()
+    );
+}
+
+#[test]
+fn test_procedures_called_from_other_files() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    let page = root.join("default.asp");
+    let lib = root.join("lib.asp");
+    let lib_source = "<%\nFunction Query(sql)\nEnd Function\nFunction Unused()\nEnd Function\n%>";
+    fs::write(&lib, lib_source).expect("Failed to write file");
+    fs::write(
+        &page,
+        "<!--#include file=\"lib.asp\" -->\n<% Query \"SELECT 1\" %>",
+    )
+    .expect("Failed to write file");
+
+    let resolver = IncludeResolver::new();
+    let graph = IncludeGraph::build(std::slice::from_ref(&page), &resolver);
+    let index = WorkspaceIndex::from_graph(&graph, resolver);
+    let diagnostics = lint::lint_file(&lib, lib_source, &Registry::default(), &index);
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::UnusedProcedure)
+        .map(|d| d.message.as_str())
+        .collect();
+    assert_eq!(messages, ["Function 'Unused' is never called"]);
+}
+
+#[test]
+fn test_missing_set() {
+    let source = "<%\nDim conn, cart, items, rs, text\nconn = Server.CreateObject(\"ADODB.Connection\")\ncart = New ShoppingCart\nitems = CreateObject(\"Scripting.Dictionary\")\nSet rs = conn.Execute(\"SELECT 1\")\nrs = Nothing\ntext = conn.ConnectionString\nResponse.Write cart & items & rs & text\n%>";
+    let diagnostics = lint::lint(source, &Registry::default());
+    let missing: Vec<(&str, usize)> = diagnostics
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::MissingSet)
+        .map(|d| (d.message.as_str(), d.span.line))
+        .collect();
+    assert_eq!(
+        missing,
+        [
+            ("Server.CreateObject result is assigned without 'Set'", 3),
+            ("New ShoppingCart is assigned without 'Set'", 4),
+            ("CreateObject result is assigned without 'Set'", 5),
+            ("Nothing is assigned without 'Set'", 7),
+        ]
+    );
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
+
+#[test]
+fn test_set_on_value() {
+    let source = "<%\nDim count, name, conn\nSet count = 0\nSet name = \"a\" & \"b\"\nSet conn = Nothing\nSet conn = Application(\"conn\")\nResponse.Write count & name & conn\n%>";
+    let lines: Vec<usize> = lint::lint(source, &Registry::default())
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::SetOnValue)
+        .map(|d| d.span.line)
+        .collect();
+    assert_eq!(lines, [3, 4]);
+}
+
+#[test]
+fn test_duplicate_declarations() {
+    let source = "<%\nDim total, count\nDim total\nConst count = 1\nSub Add(value)\n  Dim value\nEnd Sub\nSub Add()\nEnd Sub\nClass Cart\n  Property Get Items()\n  End Property\n  Property Let Items(value)\n  End Property\nEnd Class\n%>";
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|d| d.code == DiagnosticCode::DuplicateDeclaration)
+        .map(|d| (d.message, d.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            ("'total' is already declared at line 2".to_string(), 3),
+            ("'count' is already declared at line 2".to_string(), 4),
+            ("'value' is already declared at line 5".to_string(), 6),
+            ("'Add' is already declared at line 5".to_string(), 8),
+        ]
+    );
+}
+
+#[test]
+fn test_shadowed_variables() {
+    let source = "<%\nDim total\nSub Add(total)\nEnd Sub\nSub Reset()\n  Dim total\nEnd Sub\nClass Cart\n  Private items\n  Sub Clear()\n    Dim items\n  End Sub\nEnd Class\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::ShadowedVariable),
+        [
+            "The parameter 'total' hides the variable declared at line 2",
+            "The variable 'total' hides the variable declared at line 2",
+            "The variable 'items' hides the variable declared at line 9",
+        ]
+    );
+}
+
+#[test]
+fn test_related_locations() {
+    let source = "<%\nDim total\nDim total\nSub Add(total)\nEnd Sub\n%>";
+    let related: Vec<(DiagnosticCode, Option<std::path::PathBuf>, usize, String)> =
+        lint::lint(source, &Registry::default())
+            .into_iter()
+            .flat_map(|d| {
+                d.related
+                    .into_iter()
+                    .map(move |related| (d.code, related.path, related.span.line, related.message))
+            })
+            .collect();
+    assert_eq!(
+        related,
+        [
+            (
+                DiagnosticCode::DuplicateDeclaration,
+                None,
+                2,
+                "first declared here".to_string()
+            ),
+            (
+                DiagnosticCode::ShadowedVariable,
+                None,
+                2,
+                "variable declared here".to_string()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_name_conflicts() {
+    let source = "<%\nDim Total\nFunction Total()\nEnd Function\nClass Cart\n  Private count\n  Property Get Count()\n  End Property\nEnd Class\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::NameConflict),
+        [
+            "The function 'Total' has the same name as the variable at line 2",
+            "The property 'Count' has the same name as the variable at line 6",
+        ]
+    );
+}
+
+#[test]
+fn test_conflicts_with_included_files() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let root = dir.path();
+    let page = root.join("default.asp");
+    fs::write(root.join("config.asp"), "<%\nDim title, connection\n%>")
+        .expect("Failed to write file");
+    let source = "<!--#include file=\"config.asp\" -->\n<%\nSub Render(title)\nEnd Sub\nFunction Connection()\nEnd Function\n%>";
+    fs::write(&page, source).expect("Failed to write file");
+
+    let resolver = IncludeResolver::new();
+    let graph = IncludeGraph::build(std::slice::from_ref(&page), &resolver);
+    let index = WorkspaceIndex::from_graph(&graph, resolver);
+    let diagnostics = lint::lint_file(&page, source, &Registry::default(), &index);
+    let messages: Vec<&str> = diagnostics
+        .iter()
+        .filter(|d| {
+            d.code == DiagnosticCode::ShadowedVariable || d.code == DiagnosticCode::NameConflict
+        })
+        .map(|d| d.message.as_str())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "The parameter 'title' hides the variable declared in config.asp",
+            "The function 'Connection' has the same name as the variable declared in config.asp",
+        ]
+    );
+    let shadowed = diagnostics
+        .iter()
+        .find(|d| d.code == DiagnosticCode::ShadowedVariable)
+        .unwrap();
+    let related = &shadowed.related[0];
+    assert!(related.path.as_ref().unwrap().ends_with("config.asp"));
+    assert_eq!(related.span.line, 2);
+}
+
+#[test]
+fn test_unchecked_errors() {
+    let source = "<%\nSub Save(conn)\n  On Error Resume Next\n  conn.Execute \"UPDATE t\"\n  If Err.Number <> 0 Then Response.Write \"failed\"\n  On Error GoTo 0\nEnd Sub\nSub Delete(conn)\n  On Error Resume Next\n  conn.Execute \"DELETE t\"\n  On Error GoTo 0\n  conn.Close\nEnd Sub\nSub Close(conn)\n  On Error Resume Next\n  conn.Close\n  If Err Then Err.Clear\nEnd Sub\nOn Error Resume Next\nSave Nothing\n%>";
+    let lines: Vec<usize> = lint::lint(source, &Registry::default())
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::UncheckedError)
+        .map(|d| d.span.line)
+        .collect();
+    assert_eq!(lines, [9, 19]);
+
+    // The severity can be tuned like any rule
+    let mut registry = Registry::default();
+    registry.set_severity(DiagnosticCode::UncheckedError, Severity::Error);
+    let diagnostics = lint::lint(source, &registry);
+    assert!(
+        diagnostics
+            .iter()
+            .filter(|d| d.code == DiagnosticCode::UncheckedError)
+            .all(|d| d.severity == Severity::Error)
+    );
+}
+
+#[test]
+fn test_cross_site_scripting() {
+    let source = "<%\nDim name, id, safe\nname = Trim(Request.Form(\"name\"))\nid = CInt(Request(\"id\"))\nsafe = Server.HTMLEncode(name)\nResponse.Write \"Hello \" & name\nResponse.Write(Request.QueryString(\"q\"))\nResponse.Write safe & id\nname = \"guest\"\nResponse.Write name\n%>\n<p><%= Request.Cookies(\"theme\") %></p>\n<p><%= Server.HTMLEncode(Request(\"q\")) %></p>";
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|d| d.code == DiagnosticCode::CrossSiteScripting)
+        .map(|d| (d.message, d.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            (
+                "'name' comes from the request and is written to the page without Server.HTMLEncode"
+                    .to_string(),
+                6
+            ),
+            (
+                "'Request.QueryString(\"q\")' comes from the request and is written to the page without Server.HTMLEncode"
+                    .to_string(),
+                7
+            ),
+            (
+                "'Request.Cookies(\"theme\")' comes from the request and is written to the page without Server.HTMLEncode"
+                    .to_string(),
+                12
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_cross_site_scripting_sanitizers() {
+    let source = "<%= HtmlSafe(Request(\"q\")) %>";
+    assert_eq!(
+        messages(source, DiagnosticCode::CrossSiteScripting).len(),
+        1
+    );
+
+    let mut rule = lint::rules::CrossSiteScripting::default();
+    rule.sanitizers.push("HtmlSafe".to_string());
+    let mut registry = Registry::new();
+    registry.register(Box::new(rule));
+    assert!(lint::lint(source, &registry).is_empty());
+}
+
+#[test]
+fn test_hardcoded_credentials() {
+    let source = "<%\nConst DB_PASSWORD = \"s3cret\"\nDim conn, url, password\nconn = \"Provider=SQLOLEDB;Data Source=db;\" & _\n  \"User ID=app;Password=hunter2\"\nconn = \"Provider=SQLOLEDB;User ID=sa;Password=\" & password\nurl = \"https://api.example.com/v1?api_key=abc123&q=\" & Request(\"q\")\npassword = Request.Form(\"password\")\nconn = \"Provider=SQLOLEDB;User ID=app;Password=\" & password\n%>";
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &Registry::default())
+        .into_iter()
+        .filter(|d| d.code == DiagnosticCode::HardcodedCredentials)
+        .map(|d| (d.message, d.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            ("Hardcoded secret assigned to 'DB_PASSWORD'".to_string(), 2),
+            ("Connection string with an embedded password".to_string(), 4),
+            ("Connection string using the 'sa' account".to_string(), 6),
+            ("Hardcoded secret in the 'api_key' parameter".to_string(), 7),
+        ]
+    );
+}
+
+#[test]
+fn test_dangerous_functions() {
+    let source = "<%\nConst INIT = \"x = 1\"\nExecute INIT\nExecute Request(\"code\")\nresult = Eval(\"1 + \" & Request(\"n\"))\nServer.Execute \"footer.asp\"\nServer.Transfer Request(\"page\") & \".asp\"\nSet shell = Server.CreateObject(\"WScript.Shell\")\nSet fso = CreateObject(\"Scripting.FileSystemObject\")\nMsgBox \"Saved\"\nStop\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::DangerousFunction),
+        [
+            "'Execute' runs code built at runtime, which may come from the user",
+            "'Eval' runs code built at runtime, which may come from the user",
+            "'Server.Transfer' runs a page whose path is built at runtime",
+            "'WScript.Shell' objects give pages access to the server",
+            "'MsgBox' is not supported by ASP",
+            "'Stop' is not supported by ASP",
+        ]
+    );
+
+    let mut rule = lint::rules::DangerousFunction::default();
+    rule.unsupported.clear();
+    rule.objects.push("Scripting.FileSystemObject".to_string());
+    let mut registry = Registry::new();
+    registry.register(Box::new(rule));
+    assert_eq!(lint::lint(source, &registry).len(), 5);
+}
+
+#[test]
+fn test_unknown_members() {
+    let source = "<%\nResponse.Wrtie \"a\"\nresponse.write \"b\"\nIf Request.Form(\"x\") = \"\" Then Response.Redirec \"/\"\nSet e = Server.GetLastError()\nx = Server.GetLastError().Lien & Server.GetLastError.Line\nWith Session\n  .Abandon\n  .Clear\n  With .Contents\n    .RemoveAll\n  End With\nEnd With\nSub Show()\n  Err.Raise 1 : Err.Numbr = 2 : Application.Frobnicate\nEnd Sub\nx = e.Anything & Customer.Anything & ObjectContext.SetAbort\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::UnknownMember),
+        [
+            "'Wrtie' is not a member of the Response object; did you mean 'Write'?",
+            "'Redirec' is not a member of the Response object; did you mean 'Redirect'?",
+            "'Lien' is not a member of the ASPError object; did you mean 'Line'?",
+            "'Clear' is not a member of the Session object",
+            "'Numbr' is not a member of the Err object; did you mean 'Number'?",
+            "'Frobnicate' is not a member of the Application object",
+            "'Anything' is not a member of the ASPError object",
+        ]
+    );
+    let diagnostics = lint::lint(source, &Registry::default());
+    let first = diagnostics
+        .iter()
+        .find(|d| d.code == DiagnosticCode::UnknownMember)
+        .unwrap();
+    assert_eq!((first.span.line, first.span.column), (2, 10));
+    assert_eq!(first.severity, Severity::Warning);
+
+    // Names hiding an intrinsic object are not checked
+    let source = "<%\nSub Send(response)\n  response.Send\nEnd Sub\nDim Server : Set Server = GetServer() : Server.Start\n%>";
+    assert!(messages(source, DiagnosticCode::UnknownMember).is_empty());
+}
+
+#[test]
+fn test_unknown_members_of_com_objects() {
+    let source = "<%\nOption Explicit\nDim conn, rs, field, fso, file, mail, unknown\nSet conn = Server.CreateObject(\"ADODB.Connection\")\nconn.Opne \"dsn\"\nSet rs = conn.Execute(\"SELECT 1\")\nDo Until rs.EOF : rs.MoveNxt : Loop\nFor Each field In rs.Fields\n  Response.Write field.Name & field.Vaue & rs.Fields(\"id\").Valeu\nNext\nrs.Close : Set rs = Nothing\nSet fso = CreateObject(\"scripting.filesystemobject\")\nWith fso.OpenTextFile(\"a.txt\")\n  .ReadAll\n  .ReadToEnd\nEnd With\nSet mail = Server.CreateObject(\"CDO.Message\")\nmail.Configuration.Fields.Item(\"x\") = 1\nmail.Sned\nSet unknown = Server.CreateObject(\"Acme.Widget\")\nunknown.Anything\n%>";
+    assert_eq!(
+        messages(source, DiagnosticCode::UnknownMember),
+        [
+            "'Opne' is not a member of the ADODB.Connection object; did you mean 'Open'?",
+            "'MoveNxt' is not a member of the ADODB.Recordset object; did you mean 'MoveNext'?",
+            "'Vaue' is not a member of the ADODB.Field object; did you mean 'Value'?",
+            "'Valeu' is not a member of the ADODB.Field object; did you mean 'Value'?",
+            "'ReadToEnd' is not a member of the Scripting.TextStream object",
+            "'Sned' is not a member of the CDO.Message object; did you mean 'Send'?",
+        ]
+    );
+
+    // Variables assigned objects of different types are not checked
+    let source = "<%\nSet x = CreateObject(\"Scripting.Dictionary\")\nx.Add 1, 2\nSet x = CreateObject(\"MSXML2.DOMDocument.6.0\")\nx.loadXML \"<a/>\"\nx.Anything\nSet file = fso.GetFile(\"a\")\nfile.Anything\n%>";
+    assert!(messages(source, DiagnosticCode::UnknownMember).is_empty());
+}
+
+#[test]
+fn test_complex_procedures() {
+    let source = "<%\nFunction Grade(score, bonus)\n  If score > 90 And bonus Then\n    Grade = \"A\"\n  ElseIf score > 75 Then\n    Grade = \"B\"\n  Else\n    Grade = \"C\"\n  End If\n  Select Case Grade\n    Case \"A\", \"B\"\n      Response.Write \"pass\"\n    Case Else\n      Response.Write \"fail\"\n  End Select\nEnd Function\nSub Simple()\n  Response.Write Grade(80, False)\nEnd Sub\n%>";
+    assert!(messages(source, DiagnosticCode::ComplexProcedure).is_empty());
+
+    let mut registry = Registry::new();
+    registry.register(Box::new(lint::rules::ComplexProcedure {
+        max_complexity: 4,
+    }));
+    let diagnostics = lint::lint(source, &registry);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "'Grade' has a cyclomatic complexity of 5 (maximum 4)"
+    );
+    assert_eq!(
+        (diagnostics[0].span.line, diagnostics[0].span.column),
+        (2, 10)
+    );
+}
+
+#[test]
+fn test_size_limits_are_optional() {
+    let source = "<%\nSub Render(rows)\n  Dim row, cell\n  For Each row In rows\n    If row.Visible Then\n      For Each cell In row.Cells\n        If cell.Visible Then\n          With cell\n            If .Value <> \"\" Then\n              Response.Write .Value\n            End If\n          End With\n        End If\n      Next\n    End If\n  Next\nEnd Sub\nRender Array()\n%>";
+    assert!(messages(source, DiagnosticCode::LongProcedure).is_empty());
+    assert!(messages(source, DiagnosticCode::DeepNesting).is_empty());
+
+    let mut registry = Registry::default();
+    registry.set_severity(DiagnosticCode::LongProcedure, Severity::Warning);
+    registry.set_severity(DiagnosticCode::DeepNesting, Severity::Warning);
+    let diagnostics = lint::lint(source, &registry);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "Blocks of 'Render' are nested more than 4 levels deep"
+    );
+    assert_eq!(diagnostics[0].span.line, 8);
+
+    let mut registry = Registry::new();
+    registry.register(Box::new(lint::rules::LongProcedure { max_lines: 10 }));
+    registry.register(Box::new(lint::rules::DeepNesting { max_depth: 5 }));
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &registry)
+        .into_iter()
+        .map(|d| (d.message, d.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            ("'Render' has 16 lines (maximum 10)".to_string(), 2),
+            (
+                "Blocks of 'Render' are nested more than 5 levels deep".to_string(),
+                9
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_naming_conventions() {
+    let source = "<%\nConst MAX_ITEMS = 10\nDim user_name, itemCount\nClass shopping_cart\n  Private m_items\n  Private total\n  Public Count\n  Private Sub Class_Initialize()\n  End Sub\n  Function get_total(TaxRate)\n  End Function\nEnd Class\nSub renderXMLFeed()\nEnd Sub\n%>";
+    assert!(messages(source, DiagnosticCode::NamingConvention).is_empty());
+
+    let mut registry = Registry::default();
+    registry.set_severity(DiagnosticCode::NamingConvention, Severity::Notice);
+    let diagnostics: Vec<(String, usize)> = lint::lint(source, &registry)
+        .into_iter()
+        .filter(|d| d.code == DiagnosticCode::NamingConvention)
+        .map(|d| (d.message, d.span.line))
+        .collect();
+    assert_eq!(
+        diagnostics,
+        [
+            (
+                "Variable 'user_name' is not camelCase; rename it 'userName'".to_string(),
+                3
+            ),
+            (
+                "Class 'shopping_cart' is not PascalCase; rename it 'ShoppingCart'".to_string(),
+                4
+            ),
+            (
+                "Private member 'total' does not start with 'm_'; rename it 'm_total'".to_string(),
+                6
+            ),
+            (
+                "Variable 'Count' is not camelCase; rename it 'count'".to_string(),
+                7
+            ),
+            (
+                "Procedure 'get_total' is not PascalCase; rename it 'GetTotal'".to_string(),
+                10
+            ),
+            (
+                "Parameter 'TaxRate' is not camelCase; rename it 'taxRate'".to_string(),
+                10
+            ),
+            (
+                "Procedure 'renderXMLFeed' is not PascalCase; rename it 'RenderXmlFeed'"
+                    .to_string(),
+                13
+            ),
+        ]
+    );
+
+    let mut registry = Registry::new();
+    registry.register(Box::new(lint::rules::NamingConvention {
+        variables: Some(lint::rules::NamingStyle::Hungarian),
+        procedures: None,
+        classes: None,
+        constants: Some(lint::rules::NamingStyle::UpperSnakeCase),
+        member_prefix: None,
+    }));
+    let messages: Vec<String> = lint::lint(source, &registry)
+        .into_iter()
+        .map(|d| d.message)
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "Variable 'user_name' does not use Hungarian notation",
+            "Variable 'm_items' does not use Hungarian notation",
+            "Variable 'total' does not use Hungarian notation",
+            "Variable 'Count' does not use Hungarian notation",
+            "Parameter 'TaxRate' does not use Hungarian notation",
+        ]
+    );
+}
+
+#[test]
+fn test_configure_rules() {
+    let mut registry = Registry::default();
+    let options: toml::Table =
+        toml::from_str("variables = \"snake-case\"\nmember_prefix = \"off\"").unwrap();
+    registry
+        .configure(DiagnosticCode::NamingConvention, &options)
+        .unwrap();
+    registry.set_severity(DiagnosticCode::NamingConvention, Severity::Notice);
+    let source = "<%\nDim userName\nClass Cart\n  Private total\nEnd Class\n%>";
+    assert_eq!(
+        lint::lint(source, &registry)
+            .into_iter()
+            .filter(|d| d.code == DiagnosticCode::NamingConvention)
+            .map(|d| d.message)
+            .collect::<Vec<_>>(),
+        ["Variable 'userName' is not snake_case; rename it 'user_name'"]
+    );
+
+    let options: toml::Table = toml::from_str("max_complexity = \"high\"").unwrap();
+    assert!(
+        registry
+            .configure(DiagnosticCode::ComplexProcedure, &options)
+            .is_err()
+    );
+    let options: toml::Table = toml::from_str("sanitizers = []").unwrap();
+    assert!(
+        registry
+            .configure(DiagnosticCode::UndeclaredName, &options)
+            .unwrap_err()
+            .contains("sanitizers")
+    );
+}
+
+#[test]
+fn test_baseline_round_trip() {
+    let dir = tempdir().unwrap();
+    let page = dir.path().join("pages").join("list.asp");
+    let registry = Registry::default();
+    let source = "<%\nOption Explicit\nx = 1\nx = 2\n%>";
+
+    let mut baseline = lint::baseline::Baseline::new(dir.path());
+    baseline.add(&page, &lint::lint(source, &registry));
+    assert_eq!(baseline.len(), 2);
+    let baseline_path = dir.path().join("baseline.json");
+    baseline.save(&baseline_path).unwrap();
+    let content = fs::read_to_string(&baseline_path).unwrap();
+    assert!(
+        content.contains("\"file\": \"pages/list.asp\""),
+        "{}",
+        content
+    );
+    assert!(content.contains("\"count\": 2"), "{}", content);
+
+    let loaded = lint::baseline::Baseline::load(&baseline_path).unwrap();
+    let page = fs::canonicalize(dir.path())
+        .unwrap()
+        .join("pages")
+        .join("list.asp");
+    let more = "<%\nOption Explicit\nx = 1\nx = 2\nx = 3\n%>";
+    let diagnostics = loaded.filter(&page, lint::lint(more, &registry));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].span.line, 5);
+    assert_eq!(
+        loaded
+            .filter(&dir.path().join("other.asp"), lint::lint(source, &registry))
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn test_explanations_match_rules() {
+    let registry = Registry::default();
+    for rule in registry.rules() {
+        let code = rule.code();
+        let explanation = lint::explain::explain(code);
+        assert!(!explanation.rationale.is_empty(), "{}", code);
+        if !rule.enabled_by_default() || code == DiagnosticCode::ComplexProcedure {
+            continue;
+        }
+        let reported = |source: &str| {
+            lint::lint(source, &registry)
+                .iter()
+                .any(|diagnostic| diagnostic.code == code)
+        };
+        assert!(reported(explanation.bad), "{} bad example", code);
+        assert!(!reported(explanation.good), "{} good example", code);
+    }
+
+    let text = lint::explain::explain(DiagnosticCode::DeepNesting).to_string();
+    assert!(text.starts_with("ASP025 deep-nesting (warning, disabled by default)"));
+    assert!(text.contains("    max_depth: "), "{}", text);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_plugin() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempdir().unwrap();
+    let write_plugin = |name: &str, script: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    let plugin = write_plugin(
+        "no_inline_sql",
+        "#!/bin/sh\ngrep -q '\"source\":\"<%' || exit 3\necho '[{\"line\": 2, \"column\": 9, \"end_line\": 2, \"end_column\": 15, \"message\": \"Use the data layer\", \"rule\": \"no-inline-sql\", \"severity\": \"error\"}]'\n",
+    );
+    let broken = write_plugin("broken", "#!/bin/sh\necho oops >&2\nexit 1\n");
+
+    let source = "<%\nrs.Open \"SELECT 1\", conn\n%>";
+    let mut registry = Registry::new();
+    registry.register(Box::new(lint::plugin::CommandPlugin::new(&plugin)));
+    let diagnostics = lint::lint(source, &registry);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::PluginRule);
+    assert_eq!(diagnostics[0].message, "no-inline-sql: Use the data layer");
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(
+        &source[diagnostics[0].span.start..diagnostics[0].span.end],
+        "\"SELEC"
+    );
+
+    registry.set_severity(DiagnosticCode::PluginRule, Severity::Notice);
+    registry.register(Box::new(lint::plugin::CommandPlugin::new(&broken)));
+    let diagnostics = lint::lint(source, &registry);
+    assert_eq!(diagnostics.len(), 2);
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Notice));
+    assert!(
+        diagnostics
+            .iter()
+            .any(|d| d.message.starts_with("Plugin '") && d.message.ends_with("oops"))
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_command_plugin_pipes_and_timeout() {
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, Instant};
+
+    let dir = tempdir().unwrap();
+    let write_plugin = |name: &str, script: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, script).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    };
+    // Copies its whole input to stderr while reading it, more than a pipe holds
+    let chatty = write_plugin("chatty", "#!/bin/sh\ncat >&2\necho '[]'\n");
+    let hung = write_plugin("hung", "#!/bin/sh\nexec sleep 30\n");
+
+    let source = format!(
+        "<%\n{}%>",
+        "x = \"a long line of the page\"\n".repeat(20_000)
+    );
+    let mut registry = Registry::new();
+    registry.register(Box::new(lint::plugin::CommandPlugin::new(&chatty)));
+    assert!(lint::lint(&source, &registry).is_empty());
+
+    let mut registry = Registry::new();
+    registry.register(Box::new(
+        lint::plugin::CommandPlugin::new(&hung).with_timeout(Duration::from_millis(200)),
+    ));
+    let start = Instant::now();
+    let diagnostics = lint::lint("<% x = 1 %>", &registry);
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert_eq!(diagnostics.len(), 1);
+    assert!(diagnostics[0].message.contains("timed out"));
+}
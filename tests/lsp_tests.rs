@@ -1,7 +1,19 @@
 //! Integration tests for the LSP server.
 //! These tests ensure the Language Server Protocol implementation functions correctly.
 
-use asp_classic_parser::lsp::parse_asp_file;
+use asp_classic_parser::config::Config;
+use asp_classic_parser::formatter::{FormatOptions, IndentStyle};
+use asp_classic_parser::lsp::{
+    InlayHintSettings, ParsedDocument, ServerSettings, apply_change, document_highlights,
+    inlay_hints, is_source_file, member_completions, on_type_formatting, parse_asp_file,
+    position_in_asp_code, selection_range, semantic_tokens, source_extensions, word_at_position,
+};
+use ropey::Rope;
+use std::path::Path;
+use tower_lsp::lsp_types::{
+    CompletionItemKind, DocumentHighlightKind, DocumentSymbol, InlayHintLabel, Position, Range,
+    SelectionRange, TextDocumentContentChangeEvent,
+};
 
 #[test]
 fn test_parse_asp_file_valid() {
@@ -60,3 +72,442 @@ fn test_parse_asp_file_empty() {
     let result = parse_asp_file("test.asp", content);
     assert!(result.is_err(), "Should detect empty file");
 }
+
+#[test]
+fn test_parse_asp_file_reports_every_syntax_error() {
+    let content = "<%\nDim\nx = 1\nErase\n%>";
+
+    let errors = parse_asp_file("test.asp", content).expect_err("Should detect syntax errors");
+    let lines: Vec<Option<usize>> = errors.iter().map(|error| error.line).collect();
+    assert_eq!(lines, vec![Some(2), Some(4)]);
+    assert!(
+        errors
+            .iter()
+            .all(|error| error.code.as_deref() == Some("ASP001"))
+    );
+}
+
+#[test]
+fn test_semantic_tokens() {
+    let content = "<%@ Language=\"VBScript\" %>
+<!-- list -->
+<%
+Const MAX = 10
+Function Total(items)
+  Total = UBound(items) + MAX ' count
+End Function
+Response.Write Total(list)
+%>";
+
+    // Returns the text, type and modifiers of each token
+    let types = [
+        "keyword",
+        "operator",
+        "string",
+        "number",
+        "comment",
+        "macro",
+        "function",
+        "class",
+        "variable",
+        "parameter",
+        "property",
+    ];
+    let lines: Vec<&str> = content.lines().collect();
+    let (mut line, mut column) = (0, 0);
+    let tokens: Vec<(String, &str, u32)> = semantic_tokens(content)
+        .into_iter()
+        .map(|token| {
+            if token.delta_line > 0 {
+                column = 0;
+            }
+            line += token.delta_line as usize;
+            column += token.delta_start as usize;
+            let text: String = lines[line]
+                .chars()
+                .skip(column)
+                .take(token.length as usize)
+                .collect();
+            (
+                text,
+                types[token.token_type as usize],
+                token.token_modifiers_bitset,
+            )
+        })
+        .collect();
+
+    let expected = [
+        ("<%@", "macro", 0),
+        ("Language", "property", 0),
+        ("=", "operator", 0),
+        ("\"VBScript\"", "string", 0),
+        ("%>", "macro", 0),
+        ("<!-- list -->", "comment", 0),
+        ("<%", "macro", 0),
+        ("Const", "keyword", 0),
+        ("MAX", "variable", 0b011),
+        ("=", "operator", 0),
+        ("10", "number", 0),
+        ("Function", "keyword", 0),
+        ("Total", "function", 0b001),
+        ("items", "parameter", 0b001),
+        ("Total", "function", 0),
+        ("=", "operator", 0),
+        ("UBound", "function", 0b100),
+        ("items", "parameter", 0),
+        ("+", "operator", 0),
+        ("MAX", "variable", 0b010),
+        ("' count", "comment", 0),
+        ("End", "keyword", 0),
+        ("Function", "keyword", 0),
+        ("Response", "variable", 0b100),
+        ("Write", "property", 0),
+        ("Total", "function", 0),
+        ("list", "variable", 0),
+        ("%>", "macro", 0),
+    ];
+    let expected: Vec<(String, &str, u32)> = expected
+        .into_iter()
+        .map(|(text, kind, modifiers)| (text.to_string(), kind, modifiers))
+        .collect();
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn test_apply_change() {
+    let change = |start: (u32, u32), end: (u32, u32), text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: Position::new(start.0, start.1),
+            end: Position::new(end.0, end.1),
+        }),
+        range_length: None,
+        text: text.to_string(),
+    };
+
+    // Characters are counted in UTF-16 code units: "é" is one, "😀" two
+    let mut document = Rope::from_str("<%\r\nx = \"é😀\" : y = 1\r\n%>");
+    apply_change(&mut document, change((1, 12), (1, 13), "z"));
+    assert_eq!(document.to_string(), "<%\r\nx = \"é😀\" : z = 1\r\n%>");
+
+    // Positions past the end of a line stop before its line break
+    apply_change(&mut document, change((1, 99), (1, 99), " + 2"));
+    assert_eq!(document.to_string(), "<%\r\nx = \"é😀\" : z = 1 + 2\r\n%>");
+
+    // Joining lines, and inserting past the last line
+    apply_change(&mut document, change((0, 2), (1, 0), " "));
+    apply_change(&mut document, change((9, 0), (9, 0), "\n"));
+    assert_eq!(document.to_string(), "<% x = \"é😀\" : z = 1 + 2\r\n%>\n");
+
+    // A change without range replaces the document
+    apply_change(
+        &mut document,
+        TextDocumentContentChangeEvent {
+            range: None,
+            range_length: None,
+            text: "<% %>".to_string(),
+        },
+    );
+    assert_eq!(document.to_string(), "<% %>");
+}
+
+#[test]
+fn test_word_at_position() {
+    let document = Rope::from_str("<%\r\nx = \"é\" & user_name\r%>");
+    let word = |line, character| word_at_position(&document, Position::new(line, character));
+
+    assert_eq!(word(1, 0), Some("x".to_string()));
+    assert_eq!(word(1, 12), Some("user_name".to_string()));
+    assert_eq!(word(1, 2), None);
+    // Past the end of its line or of the document
+    assert_eq!(word(1, 99), None);
+    assert_eq!(word(9, 0), None);
+}
+
+#[test]
+fn test_position_in_asp_code() {
+    let document = Rope::from_str("<p>100%</p>\n<% x = \"<b>\"\n%>\n<p>\n<%= x %>");
+    let in_code = |line, character| position_in_asp_code(&document, Position::new(line, character));
+
+    assert!(!in_code(0, 7));
+    assert!(in_code(1, 2));
+    assert!(in_code(1, 12));
+    assert!(!in_code(2, 2));
+    assert!(!in_code(3, 1));
+    assert!(in_code(4, 4));
+}
+
+#[test]
+fn test_document_highlights() {
+    let content = "<%\nDim total\ntotal = 0\nFunction Add(total)\n  Add = total + 1\nEnd Function\nResponse.Write total\nResponse.Write Now\n%>";
+    let highlights = |line: u32, character: u32| -> Vec<(u32, u32, DocumentHighlightKind)> {
+        document_highlights(content, Position::new(line, character))
+            .into_iter()
+            .map(|highlight| {
+                (
+                    highlight.range.start.line,
+                    highlight.range.start.character,
+                    highlight.kind.unwrap(),
+                )
+            })
+            .collect()
+    };
+
+    // The global variable, not the parameter of the same name
+    let global = vec![
+        (1, 4, DocumentHighlightKind::WRITE),
+        (2, 0, DocumentHighlightKind::WRITE),
+        (6, 15, DocumentHighlightKind::READ),
+    ];
+    assert_eq!(highlights(6, 17), global);
+    assert_eq!(highlights(1, 5), global);
+    assert_eq!(
+        highlights(4, 10),
+        vec![
+            (3, 13, DocumentHighlightKind::WRITE),
+            (4, 8, DocumentHighlightKind::READ),
+        ]
+    );
+    // Built-in objects are matched by name
+    assert_eq!(
+        highlights(7, 2),
+        vec![
+            (6, 0, DocumentHighlightKind::READ),
+            (7, 0, DocumentHighlightKind::READ),
+        ]
+    );
+    assert!(highlights(0, 0).is_empty());
+}
+
+#[test]
+fn test_selection_range() {
+    let content = "<%\nIf a Then\n  x = (b + 1) * 2\nEnd If\n%>";
+    let mut ranges = Vec::new();
+    let mut selection: Option<SelectionRange> = Some(selection_range(content, Position::new(2, 7)));
+    while let Some(current) = selection {
+        let range = current.range;
+        ranges.push((
+            (range.start.line, range.start.character),
+            (range.end.line, range.end.character),
+        ));
+        selection = current.parent.map(|parent| *parent);
+    }
+    assert_eq!(
+        ranges,
+        vec![
+            ((2, 7), (2, 8)),  // b
+            ((2, 7), (2, 12)), // b + 1
+            ((2, 6), (2, 13)), // (b + 1)
+            ((2, 6), (2, 17)), // (b + 1) * 2
+            ((2, 2), (2, 17)), // x = (b + 1) * 2
+            ((1, 0), (3, 6)),  // If ... End If
+            ((0, 0), (4, 2)),  // the page
+        ]
+    );
+}
+
+#[test]
+fn test_inlay_hints() {
+    let content = "<%\nFunction GetUser(id, ByVal cache)\nEnd Function\nSub Log(message)\nEnd Sub\nSet user = GetUser(42, True)\nLog user.Name\nCall Log(message)\nLog (text)\nSave total, 1\n%>";
+    let library = asp_classic_parser::parser::parse_recovering(
+        "<%\nSub Save(ByRef value, count)\nEnd Sub\n%>",
+    )
+    .program;
+    let hints = |settings: InlayHintSettings| -> Vec<(u32, u32, String)> {
+        inlay_hints(content, &[&library], settings)
+            .into_iter()
+            .map(|hint| {
+                let InlayHintLabel::String(label) = hint.label else {
+                    panic!("Unexpected label parts");
+                };
+                (hint.position.line, hint.position.character, label)
+            })
+            .collect()
+    };
+
+    assert_eq!(
+        hints(InlayHintSettings::default()),
+        [
+            (5, 19, "id:".to_string()),
+            (5, 23, "cache:".to_string()),
+            (6, 4, "message:".to_string()),
+            // Named like the parameter, but changed by the procedure
+            (7, 9, "ByRef".to_string()),
+            (8, 4, "message:".to_string()),
+            (9, 5, "ByRef value:".to_string()),
+            (9, 12, "count:".to_string()),
+        ]
+    );
+    let by_ref_only = InlayHintSettings {
+        parameter_names: false,
+        by_ref: true,
+    };
+    assert_eq!(
+        hints(by_ref_only),
+        [(7, 9, "ByRef".to_string()), (9, 5, "ByRef".to_string())]
+    );
+}
+
+#[test]
+fn test_parsed_document() {
+    let content = "<%\nClass Cart\n  Public Sub Add(item)\n  End Sub\nEnd Class\nDim total\ntotal = total + 1\n%>";
+    let parsed = ParsedDocument::new(content);
+    assert_eq!(parsed.source, content);
+
+    // Every feature answers from the same syntax tree as a fresh parse
+    let position = Position::new(6, 9);
+    assert_eq!(parsed.semantic_tokens(), semantic_tokens(content));
+    assert_eq!(
+        parsed.document_highlights(position),
+        document_highlights(content, position)
+    );
+    assert_eq!(parsed.document_highlights(position).len(), 3);
+    assert_eq!(
+        parsed.selection_range(position),
+        selection_range(content, position)
+    );
+
+    fn names(symbols: &[DocumentSymbol]) -> Vec<String> {
+        symbols
+            .iter()
+            .flat_map(|symbol| {
+                std::iter::once(symbol.name.clone())
+                    .chain(names(symbol.children.as_deref().unwrap_or_default()))
+            })
+            .collect()
+    }
+    assert_eq!(names(&parsed.document_symbols()), ["Cart", "Add", "total"]);
+
+    // A new version is parsed from the previous one
+    let edited = content.replace("total + 1", "total + count");
+    let reparsed = parsed.reparse(&edited);
+    assert_eq!(reparsed.source, edited);
+    assert_eq!(reparsed.parse, ParsedDocument::new(&edited).parse);
+    assert_eq!(reparsed.semantic_tokens(), semantic_tokens(&edited));
+}
+
+#[test]
+fn test_on_type_formatting() {
+    let spaces = FormatOptions {
+        indent_width: 2,
+        ..FormatOptions::default()
+    };
+    let edits = |content: &str, line: u32, character: u32, ch: &str| -> Vec<(Range, String)> {
+        on_type_formatting(content, Position::new(line, character), ch, &spaces)
+            .into_iter()
+            .map(|edit| (edit.range, edit.new_text))
+            .collect()
+    };
+    let range = |line: u32, end: u32| Range::new(Position::new(line, 0), Position::new(line, end));
+
+    // Closing a block lines it up with its opening statement
+    let content = "<%\nIf x Then\n  y = 1\n  end if\n%>";
+    assert_eq!(edits(content, 3, 8, "f"), [(range(3, 2), String::new())]);
+    let content = "<%\nFor i = 1 To 3\n  Do\n    y = i\n    Loop\n%>";
+    assert_eq!(edits(content, 4, 8, "p"), [(range(4, 4), "  ".to_string())]);
+    // Other words ending with a trigger letter are left alone
+    assert!(edits("<%\nIf x Then\n  If\n%>", 2, 4, "f").is_empty());
+
+    // A line break indents the new line in the block, and re-indents the previous one
+    let content = "<%\nIf x Then\n\n%>";
+    assert_eq!(
+        edits(content, 2, 0, "\n"),
+        [(range(2, 0), "  ".to_string())]
+    );
+    let content = "<%\nIf x Then\n  y = 1\n  End If\n  \n%>";
+    assert_eq!(
+        edits(content, 4, 2, "\n"),
+        [(range(3, 2), String::new()), (range(4, 2), String::new())]
+    );
+    let tabs = FormatOptions {
+        indent_style: IndentStyle::Tabs,
+        ..FormatOptions::default()
+    };
+    assert_eq!(
+        on_type_formatting("<%\nSub Foo\n\n%>", Position::new(2, 0), "\n", &tabs)[0].new_text,
+        "\t"
+    );
+    // The HTML keeps its indentation
+    assert!(edits("<ul>\n  \n</ul>", 1, 2, "\n").is_empty());
+    // Long statements are not wrapped, so the lines below do not move
+    let narrow = FormatOptions {
+        max_line_length: Some(20),
+        ..spaces.clone()
+    };
+    let content = "<%\nIf x Then\nResponse.Write \"a long line\" & name\n\n%>";
+    let edits = on_type_formatting(content, Position::new(3, 0), "\n", &narrow);
+    let lines: Vec<u32> = edits.iter().map(|edit| edit.range.start.line).collect();
+    assert_eq!(lines, [2, 3]);
+}
+
+#[test]
+fn test_member_completions() {
+    let labels = |content: &str, position: Position| {
+        member_completions(content, position)
+            .map(|items| items.into_iter().map(|item| item.label).collect::<Vec<_>>())
+    };
+
+    // Members of the intrinsic objects, starting with the letters typed
+    let content = "<%\nResponse.Re\n%>";
+    assert_eq!(labels(content, Position::new(1, 11)).unwrap(), ["Redirect"]);
+    let items = member_completions(content, Position::new(1, 9)).unwrap();
+    assert_eq!(items.len(), 20);
+    assert_eq!(items[0].kind, Some(CompletionItemKind::METHOD));
+    assert_eq!(
+        items[0].detail.as_deref(),
+        Some("method of the Response object")
+    );
+
+    // Members of the COM objects assigned to variables, even in a page being typed
+    let content =
+        "<%\nDim conn\nSet conn = Server.CreateObject(\"ADODB.Connection\")\n%>\n<%\nconn.Be\n%>";
+    assert_eq!(
+        labels(content, Position::new(5, 7)).unwrap(),
+        ["BeginTrans"]
+    );
+    let content = "<%\nSet fso = CreateObject(\"Scripting.FileSystemObject\")\nfso.\n%>";
+    let members = labels(content, Position::new(2, 4)).unwrap();
+    assert!(members.contains(&"OpenTextFile".to_string()));
+
+    // Names of unknown objects, or hiding an intrinsic object
+    assert_eq!(labels("<%\nrs.\n%>", Position::new(1, 3)), None);
+    assert_eq!(
+        labels("<%\nDim Response\nResponse.\n%>", Position::new(2, 9)),
+        None
+    );
+    assert_eq!(labels("<%\nResponse\n%>", Position::new(1, 8)), None);
+}
+
+#[test]
+fn test_source_files() {
+    let extensions = source_extensions(&Config::default());
+    for path in [
+        "default.asp",
+        "global.asa",
+        "lib/menu.inc",
+        "utils.vbs",
+        "PAGE.ASP",
+    ] {
+        assert!(is_source_file(Path::new(path), &extensions), "{}", path);
+    }
+    for path in ["readme.txt", "style.css", "Makefile"] {
+        assert!(!is_source_file(Path::new(path), &extensions), "{}", path);
+    }
+
+    // The extensions of the configuration replace the default ones
+    let config: Config = toml::from_str("extensions = [\".ASP\", \"aspx\"]").unwrap();
+    let extensions = source_extensions(&config);
+    assert_eq!(extensions, ["asp", "aspx"]);
+    assert!(is_source_file(Path::new("page.aspx"), &extensions));
+    assert!(!is_source_file(Path::new("lib/menu.inc"), &extensions));
+}
+
+#[test]
+fn test_server_settings_disable_plugins_by_default() {
+    let settings: ServerSettings =
+        serde_json::from_str(r#"{"inlayHints": {"byRef": false}}"#).unwrap();
+    assert!(!settings.enable_plugins);
+    assert!(settings.inlay_hints.parameter_names);
+
+    let settings: ServerSettings = serde_json::from_str(r#"{"enablePlugins": true}"#).unwrap();
+    assert!(settings.enable_plugins);
+}
@@ -0,0 +1,153 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, AspParser, Rule};
+use pest::Parser;
+
+#[test]
+fn test_directive_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/directives.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read directives fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing directives fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_directive_attributes_in_parse_tree() {
+    let asp_code =
+        "<%@ Language=\"VBScript\" CodePage=65001 LCID='1036' %>\n<% Response.Write \"x\" %>";
+
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("Directive should parse");
+    let names: Vec<&str> = pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::directive_name)
+        .map(|pair| pair.as_str())
+        .collect();
+    assert_eq!(names, vec!["Language", "CodePage", "LCID"]);
+
+    let attributes = parser::parse_directives(asp_code);
+    assert_eq!(attributes.len(), 3);
+    assert_eq!(attributes[0].value, "VBScript");
+    assert_eq!(attributes[1].value, "65001");
+    assert_eq!(attributes[2].value, "1036");
+}
+
+#[test]
+fn test_directive_only_file_counts_as_asp() {
+    // A directive is an ASP tag on its own, so the file must not be skipped
+    let result = parser::parse("<%@ Language=VBScript %>\n<html></html>");
+    assert!(
+        result.is_ok(),
+        "Directive-only file failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_server_script_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/server_script.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read server script fixture");
+
+    // The file has no <% %> tags, but its server script block is ASP code
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing server script fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_server_script_block_recognition() {
+    let asp_code =
+        "<SCRIPT LANGUAGE=VBScript RUNAT=Server>\nDim x\n</SCRIPT>\n<script>var y;</script>";
+
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("Server script should parse");
+    let blocks: Vec<&str> = pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::server_script_content)
+        .map(|pair| pair.as_str())
+        .collect();
+    assert_eq!(blocks, vec!["\nDim x\n"]);
+}
+
+#[test]
+fn test_client_script_is_not_asp() {
+    let result = parser::parse("<script type=\"text/javascript\">var x;</script>");
+    let error = result.expect_err("Client-side script should not count as ASP code");
+    let asp_error = error
+        .downcast_ref::<parser::AspParseError>()
+        .expect("Should be an AspParseError");
+    assert!(asp_error.is_no_asp_tags_error());
+}
+
+#[test]
+fn test_attribute_expressions_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/attribute_expressions.asp");
+    let content =
+        fs::read_to_string(fixture_path).expect("Failed to read attribute expressions fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing attribute expressions fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_expression_blocks_inside_attributes() {
+    let asp_code = "<a href=\"page.asp?id=<%= id %>\">Link</a>\n<input <%= checked %>>";
+
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("Attribute expressions should parse");
+    let rules: Vec<Rule> = pairs.flatten().map(|pair| pair.as_rule()).collect();
+
+    // Both <%= %> tags must be recognized as expression blocks, not as script blocks
+    let expression_blocks = rules
+        .iter()
+        .filter(|rule| **rule == Rule::asp_expression_block)
+        .count();
+    assert_eq!(expression_blocks, 2);
+    assert!(!rules.contains(&Rule::asp_script_block));
+}
+
+#[test]
+fn test_html_comments_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/html_comments.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read HTML comments fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing HTML comments fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_commented_out_asp_is_not_code() {
+    // An unclosed ASP tag inside an HTML comment must not cause a parse error
+    let asp_code = "<!-- <% unfinished -->\n<% Response.Write \"ok\" %>";
+
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("Commented ASP should parse");
+    let script_blocks = pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::asp_script_block)
+        .count();
+    assert_eq!(script_blocks, 1, "Only the active script block is code");
+}
+
+#[test]
+fn test_commented_out_asp_only_has_no_asp_tags() {
+    let result = parser::parse("<html><!-- <% Response.Write \"old\" %> --></html>");
+    let error = result.expect_err("Commented-out ASP is not active code");
+    let asp_error = error
+        .downcast_ref::<parser::AspParseError>()
+        .expect("Should be an AspParseError");
+    assert!(asp_error.is_no_asp_tags_error());
+}
@@ -0,0 +1,111 @@
+use asp_classic_parser::includes::{IncludeGraph, IncludeResolver};
+use asp_classic_parser::metrics::{ComplexityDistribution, file_metrics, project_metrics};
+use std::fs;
+use std::path::Path;
+use tempfile::tempdir;
+
+const PAGE: &str = r#"<%@ Language="VBScript" %>
+<html>
+<body>
+<% If user <> "" Then %><p><%= user %></p><% End If %>
+<script runat="server" language="JScript">
+function total(items) { return items.length; }
+</script>
+<%
+' Cart of the current user
+Class Cart
+    Private items
+
+    Public Property Get Count
+        Count = UBound(items) + 1
+    End Property
+
+    Public Function Find(id)
+        Dim i
+        For i = 0 To UBound(items)
+            If items(i) = id And id > 0 Then Find = i
+        Next
+    End Function
+End Class
+
+Sub Clear()
+End Sub
+%>
+</body>
+</html>"#;
+
+#[test]
+fn test_file_metrics() {
+    let metrics = file_metrics(Path::new("cart.asp"), PAGE);
+    assert_eq!(metrics.lines, 29);
+    // The directive, the mixed line 4 and lines 8 to 27 but the blank ones; the
+    // JScript block is neither VBScript nor HTML
+    assert_eq!(metrics.vbscript_lines, 19);
+    // Lines 2 to 4 and the end of the page
+    assert_eq!(metrics.html_lines, 5);
+    assert_eq!(
+        (
+            metrics.functions,
+            metrics.subs,
+            metrics.properties,
+            metrics.classes
+        ),
+        (1, 1, 1, 1)
+    );
+    assert_eq!(metrics.includes, 0);
+    // `For`, `If` and `And`
+    assert_eq!(metrics.max_complexity, 4);
+    assert_eq!(
+        metrics.complexity,
+        ComplexityDistribution {
+            low: 3,
+            ..ComplexityDistribution::default()
+        }
+    );
+}
+
+#[test]
+fn test_file_metrics_cr_line_endings() {
+    let path = Path::new("fixtures/passing/line_endings_cr.asp");
+    let source = fs::read_to_string(path).expect("Failed to read fixture");
+    let metrics = file_metrics(path, &source);
+    // Every line ends with a lone `\r`; only line 9 has HTML
+    assert_eq!(metrics.lines, 10);
+    assert_eq!(metrics.vbscript_lines, 10);
+    assert_eq!(metrics.html_lines, 1);
+}
+
+#[test]
+fn test_project_metrics() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let root = temp_dir.path();
+    fs::write(root.join("lib.asp"), "<%\nSub Log(message)\nEnd Sub\n%>")
+        .expect("Failed to write lib.asp");
+    for page in ["a.asp", "b.asp"] {
+        fs::write(
+            root.join(page),
+            "<!--#include file=\"lib.asp\" -->\n<%\nLog \"page\"\n%>",
+        )
+        .expect("Failed to write page");
+    }
+
+    let paths = vec![root.join("a.asp"), root.join("lib.asp")];
+    let graph = IncludeGraph::build(
+        &[paths.clone(), vec![root.join("b.asp")]].concat(),
+        &IncludeResolver::new(),
+    );
+    let project = project_metrics(&graph, &paths);
+    assert_eq!(project.files.len(), 2);
+    assert_eq!(
+        (project.files[0].includes, project.files[0].included_by),
+        (1, 0)
+    );
+    // Included by a page that is not measured too
+    assert_eq!(
+        (project.files[1].includes, project.files[1].included_by),
+        (0, 2)
+    );
+    assert_eq!(project.total.lines, 8);
+    assert_eq!(project.total.subs, 1);
+    assert_eq!(project.total.complexity.low, 1);
+}
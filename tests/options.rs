@@ -0,0 +1,129 @@
+use asp_classic_parser::parser::{
+    self, Diagnostic, DiagnosticCode, IncludeHandling, ParseOptions, ScriptLanguage,
+};
+
+/// Returns the codes of the errors reported for a page
+fn error_codes(asp_code: &str, options: &ParseOptions) -> Vec<DiagnosticCode> {
+    match parser::parse_with_options(asp_code, options) {
+        Ok(_) => Vec::new(),
+        Err(diagnostics) => diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect(),
+    }
+}
+
+fn errors(asp_code: &str, options: &ParseOptions) -> Vec<Diagnostic> {
+    parser::parse_with_options(asp_code, options).unwrap_err()
+}
+
+#[test]
+fn test_default_options_match_parse() {
+    let options = ParseOptions::default();
+    for page in ["<% x = 1 %>", "<p>hi</p>", "", "<%\nDim\n%>"] {
+        assert_eq!(
+            parser::parse_with_options(page, &options).is_ok(),
+            parser::parse(page).is_ok(),
+            "{:?}",
+            page
+        );
+    }
+    assert_eq!(
+        error_codes("<p>hi</p>", &options),
+        vec![DiagnosticCode::NoAspTags]
+    );
+    assert_eq!(error_codes("  ", &options), vec![DiagnosticCode::EmptyFile]);
+}
+
+#[test]
+fn test_syntax_errors_are_all_reported_up_to_the_maximum() {
+    let page = "<%\nDim\nx = 1\nDim\nIf\n%>";
+    assert_eq!(errors(page, &ParseOptions::default()).len(), 3);
+
+    let options = ParseOptions {
+        max_errors: 2,
+        ..ParseOptions::default()
+    };
+    let diagnostics = errors(page, &options);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[1].span.line, 4);
+}
+
+#[test]
+fn test_plain_html_is_accepted_without_strict_html() {
+    let options = ParseOptions {
+        strict_html: false,
+        ..ParseOptions::default()
+    };
+    let program = parser::parse_with_options("<html><body></body></html>", &options).unwrap();
+    assert_eq!(program.body.len(), 1);
+    // Empty files are still reported
+    assert_eq!(error_codes("", &options), vec![DiagnosticCode::EmptyFile]);
+}
+
+#[test]
+fn test_languages_can_be_restricted() {
+    let options = ParseOptions {
+        allowed_languages: vec![ScriptLanguage::JScript],
+        ..ParseOptions::default()
+    };
+    let diagnostics = errors("<html>\n<% x = 1 %>", &options);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::DisallowedLanguage);
+    assert_eq!(diagnostics[0].code.as_str(), "ASP007");
+    assert!(parser::parse_with_options("<%@ Language=JScript %><% var x; %>", &options).is_ok());
+
+    let options = ParseOptions {
+        allowed_languages: vec![ScriptLanguage::VBScript],
+        ..ParseOptions::default()
+    };
+    let diagnostics = errors("\n<%@ Language=\"JScript\" %>\n<% var x; %>", &options);
+    assert_eq!(diagnostics[0].span.line, 2);
+    assert!(diagnostics[0].message.contains("JScript"));
+}
+
+#[test]
+fn test_include_handling() {
+    let page = "<!--#include file=\"header.asp\"-->\n<p>Body</p>\n<!--#INCLUDE virtual=\"/footer.asp\" -->";
+
+    let ignore = ParseOptions::default();
+    assert_eq!(error_codes(page, &ignore), vec![DiagnosticCode::NoAspTags]);
+
+    let count = ParseOptions {
+        include_handling: IncludeHandling::CountAsCode,
+        ..ParseOptions::default()
+    };
+    assert!(parser::parse_with_options(page, &count).is_ok());
+
+    let forbid = ParseOptions {
+        include_handling: IncludeHandling::Forbid,
+        ..ParseOptions::default()
+    };
+    let diagnostics = errors(page, &forbid);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, DiagnosticCode::ForbiddenInclude);
+    assert_eq!(
+        diagnostics[0].span.as_str(page),
+        "<!--#include file=\"header.asp\"-->"
+    );
+
+    // Commented out directives and strings are not includes
+    let page =
+        "<!-- <!--#include file=\"a.asp\"--> -->\n<% s = \"<!--#include file='b.asp'-->\" %>";
+    assert!(parser::parse_with_options(page, &forbid).is_ok());
+}
+
+#[test]
+fn test_tab_width_changes_columns() {
+    let page = "<%\n\tDim\n%>";
+    assert_eq!(errors(page, &ParseOptions::default())[0].span.column, 5);
+
+    let options = ParseOptions {
+        tab_width: 4,
+        ..ParseOptions::default()
+    };
+    let diagnostics = errors(page, &options);
+    assert_eq!(diagnostics[0].span.column, 8);
+
+    let program = parser::parse_with_options("<%\n\tx = 1\n%>", &options).unwrap();
+    assert_eq!(program.body[0].span().column, 5);
+}
@@ -0,0 +1,78 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, ast::Statement};
+
+/// Returns the line of each error reported by the recovering parser
+fn error_lines(asp_code: &str) -> Vec<usize> {
+    parser::parse_recovering(asp_code)
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.span.line)
+        .collect()
+}
+
+#[test]
+fn test_invalid_fixture_reports_several_errors() {
+    let fixture_path = Path::new("fixtures/failing/invalid_syntax.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read invalid syntax fixture");
+
+    let lines = error_lines(&content);
+    assert!(lines.len() > 1, "Expected several errors, got {:?}", lines);
+    assert!(lines.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    // The first error is the one reported by the non-recovering parser
+    let error = parser::parse_to_ast(&content).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with(&format!("Parse error at line {},", lines[0])),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_valid_page_has_no_diagnostics() {
+    let result = parser::parse_recovering("<% Dim x : x = 1 %><p><%= x %></p>");
+    assert!(result.diagnostics.is_empty());
+    assert_eq!(result.program.body.len(), 5);
+}
+
+#[test]
+fn test_parsing_resumes_after_a_syntax_error() {
+    let code = "<%\nDim\nx = 1\nConst = 2\ny = 2\n%>";
+    let result = parser::parse_recovering(code);
+    assert_eq!(
+        result
+            .diagnostics
+            .iter()
+            .map(|d| (d.span.line, d.span.column))
+            .collect::<Vec<_>>(),
+        vec![(2, 4), (4, 7)]
+    );
+
+    // Valid statements around the errors are kept
+    let assignments = result
+        .program
+        .body
+        .iter()
+        .filter(|statement| matches!(statement, Statement::Assignment(_)))
+        .count();
+    assert_eq!(assignments, 2);
+}
+
+#[test]
+fn test_structural_errors_do_not_stop_parsing() {
+    let code = "<%\nSub A\nSub B\nEnd Sub\nEnd Sub\nIf x Then\n%>";
+    assert_eq!(error_lines(code), vec![3, 5, 6]);
+
+    let result = parser::parse_recovering(code);
+    assert!(matches!(result.program.body.last(), Some(Statement::If(_))));
+}
+
+#[test]
+fn test_errors_in_several_asp_blocks() {
+    let code = "<% Dim %>\n<p>ok</p>\n<% Erase %>\n<% x = 1 %>";
+    assert_eq!(error_lines(code), vec![1, 3]);
+}
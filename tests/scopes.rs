@@ -0,0 +1,192 @@
+use asp_classic_parser::analysis::{self, Bindings, DeclarationKind, Resolution, ScopeKind};
+use asp_classic_parser::parser::{self, ast::ProcedureKind};
+
+fn bindings(asp_code: &str) -> Bindings {
+    let program = parser::parse_to_ast(asp_code)
+        .unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", asp_code, e));
+    analysis::bindings(&program)
+}
+
+/// Returns the kind and line of the declaration each use of `name` resolves to
+fn resolved(bindings: &Bindings, name: &str) -> Vec<Option<(DeclarationKind, usize)>> {
+    bindings
+        .references
+        .iter()
+        .filter(|reference| reference.name.eq_ignore_ascii_case(name))
+        .map(|reference| {
+            bindings
+                .declaration_of(reference)
+                .map(|declaration| (declaration.kind, declaration.span.line))
+        })
+        .collect()
+}
+
+#[test]
+fn test_locals_shadow_globals() {
+    let bindings = bindings(
+        "<%\nDim x\nx = 1\nSub Test(y)\n  Dim x\n  x = y\nEnd Sub\nFunction Twice(x)\n  Twice = x * 2\nEnd Function\n%>",
+    );
+    assert_eq!(
+        resolved(&bindings, "x"),
+        vec![
+            Some((DeclarationKind::Variable, 2)),
+            Some((DeclarationKind::Variable, 5)),
+            Some((DeclarationKind::Parameter, 8)),
+        ]
+    );
+    assert_eq!(
+        resolved(&bindings, "y"),
+        vec![Some((DeclarationKind::Parameter, 4))]
+    );
+    // Assigning the function name sets its return value
+    assert_eq!(
+        resolved(&bindings, "Twice"),
+        vec![Some((
+            DeclarationKind::Procedure(ProcedureKind::Function),
+            8
+        ))]
+    );
+
+    let kinds: Vec<ScopeKind> = bindings.scopes.iter().map(|scope| scope.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ScopeKind::Global,
+            ScopeKind::Procedure,
+            ScopeKind::Procedure
+        ]
+    );
+    assert_eq!(bindings.scopes[2].name.as_deref(), Some("Twice"));
+}
+
+#[test]
+fn test_declarations_are_hoisted() {
+    let bindings = bindings(
+        "<%\nOption Explicit\nCall Show()\ntotal = LIMIT\nSub Show()\n  Response.Write total\nEnd Sub\nDim total\nConst LIMIT = 10\n%>",
+    );
+    assert_eq!(bindings.undeclared().count(), 0);
+    assert_eq!(
+        resolved(&bindings, "total"),
+        vec![
+            Some((DeclarationKind::Variable, 8)),
+            Some((DeclarationKind::Variable, 8)),
+        ]
+    );
+    assert_eq!(
+        resolved(&bindings, "LIMIT"),
+        vec![Some((DeclarationKind::Constant, 9))]
+    );
+}
+
+#[test]
+fn test_class_members_are_visible_in_methods() {
+    let bindings = bindings(
+        "<%\nOption Explicit\nClass Counter\n  Private m_count\n  Public Sub Increment()\n    m_count = m_count + 1\n    Me.Log m_count\n  End Sub\n  Private Sub Log(value)\n  End Sub\nEnd Class\nDim c\nSet c = New Counter\nc.Increment\n%>",
+    );
+    assert_eq!(bindings.undeclared().count(), 0);
+    let m_count = resolved(&bindings, "m_count");
+    assert_eq!(m_count.len(), 3);
+    assert!(
+        m_count
+            .iter()
+            .all(|d| *d == Some((DeclarationKind::Variable, 4)))
+    );
+    assert_eq!(
+        resolved(&bindings, "Counter"),
+        vec![Some((DeclarationKind::Class, 3))]
+    );
+
+    let me = bindings.references.iter().find(|r| r.name == "Me").unwrap();
+    assert_eq!(me.resolution, Resolution::Builtin);
+    let class_scope = &bindings.scopes[1];
+    assert_eq!(class_scope.kind, ScopeKind::Class);
+    assert_eq!(bindings.scopes[2].parent, Some(1));
+}
+
+#[test]
+fn test_option_explicit() {
+    let page =
+        "Sub Test()\n  count = 1\n  Response.Write count & vbCrLf & UCase(name)\nEnd Sub\n%>";
+
+    let strict = bindings(&format!("<%\nOption Explicit\n{}", page));
+    assert!(strict.option_explicit);
+    let undeclared: Vec<(&str, usize)> = strict
+        .undeclared()
+        .map(|reference| (reference.name.as_str(), reference.span.line))
+        .collect();
+    assert_eq!(undeclared, vec![("count", 4), ("count", 5), ("name", 5)]);
+
+    // Without Option Explicit, the first use declares a local variable
+    let loose = bindings(&format!("<%\n{}", page));
+    assert_eq!(loose.undeclared().count(), 0);
+    assert_eq!(
+        resolved(&loose, "count"),
+        vec![
+            Some((DeclarationKind::Implicit, 3)),
+            Some((DeclarationKind::Implicit, 3)),
+        ]
+    );
+    let count = loose
+        .declarations
+        .iter()
+        .find(|declaration| declaration.name == "count")
+        .unwrap();
+    assert_eq!(loose.scopes[count.scope].kind, ScopeKind::Procedure);
+}
+
+#[test]
+fn test_writes_and_lookup_by_offset() {
+    let page = "<%\nDim items()\nReDim items(5)\nitems(0) = 1\nFor i = 0 To UBound(items)\n  Response.Write items(i)\nNext\n%>";
+    let bindings = bindings(page);
+
+    let writes: Vec<(&str, bool)> = bindings
+        .references
+        .iter()
+        .filter(|reference| reference.name != "Response")
+        .map(|reference| (reference.name.as_str(), reference.is_write))
+        .collect();
+    assert_eq!(
+        writes,
+        vec![
+            ("items", true),
+            ("items", true),
+            ("i", true),
+            ("UBound", false),
+            ("items", false),
+            ("items", false),
+            ("i", false),
+        ]
+    );
+
+    let declaration = bindings.declaration_at(page.rfind("items").unwrap() + 2);
+    assert_eq!(declaration, Some(0));
+    assert_eq!(bindings.references_to(0).count(), 4);
+    // On the declaration itself
+    assert_eq!(
+        bindings.declaration_at(page.find("items").unwrap()),
+        Some(0)
+    );
+}
+
+#[test]
+fn test_visible_declarations() {
+    let page = "<%\nDim total, name\nClass Cart\n  Private m_items\n  Function Count(name)\n    Dim i\n    Count = 0\n  End Function\nEnd Class\nSub Show()\nEnd Sub\n%>";
+    let bindings = bindings(page);
+    let visible = |offset: usize| -> Vec<&str> {
+        bindings
+            .visible_declarations(offset)
+            .iter()
+            .map(|declaration| declaration.name.as_str())
+            .collect()
+    };
+
+    // The parameter hides the global variable
+    assert_eq!(
+        visible(page.find("Count = 0").unwrap()),
+        ["name", "i", "m_items", "Count", "total", "Cart", "Show"]
+    );
+    assert_eq!(
+        visible(page.find("Sub Show").unwrap() + 5),
+        ["total", "name", "Cart", "Show"]
+    );
+}
@@ -0,0 +1,55 @@
+use std::fs;
+use std::path::Path;
+
+use asp_classic_parser::parser::{self, AspParser, Rule};
+use pest::Parser;
+
+/// Returns the rules of all statements recognized in the given ASP code
+fn statement_rules(asp_code: &str) -> Vec<Rule> {
+    let pairs = AspParser::parse(Rule::file, asp_code).expect("ASP code should parse");
+    pairs
+        .flatten()
+        .filter(|pair| pair.as_rule() == Rule::statement)
+        .filter_map(|pair| pair.into_inner().next().map(|inner| inner.as_rule()))
+        .collect()
+}
+
+#[test]
+fn test_stop_randomize_fixture_parsing() {
+    let fixture_path = Path::new("fixtures/passing/stop_randomize.asp");
+    let content = fs::read_to_string(fixture_path).expect("Failed to read Stop/Randomize fixture");
+
+    let result = parser::parse(&content);
+    assert!(
+        result.is_ok(),
+        "Parsing Stop/Randomize fixture failed with error: {:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn test_stop_statement() {
+    let rules = statement_rules("<%\nStop\nstop ' break here\n%>");
+    assert_eq!(rules, vec![Rule::stop_statement, Rule::stop_statement]);
+}
+
+#[test]
+fn test_randomize_statement_with_and_without_seed() {
+    let rules = statement_rules("<% Randomize : Randomize Timer : RANDOMIZE 42 %>");
+    assert_eq!(
+        rules,
+        vec![
+            Rule::randomize_statement,
+            Rule::randomize_statement,
+            Rule::randomize_statement
+        ]
+    );
+}
+
+#[test]
+fn test_keywords_require_word_boundary() {
+    // Identifiers starting with a keyword are not Stop/Randomize statements
+    let rules = statement_rules("<%\nStopwatch = 1\nRandomizer.Run\n%>");
+    assert!(!rules.contains(&Rule::stop_statement));
+    assert!(!rules.contains(&Rule::randomize_statement));
+}
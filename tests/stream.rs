@@ -0,0 +1,123 @@
+use std::fs;
+use std::io::{self, BufRead, Read};
+
+use asp_classic_parser::parser::{self, Diagnostic, ScriptLanguage, stream};
+
+/// Pages mixing code blocks spanning several lines and ASP blocks, unbalanced
+/// blocks, comments and script blocks hiding ASP tags, and syntax errors
+const PAGES: [&str; 10] = [
+    "<html>\n<% Dim total\ntotal = 0 %>\n<p>Café</p>\n<% For i = 1 To 3\n  total = total + i %>\n<li><%= i %></li>\n<% Next %>\n<!-- footer -->\n<p><% Response.Write total %></p>\n</html>",
+    "<p>start</p>\n<% x = %>\n<p>middle</p>\n<% y = 1 : z = (2 %>\n<p>end</p>\n<% Dim %>\n",
+    "<% If x Then %>\n<p>a</p>\n<%\tDim\n%>\n<% Wend %>\n<% Sub Show()\n<% End Sub %>\n",
+    "<% If x Then %>\r\n<p>a</p>\r\n<% Dim %>\r\n<% ElseIf %>\r\n<% End If %>\r\n<% Next %>\r\n",
+    "<!-- a\n<% If x Then %>\n-->\n<% Next %>\n<!-- unterminated\n<% Dim %>\n<% End If %>\n",
+    "<script runat=\"server\">\nSub A()\n<% x\ny = \nEnd Sub\n</script\n>\n<% Dim %>\n<% If x Then %>\n",
+    "<script\n runat=server>\nx = \n</script>\n<% y = 1 %>\n<script>\n<% Dim %>\n</script>\n",
+    "<% Select Case x %>\n<p>text</p>\n<% Case 1 %>\n<% End Select %>\n<% Select Case y %>\n\n<% Case 2 %>\n",
+    "\n\n<%@ Language=\"JScript\" %>\n<% if (x) { %>\n<p>a</p>\n<% } %>\n<% var y = \"%>\";\n",
+    "<%@ Language=\"VBScript\" %>\n<% Class A\n<% Class B\n<% End Class\n<% Do While x\n<% Loop Until y\n",
+];
+
+/// Returns the errors reported when parsing a page in pieces, in source order
+fn streamed_errors(page: &str, piece_size: usize) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
+    let summary = stream::parse_in_pieces(page.as_bytes(), piece_size, |error| errors.push(error))
+        .expect("reading a string does not fail");
+    assert_eq!(summary.errors, errors.len());
+    errors.sort_by_key(|error| error.span.start);
+    errors
+}
+
+#[test]
+fn test_stream_matches_recovering_parse() {
+    for page in PAGES {
+        let expected = parser::parse_recovering(page).diagnostics;
+        for piece_size in [1, 20, 60, stream::DEFAULT_PIECE_SIZE] {
+            assert_eq!(
+                streamed_errors(page, piece_size),
+                expected,
+                "{:?} in pieces of {} bytes",
+                page,
+                piece_size
+            );
+        }
+    }
+}
+
+#[test]
+fn test_stream_matches_recovering_parse_of_fixtures() {
+    for directory in ["fixtures/passing", "fixtures/failing"] {
+        for entry in fs::read_dir(directory).expect("Failed to read fixtures") {
+            let path = entry.expect("Failed to read fixture").path();
+            let page = fs::read_to_string(&path).expect("Failed to read fixture");
+            let expected = parser::parse_recovering(&page).diagnostics;
+            assert_eq!(streamed_errors(&page, 1), expected, "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn test_stream_summary() {
+    let page = "\n<%@ Language=\"JScript\" %>\n<% if (x) { %>\n<p>a</p>\n<% } %>\n";
+    let summary = parser::parse_stream(page.as_bytes(), |_| {}).unwrap();
+    assert_eq!(summary.language, ScriptLanguage::JScript);
+    assert_eq!(summary.code_blocks, 3);
+    assert_eq!(summary.errors, 0);
+
+    let summary = parser::parse_stream("<p>Static page</p>".as_bytes(), |_| {}).unwrap();
+    assert_eq!(summary.language, ScriptLanguage::VBScript);
+    assert_eq!(summary.code_blocks, 0);
+
+    let summary = parser::parse_stream(io::empty(), |_| {}).unwrap();
+    assert_eq!(summary.code_blocks, 0);
+}
+
+/// Reader counting the bytes read
+struct CountingReader<'a> {
+    inner: &'a [u8],
+    read: &'a std::cell::Cell<usize>,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.read.set(self.read.get() + count);
+        Ok(count)
+    }
+}
+
+impl BufRead for CountingReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+        self.read.set(self.read.get() + amount);
+    }
+}
+
+#[test]
+fn test_stream_reports_errors_before_the_end_of_the_page() {
+    let section = "<tr>\n<% For Each row In rows %>\n<td><%= row(0) %></td>\n<% Next %>\n</tr>\n";
+    let mut page = String::from("<% Dim %>\n");
+    page.push_str(&section.repeat(20_000));
+    page.push_str("<% Wend %>\n");
+
+    let read = std::cell::Cell::new(0);
+    let reader = CountingReader {
+        inner: page.as_bytes(),
+        read: &read,
+    };
+    let mut read_at_errors = Vec::new();
+    parser::parse_stream(reader, |error| {
+        read_at_errors.push((error.span.line, read.get()))
+    })
+    .unwrap();
+
+    assert_eq!(read_at_errors.len(), 2);
+    let (first_line, read_at_first) = read_at_errors[0];
+    assert_eq!(first_line, 1);
+    assert!(read_at_first < page.len() / 10);
+    assert_eq!(read_at_errors[1].0, 100_002);
+}
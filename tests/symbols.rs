@@ -0,0 +1,111 @@
+use asp_classic_parser::analysis::{self, SymbolKind, SymbolTable};
+use asp_classic_parser::parser::{self, ast::Visibility};
+
+fn symbols(asp_code: &str) -> SymbolTable {
+    let program = parser::parse_to_ast(asp_code)
+        .unwrap_or_else(|e| panic!("Failed to parse {:?}: {}", asp_code, e));
+    analysis::symbols(&program)
+}
+
+#[test]
+fn test_module_level_declarations() {
+    let table = symbols(
+        "<%\nOption Explicit\nConst A = 1, B = 2\nDim x, items(10)\nPrivate secret\n\nFunction Add(a, ByVal b)\n  Dim local\n  Add = a + b\nEnd Function\n\nPublic Sub Show()\nEnd Sub\n%>",
+    );
+    let summary: Vec<(&str, SymbolKind, Visibility)> = table
+        .symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.kind, symbol.visibility))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("A", SymbolKind::Constant, Visibility::Public),
+            ("B", SymbolKind::Constant, Visibility::Public),
+            ("x", SymbolKind::Variable, Visibility::Public),
+            ("items", SymbolKind::Variable, Visibility::Public),
+            ("secret", SymbolKind::Variable, Visibility::Private),
+            ("Add", SymbolKind::Function, Visibility::Public),
+            ("Show", SymbolKind::Sub, Visibility::Public),
+        ]
+    );
+
+    let add = table.get("ADD").unwrap();
+    assert_eq!(add.parameters, vec!["a", "b"]);
+    assert_eq!((add.name_span.line, add.name_span.column), (7, 10));
+    assert_eq!((add.span.line, add.span.column), (7, 1));
+    assert!(table.get("local").is_none(), "Locals are not listed");
+}
+
+#[test]
+fn test_class_members() {
+    let table = symbols(
+        "<%\nClass Person\n  Private m_name\n  Const KIND = \"person\"\n  Public Property Get Name()\n    Name = m_name\n  End Property\n  Public Property Let Name(value)\n    m_name = value\n  End Property\n  Private Sub Class_Initialize()\n  End Sub\nEnd Class\n%>",
+    );
+    assert_eq!(table.symbols.len(), 1);
+    let person = &table.symbols[0];
+    assert_eq!(person.kind, SymbolKind::Class);
+
+    let members: Vec<(&str, SymbolKind, Visibility)> = person
+        .members
+        .iter()
+        .map(|member| (member.name.as_str(), member.kind, member.visibility))
+        .collect();
+    assert_eq!(
+        members,
+        vec![
+            ("m_name", SymbolKind::Variable, Visibility::Private),
+            ("KIND", SymbolKind::Constant, Visibility::Public),
+            ("Name", SymbolKind::PropertyGet, Visibility::Public),
+            ("Name", SymbolKind::PropertyLet, Visibility::Public),
+            ("Class_Initialize", SymbolKind::Sub, Visibility::Private),
+        ]
+    );
+    assert_eq!(
+        person.member("class_initialize").map(|m| m.kind),
+        Some(SymbolKind::Sub)
+    );
+    assert_eq!(table.iter().count(), 6);
+}
+
+#[test]
+fn test_declarations_in_blocks_and_script_tags() {
+    let table = symbols(
+        "<% If debug Then %>\n<% Dim trace %>\n<% End If %>\n<script language=\"VBScript\" runat=\"server\">\nSub Log(message)\nEnd Sub\n</script>",
+    );
+    let names: Vec<&str> = table.iter().map(|symbol| symbol.name.as_str()).collect();
+    assert_eq!(names, vec!["trace", "Log"]);
+    assert_eq!(table.get("log").unwrap().span.line, 5);
+}
+
+#[test]
+fn test_partial_trees_have_symbols() {
+    let result = parser::parse_recovering(
+        "<%\nSub Valid()\nEnd Sub\nDim\nFunction Other()\nEnd Function\n%>",
+    );
+    assert!(!result.diagnostics.is_empty());
+    let table = analysis::symbols(&result.program);
+    assert!(table.get("Valid").is_some());
+    assert!(table.get("Other").is_some());
+}
+
+#[test]
+fn test_doc_comments() {
+    let source = "<%\n''' Shopping cart\n'''\nClass Cart\n  ' Number of items\n  Public Function Count()\n  End Function\n\n  Rem Empties the cart\n  REM   keeping its id\n  Public Sub Clear()\n  End Sub\nEnd Class\n\n' Not the comment of total\n\nDim total : ' Not the comment of tax\nDim tax\n%>";
+    let table = symbols(source);
+    let doc = |symbol: &analysis::Symbol| analysis::doc_comment(source, symbol.span);
+
+    let cart = table.get("Cart").unwrap();
+    assert_eq!(doc(cart).as_deref(), Some("Shopping cart"));
+    assert_eq!(
+        doc(cart.member("Count").unwrap()).as_deref(),
+        Some("Number of items")
+    );
+    assert_eq!(
+        doc(cart.member("Clear").unwrap()).as_deref(),
+        Some("Empties the cart\n  keeping its id")
+    );
+    // Blank lines and code separate comments from declarations
+    assert_eq!(doc(table.get("total").unwrap()), None);
+    assert_eq!(doc(table.get("tax").unwrap()), None);
+}